@@ -0,0 +1,54 @@
+//! Compares loading a cached program with parsing its 5 MB source:
+//! `cargo bench --bench cache`.
+
+use std::time::Instant;
+
+use rlisp::{Lexer, Parser, Program};
+
+const SIZE: usize = 5 * 1024 * 1024;
+
+fn source() -> String {
+    let mut source = String::with_capacity(SIZE);
+    let mut i = 0;
+    while source.len() < SIZE {
+        source.push_str(&format!(
+            "fn f{i}(a, b) {{ let name = \"value {i}\"; if (a < b) {{ return a + b * 2.5; }} return name; }}\n"
+        ));
+        i += 1;
+    }
+    source
+}
+
+// Best time in seconds over a few runs.
+fn best(runs: usize, mut f: impl FnMut() -> Program) -> f64 {
+    let mut best = f64::INFINITY;
+    for _ in 0..runs {
+        let start = Instant::now();
+        let program = f();
+        best = best.min(start.elapsed().as_secs_f64());
+        drop(program);
+    }
+    best
+}
+
+fn main() {
+    let source = source();
+    let parse = || {
+        Parser::new(Lexer::new(source.clone()))
+            .parse_program()
+            .expect("generated source parses")
+    };
+    let bytes = parse().to_bytes(&source);
+    let runs = 5;
+    let parsed = best(runs, parse);
+    let loaded = best(runs, || {
+        Program::from_bytes(&bytes, Some(&source)).expect("cache loads")
+    });
+    println!(
+        "parse: {:.1}ms, load from a {:.1} MB cache: {:.1}ms (best of {runs}), {:.1}x faster",
+        parsed * 1000.0,
+        bytes.len() as f64 / (1024.0 * 1024.0),
+        loaded * 1000.0,
+        parsed / loaded
+    );
+}
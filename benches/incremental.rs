@@ -0,0 +1,63 @@
+//! Compares lexing a whole file again with re-lexing it after a one-char
+//! edit in the middle, for files of growing size:
+//! `cargo bench --bench incremental`. An edit lexes a few tokens, but still
+//! moves the spans after it, so it too grows with the file, only far slower.
+
+use std::time::Instant;
+
+use rlisp::internals::{IncrementalLexer, TokenType};
+use rlisp::Lexer;
+
+fn source(size: usize) -> String {
+    let mut source = String::with_capacity(size);
+    let mut i = 0;
+    while source.len() < size {
+        source.push_str(&format!(
+            "fn f{i}(a, b) {{ let name = \"value {i}\"; return a + b * 2.5; }}\n"
+        ));
+        i += 1;
+    }
+    source
+}
+
+// The best time of a few runs in seconds.
+fn best(runs: usize, mut f: impl FnMut()) -> f64 {
+    let mut best = f64::INFINITY;
+    for _ in 0..runs {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    best
+}
+
+fn main() {
+    for size in [64 * 1024, 512 * 1024, 4 * 1024 * 1024] {
+        let source = source(size);
+        let full = best(5, || {
+            let mut lexer = Lexer::new(source.clone());
+            while lexer.next_token().token != TokenType::Eof {}
+        });
+        // typing a char into a name and taking it out again
+        let mut lexer = IncrementalLexer::new(&source);
+        let middle = source.len() / 2;
+        let at = middle
+            + source[middle..]
+                .find("name")
+                .expect("a name after the middle");
+        let edits = 100;
+        let edited = best(5, || {
+            for _ in 0..edits {
+                lexer.apply_edit(at..at, "x");
+                lexer.apply_edit(at..at + 1, "");
+            }
+        }) / (2 * edits) as f64;
+        println!(
+            "{:>5} KB: full lex {:.2}ms, one edit {:.1}µs, {:.0}x faster",
+            size / 1024,
+            full * 1000.0,
+            edited * 1e6,
+            full / edited
+        );
+    }
+}
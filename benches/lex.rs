@@ -0,0 +1,56 @@
+//! Times tokenizing generated 5 MB inputs, one mostly numbers and one mostly
+//! strings: `cargo bench --bench lex`.
+
+use std::time::Instant;
+
+use rlisp::internals::TokenType;
+use rlisp::Lexer;
+
+const SIZE: usize = 5 * 1024 * 1024;
+
+fn source(line: impl Fn(usize) -> String) -> String {
+    let mut source = String::with_capacity(SIZE);
+    let mut i = 0;
+    while source.len() < SIZE {
+        source.push_str(&line(i));
+        i += 1;
+    }
+    source
+}
+
+// The best time of a few runs in seconds, and the number of tokens.
+fn time(source: &str) -> (f64, usize) {
+    let runs = 5;
+    let mut best = f64::INFINITY;
+    let mut tokens = 0;
+    for _ in 0..runs {
+        let mut lexer = Lexer::new(source.to_string());
+        let start = Instant::now();
+        tokens = 0;
+        while lexer.next_token().token != TokenType::Eof {
+            tokens += 1;
+        }
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    (best, tokens)
+}
+
+fn main() {
+    let numbers = source(|i| {
+        format!(
+            "let n{i} = ({i}, 1_000_000.25, 0xFF_{:X}, 6.02e23);\n",
+            i % 4096
+        )
+    });
+    let strings =
+        source(|i| format!("let s{i} = (\"value {i}\", \"a somewhat longer string literal\");\n"));
+    for (name, source) in [("numbers", numbers), ("strings", strings)] {
+        let (best, tokens) = time(&source);
+        let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+        println!(
+            "lex {name}: {megabytes:.1} MB, {tokens} tokens in {:.1}ms (best of 5), {:.1} MB/s",
+            best * 1000.0,
+            megabytes / best
+        );
+    }
+}
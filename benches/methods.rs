@@ -0,0 +1,33 @@
+//! Times a loop calling an inherited method: `cargo bench --bench methods`.
+
+use std::{io, time::Instant};
+
+use rlisp::{Interpretor, Lexer, Parser};
+
+const CALLS: usize = 1_000_000;
+
+fn main() {
+    let source = format!(
+        "class Base {{ step(n) {{ return n + 1; }} }}
+class Counter < Base {{}}
+let counter = Counter();
+let i = 0;
+while (i < {CALLS}) i = counter.step(i);"
+    );
+    let program = Parser::new(Lexer::new(source))
+        .parse_program()
+        .expect("benchmark source parses");
+    let mut interpretor = Interpretor::with_output(Box::new(io::sink()));
+    let start = Instant::now();
+    interpretor.run(&program).expect("benchmark runs");
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let stats = interpretor.stats();
+    assert!(stats.chain_walks <= 1, "method cache missed: {stats:?}");
+    println!(
+        "methods: {CALLS} calls in {:.1}ms, {:.0}ns per call, {} chain walks",
+        elapsed * 1000.0,
+        elapsed * 1e9 / CALLS as f64,
+        stats.chain_walks
+    );
+}
@@ -0,0 +1,39 @@
+//! Times parsing a generated 5 MB script: `cargo bench --bench parse`.
+
+use std::time::Instant;
+
+use rlisp::{Lexer, Parser};
+
+const SIZE: usize = 5 * 1024 * 1024;
+
+fn source() -> String {
+    let mut source = String::with_capacity(SIZE);
+    let mut i = 0;
+    while source.len() < SIZE {
+        source.push_str(&format!(
+            "fn f{i}(a, b) {{ let name = \"value {i}\"; if (a < b) {{ return a + b * 2.5; }} return name; }}\n"
+        ));
+        i += 1;
+    }
+    source
+}
+
+fn main() {
+    let source = source();
+    let runs = 5;
+    let mut best = f64::INFINITY;
+    for _ in 0..runs {
+        let start = Instant::now();
+        let program = Parser::new(Lexer::new(source.clone()))
+            .parse_program()
+            .expect("generated source parses");
+        best = best.min(start.elapsed().as_secs_f64());
+        drop(program);
+    }
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+    println!(
+        "parse: {megabytes:.1} MB in {:.1}ms (best of {runs}), {:.1} MB/s",
+        best * 1000.0,
+        megabytes / best
+    );
+}
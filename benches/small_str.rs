@@ -0,0 +1,49 @@
+//! Times reading inline strings, the way comparing and hashing them does,
+//! against checking their bytes are UTF-8 on every read as `as_str` once
+//! did: `cargo bench --bench small_str`.
+
+use std::{hint::black_box, time::Instant};
+
+use rlisp::Object;
+
+const STRINGS: usize = 1_000;
+const ROUNDS: usize = 10_000;
+
+// Reads every string `ROUNDS` times through `read`, returning the time
+// taken per read in nanoseconds and a checksum of what was read.
+fn time(strings: &[Object], read: impl Fn(&Object) -> &str) -> (f64, usize) {
+    let start = Instant::now();
+    let mut sum = 0;
+    for _ in 0..ROUNDS {
+        for string in strings {
+            let text = read(black_box(string));
+            sum += text.len() + text.as_bytes()[0] as usize;
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64() * 1e9;
+    (elapsed / (STRINGS * ROUNDS) as f64, sum)
+}
+
+fn text(value: &Object) -> &str {
+    match value {
+        Object::String(s) => s,
+        _ => unreachable!("only strings are read"),
+    }
+}
+
+fn main() {
+    let strings: Vec<_> = (0..STRINGS)
+        .map(|i| Object::String(format!("key_{i}_ü").as_str().into()))
+        .collect();
+    let (unchecked, unchecked_sum) = time(&strings, text);
+    let (checked, checked_sum) = time(&strings, |value| {
+        std::str::from_utf8(text(value).as_bytes()).expect("strings are UTF-8")
+    });
+    assert_eq!(unchecked_sum, checked_sum, "both read the same text");
+    println!(
+        "small_str: {:.2}ns per read, {:.2}ns checking UTF-8 each time ({:.1}x)",
+        unchecked,
+        checked,
+        checked / unchecked
+    );
+}
@@ -0,0 +1,53 @@
+//! Times building a string from many small pieces, with `+` against a
+//! `StringBuilder()`, with and without making room first:
+//! `cargo bench --bench strings`.
+
+use std::{io, time::Instant};
+
+use rlisp::{Interpretor, Lexer, Parser};
+
+const APPENDS: usize = 100_000;
+
+// Runs `body` in a loop of `APPENDS` steps with `i` counting, returning the
+// time taken in milliseconds and the length of the string it leaves in `out`.
+fn time(setup: &str, body: &str, result: &str) -> (f64, String) {
+    let source = format!(
+        "{setup}
+let i = 0;
+while (i < {APPENDS}) {{ {body} i = i + 1; }}
+let out = {result};"
+    );
+    let program = Parser::new(Lexer::new(source))
+        .parse_program()
+        .expect("benchmark source parses");
+    let mut interpretor = Interpretor::with_output(Box::new(io::sink()));
+    let start = Instant::now();
+    interpretor.run(&program).expect("benchmark runs");
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+    let out = interpretor
+        .globals
+        .borrow_mut()
+        .get("out")
+        .expect("out is defined");
+    (elapsed, out.to_string())
+}
+
+fn main() {
+    let (naive, naive_out) = time("let s = \"\";", "s = s + \"ab\";", "s");
+    let (builder, builder_out) = time(
+        "let sb = StringBuilder();",
+        "sb.append(\"ab\");",
+        "sb.build()",
+    );
+    let (reserved, reserved_out) = time(
+        &format!("let sb = StringBuilder({});", 2 * APPENDS),
+        "sb.append(\"ab\");",
+        "sb.build()",
+    );
+    assert_eq!(naive_out, builder_out, "both loops build the same string");
+    assert_eq!(builder_out, reserved_out, "making room changes nothing");
+    println!(
+        "strings: {APPENDS} appends, {naive:.1}ms with +, {builder:.1}ms with StringBuilder ({:.1}x), {reserved:.1}ms with its capacity given",
+        naive / builder
+    );
+}
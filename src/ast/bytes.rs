@@ -0,0 +1,1158 @@
+use std::{collections::HashMap, fmt};
+
+use crate::ast::{
+    FieldInit, ImportForm, ImportName, MatchArm, NamedArg, NodeId, Pattern, Program, Signature,
+};
+use crate::{Expr, Lexer, LiteralType, Span, Stmt, TokenInfo};
+
+const MAGIC: &[u8; 4] = b"rlst";
+/// Bumped whenever the encoding of any node changes.
+pub const FORMAT_VERSION: u32 = 18;
+
+/// Why `Program::from_bytes` rejected its input.
+#[derive(Debug, PartialEq, Clone)]
+pub enum CacheError {
+    /// The bytes were written by another version of the format.
+    UnknownVersion(u32),
+    /// The program was compiled from a different source.
+    SourceChanged,
+    /// The bytes are truncated, damaged or not a cached program at all.
+    Corrupt(String),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::UnknownVersion(version) => write!(
+                f,
+                "cache has format version {version}, expected {FORMAT_VERSION}"
+            ),
+            CacheError::SourceChanged => write!(f, "source changed since the cache was written"),
+            CacheError::Corrupt(why) => write!(f, "corrupt cache: {why}"),
+        }
+    }
+}
+
+impl Program {
+    /// Encodes the program, including its side tables, for `from_bytes`.
+    /// The hash of `source`, the text it was parsed from, goes in the header.
+    ///
+    /// The layout is the magic `rlst`, the format version as four
+    /// little-endian bytes, the source hash as eight, then the program.
+    /// Integers are LEB128 varints, strings are a length and UTF-8 bytes,
+    /// numbers are little-endian `f64`s and every node starts with a tag.
+    /// Node ids and a span's line and start are stored as the difference
+    /// from the previous ones, which keeps most of them to a byte.
+    pub fn to_bytes(&self, source: &str) -> Vec<u8> {
+        let mut encoder = Encoder {
+            bytes: Vec::new(),
+            last: (0, 0, 0),
+        };
+        encoder.bytes.extend_from_slice(MAGIC);
+        encoder
+            .bytes
+            .extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        encoder.bytes.extend_from_slice(&hash(source).to_le_bytes());
+        encoder.uint(self.node_count as u64);
+        encoder.list(&self.stmts, Encoder::stmt);
+        encoder.table(&self.synthetic);
+        encoder.table(&self.stmt_spans);
+        encoder.bytes
+    }
+
+    /// Decodes a program written by `to_bytes`. With `source`, the program
+    /// is also rejected unless it was compiled from exactly that text.
+    pub fn from_bytes(bytes: &[u8], source: Option<&str>) -> Result<Program, CacheError> {
+        let mut decoder = Decoder {
+            bytes,
+            pos: 0,
+            last: (0, 0, 0),
+        };
+        if decoder.take(4)? != MAGIC {
+            return Err(CacheError::Corrupt("not a cached program".to_string()));
+        }
+        let version = u32::from_le_bytes(decoder.array()?);
+        if version != FORMAT_VERSION {
+            return Err(CacheError::UnknownVersion(version));
+        }
+        let source_hash = u64::from_le_bytes(decoder.array()?);
+        if source.is_some_and(|source| hash(source) != source_hash) {
+            return Err(CacheError::SourceChanged);
+        }
+        let node_count = decoder.u32()?;
+        let stmts = decoder.list(Decoder::stmt)?;
+        let mut program = Program::new(stmts, node_count);
+        program.synthetic = decoder.table()?;
+        program.stmt_spans = decoder.table()?;
+        if decoder.pos != bytes.len() {
+            return Err(decoder.corrupt("trailing bytes"));
+        }
+        Ok(program)
+    }
+}
+
+// FNV-1a, which unlike std's hasher gives the same result on every build.
+fn hash(source: &str) -> u64 {
+    source.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+struct Encoder {
+    bytes: Vec<u8>,
+    // the last id written, and line and start of the last span
+    last: (u32, usize, usize),
+}
+
+impl Encoder {
+    fn uint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.bytes.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.bytes.push(n as u8);
+    }
+
+    fn str(&mut self, s: &str) {
+        self.uint(s.len() as u64);
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    fn list<T>(&mut self, items: &[T], mut item: impl FnMut(&mut Self, &T)) {
+        self.uint(items.len() as u64);
+        items.iter().for_each(|x| item(self, x));
+    }
+
+    fn option<T>(&mut self, value: Option<&T>, item: impl FnOnce(&mut Self, &T)) {
+        match value {
+            Some(value) => {
+                self.bytes.push(1);
+                item(self, value);
+            }
+            None => self.bytes.push(0),
+        }
+    }
+
+    fn id(&mut self, id: NodeId) {
+        self.int(id.0 as i64 - self.last.0 as i64);
+        self.last.0 = id.0;
+    }
+
+    // zigzag, so small negative numbers stay small too
+    fn int(&mut self, n: i64) {
+        self.uint(((n << 1) ^ (n >> 63)) as u64);
+    }
+
+    fn span(&mut self, span: &Span) {
+        let (_, line, start) = self.last;
+        self.int(span.line as i64 - line as i64);
+        self.int(span.start as i64 - start as i64);
+        self.int(span.line_start as i64 - span.start as i64);
+        self.int(span.end as i64 - span.start as i64);
+        self.last = (self.last.0, span.line, span.start);
+    }
+
+    // sorted so the same program always encodes to the same bytes
+    fn table(&mut self, table: &HashMap<NodeId, Span>) {
+        let mut entries: Vec<_> = table.iter().collect();
+        entries.sort_by_key(|(id, _)| **id);
+        self.list(&entries, |e, (id, span)| {
+            e.id(**id);
+            e.span(span);
+        });
+    }
+
+    // Operators are stored as they are written and lexed again on load.
+    fn op(&mut self, op: &TokenInfo) {
+        self.str(&op.token.to_string());
+        self.span(&op.span);
+    }
+
+    fn literal(&mut self, value: &LiteralType) {
+        match value {
+            LiteralType::String(s) => {
+                self.bytes.push(0);
+                self.str(s);
+            }
+            LiteralType::Number(n, lexeme) => {
+                self.bytes.push(1);
+                self.bytes.extend_from_slice(&n.to_le_bytes());
+                self.option(lexeme.as_ref(), |e, lexeme| e.str(lexeme));
+            }
+            LiteralType::Boolean(b) => self.bytes.extend_from_slice(&[2, *b as u8]),
+            LiteralType::Nil => self.bytes.push(3),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign {
+                id,
+                name,
+                value,
+                span,
+            } => {
+                self.bytes.push(0);
+                self.id(*id);
+                self.str(name);
+                self.expr(value);
+                self.span(span);
+            }
+            Expr::Binary {
+                id,
+                left,
+                op,
+                right,
+            } => {
+                self.bytes.push(1);
+                self.id(*id);
+                self.expr(left);
+                self.op(op);
+                self.expr(right);
+            }
+            Expr::Call {
+                id,
+                callee,
+                args,
+                named,
+                span,
+            } => {
+                self.bytes.push(2);
+                self.id(*id);
+                self.expr(callee);
+                self.list(args, Self::expr);
+                self.list(named, |e, arg| {
+                    e.str(&arg.name);
+                    e.expr(&arg.value);
+                    e.span(&arg.span);
+                });
+                self.span(span);
+            }
+            Expr::Class {
+                id,
+                name,
+                super_class,
+                mixins,
+                methods,
+                fields,
+                private,
+                doc,
+                span,
+            } => {
+                self.bytes.push(3);
+                self.id(*id);
+                self.option(name.as_ref(), |e, name| e.str(name));
+                self.option(super_class.as_ref(), |e, expr| e.expr(expr));
+                self.list(mixins, Self::expr);
+                self.list(methods, Self::stmt);
+                self.list(fields, Self::field);
+                self.list(private, |e, name| e.str(name));
+                self.option(doc.as_ref(), |e, doc| e.str(doc));
+                self.span(span);
+            }
+            Expr::Get {
+                id,
+                object,
+                name,
+                span,
+            } => {
+                self.bytes.push(4);
+                self.id(*id);
+                self.expr(object);
+                self.str(name);
+                self.span(span);
+            }
+            Expr::Grouping { id, expr, span } => {
+                self.bytes.push(5);
+                self.id(*id);
+                self.expr(expr);
+                self.span(span);
+            }
+            Expr::Literal { id, value, span } => {
+                self.bytes.push(6);
+                self.id(*id);
+                self.literal(value);
+                self.span(span);
+            }
+            Expr::Logical {
+                id,
+                left,
+                op,
+                right,
+            } => {
+                self.bytes.push(7);
+                self.id(*id);
+                self.expr(left);
+                self.op(op);
+                self.expr(right);
+            }
+            Expr::Set {
+                id,
+                object,
+                name,
+                op,
+                value,
+                span,
+            } => {
+                self.bytes.push(8);
+                self.id(*id);
+                self.expr(object);
+                self.str(name);
+                self.option(op.as_ref(), Self::op);
+                self.expr(value);
+                self.span(span);
+            }
+            Expr::Super { id, name, span } => {
+                self.bytes.push(9);
+                self.id(*id);
+                self.str(name);
+                self.span(span);
+            }
+            Expr::Unary { id, op, right } => {
+                self.bytes.push(10);
+                self.id(*id);
+                self.op(op);
+                self.expr(right);
+            }
+            Expr::Variable { id, name, span } => {
+                self.bytes.push(11);
+                self.id(*id);
+                self.str(name);
+                self.span(span);
+            }
+            Expr::Match {
+                id,
+                value,
+                arms,
+                span,
+            } => {
+                self.bytes.push(12);
+                self.id(*id);
+                self.expr(value);
+                self.list(arms, |e, arm| {
+                    e.pattern(&arm.pattern);
+                    e.option(arm.guard.as_ref(), Self::expr);
+                    e.expr(&arm.body);
+                });
+                self.span(span);
+            }
+            Expr::Index {
+                id,
+                object,
+                index,
+                span,
+            } => {
+                self.bytes.push(13);
+                self.id(*id);
+                self.expr(object);
+                self.expr(index);
+                self.span(span);
+            }
+            Expr::Tuple { id, elements, span } => {
+                self.bytes.push(14);
+                self.id(*id);
+                self.list(elements, Self::expr);
+                self.span(span);
+            }
+            Expr::Placeholder { id, name, span } => {
+                self.bytes.push(15);
+                self.id(*id);
+                self.str(name);
+                self.span(span);
+            }
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Wildcard => self.bytes.push(0),
+            Pattern::Literal(value) => {
+                self.bytes.push(1);
+                self.literal(value);
+            }
+            Pattern::Bind(name) => {
+                self.bytes.push(2);
+                self.str(name);
+            }
+            Pattern::Fields(fields) => {
+                self.bytes.push(3);
+                self.list(fields, |e, (name, pattern)| {
+                    e.str(name);
+                    e.pattern(pattern);
+                });
+            }
+        }
+    }
+
+    fn field(&mut self, field: &FieldInit) {
+        self.id(field.id);
+        self.str(&field.name);
+        self.expr(&field.value);
+        self.span(&field.span);
+    }
+
+    fn import_form(&mut self, form: &ImportForm) {
+        let names = |e: &mut Self, names: &[ImportName]| {
+            e.list(names, |e, name| {
+                e.str(&name.name);
+                e.option(name.alias.as_ref(), |e, alias| e.str(alias));
+                e.span(&name.span);
+            })
+        };
+        match form {
+            ImportForm::All => self.bytes.push(0),
+            ImportForm::Module { alias, span } => {
+                self.bytes.push(1);
+                self.str(alias);
+                self.span(span);
+            }
+            ImportForm::Names(list) => {
+                self.bytes.push(2);
+                names(self, list);
+            }
+            ImportForm::ReExport(list) => {
+                self.bytes.push(3);
+                names(self, list);
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr { id, expr } => {
+                self.bytes.push(0);
+                self.id(*id);
+                self.expr(expr);
+            }
+            Stmt::Print { id, exprs } => {
+                self.bytes.push(1);
+                self.id(*id);
+                self.list(exprs, Self::expr);
+            }
+            Stmt::Let {
+                id,
+                name,
+                annotation,
+                value,
+                is_const,
+                span,
+            } => {
+                self.bytes.push(2);
+                self.id(*id);
+                self.str(name);
+                self.option(annotation.as_ref(), |e, annotation| e.str(annotation));
+                self.option(value.as_ref(), Self::expr);
+                self.bytes.push(*is_const as u8);
+                self.span(span);
+            }
+            Stmt::Block { id, stmts } => {
+                self.bytes.push(3);
+                self.id(*id);
+                self.list(stmts, Self::stmt);
+            }
+            Stmt::If {
+                id,
+                condition,
+                truthy,
+                falsy,
+                span,
+            } => {
+                self.bytes.push(4);
+                self.id(*id);
+                self.expr(condition);
+                self.stmt(truthy);
+                self.option(falsy.as_ref(), |e, stmt| e.stmt(stmt));
+                self.span(span);
+            }
+            Stmt::While {
+                id,
+                condition,
+                body,
+                span,
+            } => {
+                self.bytes.push(5);
+                self.id(*id);
+                self.expr(condition);
+                self.stmt(body);
+                self.span(span);
+            }
+            Stmt::For {
+                id,
+                initializer,
+                condition,
+                increment,
+                body,
+                span,
+            } => {
+                self.bytes.push(6);
+                self.id(*id);
+                self.option(initializer.as_ref(), |e, stmt| e.stmt(stmt));
+                self.option(condition.as_ref(), Self::expr);
+                self.option(increment.as_ref(), Self::expr);
+                self.stmt(body);
+                self.span(span);
+            }
+            Stmt::Function {
+                id,
+                name,
+                params,
+                signature,
+                requires,
+                ensures,
+                body,
+                doc,
+                span,
+            } => {
+                self.bytes.push(7);
+                self.id(*id);
+                self.str(name);
+                self.list(params, |e, param| e.str(param));
+                self.list(&signature.params, |e, param| {
+                    e.option(param.as_ref(), |e, annotation| e.str(annotation))
+                });
+                self.option(signature.returns.as_ref(), |e, annotation| {
+                    e.str(annotation)
+                });
+                self.list(requires, Self::expr);
+                self.list(ensures, Self::expr);
+                self.list(body, Self::stmt);
+                self.option(doc.as_ref(), |e, doc| e.str(doc));
+                self.span(span);
+            }
+            Stmt::Return { id, value, span } => {
+                self.bytes.push(8);
+                self.id(*id);
+                self.option(value.as_ref(), Self::expr);
+                self.span(span);
+            }
+            Stmt::Class {
+                id,
+                name,
+                super_class,
+                mixins,
+                methods,
+                fields,
+                private,
+                doc,
+                span,
+            } => {
+                self.bytes.push(9);
+                self.id(*id);
+                self.str(name);
+                self.option(super_class.as_ref(), Self::expr);
+                self.list(mixins, Self::expr);
+                self.list(methods, Self::stmt);
+                self.list(fields, Self::field);
+                self.list(private, |e, name| e.str(name));
+                self.option(doc.as_ref(), |e, doc| e.str(doc));
+                self.span(span);
+            }
+            Stmt::Break { id, span } => {
+                self.bytes.push(10);
+                self.id(*id);
+                self.span(span);
+            }
+            Stmt::Continue { id, span } => {
+                self.bytes.push(11);
+                self.id(*id);
+                self.span(span);
+            }
+            Stmt::Defer { id, body, span } => {
+                self.bytes.push(12);
+                self.id(*id);
+                self.stmt(body);
+                self.span(span);
+            }
+            Stmt::Destructure {
+                id,
+                names,
+                value,
+                is_const,
+                span,
+            } => {
+                self.bytes.push(13);
+                self.id(*id);
+                self.list(names, |e, name| e.str(name));
+                self.expr(value);
+                self.bytes.push(*is_const as u8);
+                self.span(span);
+            }
+            Stmt::Empty { id, span } => {
+                self.bytes.push(14);
+                self.id(*id);
+                self.span(span);
+            }
+            Stmt::ForRange {
+                id,
+                initializer,
+                end,
+                step,
+                body,
+                span,
+            } => {
+                self.bytes.push(15);
+                self.id(*id);
+                self.stmt(initializer);
+                self.expr(end);
+                self.option(step.as_ref(), Self::expr);
+                self.stmt(body);
+                self.span(span);
+            }
+            Stmt::Import {
+                id,
+                specifier,
+                form,
+                span,
+            } => {
+                self.bytes.push(16);
+                self.id(*id);
+                self.str(specifier);
+                self.import_form(form);
+                self.span(span);
+            }
+            Stmt::Error { id, span } => {
+                self.bytes.push(17);
+                self.id(*id);
+                self.span(span);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    // the last id read, and line and start of the last span
+    last: (u32, usize, usize),
+}
+
+impl<'a> Decoder<'a> {
+    fn corrupt(&self, why: &str) -> CacheError {
+        CacheError::Corrupt(format!("{why} at byte {}", self.pos))
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], CacheError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(self.corrupt("unexpected end of input"));
+        }
+        self.pos += n;
+        Ok(&self.bytes[self.pos - n..self.pos])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], CacheError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn byte(&mut self) -> Result<u8, CacheError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn uint(&mut self) -> Result<u64, CacheError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(self.corrupt("integer too long"))
+    }
+
+    fn u32(&mut self) -> Result<u32, CacheError> {
+        let n = self.uint()?;
+        u32::try_from(n).map_err(|_| self.corrupt("integer out of range"))
+    }
+
+    fn usize(&mut self) -> Result<usize, CacheError> {
+        let n = self.uint()?;
+        usize::try_from(n).map_err(|_| self.corrupt("integer out of range"))
+    }
+
+    fn bool(&mut self) -> Result<bool, CacheError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(self.corrupt("bad boolean")),
+        }
+    }
+
+    fn str(&mut self) -> Result<String, CacheError> {
+        let len = self.usize()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.corrupt("invalid utf-8"))
+    }
+
+    // The length is checked against what is left, so a damaged length
+    // cannot make us reserve huge amounts of memory.
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, CacheError>,
+    ) -> Result<Vec<T>, CacheError> {
+        let len = self.usize()?;
+        if len > self.bytes.len() - self.pos {
+            return Err(self.corrupt("list longer than the input"));
+        }
+        let mut items = Vec::with_capacity(len);
+        for _ in 0..len {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn option<T>(
+        &mut self,
+        item: impl FnOnce(&mut Self) -> Result<T, CacheError>,
+    ) -> Result<Option<T>, CacheError> {
+        match self.bool()? {
+            true => item(self).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    fn id(&mut self) -> Result<NodeId, CacheError> {
+        let n = self.int()?;
+        let id = (self.last.0 as i64)
+            .checked_add(n)
+            .and_then(|n| u32::try_from(n).ok())
+            .ok_or_else(|| self.corrupt("bad node id"))?;
+        self.last.0 = id;
+        Ok(NodeId(id))
+    }
+
+    fn int(&mut self) -> Result<i64, CacheError> {
+        let n = self.uint()?;
+        Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+    }
+
+    fn offset(&mut self, base: usize) -> Result<usize, CacheError> {
+        let n = self.int()?;
+        (base as i64)
+            .checked_add(n)
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or_else(|| self.corrupt("bad span"))
+    }
+
+    fn span(&mut self) -> Result<Span, CacheError> {
+        let line = self.offset(self.last.1)?;
+        let start = self.offset(self.last.2)?;
+        let line_start = self.offset(start)?;
+        let end = self.offset(start)?;
+        if line_start > start {
+            return Err(self.corrupt("bad span"));
+        }
+        self.last = (self.last.0, line, start);
+        Ok(Span::new(line, line_start, start, end))
+    }
+
+    fn table(&mut self) -> Result<HashMap<NodeId, Span>, CacheError> {
+        let entries = self.list(|d| Ok((d.id()?, d.span()?)))?;
+        Ok(entries.into_iter().collect())
+    }
+
+    fn op(&mut self) -> Result<TokenInfo, CacheError> {
+        let text = self.str()?;
+        // only operator characters reach the lexer
+        let is_operator = |c| "+-*/%<>=!&|^".contains(c);
+        if text.is_empty() || text.len() > 4 || !text.chars().all(is_operator) {
+            return Err(self.corrupt("unknown operator"));
+        }
+        let token = Lexer::new(text.clone()).next_token().token;
+        if token.to_string() != text {
+            return Err(self.corrupt("unknown operator"));
+        }
+        Ok(TokenInfo {
+            token,
+            span: self.span()?,
+            leading_trivia: Vec::new(),
+        })
+    }
+
+    fn boxed(&mut self) -> Result<Box<Expr>, CacheError> {
+        self.expr().map(Box::new)
+    }
+
+    fn boxed_stmt(&mut self) -> Result<Box<Stmt>, CacheError> {
+        self.stmt().map(Box::new)
+    }
+
+    fn literal(&mut self) -> Result<LiteralType, CacheError> {
+        Ok(match self.byte()? {
+            0 => LiteralType::String(self.str()?),
+            1 => {
+                let n = f64::from_le_bytes(self.array()?);
+                LiteralType::Number(n, self.option(Self::str)?)
+            }
+            2 => LiteralType::Boolean(self.bool()?),
+            3 => LiteralType::Nil,
+            _ => return Err(self.corrupt("unknown literal")),
+        })
+    }
+
+    fn expr(&mut self) -> Result<Expr, CacheError> {
+        Ok(match self.byte()? {
+            0 => Expr::Assign {
+                id: self.id()?,
+                name: self.str()?,
+                value: self.boxed()?,
+                span: self.span()?,
+            },
+            1 => Expr::Binary {
+                id: self.id()?,
+                left: self.boxed()?,
+                op: self.op()?,
+                right: self.boxed()?,
+            },
+            2 => Expr::Call {
+                id: self.id()?,
+                callee: self.boxed()?,
+                args: self.list(Self::expr)?,
+                named: self.list(|d| {
+                    Ok(NamedArg {
+                        name: d.str()?,
+                        value: d.expr()?,
+                        span: d.span()?,
+                    })
+                })?,
+                span: self.span()?,
+            },
+            3 => Expr::Class {
+                id: self.id()?,
+                name: self.option(Self::str)?,
+                super_class: self.option(Self::boxed)?,
+                mixins: self.list(Self::expr)?,
+                methods: self.list(Self::stmt)?,
+                fields: self.list(Self::field)?,
+                private: self.list(Self::str)?,
+                doc: self.option(Self::str)?,
+                span: self.span()?,
+            },
+            4 => Expr::Get {
+                id: self.id()?,
+                object: self.boxed()?,
+                name: self.str()?,
+                span: self.span()?,
+            },
+            5 => Expr::Grouping {
+                id: self.id()?,
+                expr: self.boxed()?,
+                span: self.span()?,
+            },
+            6 => Expr::Literal {
+                id: self.id()?,
+                value: self.literal()?,
+                span: self.span()?,
+            },
+            7 => Expr::Logical {
+                id: self.id()?,
+                left: self.boxed()?,
+                op: self.op()?,
+                right: self.boxed()?,
+            },
+            8 => Expr::Set {
+                id: self.id()?,
+                object: self.boxed()?,
+                name: self.str()?,
+                op: self.option(Self::op)?,
+                value: self.boxed()?,
+                span: self.span()?,
+            },
+            9 => Expr::Super {
+                id: self.id()?,
+                name: self.str()?,
+                span: self.span()?,
+            },
+            10 => Expr::Unary {
+                id: self.id()?,
+                op: self.op()?,
+                right: self.boxed()?,
+            },
+            11 => Expr::Variable {
+                id: self.id()?,
+                name: self.str()?,
+                span: self.span()?,
+            },
+            12 => Expr::Match {
+                id: self.id()?,
+                value: self.boxed()?,
+                arms: self.list(|d| {
+                    Ok(MatchArm {
+                        pattern: d.pattern()?,
+                        guard: d.option(Self::expr)?,
+                        body: d.expr()?,
+                    })
+                })?,
+                span: self.span()?,
+            },
+            13 => Expr::Index {
+                id: self.id()?,
+                object: self.boxed()?,
+                index: self.boxed()?,
+                span: self.span()?,
+            },
+            14 => Expr::Tuple {
+                id: self.id()?,
+                elements: self.list(Self::expr)?,
+                span: self.span()?,
+            },
+            15 => Expr::Placeholder {
+                id: self.id()?,
+                name: self.str()?,
+                span: self.span()?,
+            },
+            _ => return Err(self.corrupt("unknown expression")),
+        })
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, CacheError> {
+        Ok(match self.byte()? {
+            0 => Pattern::Wildcard,
+            1 => Pattern::Literal(self.literal()?),
+            2 => Pattern::Bind(self.str()?),
+            3 => Pattern::Fields(self.list(|d| Ok((d.str()?, d.pattern()?)))?),
+            _ => return Err(self.corrupt("unknown pattern")),
+        })
+    }
+
+    fn field(&mut self) -> Result<FieldInit, CacheError> {
+        Ok(FieldInit {
+            id: self.id()?,
+            name: self.str()?,
+            value: self.expr()?,
+            span: self.span()?,
+        })
+    }
+
+    fn import_form(&mut self) -> Result<ImportForm, CacheError> {
+        let names = |d: &mut Self| {
+            d.list(|d| {
+                Ok(ImportName {
+                    name: d.str()?,
+                    alias: d.option(Self::str)?,
+                    span: d.span()?,
+                })
+            })
+        };
+        Ok(match self.byte()? {
+            0 => ImportForm::All,
+            1 => ImportForm::Module {
+                alias: self.str()?,
+                span: self.span()?,
+            },
+            2 => ImportForm::Names(names(self)?),
+            3 => ImportForm::ReExport(names(self)?),
+            _ => return Err(self.corrupt("unknown import")),
+        })
+    }
+
+    fn stmt(&mut self) -> Result<Stmt, CacheError> {
+        Ok(match self.byte()? {
+            0 => Stmt::Expr {
+                id: self.id()?,
+                expr: self.expr()?,
+            },
+            1 => Stmt::Print {
+                id: self.id()?,
+                exprs: self.list(Self::expr)?,
+            },
+            2 => Stmt::Let {
+                id: self.id()?,
+                name: self.str()?,
+                annotation: self.option(Self::str)?,
+                value: self.option(Self::expr)?,
+                is_const: self.bool()?,
+                span: self.span()?,
+            },
+            3 => Stmt::Block {
+                id: self.id()?,
+                stmts: self.list(Self::stmt)?,
+            },
+            4 => Stmt::If {
+                id: self.id()?,
+                condition: self.expr()?,
+                truthy: self.boxed_stmt()?,
+                falsy: self.option(Self::boxed_stmt)?,
+                span: self.span()?,
+            },
+            5 => Stmt::While {
+                id: self.id()?,
+                condition: self.expr()?,
+                body: self.boxed_stmt()?,
+                span: self.span()?,
+            },
+            6 => Stmt::For {
+                id: self.id()?,
+                initializer: self.option(Self::boxed_stmt)?,
+                condition: self.option(Self::expr)?,
+                increment: self.option(Self::expr)?,
+                body: self.boxed_stmt()?,
+                span: self.span()?,
+            },
+            7 => Stmt::Function {
+                id: self.id()?,
+                name: self.str()?,
+                params: self.list(Self::str)?,
+                signature: Signature {
+                    params: self.list(|d| d.option(Self::str))?,
+                    returns: self.option(Self::str)?,
+                },
+                requires: self.list(Self::expr)?,
+                ensures: self.list(Self::expr)?,
+                body: self.list(Self::stmt)?,
+                doc: self.option(Self::str)?,
+                span: self.span()?,
+            },
+            8 => Stmt::Return {
+                id: self.id()?,
+                value: self.option(Self::expr)?,
+                span: self.span()?,
+            },
+            9 => Stmt::Class {
+                id: self.id()?,
+                name: self.str()?,
+                super_class: self.option(Self::expr)?,
+                mixins: self.list(Self::expr)?,
+                methods: self.list(Self::stmt)?,
+                fields: self.list(Self::field)?,
+                private: self.list(Self::str)?,
+                doc: self.option(Self::str)?,
+                span: self.span()?,
+            },
+            10 => Stmt::Break {
+                id: self.id()?,
+                span: self.span()?,
+            },
+            11 => Stmt::Continue {
+                id: self.id()?,
+                span: self.span()?,
+            },
+            12 => Stmt::Defer {
+                id: self.id()?,
+                body: self.boxed_stmt()?,
+                span: self.span()?,
+            },
+            13 => Stmt::Destructure {
+                id: self.id()?,
+                names: self.list(Self::str)?,
+                value: self.expr()?,
+                is_const: self.bool()?,
+                span: self.span()?,
+            },
+            14 => Stmt::Empty {
+                id: self.id()?,
+                span: self.span()?,
+            },
+            15 => Stmt::ForRange {
+                id: self.id()?,
+                initializer: self.boxed_stmt()?,
+                end: self.expr()?,
+                step: self.option(Self::expr)?,
+                body: self.boxed_stmt()?,
+                span: self.span()?,
+            },
+            16 => Stmt::Import {
+                id: self.id()?,
+                specifier: self.str()?,
+                form: self.import_form()?,
+                span: self.span()?,
+            },
+            17 => Stmt::Error {
+                id: self.id()?,
+                span: self.span()?,
+            },
+            _ => return Err(self.corrupt("unknown statement")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CacheError, FORMAT_VERSION};
+    use crate::{lower, Interpretor, Lexer, Parser, Program};
+
+    const SOURCE: &str = "
+let a = 0xFF;
+const s = \"text\";
+fn f(x, y) { defer print \"bye\"; if (x < y && !false) { return -x; } else return y; }
+class A { init(n) { this.n = n; this.n *= 2; } get() { return this.n; } }
+class B < A { k = -1 + 2; get() { return super.get() + this.k; } }
+let anon = class { priv k; m() { return nil; } priv fn h() {} };
+for (let i = 0; i < 2; i = i + 1) { while (false) {} ; }
+print f(1, y: 2);
+a += (1 << 3) >>> 1;
+print B(a).get();
+fn pair() { return 1, (2,); }
+let (p, q) = pair();
+print q[0];
+fn template() { return @{n}; }
+print match (B(1)) { {n: -1} => 0, {n} if n > 0 => n, \"s\" => true, _ => nil };
+";
+
+    fn parse(source: &str) -> Program {
+        Parser::new(Lexer::new(source.to_string()))
+            .parse_program()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for program in [parse(SOURCE), lower(parse(SOURCE))] {
+            let bytes = program.to_bytes(SOURCE);
+            let loaded = Program::from_bytes(&bytes, Some(SOURCE)).unwrap();
+            assert_eq!(loaded, program);
+            // statement spans are left out of equality, but not of the bytes
+            assert_eq!(loaded.to_bytes(SOURCE), bytes);
+        }
+
+        let run = |program: &Program| {
+            let mut interpretor = Interpretor::buffered();
+            interpretor.run(program).unwrap();
+            interpretor.take_output()
+        };
+        let program = lower(parse(SOURCE));
+        let loaded = Program::from_bytes(&program.to_bytes(SOURCE), None).unwrap();
+        assert_eq!(run(&loaded), run(&program));
+
+        // every form of import, which needs modules to run
+        let source = "import \"a.lang\";\nimport \"b.lang\" as b;
+import { x, y as z } from \"c.lang\";\nexport { w as v } from \"d.lang\";";
+        let program = parse(source);
+        let loaded = Program::from_bytes(&program.to_bytes(source), None).unwrap();
+        assert_eq!(loaded, program);
+    }
+
+    #[test]
+    fn test_rejected() {
+        let bytes = parse(SOURCE).to_bytes(SOURCE);
+        assert_eq!(
+            Program::from_bytes(&bytes, Some("print 1;")),
+            Err(CacheError::SourceChanged)
+        );
+
+        let mut newer = bytes.clone();
+        newer[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            Program::from_bytes(&newer, None),
+            Err(CacheError::UnknownVersion(FORMAT_VERSION + 1))
+        );
+
+        let corrupt = |bytes: &[u8]| {
+            matches!(
+                Program::from_bytes(bytes, None),
+                Err(CacheError::Corrupt(_))
+            )
+        };
+        assert!(corrupt(b""));
+        assert!(corrupt(b"print 1;"));
+        assert!(corrupt(&[bytes.as_slice(), &[0]].concat()));
+        for len in 0..bytes.len() {
+            assert!(corrupt(&bytes[..len]), "truncated to {len} bytes");
+        }
+        // flipping bytes must never panic, whatever it decodes to
+        for i in 0..bytes.len() {
+            let mut damaged = bytes.clone();
+            damaged[i] ^= 0xff;
+            let _ = Program::from_bytes(&damaged, None);
+        }
+    }
+}
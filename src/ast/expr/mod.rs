@@ -1,53 +1,190 @@
-use crate::{ErrorInfo, LiteralType, Object, Span, TokenInfo, TokenType};
+use crate::ast::NodeId;
+use crate::{ErrorInfo, LiteralType, Object, Span, Stmt, TokenInfo};
 use std::fmt;
 
 mod visitor;
 pub use visitor::Visitor;
 
+/// A `name: value` argument at a call site.
+#[derive(Debug, PartialEq, Clone)]
+pub struct NamedArg {
+    pub name: String,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// A `name = value;` member of a class. Each new instance gets the field
+/// before `init` runs, with `value` evaluated as a method body would be.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldInit {
+    pub id: NodeId,
+    pub name: String,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// One `pattern => body` arm of a `match`, optionally guarded as in
+/// `[x, y] if x > y => body`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+/// What a `match` arm tests the value against.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    /// `_`, matching anything without binding it.
+    Wildcard,
+    /// Matches values equal to the literal.
+    Literal(LiteralType),
+    /// A name, matching anything and binding it for the arm.
+    Bind(String),
+    /// `{name: pattern, ...}`, matching instances whose fields match. A
+    /// field written alone, as in `{name}`, binds a variable of that name.
+    Fields(Vec<(String, Pattern)>),
+}
+
+impl Pattern {
+    /// The names the pattern binds, in order.
+    pub fn names(&self) -> Vec<&str> {
+        match self {
+            Pattern::Wildcard | Pattern::Literal(_) => vec![],
+            Pattern::Bind(name) => vec![name],
+            Pattern::Fields(fields) => fields.iter().flat_map(|(_, p)| p.names()).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Literal(value) => write!(f, "{:?}", value),
+            Pattern::Bind(name) => write!(f, "{name}"),
+            Pattern::Fields(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, pattern)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{name}: {pattern}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expr {
     Assign {
+        id: NodeId,
         name: String,
         value: Box<Expr>,
         span: Span,
     },
     Binary {
+        id: NodeId,
         left: Box<Expr>,
         op: TokenInfo,
         right: Box<Expr>,
     },
     Call {
+        id: NodeId,
         callee: Box<Expr>,
         args: Vec<Expr>,
+        named: Vec<NamedArg>,
+        // from the start of the callee to the closing paren
+        span: Span,
+    },
+    Class {
+        id: NodeId,
+        name: Option<String>,
+        super_class: Option<Box<Expr>>,
+        // the classes after `with`, whose methods are copied in
+        mixins: Vec<Expr>,
+        methods: Vec<Stmt>,
+        // initialized in order, after those of the superclass
+        fields: Vec<FieldInit>,
+        // the members declared `priv`, methods and fields alike
+        private: Vec<String>,
+        // a string right after the `{`
+        doc: Option<String>,
         span: Span,
     },
     Get {
+        id: NodeId,
         object: Box<Expr>,
         name: String,
         span: Span,
     },
     Grouping {
+        id: NodeId,
         expr: Box<Expr>,
         span: Span,
     },
+    /// `object[index]`.
+    Index {
+        id: NodeId,
+        object: Box<Expr>,
+        index: Box<Expr>,
+        // from the start of the object to the closing bracket
+        span: Span,
+    },
     Literal {
+        id: NodeId,
         value: LiteralType,
+        span: Span,
+    },
+    Logical {
+        id: NodeId,
+        left: Box<Expr>,
+        op: TokenInfo,
+        right: Box<Expr>,
     },
+    Match {
+        id: NodeId,
+        value: Box<Expr>,
+        arms: Vec<MatchArm>,
+        // from `match` to the paren closing the value
+        span: Span,
+    },
+    /// `@{name}`, a value the host passes to `Interpretor::run_with_params`.
+    Placeholder {
+        id: NodeId,
+        name: String,
+        span: Span,
+    },
+    /// `object.name = value`, or with `op` the compound `object.name op=
+    /// value`. The object is evaluated once, before the value.
     Set {
+        id: NodeId,
         object: Box<Expr>,
         name: String,
+        op: Option<TokenInfo>,
         value: Box<Expr>,
         span: Span,
     },
     Super {
+        id: NodeId,
         name: String,
         span: Span,
     },
+    /// `(a, b)` or `(a,)`, and the values of `return a, b;`.
+    Tuple {
+        id: NodeId,
+        elements: Vec<Expr>,
+        span: Span,
+    },
     Unary {
+        id: NodeId,
         op: TokenInfo,
         right: Box<Expr>,
     },
     Variable {
+        id: NodeId,
         name: String,
         span: Span,
     },
@@ -56,42 +193,336 @@ pub enum Expr {
 impl Expr {
     pub fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<Object, ErrorInfo> {
         match self {
-            Expr::Assign { name, value, span } => visitor.visit_assign_expr(name, value, span),
-            Expr::Binary { left, op, right } => visitor.visit_binary_expr(left, op, right),
-            Expr::Call { callee, args, span } => visitor.visit_call_expr(callee, args, span),
-            Expr::Get { object, name, span } => visitor.visit_get_expr(object, name, span),
-            Expr::Grouping { expr, span } => visitor.visit_grouping_expr(expr, span),
-            Expr::Literal { value } => visitor.visit_literal_expr(value),
+            Expr::Assign {
+                id,
+                name,
+                value,
+                span,
+            } => visitor.visit_assign_expr(*id, name, value, span),
+            Expr::Binary {
+                left, op, right, ..
+            } => visitor.visit_binary_expr(left, op, right),
+            Expr::Call {
+                callee,
+                args,
+                named,
+                span,
+                ..
+            } => visitor.visit_call_expr(callee, args, named, span),
+            Expr::Class {
+                name,
+                super_class,
+                mixins,
+                methods,
+                fields,
+                private,
+                doc,
+                span,
+                ..
+            } => visitor.visit_class_expr(
+                name.as_deref(),
+                super_class.as_deref(),
+                mixins,
+                methods,
+                fields,
+                private,
+                doc.as_deref(),
+                span,
+            ),
+            Expr::Get {
+                id,
+                object,
+                name,
+                span,
+            } => visitor.visit_get_expr(*id, object, name, span),
+            Expr::Grouping { expr, span, .. } => visitor.visit_grouping_expr(expr, span),
+            Expr::Index {
+                object,
+                index,
+                span,
+                ..
+            } => visitor.visit_index_expr(object, index, span),
+            Expr::Literal { value, .. } => visitor.visit_literal_expr(value),
+            Expr::Logical {
+                left, op, right, ..
+            } => visitor.visit_logical_expr(left, op, right),
+            Expr::Match {
+                value, arms, span, ..
+            } => visitor.visit_match_expr(value, arms, span),
+            Expr::Placeholder { name, span, .. } => visitor.visit_placeholder_expr(name, span),
             Expr::Set {
                 object,
                 name,
+                op,
                 value,
                 span,
-            } => visitor.visit_set_expr(object, name, value, span),
-            Expr::Super { name, span } => visitor.visit_super_expr(name, span),
-            Expr::Unary { op, right } => visitor.visit_unary_expr(op, right),
-            Expr::Variable { name, span } => visitor.visit_variable_expr(name, span),
+                ..
+            } => visitor.visit_set_expr(object, name, op.as_ref(), value, span),
+            Expr::Super { id, name, span } => visitor.visit_super_expr(*id, name, span),
+            Expr::Tuple { elements, span, .. } => visitor.visit_tuple_expr(elements, span),
+            Expr::Unary { op, right, .. } => visitor.visit_unary_expr(op, right),
+            Expr::Variable { id, name, span } => visitor.visit_variable_expr(*id, name, span),
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        match self {
+            Expr::Assign { id, .. }
+            | Expr::Binary { id, .. }
+            | Expr::Call { id, .. }
+            | Expr::Class { id, .. }
+            | Expr::Get { id, .. }
+            | Expr::Grouping { id, .. }
+            | Expr::Index { id, .. }
+            | Expr::Literal { id, .. }
+            | Expr::Logical { id, .. }
+            | Expr::Match { id, .. }
+            | Expr::Placeholder { id, .. }
+            | Expr::Set { id, .. }
+            | Expr::Super { id, .. }
+            | Expr::Tuple { id, .. }
+            | Expr::Unary { id, .. }
+            | Expr::Variable { id, .. } => *id,
+        }
+    }
+
+    /// The source the expression covers, from the first char of it or its
+    /// operands to the last, e.g. all of `f(1) + "x"` for that sum.
+    pub fn extent(&self) -> Span {
+        let own = match self {
+            Expr::Binary { op, .. } | Expr::Logical { op, .. } | Expr::Unary { op, .. } => &op.span,
+            Expr::Assign { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Class { span, .. }
+            | Expr::Get { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Index { span, .. }
+            | Expr::Literal { span, .. }
+            | Expr::Match { span, .. }
+            | Expr::Placeholder { span, .. }
+            | Expr::Set { span, .. }
+            | Expr::Super { span, .. }
+            | Expr::Tuple { span, .. }
+            | Expr::Variable { span, .. } => span,
+        };
+        self.children()
+            .iter()
+            .map(|child| child.extent())
+            .fold(own.clone(), |extent, child| {
+                // the line is that of whichever starts first
+                if child.start < extent.start {
+                    child.merge(&extent)
+                } else {
+                    extent.merge(&child)
+                }
+            })
+    }
+
+    /// Whether this is the `this` of a method.
+    pub fn is_this(&self) -> bool {
+        matches!(self, Expr::Variable { name, .. } if name == "this")
+    }
+
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Assign { value, .. } => vec![value],
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                vec![left, right]
+            }
+            Expr::Call {
+                callee,
+                args,
+                named,
+                ..
+            } => {
+                let mut children = vec![callee.as_ref()];
+                children.extend(args);
+                children.extend(named.iter().map(|arg| &arg.value));
+                children
+            }
+            Expr::Class {
+                super_class,
+                mixins,
+                ..
+            } => super_class
+                .iter()
+                .map(|expr| &**expr)
+                .chain(mixins)
+                .collect(),
+            Expr::Get { object, .. } => vec![object],
+            Expr::Grouping { expr, .. } => vec![expr],
+            Expr::Index { object, index, .. } => vec![object, index],
+            Expr::Match { value, arms, .. } => {
+                let mut children = vec![value.as_ref()];
+                for arm in arms {
+                    children.extend(&arm.guard);
+                    children.push(&arm.body);
+                }
+                children
+            }
+            Expr::Set { object, value, .. } => vec![object, value],
+            Expr::Tuple { elements, .. } => elements.iter().collect(),
+            Expr::Unary { right, .. } => vec![right],
+            Expr::Literal { .. }
+            | Expr::Placeholder { .. }
+            | Expr::Super { .. }
+            | Expr::Variable { .. } => vec![],
+        }
+    }
+
+    /// Mutable counterpart of [`Expr::children`], for passes that rewrite the
+    /// tree in place.
+    pub fn children_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Expr::Assign { value, .. } => vec![value],
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                vec![left, right]
+            }
+            Expr::Call {
+                callee,
+                args,
+                named,
+                ..
+            } => {
+                let mut children = vec![callee.as_mut()];
+                children.extend(args);
+                children.extend(named.iter_mut().map(|arg| &mut arg.value));
+                children
+            }
+            Expr::Class {
+                super_class,
+                mixins,
+                ..
+            } => super_class
+                .iter_mut()
+                .map(|expr| &mut **expr)
+                .chain(mixins)
+                .collect(),
+            Expr::Get { object, .. } => vec![object],
+            Expr::Grouping { expr, .. } => vec![expr],
+            Expr::Index { object, index, .. } => vec![object, index],
+            Expr::Match { value, arms, .. } => {
+                let mut children = vec![value.as_mut()];
+                for arm in arms {
+                    children.extend(&mut arm.guard);
+                    children.push(&mut arm.body);
+                }
+                children
+            }
+            Expr::Set { object, value, .. } => vec![object, value],
+            Expr::Tuple { elements, .. } => elements.iter_mut().collect(),
+            Expr::Unary { right, .. } => vec![right],
+            Expr::Literal { .. }
+            | Expr::Placeholder { .. }
+            | Expr::Super { .. }
+            | Expr::Variable { .. } => vec![],
         }
     }
 }
 
+// The format is described on `Program`'s `Display`.
 impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Expr::Assign { name, value, .. } => write!(f, "(= {name} {value})"),
-            Expr::Binary { left, op, right } => write!(f, "({} {left} {right})", op.token),
-            Expr::Call { callee, args, .. } => write!(f, "(call {callee} {:?})", args),
+            Expr::Binary {
+                left, op, right, ..
+            }
+            | Expr::Logical {
+                left, op, right, ..
+            } => write!(f, "({} {left} {right})", op.token),
+            Expr::Call {
+                callee,
+                args,
+                named,
+                ..
+            } => {
+                write!(f, "(call {callee}")?;
+                for arg in args {
+                    write!(f, " {arg}")?;
+                }
+                for arg in named {
+                    write!(f, " ({}: {})", arg.name, arg.value)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Class {
+                name,
+                super_class,
+                mixins,
+                methods,
+                fields,
+                private,
+                doc,
+                ..
+            } => {
+                write!(f, "(class")?;
+                if let Some(name) = name {
+                    write!(f, " {name}")?;
+                }
+                if let Some(super_class) = super_class {
+                    write!(f, " < {super_class}")?;
+                }
+                if !mixins.is_empty() {
+                    write!(f, " (with")?;
+                    for mixin in mixins {
+                        write!(f, " {mixin}")?;
+                    }
+                    write!(f, ")")?;
+                }
+                if let Some(doc) = doc {
+                    write!(f, " (doc {doc:?})")?;
+                }
+                for name in private {
+                    write!(f, " (priv {name})")?;
+                }
+                for field in fields {
+                    write!(f, " (field {} {})", field.name, field.value)?;
+                }
+                for method in methods {
+                    write!(f, " {method}")?;
+                }
+                write!(f, ")")
+            }
             Expr::Get { object, name, .. } => write!(f, "(get {object} {name})"),
             Expr::Grouping { expr, .. } => write!(f, "{expr}"),
-            Expr::Literal { value } => write!(f, "{:?}", value),
+            Expr::Index { object, index, .. } => write!(f, "(index {object} {index})"),
+            Expr::Literal { value, .. } => write!(f, "{:?}", value),
+            Expr::Placeholder { name, .. } => write!(f, "@{{{name}}}"),
+            Expr::Match { value, arms, .. } => {
+                write!(f, "(match {value}")?;
+                for arm in arms {
+                    write!(f, " ({}", arm.pattern)?;
+                    if let Some(guard) = &arm.guard {
+                        write!(f, " if {guard}")?;
+                    }
+                    write!(f, " => {})", arm.body)?;
+                }
+                write!(f, ")")
+            }
             Expr::Set {
                 object,
                 name,
+                op: None,
                 value,
                 ..
             } => write!(f, "(set {object} {name} {value})"),
+            Expr::Set {
+                object,
+                name,
+                op: Some(op),
+                value,
+                ..
+            } => write!(f, "(set {object} {name} {}= {value})", op.token),
             Expr::Super { name, .. } => write!(f, "(super {name})"),
-            Expr::Unary { op, right } => write!(f, "({} {right})", op.token),
+            Expr::Tuple { elements, .. } => {
+                write!(f, "(tuple")?;
+                for element in elements {
+                    write!(f, " {element}")?;
+                }
+                write!(f, ")")
+            }
+            Expr::Unary { op, right, .. } => write!(f, "({} {right})", op.token),
             Expr::Variable { name, .. } => write!(f, "{name}"),
         }
     }
@@ -1,44 +1,96 @@
-use crate::{ErrorInfo, Expr, LiteralType, Object, Span, TokenType, TokenInfo};
+use crate::ast::{FieldInit, MatchArm, NamedArg, NodeId};
+use crate::{ErrorInfo, Expr, LiteralType, Object, Span, Stmt, TokenInfo};
 
+/// Expression visitor.
+///
+/// Children are passed as plain borrows (`&Expr`, `&[Expr]`, `&str`) rather
+/// than the containers the AST stores them in. Nodes that bind to a variable
+/// or look up a property also receive their [`NodeId`] so implementors can
+/// key side tables by it.
 pub trait Visitor {
     fn visit_assign_expr(
         &mut self,
-        name: &String,
-        value: &Box<Expr>,
+        id: NodeId,
+        name: &str,
+        value: &Expr,
         span: &Span,
     ) -> Result<Object, ErrorInfo>;
     fn visit_binary_expr(
         &mut self,
-        left: &Box<Expr>,
+        left: &Expr,
         op: &TokenInfo,
-        right: &Box<Expr>,
+        right: &Expr,
     ) -> Result<Object, ErrorInfo>;
+    /// `span` covers the whole call, from the start of the callee to the
+    /// closing paren.
     fn visit_call_expr(
         &mut self,
-        callee: &Box<Expr>,
-        args: &Vec<Expr>,
+        callee: &Expr,
+        args: &[Expr],
+        named: &[NamedArg],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo>;
+    #[allow(clippy::too_many_arguments)]
+    fn visit_class_expr(
+        &mut self,
+        name: Option<&str>,
+        super_class: Option<&Expr>,
+        mixins: &[Expr],
+        methods: &[Stmt],
+        fields: &[FieldInit],
+        private: &[String],
+        doc: Option<&str>,
         span: &Span,
     ) -> Result<Object, ErrorInfo>;
     fn visit_get_expr(
         &mut self,
-        object: &Box<Expr>,
-        name: &String,
+        id: NodeId,
+        object: &Expr,
+        name: &str,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo>;
+    fn visit_grouping_expr(&mut self, expr: &Expr, span: &Span) -> Result<Object, ErrorInfo>;
+    /// `span` runs from the start of the object to the closing bracket.
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
         span: &Span,
     ) -> Result<Object, ErrorInfo>;
-    fn visit_grouping_expr(&mut self, expr: &Box<Expr>, span: &Span) -> Result<Object, ErrorInfo>;
     fn visit_literal_expr(&mut self, value: &LiteralType) -> Result<Object, ErrorInfo>;
+    fn visit_logical_expr(
+        &mut self,
+        left: &Expr,
+        op: &TokenInfo,
+        right: &Expr,
+    ) -> Result<Object, ErrorInfo>;
+    fn visit_match_expr(
+        &mut self,
+        value: &Expr,
+        arms: &[MatchArm],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo>;
+    fn visit_placeholder_expr(&mut self, name: &str, span: &Span) -> Result<Object, ErrorInfo>;
     fn visit_set_expr(
         &mut self,
-        object: &Box<Expr>,
-        name: &String,
-        value: &Box<Expr>,
+        object: &Expr,
+        name: &str,
+        op: Option<&TokenInfo>,
+        value: &Expr,
         span: &Span,
     ) -> Result<Object, ErrorInfo>;
-    fn visit_super_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo>;
-    fn visit_unary_expr(
+    fn visit_super_expr(
         &mut self,
-        op: &TokenInfo,
-        right: &Box<Expr>,
+        id: NodeId,
+        name: &str,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo>;
+    fn visit_tuple_expr(&mut self, elements: &[Expr], span: &Span) -> Result<Object, ErrorInfo>;
+    fn visit_unary_expr(&mut self, op: &TokenInfo, right: &Expr) -> Result<Object, ErrorInfo>;
+    fn visit_variable_expr(
+        &mut self,
+        id: NodeId,
+        name: &str,
+        span: &Span,
     ) -> Result<Object, ErrorInfo>;
-    fn visit_variable_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo>;
 }
@@ -1,18 +1,35 @@
 use std::fmt;
 
-#[derive(PartialEq, Clone)]
+#[derive(Clone)]
 pub enum LiteralType {
     String(String),
-    Number(f64),
+    // the value and, when parsed from source, the lexeme as written (`0xFF`,
+    // `1_000`, `1e3`) so tools can reproduce the author's spelling
+    Number(f64, Option<String>),
     Boolean(bool),
     Nil,
 }
 
+// Literals are equal when their values are: `0xFF` and `255` are the same
+// number however they were spelled.
+impl PartialEq for LiteralType {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (LiteralType::String(l), LiteralType::String(r)) => l == r,
+            (LiteralType::Number(l, _), LiteralType::Number(r, _)) => l == r,
+            (LiteralType::Boolean(l), LiteralType::Boolean(r)) => l == r,
+            (LiteralType::Nil, LiteralType::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Debug for LiteralType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            LiteralType::String(s) => write!(f, "\"{s}\""),
-            LiteralType::Number(n) => write!(f, "{n}"),
+            LiteralType::String(s) => write!(f, "{s:?}"),
+            LiteralType::Number(_, Some(lexeme)) => write!(f, "{lexeme}"),
+            LiteralType::Number(n, None) => write!(f, "{n}"),
             LiteralType::Boolean(b) => write!(f, "{b}"),
             LiteralType::Nil => write!(f, "nil"),
         }
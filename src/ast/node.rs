@@ -0,0 +1,23 @@
+use std::fmt;
+
+use crate::{Expr, Stmt};
+
+/// Stable identifier of an AST node, handed out by the parser as nodes are built.
+///
+/// Side tables (resolver bindings, caches, profilers) are keyed by `NodeId`
+/// rather than by span, since zero-width or synthetic nodes can share a span.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct NodeId(pub u32);
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Borrowed view of a node returned by [`crate::ast::Program::node`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Node<'a> {
+    Expr(&'a Expr),
+    Stmt(&'a Stmt),
+}
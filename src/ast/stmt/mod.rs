@@ -1,4 +1,5 @@
-use crate::{ErrorInfo, Expr, Object, Span};
+use crate::ast::{FieldInit, NodeId};
+use crate::{ErrorInfo, Expr, Span};
 
 mod visitor;
 pub use visitor::Visitor;
@@ -8,49 +9,137 @@ use std::fmt;
 #[derive(Debug, PartialEq, Clone)]
 pub enum Stmt {
     Expr {
+        id: NodeId,
         expr: Expr,
     },
+    /// `print a, b;`, printing nothing but the newline when empty.
     Print {
-        expr: Expr,
+        id: NodeId,
+        exprs: Vec<Expr>,
     },
     Let {
+        id: NodeId,
         name: String,
+        // the type in `let s: string`, only checked with strict types
+        annotation: Option<String>,
         value: Option<Expr>,
         is_const: bool,
         span: Span,
     },
+    /// `let (a, b) = value;`, unpacking a tuple into one variable per name.
+    Destructure {
+        id: NodeId,
+        names: Vec<String>,
+        value: Expr,
+        is_const: bool,
+        // the parenthesized names
+        span: Span,
+    },
     Block {
+        id: NodeId,
         stmts: Vec<Stmt>,
     },
     If {
+        id: NodeId,
         condition: Expr,
         truthy: Box<Stmt>,
         falsy: Option<Box<Stmt>>,
+        // the `if` keyword
+        span: Span,
     },
     While {
+        id: NodeId,
         condition: Expr,
         body: Box<Stmt>,
+        // the `while` keyword, or `for` for a lowered for loop
+        span: Span,
+    },
+    /// Turned into a `while` loop by `lower` before the program runs.
+    For {
+        id: NodeId,
+        initializer: Option<Box<Stmt>>,
+        condition: Option<Expr>,
+        increment: Option<Expr>,
+        body: Box<Stmt>,
+        // the `for` keyword
+        span: Span,
+    },
+    /// `for (let i = start to end step s) body`, counting `i` from `start`
+    /// up to and including `end`. Turned into a `while` loop by `lower`.
+    ForRange {
+        id: NodeId,
+        // a `let` of the counter, with `start` as its value
+        initializer: Box<Stmt>,
+        end: Expr,
+        step: Option<Expr>,
+        body: Box<Stmt>,
+        // the `for` keyword
+        span: Span,
     },
     Function {
+        id: NodeId,
         name: String,
         params: Vec<String>,
+        signature: Signature,
+        // checked on entry, with the arguments bound
+        requires: Vec<Expr>,
+        // checked on every return, with `result` bound to the value returned
+        ensures: Vec<Expr>,
         body: Vec<Stmt>,
+        // the string the body starts with, which is also left in the body
+        doc: Option<String>,
         span: Span,
     },
     Return {
+        id: NodeId,
         value: Option<Expr>,
         span: Span,
     },
     Class {
+        id: NodeId,
         name: String,
-        super_class: Option<String>,
+        super_class: Option<Expr>,
+        // the classes after `with`, whose methods are copied in
+        mixins: Vec<Expr>,
         methods: Vec<Stmt>,
+        // initialized in order, after those of the superclass
+        fields: Vec<FieldInit>,
+        // the members declared `priv`, methods and fields alike
+        private: Vec<String>,
+        // a string right after the `{`
+        doc: Option<String>,
         span: Span,
     },
     Break {
+        id: NodeId,
         span: Span,
     },
     Continue {
+        id: NodeId,
+        span: Span,
+    },
+    Defer {
+        id: NodeId,
+        body: Box<Stmt>,
+        span: Span,
+    },
+    /// `import "path.lang";` and the forms after it in `ImportForm`, running
+    /// a module once and binding what it exports.
+    Import {
+        id: NodeId,
+        specifier: String,
+        form: ImportForm,
+        span: Span,
+    },
+    /// A lone `;`, which does nothing.
+    Empty {
+        id: NodeId,
+        span: Span,
+    },
+    /// A statement that failed to parse, standing in for it in a program
+    /// parsed with `Parser::parse_partial`. Running it is an error.
+    Error {
+        id: NodeId,
         span: Span,
     },
 }
@@ -58,62 +147,244 @@ pub enum Stmt {
 impl Stmt {
     pub fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ErrorInfo> {
         match self {
-            Stmt::Expr { expr } => visitor.visit_expr_stmt(expr),
-            Stmt::Print { expr } => visitor.visit_print_stmt(expr),
+            Stmt::Expr { expr, .. } => visitor.visit_expr_stmt(expr),
+            Stmt::Print { exprs, .. } => visitor.visit_print_stmt(exprs),
             Stmt::Let {
                 name,
+                annotation,
+                value,
+                is_const,
+                span,
+                ..
+            } => {
+                visitor.visit_let_stmt(name, annotation.as_deref(), value.as_ref(), *is_const, span)
+            }
+            Stmt::Destructure {
+                names,
                 value,
                 is_const,
                 span,
-            } => visitor.visit_let_stmt(name, value, *is_const, span),
-            Stmt::Block { stmts } => visitor.visit_block_stmt(stmts),
+                ..
+            } => visitor.visit_destructure_stmt(names, value, *is_const, span),
+            Stmt::Block { stmts, .. } => visitor.visit_block_stmt(stmts),
             Stmt::If {
                 condition,
                 truthy,
                 falsy,
-            } => visitor.visit_if_stmt(condition, truthy, falsy),
-            Stmt::While { condition, body } => visitor.visit_while_stmt(condition, body),
+                ..
+            } => visitor.visit_if_stmt(condition, truthy, falsy.as_deref()),
+            Stmt::While {
+                condition, body, ..
+            } => visitor.visit_while_stmt(condition, body),
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                span,
+                ..
+            } => visitor.visit_for_stmt(
+                initializer.as_deref(),
+                condition.as_ref(),
+                increment.as_ref(),
+                body,
+                span,
+            ),
+            Stmt::ForRange {
+                initializer,
+                end,
+                step,
+                body,
+                span,
+                ..
+            } => visitor.visit_for_range_stmt(initializer, end, step.as_ref(), body, span),
             Stmt::Function {
                 name,
                 params,
+                signature,
+                requires,
+                ensures,
                 body,
+                doc,
                 span,
-            } => visitor.visit_function_stmt(name, params, body, span),
-            Stmt::Return { value, span } => visitor.visit_return_stmt(value, span),
+                ..
+            } => visitor.visit_function_stmt(
+                name,
+                params,
+                signature,
+                requires,
+                ensures,
+                body,
+                doc.as_deref(),
+                span,
+            ),
+            Stmt::Return { value, span, .. } => visitor.visit_return_stmt(value.as_ref(), span),
             Stmt::Class {
                 name,
                 super_class,
+                mixins,
                 methods,
+                fields,
+                private,
+                doc,
                 span,
-            } => visitor.visit_class_stmt(name, super_class, methods, span),
-            Stmt::Break { span } => visitor.visit_break_stmt(span),
-            Stmt::Continue { span } => visitor.visit_continue_stmt(span),
+                ..
+            } => visitor.visit_class_stmt(
+                name,
+                super_class.as_ref(),
+                mixins,
+                methods,
+                fields,
+                private,
+                doc.as_deref(),
+                span,
+            ),
+            Stmt::Break { span, .. } => visitor.visit_break_stmt(span),
+            Stmt::Continue { span, .. } => visitor.visit_continue_stmt(span),
+            Stmt::Defer { body, span, .. } => visitor.visit_defer_stmt(body, span),
+            Stmt::Import {
+                specifier,
+                form,
+                span,
+                ..
+            } => visitor.visit_import_stmt(specifier, form, span),
+            Stmt::Empty { .. } => visitor.visit_empty_stmt(),
+            Stmt::Error { span, .. } => visitor.visit_error_stmt(span),
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        match self {
+            Stmt::Expr { id, .. }
+            | Stmt::Print { id, .. }
+            | Stmt::Let { id, .. }
+            | Stmt::Destructure { id, .. }
+            | Stmt::Block { id, .. }
+            | Stmt::If { id, .. }
+            | Stmt::While { id, .. }
+            | Stmt::For { id, .. }
+            | Stmt::ForRange { id, .. }
+            | Stmt::Function { id, .. }
+            | Stmt::Return { id, .. }
+            | Stmt::Class { id, .. }
+            | Stmt::Break { id, .. }
+            | Stmt::Continue { id, .. }
+            | Stmt::Defer { id, .. }
+            | Stmt::Import { id, .. }
+            | Stmt::Empty { id, .. }
+            | Stmt::Error { id, .. } => *id,
         }
     }
 }
 
+/// The types a function's parameters and result are annotated with, as in
+/// `fn add(a: number, b: number): number`. They are only checked with strict
+/// types.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Signature {
+    /// One entry per parameter, or none at all when no parameter has a type.
+    pub params: Vec<Option<String>>,
+    pub returns: Option<String>,
+}
+
+impl Signature {
+    /// The type of the `index`th parameter, if it has one.
+    pub fn param(&self, index: usize) -> Option<&str> {
+        self.params.get(index).and_then(Option::as_deref)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.returns.is_none() && self.params.iter().all(Option::is_none)
+    }
+}
+
+/// What an import binds. A module exports each of its top-level names and
+/// each name it re-exports.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ImportForm {
+    /// `import "m.lang";`, binding every name the module exports.
+    All,
+    /// `import "m.lang" as m;`, binding `alias` to the module, a read-only
+    /// object whose properties are its exports.
+    Module { alias: String, span: Span },
+    /// `import { a, b as c } from "m.lang";`, binding the names listed.
+    Names(Vec<ImportName>),
+    /// `export { a, b as c } from "m.lang";`, adding the names listed to
+    /// what the module it is in exports without binding them there.
+    ReExport(Vec<ImportName>),
+}
+
+/// `name` or `name as alias` in the braces of an import or re-export.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImportName {
+    pub name: String,
+    pub alias: Option<String>,
+    pub span: Span,
+}
+
+impl ImportName {
+    /// The name this binds or exports, the alias if there is one.
+    pub fn bound(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+impl fmt::Display for ImportName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.alias {
+            Some(alias) => write!(f, "({} as {alias})", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// `name:type`, or just `name` without a type.
+fn annotated(name: &str, annotation: Option<&str>) -> String {
+    match annotation {
+        Some(annotation) => format!("{name}:{annotation}"),
+        None => name.to_string(),
+    }
+}
+
+// The format is described on `Program`'s `Display`.
 impl fmt::Display for Stmt {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Stmt::Expr { expr } => write!(f, "{}", expr),
-            Stmt::Print { expr } => write!(f, "(print {})", expr),
+            Stmt::Expr { expr, .. } => write!(f, "{}", expr),
+            Stmt::Print { exprs, .. } => {
+                write!(f, "(print")?;
+                for expr in exprs {
+                    write!(f, " {expr}")?;
+                }
+                write!(f, ")")
+            }
             Stmt::Let {
                 name,
+                annotation,
                 value,
                 is_const,
-                span: _,
+                ..
             } => {
-                if *is_const {
-                    write!(f, "(const {} {})", name, value.as_ref().unwrap())
-                } else {
-                    write!(f, "(let {} {})", name, value.as_ref().unwrap())
+                let keyword = if *is_const { "const" } else { "let" };
+                let name = annotated(name, annotation.as_deref());
+                match value {
+                    Some(value) => write!(f, "({keyword} {name} {value})"),
+                    None => write!(f, "({keyword} {name})"),
                 }
             }
-            Stmt::Block { stmts } => {
-                let mut s = String::new();
-                write!(f, "(")?;
+            Stmt::Destructure {
+                names,
+                value,
+                is_const,
+                ..
+            } => {
+                let keyword = if *is_const { "const" } else { "let" };
+                write!(f, "({keyword} ({}) {value})", names.join(" "))
+            }
+            Stmt::Block { stmts, .. } => {
+                write!(f, "(block")?;
                 for stmt in stmts {
-                    write!(f, "{}", stmt)?;
+                    write!(f, " {stmt}")?;
                 }
                 write!(f, ")")
             }
@@ -121,57 +392,144 @@ impl fmt::Display for Stmt {
                 condition,
                 truthy,
                 falsy,
+                ..
             } => {
+                // closed either way, so a nested `if` shows which one an
+                // `else` belongs to
                 write!(f, "(if {condition} then {truthy}")?;
                 if let Some(else_block) = falsy {
-                    write!(f, " else {else_block})")?;
+                    write!(f, " else {else_block}")?;
                 }
-                Ok(())
+                write!(f, ")")
+            }
+            Stmt::While {
+                condition, body, ..
+            } => write!(f, "(while {condition} {body})"),
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                write!(f, "(for (")?;
+                if let Some(initializer) = initializer {
+                    write!(f, "{initializer}")?;
+                }
+                write!(f, "; ")?;
+                if let Some(condition) = condition {
+                    write!(f, "{condition}")?;
+                }
+                write!(f, "; ")?;
+                if let Some(increment) = increment {
+                    write!(f, "{increment}")?;
+                }
+                write!(f, ") {body})")
+            }
+            Stmt::ForRange {
+                initializer,
+                end,
+                step,
+                body,
+                ..
+            } => {
+                write!(f, "(for {initializer} to {end}")?;
+                if let Some(step) = step {
+                    write!(f, " step {step}")?;
+                }
+                write!(f, " {body})")
             }
-            Stmt::While { condition, body } => write!(f, "(while ({}) {})", condition, body),
             Stmt::Function {
                 name,
                 params,
+                signature,
+                requires,
+                ensures,
                 body,
-                span: _,
+                ..
             } => {
-                let mut s = String::new();
-                write!(f, "function {name} (")?;
-                for (i, param) in params.iter().enumerate() {
-                    if i != 0 {
-                        write!(f, ", ")?;
+                let params: Vec<_> = params
+                    .iter()
+                    .enumerate()
+                    .map(|(index, param)| annotated(param, signature.param(index)))
+                    .collect();
+                let name = annotated(name, signature.returns.as_deref());
+                write!(f, "(fn {name} ({})", params.join(" "))?;
+                for (keyword, clauses) in [("requires", requires), ("ensures", ensures)] {
+                    if !clauses.is_empty() {
+                        write!(f, " ({keyword}")?;
+                        for clause in clauses {
+                            write!(f, " {clause}")?;
+                        }
+                        write!(f, ")")?;
                     }
-                    write!(f, "{param}")?;
                 }
-                write!(f, ") ")
-            }
-            Stmt::Return { value, span: _ } => {
-                if let Some(value) = value {
-                    write!(f, "return {}", value)
-                } else {
-                    write!(f, "return")
+                for stmt in body {
+                    write!(f, " {stmt}")?;
                 }
+                write!(f, ")")
             }
+            Stmt::Return { value, .. } => match value {
+                Some(value) => write!(f, "(return {value})"),
+                None => write!(f, "(return)"),
+            },
             Stmt::Class {
                 name,
                 super_class,
+                mixins,
                 methods,
-                span: _,
+                fields,
+                private,
+                doc,
+                ..
             } => {
-                let mut s = String::new();
-                s.push_str(&format!("class {} ", name));
+                write!(f, "(class {name}")?;
                 if let Some(super_class) = super_class {
-                    s.push_str(&format!("extends {} ", super_class));
+                    write!(f, " < {super_class}")?;
+                }
+                if !mixins.is_empty() {
+                    write!(f, " (with")?;
+                    for mixin in mixins {
+                        write!(f, " {mixin}")?;
+                    }
+                    write!(f, ")")?;
+                }
+                if let Some(doc) = doc {
+                    write!(f, " (doc {doc:?})")?;
+                }
+                for name in private {
+                    write!(f, " (priv {name})")?;
+                }
+                for field in fields {
+                    write!(f, " (field {} {})", field.name, field.value)?;
                 }
-                s.push_str("{\n");
                 for method in methods {
-                    s.push_str(&format!("{}\n", method));
+                    write!(f, " {method}")?;
                 }
-                s.push_str("}");
-                write!(f, "{}", s)
+                write!(f, ")")
+            }
+            Stmt::Break { .. } => write!(f, "(break)"),
+            Stmt::Continue { .. } => write!(f, "(continue)"),
+            Stmt::Defer { body, .. } => write!(f, "(defer {body})"),
+            Stmt::Import {
+                specifier, form, ..
+            } => {
+                let (keyword, names) = match form {
+                    ImportForm::All => return write!(f, "(import \"{specifier}\")"),
+                    ImportForm::Module { alias, .. } => {
+                        return write!(f, "(import \"{specifier}\" as {alias})")
+                    }
+                    ImportForm::Names(names) => ("import", names),
+                    ImportForm::ReExport(names) => ("export", names),
+                };
+                write!(f, "({keyword} \"{specifier}\"")?;
+                for name in names {
+                    write!(f, " {name}")?;
+                }
+                write!(f, ")")
             }
-            Stmt::Break { span: _ } => write!(f, "break"),
-            Stmt::Continue { span: _ } => write!(f, "continue"),
+            Stmt::Empty { .. } => write!(f, ";"),
+            Stmt::Error { .. } => write!(f, "(error)"),
         }
     }
 }
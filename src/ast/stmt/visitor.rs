@@ -1,38 +1,86 @@
+use crate::ast::{FieldInit, ImportForm, Signature};
 use crate::{ErrorInfo, Expr, Span, Stmt};
 
+/// Statement visitor.
+///
+/// Children are passed as plain borrows: `&Stmt` for boxed bodies,
+/// `Option<&Stmt>`/`Option<&Expr>` for optional parts and slices for lists.
 pub trait Visitor {
     fn visit_expr_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo>;
-    fn visit_print_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo>;
-    fn visit_block_stmt(&mut self, stmts: &Vec<Stmt>) -> Result<(), ErrorInfo>;
+    fn visit_print_stmt(&mut self, exprs: &[Expr]) -> Result<(), ErrorInfo>;
+    fn visit_block_stmt(&mut self, stmts: &[Stmt]) -> Result<(), ErrorInfo>;
+    #[allow(clippy::too_many_arguments)]
     fn visit_function_stmt(
         &mut self,
-        name: &String,
-        params: &Vec<String>,
-        body: &Vec<Stmt>,
+        name: &str,
+        params: &[String],
+        signature: &Signature,
+        requires: &[Expr],
+        ensures: &[Expr],
+        body: &[Stmt],
+        doc: Option<&str>,
         span: &Span,
     ) -> Result<(), ErrorInfo>;
     fn visit_if_stmt(
         &mut self,
         condition: &Expr,
-        truthy: &Box<Stmt>,
-        falsy: &Option<Box<Stmt>>,
+        truthy: &Stmt,
+        falsy: Option<&Stmt>,
     ) -> Result<(), ErrorInfo>;
     fn visit_let_stmt(
         &mut self,
-        name: &String,
-        value: &Option<Expr>,
+        name: &str,
+        annotation: Option<&str>,
+        value: Option<&Expr>,
         is_const: bool,
         span: &Span,
     ) -> Result<(), ErrorInfo>;
-    fn visit_return_stmt(&mut self, value: &Option<Expr>, span: &Span) -> Result<(), ErrorInfo>;
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &Box<Stmt>) -> Result<(), ErrorInfo>;
+    fn visit_destructure_stmt(
+        &mut self,
+        names: &[String],
+        value: &Expr,
+        is_const: bool,
+        span: &Span,
+    ) -> Result<(), ErrorInfo>;
+    fn visit_return_stmt(&mut self, value: Option<&Expr>, span: &Span) -> Result<(), ErrorInfo>;
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<(), ErrorInfo>;
+    fn visit_for_stmt(
+        &mut self,
+        initializer: Option<&Stmt>,
+        condition: Option<&Expr>,
+        increment: Option<&Expr>,
+        body: &Stmt,
+        span: &Span,
+    ) -> Result<(), ErrorInfo>;
+    fn visit_for_range_stmt(
+        &mut self,
+        initializer: &Stmt,
+        end: &Expr,
+        step: Option<&Expr>,
+        body: &Stmt,
+        span: &Span,
+    ) -> Result<(), ErrorInfo>;
+    #[allow(clippy::too_many_arguments)]
     fn visit_class_stmt(
         &mut self,
-        name: &String,
-        super_class: &Option<String>,
-        methods: &Vec<Stmt>,
+        name: &str,
+        super_class: Option<&Expr>,
+        mixins: &[Expr],
+        methods: &[Stmt],
+        fields: &[FieldInit],
+        private: &[String],
+        doc: Option<&str>,
         span: &Span,
     ) -> Result<(), ErrorInfo>;
     fn visit_break_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo>;
     fn visit_continue_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo>;
+    fn visit_defer_stmt(&mut self, body: &Stmt, span: &Span) -> Result<(), ErrorInfo>;
+    fn visit_import_stmt(
+        &mut self,
+        specifier: &str,
+        form: &ImportForm,
+        span: &Span,
+    ) -> Result<(), ErrorInfo>;
+    fn visit_empty_stmt(&mut self) -> Result<(), ErrorInfo>;
+    fn visit_error_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo>;
 }
@@ -0,0 +1,269 @@
+use crate::ast::NodeId;
+use crate::{Expr, Span, Stmt};
+
+// Trees are dropped with a worklist rather than by recursion, so that a
+// deeply nested expression, such as one a program generates, cannot
+// overflow the stack when it goes away. Each node has its children taken
+// out before it is dropped, leaving nothing for the compiler's drop glue to
+// recurse into.
+
+// The nodes taken out of a tree being dropped.
+#[derive(Default)]
+struct Parts {
+    exprs: Vec<Expr>,
+    stmts: Vec<Stmt>,
+}
+
+impl Parts {
+    fn drop_all(mut self) {
+        loop {
+            if let Some(mut expr) = self.exprs.pop() {
+                expr.take_children(&mut self);
+            } else if let Some(mut stmt) = self.stmts.pop() {
+                stmt.take_children(&mut self);
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+impl Expr {
+    /// Moves the expression out, leaving an empty tuple in its place.
+    pub(crate) fn take(&mut self) -> Expr {
+        let empty = Expr::Tuple {
+            id: NodeId(0),
+            elements: Vec::new(),
+            span: Span::default(),
+        };
+        std::mem::replace(self, empty)
+    }
+
+    fn take_children(&mut self, parts: &mut Parts) {
+        match self {
+            Expr::Assign { value: expr, .. }
+            | Expr::Get { object: expr, .. }
+            | Expr::Grouping { expr, .. }
+            | Expr::Unary { right: expr, .. } => parts.exprs.push(expr.take()),
+            Expr::Binary { left, right, .. }
+            | Expr::Logical { left, right, .. }
+            | Expr::Index {
+                object: left,
+                index: right,
+                ..
+            }
+            | Expr::Set {
+                object: left,
+                value: right,
+                ..
+            } => {
+                parts.exprs.push(left.take());
+                parts.exprs.push(right.take());
+            }
+            Expr::Call {
+                callee,
+                args,
+                named,
+                ..
+            } => {
+                parts.exprs.push(callee.take());
+                parts.exprs.append(args);
+                let named = std::mem::take(named).into_iter();
+                parts.exprs.extend(named.map(|arg| arg.value));
+            }
+            Expr::Class {
+                super_class,
+                mixins,
+                methods,
+                fields,
+                ..
+            } => {
+                if let Some(super_class) = super_class.take() {
+                    parts.exprs.push(*super_class);
+                }
+                parts.exprs.append(mixins);
+                parts.stmts.append(methods);
+                let fields = std::mem::take(fields).into_iter();
+                parts.exprs.extend(fields.map(|field| field.value));
+            }
+            Expr::Match { value, arms, .. } => {
+                parts.exprs.push(value.take());
+                for arm in std::mem::take(arms) {
+                    parts.exprs.extend(arm.guard);
+                    parts.exprs.push(arm.body);
+                }
+            }
+            Expr::Tuple { elements, .. } => parts.exprs.append(elements),
+            Expr::Literal { .. }
+            | Expr::Placeholder { .. }
+            | Expr::Super { .. }
+            | Expr::Variable { .. } => {}
+        }
+    }
+}
+
+impl Drop for Expr {
+    fn drop(&mut self) {
+        let mut parts = Parts::default();
+        self.take_children(&mut parts);
+        parts.drop_all();
+    }
+}
+
+impl Stmt {
+    /// Moves the statement out, leaving an empty one in its place.
+    pub(crate) fn take(&mut self) -> Stmt {
+        let empty = Stmt::Empty {
+            id: NodeId(0),
+            span: Span::default(),
+        };
+        std::mem::replace(self, empty)
+    }
+
+    fn take_children(&mut self, parts: &mut Parts) {
+        match self {
+            Stmt::Expr { expr, .. } | Stmt::Destructure { value: expr, .. } => {
+                parts.exprs.push(expr.take())
+            }
+            Stmt::Print { exprs, .. } => parts.exprs.append(exprs),
+            Stmt::Let { value, .. } | Stmt::Return { value, .. } => {
+                parts.exprs.extend(value.take());
+            }
+            Stmt::Block { stmts, .. } => parts.stmts.append(stmts),
+            Stmt::If {
+                condition,
+                truthy,
+                falsy,
+                ..
+            } => {
+                parts.exprs.push(condition.take());
+                parts.stmts.push(truthy.take());
+                parts.stmts.extend(falsy.take().map(|falsy| *falsy));
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                parts.exprs.push(condition.take());
+                parts.stmts.push(body.take());
+            }
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                parts.stmts.extend(initializer.take().map(|stmt| *stmt));
+                parts.exprs.extend(condition.take());
+                parts.exprs.extend(increment.take());
+                parts.stmts.push(body.take());
+            }
+            Stmt::ForRange {
+                initializer,
+                end,
+                step,
+                body,
+                ..
+            } => {
+                parts.stmts.push(initializer.take());
+                parts.exprs.push(end.take());
+                parts.exprs.extend(step.take());
+                parts.stmts.push(body.take());
+            }
+            Stmt::Function {
+                requires,
+                ensures,
+                body,
+                ..
+            } => {
+                parts.exprs.append(requires);
+                parts.exprs.append(ensures);
+                parts.stmts.append(body);
+            }
+            Stmt::Class {
+                super_class,
+                mixins,
+                methods,
+                fields,
+                ..
+            } => {
+                parts.exprs.extend(super_class.take());
+                parts.exprs.append(mixins);
+                parts.stmts.append(methods);
+                let fields = std::mem::take(fields).into_iter();
+                parts.exprs.extend(fields.map(|field| field.value));
+            }
+            Stmt::Defer { body, .. } => parts.stmts.push(body.take()),
+            Stmt::Break { .. }
+            | Stmt::Continue { .. }
+            | Stmt::Import { .. }
+            | Stmt::Empty { .. }
+            | Stmt::Error { .. } => {}
+        }
+    }
+}
+
+impl Drop for Stmt {
+    fn drop(&mut self) {
+        let mut parts = Parts::default();
+        self.take_children(&mut parts);
+        parts.drop_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ast::NodeId;
+    use crate::{Expr, LiteralType, Span, Stmt, TokenInfo, TokenType};
+
+    // Far less than the main thread gets, and far less than dropping the
+    // trees below by recursion would take.
+    const STACK: usize = 256 * 1024;
+
+    fn on_small_stack(f: impl FnOnce() + Send + 'static) {
+        let thread = std::thread::Builder::new().stack_size(STACK);
+        thread.spawn(f).unwrap().join().unwrap();
+    }
+
+    fn one() -> Expr {
+        Expr::Literal {
+            id: NodeId(0),
+            value: LiteralType::Number(1.0, None),
+            span: Span::default(),
+        }
+    }
+
+    #[test]
+    fn test_deep_binary_chain() {
+        on_small_stack(|| {
+            // 1 + (1 + (1 + ...))
+            let mut expr = one();
+            for _ in 0..200_000 {
+                expr = Expr::Binary {
+                    id: NodeId(0),
+                    left: Box::new(one()),
+                    op: TokenInfo::new(TokenType::Plus, 0, 0, 1, 0),
+                    right: Box::new(expr),
+                };
+            }
+            drop(expr);
+        });
+    }
+
+    #[test]
+    fn test_deep_blocks() {
+        on_small_stack(|| {
+            let mut stmt = Stmt::Expr {
+                id: NodeId(0),
+                expr: one(),
+            };
+            for _ in 0..200_000 {
+                stmt = Stmt::Block {
+                    id: NodeId(0),
+                    stmts: vec![stmt],
+                };
+            }
+            drop(stmt);
+        });
+    }
+}
@@ -0,0 +1,516 @@
+//! Structural comparison of two versions of a program, for review tools.
+//! Statements and expressions are compared by the S-expression form they
+//! print as, so formatting, comments and where things sit in the source
+//! make no difference.
+
+use std::fmt;
+
+use crate::{FieldInit, Program, Span, Stmt};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// One difference `diff_programs` found, such as a function whose
+/// parameters changed or a method added to a class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstChange {
+    pub kind: ChangeKind,
+    /// Where the statement is in the old source; None for one added.
+    pub old: Option<Span>,
+    /// Where the statement is in the new source; None for one removed.
+    pub new: Option<Span>,
+    /// What changed, e.g. `function 'render': parameter list changed`.
+    pub description: String,
+}
+
+/// `added: function 'area' (line 4)`, or for a change
+/// `modified: if condition modified (line 7 -> line 9)`.
+impl fmt::Display for AstChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Modified => "modified",
+        };
+        write!(f, "{kind}: {}", self.description)?;
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => write!(f, " (line {} -> line {})", old.line, new.line),
+            (Some(span), None) | (None, Some(span)) => write!(f, " (line {})", span.line),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// The differences between the top-level statements of `old` and `new`,
+/// in the order they appear in the new source, removals where they were
+/// in the old one.
+///
+/// Functions, classes, variables and constants are paired by kind and
+/// name wherever they are, so moving one is no change. A pair that differs
+/// is one `Modified` entry, except that the methods of a class are paired
+/// by name in turn and reported one by one. Other statements are paired
+/// in order, skipping those that are the same on both sides; an old and a
+/// new one left between the same neighbours are a modification of one
+/// another when they are the same kind of statement. Whatever is left
+/// unpaired was added or removed whole.
+pub fn diff_programs(old: &Program, new: &Program) -> Vec<AstChange> {
+    let mut diff = Diff {
+        old,
+        new,
+        changes: Vec::new(),
+    };
+    diff.statements("", &old.stmts, &new.stmts);
+    diff.changes.sort_by_key(|change| {
+        let span = change.new.as_ref().or(change.old.as_ref());
+        span.map_or(0, |span| span.start)
+    });
+    diff.changes
+}
+
+struct Diff<'a> {
+    old: &'a Program,
+    new: &'a Program,
+    changes: Vec<AstChange>,
+}
+
+impl Diff<'_> {
+    // Compares two lists of statements, naming what it reports after
+    // `context`, e.g. `class 'Shape': ` for methods.
+    fn statements(&mut self, context: &str, old: &[Stmt], new: &[Stmt]) {
+        // only the statements of a class come with a context
+        let declared = |stmt: &Stmt| declared(stmt, !context.is_empty());
+        let mut paired = vec![false; new.len()];
+        let mut old_rest = Vec::new();
+        for stmt in old {
+            let Some(name) = declared(stmt) else {
+                old_rest.push(stmt);
+                continue;
+            };
+            let found = new
+                .iter()
+                .enumerate()
+                .position(|(i, other)| !paired[i] && declared(other).as_ref() == Some(&name));
+            match found {
+                Some(i) => {
+                    paired[i] = true;
+                    self.declaration(&format!("{context}{name}"), stmt, &new[i]);
+                }
+                None => self.push(ChangeKind::Removed, Some(stmt), None, context, &name),
+            }
+        }
+        let mut new_rest = Vec::new();
+        for (stmt, paired) in new.iter().zip(paired) {
+            match declared(stmt) {
+                Some(name) if !paired => {
+                    self.push(ChangeKind::Added, None, Some(stmt), context, &name)
+                }
+                Some(_) => {}
+                None => new_rest.push(stmt),
+            }
+        }
+        self.in_order(context, &old_rest, &new_rest);
+    }
+
+    // Pairs statements that print the same, keeping their order, and
+    // reports those in between.
+    fn in_order(&mut self, context: &str, old: &[&Stmt], new: &[&Stmt]) {
+        let old_text: Vec<_> = old.iter().map(|stmt| stmt.to_string()).collect();
+        let new_text: Vec<_> = new.iter().map(|stmt| stmt.to_string()).collect();
+        let (mut i, mut j) = (0, 0);
+        for (next_i, next_j) in common(&old_text, &new_text)
+            .into_iter()
+            .chain([(old.len(), new.len())])
+        {
+            self.gap(context, &old[i..next_i], &new[j..next_j]);
+            (i, j) = (next_i + 1, next_j + 1);
+        }
+    }
+
+    // The statements between two that are the same on both sides.
+    fn gap(&mut self, context: &str, removed: &[&Stmt], added: &[&Stmt]) {
+        let mut added = added.iter().copied().peekable();
+        for &old in removed {
+            match added.next_if(|new| same_kind(old, new)) {
+                Some(new) => {
+                    let description = format!("{context}{}", modified(old, new));
+                    self.changes.push(AstChange {
+                        kind: ChangeKind::Modified,
+                        old: self.old_span(old),
+                        new: self.new_span(new),
+                        description,
+                    });
+                }
+                None => {
+                    let what = format!("{} statement", kind(old));
+                    self.push(ChangeKind::Removed, Some(old), None, context, &what);
+                }
+            }
+        }
+        for new in added {
+            let what = format!("{} statement", kind(new));
+            self.push(ChangeKind::Added, None, Some(new), context, &what);
+        }
+    }
+
+    // Two declarations of the same name, `named`.
+    fn declaration(&mut self, named: &str, old: &Stmt, new: &Stmt) {
+        let mut details = Vec::new();
+        match (old, new) {
+            (
+                Stmt::Function {
+                    params,
+                    signature,
+                    requires,
+                    ensures,
+                    body,
+                    ..
+                },
+                Stmt::Function {
+                    params: new_params,
+                    signature: new_signature,
+                    requires: new_requires,
+                    ensures: new_ensures,
+                    body: new_body,
+                    ..
+                },
+            ) => {
+                if params != new_params {
+                    details.push("parameter list changed".to_string());
+                }
+                if signature != new_signature {
+                    details.push("type annotations changed".to_string());
+                }
+                if printed(requires) != printed(new_requires)
+                    || printed(ensures) != printed(new_ensures)
+                {
+                    details.push("contracts changed".to_string());
+                }
+                if let Some(detail) = body_change(body, new_body) {
+                    details.push(detail);
+                }
+            }
+            (
+                Stmt::Class {
+                    super_class,
+                    mixins,
+                    methods,
+                    fields,
+                    private,
+                    doc,
+                    ..
+                },
+                Stmt::Class {
+                    super_class: new_super_class,
+                    mixins: new_mixins,
+                    methods: new_methods,
+                    fields: new_fields,
+                    private: new_private,
+                    doc: new_doc,
+                    ..
+                },
+            ) => {
+                if printed(super_class) != printed(new_super_class) {
+                    details.push("superclass changed".to_string());
+                }
+                if printed(mixins) != printed(new_mixins) {
+                    details.push("mixins changed".to_string());
+                }
+                if field_texts(fields) != field_texts(new_fields) {
+                    details.push("fields changed".to_string());
+                }
+                if private != new_private {
+                    details.push("private members changed".to_string());
+                }
+                if doc != new_doc {
+                    details.push("doc string changed".to_string());
+                }
+                self.statements(&format!("{named}: "), methods, new_methods);
+            }
+            (
+                Stmt::Let {
+                    annotation, value, ..
+                },
+                Stmt::Let {
+                    annotation: new_annotation,
+                    value: new_value,
+                    ..
+                },
+            ) => {
+                if annotation != new_annotation {
+                    details.push("type changed".to_string());
+                }
+                if printed(value) != printed(new_value) {
+                    details.push("value changed".to_string());
+                }
+            }
+            // paired declarations are of the same kind
+            _ => {}
+        }
+        if !details.is_empty() {
+            self.changes.push(AstChange {
+                kind: ChangeKind::Modified,
+                old: self.old_span(old),
+                new: self.new_span(new),
+                description: format!("{named}: {}", details.join(", ")),
+            });
+        }
+    }
+
+    fn push(
+        &mut self,
+        kind: ChangeKind,
+        old: Option<&Stmt>,
+        new: Option<&Stmt>,
+        context: &str,
+        what: &str,
+    ) {
+        let change = AstChange {
+            kind,
+            old: old.and_then(|stmt| self.old_span(stmt)),
+            new: new.and_then(|stmt| self.new_span(stmt)),
+            description: format!("{context}{what}"),
+        };
+        self.changes.push(change);
+    }
+
+    fn old_span(&self, stmt: &Stmt) -> Option<Span> {
+        span(self.old, stmt)
+    }
+
+    fn new_span(&self, stmt: &Stmt) -> Option<Span> {
+        span(self.new, stmt)
+    }
+}
+
+// What a declaration is paired by, e.g. `function 'area'`, or `method
+// 'area'` among `methods`. None for other statements.
+fn declared(stmt: &Stmt, methods: bool) -> Option<String> {
+    match stmt {
+        Stmt::Function { name, .. } if methods => Some(format!("method '{name}'")),
+        Stmt::Function { name, .. } => Some(format!("function '{name}'")),
+        Stmt::Class { name, .. } => Some(format!("class '{name}'")),
+        Stmt::Let {
+            name,
+            is_const: true,
+            ..
+        } => Some(format!("constant '{name}'")),
+        Stmt::Let { name, .. } => Some(format!("variable '{name}'")),
+        _ => None,
+    }
+}
+
+// The statement's kind, as named in a change.
+fn kind(stmt: &Stmt) -> &'static str {
+    match stmt {
+        Stmt::Expr { .. } => "expression",
+        Stmt::Print { .. } => "print",
+        Stmt::Let { is_const: true, .. } | Stmt::Destructure { is_const: true, .. } => "const",
+        Stmt::Let { .. } | Stmt::Destructure { .. } => "let",
+        Stmt::Block { .. } => "block",
+        Stmt::If { .. } => "if",
+        Stmt::While { .. } => "while",
+        Stmt::For { .. } | Stmt::ForRange { .. } => "for",
+        Stmt::Function { .. } => "function",
+        Stmt::Return { .. } => "return",
+        Stmt::Class { .. } => "class",
+        Stmt::Break { .. } => "break",
+        Stmt::Continue { .. } => "continue",
+        Stmt::Defer { .. } => "defer",
+        Stmt::Import { .. } => "import",
+        Stmt::Empty { .. } => "empty",
+        Stmt::Error { .. } => "broken",
+    }
+}
+
+fn same_kind(old: &Stmt, new: &Stmt) -> bool {
+    kind(old) == kind(new)
+}
+
+// What changed between two statements of the same kind that differ, e.g.
+// `if condition modified`.
+fn modified(old: &Stmt, new: &Stmt) -> String {
+    let part = match (old, new) {
+        (
+            Stmt::If { condition, .. } | Stmt::While { condition, .. },
+            Stmt::If {
+                condition: new_condition,
+                ..
+            }
+            | Stmt::While {
+                condition: new_condition,
+                ..
+            },
+        ) if condition.to_string() != new_condition.to_string() => " condition",
+        (Stmt::If { .. }, _) => " branches",
+        (Stmt::While { .. }, _) => " body",
+        (Stmt::Return { .. }, _) => " value",
+        (Stmt::Import { .. }, _) => " path",
+        _ => "",
+    };
+    format!("{}{part} modified", kind(old))
+}
+
+// The one statement of a function body that changed, when only one did,
+// or that the body changed.
+fn body_change(old: &[Stmt], new: &[Stmt]) -> Option<String> {
+    if printed(old) == printed(new) {
+        return None;
+    }
+    let differing: Vec<_> = old
+        .iter()
+        .zip(new)
+        .filter(|(old, new)| old.to_string() != new.to_string())
+        .collect();
+    match differing[..] {
+        [(old_stmt, new_stmt)] if old.len() == new.len() && same_kind(old_stmt, new_stmt) => {
+            Some(modified(old_stmt, new_stmt))
+        }
+        _ => Some("body changed".to_string()),
+    }
+}
+
+fn printed<'a, T: fmt::Display + 'a>(items: impl IntoIterator<Item = &'a T>) -> Vec<String> {
+    items.into_iter().map(ToString::to_string).collect()
+}
+
+fn field_texts(fields: &[FieldInit]) -> Vec<(&str, String)> {
+    fields
+        .iter()
+        .map(|field| (field.name.as_str(), field.value.to_string()))
+        .collect()
+}
+
+// Where `stmt` starts in the source of `program`, or the span it keeps
+// itself for a statement made outside the parser, such as a method.
+fn span(program: &Program, stmt: &Stmt) -> Option<Span> {
+    if let Some(span) = program.stmt_span(stmt.id()) {
+        return Some(span.clone());
+    }
+    match stmt {
+        Stmt::Let { span, .. }
+        | Stmt::Destructure { span, .. }
+        | Stmt::If { span, .. }
+        | Stmt::While { span, .. }
+        | Stmt::For { span, .. }
+        | Stmt::ForRange { span, .. }
+        | Stmt::Function { span, .. }
+        | Stmt::Return { span, .. }
+        | Stmt::Class { span, .. }
+        | Stmt::Break { span, .. }
+        | Stmt::Continue { span, .. }
+        | Stmt::Defer { span, .. }
+        | Stmt::Import { span, .. }
+        | Stmt::Empty { span, .. }
+        | Stmt::Error { span, .. } => Some(span.clone()),
+        Stmt::Expr { expr, .. } => Some(expr.extent()),
+        Stmt::Print { .. } | Stmt::Block { .. } => None,
+    }
+}
+
+// The positions of a longest run of equal items the two lists share, in
+// order, as (index in `old`, index in `new`).
+fn common(old: &[String], new: &[String]) -> Vec<(usize, usize)> {
+    // lengths[i][j] is the length of the longest for old[i..] and new[j..]
+    let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            pairs.push((i, j));
+            (i, j) = (i + 1, j + 1);
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_programs, AstChange, ChangeKind};
+    use crate::{Lexer, Parser};
+
+    fn diff(old: &str, new: &str) -> Vec<AstChange> {
+        let parse = |source: &str| {
+            Parser::new(Lexer::new(source.to_string()))
+                .parse_program()
+                .unwrap()
+        };
+        diff_programs(&parse(old), &parse(new))
+    }
+
+    fn shown(changes: &[AstChange]) -> Vec<String> {
+        changes.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_renamed_variable() {
+        let old = "fn area(w, h) {\n  let a = w * h;\n  return a;\n}\nprint area(2, 3);";
+        let new = "fn area(w, h) {\n  let size = w * h;\n  return size;\n}\n\nprint area(2, 3);";
+        let changes = diff(old, new);
+        assert_eq!(
+            shown(&changes),
+            ["modified: function 'area': body changed (line 1 -> line 1)"]
+        );
+        assert_eq!(changes[0].kind, ChangeKind::Modified);
+    }
+
+    #[test]
+    fn test_formatting_and_order_ignored() {
+        let old = "fn a() { return 1; }\n# first\nfn b(x) { return x; }\nlet n = a();";
+        let new = "fn b(x) {\n  return x;\n}\nfn a() {\n  return (1);\n}\n\nlet n = a();";
+        assert_eq!(diff(old, new), []);
+    }
+
+    #[test]
+    fn test_added_method() {
+        let old = "class Shape {\n  init(n) { this.n = n; }\n}";
+        let new = "class Shape {\n  init(n) { this.n = n; }\n  area() { return 0; }\n}";
+        let changes = diff(old, new);
+        assert_eq!(
+            shown(&changes),
+            ["added: class 'Shape': method 'area' (line 3)"]
+        );
+    }
+
+    #[test]
+    fn test_changes() {
+        let old = "let x = 1;
+fn render(a) { if (a > 1) { print a; } return a; }
+if (x) { print 1; }
+print \"done\";
+class A { f() {} }";
+        let new = "let x = 2;
+fn render(a, b) { if (a > 2) { print a; } return a; }
+if (!x) { print 1; }
+class A < B { f() {} }
+while (x) {}";
+        assert_eq!(
+            shown(&diff(old, new)),
+            [
+                "modified: variable 'x': value changed (line 1 -> line 1)",
+                "modified: function 'render': parameter list changed, if condition modified \
+                 (line 2 -> line 2)",
+                "modified: if condition modified (line 3 -> line 3)",
+                "removed: print statement (line 4)",
+                "modified: class 'A': superclass changed (line 5 -> line 4)",
+                "added: while statement (line 5)",
+            ]
+        );
+    }
+}
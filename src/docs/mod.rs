@@ -0,0 +1,193 @@
+//! Markdown reference pages written from the doc strings of a program: the
+//! string a function or method body starts with, and the one right after
+//! the `{` of a class.
+
+use crate::{Expr, Program, Signature, Stmt};
+
+/// A Markdown page headed `title` listing the top-level functions and
+/// classes of `program` in the order they are declared, each with its
+/// parameters and doc string. A class lists its methods under it, leaving
+/// out those declared `priv`. A class bound with `let Name = class { ... }`
+/// is listed under that name.
+pub fn document(title: &str, program: &Program) -> String {
+    let mut page = format!("# {title}\n");
+    for stmt in &program.stmts {
+        match stmt {
+            Stmt::Function { .. } => function(&mut page, "##", "fn ", stmt),
+            Stmt::Class {
+                name,
+                super_class,
+                methods,
+                private,
+                doc,
+                ..
+            } => class(&mut page, name, super_class.as_ref(), methods, private, doc),
+            Stmt::Let {
+                name,
+                value:
+                    Some(Expr::Class {
+                        super_class,
+                        methods,
+                        private,
+                        doc,
+                        ..
+                    }),
+                ..
+            } => class(
+                &mut page,
+                name,
+                super_class.as_deref(),
+                methods,
+                private,
+                doc,
+            ),
+            _ => {}
+        }
+    }
+    page
+}
+
+fn class(
+    page: &mut String,
+    name: &str,
+    super_class: Option<&Expr>,
+    methods: &[Stmt],
+    private: &[String],
+    doc: &Option<String>,
+) {
+    match super_class {
+        Some(Expr::Variable {
+            name: super_class, ..
+        }) => heading(page, "##", &format!("class {name} < {super_class}")),
+        _ => heading(page, "##", &format!("class {name}")),
+    }
+    paragraph(page, doc);
+    for method in methods {
+        if let Stmt::Function { name, .. } = method {
+            if !private.contains(name) {
+                function(page, "###", "", method);
+            }
+        }
+    }
+}
+
+// A function or method as it is declared, e.g. `fn area(w, h: Number): Number`.
+fn function(page: &mut String, level: &str, keyword: &str, stmt: &Stmt) {
+    let Stmt::Function {
+        name,
+        params,
+        signature,
+        doc,
+        ..
+    } = stmt
+    else {
+        return;
+    };
+    let mut text = format!("{keyword}{name}({})", parameters(params, signature));
+    if let Some(returns) = &signature.returns {
+        text.push_str(&format!(": {returns}"));
+    }
+    heading(page, level, &text);
+    paragraph(page, doc);
+}
+
+fn parameters(params: &[String], signature: &Signature) -> String {
+    let mut list = String::new();
+    for (index, param) in params.iter().enumerate() {
+        if index > 0 {
+            list.push_str(", ");
+        }
+        list.push_str(param);
+        if let Some(annotation) = signature.param(index) {
+            list.push_str(&format!(": {annotation}"));
+        }
+    }
+    list
+}
+
+fn heading(page: &mut String, level: &str, text: &str) {
+    page.push_str(&format!("\n{level} `{text}`\n"));
+}
+
+// The doc string with each line trimmed, so one written indented to match
+// the code reads as a plain paragraph.
+fn paragraph(page: &mut String, doc: &Option<String>) {
+    let Some(doc) = doc else {
+        return;
+    };
+    let lines: Vec<_> = doc.trim().lines().map(str::trim).collect();
+    page.push_str(&format!("\n{}\n", lines.join("\n")));
+}
+
+#[cfg(test)]
+mod test {
+    use super::document;
+    use crate::{Lexer, Parser};
+
+    #[test]
+    fn test_markdown() {
+        let source = r#"
+fn area(w, h: Number): Number {
+  "The area of a w by h rectangle.";
+  return w * h;
+}
+
+let unit = 1;
+
+class Shape {
+  "Something with an area.
+   Subclasses override area."
+  init(name) { this.name = name; }
+  area() { return 0; }
+}
+
+class Square < Shape {
+  init(side) {
+    "A square with sides of length side.";
+    super.init("square");
+    this.side = side;
+  }
+  area() { return area(this.side, this.side); }
+  priv check() { return this.side > 0; }
+}
+
+let Point = class {
+  "A position on the plane.";
+  init(x, y) { this.x = x; this.y = y; }
+};
+"#;
+        let program = Parser::new(Lexer::new(source.to_string()))
+            .parse_program()
+            .unwrap();
+        let expected = "# shapes.lang
+
+## `fn area(w, h: Number): Number`
+
+The area of a w by h rectangle.
+
+## `class Shape`
+
+Something with an area.
+Subclasses override area.
+
+### `init(name)`
+
+### `area()`
+
+## `class Square < Shape`
+
+### `init(side)`
+
+A square with sides of length side.
+
+### `area()`
+
+## `class Point`
+
+A position on the plane.
+
+### `init(x, y)`
+";
+        assert_eq!(document("shapes.lang", &program), expected);
+    }
+}
@@ -1,17 +1,27 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
+use crate::object::Teardown;
 use crate::{Error, Object};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Environment {
     values: HashMap<String, (Object, bool)>,
+    // the strict-types annotations of the names declared here with one
+    types: HashMap<String, String>,
     enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     pub fn new() -> Environment {
         Self {
             values: HashMap::new(),
+            types: HashMap::new(),
             enclosing: None,
         }
     }
@@ -19,10 +29,13 @@ impl Environment {
     pub fn new_from_closure(enclosing: &Rc<RefCell<Environment>>) -> Self {
         Self {
             values: HashMap::new(),
+            types: HashMap::new(),
             enclosing: Some(Rc::clone(enclosing)),
         }
     }
 
+    /// Declares `name` here. A `let` without a value declares it as nil, so
+    /// reading it is not an error, unlike reading a name never declared.
     pub fn define(&mut self, name: String, value: Object, is_const: bool) -> Result<(), Error> {
         if is_const && value.is_nil() {
             return Err(Error::Syntax(
@@ -31,17 +44,34 @@ impl Environment {
         }
         if let Some((_, constant)) = self.values.get(&name) {
             if *constant {
-                return Err(Error::Syntax(
-                    "cannot reassign a constant variable".to_string(),
-                ));
+                return Err(Error::ConstReassign { name });
             }
         }
-        self.values.insert(name, (value.clone(), is_const));
+        self.types.remove(&name);
+        if let Some((old, _)) = self.values.insert(name, (value, is_const)) {
+            Teardown::discard(old);
+        }
         Ok(())
     }
 
-    pub fn get(&mut self, name: &String) -> Result<Object, Error> {
-        if let Some(value) = self.values.get(&name.to_string()) {
+    /// Records the type `name`, just declared here, is annotated with.
+    pub fn annotate(&mut self, name: &str, annotation: &str) {
+        self.types.insert(name.to_string(), annotation.to_string());
+    }
+
+    /// The type annotating the nearest declaration of `name`, if it has one.
+    pub fn annotation(&self, name: &str) -> Option<String> {
+        if self.values.contains_key(name) {
+            self.types.get(name).cloned()
+        } else {
+            self.enclosing.as_ref()?.borrow().annotation(name)
+        }
+    }
+
+    /// Fails with `Error::Name` when no environment up the chain declares
+    /// `name`.
+    pub fn get(&mut self, name: &str) -> Result<Object, Error> {
+        if let Some(value) = self.values.get(name) {
             Ok(value.0.clone())
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow_mut().get(name)
@@ -50,22 +80,96 @@ impl Environment {
         }
     }
 
-    pub fn assign(&mut self, name: &String, value: Object) -> Result<Object, Error> {
-        if let Some((_, is_const)) = self.values.get(&name.to_string()) {
+    /// The names defined directly in this environment, sorted, with their
+    /// values. Enclosing environments are not included.
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        let mut bindings: Vec<_> = self
+            .values
+            .iter()
+            .map(|(name, (value, _))| (name.clone(), value.clone()))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    /// The values defined directly in this environment with their names,
+    /// unsorted.
+    pub fn values(&self) -> impl Iterator<Item = (&str, &Object)> {
+        self.values
+            .iter()
+            .map(|(name, (value, _))| (name.as_str(), value))
+    }
+
+    pub fn enclosing(&self) -> Option<&Rc<RefCell<Environment>>> {
+        self.enclosing.as_ref()
+    }
+
+    /// Every name a lookup from here could find, including those in
+    /// enclosing environments, unsorted and possibly repeated.
+    pub fn visible_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.values.keys().cloned().collect();
+        if let Some(enclosing) = &self.enclosing {
+            names.extend(enclosing.borrow().visible_names());
+        }
+        names
+    }
+
+    /// Whether `environment` is `scope` or nested somewhere inside it.
+    pub fn is_within(
+        environment: &Rc<RefCell<Environment>>,
+        scope: &Rc<RefCell<Environment>>,
+    ) -> bool {
+        let mut current = environment.clone();
+        loop {
+            if Rc::ptr_eq(&current, scope) {
+                return true;
+            }
+            let Some(enclosing) = current.borrow().enclosing.clone() else {
+                return false;
+            };
+            current = enclosing;
+        }
+    }
+
+    /// Assigns to the nearest declaration of `name`. Without one it fails
+    /// with `Error::Name` rather than declaring a global.
+    pub fn assign(&mut self, name: &str, value: Object) -> Result<Object, Error> {
+        if let Some((_, is_const)) = self.values.get(name) {
             if *is_const {
-                return Err(Error::Syntax(
-                    "cannot reassign to a constant variable".to_string(),
-                ));
+                return Err(Error::ConstReassign {
+                    name: name.to_string(),
+                });
             }
-            self.values
+            let old = self
+                .values
                 .insert(name.to_string(), (value.clone(), *is_const));
-            return Ok(value);
+            if let Some((old, _)) = old {
+                Teardown::discard(old);
+            }
+            Ok(value)
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow_mut().assign(name, value)
         } else {
             Err(Error::Name(name.to_string()))
         }
     }
+
+    pub(crate) fn tear_down(&mut self, teardown: &mut Teardown) {
+        for (_, (value, _)) in self.values.drain() {
+            teardown.object(value);
+        }
+        if let Some(enclosing) = self.enclosing.take() {
+            teardown.scope(enclosing);
+        }
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        let mut teardown = Teardown::default();
+        self.tear_down(&mut teardown);
+        teardown.run();
+    }
 }
 
 #[cfg(test)]
@@ -78,13 +182,13 @@ mod test {
         env
             .define("a".to_string(), Object::Nil, false)
             .unwrap();
-        assert_eq!(env.get(&"a".to_string()).unwrap(), Object::Nil);
+        assert_eq!(env.get("a").unwrap(), Object::Nil);
 
         env
-            .assign(&"a".to_string(), Object::Number(1.0))
+            .assign("a", Object::Number(1.0))
             .unwrap();
         assert_eq!(
-            env.get(&"a".to_string()).unwrap(),
+            env.get("a").unwrap(),
             Object::Number(1.0)
         );
     }
@@ -1,32 +1,226 @@
-use std::fmt;
+use std::{fmt, time::Duration};
 
-use crate::Object;
+use crate::{numfmt, Object, TokenType};
 
+/// Every error a script can run into. Messages are made by `Display` from
+/// the fields, so embedders match on the variants rather than the text; the
+/// `String` variants are the ones whose messages vary too much to be split
+/// into fields.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Error {
+    /// Text the lexer cannot make a token of.
+    Lex(String),
+    /// The parser needed one token and found another.
+    Expected {
+        expected: String,
+        found: String,
+    },
+    /// A binary operator given operands of types it does not take, named by
+    /// `Object::type_name`.
+    Operands {
+        op: TokenType,
+        left: &'static str,
+        right: &'static str,
+    },
+    /// A unary operator given an operand of a type it does not take.
+    Operand {
+        op: TokenType,
+        operand: &'static str,
+    },
+    /// A call with more arguments than the callee takes.
+    Arity {
+        expected: usize,
+        got: usize,
+    },
+    /// A position outside the tuple or string indexed.
+    Index {
+        index: f64,
+        len: usize,
+    },
+    /// An assignment to, or a second declaration of, a constant.
+    ConstReassign {
+        name: String,
+    },
+    /// The values a script can reach took more than `limit` bytes, see
+    /// `Interpretor::max_heap_bytes`.
+    MemoryLimit {
+        limit: usize,
+    },
+    /// A call made with `limit` calls already in progress, see
+    /// `Interpretor::max_call_depth`.
+    CallDepth {
+        limit: usize,
+    },
+    /// A run went on for `elapsed`, reaching its `limit`, see
+    /// `Interpretor::timeout`.
+    Timeout {
+        limit: Duration,
+        elapsed: Duration,
+    },
+    /// A bug in the interpreter rather than in the script.
+    Internal(String),
     Syntax(String),
     Value(String),
     Parse(String),
     Runtime(String),
     Name(String),
     ZeroDivision,
+    // an exact arithmetic native's result would have been rounded
+    Overflow(String),
     TooManyParamerters,
     Return(Object),
+    // unwinds from a native waiting on the host, see `Interpretor::pending`
+    Suspend(u64),
     Type(String),
+    Attribute(String),
+    Resolve(String),
+    // a `requires` or `ensures` clause was falsy
+    Contract(String),
+    // a module could not be found, read or loaded
+    Import(String),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Error::Lex(x) => write!(f, "SyntaxError: {x}"),
+            Error::Expected { expected, found } => {
+                write!(
+                    f,
+                    "SyntaxError: Expected: \"{expected}\" Found: \"{found}\""
+                )
+            }
+            Error::Operands { op, left, right } => {
+                write!(
+                    f,
+                    "TypeError: \"{op}\" not supported between {left} and {right}"
+                )
+            }
+            Error::Operand { op, operand } => {
+                write!(
+                    f,
+                    "TypeError: bad operand type for unary \"{op}\": {operand}"
+                )
+            }
+            Error::Arity { expected, got } => {
+                write!(f, "TypeError: expected {expected} arguments but got {got}")
+            }
+            Error::Index { index, len } => write!(
+                f,
+                "IndexError: index {index} is out of range for a length of {len}"
+            ),
+            Error::ConstReassign { name } => {
+                write!(f, "SyntaxError: cannot reassign the constant \"{name}\"")
+            }
+            Error::MemoryLimit { limit } => {
+                write!(f, "MemoryError: the script needs more than {limit} bytes")
+            }
+            Error::CallDepth { limit } => {
+                write!(f, "RecursionError: calls nested more than {limit} deep")
+            }
+            Error::Timeout { limit, elapsed } => {
+                let ms = |duration: &Duration| numfmt::shortest(duration.as_secs_f64() * 1000.0);
+                write!(
+                    f,
+                    "TimeoutError: ran for {}ms, past the limit of {}ms",
+                    ms(elapsed),
+                    ms(limit)
+                )
+            }
+            Error::Internal(x) => write!(f, "InternalError: {x}"),
             Error::Syntax(x) => write!(f, "SyntaxError: {x}"),
             Error::Value(x) => write!(f, "ValueError: {x}"),
             Error::Parse(x) => write!(f, "ParseError: {x}"),
-            Error::Runtime(x) => write!(f, "ParseError: {x}"),
+            Error::Runtime(x) => write!(f, "RuntimeError: {x}"),
             Error::ZeroDivision => write!(f, "ZeroDivisionError: division by zero"),
+            Error::Overflow(x) => write!(f, "OverflowError: {x}"),
             Error::Name(x) => write!(f, "NameError: undefined variable \"{x}\""),
             Error::TooManyParamerters => write!(f, "TooManyParamerters: excedded maximum number of parameters"),
-            Error:: Return(x) => write!(f, "return {x}"),
+            Error::Return(x) => write!(f, "return {x}"),
+            Error::Suspend(token) => write!(f, "suspended on {token}"),
             Error::Type(x) => write!(f, "TypeError: {x}"),
+            Error::Attribute(x) => write!(f, "AttributeError: {x}"),
+            Error::Resolve(x) => write!(f, "ResolveError: {x}"),
+            Error::Contract(x) => write!(f, "ContractError: {x}"),
+            Error::Import(x) => write!(f, "ImportError: {x}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::Error;
+    use crate::TokenType;
+
+    #[test]
+    fn test_structured_messages() {
+        let cases = [
+            (
+                Error::Expected {
+                    expected: ";".to_string(),
+                    found: "}".to_string(),
+                },
+                "SyntaxError: Expected: \";\" Found: \"}\"",
+            ),
+            (
+                Error::Operands {
+                    op: TokenType::Lt,
+                    left: "number",
+                    right: "nil",
+                },
+                "TypeError: \"<\" not supported between number and nil",
+            ),
+            (
+                Error::Operand {
+                    op: TokenType::Minus,
+                    operand: "string",
+                },
+                "TypeError: bad operand type for unary \"-\": string",
+            ),
+            (
+                Error::Arity {
+                    expected: 1,
+                    got: 3,
+                },
+                "TypeError: expected 1 arguments but got 3",
+            ),
+            (
+                Error::Index {
+                    index: -1.0,
+                    len: 2,
+                },
+                "IndexError: index -1 is out of range for a length of 2",
+            ),
+            (
+                Error::ConstReassign {
+                    name: "pi".to_string(),
+                },
+                "SyntaxError: cannot reassign the constant \"pi\"",
+            ),
+            (
+                Error::MemoryLimit { limit: 1024 },
+                "MemoryError: the script needs more than 1024 bytes",
+            ),
+            (
+                Error::Overflow("too big".to_string()),
+                "OverflowError: too big",
+            ),
+            (
+                Error::CallDepth { limit: 200 },
+                "RecursionError: calls nested more than 200 deep",
+            ),
+            (
+                Error::Timeout {
+                    limit: Duration::from_millis(50),
+                    elapsed: Duration::from_micros(50_500),
+                },
+                "TimeoutError: ran for 50.5ms, past the limit of 50ms",
+            ),
+        ];
+        for (error, message) in cases {
+            assert_eq!(error.to_string(), message);
         }
     }
 }
@@ -1,5 +1,17 @@
+#[allow(clippy::module_inception)]
 mod error;
 pub use error::Error;
+mod set;
+pub use set::DiagnosticSet;
+mod snippet;
+pub use snippet::Snippet;
+mod style;
+pub use style::{ColorChoice, Style};
+mod suggest;
+mod suppress;
+pub use suppress::Suppressions;
+
+use std::fmt;
 
 use crate::Span;
 
@@ -7,24 +19,240 @@ use crate::Span;
 pub struct ErrorInfo {
     pub error: Error,
     span: Span,
+    // boxed, as most errors have none of it, to keep every `Result`
+    // carrying an ErrorInfo small
+    details: Option<Box<Details>>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+struct Details {
+    // extra locations that explain the error, e.g. a related declaration
+    notes: Vec<(String, Span)>,
+    // code near the error underlined alongside it, each with a short label
+    labels: Vec<(String, Span)>,
+    // a suggested fix, kept apart from the error itself
+    hint: Option<String>,
 }
 
 impl ErrorInfo {
     pub fn new(error: Error, line: usize, line_start: usize, start: usize, end: usize) -> Self {
+        Self::new_with_span(error, Span::new(line, line_start, start, end))
+    }
+
+    pub fn new_with_span(error: Error, span: Span) -> Self {
         Self {
             error,
-            span: Span::new(line, line_start, start, end),
+            span,
+            details: None,
         }
     }
 
-    pub fn new_with_span(error: Error, span: Span) -> Self {
+    fn details_mut(&mut self) -> &mut Details {
+        self.details.get_or_insert_with(Box::default)
+    }
+
+    pub fn with_note(mut self, message: String, span: Span) -> Self {
+        self.details_mut().notes.push((message, span));
+        self
+    }
+
+    pub fn notes(&self) -> &[(String, Span)] {
+        self.details.as_ref().map_or(&[], |details| &details.notes)
+    }
+
+    /// Underlines `span` next to the error's own span when the source is
+    /// shown, with `label` under it, e.g. the type of an operand. Unlike a
+    /// note it is not listed when there is no source to show.
+    pub fn with_label(mut self, label: String, span: Span) -> Self {
+        self.details_mut().labels.push((label, span));
+        self
+    }
+
+    pub fn labels(&self) -> &[(String, Span)] {
+        self.details.as_ref().map_or(&[], |details| &details.labels)
+    }
+
+    /// Adds a "did you mean" hint when one of `candidates` is a near miss
+    /// for the misspelled `name`.
+    pub fn with_suggestion<'a, I>(mut self, name: &str, candidates: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        if let Some(candidate) = suggest::closest(name, candidates) {
+            self.details_mut().hint = Some(format!("did you mean '{candidate}'?"));
+        }
+        self
+    }
+
+    pub fn hint(&self) -> Option<&str> {
+        self.details.as_ref()?.hint.as_deref()
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    pub fn report(&self, style: Style) {
+        eprint!("{}", self.render_styled(None, style));
+    }
+
+    /// Like `report`, with the lines of `source` the error points at shown
+    /// under it.
+    pub fn report_in(&self, source: &str, style: Style) {
+        eprint!("{}", self.render_styled(Some(&Snippet::new(source)), style));
+    }
+
+    pub fn render(&self, snippet: Option<&Snippet>) -> String {
+        self.render_styled(snippet, Style::PLAIN)
+    }
+
+    pub fn render_styled(&self, snippet: Option<&Snippet>, style: Style) -> String {
+        let mut out = format!(
+            "{}, {}\n",
+            style.error(&self.error.to_string()),
+            style.location(&location(&self.span))
+        );
+        match snippet {
+            Some(snippet) if self.labels().is_empty() => {
+                out.push_str(&snippet.render_styled(&self.span, style))
+            }
+            Some(snippet) => out.push_str(&snippet.render_labels(&self.span, self.labels(), style)),
+            None => {}
+        }
+        for (message, span) in self.notes() {
+            out.push_str(&format!(
+                "  note: {message}, line {}, pos {}\n",
+                span.line,
+                span.start.saturating_sub(span.line_start)
+            ));
+        }
+        if let Some(hint) = self.hint() {
+            out.push_str(&format!("  hint: {hint}\n"));
+        }
+        out
+    }
+}
+
+impl fmt::Display for ErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}", self.error, location(&self.span))
+    }
+}
+
+fn location(span: &Span) -> String {
+    format!(
+        "line {}, pos {}",
+        span.line,
+        span.start.saturating_sub(span.line_start)
+    )
+}
+
+/// The code of every warning, as named in a `# lang-ignore:` pragma.
+pub const WARNING_CODES: &[&str] = &[
+    "missing-semicolon",
+    "shadowing",
+    "string-append-in-loop",
+    "empty-if-body",
+    "print-call",
+    "unreachable-code",
+    "constant-condition",
+    "unknown-code",
+    "misplaced-pragma",
+    "unused-suppression",
+];
+
+/// A diagnostic that does not stop the program from running.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Warning {
+    pub message: String,
+    // one of `WARNING_CODES`
+    code: &'static str,
+    span: Span,
+}
+
+impl Warning {
+    pub fn new(code: &'static str, message: String, span: Span) -> Self {
+        debug_assert!(WARNING_CODES.contains(&code), "unknown code {code}");
         Self {
-            error,
+            message,
+            code,
             span,
         }
     }
 
-    pub fn report(&self) {
-        eprintln!("{}, line {}, pos {}", self.error, self.span.line, self.span.start - self.span.line_start);
+    /// What kind of warning this is, e.g. `shadowing`.
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    pub fn report(&self, style: Style) {
+        eprintln!("{}", self.render(style));
+    }
+
+    pub fn render(&self, style: Style) -> String {
+        format!(
+            "{}: {}, {}",
+            style.warning("warning"),
+            self.message,
+            style.location(&location(&self.span))
+        )
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(Style::PLAIN))
+    }
+}
+
+/// An error or a warning, in the order a pipeline such as `run_source` met
+/// them.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Diagnostic {
+    Error(ErrorInfo),
+    Warning(Warning),
+}
+
+impl Diagnostic {
+    pub fn span(&self) -> &Span {
+        match self {
+            Diagnostic::Error(err) => err.span(),
+            Diagnostic::Warning(warning) => warning.span(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Diagnostic::Error(_))
+    }
+
+    /// Prints it to stderr, with the lines of `source` an error points at
+    /// when the source is known.
+    pub fn report_in(&self, source: Option<&str>, style: Style) {
+        match (self, source) {
+            (Diagnostic::Error(err), Some(source)) => err.report_in(source, style),
+            (Diagnostic::Error(err), None) => err.report(style),
+            (Diagnostic::Warning(warning), _) => warning.report(style),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::Error(err) => write!(f, "{err}"),
+            Diagnostic::Warning(warning) => write!(f, "{warning}"),
+        }
     }
 }
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use super::{Diagnostic, Snippet, Style};
+
+/// The diagnostics of one or more files, in the order they are shown.
+///
+/// `sort_dedup` puts them in order of file, then errors before warnings,
+/// then position, dropping those reported twice. `render_all` shows at most
+/// `limit` errors, so a cascade ends in "and N more errors".
+#[derive(Debug, Clone)]
+pub struct DiagnosticSet {
+    // with the file each is in, "" when there is only the one
+    diagnostics: Vec<(String, Diagnostic)>,
+    /// The most errors `render_all` shows. Warnings are always shown.
+    pub limit: usize,
+    /// Puts the warnings of each file before its errors.
+    pub warnings_first: bool,
+}
+
+impl Default for DiagnosticSet {
+    fn default() -> Self {
+        Self {
+            diagnostics: Vec::new(),
+            limit: 20,
+            warnings_first: false,
+        }
+    }
+}
+
+impl DiagnosticSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `diagnostic`, found in `file`.
+    pub fn push(&mut self, file: &str, diagnostic: Diagnostic) {
+        self.diagnostics.push((file.to_string(), diagnostic));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().map(|(_, diagnostic)| diagnostic)
+    }
+
+    /// The diagnostics, without their files.
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.diagnostics
+            .into_iter()
+            .map(|(_, diagnostic)| diagnostic)
+            .collect()
+    }
+
+    /// Orders the diagnostics by file, then errors before warnings (or
+    /// after, with `warnings_first`), then where they start. Of two with
+    /// the same error or warning code at the same span, as error recovery
+    /// can report, only the first is kept.
+    pub fn sort_dedup(&mut self) {
+        let warnings_first = self.warnings_first;
+        self.diagnostics.sort_by(|(a_file, a), (b_file, b)| {
+            let key = |diagnostic: &Diagnostic| {
+                (
+                    diagnostic.is_error() == warnings_first,
+                    diagnostic.span().start,
+                )
+            };
+            (a_file, key(a)).cmp(&(b_file, key(b)))
+        });
+        let mut kept: Vec<(String, Diagnostic)> = Vec::with_capacity(self.diagnostics.len());
+        for (file, diagnostic) in std::mem::take(&mut self.diagnostics) {
+            // duplicates sort next to each other, among those starting at
+            // the same place
+            let duplicate = kept
+                .iter()
+                .rev()
+                .take_while(|(kept_file, kept)| {
+                    *kept_file == file && kept.span().start == diagnostic.span().start
+                })
+                .any(|(_, kept)| same(kept, &diagnostic));
+            if !duplicate {
+                kept.push((file, diagnostic));
+            }
+        }
+        self.diagnostics = kept;
+    }
+
+    /// Renders the diagnostics in their current order, each error with the
+    /// lines of its file's source in `sources` it points at. Each line
+    /// starts with its file unless that is "". Past `limit` errors the rest
+    /// are counted instead.
+    pub fn render_all(&self, sources: &HashMap<&str, &str>, style: Style) -> String {
+        let mut out = String::new();
+        let mut errors = 0;
+        // the snippet of the file last rendered, as files come in runs
+        let mut snippet: Option<(&str, Option<Snippet>)> = None;
+        for (file, diagnostic) in &self.diagnostics {
+            let prefix = if file.is_empty() {
+                String::new()
+            } else {
+                format!("{file}: ")
+            };
+            match diagnostic {
+                Diagnostic::Error(err) => {
+                    errors += 1;
+                    if errors > self.limit {
+                        continue;
+                    }
+                    if snippet.as_ref().is_none_or(|(name, _)| name != file) {
+                        let source = sources
+                            .get(file.as_str())
+                            .map(|source| Snippet::new(source));
+                        snippet = Some((file, source));
+                    }
+                    let shown = snippet.as_ref().and_then(|(_, snippet)| snippet.as_ref());
+                    out.push_str(&prefix);
+                    out.push_str(&err.render_styled(shown, style));
+                }
+                Diagnostic::Warning(warning) => {
+                    out.push_str(&format!("{prefix}{}\n", warning.render(style)));
+                }
+            }
+        }
+        if errors > self.limit {
+            let more = errors - self.limit;
+            let noun = if more == 1 { "error" } else { "errors" };
+            out.push_str(&format!("and {more} more {noun}\n"));
+        }
+        out
+    }
+
+    /// The diagnostics as a JSON array, one object per diagnostic in their
+    /// current order. Unlike `render_all` it leaves none out.
+    pub fn to_json(&self) -> String {
+        let objects: Vec<_> = self
+            .diagnostics
+            .iter()
+            .map(|(file, diagnostic)| {
+                let span = diagnostic.span();
+                let (severity, code, message) = match diagnostic {
+                    Diagnostic::Error(err) => ("error", "null".to_string(), err.error.to_string()),
+                    Diagnostic::Warning(warning) => {
+                        ("warning", json_string(warning.code()), warning.message.clone())
+                    }
+                };
+                format!(
+                    "{{\"file\":{},\"severity\":\"{severity}\",\"code\":{code},\"message\":{},\"line\":{},\"column\":{}}}",
+                    json_string(file),
+                    json_string(&message),
+                    span.line,
+                    span.start.saturating_sub(span.line_start)
+                )
+            })
+            .collect();
+        format!("[{}]", objects.join(","))
+    }
+}
+
+// Whether `a` and `b` report the same thing at the same place.
+fn same(a: &Diagnostic, b: &Diagnostic) -> bool {
+    a.span() == b.span()
+        && match (a, b) {
+            (Diagnostic::Error(a), Diagnostic::Error(b)) => a.error == b.error,
+            (Diagnostic::Warning(a), Diagnostic::Warning(b)) => a.code() == b.code(),
+            _ => false,
+        }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::DiagnosticSet;
+    use crate::{Diagnostic, Error, ErrorInfo, Span, Style, Warning};
+
+    fn error(message: &str, line: usize, start: usize) -> Diagnostic {
+        let span = Span::new(line, 0, start, start + 1);
+        Diagnostic::Error(ErrorInfo::new_with_span(
+            Error::Name(message.to_string()),
+            span,
+        ))
+    }
+
+    fn warning(line: usize, start: usize) -> Diagnostic {
+        let span = Span::new(line, 0, start, start + 1);
+        Diagnostic::Warning(Warning::new("shadowing", "shadows".to_string(), span))
+    }
+
+    #[test]
+    fn test_sort_dedup() {
+        let mut set = DiagnosticSet::new();
+        set.push("b.lang", error("y", 1, 0));
+        set.push("a.lang", warning(1, 0));
+        set.push("a.lang", error("x", 2, 4));
+        set.push("a.lang", error("w", 1, 2));
+        set.push("a.lang", error("x", 2, 4));
+        set.push("a.lang", error("z", 2, 4));
+        set.sort_dedup();
+        let sources = HashMap::new();
+        assert_eq!(
+            set.render_all(&sources, Style::PLAIN),
+            "a.lang: NameError: undefined variable \"w\", line 1, pos 2\n\
+             a.lang: NameError: undefined variable \"x\", line 2, pos 4\n\
+             a.lang: NameError: undefined variable \"z\", line 2, pos 4\n\
+             a.lang: warning: shadows, line 1, pos 0\n\
+             b.lang: NameError: undefined variable \"y\", line 1, pos 0\n"
+        );
+
+        set.warnings_first = true;
+        set.sort_dedup();
+        let first = set.iter().next().unwrap();
+        assert!(!first.is_error());
+    }
+
+    #[test]
+    fn test_limit() {
+        let mut set = DiagnosticSet::new();
+        for start in 0..25 {
+            set.push("", error("x", 1, start));
+        }
+        set.push("", warning(1, 30));
+        set.sort_dedup();
+        let source = "x".repeat(40);
+        let sources = HashMap::from([("", source.as_str())]);
+        let rendered = set.render_all(&sources, Style::PLAIN);
+        assert_eq!(rendered.matches("NameError").count(), 20);
+        assert!(rendered.ends_with("warning: shadows, line 1, pos 30\nand 5 more errors\n"));
+
+        // JSON shows them all
+        let json = set.to_json();
+        assert_eq!(json.matches("\"severity\":\"error\"").count(), 25);
+        assert!(json.starts_with(
+            "[{\"file\":\"\",\"severity\":\"error\",\"code\":null,\
+             \"message\":\"NameError: undefined variable \\\"x\\\"\",\"line\":1,\"column\":0},"
+        ));
+        assert!(json.ends_with(
+            "\"code\":\"shadowing\",\"message\":\"shadows\",\"line\":1,\"column\":30}]"
+        ));
+    }
+}
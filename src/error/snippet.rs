@@ -0,0 +1,367 @@
+use super::Style;
+use crate::Span;
+
+/// Renders the source lines a span covers with the span underlined, for
+/// showing under a diagnostic. Spans count chars, so columns here do too.
+pub struct Snippet {
+    chars: Vec<char>,
+    /// Widest source line shown, in chars, ellipses included. Longer lines
+    /// are cut down to a window around the span.
+    pub width: usize,
+    /// Most source lines shown for a span covering several. Past that only
+    /// the first and last lines are shown.
+    pub max_lines: usize,
+}
+
+impl Snippet {
+    pub fn new(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            width: 120,
+            max_lines: 4,
+        }
+    }
+
+    pub fn render(&self, span: &Span) -> String {
+        self.render_styled(span, Style::PLAIN)
+    }
+
+    /// Like `render`, with the carets in `style`.
+    pub fn render_styled(&self, span: &Span, style: Style) -> String {
+        let len = self.chars.len();
+        let start = span.start.min(len);
+        let end = span.end.clamp(start, len);
+        let first = self.line_at(start);
+        let last = self.line_at(end.saturating_sub(1).max(start));
+        let last_line = span.line
+            + self.chars[first.0..last.0]
+                .iter()
+                .filter(|c| **c == '\n')
+                .count();
+        let gutter = last_line.to_string().len();
+        let blank = format!("{:gutter$} |", "");
+
+        let mut out = format!("{blank}\n");
+        if first == last {
+            let (text, offset, carets) = self.window(first, start, end - start);
+            push_line(&mut out, format!("{:>gutter$} | {text}", span.line));
+            let carets = style.caret(&"^".repeat(carets));
+            out.push_str(&format!("{blank} {:offset$}{carets}\n", ""));
+            return out;
+        }
+
+        let mut lines = vec![first];
+        while lines[lines.len() - 1] != last {
+            let next = lines[lines.len() - 1].1 + 1;
+            lines.push(self.line_at(next));
+        }
+        let count = lines.len();
+        for (i, line) in lines.into_iter().enumerate() {
+            if count > self.max_lines && i > 0 && i < count - 1 {
+                if i == 1 {
+                    out.push_str(&format!("{:gutter$} | |\n", "..."));
+                }
+                continue;
+            }
+            let (text, ..) = match i {
+                0 => self.window(line, start, line.1 - start),
+                _ if i == count - 1 => self.window(line, end - 1, 1),
+                _ => self.window(line, line.0, 0),
+            };
+            let marker = if i == 0 { '/' } else { '|' };
+            push_line(
+                &mut out,
+                format!("{:>gutter$} | {marker} {text}", span.line + i),
+            );
+        }
+        let (_, offset, _) = self.window(last, end - 1, 1);
+        let underline = style.caret(&format!("{}^", "_".repeat(offset + 1)));
+        out.push_str(&format!("{blank} |{underline}\n"));
+        out
+    }
+
+    /// Renders the lines `primary` and the `labels` are on, with `primary`
+    /// underlined by carets and each labeled span by dashes with its label
+    /// under it, or after it when it is the last on its line. A span over
+    /// several lines is only underlined on its first.
+    pub fn render_labels(&self, primary: &Span, labels: &[(String, Span)], style: Style) -> String {
+        let len = self.chars.len();
+        let mut marks: Vec<Mark> = std::iter::once(("", primary))
+            .chain(labels.iter().map(|(label, span)| (label.as_str(), span)))
+            .enumerate()
+            .map(|(i, (label, span))| {
+                let start = span.start.min(len);
+                let bounds = self.line_at(start);
+                Mark {
+                    line: span.line,
+                    bounds,
+                    start,
+                    end: span.end.clamp(start, bounds.1),
+                    label,
+                    primary: i == 0,
+                }
+            })
+            .collect();
+        marks.sort_by_key(|mark| mark.start);
+        let last_line = marks.iter().map(|mark| mark.line).max().unwrap_or(1);
+        let gutter = last_line.to_string().len();
+        let blank = format!("{:gutter$} |", "");
+
+        let mut out = format!("{blank}\n");
+        let mut previous: Option<usize> = None;
+        for group in marks.chunk_by(|a, b| a.bounds == b.bounds) {
+            let line = group[0].line;
+            if previous.is_some_and(|previous| line > previous + 1) {
+                out.push_str(&format!("{:gutter$} |\n", "..."));
+            }
+            previous = Some(line);
+            let first = group[0].start;
+            let last = group.iter().map(|mark| mark.end).max().unwrap_or(first);
+            let (text, offset, _) = self.window(group[0].bounds, first, last - first);
+            push_line(&mut out, format!("{line:>gutter$} | {text}"));
+
+            let width = text.chars().count();
+            let cols: Vec<usize> = group
+                .iter()
+                .map(|mark| (mark.start - first + offset).min(width))
+                .collect();
+            let mut underline = String::new();
+            let mut at = 0;
+            for (mark, col) in group.iter().zip(&cols) {
+                let col = (*col).max(at);
+                let dashes = (mark.end - mark.start).max(1);
+                let marker = if mark.primary { "^" } else { "-" };
+                underline.push_str(&" ".repeat(col - at));
+                underline.push_str(&style.caret(&marker.repeat(dashes)));
+                at = col + dashes;
+            }
+            let labeled: Vec<(usize, &str)> = group
+                .iter()
+                .zip(&cols)
+                .filter(|(mark, _)| !mark.label.is_empty())
+                .map(|(mark, col)| (*col, mark.label))
+                .collect();
+            let mut below = labeled.as_slice();
+            if let Some((_, label)) = labeled.last().filter(|(col, _)| Some(col) == cols.last()) {
+                underline.push_str(&format!(" {label}"));
+                below = &labeled[..labeled.len() - 1];
+            }
+            push_line(&mut out, format!("{blank} {underline}"));
+            if below.is_empty() {
+                continue;
+            }
+            // the rightmost label first, each hanging from its span
+            push_line(&mut out, format!("{blank} {}", connectors(below)));
+            for i in (0..below.len()).rev() {
+                let (col, label) = below[i];
+                let mut row = connectors(&below[..i]);
+                row.push_str(&" ".repeat(col.saturating_sub(row.chars().count())));
+                row.push_str(label);
+                push_line(&mut out, format!("{blank} {row}"));
+            }
+        }
+        out
+    }
+
+    // The bounds of the line holding the char at `at`, without its newline.
+    fn line_at(&self, at: usize) -> (usize, usize) {
+        let start = self.chars[..at]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map_or(0, |i| i + 1);
+        let end = self.chars[at..]
+            .iter()
+            .position(|c| *c == '\n')
+            .map_or(self.chars.len(), |i| at + i);
+        (start, end)
+    }
+
+    // The part of `line` shown for `len` chars at `at`, where the first of
+    // them lands in it, and how many of them are shown. A span at the very
+    // end of the line or the file still gets one caret.
+    fn window(
+        &self,
+        (start, end): (usize, usize),
+        at: usize,
+        len: usize,
+    ) -> (String, usize, usize) {
+        let col = at - start;
+        let line_len = end - start;
+        let width = self.width.max(8);
+        let (lo, hi) = if line_len <= width {
+            (0, line_len)
+        } else {
+            // room for the span's first chars with an ellipsis on both sides
+            let inner = width - 2;
+            let lo = (col + len.min(inner) / 2)
+                .saturating_sub(inner / 2)
+                .min(col);
+            if lo == 0 {
+                (0, width - 1)
+            } else if lo + inner >= line_len {
+                (line_len - (width - 1), line_len)
+            } else {
+                (lo, lo + inner)
+            }
+        };
+        let shown =
+            self.chars[start + lo..start + hi]
+                .iter()
+                .map(|c| if c.is_whitespace() { ' ' } else { *c });
+        let mut text = String::new();
+        if lo > 0 {
+            text.push('…');
+        }
+        text.extend(shown);
+        if hi < line_len {
+            text.push('…');
+        }
+        let offset = col - lo + usize::from(lo > 0);
+        let carets = (col + len).min(hi).saturating_sub(col).max(1);
+        (text, offset, carets)
+    }
+}
+
+// A span `render_labels` underlines, in chars.
+struct Mark<'a> {
+    line: usize,
+    bounds: (usize, usize),
+    start: usize,
+    end: usize,
+    label: &'a str,
+    primary: bool,
+}
+
+// A `|` at the column of each label, for those still to be written under
+// their spans.
+fn connectors(labels: &[(usize, &str)]) -> String {
+    let mut row = String::new();
+    for (col, _) in labels {
+        row.push_str(&" ".repeat(col.saturating_sub(row.chars().count())));
+        row.push('|');
+    }
+    row
+}
+
+fn push_line(out: &mut String, line: String) {
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod test {
+    use super::Snippet;
+    use crate::{Span, Style};
+
+    // A span over the first `needle` in `source`, counted in chars.
+    fn span(source: &str, needle: &str) -> Span {
+        let byte = source.find(needle).unwrap();
+        let start = source[..byte].chars().count();
+        let line = source[..byte].matches('\n').count() + 1;
+        let line_start = source[..byte]
+            .rfind('\n')
+            .map_or(0, |i| source[..=i].chars().count());
+        Span::new(line, line_start, start, start + needle.chars().count())
+    }
+
+    #[test]
+    fn test_single_line() {
+        let source = "let a = 1;\nprint a + b;\n";
+        assert_eq!(
+            Snippet::new(source).render(&span(source, "b")),
+            "  |\n2 | print a + b;\n  |           ^\n"
+        );
+    }
+
+    #[test]
+    fn test_long_line() {
+        let source = format!(
+            "let a = {}missing{};",
+            "1 + ".repeat(5000),
+            " + é".repeat(5000)
+        );
+        let mut snippet = Snippet::new(&source);
+        snippet.width = 30;
+        let rendered = snippet.render(&span(&source, "missing"));
+        assert_eq!(
+            rendered,
+            "  |\n1 | … + 1 + 1 + missing + é + é +…\n  |             ^^^^^^^\n"
+        );
+        for line in rendered.lines() {
+            assert!(line.chars().count() <= 30 + 4, "{line}");
+        }
+
+        // at the edges of the line, one ellipsis is enough
+        let source = format!("é{};", " + é".repeat(100));
+        let mut snippet = Snippet::new(&source);
+        snippet.width = 20;
+        assert_eq!(
+            snippet.render(&Span::new(1, 0, 0, 1)),
+            "  |\n1 | é + é + é + é + é +…\n  | ^\n"
+        );
+        let end = source.chars().count();
+        assert_eq!(
+            snippet.render(&Span::new(1, 0, end - 1, end)),
+            "  |\n1 | … é + é + é + é + é;\n  |                    ^\n"
+        );
+    }
+
+    #[test]
+    fn test_multi_line() {
+        let source = "let total = add(\n  1,\n  2\n);\nprint total;";
+        let call = span(source, "add(\n  1,\n  2\n)");
+        let snippet = Snippet::new(source);
+        assert_eq!(
+            snippet.render(&call),
+            "  |\n1 | / let total = add(\n2 | |   1,\n3 | |   2\n4 | | );\n  | |_^\n"
+        );
+
+        let mut snippet = Snippet::new(source);
+        snippet.max_lines = 2;
+        assert_eq!(
+            snippet.render(&call),
+            "  |\n1 | / let total = add(\n... | |\n4 | | );\n  | |_^\n"
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let source = "let a = f(x) * g(y) + h;\nprint a;";
+        let labels = [
+            ("one".to_string(), span(source, "f(x)")),
+            ("two".to_string(), span(source, "g(y)")),
+            ("three".to_string(), span(source, "h")),
+        ];
+        let snippet = Snippet::new(source);
+        assert_eq!(
+            snippet.render_labels(&span(source, "*"), &labels, Style::PLAIN),
+            "  |\n1 | let a = f(x) * g(y) + h;\n  |         ---- ^ ----   - three\n  |         |      |\n  |         |      two\n  |         one\n"
+        );
+        // without a label of its own the last span takes none
+        let labels = [("left".to_string(), span(source, "a"))];
+        assert_eq!(
+            snippet.render_labels(&span(source, "print"), &labels, Style::PLAIN),
+            "  |\n1 | let a = f(x) * g(y) + h;\n  |     - left\n2 | print a;\n  | ^^^^^\n"
+        );
+    }
+
+    #[test]
+    fn test_file_edges() {
+        let source = "x;\n\nlet y = 2;\n";
+        let snippet = Snippet::new(source);
+        assert_eq!(
+            snippet.render(&Span::new(1, 0, 0, 1)),
+            "  |\n1 | x;\n  | ^\n"
+        );
+        // end of file, past the last char
+        let end = source.chars().count();
+        assert_eq!(
+            snippet.render(&Span::new(4, end, end, end)),
+            "  |\n4 |\n  | ^\n"
+        );
+        assert_eq!(
+            Snippet::new("").render(&Span::new(1, 0, 0, 0)),
+            "  |\n1 |\n  | ^\n"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+/// Whether diagnostics are colored, as `--color=` picks it.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ColorChoice {
+    /// Colored on a terminal, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+    /// Colored everywhere, e.g. for a pager that understands ANSI codes.
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    /// The style for a sink, decided once from whether the sink is a
+    /// terminal and whether `NO_COLOR` is set.
+    pub fn style(self, is_tty: bool, no_color: bool) -> Style {
+        let color = match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_tty && !no_color,
+        };
+        Style { color }
+    }
+}
+
+/// How a rendered diagnostic is dressed: plain text, or with ANSI colors.
+/// Plain is the default, so anything that captures output gets text it can
+/// compare.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Style {
+    color: bool,
+}
+
+impl Style {
+    pub const PLAIN: Style = Style { color: false };
+    pub const COLORED: Style = Style { color: true };
+
+    pub fn error(self, text: &str) -> String {
+        self.paint("1;31", text)
+    }
+
+    pub fn warning(self, text: &str) -> String {
+        self.paint("1;33", text)
+    }
+
+    /// A line and position in a file.
+    pub fn location(self, text: &str) -> String {
+        self.paint("36", text)
+    }
+
+    /// The carets under a span in a snippet.
+    pub fn caret(self, text: &str) -> String {
+        self.paint("1", text)
+    }
+
+    fn paint(self, code: &str, text: &str) -> String {
+        if self.color && !text.is_empty() {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ColorChoice, Style};
+    use crate::{Error, ErrorInfo, Snippet, Span, Warning};
+
+    #[test]
+    fn test_detection() {
+        for (choice, is_tty, no_color, expected) in [
+            (ColorChoice::Auto, true, false, Style::COLORED),
+            (ColorChoice::Auto, false, false, Style::PLAIN),
+            (ColorChoice::Auto, true, true, Style::PLAIN),
+            (ColorChoice::Always, false, false, Style::COLORED),
+            (ColorChoice::Always, true, true, Style::COLORED),
+            (ColorChoice::Never, true, false, Style::PLAIN),
+        ] {
+            assert_eq!(
+                choice.style(is_tty, no_color),
+                expected,
+                "{choice:?}, tty: {is_tty}, NO_COLOR: {no_color}"
+            );
+        }
+        assert_eq!(ColorChoice::parse("always"), Some(ColorChoice::Always));
+        assert_eq!(ColorChoice::parse("sometimes"), None);
+        assert_eq!(Style::default(), Style::PLAIN);
+    }
+
+    #[test]
+    fn test_rendering() {
+        let source = "let a = 1;\nprint a + b;\n";
+        let err = ErrorInfo::new_with_span(Error::Name("b".to_string()), Span::new(2, 11, 21, 22));
+        let snippet = Snippet::new(source);
+        assert_eq!(
+            err.render(Some(&snippet)),
+            "NameError: undefined variable \"b\", line 2, pos 10\n  \
+             |\n2 | print a + b;\n  |           ^\n"
+        );
+        assert_eq!(
+            err.render_styled(Some(&snippet), Style::COLORED),
+            "\x1b[1;31mNameError: undefined variable \"b\"\x1b[0m, \
+             \x1b[36mline 2, pos 10\x1b[0m\n  \
+             |\n2 | print a + b;\n  |           \x1b[1m^\x1b[0m\n"
+        );
+
+        let warning = Warning::new(
+            "shadowing",
+            "unused variable \"a\"".to_string(),
+            Span::new(1, 0, 4, 5),
+        );
+        assert_eq!(
+            warning.render(Style::PLAIN),
+            "warning: unused variable \"a\", line 1, pos 4"
+        );
+        assert_eq!(
+            warning.render(Style::COLORED),
+            "\x1b[1;33mwarning\x1b[0m: unused variable \"a\", \x1b[36mline 1, pos 4\x1b[0m"
+        );
+    }
+}
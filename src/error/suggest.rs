@@ -0,0 +1,66 @@
+/// Picks the candidate closest to `name` for a "did you mean" hint. A
+/// candidate counts when it is at most two edits away and the edits change
+/// no more than a third of `name`, so short names like `x` never get a
+/// suggestion. Ties go to the alphabetically first candidate.
+pub fn closest<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let len = name.chars().count();
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2 && distance * 3 <= len)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+// Edit distance over chars where swapping two neighbours counts as one
+// edit, as in `widht` for `width`.
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // d[i][j] is the distance between the first i chars of a and j of b
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{closest, distance};
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("count", "count"), 0);
+        assert_eq!(distance("cout", "count"), 1);
+        assert_eq!(distance("lenght", "length"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_closest() {
+        let names = ["count", "counter", "total", "x"];
+        assert_eq!(closest("cont", names), Some("count"));
+        assert_eq!(closest("countr", names), Some("count"));
+        assert_eq!(closest("totl", names), Some("total"));
+        assert_eq!(closest("y", names), None);
+        assert_eq!(closest("width", names), None);
+        assert_eq!(closest("count", names), None);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{Lexer, LexerMode, Span, TokenType, TriviaKind, Warning, WARNING_CODES};
+
+/// The warnings a source file turns off with pragma comments.
+///
+/// `# lang-ignore: code, ...` on a line of its own covers the statement
+/// that starts on the next line of code, all of its lines. At the end of a
+/// line it covers the statement that ends there, or just that line when
+/// none does. `# lang-ignore-file: code, ...` before any code covers the
+/// whole file.
+#[derive(Debug, Default)]
+pub struct Suppressions {
+    pragmas: Vec<Pragma>,
+    // what is wrong with the pragmas themselves
+    warnings: Vec<Warning>,
+}
+
+#[derive(Debug)]
+struct Pragma {
+    code: &'static str,
+    // the first and last line covered, or None for the whole file
+    lines: Option<(usize, usize)>,
+    span: Span,
+    used: bool,
+}
+
+impl Suppressions {
+    /// Finds the pragmas in `source`. `stmt_lines` are the lines of its
+    /// statements, from `Parser::stmt_lines`.
+    pub fn scan(source: &str, stmt_lines: &[(usize, usize)]) -> Self {
+        let mut suppressions = Self::default();
+        // most files have none, and lexing again is not free
+        if !source.contains("lang-ignore") {
+            return suppressions;
+        }
+        let mut lexer = Lexer::with_mode(source.to_string(), LexerMode::WithTrivia);
+        // the line of the last token, None before the first
+        let mut prev_line = None;
+        loop {
+            let token = lexer.next_token();
+            for trivia in &token.leading_trivia {
+                if let TriviaKind::Comment(text) = &trivia.kind {
+                    let lines = if prev_line == Some(trivia.span.line) {
+                        ending_on(stmt_lines, trivia.span.line)
+                    } else {
+                        starting_on(stmt_lines, token.span.line)
+                    };
+                    suppressions.pragma(text, &trivia.span, lines, prev_line.is_none());
+                }
+            }
+            if token.is(TokenType::Eof) {
+                break;
+            }
+            prev_line = Some(token.span.line);
+        }
+        suppressions
+    }
+
+    // Records the codes of the comment `text` if it is a pragma. `lines` is
+    // what a `lang-ignore` there covers; `at_top` is whether any code comes
+    // before it.
+    fn pragma(&mut self, text: &str, span: &Span, lines: (usize, usize), at_top: bool) {
+        let text = text.trim_start_matches('#').trim();
+        let (lines, codes) = if let Some(codes) = text.strip_prefix("lang-ignore-file:") {
+            if !at_top {
+                self.warnings.push(Warning::new(
+                    "misplaced-pragma",
+                    "lang-ignore-file only works before any code".to_string(),
+                    span.clone(),
+                ));
+                return;
+            }
+            (None, codes)
+        } else if let Some(codes) = text.strip_prefix("lang-ignore:") {
+            (Some(lines), codes)
+        } else {
+            return;
+        };
+        for code in codes
+            .split(',')
+            .map(str::trim)
+            .filter(|code| !code.is_empty())
+        {
+            match WARNING_CODES.iter().find(|known| **known == code) {
+                Some(code) => self.pragmas.push(Pragma {
+                    code,
+                    lines,
+                    span: span.clone(),
+                    used: false,
+                }),
+                None => self.warnings.push(Warning::new(
+                    "unknown-code",
+                    format!("unknown warning code '{code}'"),
+                    span.clone(),
+                )),
+            }
+        }
+    }
+
+    // Whether a pragma covers `warning`, marking each one that does as used.
+    fn covers(&mut self, warning: &Warning) -> bool {
+        let line = warning.line();
+        let mut covered = false;
+        for pragma in &mut self.pragmas {
+            let in_lines = pragma
+                .lines
+                .is_none_or(|(first, last)| first <= line && line <= last);
+            if pragma.code == warning.code() && in_lines {
+                pragma.used = true;
+                covered = true;
+            }
+        }
+        covered
+    }
+
+    /// Drops the `warnings` a pragma covers and adds those about the
+    /// pragmas. Only a `complete` run, one that got as far as every warning
+    /// it can give, warns about pragmas that covered nothing.
+    pub fn apply(mut self, warnings: Vec<Warning>, complete: bool) -> Vec<Warning> {
+        let mut kept = std::mem::take(&mut self.warnings);
+        kept.extend(warnings);
+        kept.retain(|warning| !self.covers(warning));
+        if complete {
+            let unused: Vec<_> = self
+                .pragmas
+                .iter()
+                // such a pragma covers nothing until this very check
+                .filter(|pragma| !pragma.used && pragma.code != "unused-suppression")
+                .map(|pragma| {
+                    Warning::new(
+                        "unused-suppression",
+                        format!("'{}' is not reported here", pragma.code),
+                        pragma.span.clone(),
+                    )
+                })
+                .collect();
+            for warning in unused {
+                if !self.covers(&warning) {
+                    kept.push(warning);
+                }
+            }
+        }
+        kept
+    }
+}
+
+// The lines of the outermost statement starting on `line`, or just the line.
+fn starting_on(stmt_lines: &[(usize, usize)], line: usize) -> (usize, usize) {
+    stmt_lines
+        .iter()
+        .filter(|(first, _)| *first == line)
+        .max_by_key(|(_, last)| *last)
+        .copied()
+        .unwrap_or((line, line))
+}
+
+// The lines of the outermost statement ending on `line`, or just the line.
+fn ending_on(stmt_lines: &[(usize, usize)], line: usize) -> (usize, usize) {
+    stmt_lines
+        .iter()
+        .filter(|(_, last)| *last == line)
+        .min_by_key(|(first, _)| *first)
+        .copied()
+        .unwrap_or((line, line))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Suppressions;
+    use crate::{Lexer, Parser, Resolver};
+
+    // What resolving `input` warns about, as code and line, once its
+    // pragmas have been applied.
+    fn warnings(input: &str) -> Vec<(&'static str, usize)> {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        let mut resolver = Resolver::new();
+        resolver.resolve(&program).unwrap();
+        let suppressions = Suppressions::scan(input, parser.stmt_lines());
+        suppressions
+            .apply(resolver.warnings().to_vec(), true)
+            .iter()
+            .map(|warning| (warning.code(), warning.line()))
+            .collect()
+    }
+
+    #[test]
+    fn test_line_pragmas() {
+        let input = "let trace = 1;\n\
+                     # lang-ignore: shadowing\n\
+                     let sleep = 2;\n\
+                     let freeze = 3; # lang-ignore: shadowing\n\
+                     let fields = 4;";
+        assert_eq!(warnings(input), [("shadowing", 1), ("shadowing", 5)]);
+
+        // a pragma covers every line of its statement
+        let input = "# lang-ignore: empty-if-body, string-append-in-loop\n\
+                     while (true) {\n  let s = \"\";\n  s += \"a\";\n  if (s);\n}\n\
+                     if (1);";
+        assert_eq!(warnings(input), [("empty-if-body", 7)]);
+        let input = "while (true) {\n  if (1);\n} # lang-ignore: empty-if-body\n";
+        assert!(warnings(input).is_empty());
+    }
+
+    #[test]
+    fn test_file_pragmas() {
+        let input = "# lang-ignore-file: shadowing\n\nlet trace = 1;\nfn f() {}\nlet sleep = 2;";
+        assert!(warnings(input).is_empty());
+
+        // only before any code
+        let input = "let x = 1;\n# lang-ignore-file: shadowing\nlet trace = 1;";
+        assert_eq!(warnings(input), [("misplaced-pragma", 2), ("shadowing", 3)]);
+    }
+
+    #[test]
+    fn test_pragma_warnings() {
+        let input = "# lang-ignore: shadowing, unused-variable\nlet trace = 1;";
+        assert_eq!(warnings(input), [("unknown-code", 1)]);
+
+        let input = "# lang-ignore: empty-if-body\nlet trace = 1;";
+        assert_eq!(
+            warnings(input),
+            [("shadowing", 2), ("unused-suppression", 1)]
+        );
+        let input = "# lang-ignore-file: unused-suppression\n# lang-ignore: shadowing\nlet x = 1;";
+        assert!(warnings(input).is_empty());
+
+        // an incomplete run may not have reached what a pragma covers
+        let input = "let x = 1;\n# lang-ignore: shadowing\nlet y = 2;";
+        let suppressions = Suppressions::scan(input, &[(1, 1), (3, 3)]);
+        assert!(suppressions.apply(Vec::new(), false).is_empty());
+    }
+}
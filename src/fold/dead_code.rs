@@ -0,0 +1,246 @@
+use crate::ast::Program;
+use crate::{Expr, LiteralType, Span, Stmt, Warning};
+
+/// Removes code that can never run, with a warning for every removal:
+///
+/// - statements after a `return`, `break` or `continue` in the same block,
+/// - the untaken branch of an `if` whose condition is a literal,
+/// - `while` loops whose condition is a falsy literal.
+///
+/// Only literal conditions (possibly parenthesized) count, so run
+/// `fold_constants` first. A condition with anything else in it, such as
+/// `f() && false`, is kept along with both branches, since evaluating it may
+/// have side effects.
+pub fn eliminate_dead_code(mut program: Program) -> (Program, Vec<Warning>) {
+    let mut warnings = Vec::new();
+    prune_list(&mut program.stmts, &mut warnings);
+    (program, warnings)
+}
+
+fn prune_list(stmts: &mut Vec<Stmt>, warnings: &mut Vec<Warning>) {
+    let mut kept = Vec::with_capacity(stmts.len());
+    let mut rest = std::mem::take(stmts).into_iter();
+    while let Some(stmt) = rest.next() {
+        let Some(stmt) = prune(stmt, warnings) else {
+            continue;
+        };
+        let exit = exit(&stmt);
+        kept.push(stmt);
+        if let Some((keyword, span)) = exit {
+            if rest.len() > 0 {
+                warnings.push(Warning::new(
+                    "unreachable-code",
+                    format!("unreachable code after \"{keyword}\" was removed"),
+                    span,
+                ));
+            }
+            break;
+        }
+    }
+    *stmts = kept;
+}
+
+// Prunes `stmt`, returning None when nothing of it is left.
+fn prune(mut stmt: Stmt, warnings: &mut Vec<Warning>) -> Option<Stmt> {
+    match &mut stmt {
+        Stmt::Block { stmts, .. } | Stmt::Function { body: stmts, .. } => {
+            prune_list(stmts, warnings);
+        }
+        Stmt::Class { methods, .. } => {
+            *methods = std::mem::take(methods)
+                .into_iter()
+                .filter_map(|method| prune(method, warnings))
+                .collect();
+        }
+        Stmt::If {
+            condition,
+            truthy,
+            falsy,
+            span,
+            ..
+        } => {
+            let pruned = prune_or_empty(truthy.take(), warnings);
+            let pruned_falsy = falsy.take().map(|falsy| prune_or_empty(*falsy, warnings));
+            match literal_condition(condition) {
+                Some(true) => {
+                    if pruned_falsy.is_some() {
+                        let message = "condition is always true, the else branch was removed";
+                        warnings.push(Warning::new(
+                            "constant-condition",
+                            message.to_string(),
+                            span.clone(),
+                        ));
+                    }
+                    return Some(pruned);
+                }
+                Some(false) => {
+                    let message = "condition is always false, the branch was removed";
+                    warnings.push(Warning::new(
+                        "constant-condition",
+                        message.to_string(),
+                        span.clone(),
+                    ));
+                    return pruned_falsy;
+                }
+                None => {
+                    **truthy = pruned;
+                    *falsy = pruned_falsy.map(Box::new);
+                }
+            }
+        }
+        Stmt::While {
+            condition, span, ..
+        } if literal_condition(condition) == Some(false) => {
+            let message = "condition is always false, the loop was removed";
+            warnings.push(Warning::new(
+                "constant-condition",
+                message.to_string(),
+                span.clone(),
+            ));
+            return None;
+        }
+        Stmt::While { body, .. }
+        | Stmt::For { body, .. }
+        | Stmt::ForRange { body, .. }
+        | Stmt::Defer { body, .. } => {
+            **body = prune_or_empty(body.take(), warnings);
+        }
+        _ => {}
+    }
+    Some(stmt)
+}
+
+// For places that need a statement, such as a loop body: a statement pruned
+// away entirely becomes an empty block that keeps its id.
+fn prune_or_empty(stmt: Stmt, warnings: &mut Vec<Warning>) -> Stmt {
+    let id = stmt.id();
+    prune(stmt, warnings).unwrap_or(Stmt::Block {
+        id,
+        stmts: Vec::new(),
+    })
+}
+
+// Statements after which nothing else in the block runs.
+fn exit(stmt: &Stmt) -> Option<(&'static str, Span)> {
+    match stmt {
+        Stmt::Return { span, .. } => Some(("return", span.clone())),
+        Stmt::Break { span, .. } => Some(("break", span.clone())),
+        Stmt::Continue { span, .. } => Some(("continue", span.clone())),
+        _ => None,
+    }
+}
+
+// The truthiness of a condition that is a literal.
+fn literal_condition(condition: &Expr) -> Option<bool> {
+    match condition {
+        Expr::Grouping { expr, .. } => literal_condition(expr),
+        Expr::Literal { value, .. } => Some(match value {
+            LiteralType::Nil => false,
+            LiteralType::Boolean(b) => *b,
+            LiteralType::Number(n, _) => *n != 0.0,
+            LiteralType::String(s) => !s.is_empty(),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::eliminate_dead_code;
+    use crate::{Interpretor, Lexer, Parser, Program, Stmt};
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap()
+    }
+
+    fn optimize(input: &str) -> (String, Vec<String>) {
+        let (program, warnings) = eliminate_dead_code(parse(input));
+        let warnings = warnings
+            .iter()
+            .map(|warning| format!("{} (line {})", warning.message, warning.line()))
+            .collect();
+        (program.to_string(), warnings)
+    }
+
+    #[test]
+    fn test_unreachable_after_return() {
+        let input = "fn f() {\n return 1;\n print 2;\n print 3;\n}";
+        let (program, warnings) = eliminate_dead_code(parse(input));
+        let Stmt::Function { body, .. } = &program.stmts[0] else {
+            panic!("expected a function");
+        };
+        assert_eq!(body.len(), 1);
+        let warnings: Vec<_> = warnings
+            .iter()
+            .map(|w| (w.message.as_str(), w.line()))
+            .collect();
+        assert_eq!(
+            warnings,
+            [("unreachable code after \"return\" was removed", 2)]
+        );
+        assert_eq!(
+            optimize("fn f() { if (x) { return 1; } print 2; }").1,
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_literal_conditions() {
+        let (program, warnings) = optimize("if (true) print 1; else print 2;");
+        assert_eq!(program, "((print 1))");
+        assert_eq!(
+            warnings,
+            ["condition is always true, the else branch was removed (line 1)"]
+        );
+
+        let (program, warnings) = optimize("if ((nil)) print 1;\nwhile (false) print 2;\nprint 3;");
+        assert_eq!(program, "((print 3))");
+        assert_eq!(
+            warnings,
+            [
+                "condition is always false, the branch was removed (line 1)",
+                "condition is always false, the loop was removed (line 2)",
+            ]
+        );
+
+        let (program, _) = optimize("while (x) if (false) print 1;");
+        assert_eq!(program, "((while x (block)))");
+    }
+
+    #[test]
+    fn test_conditions_with_side_effects_are_kept() {
+        let input = "if (f() && false) print 1; while (g() || false) print 2;";
+        let (program, warnings) = optimize(input);
+        assert_eq!(program, parse(input).to_string());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_optimized_output_is_unchanged() {
+        let scripts = [
+            "fn f(n) { if (n > 1) { return n; print \"never\"; } return 0; } print f(3); print f(0);",
+            "let i = 0; while (i < 3) { if (false) { print \"no\"; } else { print i; } i = i + 1; }",
+            "fn noisy() { print \"called\"; return true; } if (noisy() && false) print 1; else print 2; while (false) print 3;",
+            "class A { init() { this.x = 1; return; this.x = 2; } } print A().x;",
+            "if (\"\") print \"empty\"; else print \"falsy\"; while (0) print \"loop\";",
+        ];
+        let run = |program: &Program| {
+            let mut interpretor = Interpretor::buffered();
+            interpretor.run(program).unwrap();
+            interpretor.take_output()
+        };
+        for script in scripts {
+            let program = parse(script);
+            let (optimized, warnings) = eliminate_dead_code(program.clone());
+            assert!(!warnings.is_empty(), "{script}");
+            // Display leaves out function bodies, Debug shows the whole tree
+            assert!(
+                format!("{optimized:?}").len() < format!("{program:?}").len(),
+                "{script}"
+            );
+            assert_eq!(run(&optimized), run(&program), "{script}");
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::ast::Program;
+use crate::{Expr, LiteralType, Stmt, TokenType};
+
+mod dead_code;
+pub use dead_code::eliminate_dead_code;
+
+/// Folds expressions whose value is known before the program runs.
+///
+/// Only `+` between two string literals is folded, so `"a" + "b" + name`
+/// becomes `"ab" + name`. Mixed operands are left alone: `"a" + 1` is a
+/// runtime error and must stay one. The folded literal takes the id of the
+/// `+` node it replaces.
+pub fn fold_constants(program: &mut Program) {
+    program.stmts.iter_mut().for_each(fold_stmt);
+}
+
+fn fold_stmt(stmt: &mut Stmt) {
+    match stmt {
+        Stmt::Expr { expr, .. } | Stmt::Destructure { value: expr, .. } => fold_expr(expr),
+        Stmt::Print { exprs, .. } => exprs.iter_mut().for_each(fold_expr),
+        Stmt::Let { value, .. } | Stmt::Return { value, .. } => {
+            if let Some(value) = value {
+                fold_expr(value);
+            }
+        }
+        Stmt::Block { stmts, .. } => stmts.iter_mut().for_each(fold_stmt),
+        Stmt::Function {
+            requires,
+            ensures,
+            body,
+            ..
+        } => {
+            requires.iter_mut().chain(ensures).for_each(fold_expr);
+            body.iter_mut().for_each(fold_stmt);
+        }
+        Stmt::Class {
+            super_class,
+            mixins,
+            methods,
+            fields,
+            ..
+        } => {
+            super_class.iter_mut().chain(mixins).for_each(fold_expr);
+            methods.iter_mut().for_each(fold_stmt);
+            fields
+                .iter_mut()
+                .for_each(|field| fold_expr(&mut field.value));
+        }
+        Stmt::If {
+            condition,
+            truthy,
+            falsy,
+            ..
+        } => {
+            fold_expr(condition);
+            fold_stmt(truthy);
+            if let Some(falsy) = falsy {
+                fold_stmt(falsy);
+            }
+        }
+        Stmt::While {
+            condition, body, ..
+        } => {
+            fold_expr(condition);
+            fold_stmt(body);
+        }
+        Stmt::For {
+            initializer,
+            condition,
+            increment,
+            body,
+            ..
+        } => {
+            if let Some(initializer) = initializer {
+                fold_stmt(initializer);
+            }
+            condition.iter_mut().chain(increment).for_each(fold_expr);
+            fold_stmt(body);
+        }
+        Stmt::ForRange {
+            initializer,
+            end,
+            step,
+            body,
+            ..
+        } => {
+            fold_stmt(initializer);
+            std::iter::once(end).chain(step).for_each(fold_expr);
+            fold_stmt(body);
+        }
+        Stmt::Defer { body, .. } => fold_stmt(body),
+        Stmt::Break { .. }
+        | Stmt::Continue { .. }
+        | Stmt::Import { .. }
+        | Stmt::Empty { .. }
+        | Stmt::Error { .. } => {}
+    }
+}
+
+fn fold_expr(expr: &mut Expr) {
+    if let Expr::Class {
+        methods, fields, ..
+    } = expr
+    {
+        methods.iter_mut().for_each(fold_stmt);
+        fields
+            .iter_mut()
+            .for_each(|field| fold_expr(&mut field.value));
+    }
+    expr.children_mut().into_iter().for_each(fold_expr);
+
+    if let Expr::Binary {
+        id,
+        left,
+        op,
+        right,
+    } = expr
+    {
+        if let (
+            TokenType::Plus,
+            Expr::Literal {
+                value: LiteralType::String(l),
+                ..
+            },
+            Expr::Literal {
+                value: LiteralType::String(r),
+                ..
+            },
+        ) = (&op.token, left.as_ref(), right.as_ref())
+        {
+            *expr = Expr::Literal {
+                id: *id,
+                value: LiteralType::String(format!("{l}{r}")),
+                span: left.extent().merge(&right.extent()),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::fold_constants;
+    use crate::{Interpretor, Lexer, Parser, Program};
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_fold_string_prefix() {
+        let mut program =
+            parse("print \"a\" + \"b\" + name; print name + \"a\" + \"b\"; print \"a\" + 1;");
+        fold_constants(&mut program);
+        assert_eq!(
+            program.to_string(),
+            "((print (+ \"ab\" name)) (print (+ (+ name \"a\") \"b\")) (print (+ \"a\" 1)))"
+        );
+    }
+
+    #[test]
+    fn test_folded_output_is_unchanged() {
+        let input = "
+        let name = \"x\";
+        fn greet() { return \"hello\" + \", \" + name + \"!\"; }
+        print greet();
+        print \"a\" + \"b\" + \"c\";
+        ";
+        let run = |program: Program| {
+            let mut interpretor = Interpretor::buffered();
+            interpretor.run(&program).unwrap();
+            interpretor.take_output()
+        };
+        let mut folded = parse(input);
+        fold_constants(&mut folded);
+        assert_ne!(folded, parse(input));
+        assert_eq!(run(folded), run(parse(input)));
+    }
+}
@@ -0,0 +1,178 @@
+use std::{cell::RefCell, fmt::Write, rc::Rc};
+
+use crate::object::{Function, NativeFn};
+use crate::{Error, ErrorInfo, Interpretor, Object, Span};
+
+// name, parameter names, how many of them are required, implementation
+type Method = (&'static str, &'static [&'static str], usize, NativeFn);
+
+const METHODS: [Method; 7] = [
+    ("append", &["value"], 1, append),
+    ("append_line", &["value"], 0, append_line),
+    ("fill", &["value", "n"], 2, fill),
+    ("reserve", &["n"], 1, reserve),
+    ("len", &[], 0, len),
+    ("clear", &[], 0, clear),
+    ("build", &[], 0, build),
+];
+
+/// The most bytes a builder holds, 1 GiB. Growing one past it, or past the
+/// interpreter's `max_heap_bytes`, fails with `Error::MemoryLimit` rather
+/// than aborting the process when the allocation does. Repeating a string
+/// with `*` is held to it too.
+pub const MAX_LEN: usize = 1 << 30;
+
+/// `StringBuilder()` is an empty builder. Appending to it grows one buffer
+/// in place, where `s = s + piece` copies all of `s` every time.
+/// `StringBuilder(capacity)` starts with room for that many bytes.
+pub fn new(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let mut buffer = String::new();
+    if !args[0].is_nil() {
+        let capacity = count("capacity", &args[0], span)?;
+        make_room(interpretor, &mut buffer, capacity, span)?;
+    }
+    Ok(Object::Builder(Rc::new(RefCell::new(buffer))))
+}
+
+/// The names `method` finds.
+pub fn method_names() -> impl Iterator<Item = &'static str> {
+    METHODS.into_iter().map(|method| method.0)
+}
+
+/// Looks up a method on a string builder, e.g. `sb.append`, bound to
+/// `builder`.
+pub fn method(builder: &Object, name: &str) -> Result<Object, Error> {
+    let Some((name, params, required, func)) = METHODS.into_iter().find(|method| method.0 == name)
+    else {
+        return Err(Error::Attribute(format!("undefined property \"{name}\"")));
+    };
+    Ok(Object::Function(Rc::new(Function::Method {
+        name,
+        receiver: builder.clone(),
+        params,
+        required,
+        variadic: false,
+        func: Box::new(func),
+    })))
+}
+
+fn receiver(args: &[Object]) -> &Rc<RefCell<String>> {
+    match &args[0] {
+        Object::Builder(buffer) => buffer,
+        _ => unreachable!("builder methods are only bound to builders"),
+    }
+}
+
+// A count of things for `name`, which must be a whole number from 0.
+fn count(name: &str, value: &Object, span: &Span) -> Result<usize, ErrorInfo> {
+    match *value {
+        Object::Number(n) if n >= 0.0 && n.fract() == 0.0 && n.is_finite() => Ok(n as usize),
+        _ => Err(ErrorInfo::new_with_span(
+            Error::Value(format!("{name} must be a whole number from 0, not {value}")),
+            span.clone(),
+        )),
+    }
+}
+
+// Makes room in `buffer` for `additional` more bytes, failing before
+// allocating anything if that would pass the limit.
+fn make_room(
+    interpretor: &Interpretor,
+    buffer: &mut String,
+    additional: usize,
+    span: &Span,
+) -> Result<(), ErrorInfo> {
+    let limit = interpretor
+        .max_heap_bytes
+        .map_or(MAX_LEN, |max| max.min(MAX_LEN));
+    let fits = buffer
+        .len()
+        .checked_add(additional)
+        .is_some_and(|len| len <= limit);
+    if fits && buffer.try_reserve(additional).is_ok() {
+        return Ok(());
+    }
+    let error = Error::MemoryLimit { limit };
+    Err(ErrorInfo::new_with_span(error, span.clone()))
+}
+
+/// `sb.append(value)` adds `value` as string concatenation would show it,
+/// returning the builder so calls can be chained.
+fn append(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let mut buffer = receiver(&args).borrow_mut();
+    let len = buffer.len();
+    write!(buffer, "{}", args[1]).expect("writing to a String never fails");
+    if buffer.len() > MAX_LEN {
+        buffer.truncate(len);
+        let error = Error::MemoryLimit { limit: MAX_LEN };
+        return Err(ErrorInfo::new_with_span(error, span.clone()));
+    }
+    drop(buffer);
+    Ok(args[0].clone())
+}
+
+/// `sb.fill(value, n)` appends `value` `n` times over, making room for all
+/// of them first.
+fn fill(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let n = count("n", &args[2], span)?;
+    let piece = args[1].to_string();
+    let mut buffer = receiver(&args).borrow_mut();
+    let additional = piece.len().saturating_mul(n);
+    make_room(interpretor, &mut buffer, additional, span)?;
+    // an empty piece adds nothing, however many times
+    for _ in 0..if piece.is_empty() { 0 } else { n } {
+        buffer.push_str(&piece);
+    }
+    drop(buffer);
+    Ok(args[0].clone())
+}
+
+/// `sb.reserve(n)` makes room for `n` more bytes, so appending that much
+/// never has to move what is there.
+fn reserve(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let n = count("n", &args[1], span)?;
+    make_room(interpretor, &mut receiver(&args).borrow_mut(), n, span)?;
+    Ok(args[0].clone())
+}
+
+/// `sb.append_line(value)` is `sb.append(value)` followed by a newline.
+/// Without a value only the newline is added.
+fn append_line(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let mut buffer = receiver(&args).borrow_mut();
+    if args[1] != Object::Nil {
+        write!(buffer, "{}", args[1]).expect("writing to a String never fails");
+    }
+    buffer.push('\n');
+    drop(buffer);
+    Ok(args[0].clone())
+}
+
+/// The number of chars appended so far, as indexing the built string counts
+/// them.
+fn len(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let count = receiver(&args).borrow().chars().count();
+    Ok(Object::Number(count as f64))
+}
+
+fn clear(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    receiver(&args).borrow_mut().clear();
+    Ok(Object::Nil)
+}
+
+/// The string appended so far. The builder keeps it, so appending can go on
+/// after `build`.
+fn build(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::String(receiver(&args).borrow().as_str().into()))
+}
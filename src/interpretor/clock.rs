@@ -0,0 +1,42 @@
+use std::{
+    cell::Cell,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Source of the current time for the timing natives. Hosts can swap in
+/// their own, e.g. a fake clock that makes timings reproducible in tests.
+pub trait Clock {
+    /// Time elapsed since the Unix epoch.
+    fn now(&self) -> Duration;
+
+    /// Waits for `duration` to pass, for the `sleep` native.
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
+
+/// A clock that starts at 0 and only moves when a script sleeps, which
+/// returns at once. Runs using it read the same times on every machine.
+#[derive(Default)]
+pub struct VirtualClock(Cell<Duration>);
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.0.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.0.set(self.0.get().saturating_add(duration));
+    }
+}
@@ -0,0 +1,350 @@
+use std::{fmt, ops::Range};
+
+use super::{builder, number, reflect, tuple};
+use crate::{Interpretor, Lexer, LexerMode, Object, TokenInfo, TokenType, TriviaKind, KEYWORDS};
+
+/// What a completed name is.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CompletionKind {
+    Variable,
+    Function,
+    Class,
+    Method,
+    Field,
+    Keyword,
+    /// A built-in function, native or from the prelude.
+    Native,
+}
+
+/// A name that fits where the cursor is, from `Interpretor::complete`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Completion {
+    pub text: String,
+    pub kind: CompletionKind,
+    /// The bytes of the line `text` replaces: the whole word the cursor is
+    /// in or just after, or an empty range at the cursor when there is none.
+    pub replace: Range<usize>,
+}
+
+impl Interpretor {
+    /// The names that could go at byte `cursor` of `line`, sorted, for tab
+    /// completion. Nothing is run to find them. In an expression they are
+    /// the globals and keywords starting with the word at the cursor. After
+    /// `receiver.` they are the members of its value: the methods of a
+    /// number, tuple or built-in, or an instance's public fields and
+    /// methods, when the receiver is a literal, a global or a chain of
+    /// fields starting at one. Inside a string or a comment there are none.
+    pub fn complete(&self, line: &str, cursor: usize) -> Vec<Completion> {
+        let Some(before) = line.get(..cursor) else {
+            return Vec::new();
+        };
+        // spans count chars, where `cursor` and `replace` count bytes
+        let cursor = before.chars().count();
+        let bytes: Vec<usize> = line
+            .char_indices()
+            .map(|(at, _)| at)
+            .chain([line.len()])
+            .collect();
+        let chars: Vec<char> = line.chars().collect();
+        let Some(tokens) = tokens(line, cursor) else {
+            return Vec::new();
+        };
+
+        // the token the cursor is in or just after
+        let at = tokens
+            .iter()
+            .rposition(|token| token.span.start < cursor && cursor <= token.span.end);
+        let (prefix, replace, previous) = match at.map(|at| (at, &tokens[at])) {
+            Some((_, token))
+                if matches!(
+                    token.token,
+                    TokenType::String(_) | TokenType::Number(_) | TokenType::Placeholder(_)
+                ) =>
+            {
+                return Vec::new();
+            }
+            Some((at, token)) if is_word(&chars[token.span.start..token.span.end]) => {
+                let prefix: String = chars[token.span.start..cursor].iter().collect();
+                let replace = bytes[token.span.start]..bytes[token.span.end];
+                (prefix, replace, at.checked_sub(1))
+            }
+            _ => {
+                let previous = tokens.iter().rposition(|token| token.span.end <= cursor);
+                (String::new(), bytes[cursor]..bytes[cursor], previous)
+            }
+        };
+
+        let mut candidates = match previous.map(|previous| (previous, &tokens[previous].token)) {
+            Some((dot, TokenType::Dot)) => match self.receiver(&tokens[..dot]) {
+                Some(value) => members(&value),
+                None => Vec::new(),
+            },
+            // what follows names something new
+            Some((_, TokenType::Let | TokenType::Const | TokenType::Function))
+            | Some((_, TokenType::Class | TokenType::Priv)) => Vec::new(),
+            _ => self.globals_and_keywords(),
+        };
+        candidates.retain(|(text, _)| text.starts_with(&prefix));
+        candidates.sort_by(|a, b| a.0.cmp(&b.0));
+        // a method overridden in a subclass is listed once
+        candidates.dedup_by(|a, b| a.0 == b.0);
+        candidates
+            .into_iter()
+            .map(|(text, kind)| Completion {
+                text,
+                kind,
+                replace: replace.clone(),
+            })
+            .collect()
+    }
+
+    // The value the expression ending `tokens` has, as far as it can be
+    // known without running anything.
+    fn receiver(&self, tokens: &[TokenInfo]) -> Option<Object> {
+        let (last, rest) = tokens.split_last()?;
+        match &last.token {
+            TokenType::Number(n) => Some(Object::Number(*n)),
+            TokenType::String(s) => Some(Object::String(s.as_str().into())),
+            TokenType::Identifier(name) => match rest.split_last() {
+                Some((dot, rest)) if dot.token == TokenType::Dot => {
+                    let Object::Instance(instance) = self.receiver(rest)? else {
+                        return None;
+                    };
+                    let instance = instance.borrow();
+                    if instance.class.private_owner(name).is_some() {
+                        return None;
+                    }
+                    instance.field(name)
+                }
+                _ => self.globals.borrow_mut().get(name).ok(),
+            },
+            _ => None,
+        }
+    }
+
+    fn globals_and_keywords(&self) -> Vec<(String, CompletionKind)> {
+        let globals = self.globals.borrow().bindings();
+        let mut candidates: Vec<_> = globals
+            .into_iter()
+            .map(|(name, value)| {
+                let kind = match &value {
+                    Object::Function(function) if function.is_native() => CompletionKind::Native,
+                    value if self.in_prelude(value) => CompletionKind::Native,
+                    Object::Function(_) => CompletionKind::Function,
+                    Object::Class(_) => CompletionKind::Class,
+                    _ => CompletionKind::Variable,
+                };
+                (name, kind)
+            })
+            .collect();
+        candidates.extend(
+            KEYWORDS
+                .iter()
+                .map(|keyword| (keyword.to_string(), CompletionKind::Keyword)),
+        );
+        candidates
+    }
+}
+
+// The tokens of `line`, or None when `cursor` is inside a comment or
+// something that does not lex, such as a string without its closing quote.
+fn tokens(line: &str, cursor: usize) -> Option<Vec<TokenInfo>> {
+    let inside = |start: usize, end: usize| start < cursor && cursor <= end;
+    let mut lexer = Lexer::with_mode(line.to_string(), LexerMode::WithTrivia);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        let in_comment = token.leading_trivia.iter().any(|trivia| {
+            matches!(trivia.kind, TriviaKind::Comment(_))
+                && inside(trivia.span.start, trivia.span.end)
+        });
+        if in_comment {
+            return None;
+        }
+        if token.is(TokenType::Eof) {
+            break;
+        }
+        tokens.push(token);
+    }
+    if lexer
+        .errors()
+        .iter()
+        .any(|err| inside(err.span().start, err.span().end))
+    {
+        return None;
+    }
+    Some(tokens)
+}
+
+// Whether `text` is a name or a keyword, which the cursor may be completing.
+fn is_word(text: &[char]) -> bool {
+    text.first()
+        .is_some_and(|first| first.is_alphabetic() || *first == '_')
+}
+
+// The public members of `value`.
+fn members(value: &Object) -> Vec<(String, CompletionKind)> {
+    let methods: Box<dyn Iterator<Item = &str>> = match value {
+        Object::Instance(instance) => {
+            let instance = instance.borrow();
+            let class = &instance.class;
+            let fields = instance
+                .field_names()
+                .map(|name| (name.to_string(), CompletionKind::Field));
+            let methods = class
+                .method_names()
+                .into_iter()
+                .map(|name| (name.to_string(), CompletionKind::Method));
+            return fields
+                .chain(methods)
+                .filter(|(name, _)| class.private_owner(name).is_none())
+                .collect();
+        }
+        Object::Function(_) | Object::Class(_) => Box::new(reflect::method_names()),
+        Object::Tuple(_) => Box::new(tuple::method_names()),
+        Object::Number(_) => Box::new(number::method_names()),
+        Object::Builder(_) => Box::new(builder::method_names()),
+        Object::Module(module) => {
+            let exports = module.exports().map(|(name, value)| {
+                let kind = match value {
+                    Object::Function(_) => CompletionKind::Function,
+                    Object::Class(_) => CompletionKind::Class,
+                    _ => CompletionKind::Variable,
+                };
+                (name.to_string(), kind)
+            });
+            return exports.collect();
+        }
+        Object::Boolean(_) | Object::String(_) | Object::Nil => return Vec::new(),
+    };
+    methods
+        .map(|name| (name.to_string(), CompletionKind::Method))
+        .collect()
+}
+
+impl fmt::Display for CompletionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self {
+            CompletionKind::Variable => "variable",
+            CompletionKind::Function => "function",
+            CompletionKind::Class => "class",
+            CompletionKind::Method => "method",
+            CompletionKind::Field => "field",
+            CompletionKind::Keyword => "keyword",
+            CompletionKind::Native => "native",
+        };
+        write!(f, "{kind}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Completion, CompletionKind};
+    use crate::{Interpretor, Lexer, Parser};
+
+    fn interpretor(input: &str) -> Interpretor {
+        let mut interpretor = Interpretor::buffered();
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        interpretor.run(&program).unwrap();
+        interpretor
+    }
+
+    // The names completed at the end of `line`.
+    fn names(interpretor: &Interpretor, line: &str) -> Vec<String> {
+        let completions = interpretor.complete(line, line.len());
+        completions.into_iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn test_globals() {
+        let interpretor =
+            interpretor("let counter = 0; fn count() {} class Counter {} let other = 1;");
+        let completions = interpretor.complete("print cou + 1", 9);
+        let kinds: Vec<_> = completions
+            .iter()
+            .map(|c| (c.text.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("count", CompletionKind::Function),
+                ("count_if", CompletionKind::Native),
+                ("counter", CompletionKind::Variable),
+            ]
+        );
+        // the whole word is replaced, wherever in it the cursor is
+        assert_eq!(completions[0].replace, 6..9);
+        assert_eq!(interpretor.complete("print counter", 8)[0].replace, 6..13);
+
+        assert_eq!(names(&interpretor, "whi"), ["while"]);
+        assert_eq!(
+            names(&interpretor, "let x = prin"),
+            ["print", "print_table"]
+        );
+        let completion = &interpretor.complete("x = le", 6)[0];
+        assert_eq!(completion.kind, CompletionKind::Keyword);
+        assert_eq!(
+            interpretor.complete("fre", 3)[0],
+            Completion {
+                text: "freeze".to_string(),
+                kind: CompletionKind::Native,
+                replace: 0..3,
+            }
+        );
+        // with no word at the cursor, everything fits
+        let all = interpretor.complete("print ", 6);
+        assert!(all.iter().any(|c| c.text == "other" && c.replace == (6..6)));
+        assert!(!all.iter().any(|c| c.text == "for.step"));
+        let globals = interpretor.globals.borrow().bindings();
+        assert!(!globals.iter().any(|(name, _)| name == "for.step"));
+        // a name being declared is new
+        assert!(names(&interpretor, "let cou").is_empty());
+        assert!(names(&interpretor, "fn cou").is_empty());
+    }
+
+    #[test]
+    fn test_members() {
+        let interpretor = interpretor(
+            "class Shape { area() {} priv secret() {} }
+             class Square < Shape { init(side) { this.side = side; this.pin = 1; } priv pin; scale() {} }
+             let square = Square(2);
+             let pair = (1, 2);
+             let holder = Shape();
+             holder.inner = square;",
+        );
+        assert_eq!(
+            names(&interpretor, "square."),
+            ["area", "init", "scale", "side"]
+        );
+        let completion = &interpretor.complete("square.si", 9)[0];
+        assert_eq!(
+            (completion.kind, completion.replace.clone()),
+            (CompletionKind::Field, 7..9)
+        );
+        assert_eq!(names(&interpretor, "holder.inner.sc"), ["scale"]);
+        assert_eq!(names(&interpretor, "pair."), ["len", "sorted"]);
+        assert_eq!(names(&interpretor, "3.fl"), ["floor"]);
+        assert_eq!(names(&interpretor, "Square.ar"), ["arity"]);
+        // nothing is known about what a call returns
+        assert!(names(&interpretor, "Square(1).").is_empty());
+        assert!(names(&interpretor, "missing.").is_empty());
+    }
+
+    #[test]
+    fn test_strings_and_comments() {
+        let interpretor = interpretor("let counter = 0;");
+        assert!(names(&interpretor, "print \"cou").is_empty());
+        assert!(interpretor.complete("print \"cou\";", 10).is_empty());
+        assert!(names(&interpretor, "1 + 1 # cou").is_empty());
+        // after a string is not inside it
+        assert_eq!(
+            names(&interpretor, "print \"a\" + coun"),
+            ["count_if", "counter"]
+        );
+        // nor is a cursor past the line or inside a character
+        assert!(interpretor.complete("cou", 4).is_empty());
+        assert!(interpretor.complete("é", 1).is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{Expr, Interpretor, Program, Stmt};
+
+/// How often each line of a program ran, from `Interpretor::coverage_report`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Coverage {
+    /// Every line that holds a statement, in order. A line with several
+    /// statements reports the count of the one that ran most.
+    pub lines: Vec<LineCount>,
+    /// Statements in the program, not counting blocks and generated code.
+    pub statements: usize,
+    /// How many of those ran at least once.
+    pub executed: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LineCount {
+    pub line: usize,
+    pub count: usize,
+}
+
+impl Coverage {
+    /// Share of statements that ran, from 0 to 100. A program without
+    /// statements is fully covered.
+    pub fn percent(&self) -> f64 {
+        if self.statements == 0 {
+            return 100.0;
+        }
+        self.executed as f64 * 100.0 / self.statements as f64
+    }
+
+    /// `source` with each line's count in the gutter, blank for lines
+    /// without statements, followed by a summary line.
+    pub fn annotate(&self, source: &str) -> String {
+        let counts: HashMap<_, _> = self.lines.iter().map(|l| (l.line, l.count)).collect();
+        let mut out = String::new();
+        for (i, text) in source.lines().enumerate() {
+            match counts.get(&(i + 1)) {
+                Some(count) => out.push_str(&format!("{count:>6} | {text}\n")),
+                None => out.push_str(&format!("{:>6} | {text}\n", "")),
+            }
+        }
+        out.push_str(&format!(
+            "coverage: {} of {} statements executed ({:.1}%)\n",
+            self.executed,
+            self.statements,
+            self.percent()
+        ));
+        out
+    }
+}
+
+impl Interpretor {
+    /// Starts counting how often each statement runs, for `coverage_report`.
+    /// Without it the interpreter does not count anything.
+    pub fn enable_coverage(&mut self) {
+        self.coverage.get_or_insert_with(HashMap::new);
+    }
+
+    pub(super) fn count(&mut self, stmt: &Stmt) {
+        if let Some(counts) = &mut self.coverage {
+            *counts.entry(stmt.id()).or_default() += 1;
+        }
+    }
+
+    /// How often the statements of `program` have run so far. Everything
+    /// reports 0 unless `enable_coverage` was called before running it.
+    pub fn coverage_report(&self, program: &Program) -> Coverage {
+        let mut stmts = Vec::new();
+        program
+            .stmts
+            .iter()
+            .for_each(|stmt| collect_stmt(stmt, &mut stmts));
+
+        let mut lines = BTreeMap::new();
+        let (mut statements, mut executed) = (0, 0);
+        for stmt in stmts {
+            let Some(span) = program.stmt_span(stmt.id()) else {
+                continue;
+            };
+            let count = self
+                .coverage
+                .as_ref()
+                .and_then(|counts| counts.get(&stmt.id()))
+                .copied()
+                .unwrap_or(0);
+            let line = lines.entry(span.line).or_insert(0);
+            *line = count.max(*line);
+            if !program.is_synthetic(stmt.id()) {
+                statements += 1;
+                executed += usize::from(count > 0);
+            }
+        }
+        Coverage {
+            lines: lines
+                .into_iter()
+                .map(|(line, count)| LineCount { line, count })
+                .collect(),
+            statements,
+            executed,
+        }
+    }
+}
+
+// Every statement that runs on its own, leaving out blocks, which only hold
+// other statements.
+fn collect_stmt<'a>(stmt: &'a Stmt, out: &mut Vec<&'a Stmt>) {
+    if !matches!(stmt, Stmt::Block { .. }) {
+        out.push(stmt);
+    }
+    match stmt {
+        Stmt::Expr { expr, .. } | Stmt::Destructure { value: expr, .. } => collect_expr(expr, out),
+        Stmt::Print { exprs, .. } => exprs.iter().for_each(|expr| collect_expr(expr, out)),
+        Stmt::Let { value, .. } | Stmt::Return { value, .. } => {
+            value.iter().for_each(|expr| collect_expr(expr, out))
+        }
+        Stmt::Block { stmts, .. } => stmts.iter().for_each(|stmt| collect_stmt(stmt, out)),
+        Stmt::Function {
+            requires,
+            ensures,
+            body,
+            ..
+        } => {
+            requires
+                .iter()
+                .chain(ensures)
+                .for_each(|expr| collect_expr(expr, out));
+            body.iter().for_each(|stmt| collect_stmt(stmt, out));
+        }
+        Stmt::Class {
+            super_class,
+            mixins,
+            methods,
+            fields,
+            ..
+        } => {
+            super_class
+                .iter()
+                .chain(mixins)
+                .chain(fields.iter().map(|field| &field.value))
+                .for_each(|expr| collect_expr(expr, out));
+            methods.iter().for_each(|stmt| collect_stmt(stmt, out));
+        }
+        Stmt::If {
+            condition,
+            truthy,
+            falsy,
+            ..
+        } => {
+            collect_expr(condition, out);
+            collect_stmt(truthy, out);
+            falsy.iter().for_each(|stmt| collect_stmt(stmt, out));
+        }
+        Stmt::While {
+            condition, body, ..
+        } => {
+            collect_expr(condition, out);
+            collect_stmt(body, out);
+        }
+        Stmt::For {
+            initializer,
+            condition,
+            increment,
+            body,
+            ..
+        } => {
+            initializer.iter().for_each(|stmt| collect_stmt(stmt, out));
+            condition
+                .iter()
+                .chain(increment)
+                .for_each(|expr| collect_expr(expr, out));
+            collect_stmt(body, out);
+        }
+        Stmt::ForRange {
+            initializer,
+            end,
+            step,
+            body,
+            ..
+        } => {
+            collect_stmt(initializer, out);
+            std::iter::once(end)
+                .chain(step)
+                .for_each(|expr| collect_expr(expr, out));
+            collect_stmt(body, out);
+        }
+        Stmt::Defer { body, .. } => collect_stmt(body, out),
+        Stmt::Break { .. }
+        | Stmt::Continue { .. }
+        | Stmt::Import { .. }
+        | Stmt::Empty { .. }
+        | Stmt::Error { .. } => {}
+    }
+}
+
+// Class expressions hold method bodies.
+fn collect_expr<'a>(expr: &'a Expr, out: &mut Vec<&'a Stmt>) {
+    if let Expr::Class {
+        methods, fields, ..
+    } = expr
+    {
+        methods.iter().for_each(|stmt| collect_stmt(stmt, out));
+        fields
+            .iter()
+            .for_each(|field| collect_expr(&field.value, out));
+    }
+    expr.children()
+        .into_iter()
+        .for_each(|expr| collect_expr(expr, out));
+}
+
+#[cfg(test)]
+mod test {
+    use super::LineCount;
+    use crate::ast::NodeId;
+    use crate::{lower, Interpretor, Lexer, Parser, Program};
+
+    fn parse(input: &str) -> Program {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        lower(program)
+    }
+
+    fn counts(input: &str) -> (Vec<(usize, usize)>, usize, usize) {
+        let program = parse(input);
+        let mut interpretor = Interpretor::buffered();
+        interpretor.enable_coverage();
+        interpretor.run(&program).unwrap();
+        let report = interpretor.coverage_report(&program);
+        let lines = report
+            .lines
+            .iter()
+            .map(|LineCount { line, count }| (*line, *count))
+            .collect();
+        (lines, report.statements, report.executed)
+    }
+
+    #[test]
+    fn test_counts() {
+        let input = "fn sign(n) {
+  if (n < 0) {
+    return -1;
+  } else {
+    return 1;
+  }
+}
+let i = 0;
+while (i < 3) {
+  print sign(i);
+  i = i + 1;
+}";
+        let (lines, statements, executed) = counts(input);
+        assert_eq!(
+            lines,
+            [
+                (1, 1),
+                (2, 3),
+                (3, 0),
+                (5, 3),
+                (8, 1),
+                (9, 1),
+                (10, 3),
+                (11, 3),
+            ]
+        );
+        assert_eq!((statements, executed), (8, 7));
+    }
+
+    #[test]
+    fn test_same_line() {
+        let (lines, statements, executed) =
+            counts("if (false) print 1; else print 2;\nfor (let i = 0; i < 2; i = i + 1) print i;");
+        // the if and its else ran; the increment counts on the `for` line
+        assert_eq!(lines, [(1, 1), (2, 2)]);
+        assert_eq!((statements, executed), (5, 4));
+    }
+
+    #[test]
+    fn test_annotate() {
+        let input = "let a = 1;\n\nif (a > 1)\n  print a;\n";
+        let program = parse(input);
+        let mut interpretor = Interpretor::buffered();
+        interpretor.enable_coverage();
+        interpretor.run(&program).unwrap();
+        let report = interpretor.coverage_report(&program);
+        assert_eq!(
+            report.annotate(input),
+            "     1 | let a = 1;\n       | \n     1 | if (a > 1)\n     0 |   print a;\ncoverage: 2 of 3 statements executed (66.7%)\n"
+        );
+        assert_eq!(
+            Interpretor::buffered().coverage_report(&program).executed,
+            0
+        );
+        assert!(program
+            .stmt_span(NodeId(program.node_count() as u32))
+            .is_none());
+    }
+}
@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use crate::{Error, Interpretor};
+
+// expressions evaluated between two looks at the clock
+const CHECK_EVERY: u64 = 1024;
+
+/// When the current call into the interpreter started, by its clock, and
+/// how long it may take.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Deadline {
+    started: Duration,
+    limit: Duration,
+}
+
+impl Interpretor {
+    // Runs `f` against a deadline of its own when there is a timeout, for
+    // the host's calls into the interpreter. A call made while one is
+    // timed, such as a callback or the pipeline's entry call, counts
+    // towards that one instead. The deadline ends with the call, so time
+    // the host spends between calls is never counted.
+    pub(crate) fn timed<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        if self.deadline.is_some() || self.timeout.is_none() {
+            return f(self);
+        }
+        self.deadline = self.timeout.map(|limit| Deadline {
+            started: self.clock.now(),
+            limit,
+        });
+        let result = f(self);
+        self.deadline = None;
+        result
+    }
+
+    // `check_deadline` every `CHECK_EVERY` expressions, so reading the clock
+    // costs little however cheap each expression is.
+    pub(super) fn tick_deadline(&self) -> Result<(), Error> {
+        match self.deadline {
+            Some(_) if self.stats.expressions.is_multiple_of(CHECK_EVERY) => self.check_deadline(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails with `Error::Timeout` once the run has taken its whole timeout.
+    pub(super) fn check_deadline(&self) -> Result<(), Error> {
+        let Some(Deadline { started, limit }) = self.deadline else {
+            return Ok(());
+        };
+        let elapsed = self.clock.now().saturating_sub(started);
+        if elapsed >= limit {
+            return Err(Error::Timeout { limit, elapsed });
+        }
+        Ok(())
+    }
+
+    // Waits `duration` on the clock, or only until the deadline when that
+    // comes first, which is then an `Error::Timeout`.
+    pub(super) fn sleep(&mut self, duration: Duration) -> Result<(), Error> {
+        self.check_deadline()?;
+        let left = self.deadline.map(|Deadline { started, limit }| {
+            let elapsed = self.clock.now().saturating_sub(started);
+            limit.saturating_sub(elapsed)
+        });
+        self.clock
+            .sleep(left.map_or(duration, |left| duration.min(left)));
+        self.check_deadline()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, time::Duration};
+
+    use crate::{Clock, Error, Interpretor, Lexer, Object, Parser, Span, VirtualClock};
+
+    // A clock that moves a millisecond each time it is read.
+    struct Ticking(Cell<Duration>);
+
+    impl Clock for Ticking {
+        fn now(&self) -> Duration {
+            let now = self.0.get();
+            self.0.set(now + Duration::from_millis(1));
+            now
+        }
+    }
+
+    fn run(interpretor: &mut Interpretor, input: &str) -> Result<(), Error> {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        interpretor
+            .run(&crate::lower(program))
+            .map(|_| ())
+            .map_err(|err| err.error)
+    }
+
+    fn timed(clock: impl Clock + 'static, ms: u64) -> Interpretor {
+        let mut interpretor = Interpretor::buffered();
+        interpretor.set_clock(clock);
+        interpretor.timeout = Some(Duration::from_millis(ms));
+        interpretor
+    }
+
+    #[test]
+    fn test_loop_past_deadline() {
+        let mut interpretor = timed(Ticking(Cell::default()), 50);
+        assert_eq!(
+            run(&mut interpretor, "while (true) {}"),
+            Err(Error::Timeout {
+                limit: Duration::from_millis(50),
+                elapsed: Duration::from_millis(50),
+            })
+        );
+        // read once as the run started, then once every 1024 expressions
+        assert_eq!(interpretor.stats().expressions, 50 * 1024);
+
+        // each run gets the whole timeout
+        assert_eq!(
+            run(&mut interpretor, "let i = 0; while (i < 100) i += 1;"),
+            Ok(())
+        );
+        // with whichever other limit trips first
+        interpretor.max_call_depth = Some(10);
+        let input = "fn f() { return f(); } f();";
+        assert_eq!(
+            run(&mut interpretor, input),
+            Err(Error::CallDepth { limit: 10 })
+        );
+    }
+
+    #[test]
+    fn test_host_calls_timed_apart() {
+        let mut interpretor = timed(VirtualClock::default(), 50);
+        let input = "fn handler(ms) { sleep(ms); return ms; }\nfn main() { sleep(30); }";
+        assert_eq!(run(&mut interpretor, input), Ok(()));
+        // the host waiting past the limit after the run is not counted
+        interpretor.timeout = None;
+        assert_eq!(run(&mut interpretor, "sleep(100);"), Ok(()));
+        interpretor.timeout = Some(Duration::from_millis(50));
+
+        let handler = interpretor.globals.borrow_mut().get("handler").unwrap();
+        let mut call = |ms: f64| {
+            let args = vec![Object::Number(ms)];
+            let result = interpretor.call_value(handler.clone(), args, vec![], &Span::default());
+            result.map_err(|err| err.error)
+        };
+        // each call gets the whole timeout
+        assert_eq!(call(30.0), Ok(Object::Number(30.0)));
+        assert_eq!(call(30.0), Ok(Object::Number(30.0)));
+        assert_eq!(
+            call(60.0),
+            Err(Error::Timeout {
+                limit: Duration::from_millis(50),
+                elapsed: Duration::from_millis(50),
+            })
+        );
+        let entry = interpretor.call_entry("main", &[]);
+        assert_eq!(entry.map_err(|err| err.error), Ok(Some(Object::Nil)));
+    }
+
+    #[test]
+    fn test_finishing_just_under() {
+        let mut interpretor = timed(VirtualClock::default(), 50);
+        let input = "sleep(49);\nfor (let i = 0; i < 10000; i += 1) {}";
+        assert_eq!(run(&mut interpretor, input), Ok(()));
+        assert_eq!(
+            run(&mut interpretor, "sleep(49);\nsleep(1);"),
+            Err(Error::Timeout {
+                limit: Duration::from_millis(50),
+                elapsed: Duration::from_millis(50),
+            })
+        );
+    }
+
+    #[test]
+    fn test_sleep_cut_short() {
+        let mut interpretor = timed(VirtualClock::default(), 50);
+        assert_eq!(
+            run(
+                &mut interpretor,
+                "sleep(20);\nsleep(1000);\nprint \"not reached\";"
+            ),
+            Err(Error::Timeout {
+                limit: Duration::from_millis(50),
+                elapsed: Duration::from_millis(50),
+            })
+        );
+        // the second sleep woke at the deadline rather than a second later
+        interpretor.timeout = None;
+        assert_eq!(run(&mut interpretor, "print time();"), Ok(()));
+        assert_eq!(interpretor.take_output(), "50000000\n");
+    }
+}
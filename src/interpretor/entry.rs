@@ -0,0 +1,105 @@
+use crate::object::Function;
+use crate::{Error, ErrorInfo, Interpretor, Object, Program, RunState, Span};
+
+impl Interpretor {
+    /// Runs `program` like `run`, then calls its `entry` function, see
+    /// `call_entry`. A program that suspends never reaches its entry, so
+    /// hosts that suspend should `run` and `resume` it and then call
+    /// `call_entry` themselves.
+    pub fn run_with_entry(
+        &mut self,
+        program: &Program,
+        entry: &str,
+        args: &[String],
+    ) -> Result<Option<Object>, ErrorInfo> {
+        // the entry runs within the same time as the program
+        self.timed(|interpretor| {
+            if let RunState::Suspended(..) = interpretor.run(program)? {
+                let error = Error::Runtime(format!("suspended before {entry} could run"));
+                return Err(ErrorInfo::new_with_span(error, Span::default()));
+            }
+            interpretor.call_entry(entry, args)
+        })
+    }
+
+    /// Calls the global function named `entry`, as the CLI calls a script's
+    /// `main` once its top-level statements have run, and returns what it
+    /// returned. It gets `args` as a tuple of strings if it takes one
+    /// parameter and nothing if it takes none; any other arity is a
+    /// TypeError. When no global `entry` exists, or it is not a function,
+    /// nothing is called and the result is None, so scripts without one
+    /// behave as they always have.
+    pub fn call_entry(
+        &mut self,
+        entry: &str,
+        args: &[String],
+    ) -> Result<Option<Object>, ErrorInfo> {
+        let Ok(Object::Function(function)) = self.globals.borrow_mut().get(entry) else {
+            return Ok(None);
+        };
+        let span = match &*function {
+            Function::User { span, .. } => span.clone(),
+            _ => Span::default(),
+        };
+        let arguments = match (function.min_arity(), function.max_arity()) {
+            (0, Some(0)) => vec![],
+            (0 | 1, max) if max != Some(0) => {
+                let args = args.iter().map(|arg| Object::String(arg.as_str().into()));
+                vec![Object::Tuple(args.collect())]
+            }
+            (_, max) => {
+                let count = max.unwrap_or(function.min_arity());
+                let error = Error::Type(format!(
+                    "{entry} must take no parameters or one, the arguments, not {count}"
+                ));
+                return Err(self.failed(ErrorInfo::new_with_span(error, span)));
+            }
+        };
+        let callee = Object::Function(function);
+        let result = self.call_value(callee, arguments, vec![], &span);
+        result.map(Some).map_err(|err| self.failed(err))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Interpretor, Lexer, Object, Parser, Program};
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap()
+    }
+
+    fn run(input: &str, args: &[&str]) -> (Result<Option<Object>, Error>, String) {
+        let mut interpretor = Interpretor::buffered();
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let result = interpretor.run_with_entry(&parse(input), "main", &args);
+        (result.map_err(|err| err.error), interpretor.take_output())
+    }
+
+    #[test]
+    fn test_entry() {
+        let input = "print \"top\";\nfn main(args) { print args; return args.len(); }";
+        let (result, output) = run(input, &["a", "b"]);
+        assert_eq!(result, Ok(Some(Object::Number(2.0))));
+        // top-level statements run first, including those after `main`
+        assert_eq!(output, "top\n(a, b)\n");
+
+        let (result, output) = run("fn main() { print \"no args\"; }", &["a"]);
+        assert_eq!(result, Ok(Some(Object::Nil)));
+        assert_eq!(output, "no args\n");
+
+        // nothing to call
+        assert_eq!(run("print 1;", &[]).0, Ok(None));
+        assert_eq!(run("let main = 3;", &[]).0, Ok(None));
+        assert_eq!(run("class main {}", &[]).0, Ok(None));
+
+        let (result, _) = run("fn main(a, b) {}", &[]);
+        let message = "main must take no parameters or one, the arguments, not 2";
+        assert_eq!(result, Err(Error::Type(message.to_string())));
+        let (result, output) = run("print 1;\nfn main() { return nope; }", &[]);
+        assert_eq!(result, Err(Error::Name("nope".to_string())));
+        assert_eq!(output, "1\n");
+    }
+}
@@ -0,0 +1,127 @@
+//! Arithmetic for scripts that cannot afford a float's silent rounding,
+//! such as those adding up money. Amounts are kept as whole numbers of
+//! cents, which a number holds exactly up to `MAX_SAFE_INTEGER`:
+//! `to_cents` and `from_cents` convert at the edges, and `add_exact` and
+//! `mul_exact` fail with an `Error::Overflow` rather than round in between.
+
+use super::number::MAX_SAFE_INTEGER;
+use crate::{numfmt, Error, ErrorInfo, Interpretor, Object, Span};
+
+/// `div_checked(a, b)` is `a / b`, or nil when `b` is zero, for a script
+/// that would rather test for a missing quotient than stop on a
+/// ZeroDivisionError.
+pub fn div_checked(
+    _: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let a = number("div_checked", "a", &args[0], span)?;
+    let b = number("div_checked", "b", &args[1], span)?;
+    if b == 0.0 {
+        return Ok(Object::Nil);
+    }
+    Ok(Object::Number(a / b))
+}
+
+/// `add_exact(a, b)` is `a + b` for integers, which fails rather than give
+/// a sum past `MAX_SAFE_INTEGER` either way, where it could be rounded.
+pub fn add_exact(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let a = integer("add_exact", "a", &args[0], span)?;
+    let b = integer("add_exact", "b", &args[1], span)?;
+    exact("add_exact", a + b, span)
+}
+
+/// `mul_exact(a, b)` is `a * b` for integers, failing like `add_exact`.
+pub fn mul_exact(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let a = integer("mul_exact", "a", &args[0], span)?;
+    let b = integer("mul_exact", "b", &args[1], span)?;
+    exact("mul_exact", a * b, span)
+}
+
+/// `to_cents(x)` is `x` in hundredths, rounded to a whole number the way
+/// `to_fixed(x, 2)` rounds: on the exact value of `x`, with ties going to
+/// the even digit. So `0.125` gives `12` and `0.375` gives `38`, while
+/// `2.675`, stored as `2.67499999...`, gives `267`.
+pub fn to_cents(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let x = number("to_cents", "x", &args[0], span)?;
+    if !x.is_finite() {
+        let error = Error::Value(format!("to_cents: x must be finite, not {x}"));
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    }
+    exact("to_cents", cents(x), span)
+}
+
+/// `from_cents(n)` is the amount `n` hundredths make, the number closest
+/// to it, so `from_cents(267)` prints as `2.67`.
+pub fn from_cents(
+    _: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let n = integer("from_cents", "n", &args[0], span)?;
+    Ok(Object::Number(n / 100.0))
+}
+
+// `x` rounded to two places and read back without its point, which is
+// exact where multiplying by 100 could round.
+fn cents(x: f64) -> f64 {
+    let text = numfmt::fixed(x, 2).expect("2 digits are in range");
+    numfmt::parse(&text.replace('.', "")).expect("fixed gives digits")
+}
+
+// `result`, unless it is past where every integer is a number.
+fn exact(native: &str, result: f64, span: &Span) -> Result<Object, ErrorInfo> {
+    if result.abs() > MAX_SAFE_INTEGER {
+        let error = Error::Overflow(format!(
+            "{native}: the result is beyond {MAX_SAFE_INTEGER}, where numbers lose precision"
+        ));
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    }
+    Ok(Object::Number(result))
+}
+
+fn number(native: &str, name: &str, value: &Object, span: &Span) -> Result<f64, ErrorInfo> {
+    match value {
+        Object::Number(n) => Ok(*n),
+        x => Err(ErrorInfo::new_with_span(
+            Error::Type(format!("{native}: {name} must be a number, not {x}")),
+            span.to_owned(),
+        )),
+    }
+}
+
+// A number that is a whole number no bigger than `MAX_SAFE_INTEGER`, so it
+// is exactly the integer it looks like.
+fn integer(native: &str, name: &str, value: &Object, span: &Span) -> Result<f64, ErrorInfo> {
+    let n = number(native, name, value, span)?;
+    if n.fract() != 0.0 || n.abs() > MAX_SAFE_INTEGER {
+        let error = Error::Value(format!(
+            "{native}: {name} must be an integer no bigger than {MAX_SAFE_INTEGER}, not {value}"
+        ));
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod test {
+    use super::cents;
+
+    #[test]
+    fn test_cents() {
+        let cases = [
+            (2.675, 267.0),
+            (0.125, 12.0),
+            (0.375, 38.0),
+            (0.135, 14.0),
+            (1.005, 100.0),
+            (19.99, 1999.0),
+            (-0.125, -12.0),
+            (-0.001, 0.0),
+            (12345678.91, 1234567891.0),
+        ];
+        for (x, expected) in cases {
+            assert_eq!(cents(x), expected, "cents({x})");
+        }
+    }
+}
@@ -1,20 +1,23 @@
-use crate::{visitor, ErrorInfo, Expr, Interpretor, LiteralType, Object, Span, TokenType, TokenInfo, Error};
+use std::rc::Rc;
+
+use super::{builder, index, natives, number, reflect, tuple};
+use crate::ast::{FieldInit, MatchArm, NamedArg, NodeId};
+use crate::{
+    visitor, Error, ErrorInfo, Expr, Interpretor, LiteralType, Object, Span, Stmt, TokenInfo,
+    TokenType,
+};
 
 impl visitor::Expr for Interpretor {
     fn visit_literal_expr(&mut self, value: &LiteralType) -> Result<Object, ErrorInfo> {
         Ok(match value {
             LiteralType::Nil => Object::Nil,
             LiteralType::Boolean(b) => Object::Boolean(*b),
-            LiteralType::Number(n) => Object::Number(*n),
-            LiteralType::String(s) => Object::String(s.clone()),
+            LiteralType::Number(n, _) => Object::Number(*n),
+            LiteralType::String(s) => Object::String(s.as_str().into()),
         })
     }
 
-    fn visit_unary_expr(
-        &mut self,
-        op: &TokenInfo,
-        right: &Box<Expr>,
-    ) -> Result<Object, ErrorInfo> {
+    fn visit_unary_expr(&mut self, op: &TokenInfo, right: &Expr) -> Result<Object, ErrorInfo> {
         self.eval(right)?
             .to_unary(&op.token)
             .map_err(|e| ErrorInfo::new_with_span(e, op.span.to_owned()))
@@ -22,36 +25,95 @@ impl visitor::Expr for Interpretor {
 
     fn visit_binary_expr(
         &mut self,
-        left: &Box<Expr>,
+        left: &Expr,
         op: &TokenInfo,
-        right: &Box<Expr>,
+        right: &Expr,
     ) -> Result<Object, ErrorInfo> {
-        let left = self.eval(left)?;
-        let right = self.eval(right)?;
-        Object::binary(left, &op.token, right).map_err(|e| ErrorInfo::new_with_span(e, op.span.to_owned()))
+        let left_value = self.eval(left)?;
+        let right_value = self.eval(right)?;
+        // kept for the labels of an error, as `binary` takes the values
+        let types = (left_value.type_name(), right_value.type_name());
+        Object::binary(left_value, &op.token, right_value).map_err(|e| {
+            let err = ErrorInfo::new_with_span(e, op.span.to_owned());
+            match err.error {
+                Error::ZeroDivision => err,
+                _ => err
+                    .with_label(types.0.to_string(), left.extent())
+                    .with_label(types.1.to_string(), right.extent()),
+            }
+        })
     }
 
+    // `&&` and `||` evaluate the right operand only when the left one does not
+    // decide the result, and yield the deciding operand itself.
+    fn visit_logical_expr(
+        &mut self,
+        left: &Expr,
+        op: &TokenInfo,
+        right: &Expr,
+    ) -> Result<Object, ErrorInfo> {
+        let left = self.eval(left)?;
+        let decided = match op.token {
+            TokenType::LogicalOr => left.to_boolean(),
+            _ => !left.to_boolean(),
+        };
+        if decided {
+            Ok(left)
+        } else {
+            self.eval(right)
+        }
+    }
 
-    fn visit_grouping_expr(&mut self, expr: &Box<Expr>, span: &Span) -> Result<Object, ErrorInfo> {
+    fn visit_grouping_expr(&mut self, expr: &Expr, _span: &Span) -> Result<Object, ErrorInfo> {
         self.eval(expr)
     }
 
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let object = self.eval(object)?;
+        let index = self.eval(index)?;
+        index::index(&object, &index).map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+    }
+
+    fn visit_tuple_expr(&mut self, elements: &[Expr], _span: &Span) -> Result<Object, ErrorInfo> {
+        let mut values = Vec::with_capacity(elements.len());
+        for element in elements {
+            values.push(self.eval(element)?);
+        }
+        self.stats.tuples += 1;
+        Ok(Object::Tuple(values.into()))
+    }
+
+    fn visit_match_expr(
+        &mut self,
+        value: &Expr,
+        arms: &[MatchArm],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let value = self.eval(value)?;
+        self.match_arms(value, arms, span)
+    }
+
     fn visit_assign_expr(
         &mut self,
-        name: &String,
-        value: &Box<Expr>,
+        _id: NodeId,
+        name: &str,
+        value: &Expr,
         span: &Span,
     ) -> Result<Object, ErrorInfo> {
         let value = self.eval(value)?;
-        self.environment
-            .borrow_mut()
-            .assign(name, value)
-            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+        self.assign(name, value, span)
     }
+
     fn visit_call_expr(
         &mut self,
-        callee: &Box<Expr>,
-        args: &Vec<Expr>,
+        callee: &Expr,
+        args: &[Expr],
+        named: &[NamedArg],
         span: &Span,
     ) -> Result<Object, ErrorInfo> {
         let callee = self.eval(callee)?;
@@ -59,48 +121,242 @@ impl visitor::Expr for Interpretor {
         for arg in args {
             arguments.push(self.eval(arg)?);
         }
-        match callee {
-            Object::Function(f) => f.call(self, &arguments),
+        let mut named_arguments = Vec::new();
+        for arg in named {
+            let value = self.eval(&arg.value)?;
+            named_arguments.push((arg.name.clone(), value, arg.span.clone()));
+        }
+        self.call_value(callee, arguments, named_arguments, span)
+    }
+
+    fn visit_get_expr(
+        &mut self,
+        id: NodeId,
+        object: &Expr,
+        name: &str,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        match self.eval(object)? {
+            Object::Instance(instance) => {
+                let class = instance.borrow().class.clone();
+                self.check_private(object.is_this(), &class, name, span)?;
+                if let Some(value) = instance.borrow().field(name) {
+                    return Ok(value);
+                }
+                instance
+                    .borrow()
+                    .check_initialized(name)
+                    .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+                match self.find_method(id, &class, name) {
+                    Some(method) => Ok(Object::Function(Rc::new(self.bind(&method, &instance)))),
+                    None => {
+                        let error = Error::Attribute(format!("undefined property \"{name}\""));
+                        Err(ErrorInfo::new_with_span(error, span.to_owned())
+                            .with_suggestion(name, instance.borrow().property_names()))
+                    }
+                }
+            }
+            x @ (Object::Function(_) | Object::Class(_)) => {
+                reflect::method(&x, name).map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+            }
+            x @ Object::Tuple(_) => {
+                tuple::method(&x, name).map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+            }
+            x @ Object::Number(_) => {
+                number::method(&x, name).map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+            }
+            x @ Object::Builder(_) => {
+                builder::method(&x, name).map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+            }
+            Object::Module(module) => match module.get(name) {
+                Some(value) => Ok(value.clone()),
+                None => {
+                    let error = Error::Attribute(format!(
+                        "module \"{}\" does not export \"{name}\"",
+                        module.name
+                    ));
+                    Err(ErrorInfo::new_with_span(error, span.to_owned())
+                        .with_suggestion(name, module.names()))
+                }
+            },
             x => Err(ErrorInfo::new_with_span(
-                Error::Type(format!("{x} is not callable")),
+                Error::Type(format!("{x} has no properties")),
                 span.to_owned(),
             )),
         }
     }
 
-    fn visit_get_expr(
+    fn visit_placeholder_expr(&mut self, name: &str, span: &Span) -> Result<Object, ErrorInfo> {
+        let Some(params) = &self.params else {
+            let error = Error::Runtime(format!(
+                "@{{{name}}} needs a value, but the program was run without params"
+            ));
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        };
+        match params.get(name) {
+            Some(value) => Ok(value.clone()),
+            None => {
+                let error = Error::Runtime(format!("no param for @{{{name}}}"));
+                Err(ErrorInfo::new_with_span(error, span.to_owned())
+                    .with_suggestion(name, params.keys().map(String::as_str)))
+            }
+        }
+    }
+
+    // The object is evaluated before the value, and only once: in
+    // `f().x += g()` the field is read and written on what `f()` returned.
+    fn visit_set_expr(
         &mut self,
-        object: &Box<Expr>,
-        name: &String,
+        object: &Expr,
+        name: &str,
+        op: Option<&TokenInfo>,
+        value: &Expr,
         span: &Span,
     ) -> Result<Object, ErrorInfo> {
-        let object = self.eval(object)?;
-        let output = self
-            .environment
+        let instance = match self.eval(object)? {
+            Object::Instance(instance) => instance,
+            Object::Module(module) => {
+                let error = Error::Type(format!(
+                    "cannot set \"{name}\" on module \"{}\", which is read-only",
+                    module.name
+                ));
+                return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+            }
+            _ => {
+                let error = Error::Type("only instances have fields".to_string());
+                return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+            }
+        };
+        let class = instance.borrow().class.clone();
+        self.check_private(object.is_this(), &class, name, span)?;
+        let value = match op {
+            Some(op) => {
+                let Some(current) = instance.borrow().field(name) else {
+                    if let Err(error) = instance.borrow().check_initialized(name) {
+                        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+                    }
+                    let error = Error::Attribute(format!("undefined field \"{name}\""));
+                    return Err(ErrorInfo::new_with_span(error, span.to_owned())
+                        .with_suggestion(name, instance.borrow().field_names()));
+                };
+                let value = self.eval(value)?;
+                Object::binary(current, &op.token, value)
+                    .map_err(|e| ErrorInfo::new_with_span(e, op.span.to_owned()))?
+            }
+            None => self.eval(value)?,
+        };
+        instance
             .borrow_mut()
-            .get(name)
+            .set(name, value.clone())
             .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
-        Ok(output)
+        Ok(value)
     }
 
-    fn visit_set_expr(
+    fn visit_super_expr(
+        &mut self,
+        _id: NodeId,
+        name: &str,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let lookup = |interpretor: &mut Self, name: &str| {
+            interpretor
+                .environment
+                .borrow_mut()
+                .get(name)
+                .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+        };
+        let (Object::Class(super_class), Object::Instance(instance)) =
+            (lookup(self, "super")?, lookup(self, "this")?)
+        else {
+            let error = Error::Runtime("super used outside of a subclass method".to_string());
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        };
+        self.check_private(true, &super_class, name, span)?;
+        match super_class.find_method(name) {
+            Some(method) => Ok(Object::Function(Rc::new(self.bind(&method, &instance)))),
+            None => {
+                let error = Error::Attribute(format!("undefined property \"{name}\""));
+                Err(ErrorInfo::new_with_span(error, span.to_owned())
+                    .with_suggestion(name, super_class.method_names()))
+            }
+        }
+    }
+
+    fn visit_class_expr(
         &mut self,
-        object: &Box<Expr>,
-        name: &String,
-        value: &Box<Expr>,
+        name: Option<&str>,
+        super_class: Option<&Expr>,
+        mixins: &[Expr],
+        methods: &[Stmt],
+        fields: &[FieldInit],
+        private: &[String],
+        doc: Option<&str>,
         span: &Span,
     ) -> Result<Object, ErrorInfo> {
-        todo!();
+        Ok(Object::Class(self.class(
+            name,
+            super_class,
+            mixins,
+            methods,
+            fields,
+            private,
+            doc,
+            span,
+        )?))
     }
-    fn visit_super_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo> {
-        todo!();
+
+    fn visit_variable_expr(
+        &mut self,
+        _id: NodeId,
+        name: &str,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        let result = self.environment.borrow_mut().get(name);
+        result
+            .or_else(|e| natives::internal(name).ok_or(e))
+            .map_err(|e| self.name_error(e, name, span))
     }
+}
 
-    fn visit_variable_expr(&mut self, name: &String, span: &Span) -> Result<Object, ErrorInfo> {
-        self.environment
-            .borrow_mut()
-            .get(name)
-            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+impl Interpretor {
+    // Assigns what an assignment evaluated to, which a suspended call to a
+    // native may only hand over once the run is resumed.
+    pub(super) fn assign(
+        &mut self,
+        name: &str,
+        value: Object,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        if self.strict_types {
+            let annotation = self.environment.borrow().annotation(name);
+            if let Some(annotation) = annotation {
+                self.check_type(&value, &annotation, || format!("\"{name}\""))
+                    .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+            }
+        }
+        let result = self.environment.borrow_mut().assign(name, value.clone());
+        match result {
+            Err(Error::Name(_)) if self.implicit_globals => {
+                let mut globals = self.globals.borrow_mut();
+                globals
+                    .define(name.to_string(), value.clone(), false)
+                    .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+                Ok(value)
+            }
+            result => result.map_err(|e| self.name_error(e, name, span)),
+        }
+    }
+
+    // Suggests a visible name when `name` is undefined.
+    fn name_error(&self, error: Error, name: &str, span: &Span) -> ErrorInfo {
+        let err = ErrorInfo::new_with_span(error, span.to_owned());
+        match err.error {
+            Error::Name(_) => {
+                let names = self.environment.borrow().visible_names();
+                err.with_suggestion(name, names.iter().map(String::as_str))
+            }
+            _ => err,
+        }
     }
 }
 
@@ -132,11 +388,7 @@ mod test {
         let program = parser.parse_program().unwrap();
         let mut interpretor = Interpretor::new();
         interpretor.interpret(program);
-        let output = interpretor
-            .environment
-            .borrow_mut()
-            .get(&"a".to_string())
-            .unwrap();
+        let output = interpretor.environment.borrow_mut().get("a").unwrap();
         assert_eq!(output, Object::Number(7.0));
     }
 }
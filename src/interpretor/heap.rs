@@ -0,0 +1,250 @@
+use std::{cell::RefCell, collections::HashSet, mem::size_of, rc::Rc};
+
+use crate::object::{Class, Function, Instance, Module};
+use crate::{Environment, Error, Expr, Interpretor, Object};
+
+// what a binding or field costs besides its name and value, for the table
+// holding it
+const ENTRY_OVERHEAD: usize = 32;
+// the least charged between two measurements, so small scripts never walk
+const MIN_STEP: usize = 64 * 1024;
+
+/// An estimate of how much memory a script holds, kept by charging each
+/// value an expression makes and, every so often, measuring everything
+/// reachable. Measuring is what finds values that were dropped; charging
+/// only decides when to measure, after as many bytes as the last
+/// measurement found. Both depend on nothing but what the script does, so
+/// a limit trips at the same point on every run.
+#[derive(Debug, Default)]
+pub(super) struct Heap {
+    // bytes charged since the last measurement
+    charged: usize,
+    // what the last measurement found
+    measured: usize,
+}
+
+impl Interpretor {
+    // Charges the value `expr` evaluated to, failing with
+    // `Error::MemoryLimit` if it takes the script past `max_heap_bytes`.
+    pub(super) fn charge(&mut self, expr: &Expr, value: &Object) -> Result<(), Error> {
+        // reading or passing a value on makes nothing new
+        if !matches!(
+            expr,
+            Expr::Binary { .. } | Expr::Call { .. } | Expr::Tuple { .. } | Expr::Index { .. }
+        ) {
+            return Ok(());
+        }
+        let bytes = shallow_size(value);
+        self.heap.charged += bytes;
+        let over = self
+            .max_heap_bytes
+            .is_some_and(|limit| self.heap.measured + self.heap.charged > limit);
+        if !over && self.heap.charged < self.heap.measured.max(MIN_STEP) {
+            return Ok(());
+        }
+        // `value` may not be reachable yet, e.g. an argument being built
+        let used = self.measure_heap() + bytes;
+        self.stats.peak_heap_bytes = self.stats.peak_heap_bytes.max(used as u64);
+        match self.max_heap_bytes {
+            Some(limit) if used > limit => Err(Error::MemoryLimit { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Measures what the script holds now, as `Stats::heap_bytes` shows.
+    /// `run` measures once it is done; hosts running code some other way
+    /// call this before reading the stats.
+    pub fn measure_heap(&mut self) -> usize {
+        let mut walk = Walk::default();
+        walk.environments.push(self.globals.clone());
+        walk.environments.push(self.environment.clone());
+        walk.environments.extend(self.scopes.iter().cloned());
+        let deferred = self.defers.iter().flatten();
+        walk.environments
+            .extend(deferred.map(|(_, environment)| environment.clone()));
+        walk.environments.extend(self.suspended_environments());
+        let used = walk.run();
+        self.heap = Heap {
+            charged: 0,
+            measured: used,
+        };
+        self.stats.heap_bytes = used as u64;
+        self.stats.peak_heap_bytes = self.stats.peak_heap_bytes.max(used as u64);
+        used
+    }
+}
+
+// What `value` itself takes beyond its slot, leaving out what it refers to.
+fn shallow_size(value: &Object) -> usize {
+    match value {
+        Object::String(s) if s.is_heap() => s.len(),
+        Object::Tuple(elements) => elements.len() * size_of::<Object>(),
+        Object::Instance(instance) => {
+            let fields = instance.borrow().fields().count();
+            size_of::<Instance>() + fields * (size_of::<Object>() + ENTRY_OVERHEAD)
+        }
+        Object::Function(_) => size_of::<Function>(),
+        Object::Class(_) => size_of::<Class>(),
+        Object::Builder(buffer) => buffer.borrow().capacity(),
+        Object::Module(module) => {
+            let exports = module.exports().count();
+            size_of::<Module>() + exports * (size_of::<Object>() + ENTRY_OVERHEAD)
+        }
+        Object::String(_) | Object::Boolean(_) | Object::Number(_) | Object::Nil => 0,
+    }
+}
+
+// Sums what is reachable from some environments, counting everything
+// shared once. It keeps its own lists rather than recursing, as a script can
+// nest values deeper than the stack goes.
+#[derive(Default)]
+struct Walk {
+    environments: Vec<Rc<RefCell<Environment>>>,
+    values: Vec<Object>,
+    seen: HashSet<*const ()>,
+    bytes: usize,
+}
+
+impl Walk {
+    fn run(mut self) -> usize {
+        loop {
+            if let Some(environment) = self.environments.pop() {
+                self.environment(&environment);
+            } else if let Some(value) = self.values.pop() {
+                self.value(&value);
+            } else {
+                return self.bytes;
+            }
+        }
+    }
+
+    // Whether the value at `ptr` is met for the first time.
+    fn first<T: ?Sized>(&mut self, ptr: *const T) -> bool {
+        self.seen.insert(ptr as *const ())
+    }
+
+    fn environment(&mut self, environment: &Rc<RefCell<Environment>>) {
+        if !self.first(Rc::as_ptr(environment)) {
+            return;
+        }
+        let environment = environment.borrow();
+        for (name, value) in environment.values() {
+            self.bytes += name.len() + size_of::<Object>() + ENTRY_OVERHEAD;
+            self.values.push(value.clone());
+        }
+        self.environments.extend(environment.enclosing().cloned());
+    }
+
+    fn value(&mut self, value: &Object) {
+        let first = match value {
+            Object::Tuple(elements) => self.first(Rc::as_ptr(elements)),
+            Object::Instance(instance) => self.first(Rc::as_ptr(instance)),
+            Object::Function(function) => self.first(Rc::as_ptr(function)),
+            Object::Class(class) => self.first(Rc::as_ptr(class)),
+            Object::Builder(buffer) => self.first(Rc::as_ptr(buffer)),
+            Object::Module(module) => self.first(Rc::as_ptr(module)),
+            Object::String(_) | Object::Boolean(_) | Object::Number(_) | Object::Nil => true,
+        };
+        if !first {
+            return;
+        }
+        self.bytes += shallow_size(value);
+        match value {
+            Object::Tuple(elements) => self.values.extend(elements.iter().cloned()),
+            Object::Instance(instance) => {
+                let instance = instance.borrow();
+                for (name, value) in instance.fields() {
+                    self.bytes += name.len();
+                    self.values.push(value.clone());
+                }
+                self.values.push(Object::Class(instance.class.clone()));
+            }
+            Object::Function(function) => match &**function {
+                Function::User { closure, .. } => self.environments.push(closure.clone()),
+                Function::Method { receiver, .. } => self.values.push(receiver.clone()),
+                Function::Inbuilt { .. } => {}
+            },
+            Object::Class(class) => {
+                let methods = class.methods.values().cloned().map(Object::Function);
+                self.values.extend(methods);
+                self.values
+                    .extend(class.super_class.clone().map(Object::Class));
+                self.environments.push(class.scope.clone());
+            }
+            Object::Module(module) => {
+                for (name, value) in module.exports() {
+                    self.bytes += name.len();
+                    self.values.push(value.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Interpretor, Lexer, Parser};
+
+    fn run(interpretor: &mut Interpretor, input: &str) -> Result<(), Error> {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        interpretor
+            .run(&program)
+            .map(|_| ())
+            .map_err(|err| err.error)
+    }
+
+    // Runs `input` in a new interpreter allowed a megabyte.
+    fn run_limited(input: &str) -> Result<(), Error> {
+        let mut interpretor = Interpretor::buffered();
+        interpretor.max_heap_bytes = Some(1 << 20);
+        run(&mut interpretor, input)
+    }
+
+    #[test]
+    fn test_limit() {
+        let limit = Error::MemoryLimit { limit: 1 << 20 };
+        let input = "let sb = StringBuilder(); while (true) { sb.append(\"xxxxxxxx\"); }";
+        assert_eq!(run_limited(input), Err(limit.clone()));
+        let input = "let s = \"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\"; while (true) { s = s + s; }";
+        assert_eq!(run_limited(input), Err(limit.clone()));
+        // making room fails before anything is allocated
+        let input = "StringBuilder().reserve(2 * 1024 * 1024);";
+        assert_eq!(run_limited(input), Err(limit));
+
+        // garbage does not count, only what is still reachable
+        let input = "let i = 0;
+while (i < 20000) {
+    let s = \"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\" + i.to_string();
+    let pair = (s, s, s);
+    i = i + 1;
+}";
+        assert_eq!(run_limited(input), Ok(()));
+    }
+
+    #[test]
+    fn test_usage() {
+        let mut interpretor = Interpretor::buffered();
+        run(&mut interpretor, "let small = 1;").unwrap();
+        let baseline = interpretor.stats().heap_bytes;
+        assert!(baseline > 0);
+
+        // a megabyte, held and then let go
+        let input = "let s = \"xxxxxxxxxxxxxxxx\";
+let i = 0;
+while (i < 16) { s = s + s; i = i + 1; }
+s = nil;";
+        run(&mut interpretor, input).unwrap();
+        let stats = interpretor.stats();
+        assert!(stats.peak_heap_bytes > 1 << 20);
+        assert!(stats.heap_bytes < baseline + 1024);
+
+        // the same run measures the same
+        let mut again = Interpretor::buffered();
+        run(&mut again, "let small = 1;").unwrap();
+        run(&mut again, input).unwrap();
+        assert_eq!(again.stats(), stats);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{ErrorInfo, Interpretor, Object};
+
+/// Callbacks an embedder gets as a script runs, to watch it without
+/// replacing where its output goes: they are called along with the output
+/// sink, not instead of it. Every method does nothing unless overridden.
+/// They run synchronously, at the point the interpreter has reached.
+pub trait InterpreterHooks {
+    /// A line the script wrote, by `print` or a native such as `time`,
+    /// without its newline.
+    fn on_print(&mut self, _text: &str) {}
+
+    /// An error a run stopped at, once, as `run` or `call_entry` returns it.
+    fn on_runtime_error(&mut self, _err: &ErrorInfo) {}
+
+    /// A call to a function or class named `name` is starting, `depth`
+    /// calls deep counting this one.
+    fn on_function_enter(&mut self, _name: &str, _depth: usize) {}
+
+    /// The call `on_function_enter` announced at the same `depth` has
+    /// returned or failed.
+    fn on_function_exit(&mut self, _name: &str, _depth: usize) {}
+}
+
+impl Interpretor {
+    /// Calls `hooks` as scripts run from now on, in place of any installed
+    /// before.
+    pub fn set_hooks(&mut self, hooks: impl InterpreterHooks + 'static) {
+        self.hooks = Some(Box::new(hooks));
+    }
+
+    // Tells the hooks a run stopped at `err`, which it returns.
+    pub(super) fn failed(&mut self, err: ErrorInfo) -> ErrorInfo {
+        if let Some(hooks) = &mut self.hooks {
+            hooks.on_runtime_error(&err);
+        }
+        err
+    }
+}
+
+// The name the hooks see for a call to `callee`.
+pub(super) fn callee_name(callee: &Object) -> &str {
+    match callee {
+        Object::Function(function) => function.name(),
+        Object::Class(class) => class.name.as_deref().unwrap_or("<anonymous>"),
+        _ => "<not callable>",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::InterpreterHooks;
+    use crate::{ErrorInfo, Interpretor, Lexer, Parser};
+
+    // Records every event as a line of text.
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl InterpreterHooks for Recorder {
+        fn on_print(&mut self, text: &str) {
+            self.0.borrow_mut().push(format!("print {text}"));
+        }
+
+        fn on_runtime_error(&mut self, err: &ErrorInfo) {
+            let line = err.span().line;
+            self.0
+                .borrow_mut()
+                .push(format!("error {} on line {line}", err.error));
+        }
+
+        fn on_function_enter(&mut self, name: &str, depth: usize) {
+            self.0.borrow_mut().push(format!("enter {name} {depth}"));
+        }
+
+        fn on_function_exit(&mut self, name: &str, depth: usize) {
+            self.0.borrow_mut().push(format!("exit {name} {depth}"));
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        let input = "class Point { init(x) { this.x = x; } }
+fn double(x) { return x * 2; }
+fn show(x) { print double(x); }
+show(Point(1).x);
+print \"done\";
+show(nil);";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut interpretor = Interpretor::buffered();
+        interpretor.set_hooks(Recorder(events.clone()));
+        assert!(interpretor.run(&program).is_err());
+        assert_eq!(
+            *events.borrow(),
+            [
+                "enter Point 1",
+                "exit Point 1",
+                "enter show 1",
+                "enter double 2",
+                "exit double 2",
+                "print 2",
+                "exit show 1",
+                "print done",
+                "enter show 1",
+                "enter double 2",
+                "exit double 2",
+                "exit show 1",
+                "error TypeError: \"*\" not supported between nil and number on line 2",
+            ]
+        );
+        // the sink still gets the output
+        assert_eq!(interpretor.take_output(), "2\ndone\n");
+    }
+}
@@ -0,0 +1,295 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::modules::Load;
+use crate::object::Module;
+use crate::{
+    fold_constants, lower, Environment, Error, ErrorInfo, ImportForm, ImportName, Interpretor,
+    Lexer, Object, Parser, Resolver, Span,
+};
+
+impl Interpretor {
+    /// Runs an `import` or `export ... from`: loads the module unless an
+    /// earlier import already did, then binds what `form` asks for of its
+    /// exports where the import is. An error inside the module is reported
+    /// at the import, with a note pointing into the module.
+    ///
+    /// One name re-exported twice by a module, as when it re-exports from
+    /// two modules that both re-export it from a third, is only an error
+    /// when the two values differ.
+    pub(super) fn import(
+        &mut self,
+        specifier: &str,
+        form: &ImportForm,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let fail = |error| ErrorInfo::new_with_span(error, span.clone());
+        // reading a module can block, so the timeout is checked on both
+        // sides of it
+        self.check_deadline().map_err(fail)?;
+        let load = self.modules.begin(specifier);
+        if let Err(error) = self.check_deadline() {
+            if let Ok(Load::Start(_)) = load {
+                self.modules.finish(None);
+            }
+            return Err(fail(error));
+        }
+        let module = match load {
+            Ok(Load::Done(module)) => module,
+            Ok(Load::Start(file)) => {
+                let result = self.run_module(&file.source);
+                let module = self.modules.finish(result.as_ref().ok().cloned());
+                result.map_err(|err| {
+                    let inner = err.span().clone();
+                    let mut outer = fail(err.error.clone());
+                    for (message, span) in err.notes() {
+                        outer = outer.with_note(message.clone(), span.clone());
+                    }
+                    outer.with_note(format!("in {}", file.name), inner)
+                })?;
+                module.expect("a module that ran to the end is kept")
+            }
+            Err(err) => return Err(fail(Error::Import(err.to_string()))),
+        };
+        let bindings: Vec<(String, Object)> = match form {
+            ImportForm::All => module
+                .exports()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+            ImportForm::Module { alias, .. } => vec![(alias.clone(), Object::Module(module))],
+            ImportForm::Names(names) => {
+                let mut bindings = Vec::with_capacity(names.len());
+                for name in names {
+                    bindings.push((name.bound().to_string(), exported(&module, name)?));
+                }
+                bindings
+            }
+            ImportForm::ReExport(names) => {
+                for name in names {
+                    let value = exported(&module, name)?;
+                    let Some(previous) = self.modules.re_export(name.bound(), value.clone()) else {
+                        continue;
+                    };
+                    if !Object::binary(previous, &crate::TokenType::Eq, value)
+                        .is_ok_and(|same| same.to_boolean())
+                    {
+                        let error = Error::Import(format!(
+                            "\"{}\" is exported twice, with different values",
+                            name.bound()
+                        ));
+                        return Err(ErrorInfo::new_with_span(error, name.span.clone()));
+                    }
+                }
+                return Ok(());
+            }
+        };
+        let mut environment = self.environment.borrow_mut();
+        for (name, value) in bindings {
+            environment.define(name, value, false).map_err(fail)?;
+        }
+        Ok(())
+    }
+
+    // Runs a module in an environment of its own below the globals and
+    // returns that environment. The module's statements are not counted
+    // towards the script's coverage, whose node ids they would collide with.
+    fn run_module(&mut self, source: &str) -> Result<Rc<RefCell<Environment>>, ErrorInfo> {
+        let program = Parser::new(Lexer::new(source.to_string())).parse_program()?;
+        Resolver::with_natives(self.native_names()).resolve(&program)?;
+        let mut program = lower(program);
+        fold_constants(&mut program);
+
+        let module = Rc::new(RefCell::new(Environment::new_from_closure(&self.globals)));
+        let environment = std::mem::replace(&mut self.environment, module.clone());
+        let coverage = self.coverage.take();
+        let result = program.stmts.iter().try_for_each(|stmt| self.exec(stmt));
+        self.coverage = coverage;
+        self.environment = environment;
+        result.map(|_| module)
+    }
+}
+
+// The value `module` exports under the name `name` imports.
+fn exported(module: &Module, name: &ImportName) -> Result<Object, ErrorInfo> {
+    if let Some(value) = module.get(&name.name) {
+        return Ok(value.clone());
+    }
+    let error = Error::Import(format!(
+        "module \"{}\" does not export \"{}\"",
+        module.name, name.name
+    ));
+    Err(ErrorInfo::new_with_span(error, name.span.clone())
+        .with_suggestion(&name.name, module.names()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, ErrorInfo, Interpretor, Lexer, MemoryModuleSource, Parser};
+
+    fn run(modules: MemoryModuleSource, input: &str) -> (Result<(), ErrorInfo>, String) {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::buffered().with_modules(Box::new(modules));
+        let result = program
+            .stmts
+            .iter()
+            .try_for_each(|stmt| interpretor.exec(stmt));
+        (result, interpretor.take_output())
+    }
+
+    #[test]
+    fn test_import() {
+        let modules = MemoryModuleSource::new()
+            .with(
+                "lib/math.lang",
+                "import \"./util.lang\";\nfn square(x) { return twice(x) / 2 * x; }",
+            )
+            .with(
+                "lib/util.lang",
+                "print \"util\";\nfn twice(x) { return x * 2; }",
+            )
+            .with(
+                "app.lang",
+                "import \"lib/util.lang\";\nconst answer = twice(21);",
+            );
+        let input =
+            "import \"lib/math.lang\";\nimport \"app.lang\";\nprint square(3), answer, twice(1);";
+        let (result, output) = run(modules, input);
+        assert!(result.is_ok(), "{result:?}");
+        // `util` runs once, though two modules import it
+        assert_eq!(output, "util\n9 42 2\n");
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let modules = MemoryModuleSource::new()
+            .with("a.lang", "import \"b.lang\";")
+            .with("b.lang", "import \"./a.lang\";")
+            .with("bad.lang", "let x = 1;\nprint nope;");
+        let (result, _) = run(modules.clone(), "import \"a.lang\";");
+        let err = result.unwrap_err();
+        let message = "import cycle: a.lang -> b.lang -> a.lang";
+        assert_eq!(err.error, Error::Import(message.to_string()));
+        // the note points at the import in `b`, through the one in `a`
+        let notes: Vec<_> = err.notes().iter().map(|(note, _)| note.as_str()).collect();
+        assert_eq!(notes, ["in b.lang", "in a.lang"]);
+
+        let (result, _) = run(modules.clone(), "import \"c.lang\";");
+        let message = "module \"c.lang\" not found";
+        assert_eq!(
+            result.unwrap_err().error,
+            Error::Import(message.to_string())
+        );
+        let (result, _) = run(modules.clone(), "import \"../a.lang\";");
+        let message = "\"../a.lang\" is outside the module root";
+        assert_eq!(
+            result.unwrap_err().error,
+            Error::Import(message.to_string())
+        );
+
+        let (result, _) = run(modules, "import \"bad.lang\";");
+        let err = result.unwrap_err();
+        assert_eq!(err.error, Error::Name("nope".to_string()));
+        assert_eq!(err.span().line, 1);
+        assert_eq!(err.notes()[0].1.line, 2);
+    }
+
+    fn math() -> MemoryModuleSource {
+        MemoryModuleSource::new()
+            .with(
+                "math.lang",
+                "print \"math\";\nfn fit(x, lo, hi) { if (x < lo) return lo; if (x > hi) return hi; return x; }\nconst pi = 3;",
+            )
+            .with(
+                "lib/util.lang",
+                "export { fit as limit, pi } from \"../math.lang\";\nfn twice(x) { return x * 2; }",
+            )
+    }
+
+    #[test]
+    fn test_import_forms() {
+        let input = "import \"math.lang\" as math;
+import { fit as limit, pi } from \"math.lang\";
+print math.fit(12, 0, 10), limit(-1, 0, 5), pi, math;
+print math == math, math.fit == limit;";
+        let (result, output) = run(math(), input);
+        assert!(result.is_ok(), "{result:?}");
+        // the module runs once, for every form that imports it
+        assert_eq!(output, "math\n10 0 3 <module \"math.lang\">\ntrue true\n");
+
+        // a re-export is exported, but not bound where it is
+        let input = "import \"lib/util.lang\" as util;
+import { limit } from \"lib/util.lang\";
+print util.twice(util.pi), limit(7, 0, 5);";
+        let (result, output) = run(math(), input);
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(output, "math\n6 5\n");
+        let (result, _) = run(
+            math(),
+            "import \"lib/util.lang\";\nprint limit(1, 0, 2), fit;",
+        );
+        assert_eq!(result.unwrap_err().error, Error::Name("fit".to_string()));
+
+        let (result, _) = run(math(), "import { fit, round } from \"math.lang\";");
+        let err = result.unwrap_err();
+        let message = "module \"math.lang\" does not export \"round\"";
+        assert_eq!(err.error, Error::Import(message.to_string()));
+        assert_eq!(err.span().start, 14);
+    }
+
+    #[test]
+    fn test_module_read_only() {
+        let input = "import \"math.lang\" as math;\nmath.pi = 4;";
+        let (result, _) = run(math(), input);
+        let message = "cannot set \"pi\" on module \"math.lang\", which is read-only";
+        assert_eq!(result.unwrap_err().error, Error::Type(message.to_string()));
+        let (result, _) = run(math(), "import \"math.lang\" as math;\nmath.pi += 1;");
+        assert!(matches!(result.unwrap_err().error, Error::Type(_)));
+        let (result, _) = run(math(), "import \"math.lang\" as math;\nmath.p;");
+        let message = "module \"math.lang\" does not export \"p\"";
+        assert_eq!(
+            result.unwrap_err().error,
+            Error::Attribute(message.to_string())
+        );
+        // nor can its exports be changed through what the module defined
+        let (result, output) = run(
+            MemoryModuleSource::new().with("count.lang", "let n = 0;\nfn bump() { n = n + 1; }"),
+            "import \"count.lang\" as count;\ncount.bump();\nprint count.n;",
+        );
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(output, "0\n");
+    }
+
+    // `top` re-exports `x` from both sides of a diamond, each of which
+    // re-exports it from `base`. The two are the same value, so that is
+    // allowed; names that differ are not.
+    #[test]
+    fn test_diamond() {
+        let modules = MemoryModuleSource::new()
+            .with("base.lang", "fn x() { return \"base\"; }")
+            .with("left.lang", "export { x } from \"base.lang\";")
+            .with("right.lang", "export { x } from \"base.lang\";")
+            .with(
+                "top.lang",
+                "export { x } from \"left.lang\";\nexport { x } from \"right.lang\";",
+            )
+            .with("other.lang", "fn x() { return \"other\"; }")
+            .with(
+                "clash.lang",
+                "export { x } from \"left.lang\";\nexport { x } from \"other.lang\";",
+            );
+        let input = "import { x } from \"top.lang\";
+import \"left.lang\" as left;
+import \"right.lang\" as right;
+print x(), left.x == right.x;";
+        let (result, output) = run(modules.clone(), input);
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(output, "base true\n");
+
+        let (result, _) = run(modules, "import \"clash.lang\";");
+        let err = result.unwrap_err();
+        let message = "\"x\" is exported twice, with different values";
+        assert_eq!(err.error, Error::Import(message.to_string()));
+        assert_eq!(err.notes()[0].1.line, 2);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Error, Object};
+
+/// The element of `object` at `index`, counting from 0: an element of a
+/// tuple, or a one-character string of a string.
+pub fn index(object: &Object, index: &Object) -> Result<Object, Error> {
+    match object {
+        Object::Tuple(elements) => Ok(elements[coerce_index(index, elements.len())?].clone()),
+        Object::String(s) => {
+            let at = coerce_index(index, s.chars().count())?;
+            let ch = s.chars().nth(at).expect("coerce_index checked the length");
+            Ok(Object::String((&*ch.encode_utf8(&mut [0; 4])).into()))
+        }
+        _ => Err(Error::Type(format!("{object} cannot be indexed"))),
+    }
+}
+
+/// Turns the script value `index` into a position in something `len` long.
+/// Every operation that takes a position goes through here, so they all
+/// fail alike. Numbers are f64, so `1.0` is the position 1, but `1.5`, NaN
+/// and the infinities are not positions at all. Negative positions are out
+/// of range, as nothing counts from the end.
+pub fn coerce_index(index: &Object, len: usize) -> Result<usize, Error> {
+    let Object::Number(n) = *index else {
+        return Err(Error::Type(format!(
+            "index must be a number, not {}",
+            index.type_name()
+        )));
+    };
+    if n.fract() != 0.0 || !n.is_finite() {
+        return Err(Error::Value(format!("index {index} is not an integer")));
+    }
+    if n < 0.0 || n >= len as f64 {
+        return Err(Error::Index { index: n, len });
+    }
+    Ok(n as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::index;
+    use crate::{Error, Object};
+
+    #[test]
+    fn test_pathological_indices() {
+        let tuple = Object::Tuple([1.0, 2.0, 3.0].map(Object::Number).into());
+        let string = Object::String("aπc".into());
+        let value = |s: &str| Error::Value(s.to_string());
+        let cases = [
+            (Object::Number(1.5), value("index 1.5 is not an integer")),
+            (
+                Object::Number(f64::NAN),
+                value("index NaN is not an integer"),
+            ),
+            (
+                Object::Number(f64::INFINITY),
+                value("index inf is not an integer"),
+            ),
+            (
+                Object::Number(f64::NEG_INFINITY),
+                value("index -inf is not an integer"),
+            ),
+            (
+                Object::Number(-1.0),
+                Error::Index {
+                    index: -1.0,
+                    len: 3,
+                },
+            ),
+            (Object::Number(3.0), Error::Index { index: 3.0, len: 3 }),
+            (
+                Object::Number(1e20),
+                Error::Index {
+                    index: 1e20,
+                    len: 3,
+                },
+            ),
+            (
+                Object::String("0".into()),
+                Error::Type("index must be a number, not string".to_string()),
+            ),
+            (
+                Object::Nil,
+                Error::Type("index must be a number, not nil".to_string()),
+            ),
+        ];
+        for (at, error) in cases {
+            for object in [&tuple, &string] {
+                assert_eq!(index(object, &at), Err(error.clone()), "{object}[{at}]");
+            }
+        }
+
+        // integral floats are exact positions, counted in chars
+        let one = Object::Number(1.0);
+        assert_eq!(index(&tuple, &one), Ok(Object::Number(2.0)));
+        assert_eq!(index(&string, &one), Ok(Object::String("π".into())));
+        let zero = Object::Number(-0.0);
+        assert_eq!(index(&string, &zero), Ok(Object::String("a".into())));
+    }
+}
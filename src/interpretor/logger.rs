@@ -0,0 +1,41 @@
+use std::fmt;
+
+/// How much a message from `log_debug`, `log_info`, `log_warn` or
+/// `log_error` matters, least first.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub enum LogLevel {
+    #[default]
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        })
+    }
+}
+
+/// Where the logging natives send their messages, apart from what a script
+/// prints. Hosts can swap in their own, e.g. one that forwards to the
+/// logging of the embedding program.
+pub trait ScriptLogger {
+    /// Records `message`, the value a script logged as `print` shows it,
+    /// logged at `level` on `line` of the script.
+    fn log(&self, level: LogLevel, message: &str, line: usize);
+}
+
+/// Writes each message to stderr as `[LEVEL] message`.
+pub struct StderrLogger;
+
+impl ScriptLogger for StderrLogger {
+    fn log(&self, level: LogLevel, message: &str, _: usize) {
+        eprintln!("[{level}] {message}");
+    }
+}
@@ -0,0 +1,96 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::NodeId,
+    object::{Class, Function},
+    Interpretor,
+};
+
+// The last class a `.name` expression found a method on. The name is kept
+// because node ids are only unique within one program, and an interpreter
+// runs many.
+pub(super) struct CacheSlot {
+    class: Rc<Class>,
+    name: String,
+    method: Rc<Function>,
+}
+
+impl Interpretor {
+    // Looks `name` up along the class chain of `class`, remembering the
+    // method for the `.name` expression `id`. One slot per expression is
+    // enough for the usual loop over objects of one class. Classes never
+    // change once created, so a slot is valid for as long as its class is.
+    pub(super) fn find_method(
+        &mut self,
+        id: NodeId,
+        class: &Rc<Class>,
+        name: &str,
+    ) -> Option<Rc<Function>> {
+        if let Some(slot) = self.method_cache.get(&id) {
+            if Rc::ptr_eq(&slot.class, class) && slot.name == name {
+                self.stats.cache_hits += 1;
+                return Some(slot.method.clone());
+            }
+        }
+        self.stats.chain_walks += 1;
+        let method = class.find_method(name)?;
+        let slot = CacheSlot {
+            class: class.clone(),
+            name: name.to_string(),
+            method: method.clone(),
+        };
+        self.method_cache.insert(id, slot);
+        Some(method)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Interpretor, Lexer, Parser, Program};
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut interpretor = Interpretor::buffered();
+        let input = "class A { get() { return 1; } }
+class B < A {}
+let b = B();
+let total = 0;
+while (total < 100) total = total + b.get();
+print total;";
+        interpretor.run(&parse(input)).unwrap();
+        assert_eq!(interpretor.take_output(), "100\n");
+        let stats = interpretor.stats();
+        assert_eq!((stats.chain_walks, stats.cache_hits), (1, 99));
+
+        // same node id, different name
+        interpretor
+            .run(&parse(
+                "class C { a() { return \"a\"; } b() { return \"b\"; } } let c = C();",
+            ))
+            .unwrap();
+        interpretor.run(&parse("print c.a();")).unwrap();
+        interpretor.run(&parse("print c.b();")).unwrap();
+        assert_eq!(interpretor.take_output(), "a\nb\n");
+    }
+
+    #[test]
+    fn test_polymorphic() {
+        let mut interpretor = Interpretor::buffered();
+        let input = "class A { name() { return \"A\"; } }
+class B < A { name() { return \"B\"; } }
+class C < A { init() { this.name = this.own; } own() { return \"field\"; } }
+fn name(object) { return object.name(); }
+print name(A()); print name(B()); print name(C()); print name(B()); print name(B());";
+        interpretor.run(&parse(input)).unwrap();
+        assert_eq!(interpretor.take_output(), "A\nB\nfield\nB\nB\n");
+        // the field on C shadows the method without a lookup
+        let stats = interpretor.stats();
+        assert_eq!((stats.chain_walks, stats.cache_hits), (3, 2));
+    }
+}
@@ -1,75 +1,1015 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, io, io::Write, rc::Rc, time::Duration};
 
 use crate::{
-    ast::Program, object::Function, Environment, Error, ErrorInfo, Expr, LiteralType, Object, Stmt,
-    TokenType,
+    ast::{FieldInit, NodeId, Program},
+    modules::{FsModuleSource, ModuleLoader, ModuleSource},
+    object::{format::Limits, Class, Function, HashSeed, Instance, NativeFn},
+    Environment, Error, ErrorInfo, Expr, Object, Snippet, Span, Stmt,
 };
+mod builder;
+mod clock;
+mod complete;
+mod coverage;
+mod deadline;
+mod entry;
+mod exact;
 mod expr;
+mod heap;
+mod hooks;
+mod import;
+mod index;
+mod logger;
+mod method_cache;
+mod natives;
+mod number;
+mod pattern;
+mod prelude;
+mod reflect;
+mod stats;
 mod stmt;
+mod suspend;
+mod template;
+mod testing;
+mod tuple;
+
+pub(crate) use builder::MAX_LEN;
+pub use clock::{Clock, SystemClock, VirtualClock};
+pub use complete::{Completion, CompletionKind};
+pub use coverage::{Coverage, LineCount};
+pub use hooks::InterpreterHooks;
+pub use logger::{LogLevel, ScriptLogger, StderrLogger};
+pub(crate) use natives::names as native_names;
+pub(crate) use prelude::names as prelude_names;
+pub use stats::Stats;
+pub use suspend::{ResumeHandle, RunState};
+
+// The calls that may be in progress at once unless the host says otherwise.
+pub(crate) const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
 
 pub struct Interpretor {
     pub globals: Rc<RefCell<Environment>>,
     pub environment: Rc<RefCell<Environment>>,
-    pub locals: std::collections::HashMap<Expr, usize>,
+    pub locals: HashMap<NodeId, usize>,
+    /// Whether scripts may read the clock through `time`.
+    pub allow_clock: bool,
+    /// Whether scripts may run code from strings through `eval`.
+    pub allow_eval: bool,
+    /// Whether scripts may log through `log_debug`, `log_info`, `log_warn`
+    /// and `log_error`.
+    pub allow_log: bool,
+    /// The least level a logged message needs to reach the logger. Those
+    /// below it are dropped before the value is formatted.
+    pub log_level: LogLevel,
+    /// Whether assigning to a name nothing declares defines it as a global
+    /// instead of failing with a NameError. Off except in a lenient REPL.
+    pub implicit_globals: bool,
+    /// How much of each value `print` and the REPL show.
+    pub print_limits: Limits,
+    /// Whether the `requires` and `ensures` clauses of functions are
+    /// checked. When off they are skipped without being evaluated.
+    pub check_contracts: bool,
+    /// Whether values are checked against the types annotating parameters,
+    /// results and `let`s. Off by default, leaving annotations unchecked.
+    pub strict_types: bool,
+    /// The most bytes the values a script can reach may take, as estimated
+    /// for `Stats::heap_bytes`. Going past it is an `Error::MemoryLimit`.
+    /// None, the default, sets no limit.
+    pub max_heap_bytes: Option<usize>,
+    /// The most calls that may be in progress at once, counting those made
+    /// from natives such as the comparator of `sorted`. A call past it is an
+    /// `Error::CallDepth`. 1000 by default, which the CLI gives its thread
+    /// the stack for; a host running on a small stack may need less. None
+    /// sets no limit, leaving runaway recursion to overflow the stack.
+    pub max_call_depth: Option<usize>,
+    /// How long each call into the interpreter may take, by its clock: a
+    /// `run` or `resume`, or a host's `call_value`, `call_entry`, `eval`,
+    /// `exec` or `run_tests`, counting the calls made while it runs. Time
+    /// the host spends between calls is not counted. The clock is read every
+    /// so many expressions and around waits such as `sleep`, so a run can go
+    /// a little past it before failing with `Error::Timeout`. None, the
+    /// default, sets no limit.
+    pub timeout: Option<Duration>,
+    deadline: Option<deadline::Deadline>,
+    // what instances hash their field names with, fixed only when
+    // `deterministic` is set
+    hash_seed: HashSeed,
+    deterministic: bool,
+    tests: testing::Tests,
+    clock: Box<dyn Clock>,
+    logger: Box<dyn ScriptLogger>,
+    hooks: Option<Box<dyn InterpreterHooks>>,
+    trace_count: usize,
+    // calls in progress, so natives know whether they were called back
+    call_depth: usize,
+    // natives in progress, so one knows whether it was called back
+    native_depth: usize,
+    suspension: Option<suspend::Suspension>,
+    // what the error of a suspending native has unwound through so far
+    unwinding: Vec<suspend::Unwound>,
+    // the frames of a resumed run still to finish
+    resuming: Vec<suspend::Frame>,
+    // how often each statement ran, when coverage is enabled
+    coverage: Option<HashMap<NodeId, usize>>,
+    // the method each `.name` expression found last
+    method_cache: HashMap<NodeId, method_cache::CacheSlot>,
+    stats: Stats,
+    heap: heap::Heap,
+    // the environments `exec_block` has left for another until it returns,
+    // which hold the locals of the calls in progress
+    scopes: Vec<Rc<RefCell<Environment>>>,
+    // one list of deferred statements per active function call
+    defers: Vec<Vec<(Stmt, Rc<RefCell<Environment>>)>>,
+    // the functions the prelude defined, unless made with `no_prelude`
+    prelude: Option<Vec<Rc<Function>>>,
+    // for each script call in progress, whether it runs prelude code
+    frames: Vec<bool>,
+    // a script function called back from the prelude failed, so the error
+    // already points into the script
+    callback_failed: bool,
+    // the values of `@{name}` placeholders while `run_with_params` runs
+    params: Option<HashMap<String, Object>>,
+    // the script `interpret` shows errors in, if known
+    source: Option<Snippet>,
+    // where `import` finds modules, and those it ran
+    modules: ModuleLoader,
+    output: Output,
+}
+
+enum Output {
+    Stream(Box<dyn Write>),
+    Buffer(Vec<u8>),
 }
 
 impl Interpretor {
     pub fn new() -> Self {
+        Self::with_output(Box::new(io::stdout()))
+    }
+
+    /// Creates an interpreter whose `print` output goes to `output` instead
+    /// of stdout.
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        Self::with_sink(Output::Stream(output))
+    }
+
+    /// Creates an interpreter that keeps its output until `take_output` is
+    /// called, for hosts that run many snippets and inspect each result.
+    pub fn buffered() -> Self {
+        Self::with_sink(Output::Buffer(Vec::new()))
+    }
+
+    fn with_sink(output: Output) -> Self {
         let globals = Rc::new(RefCell::new(Environment::new()));
-        let time = Object::Function(Function::Inbuilt {
-            arity: 0,
-            func: Box::new(|_args| {
-                let time = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_nanos() as f64;
-                Ok(Object::Number(time as f64))
-            }),
-        });
-        globals.borrow_mut().define("time".to_string(), time, true).unwrap();
+        natives::register(&mut globals.borrow_mut());
         let environment = globals.clone();
-        Self {
+        let mut interpretor = Self {
             globals,
             environment,
-            locals: std::collections::HashMap::new(),
+            locals: HashMap::new(),
+            allow_clock: true,
+            allow_eval: true,
+            allow_log: true,
+            log_level: LogLevel::default(),
+            implicit_globals: false,
+            print_limits: Limits::default(),
+            check_contracts: true,
+            strict_types: false,
+            max_heap_bytes: None,
+            max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH),
+            timeout: None,
+            deadline: None,
+            hash_seed: HashSeed::random(),
+            deterministic: false,
+            tests: testing::Tests::default(),
+            clock: Box::new(SystemClock),
+            logger: Box::new(StderrLogger),
+            hooks: None,
+            trace_count: 0,
+            call_depth: 0,
+            native_depth: 0,
+            suspension: None,
+            unwinding: Vec::new(),
+            resuming: Vec::new(),
+            coverage: None,
+            method_cache: HashMap::new(),
+            stats: Stats::default(),
+            heap: heap::Heap::default(),
+            scopes: Vec::new(),
+            defers: Vec::new(),
+            prelude: Some(Vec::new()),
+            frames: Vec::new(),
+            callback_failed: false,
+            params: None,
+            source: None,
+            modules: ModuleLoader::new(Box::new(FsModuleSource::new("."))),
+            output,
+        };
+        interpretor.load_prelude();
+        interpretor
+    }
+
+    /// Drops the prelude, leaving only the natives in the globals. Scripts
+    /// run by this interpreter see none of the functions it defines.
+    pub fn no_prelude(mut self) -> Self {
+        self.prelude = None;
+        self.clear();
+        self
+    }
+
+    /// Loads the modules scripts import from `source` instead of from files
+    /// under the working directory.
+    pub fn with_modules(mut self, source: Box<dyn ModuleSource>) -> Self {
+        self.modules = ModuleLoader::new(source);
+        self
+    }
+
+    // Runs the prelude into the globals. What it runs is not the script's,
+    // so it leaves the stats as they were.
+    fn load_prelude(&mut self) {
+        let stats = std::mem::take(&mut self.stats);
+        for stmt in &prelude::program().stmts {
+            if let Err(err) = self.exec(stmt) {
+                prelude::broken(err);
+            }
         }
+        self.stats = stats;
+        let mut globals = self.globals.borrow_mut();
+        let functions = prelude::names().iter().map(|name| match globals.get(name) {
+            Ok(Object::Function(function)) => function,
+            _ => prelude::broken(format!("\"{name}\" is not a function")),
+        });
+        self.prelude = Some(functions.collect());
+    }
+
+    fn in_prelude(&self, callee: &Object) -> bool {
+        let Object::Function(callee) = callee else {
+            return false;
+        };
+        let prelude = self.prelude.iter().flatten();
+        prelude
+            .into_iter()
+            .any(|function| Rc::ptr_eq(function, callee))
     }
 
     pub fn interpret(&mut self, program: Program) {
         for stmt in program.stmts {
-            let res = self.exec(&stmt);
-            if res.is_err() {
-                res.err().unwrap().report();
+            if let Err(err) = self.exec(&stmt) {
+                let err = self.failed(err);
+                eprint!("{}", err.render(self.source.as_ref()));
+            }
+        }
+    }
+
+    /// Makes `interpret` show the lines of `source` each error points at.
+    pub fn set_source(&mut self, source: &str) {
+        self.source = Some(Snippet::new(source));
+    }
+
+    /// Runs `program` against the current globals, stopping at the first
+    /// error. Can be called any number of times: bindings made by one run,
+    /// including those made before a failing statement, stay visible to the
+    /// next.
+    ///
+    /// A native may suspend the run to wait on the host, see `pending`. Only
+    /// one run can be suspended at a time: until it is resumed, `run` fails.
+    pub fn run(&mut self, program: &Program) -> Result<RunState, ErrorInfo> {
+        let result = self.timed(|interpretor| interpretor.run_stmts(&program.stmts));
+        self.measure_heap();
+        result
+    }
+
+    /// Runs `program` like `run`, with each `@{name}` placeholder in it
+    /// evaluating to `params[name]`. Values go straight into the program
+    /// rather than through its source, so they need no quoting. A name
+    /// missing from `params` is a runtime error at its placeholder.
+    ///
+    /// The params last as long as the run: a function it defined that uses
+    /// a placeholder fails when a later run calls it, unless that run has
+    /// params of its own. A suspended run keeps them until it is resumed to
+    /// the end.
+    pub fn run_with_params(
+        &mut self,
+        program: &Program,
+        params: HashMap<String, Object>,
+    ) -> Result<RunState, ErrorInfo> {
+        self.params = Some(params);
+        let result = self.run(program);
+        if !matches!(result, Ok(RunState::Suspended(..))) {
+            self.params = None;
+        }
+        result
+    }
+
+    /// Drops every global defined by scripts while keeping the natives and
+    /// the prelude, along with a suspended run, whose handle then completes
+    /// nothing.
+    pub fn clear(&mut self) {
+        let mut globals = Environment::new();
+        natives::register(&mut globals);
+        self.globals = Rc::new(RefCell::new(globals));
+        self.environment = self.globals.clone();
+        self.locals.clear();
+        self.method_cache.clear();
+        self.modules.clear();
+        self.suspension = None;
+        self.unwinding.clear();
+        self.params = None;
+        if self.prelude.is_some() {
+            self.load_prelude();
+        }
+    }
+
+    /// Returns what has been printed since the last call. Interpreters that
+    /// write to a stream have nothing buffered and always return "".
+    pub fn take_output(&mut self) -> String {
+        match &mut self.output {
+            Output::Buffer(buffer) => String::from_utf8_lossy(&std::mem::take(buffer)).into_owned(),
+            Output::Stream(_) => String::new(),
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    /// Sends what scripts log to `logger` instead of stderr.
+    pub fn set_logger(&mut self, logger: impl ScriptLogger + 'static) {
+        self.logger = Box::new(logger);
+    }
+
+    /// Makes every run of the same script print the same output, for test
+    /// suites run in CI. The clock becomes a `VirtualClock`, so `time` reads
+    /// 0 until the script sleeps. Nothing else a script can observe depends
+    /// on the host: natives that list names, such as `fields`, sort them,
+    /// and the fields of an instance keep the order they were set in. Field
+    /// names are hashed with a fixed seed, see `set_hash_seed`. Turning it
+    /// off goes back to the system clock and a random key.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+        if deterministic {
+            self.set_clock(VirtualClock::default());
+            self.hash_seed = HashSeed::fixed(0);
+        } else {
+            self.set_clock(SystemClock);
+            self.hash_seed = HashSeed::random();
+        }
+    }
+
+    /// The seed deterministic mode hashes field names with, 0 by default,
+    /// for reproducing a run that depended on it. Ignored outside that
+    /// mode, where each interpreter draws a random key, as std's `HashMap`
+    /// does, so that names crafted to collide cannot slow a script down.
+    pub fn set_hash_seed(&mut self, seed: u64) {
+        if self.deterministic {
+            self.hash_seed = HashSeed::fixed(seed);
+        }
+    }
+
+    /// Adds a native function to the globals. `required` of the `params` must
+    /// be given; with `variadic`, extra positional arguments are passed on
+    /// after them.
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        params: &'static [&'static str],
+        required: usize,
+        variadic: bool,
+        func: NativeFn,
+    ) {
+        natives::define(
+            &mut self.globals.borrow_mut(),
+            name,
+            params,
+            required,
+            variadic,
+            func,
+        );
+    }
+
+    /// The names of every global, natives included, sorted.
+    pub fn global_names(&self) -> Vec<String> {
+        let globals = self.globals.borrow().bindings();
+        globals.into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// The globals currently bound to built-ins, sorted: native functions,
+    /// including those added with `define_native`, and the functions of the
+    /// prelude. A built-in a script has shadowed is left out.
+    pub fn native_names(&self) -> Vec<String> {
+        self.globals
+            .borrow()
+            .bindings()
+            .into_iter()
+            .filter_map(|(name, value)| match &value {
+                Object::Function(function) if function.is_native() => Some(name),
+                value if self.in_prelude(value) => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Reads the interpreter's clock. Hosts use this to time what they run,
+    /// so a fake clock set with `set_clock` covers them too.
+    pub fn now(&self) -> Duration {
+        self.clock.now()
+    }
+
+    pub fn write_output(&mut self, text: &str) {
+        if let Some(hooks) = &mut self.hooks {
+            hooks.on_print(text);
+        }
+        match &mut self.output {
+            Output::Stream(stream) => writeln!(stream, "{text}").expect("failed to write output"),
+            Output::Buffer(buffer) => {
+                writeln!(buffer, "{text}").expect("writing to a Vec never fails")
             }
         }
     }
 
     pub fn eval(&mut self, expr: &Expr) -> Result<Object, ErrorInfo> {
-        expr.accept(self)
+        self.timed(|interpretor| {
+            interpretor.stats.expressions += 1;
+            interpretor
+                .tick_deadline()
+                .map_err(|e| ErrorInfo::new_with_span(e, expr.extent()))?;
+            let value = expr.accept(interpretor)?;
+            interpretor
+                .charge(expr, &value)
+                .map_err(|e| ErrorInfo::new_with_span(e, expr.extent()))?;
+            Ok(value)
+        })
     }
 
     pub fn exec(&mut self, stmt: &Stmt) -> Result<(), ErrorInfo> {
-        stmt.accept(self)
+        self.timed(|interpretor| {
+            interpretor.stats.statements += 1;
+            interpretor.count(stmt);
+            stmt.accept(interpretor)
+        })
     }
 
-    pub fn exec_block(
+    /// Runs `stmt` like `exec`. For an expression statement the value is
+    /// returned, e.g. for a host showing what a script's last line gave.
+    pub fn exec_value(&mut self, stmt: &Stmt) -> Result<Option<Object>, ErrorInfo> {
+        let Stmt::Expr { expr, .. } = stmt else {
+            return self.exec(stmt).map(|()| None);
+        };
+        self.timed(|interpretor| {
+            interpretor.stats.statements += 1;
+            interpretor.count(stmt);
+            interpretor.eval(expr).map(Some)
+        })
+    }
+
+    /// Calls a function or class with already evaluated arguments. Natives
+    /// that take callbacks go through here as well as call expressions, so
+    /// a callback is counted, limited and hooked like any other call, and
+    /// an error it raises reaches the native to pass on.
+    pub fn call_value(
+        &mut self,
+        callee: Object,
+        arguments: Vec<Object>,
+        named: Vec<(String, Object, Span)>,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.timed(|interpretor| interpretor.call_limited(callee, arguments, named, span))
+    }
+
+    fn call_limited(
+        &mut self,
+        callee: Object,
+        arguments: Vec<Object>,
+        named: Vec<(String, Object, Span)>,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        if let Some(limit) = self
+            .max_call_depth
+            .filter(|&limit| self.call_depth >= limit)
+        {
+            return Err(ErrorInfo::new_with_span(
+                Error::CallDepth { limit },
+                span.to_owned(),
+            ));
+        }
+        self.call_depth += 1;
+        self.stats.max_call_depth = self.stats.max_call_depth.max(self.call_depth as u64);
+        let name = self
+            .hooks
+            .as_ref()
+            .map(|_| hooks::callee_name(&callee).to_string());
+        if let (Some(hooks), Some(name)) = (&mut self.hooks, &name) {
+            hooks.on_function_enter(name, self.call_depth);
+        }
+        let result = match &callee {
+            Object::Function(function) if function.is_native() => {
+                self.native_depth += 1;
+                let result = self.call(callee, arguments, named, span);
+                self.native_depth -= 1;
+                result
+            }
+            _ => {
+                let mark = self.unwinding.len();
+                let from_prelude = self.frames.last() == Some(&true);
+                let to_prelude = self.in_prelude(&callee);
+                self.frames.push(to_prelude);
+                let result = self.call(callee.clone(), arguments, named, span);
+                self.frames.pop();
+                let result = result.map_err(|err| {
+                    let name = name.clone();
+                    self.unwound_call(mark, &callee, name, from_prelude, to_prelude, span, err)
+                });
+                if let Err(ErrorInfo {
+                    error: Error::Suspend(_),
+                    ..
+                }) = result
+                {
+                    // the rest of the call happens once the run is resumed
+                    self.call_depth -= 1;
+                    return result;
+                }
+                result.map_err(|err| self.prelude_error(err, from_prelude, to_prelude, span))
+            }
+        };
+        if let (Some(hooks), Some(name)) = (&mut self.hooks, &name) {
+            hooks.on_function_exit(name, self.call_depth);
+        }
+        self.call_depth -= 1;
+        result
+    }
+
+    // The script's source is not the prelude's, so an error raised in
+    // prelude code is reported where the script called into the prelude.
+    // One raised by a script function the prelude called back keeps its
+    // place.
+    fn prelude_error(
+        &mut self,
+        err: ErrorInfo,
+        from_prelude: bool,
+        to_prelude: bool,
+        span: &Span,
+    ) -> ErrorInfo {
+        if let Error::Suspend(_) = err.error {
+            return err;
+        }
+        match (from_prelude, to_prelude) {
+            (true, false) => {
+                self.callback_failed = true;
+                err
+            }
+            (false, true) if !std::mem::take(&mut self.callback_failed) => {
+                ErrorInfo::new_with_span(err.error, span.clone())
+            }
+            _ => err,
+        }
+    }
+
+    fn call(
+        &mut self,
+        callee: Object,
+        arguments: Vec<Object>,
+        named: Vec<(String, Object, Span)>,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        match callee {
+            Object::Function(f) => {
+                let arguments = f.bind_arguments(arguments, named, span)?;
+                if f.is_native() {
+                    self.stats.native_calls += 1;
+                } else {
+                    self.stats.script_calls += 1;
+                    self.stats.environments += 1;
+                }
+                f.call(self, &arguments, span)
+            }
+            Object::Class(class) => {
+                let instance = Rc::new(RefCell::new(Instance::new(
+                    class.clone(),
+                    self.hash_seed.clone(),
+                )));
+                self.stats.instances += 1;
+                self.init_fields(&class, &instance)?;
+                match class.find_method("init") {
+                    Some(init) => {
+                        let init = self.bind(&init, &instance);
+                        self.stats.script_calls += 1;
+                        self.stats.environments += 1;
+                        let arguments = init.bind_arguments(arguments, named, span)?;
+                        init.call(self, &arguments, span)?;
+                    }
+                    None => {
+                        if let Some((name, _, name_span)) = named.into_iter().next() {
+                            let error =
+                                Error::Type(format!("unexpected keyword argument \"{name}\""));
+                            return Err(ErrorInfo::new_with_span(error, name_span));
+                        }
+                        if !arguments.is_empty() {
+                            let error = Error::Arity {
+                                expected: 0,
+                                got: arguments.len(),
+                            };
+                            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+                        }
+                    }
+                }
+                Ok(Object::Instance(instance))
+            }
+            x => Err(ErrorInfo::new_with_span(
+                Error::Type(format!("{x} is not callable")),
+                span.to_owned(),
+            )),
+        }
+    }
+
+    // Runs the field initializers of the chain of `class` on a new
+    // `instance`, those of the superclasses first and each class's in
+    // order. Until its initializer has run, reading a field fails.
+    fn init_fields(
         &mut self,
-        stmts: &Vec<Stmt>,
+        class: &Rc<Class>,
+        instance: &Rc<RefCell<Instance>>,
+    ) -> Result<(), ErrorInfo> {
+        let mut chain = Vec::new();
+        let mut next = Some(class);
+        while let Some(class) = next {
+            chain.push(class);
+            next = class.super_class.as_ref();
+        }
+        if chain.iter().all(|class| class.fields.is_empty()) {
+            return Ok(());
+        }
+        instance.borrow_mut().begin_init();
+        let result = chain.iter().rev().try_for_each(|class| {
+            if class.fields.is_empty() {
+                return Ok(());
+            }
+            // initializers see `this` as the methods of the class do
+            let mut environment = Environment::new_from_closure(&class.scope);
+            environment
+                .define("this".to_string(), Object::Instance(instance.clone()), true)
+                .expect("this is a fresh binding");
+            self.stats.environments += 1;
+            let environment = Rc::new(RefCell::new(environment));
+            let previous = std::mem::replace(&mut self.environment, environment);
+            let result = class.fields.iter().try_for_each(|field| {
+                let value = self.eval(&field.value)?;
+                instance
+                    .borrow_mut()
+                    .set(&field.name, value)
+                    .map_err(|e| ErrorInfo::new_with_span(e, field.span.clone()))
+            });
+            self.environment = previous;
+            result
+        });
+        instance.borrow_mut().end_init();
+        result
+    }
+
+    /// Builds the class shared by `class` declarations and expressions. Its
+    /// methods close over the current environment, so a class defined inside
+    /// a function sees that call's locals.
+    #[allow(clippy::too_many_arguments)]
+    fn class(
+        &mut self,
+        name: Option<&str>,
+        super_class: Option<&Expr>,
+        mixins: &[Expr],
+        methods: &[Stmt],
+        fields: &[FieldInit],
+        private: &[String],
+        doc: Option<&str>,
+        span: &Span,
+    ) -> Result<Rc<Class>, ErrorInfo> {
+        let super_class = match super_class.map(|expr| self.eval(expr)).transpose()? {
+            Some(Object::Class(class)) => Some(class),
+            Some(x) => {
+                let error = Error::Type(format!("superclass must be a class, not {x}"));
+                return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+            }
+            None => None,
+        };
+
+        let mut closure = self.environment.clone();
+        if let Some(super_class) = &super_class {
+            let mut environment = Environment::new_from_closure(&closure);
+            environment
+                .define(
+                    "super".to_string(),
+                    Object::Class(super_class.clone()),
+                    true,
+                )
+                .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+            closure = Rc::new(RefCell::new(environment));
+            self.stats.environments += 1;
+        } else if !private.is_empty() {
+            // the scope private members are checked against is this class's
+            // own, not one it shares with whatever else is defined here
+            closure = Rc::new(RefCell::new(Environment::new_from_closure(&closure)));
+            self.stats.environments += 1;
+        }
+
+        let mut methods: HashMap<_, _> = methods
+            .iter()
+            .filter_map(|method| match method {
+                Stmt::Function {
+                    name,
+                    params,
+                    signature,
+                    requires,
+                    ensures,
+                    body,
+                    doc,
+                    span,
+                    ..
+                } => Some((
+                    name.clone(),
+                    Rc::new(Function::User {
+                        name: name.clone(),
+                        params: params.clone(),
+                        signature: signature.clone(),
+                        requires: requires.clone(),
+                        ensures: ensures.clone(),
+                        body: body.clone(),
+                        doc: doc.clone(),
+                        closure: closure.clone(),
+                        is_initializer: name == "init",
+                        span: span.clone(),
+                    }),
+                )),
+                _ => None,
+            })
+            .collect();
+        let lent = self.mixin_methods(mixins, super_class.as_ref(), &methods, span)?;
+        methods.extend(lent);
+        self.stats.functions += methods.len() as u64;
+        self.stats.classes += 1;
+        Ok(Rc::new(Class {
+            name: name.map(str::to_owned),
+            super_class,
+            methods,
+            private: private.iter().cloned().collect(),
+            fields: fields.to_vec(),
+            doc: doc.map(str::to_owned),
+            scope: closure,
+        }))
+    }
+
+    // The methods the classes `mixins` evaluate to lend a class with
+    // `super_class` and the methods `own`. Only a mixin's own methods are
+    // copied, flattened into the class rather than chained, and `super` in
+    // them means `super_class`. The class's own methods win silently; the
+    // same name from two mixins, or from a mixin and the superclass chain,
+    // is a TypeError naming both.
+    fn mixin_methods(
+        &mut self,
+        mixins: &[Expr],
+        super_class: Option<&Rc<Class>>,
+        own: &HashMap<String, Rc<Function>>,
+        span: &Span,
+    ) -> Result<Vec<(String, Rc<Function>)>, ErrorInfo> {
+        let fail =
+            |message: String| Err(ErrorInfo::new_with_span(Error::Type(message), span.clone()));
+        let mut lent: Vec<(String, Rc<Function>)> = Vec::new();
+        let mut sources: HashMap<String, String> = HashMap::new();
+        for expr in mixins {
+            let mixin = match self.eval(expr)? {
+                Object::Class(mixin) => mixin,
+                x => return fail(format!("mixin must be a class, not {x}")),
+            };
+            let source = match &mixin.name {
+                Some(name) => format!("mixin {name}"),
+                None => "an anonymous mixin".to_string(),
+            };
+            if !mixin.private.is_empty() {
+                return fail(format!(
+                    "{source} has private members, so it cannot be mixed in"
+                ));
+            }
+            if !mixin.fields.is_empty() {
+                return fail(format!(
+                    "{source} initializes fields, so it cannot be mixed in"
+                ));
+            }
+            let mut environment = Environment::new_from_closure(&mixin.scope);
+            let super_value = super_class.map_or(Object::Nil, |class| Object::Class(class.clone()));
+            environment
+                .define("super".to_string(), super_value, false)
+                .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+            let closure = Rc::new(RefCell::new(environment));
+            self.stats.environments += 1;
+
+            let mut names: Vec<_> = mixin.methods.keys().collect();
+            names.sort();
+            for name in names {
+                if own.contains_key(name) {
+                    continue;
+                }
+                if let Some(other) = sources.get(name) {
+                    return fail(format!(
+                        "method \"{name}\" comes from both {other} and {source}"
+                    ));
+                }
+                if let Some(base) = super_class.filter(|class| class.find_method(name).is_some()) {
+                    let base = base.name.as_deref().unwrap_or("<anonymous>");
+                    return fail(format!(
+                        "method \"{name}\" of {source} conflicts with superclass {base}"
+                    ));
+                }
+                let Function::User {
+                    params,
+                    signature,
+                    requires,
+                    ensures,
+                    body,
+                    doc,
+                    is_initializer,
+                    span,
+                    ..
+                } = &*mixin.methods[name]
+                else {
+                    continue;
+                };
+                let method = Function::User {
+                    name: name.clone(),
+                    params: params.clone(),
+                    signature: signature.clone(),
+                    requires: requires.clone(),
+                    ensures: ensures.clone(),
+                    body: body.clone(),
+                    doc: doc.clone(),
+                    closure: closure.clone(),
+                    is_initializer: *is_initializer,
+                    span: span.clone(),
+                };
+                sources.insert(name.clone(), source.clone());
+                lent.push((name.clone(), Rc::new(method)));
+            }
+        }
+        Ok(lent)
+    }
+
+    // `method` with `this` bound to `instance`, counted as a new function
+    // and environment.
+    fn bind(&mut self, method: &Function, instance: &Rc<RefCell<Instance>>) -> Function {
+        self.stats.functions += 1;
+        self.stats.environments += 1;
+        method.bind(instance)
+    }
+
+    // Private members are reached only as `this.name` from the methods of
+    // the class declaring them, including functions nested in those methods.
+    // Subclasses are outside: their methods close over another scope.
+    fn check_private(
+        &self,
+        through_this: bool,
+        class: &Rc<Class>,
+        name: &str,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let Some(owner) = class.private_owner(name) else {
+            return Ok(());
+        };
+        if through_this && Environment::is_within(&self.environment, &owner.scope) {
+            return Ok(());
+        }
+        let kind = if owner.methods.contains_key(name) {
+            "method"
+        } else {
+            "field"
+        };
+        let class = match &owner.name {
+            Some(class) => format!("class {class}"),
+            None => "an anonymous class".to_string(),
+        };
+        let error = Error::Attribute(format!("{kind} \"{name}\" of {class} is private"));
+        Err(ErrorInfo::new_with_span(error, span.to_owned()))
+    }
+
+    /// Runs a function body as its own call frame. Statements deferred in it
+    /// run once the body finishes, last registered first, however it
+    /// finished. They cannot change the value being returned, but an error
+    /// raised by one replaces the body's outcome; when several fail, the
+    /// last one to run wins.
+    ///
+    /// A body that suspends leaves its deferred statements to run once it
+    /// is resumed and finishes.
+    pub fn exec_frame(
+        &mut self,
+        body: &[Stmt],
         environment: Rc<RefCell<Environment>>,
     ) -> Result<(), ErrorInfo> {
-        let parent = self.environment.clone();
-        self.environment = environment;
+        self.defers.push(Vec::new());
+        let result = self.exec_block(body, environment.clone());
+        let defers = self.defers.pop().unwrap_or_default();
+        if let Err(ErrorInfo {
+            error: Error::Suspend(_),
+            ..
+        }) = result
+        {
+            self.unwound_body(environment, defers);
+            return result;
+        }
+        self.run_defers(defers, result)
+    }
 
-        let result = (|| -> Result<(), ErrorInfo> {
-            for stmt in stmts {
-                self.exec(stmt)?;
+    // Runs the statements a call deferred, see `exec_frame`.
+    fn run_defers(
+        &mut self,
+        defers: Vec<(Stmt, Rc<RefCell<Environment>>)>,
+        mut result: Result<(), ErrorInfo>,
+    ) -> Result<(), ErrorInfo> {
+        for (stmt, environment) in defers.into_iter().rev() {
+            let mark = self.unwinding.len();
+            match self.exec_block(std::slice::from_ref(&stmt), environment) {
+                Ok(())
+                | Err(ErrorInfo {
+                    error: Error::Return(_),
+                    ..
+                }) => {}
+                Err(err) => result = Err(self.no_suspend(mark, err)),
             }
-            Ok(())
-        })();
+        }
+        result
+    }
 
+    // Evaluates the `kind` clauses of `function` in `environment`, failing
+    // with a ContractError at the first one that is falsy.
+    pub(crate) fn check_contract(
+        &mut self,
+        kind: &str,
+        function: &str,
+        clauses: &[Expr],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), ErrorInfo> {
+        if !self.check_contracts || clauses.is_empty() {
+            return Ok(());
+        }
+        let parent = std::mem::replace(&mut self.environment, environment);
+        let result = clauses.iter().try_for_each(|clause| {
+            if self.eval(clause)?.to_boolean() {
+                return Ok(());
+            }
+            let span = match clause {
+                Expr::Grouping { span, .. } => span.clone(),
+                _ => Span::default(),
+            };
+            let error =
+                Error::Contract(format!("{kind} clause of \"{function}\" failed: {clause}"));
+            Err(ErrorInfo::new_with_span(error, span))
+        });
         self.environment = parent;
         result
     }
+
+    // Fails with a TypeError when strict types are on and `value` lacks the
+    // type `annotation`. `what` names the value in the message.
+    pub(crate) fn check_type(
+        &self,
+        value: &Object,
+        annotation: &str,
+        what: impl FnOnce() -> String,
+    ) -> Result<(), Error> {
+        if !self.strict_types || value.has_type(annotation) {
+            return Ok(());
+        }
+        Err(Error::Type(format!(
+            "{} should be {annotation}, got {}",
+            what(),
+            value.annotated_type()
+        )))
+    }
+
+    pub fn exec_block(
+        &mut self,
+        stmts: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), ErrorInfo> {
+        self.exec_block_from(stmts, 0, environment)
+    }
+
+    // Runs `stmts` from `from` on like `exec_block`, for a block a suspended
+    // run resumes in the middle of.
+    fn exec_block_from(
+        &mut self,
+        stmts: &[Stmt],
+        from: usize,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), ErrorInfo> {
+        let parent = std::mem::replace(&mut self.environment, environment);
+        self.scopes.push(parent);
+        let result = self.exec_stmts(stmts, from);
+        self.environment = self.scopes.pop().expect("pushed above");
+        result
+    }
+
+    // Runs `stmts` from `from` on, noting the one a suspending native
+    // unwound through, see `suspend`.
+    fn exec_stmts(&mut self, stmts: &[Stmt], from: usize) -> Result<(), ErrorInfo> {
+        for (index, stmt) in stmts.iter().enumerate().skip(from) {
+            let mark = self.unwinding.len();
+            self.exec(stmt)
+                .map_err(|err| self.unwound_stmt(index, stmt, mark, err))?;
+        }
+        Ok(())
+    }
 }
 
 impl Default for Interpretor {
@@ -77,3 +1017,195 @@ impl Default for Interpretor {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::{Error, Interpretor, Lexer, Object, Parser, Program, Resolver};
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_warm_reuse() {
+        let mut interpretor = Interpretor::buffered();
+        let library = "class Counter { init() { this.n = 0; } bump() { this.n = this.n + 1; return this.n; } }";
+        interpretor.run(&parse(library)).unwrap();
+
+        interpretor
+            .run(&parse("let c = Counter(); c.bump(); print c.bump();"))
+            .unwrap();
+        assert_eq!(interpretor.take_output(), "2\n");
+
+        let err = interpretor
+            .run(&parse(
+                "print \"partial\"; let broken = missing; print \"unreached\";",
+            ))
+            .unwrap_err();
+        assert_eq!(err.error, Error::Name("missing".to_string()));
+        assert_eq!(interpretor.take_output(), "partial\n");
+
+        let err = interpretor.run(&parse("broken;")).unwrap_err();
+        assert_eq!(err.error, Error::Name("broken".to_string()));
+        interpretor.run(&parse("print c.bump();")).unwrap();
+        assert_eq!(interpretor.take_output(), "3\n");
+        assert_eq!(interpretor.take_output(), "");
+    }
+
+    #[test]
+    fn test_undeclared_names() {
+        let mut interpretor = Interpretor::buffered();
+        interpretor.run(&parse("let a; print a;")).unwrap();
+        assert_eq!(interpretor.take_output(), "nil\n");
+
+        for input in ["print b;", "let x = 1;\nb = 2;"] {
+            let err = interpretor.run(&parse(input)).unwrap_err();
+            assert_eq!(err.error, Error::Name("b".to_string()), "{input}");
+            assert_eq!(
+                (err.line(), err.span().end - err.span().start),
+                (input.matches('\n').count() + 1, 1)
+            );
+        }
+        assert!(interpretor.globals.borrow_mut().get("b").is_err());
+
+        interpretor.implicit_globals = true;
+        interpretor
+            .run(&parse("fn f() { b = 2; } f(); print b;"))
+            .unwrap();
+        assert_eq!(interpretor.take_output(), "2\n");
+    }
+
+    #[test]
+    fn test_params() {
+        let params = |pairs: Vec<(&str, Object)>| {
+            let pairs = pairs
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value));
+            pairs.collect::<HashMap<_, _>>()
+        };
+        // quotes in a value are just text, never source
+        let input = "let total = 0;
+while (total < @{limit}) total = total + @{step};
+print total;
+print \"hello \" + @{name};
+let (first, second) = @{items};
+print second;
+fn later() { return @{step}; }";
+        let program = parse(input);
+        assert_eq!(Resolver::new().resolve(&program), Ok(()));
+        let mut interpretor = Interpretor::buffered();
+        let values = params(vec![
+            ("limit", Object::Number(250.0)),
+            ("step", Object::Number(2.5)),
+            ("name", Object::String("\"; print 1; \"".into())),
+            (
+                "items",
+                Object::Tuple(["a", "b"].map(|s| Object::String(s.into())).into()),
+            ),
+        ]);
+        interpretor.run_with_params(&program, values).unwrap();
+        assert_eq!(interpretor.take_output(), "250\nhello \"; print 1; \"\nb\n");
+
+        // params last as long as their run
+        let err = interpretor.run(&parse("later();")).unwrap_err();
+        let message = "@{step} needs a value, but the program was run without params";
+        assert_eq!(err.error, Error::Runtime(message.to_string()));
+        assert_eq!(err.line(), 7);
+
+        let err = interpretor
+            .run_with_params(
+                &parse("print 1;\nprint @{stop};"),
+                params(vec![("step", Object::Nil)]),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Runtime("no param for @{stop}".to_string())
+        );
+        assert_eq!((err.line(), err.span().end - err.span().start), (2, 7));
+        assert_eq!(err.hint(), Some("did you mean 'step'?"));
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let hint = |input: &str| {
+            let mut interpretor = Interpretor::buffered();
+            let err = interpretor.run(&parse(input)).unwrap_err();
+            err.hint().map(str::to_owned)
+        };
+        let did_you_mean = |name: &str| Some(format!("did you mean '{name}'?"));
+        let classes = "class Base { total() { return 1; } } class Point < Base { init() { this.width = 1; } } let p = Point();";
+        assert_eq!(
+            hint(&format!("{classes} print p.widht;")),
+            did_you_mean("width")
+        );
+        assert_eq!(hint(&format!("{classes} p.totl();")), did_you_mean("total"));
+        assert_eq!(hint(&format!("{classes} print p.height;")), None);
+        assert_eq!(
+            hint("let count = 0; fn f() { let local = 1; { return locl + count; } } f();"),
+            did_you_mean("local")
+        );
+        assert_eq!(
+            hint("let count = 0; fn f() { cont = 1; } f();"),
+            did_you_mean("count")
+        );
+        assert_eq!(hint("fn f() { return amount; } f();"), None);
+    }
+
+    #[test]
+    fn test_arity_span() {
+        let source = |input: &str| {
+            let mut interpretor = Interpretor::buffered();
+            let err = interpretor.run(&parse(input)).unwrap_err();
+            let span = err.span();
+            input[span.start..span.end].to_string()
+        };
+        let functions =
+            "fn add(a, b) { return a + b; } class P { at(x) { return x; } } let p = P(); ";
+        let call = |input: &str| source(&format!("{functions}{input}"));
+        assert_eq!(call("add(1, 2, 3);"), "add(1, 2, 3)");
+        assert_eq!(call("print add(1);"), "add(1)");
+        assert_eq!(call("p.at(\n  1,\n  2\n);"), "p.at(\n  1,\n  2\n)");
+        assert_eq!(call("print 1 + (add)(1);"), "(add)(1)");
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut interpretor = Interpretor::buffered();
+        interpretor.run(&parse("let a = 1;")).unwrap();
+        interpretor.clear();
+        let err = interpretor.run(&parse("print a;")).unwrap_err();
+        assert_eq!(err.error, Error::Name("a".to_string()));
+        interpretor.run(&parse("print to_fixed(1, 1);")).unwrap();
+        assert_eq!(interpretor.take_output(), "1.0\n");
+        interpretor.run(&parse("print sum((1, 2));")).unwrap();
+        assert_eq!(interpretor.take_output(), "3\n");
+    }
+
+    #[test]
+    fn test_prelude() {
+        let mut interpretor = Interpretor::buffered();
+        assert!(interpretor.native_names().contains(&"repeat".to_string()));
+        // running the prelude is not the script's work
+        assert_eq!(interpretor.stats().statements, 0);
+        interpretor
+            .run(&parse("fn repeat(s) { return s; }"))
+            .unwrap();
+        assert!(!interpretor.native_names().contains(&"repeat".to_string()));
+
+        let mut interpretor = Interpretor::buffered().no_prelude();
+        let mut natives: Vec<_> = super::native_names().map(str::to_string).collect();
+        natives.sort();
+        assert_eq!(interpretor.global_names(), natives);
+        assert_eq!(interpretor.native_names(), natives);
+        let err = interpretor.run(&parse("sum((1, 2));")).unwrap_err();
+        assert_eq!(err.error, Error::Name("sum".to_string()));
+        // clearing does not bring it back
+        interpretor.clear();
+        assert_eq!(interpretor.global_names(), natives);
+    }
+}
@@ -0,0 +1,635 @@
+use std::{rc::Rc, time::Duration};
+
+use super::{builder, exact, reflect, template, testing, LogLevel};
+use crate::object::{format, Function, NativeFn};
+use crate::parser::terminated;
+use crate::{fold_constants, lower, numfmt, Lexer, Parser, Resolver, Stmt};
+use crate::{Environment, Error, ErrorInfo, Interpretor, Object, Span};
+
+// name, parameter names, how many of them are required, whether more arguments
+// may follow them, implementation
+type Native = (&'static str, &'static [&'static str], usize, bool, NativeFn);
+
+const NATIVES: [Native; 37] = [
+    ("print", &[], 0, true, print),
+    ("repr_full", &["value"], 1, false, repr_full),
+    ("fail", &[], 0, true, raise),
+    ("eval", &["code"], 1, false, eval),
+    ("is_callable", &["value"], 1, false, is_callable),
+    ("compose", &["f", "g"], 2, false, compose),
+    ("partial", &["f"], 1, true, partial),
+    ("time", &["label", "function"], 0, false, time),
+    ("sleep", &["ms"], 1, false, sleep),
+    ("trace", &["label"], 1, false, trace),
+    ("log_debug", &["message"], 1, false, log_debug),
+    ("log_info", &["message"], 1, false, log_info),
+    ("log_warn", &["message"], 1, false, log_warn),
+    ("log_error", &["message"], 1, false, log_error),
+    ("freeze", &["value"], 1, false, freeze),
+    ("is_frozen", &["value"], 1, false, is_frozen),
+    ("to_fixed", &["x", "digits"], 2, false, to_fixed),
+    ("to_precision", &["x", "sig"], 2, false, to_precision),
+    ("div_checked", &["a", "b"], 2, false, exact::div_checked),
+    ("add_exact", &["a", "b"], 2, false, exact::add_exact),
+    ("mul_exact", &["a", "b"], 2, false, exact::mul_exact),
+    ("to_cents", &["x"], 1, false, exact::to_cents),
+    ("from_cents", &["n"], 1, false, exact::from_cents),
+    ("format", &["template"], 1, true, template::format),
+    ("print_table", &["rows"], 1, false, template::print_table),
+    ("fields", &["obj"], 1, false, reflect::fields),
+    ("has_field", &["obj", "name"], 2, false, reflect::has_field),
+    ("get_field", &["obj", "name"], 2, false, reflect::get_field),
+    (
+        "set_field",
+        &["obj", "name", "value"],
+        3,
+        false,
+        reflect::set_field,
+    ),
+    ("methods", &["value"], 1, false, reflect::methods),
+    ("StringBuilder", &["capacity"], 0, false, builder::new),
+    ("test", &["name", "function"], 2, false, testing::test),
+    ("before_each", &["function"], 1, false, testing::before_each),
+    (
+        "expect_eq",
+        &["actual", "expected"],
+        2,
+        false,
+        testing::expect_eq,
+    ),
+    ("expect_true", &["value"], 1, false, testing::expect_true),
+    (
+        "expect_throws",
+        &["function"],
+        1,
+        false,
+        testing::expect_throws,
+    ),
+    ("run_tests", &[], 0, false, testing::run_tests),
+];
+
+// Natives only the code `lower` writes calls. They live apart from the
+// globals, so reflection and completion never list them, and their names
+// have a `.` so no script can spell them.
+const INTERNAL: [Native; 1] = [
+    // called by the loops `lower` makes of `for (let i = a to b step s)`
+    ("for.step", &["step"], 1, false, for_step),
+];
+
+/// The names `register` defines.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    NATIVES.iter().map(|native| native.0)
+}
+
+/// Defines the built-in functions in `globals`. They are ordinary bindings, so
+/// a script can shadow any of them with its own definition.
+pub fn register(globals: &mut Environment) {
+    for (name, params, required, variadic, func) in NATIVES {
+        define(globals, name, params, required, variadic, func);
+    }
+}
+
+pub fn define(
+    globals: &mut Environment,
+    name: &str,
+    params: &'static [&'static str],
+    required: usize,
+    variadic: bool,
+    func: NativeFn,
+) {
+    let function = native(name, params, required, variadic, func);
+    globals.define(name.to_string(), function, false).unwrap();
+}
+
+/// The internal native called `name`, looked up when no binding has that
+/// name.
+pub(super) fn internal(name: &str) -> Option<Object> {
+    let &(name, params, required, variadic, func) =
+        INTERNAL.iter().find(|native| native.0 == name)?;
+    Some(native(name, params, required, variadic, func))
+}
+
+fn native(
+    name: &str,
+    params: &'static [&'static str],
+    required: usize,
+    variadic: bool,
+    func: NativeFn,
+) -> Object {
+    Object::Function(Rc::new(Function::Inbuilt {
+        name: name.to_string(),
+        arity: params.len(),
+        params,
+        required,
+        variadic,
+        func: Box::new(func),
+    }))
+}
+
+fn fail(error: Error, span: &Span) -> ErrorInfo {
+    ErrorInfo::new_with_span(error, span.to_owned())
+}
+
+fn number(name: &str, value: &Object, span: &Span) -> Result<f64, ErrorInfo> {
+    match value {
+        Object::Number(n) => Ok(*n),
+        x => Err(fail(
+            Error::Type(format!("{name} must be a number, not {x}")),
+            span,
+        )),
+    }
+}
+
+/// Writes `values` on one line, separated by spaces, each cut down to the
+/// interpreter's `print_limits`. Shared by the `print` native and the `print`
+/// statement.
+pub fn print_values(interpretor: &mut Interpretor, values: &[Object]) {
+    let limits = interpretor.print_limits;
+    let shown = values
+        .iter()
+        .map(|value| format::limited(value, limits))
+        .collect::<Vec<_>>();
+    interpretor.write_output(&shown.join(" "));
+}
+
+/// `for.step(step)` is `step`, checked to be a number that moves a counting
+/// loop along.
+fn for_step(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    if number("step", &args[0], span)? == 0.0 {
+        let error = Error::Value("for loop step must not be zero".to_string());
+        return Err(fail(error, span));
+    }
+    Ok(args
+        .into_iter()
+        .next()
+        .expect("for.step takes one argument"))
+}
+
+/// `repr_full(value)` is `value` as `print` would show it without limits.
+fn repr_full(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::String(args[0].to_string().as_str().into()))
+}
+
+fn join(values: &[Object]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `fail(values...)` stops the script with a RuntimeError whose message is
+/// `values` as `print` would write them.
+fn raise(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    Err(fail(Error::Runtime(join(&args)), span))
+}
+
+fn is_callable(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let callable = matches!(args[0], Object::Function(_) | Object::Class(_));
+    Ok(Object::Boolean(callable))
+}
+
+/// A function calling `g` with its arguments and then `f` with what `g`
+/// returned, so `compose(f, g)(x)` is `f(g(x))`.
+fn compose(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    for (name, value) in ["f", "g"].iter().zip(&args) {
+        callable(name, value, span)?;
+    }
+    Ok(bound("compose", args, composed))
+}
+
+fn composed(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let mut args = args.into_iter();
+    let Some(Object::Tuple(functions)) = args.next() else {
+        unreachable!("bound to the functions composed")
+    };
+    let inner = interpretor.call_value(functions[1].clone(), args.collect(), Vec::new(), span)?;
+    interpretor.call_value(functions[0].clone(), vec![inner], Vec::new(), span)
+}
+
+/// A function calling `f` with the arguments given here followed by its own,
+/// so `partial(f, a)(b)` is `f(a, b)`.
+fn partial(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    callable("f", &args[0], span)?;
+    Ok(bound("partial", args, applied))
+}
+
+fn applied(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let mut args = args.into_iter();
+    let Some(Object::Tuple(bound)) = args.next() else {
+        unreachable!("bound to the function and its first arguments")
+    };
+    let arguments = bound[1..].iter().cloned().chain(args).collect();
+    interpretor.call_value(bound[0].clone(), arguments, Vec::new(), span)
+}
+
+// A function taking any arguments, which `func` gets after `captured`.
+fn bound(name: &'static str, captured: Vec<Object>, func: NativeFn) -> Object {
+    Object::Function(Rc::new(Function::Method {
+        name,
+        receiver: Object::Tuple(captured.into()),
+        params: &[],
+        required: 0,
+        variadic: true,
+        func: Box::new(func),
+    }))
+}
+
+pub(super) fn callable(name: &str, value: &Object, span: &Span) -> Result<(), ErrorInfo> {
+    if matches!(value, Object::Function(_) | Object::Class(_)) {
+        return Ok(());
+    }
+    let error = Error::Type(format!("{name} must be callable, not {value}"));
+    Err(fail(error, span))
+}
+
+/// Runs `code` against the globals and returns the value of its last
+/// statement if that is an expression, nil otherwise. An error in the code is
+/// reported at the call, with a note pointing into the code.
+fn eval(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    if !interpretor.allow_eval {
+        let error = Error::Runtime("eval is disabled".to_string());
+        return Err(fail(error, span));
+    }
+    let Object::String(code) = &args[0] else {
+        let error = Error::Type(format!("code must be a string, not {}", args[0]));
+        return Err(fail(error, span));
+    };
+    let in_eval = |err: ErrorInfo| {
+        let inner = err.span().clone();
+        let mut outer = fail(err.error.clone(), span);
+        for (message, span) in err.notes() {
+            outer = outer.with_note(message.clone(), span.clone());
+        }
+        outer.with_note("in <eval>".to_string(), inner)
+    };
+
+    // like a line at the REPL, the code may end in a bare expression
+    let program = Parser::new(Lexer::new(terminated(code)))
+        .parse_program()
+        .map_err(in_eval)?;
+    Resolver::with_natives(interpretor.native_names())
+        .with_existing_globals(interpretor.global_names())
+        .resolve(&program)
+        .map_err(in_eval)?;
+    let mut program = lower(program);
+    fold_constants(&mut program);
+
+    let last = match program.stmts.last() {
+        Some(Stmt::Expr { .. }) => program.stmts.pop(),
+        _ => None,
+    };
+    let globals = interpretor.globals.clone();
+    let environment = std::mem::replace(&mut interpretor.environment, globals);
+    let result = (|| {
+        for stmt in &program.stmts {
+            interpretor.exec(stmt)?;
+        }
+        match &last {
+            Some(Stmt::Expr { expr, .. }) => interpretor.eval(expr),
+            _ => Ok(Object::Nil),
+        }
+    })();
+    interpretor.environment = environment;
+    result.map_err(in_eval)
+}
+
+fn print(interpretor: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    print_values(interpretor, &args);
+    Ok(Object::Nil)
+}
+
+/// `time()` is the clock reading in nanoseconds. `time(label, function)` calls
+/// `function`, prints how long it took and returns its result.
+fn time(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    if !interpretor.allow_clock {
+        let error = Error::Runtime("clock access is disabled".to_string());
+        return Err(fail(error, span));
+    }
+    let mut args = args.into_iter();
+    let (label, function) = (args.next().unwrap(), args.next().unwrap());
+    if label.is_nil() && function.is_nil() {
+        return Ok(Object::Number(interpretor.clock.now().as_nanos() as f64));
+    }
+    callable("function", &function, span)?;
+
+    let start = interpretor.clock.now();
+    let result = interpretor.call_value(function, Vec::new(), Vec::new(), span)?;
+    let elapsed = interpretor.clock.now().saturating_sub(start);
+    let millis = numfmt::shortest(elapsed.as_secs_f64() * 1000.0);
+    interpretor.write_output(&format!("{label}: {millis}ms"));
+    Ok(result)
+}
+
+/// Waits `ms` milliseconds on the interpreter's clock, which for a virtual
+/// clock only moves it forward. A run with a timeout wakes at its deadline
+/// instead when that comes first, and fails.
+fn sleep(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let ms = number("ms", &args[0], span)?;
+    if !(ms >= 0.0 && ms.is_finite()) {
+        let error = Error::Value(format!(
+            "ms must be a finite number of at least 0, not {}",
+            args[0]
+        ));
+        return Err(fail(error, span));
+    }
+    interpretor
+        .sleep(Duration::from_secs_f64(ms / 1000.0))
+        .map_err(|e| fail(e, span))?;
+    Ok(Object::Nil)
+}
+
+/// Prints `label` with the line of the call and a counter that grows with
+/// every trace, so interleaved traces can be told apart.
+fn trace(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    interpretor.trace_count += 1;
+    let line = format!(
+        "[trace {}] {} (line {})",
+        interpretor.trace_count, args[0], span.line
+    );
+    interpretor.write_output(&line);
+    Ok(Object::Nil)
+}
+
+// Hands `message` to the interpreter's logger, unless `level` is below the
+// interpreter's `log_level`.
+fn log(
+    interpretor: &mut Interpretor,
+    level: LogLevel,
+    message: &Object,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    if !interpretor.allow_log {
+        let error = Error::Runtime("logging is disabled".to_string());
+        return Err(fail(error, span));
+    }
+    if level >= interpretor.log_level {
+        let message = format::limited(message, interpretor.print_limits);
+        interpretor.logger.log(level, &message, span.line);
+    }
+    Ok(Object::Nil)
+}
+
+fn log_debug(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    log(interpretor, LogLevel::Debug, &args[0], span)
+}
+
+fn log_info(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    log(interpretor, LogLevel::Info, &args[0], span)
+}
+
+fn log_warn(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    log(interpretor, LogLevel::Warn, &args[0], span)
+}
+
+fn log_error(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    log(interpretor, LogLevel::Error, &args[0], span)
+}
+
+// Only instances carry state that can change; every other value is already
+// immutable, so freezing it is a no-op and it always reports as frozen.
+fn freeze(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    if let Object::Instance(instance) = &args[0] {
+        instance.borrow_mut().freeze();
+    }
+    Ok(args[0].clone())
+}
+
+fn is_frozen(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::Boolean(match &args[0] {
+        Object::Instance(instance) => instance.borrow().is_frozen(),
+        _ => true,
+    }))
+}
+
+pub(super) fn to_fixed(
+    _: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let x = number("x", &args[0], span)?;
+    let digits = number("digits", &args[1], span)?;
+    numfmt::digits(digits, "digits", 0)
+        .and_then(|digits| numfmt::fixed(x, digits))
+        .map(|text| Object::String(text.into()))
+        .map_err(|e| fail(e, span))
+}
+
+pub(super) fn to_precision(
+    _: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let x = number("x", &args[0], span)?;
+    let sig = number("sig", &args[1], span)?;
+    numfmt::digits(sig, "sig", 1)
+        .and_then(|sig| numfmt::precision(x, sig))
+        .map(|text| Object::String(text.into()))
+        .map_err(|e| fail(e, span))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, cell::RefCell, io, rc::Rc, time::Duration};
+
+    use crate::{Clock, Interpretor, Lexer, LogLevel, Parser, ScriptLogger};
+
+    // Every reading is 1.5ms after the previous one.
+    struct FakeClock(Cell<Duration>);
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            let now = self.0.get() + Duration::from_micros(1500);
+            self.0.set(now);
+            now
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Capture(Rc<RefCell<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run(input: &str, allow_clock: bool) -> String {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let capture = Capture::default();
+        let mut interpretor = Interpretor::with_output(Box::new(capture.clone()));
+        interpretor.set_clock(FakeClock(Cell::new(Duration::ZERO)));
+        interpretor.allow_clock = allow_clock;
+        for stmt in &program.stmts {
+            if let Err(err) = interpretor.exec(stmt) {
+                interpretor.write_output(&err.error.to_string());
+            }
+        }
+        let output = capture.0.borrow();
+        String::from_utf8(output.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_time() {
+        let input = "
+        print time();
+        fn work() { print \"working\"; return 42; }
+        print time(\"work\", work);
+        ";
+        assert_eq!(run(input, true), "1500000\nworking\nwork: 1.5ms\n42\n");
+    }
+
+    #[test]
+    fn test_time_disabled() {
+        let input = "time(); time(\"x\", time);";
+        let expected = "RuntimeError: clock access is disabled\n".repeat(2);
+        assert_eq!(run(input, false), expected);
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        let input = "let a = 1;\neval(\"print a;\nprint -nil;\");\neval(\"let = 2;\");";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::buffered();
+        let errors: Vec<_> = program
+            .stmts
+            .iter()
+            .filter_map(|stmt| interpretor.exec(stmt).err())
+            .map(|err| {
+                let notes: Vec<_> = err
+                    .notes()
+                    .iter()
+                    .map(|(message, span)| (message.clone(), span.line))
+                    .collect();
+                (err.error.to_string(), err.line(), notes)
+            })
+            .collect();
+        assert_eq!(interpretor.take_output(), "1\n");
+        assert_eq!(
+            errors,
+            [
+                (
+                    "TypeError: bad operand type for unary \"-\": nil".to_string(),
+                    2,
+                    vec![("in <eval>".to_string(), 2)]
+                ),
+                (
+                    "SyntaxError: Expected: \"Identifier\" Found: \"=\"".to_string(),
+                    4,
+                    vec![("in <eval>".to_string(), 1)]
+                ),
+            ]
+        );
+
+        interpretor.allow_eval = false;
+        let program = Parser::new(Lexer::new("eval(\"1;\");".to_string()))
+            .parse_program()
+            .unwrap();
+        let err = interpretor.run(&program).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "RuntimeError: eval is disabled, line 1, pos 0"
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let input = "
+        trace(\"start\");
+        print \"between\";
+        fn f() { trace(\"inside\"); }
+        f();
+        ";
+        let expected = "[trace 1] start (line 2)\nbetween\n[trace 2] inside (line 4)\n";
+        assert_eq!(run(input, true), expected);
+    }
+
+    #[derive(Clone, Default)]
+    struct Recording(Rc<RefCell<Vec<(LogLevel, String, usize)>>>);
+
+    impl ScriptLogger for Recording {
+        fn log(&self, level: LogLevel, message: &str, line: usize) {
+            self.0.borrow_mut().push((level, message.to_string(), line));
+        }
+    }
+
+    #[test]
+    fn test_log() {
+        let input = "log_debug(\"start\");\nprint \"out\";\n\
+                     fn f(x) { log_warn((x, \"odd\")); }\nf(1);\nlog_info(2.5);\nlog_error(nil);";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let recording = Recording::default();
+        let mut interpretor = Interpretor::buffered();
+        interpretor.set_logger(recording.clone());
+        interpretor.log_level = LogLevel::Info;
+        interpretor.run(&program).unwrap();
+        // only what is printed is output, and debug is below the level
+        assert_eq!(interpretor.take_output(), "out\n");
+        assert_eq!(
+            *recording.0.borrow(),
+            [
+                (LogLevel::Warn, "(1, odd)".to_string(), 3),
+                (LogLevel::Info, "2.5".to_string(), 5),
+                (LogLevel::Error, "nil".to_string(), 6),
+            ]
+        );
+
+        interpretor.allow_log = false;
+        let err = interpretor.run(&program).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "RuntimeError: logging is disabled, line 1, pos 0"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::rc::Rc;
+
+use super::natives;
+use crate::object::{Function, NativeFn};
+use crate::{numfmt, Error, ErrorInfo, Interpretor, Object, Span};
+
+// name, parameter names, how many of them are required, implementation
+type Method = (&'static str, &'static [&'static str], usize, NativeFn);
+
+// `to_fixed` and `to_precision` are the natives of the same name, with the
+// number as their first argument, so the two spellings always agree.
+const METHODS: [Method; 6] = [
+    ("abs", &[], 0, abs),
+    ("floor", &[], 0, floor),
+    ("round", &["digits"], 0, round),
+    ("to_string", &["base"], 0, to_string),
+    ("to_fixed", &["digits"], 1, natives::to_fixed),
+    ("to_precision", &["sig"], 1, natives::to_precision),
+];
+
+// Integers above this are not all representable, so their digits in another
+// base would be made up.
+pub(super) const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// The names `method` finds.
+pub fn method_names() -> impl Iterator<Item = &'static str> {
+    METHODS.into_iter().map(|method| method.0)
+}
+
+/// Looks up a method on a number, e.g. `x.floor`, bound to `number`.
+pub fn method(number: &Object, name: &str) -> Result<Object, Error> {
+    let Some((name, params, required, func)) = METHODS.into_iter().find(|method| method.0 == name)
+    else {
+        return Err(Error::Attribute(format!("undefined property \"{name}\"")));
+    };
+    Ok(Object::Function(Rc::new(Function::Method {
+        name,
+        receiver: number.clone(),
+        params,
+        required,
+        variadic: false,
+        func: Box::new(func),
+    })))
+}
+
+fn receiver(args: &[Object]) -> f64 {
+    match args[0] {
+        Object::Number(n) => n,
+        _ => unreachable!("number methods are only bound to numbers"),
+    }
+}
+
+fn fail(error: Error, span: &Span) -> ErrorInfo {
+    ErrorInfo::new_with_span(error, span.to_owned())
+}
+
+fn abs(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::Number(receiver(&args).abs()))
+}
+
+fn floor(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(Object::Number(receiver(&args).floor()))
+}
+
+/// The number rounded to `digits` decimals, 0 by default. It is the number
+/// `to_fixed` shows, so a half rounds the way that does.
+fn round(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let n = receiver(&args);
+    let digits = match &args[1] {
+        Object::Nil => 0,
+        Object::Number(digits) => {
+            numfmt::digits(*digits, "digits", 0).map_err(|e| fail(e, span))?
+        }
+        digits => {
+            let error = Error::Type(format!("digits must be a number, not {digits}"));
+            return Err(fail(error, span));
+        }
+    };
+    let text = numfmt::fixed(n, digits).map_err(|e| fail(e, span))?;
+    Ok(Object::Number(numfmt::parse(&text).unwrap_or(n)))
+}
+
+/// The number as `print` shows it or, given a base from 2 to 36, its digits
+/// in that base with lowercase letters past 9. Only integers up to 2^53 - 1
+/// either way can be written in another base.
+fn to_string(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let n = receiver(&args);
+    let base = match &args[1] {
+        Object::Nil => return Ok(Object::String(numfmt::shortest(n).into())),
+        Object::Number(base) if base.fract() == 0.0 && (2.0..=36.0).contains(base) => *base as u32,
+        Object::Number(base) => {
+            let error = Error::Value(format!("base must be an integer from 2 to 36, not {base}"));
+            return Err(fail(error, span));
+        }
+        base => {
+            let error = Error::Type(format!("base must be a number, not {base}"));
+            return Err(fail(error, span));
+        }
+    };
+    if n.fract() != 0.0 || n.abs() > MAX_SAFE_INTEGER {
+        let error = Error::Value(format!(
+            "only integers up to 2^53 - 1 can be written in base {base}, not {}",
+            numfmt::shortest(n)
+        ));
+        return Err(fail(error, span));
+    }
+    let mut value = n.abs() as u64;
+    let mut digits = Vec::new();
+    loop {
+        let digit = char::from_digit((value % base as u64) as u32, base).expect("digit below base");
+        digits.push(digit);
+        value /= base as u64;
+        if value == 0 {
+            break;
+        }
+    }
+    if n < 0.0 {
+        digits.push('-');
+    }
+    let text: String = digits.into_iter().rev().collect();
+    Ok(Object::String(text.into()))
+}
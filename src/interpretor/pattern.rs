@@ -0,0 +1,77 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    ast::{MatchArm, Pattern},
+    Environment, Error, ErrorInfo, Interpretor, LiteralType, Object, Span,
+};
+
+impl Interpretor {
+    // The body of the first arm whose pattern fits `value` and whose guard
+    // holds. Each arm runs in its own scope holding what its pattern binds.
+    pub(super) fn match_arms(
+        &mut self,
+        value: Object,
+        arms: &[MatchArm],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        for arm in arms {
+            let mut bindings = Vec::new();
+            if !bind(&arm.pattern, &value, &mut bindings) {
+                continue;
+            }
+            let mut environment = Environment::new_from_closure(&self.environment);
+            self.stats.environments += 1;
+            for (name, value) in bindings {
+                environment
+                    .define(name.to_string(), value, false)
+                    .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+            }
+            let parent =
+                std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+            let result = self.arm(arm);
+            self.environment = parent;
+            if let Some(value) = result? {
+                return Ok(value);
+            }
+        }
+        let error = Error::Runtime(format!("no match arm fits {value}"));
+        Err(ErrorInfo::new_with_span(error, span.to_owned()))
+    }
+
+    fn arm(&mut self, arm: &MatchArm) -> Result<Option<Object>, ErrorInfo> {
+        if let Some(guard) = &arm.guard {
+            if !self.eval(guard)?.to_boolean() {
+                return Ok(None);
+            }
+        }
+        self.eval(&arm.body).map(Some)
+    }
+}
+
+// Whether `value` fits `pattern`, collecting what it binds into `out`.
+fn bind<'a>(pattern: &'a Pattern, value: &Object, out: &mut Vec<(&'a str, Object)>) -> bool {
+    match pattern {
+        Pattern::Wildcard => true,
+        Pattern::Bind(name) => {
+            out.push((name, value.clone()));
+            true
+        }
+        Pattern::Literal(literal) => match (literal, value) {
+            (LiteralType::Number(l, _), Object::Number(r)) => l == r,
+            (LiteralType::String(l), Object::String(r)) => r == l.as_str(),
+            (LiteralType::Boolean(l), Object::Boolean(r)) => l == r,
+            (LiteralType::Nil, Object::Nil) => true,
+            _ => false,
+        },
+        // fields only: methods are not destructured
+        Pattern::Fields(fields) => {
+            let Object::Instance(instance) = value else {
+                return false;
+            };
+            fields.iter().all(|(name, pattern)| {
+                let field = instance.borrow().field(name);
+                field.is_some_and(|field| bind(pattern, &field, out))
+            })
+        }
+    }
+}
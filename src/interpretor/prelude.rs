@@ -0,0 +1,74 @@
+use std::sync::OnceLock;
+
+use super::natives;
+use crate::{fold_constants, lower, Lexer, Parser, Program, Resolver, Stmt};
+
+const SOURCE: &str = include_str!("prelude.lang");
+
+// The prelude's node ids start far above any script's, so what is kept per
+// node, such as coverage counts, never mixes the two.
+const FIRST_ID: u32 = 1 << 30;
+
+thread_local! {
+    static PROGRAM: Program = parse();
+}
+
+/// The prelude, ready to run. It is parsed once per thread, as every
+/// interpreter runs it.
+pub fn program() -> Program {
+    PROGRAM.with(Program::clone)
+}
+
+// The prelude ships with the crate, so anything wrong with it is a bug in
+// the crate rather than in a script, and panics; the crate's own tests build
+// interpreters and catch it there.
+fn parse() -> Program {
+    let mut parser = Parser::new(Lexer::new(SOURCE.to_string())).with_first_id(FIRST_ID);
+    let program = match parser.parse_program() {
+        Ok(program) => program,
+        Err(err) => broken(err),
+    };
+    let mut resolver = Resolver::with_natives(natives::names());
+    if let Err(err) = resolver.resolve(&program) {
+        broken(err);
+    }
+    if let Some(warning) = parser.warnings().iter().chain(resolver.warnings()).next() {
+        broken(warning);
+    }
+    let mut program = lower(program);
+    fold_constants(&mut program);
+    program
+}
+
+/// The names the prelude defines, in the order it defines them.
+pub fn names() -> &'static [String] {
+    static NAMES: OnceLock<Vec<String>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        let program = program();
+        let names = program.stmts.iter().filter_map(|stmt| match stmt {
+            Stmt::Function { name, .. } => Some(name.clone()),
+            _ => None,
+        });
+        names.collect()
+    })
+}
+
+pub fn broken(problem: impl std::fmt::Display) -> ! {
+    panic!("the prelude is broken: {problem}")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Interpretor, Object};
+
+    #[test]
+    fn test_names() {
+        let names = super::names();
+        assert!(names.len() >= 8, "{names:?}");
+        let interpretor = Interpretor::buffered();
+        for name in names {
+            let value = interpretor.globals.borrow_mut().get(name).unwrap();
+            assert!(matches!(value, Object::Function(_)), "{name}");
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::object::{Function, Instance, NativeFn};
+use crate::{Error, ErrorInfo, Interpretor, Object, Span};
+
+// name, implementation; none of them take arguments
+const METHODS: [(&str, NativeFn); 5] = [
+    ("name", name),
+    ("arity", arity),
+    ("max_arity", max_arity),
+    ("params", params),
+    ("doc", doc),
+];
+
+/// The names `method` finds.
+pub fn method_names() -> impl Iterator<Item = &'static str> {
+    METHODS.into_iter().map(|method| method.0)
+}
+
+/// Looks up a reflection method on a function or class, e.g. `f.arity`,
+/// bound to `callable`.
+pub fn method(callable: &Object, name: &str) -> Result<Object, Error> {
+    let Some((name, func)) = METHODS.into_iter().find(|method| method.0 == name) else {
+        return Err(Error::Attribute(format!("undefined property \"{name}\"")));
+    };
+    Ok(Object::Function(Rc::new(Function::Method {
+        name,
+        receiver: callable.clone(),
+        params: &[],
+        required: 0,
+        variadic: false,
+        func: Box::new(func),
+    })))
+}
+
+// A class is called through its `init`, or with no arguments without one.
+fn signature(callable: &Object) -> Option<Rc<Function>> {
+    match callable {
+        Object::Function(function) => Some(function.clone()),
+        Object::Class(class) => class.find_method("init"),
+        _ => unreachable!("reflection methods are only bound to callables"),
+    }
+}
+
+fn name(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(match &args[0] {
+        Object::Function(function) => Object::String(function.name().into()),
+        Object::Class(class) => class
+            .name
+            .as_deref()
+            .map_or(Object::Nil, |name| Object::String(name.into())),
+        _ => unreachable!("reflection methods are only bound to callables"),
+    })
+}
+
+/// The fewest arguments a call may pass.
+fn arity(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let arity = signature(&args[0]).map_or(0, |function| function.min_arity());
+    Ok(Object::Number(arity as f64))
+}
+
+/// The most arguments a call may pass, or nil when there is no limit.
+fn max_arity(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    Ok(match signature(&args[0]) {
+        Some(function) => function
+            .max_arity()
+            .map_or(Object::Nil, |n| Object::Number(n as f64)),
+        None => Object::Number(0.0),
+    })
+}
+
+/// The names of the parameters as a tuple of strings, empty for natives
+/// that do not name theirs.
+fn params(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let params = signature(&args[0]).map_or(Vec::new(), |function| {
+        let params = function.params().into_iter();
+        params.map(|param| Object::String(param.into())).collect()
+    });
+    Ok(Object::Tuple(params.into()))
+}
+
+/// The doc string of a function or class, or nil without one.
+fn doc(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    let doc = match &args[0] {
+        Object::Function(function) => function.doc(),
+        Object::Class(class) => class.doc.as_deref(),
+        _ => unreachable!("reflection methods are only bound to callables"),
+    };
+    Ok(doc.map_or(Object::Nil, |doc| Object::String(doc.into())))
+}
+
+// The natives below look into instances and classes by name, for code that
+// does not know the shape of an object up front, e.g. a serializer. Private
+// members stay hidden from them wherever they are called: `fields` and
+// `methods` leave them out, `has_field` reports them missing, and
+// `get_field` and `set_field` fail as `obj.name` would outside the class.
+
+fn instance(value: &Object, span: &Span) -> Result<Rc<RefCell<Instance>>, ErrorInfo> {
+    match value {
+        Object::Instance(instance) => Ok(instance.clone()),
+        value => Err(fail(
+            Error::Type(format!("obj must be an instance, not {value}")),
+            span,
+        )),
+    }
+}
+
+fn field_name<'a>(value: &'a Object, span: &Span) -> Result<&'a str, ErrorInfo> {
+    match value {
+        Object::String(name) => Ok(name),
+        value => Err(fail(
+            Error::Type(format!("name must be a string, not {value}")),
+            span,
+        )),
+    }
+}
+
+fn fail(error: Error, span: &Span) -> ErrorInfo {
+    ErrorInfo::new_with_span(error, span.to_owned())
+}
+
+/// The names of the fields set on `obj`, sorted, as a tuple of strings.
+pub fn fields(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let instance = instance(&args[0], span)?;
+    let instance = instance.borrow();
+    let mut names: Vec<_> = instance
+        .field_names()
+        .filter(|name| instance.class.private_owner(name).is_none())
+        .collect();
+    names.sort_unstable();
+    let names = names.into_iter().map(|name| Object::String(name.into()));
+    Ok(Object::Tuple(names.collect()))
+}
+
+pub fn has_field(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let instance = instance(&args[0], span)?;
+    let name = field_name(&args[1], span)?;
+    let instance = instance.borrow();
+    let visible = instance.class.private_owner(name).is_none();
+    Ok(Object::Boolean(visible && instance.field(name).is_some()))
+}
+
+/// The field `name` of `obj`. Methods are not fields, so they are not found.
+pub fn get_field(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let instance = instance(&args[0], span)?;
+    let name = field_name(&args[1], span)?;
+    let instance = instance.borrow();
+    interpretor.check_private(false, &instance.class, name, span)?;
+    instance
+        .check_initialized(name)
+        .map_err(|e| fail(e, span))?;
+    instance.field(name).ok_or_else(|| {
+        let error = Error::Attribute(format!("undefined field \"{name}\""));
+        let visible = instance
+            .field_names()
+            .filter(|name| instance.class.private_owner(name).is_none());
+        fail(error, span).with_suggestion(name, visible)
+    })
+}
+
+/// Sets the field `name` of `obj` to `value` and returns the value, like an
+/// assignment.
+pub fn set_field(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let instance = instance(&args[0], span)?;
+    let name = field_name(&args[1], span)?;
+    interpretor.check_private(false, &instance.borrow().class, name, span)?;
+    instance
+        .borrow_mut()
+        .set(name, args[2].clone())
+        .map_err(|err| fail(err, span))?;
+    Ok(args[2].clone())
+}
+
+/// The methods a class, or the class of an instance, answers to: a tuple of
+/// `(name, inherited)` pairs sorted by name, where `inherited` is true for a
+/// method found on a superclass. An overridden method is listed once.
+pub fn methods(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let class = match &args[0] {
+        Object::Class(class) => class.clone(),
+        Object::Instance(instance) => instance.borrow().class.clone(),
+        value => {
+            let error = Error::Type(format!("value must be a class or an instance, not {value}"));
+            return Err(fail(error, span));
+        }
+    };
+    let mut methods: Vec<(&str, bool)> = Vec::new();
+    let mut current = Some(&class);
+    while let Some(owner) = current {
+        for name in owner.methods.keys() {
+            let seen = methods.iter().any(|(seen, _)| seen == name);
+            if !seen && class.private_owner(name).is_none() {
+                methods.push((name, !Rc::ptr_eq(owner, &class)));
+            }
+        }
+        current = owner.super_class.as_ref();
+    }
+    methods.sort_unstable();
+    let methods = methods.into_iter().map(|(name, inherited)| {
+        let pair = [Object::String(name.into()), Object::Boolean(inherited)];
+        Object::Tuple(Rc::from(pair))
+    });
+    Ok(Object::Tuple(methods.collect()))
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+
+use crate::Interpretor;
+
+/// Counters the interpreter keeps as it runs, from `Interpretor::stats`.
+/// They count from the interpreter's creation or the last `reset_stats`.
+/// Tests and benchmarks assert on them, so what each one counts stays as
+/// documented here.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Stats {
+    /// Statements run, once per run: a loop body counts on every pass, and a
+    /// block counts as well as each statement in it.
+    pub statements: u64,
+    /// Expressions evaluated, each subexpression on its own.
+    pub expressions: u64,
+    /// Calls to functions written in the script, methods and the `init` a
+    /// class call runs included.
+    pub script_calls: u64,
+    /// Calls to natives, the methods of built-in values included.
+    pub native_calls: u64,
+    /// The most calls in progress at once.
+    pub max_call_depth: u64,
+    /// Environments made for blocks, calls, match arms, class bodies and
+    /// methods bound to an instance.
+    pub environments: u64,
+    /// Instances made by calling a class.
+    pub instances: u64,
+    /// Functions made by declarations, class methods and methods bound to
+    /// an instance.
+    pub functions: u64,
+    /// Classes made by declarations and class expressions.
+    pub classes: u64,
+    /// Tuples made by tuple expressions.
+    pub tuples: u64,
+    /// Method lookups that walked the class chain.
+    pub chain_walks: u64,
+    /// Method lookups answered by the cache of the `.name` expression.
+    pub cache_hits: u64,
+    /// The bytes the values the script can reach took when last measured,
+    /// estimated. It is measured at the end of each run, and during one as
+    /// the script allocates.
+    pub heap_bytes: u64,
+    /// The most `heap_bytes` has been.
+    pub peak_heap_bytes: u64,
+}
+
+impl Interpretor {
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Sets every counter back to 0, e.g. to measure one run of several.
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+}
+
+/// One counter per line, names left and counts right aligned.
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows = [
+            ("statements", self.statements),
+            ("expressions", self.expressions),
+            ("script calls", self.script_calls),
+            ("native calls", self.native_calls),
+            ("max call depth", self.max_call_depth),
+            ("environments", self.environments),
+            ("instances", self.instances),
+            ("functions", self.functions),
+            ("classes", self.classes),
+            ("tuples", self.tuples),
+            ("chain walks", self.chain_walks),
+            ("cache hits", self.cache_hits),
+            ("heap bytes", self.heap_bytes),
+            ("peak heap bytes", self.peak_heap_bytes),
+        ];
+        let width = rows.iter().map(|(_, n)| n.to_string().len()).max();
+        for (name, n) in rows {
+            writeln!(f, "{name:<16}{n:>width$}", width = width.unwrap_or(0))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Stats;
+    use crate::{Interpretor, Lexer, Parser};
+
+    fn run(interpretor: &mut Interpretor, input: &str) {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        interpretor.run(&program).unwrap();
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut interpretor = Interpretor::buffered();
+        let input = "fn add(a, b) { return a + b; }
+let total = 0;
+let i = 0;
+while (i < 3) {
+    total = add(total, i);
+    i = i + 1;
+}
+print total;";
+        run(&mut interpretor, input);
+        assert_eq!(interpretor.take_output(), "3\n");
+        // 4 at the top, 3 per pass of the loop body and its block, 1 per
+        // `return`, and the print
+        let stats = interpretor.stats();
+        assert_eq!(stats.statements, 4 + 3 * 3 + 3 + 1);
+        // a `print` statement is not a call
+        assert_eq!((stats.script_calls, stats.native_calls), (3, 0));
+        assert_eq!(stats.max_call_depth, 1);
+        // one per loop pass and one per call
+        assert_eq!(stats.environments, 6);
+        assert_eq!(stats.functions, 1);
+
+        interpretor.reset_stats();
+        assert_eq!(interpretor.stats(), Stats::default());
+        let input = "class Point {
+    init(x, y) { this.x = x; this.y = y; }
+    sum() { return this.x + this.y; }
+}
+fn twice(f) { return (f(), f()); }
+let pair = twice(Point(1, 2).sum);
+print pair[0].abs();";
+        run(&mut interpretor, input);
+        assert_eq!(interpretor.take_output(), "3\n");
+        let stats = interpretor.stats();
+        // 4 at the top, then the bodies of init, twice and sum twice
+        assert_eq!(stats.statements, 4 + 2 + 1 + 2);
+        // init, twice and sum twice; abs
+        assert_eq!((stats.script_calls, stats.native_calls), (4, 1));
+        assert_eq!(stats.max_call_depth, 2);
+        assert_eq!((stats.instances, stats.classes, stats.tuples), (1, 1, 1));
+        // the two methods, `twice`, and `init` and `sum` once bound
+        assert_eq!(stats.functions, 5);
+    }
+}
@@ -1,13 +1,18 @@
 use std::{cell::RefCell, rc::Rc};
 
+use super::{natives, tuple};
 use crate::{
-    object::Function, visitor, Environment, Error, ErrorInfo, Expr, Interpretor, Object, Span, Stmt,
+    object::Function, visitor, Environment, Error, ErrorInfo, Expr, FieldInit, ImportForm,
+    Interpretor, Object, Signature, Span, Stmt,
 };
 
 impl visitor::Stmt for Interpretor {
-    fn visit_print_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo> {
-        let out = self.eval(expr)?;
-        println!("{}", out);
+    fn visit_print_stmt(&mut self, exprs: &[Expr]) -> Result<(), ErrorInfo> {
+        let mut values = Vec::with_capacity(exprs.len());
+        for expr in exprs {
+            values.push(self.eval(expr)?);
+        }
+        natives::print_values(self, &values);
         Ok(())
     }
 
@@ -18,32 +23,76 @@ impl visitor::Stmt for Interpretor {
 
     fn visit_let_stmt(
         &mut self,
-        name: &String,
-        value: &Option<Expr>,
+        name: &str,
+        annotation: Option<&str>,
+        value: Option<&Expr>,
         is_const: bool,
         span: &Span,
     ) -> Result<(), ErrorInfo> {
-        let value = value
-            .as_ref()
-            .map(|v| self.eval(v))
-            .unwrap_or(Ok(Object::Nil))?;
+        let value = value.map(|value| self.eval(value)).transpose()?;
+        self.define_let(name, annotation, value, is_const, span)
+    }
 
-        self.environment
-            .borrow_mut()
-            .define(name.to_owned(), value, is_const)
-            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
+    fn visit_destructure_stmt(
+        &mut self,
+        names: &[String],
+        value: &Expr,
+        is_const: bool,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let value = self.eval(value)?;
+        let fail = |e| ErrorInfo::new_with_span(e, span.to_owned());
+        let values = tuple::unpack(value, names.len()).map_err(fail)?;
+        let mut environment = self.environment.borrow_mut();
+        for (name, value) in names.iter().zip(values.iter()) {
+            environment
+                .define(name.to_owned(), value.clone(), is_const)
+                .map_err(fail)?;
+        }
+        Ok(())
     }
 
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &Box<Stmt>) -> Result<(), ErrorInfo> {
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<(), ErrorInfo> {
         let mut flag = self.eval(condition)?;
         while flag.to_boolean() {
-            self.exec(body)?;
+            let mark = self.unwinding.len();
+            // an if or while runs its statement as a list of one
+            self.exec(body).map_err(|err| {
+                let err = self.unwound_stmt(0, body, mark, err);
+                self.unwound_branch(0, true, err)
+            })?;
             flag = self.eval(condition)?;
         }
         Ok(())
     }
 
-    fn visit_return_stmt(&mut self, value: &Option<Expr>, span: &Span) -> Result<(), ErrorInfo> {
+    // `lower` turns for loops into while loops, so the interpreter only runs
+    // the core statements
+    fn visit_for_stmt(
+        &mut self,
+        _initializer: Option<&Stmt>,
+        _condition: Option<&Expr>,
+        _increment: Option<&Expr>,
+        _body: &Stmt,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let error = Error::Internal("for loop was not lowered before running".to_string());
+        Err(ErrorInfo::new_with_span(error, span.clone()))
+    }
+
+    fn visit_for_range_stmt(
+        &mut self,
+        _initializer: &Stmt,
+        _end: &Expr,
+        _step: Option<&Expr>,
+        _body: &Stmt,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let error = Error::Internal("for loop was not lowered before running".to_string());
+        Err(ErrorInfo::new_with_span(error, span.clone()))
+    }
+
+    fn visit_return_stmt(&mut self, value: Option<&Expr>, span: &Span) -> Result<(), ErrorInfo> {
         Err(ErrorInfo::new_with_span(
             Error::Return(if let Some(expr) = value {
                 self.eval(expr)?
@@ -53,66 +102,168 @@ impl visitor::Stmt for Interpretor {
             span.to_owned(),
         ))
     }
-    fn visit_block_stmt(&mut self, stmts: &Vec<Stmt>) -> Result<(), ErrorInfo> {
+
+    fn visit_block_stmt(&mut self, stmts: &[Stmt]) -> Result<(), ErrorInfo> {
+        self.stats.environments += 1;
         self.exec_block(
             stmts,
             Rc::new(RefCell::new(Environment::new_from_closure(
                 &self.environment,
             ))),
         )
+        .map_err(|err| self.unwound_branch(0, false, err))
     }
 
     fn visit_if_stmt(
         &mut self,
         condition: &Expr,
-        truthy: &Box<Stmt>,
-        falsy: &Option<Box<Stmt>>,
+        truthy: &Stmt,
+        falsy: Option<&Stmt>,
     ) -> Result<(), ErrorInfo> {
-        if self.eval(condition)?.to_boolean() {
-            self.exec(truthy)
-        } else if let Some(expr) = falsy {
-            self.exec(expr)
-        } else {
-            Ok(())
-        }
+        let (child, stmt) = match self.eval(condition)?.to_boolean() {
+            true => (0, truthy),
+            false => match falsy {
+                Some(stmt) => (1, stmt),
+                None => return Ok(()),
+            },
+        };
+        let mark = self.unwinding.len();
+        self.exec(stmt).map_err(|err| {
+            let err = self.unwound_stmt(0, stmt, mark, err);
+            self.unwound_branch(child, false, err)
+        })
     }
 
     fn visit_function_stmt(
         &mut self,
-        name: &String,
-        params: &Vec<String>,
-        body: &Vec<Stmt>,
+        name: &str,
+        params: &[String],
+        signature: &Signature,
+        requires: &[Expr],
+        ensures: &[Expr],
+        body: &[Stmt],
+        doc: Option<&str>,
         span: &Span,
     ) -> Result<(), ErrorInfo> {
         let function = Function::User {
             name: name.to_owned(),
             span: span.to_owned(),
             params: params.to_owned(),
+            signature: signature.to_owned(),
+            requires: requires.to_owned(),
+            ensures: ensures.to_owned(),
             body: body.to_owned(),
+            doc: doc.map(str::to_owned),
             closure: self.environment.clone(),
             is_initializer: false,
         };
+        self.stats.functions += 1;
         self.environment
             .borrow_mut()
-            .define(name.to_owned(), Object::Function(function), false)
+            .define(name.to_owned(), Object::Function(Rc::new(function)), false)
             .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
     }
 
     fn visit_class_stmt(
         &mut self,
-        name: &String,
-        super_class: &Option<String>,
-        methods: &Vec<Stmt>,
+        name: &str,
+        super_class: Option<&Expr>,
+        mixins: &[Expr],
+        methods: &[Stmt],
+        fields: &[FieldInit],
+        private: &[String],
+        doc: Option<&str>,
         span: &Span,
     ) -> Result<(), ErrorInfo> {
-        todo!();
+        let class = self.class(
+            Some(name),
+            super_class,
+            mixins,
+            methods,
+            fields,
+            private,
+            doc,
+            span,
+        )?;
+        self.environment
+            .borrow_mut()
+            .define(name.to_owned(), Object::Class(class), false)
+            .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))
     }
 
-    fn visit_break_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo> {
+    fn visit_break_stmt(&mut self, _span: &Span) -> Result<(), ErrorInfo> {
         todo!();
     }
 
-    fn visit_continue_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo> {
+    fn visit_continue_stmt(&mut self, _span: &Span) -> Result<(), ErrorInfo> {
         todo!();
     }
+
+    fn visit_defer_stmt(&mut self, body: &Stmt, span: &Span) -> Result<(), ErrorInfo> {
+        let environment = self.environment.clone();
+        match self.defers.last_mut() {
+            Some(frame) => {
+                frame.push((body.clone(), environment));
+                Ok(())
+            }
+            None => Err(ErrorInfo::new_with_span(
+                Error::Runtime("defer outside of a function".to_string()),
+                span.to_owned(),
+            )),
+        }
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        specifier: &str,
+        form: &ImportForm,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        self.import(specifier, form, span)
+    }
+
+    fn visit_empty_stmt(&mut self) -> Result<(), ErrorInfo> {
+        Ok(())
+    }
+
+    fn visit_error_stmt(&mut self, span: &Span) -> Result<(), ErrorInfo> {
+        let error = Error::Syntax("this statement failed to parse".to_string());
+        Err(ErrorInfo::new_with_span(error, span.to_owned()))
+    }
+}
+
+impl Interpretor {
+    // Binds what a `let` evaluated to, which a suspended call to a native
+    // may only hand over once the run is resumed.
+    pub(super) fn define_let(
+        &mut self,
+        name: &str,
+        annotation: Option<&str>,
+        value: Option<Object>,
+        is_const: bool,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let fail = |e| ErrorInfo::new_with_span(e, span.to_owned());
+        let annotation = annotation.filter(|_| self.strict_types);
+        // a `let` without a value is left nil until it is assigned
+        let value = match value {
+            Some(value) => {
+                if let Some(annotation) = annotation {
+                    self.check_type(&value, annotation, || format!("\"{name}\""))
+                        .map_err(fail)?;
+                }
+                value
+            }
+            None => Object::Nil,
+        };
+
+        let mut environment = self.environment.borrow_mut();
+        environment
+            .define(name.to_owned(), value, is_const)
+            .map_err(fail)?;
+        if let Some(annotation) = annotation {
+            environment.annotate(name, annotation);
+        }
+        Ok(())
+    }
 }
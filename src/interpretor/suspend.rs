@@ -0,0 +1,873 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::object::Function;
+use crate::{Environment, Error, ErrorInfo, Expr, Interpretor, Object, Span, Stmt};
+
+/// How a call to `Interpretor::run` or `Interpretor::resume` ended.
+#[derive(Debug)]
+pub enum RunState {
+    Done,
+    /// A native is waiting on the host. The token is the one the native
+    /// passed to `Interpretor::pending`.
+    Suspended(u64, ResumeHandle),
+}
+
+/// Completes the operation a suspended native is waiting on. Call `resume`
+/// or `resume_err`, then `Interpretor::resume` to carry on with the script.
+#[derive(Debug, Clone)]
+pub struct ResumeHandle(Rc<RefCell<Option<Result<Object, String>>>>);
+
+impl ResumeHandle {
+    /// The native call returns `value`.
+    pub fn resume(&self, value: Object) {
+        *self.0.borrow_mut() = Some(Ok(value));
+    }
+
+    /// The native call fails with a runtime error carrying `message`.
+    pub fn resume_err(&self, message: String) {
+        *self.0.borrow_mut() = Some(Err(message));
+    }
+}
+
+const CANNOT_SUSPEND: &str =
+    "a native can only suspend as a whole statement or the value of a let, assignment or return, and not in a callback";
+
+type Env = Rc<RefCell<Environment>>;
+
+// What is left to do once the host completes the operation.
+pub(super) struct Suspension {
+    handle: ResumeHandle,
+    frames: Vec<Frame>,
+    span: Span,
+}
+
+// Where a statement list is: the statements of a program or function, then
+// for each step in `path` the statement at that index and the child of it
+// at the next, see `children`. The statements are shared, never copied.
+#[derive(Clone)]
+pub(super) struct Place {
+    root: Root,
+    path: Vec<usize>,
+}
+
+#[derive(Clone)]
+enum Root {
+    Program(Rc<[Stmt]>),
+    Function(Rc<Function>),
+}
+
+impl Place {
+    fn new(root: Root) -> Place {
+        Place {
+            root,
+            path: Vec::new(),
+        }
+    }
+
+    // The statements of a place whose path has even length.
+    fn stmts(&self) -> &[Stmt] {
+        self.root.stmts(&self.path)
+    }
+
+    // The statement of a place whose path has odd length.
+    fn stmt(&self) -> &Stmt {
+        let (index, path) = self.path.split_last().expect("a statement has an index");
+        &self.root.stmts(path)[*index]
+    }
+
+    fn child(&self, step: usize) -> Place {
+        let mut path = self.path.clone();
+        path.push(step);
+        Place {
+            root: self.root.clone(),
+            path,
+        }
+    }
+}
+
+impl Root {
+    fn stmts(&self, path: &[usize]) -> &[Stmt] {
+        let mut stmts = match self {
+            Root::Program(stmts) => stmts,
+            Root::Function(function) => function.body(),
+        };
+        for step in path.chunks(2) {
+            stmts = children(&stmts[step[0]], step[1]);
+        }
+        stmts
+    }
+}
+
+// The statements `stmt` runs as its child `child`: those of a block, the
+// branches of an if or the body of a while.
+fn children(stmt: &Stmt, child: usize) -> &[Stmt] {
+    match (stmt, child) {
+        (Stmt::Block { stmts, .. }, _) => stmts,
+        (Stmt::If { truthy, .. }, 0) => std::slice::from_ref(truthy),
+        (
+            Stmt::If {
+                falsy: Some(falsy), ..
+            },
+            _,
+        ) => std::slice::from_ref(falsy),
+        (Stmt::While { body, .. }, _) => std::slice::from_ref(body),
+        _ => unreachable!("only blocks, ifs and whiles have children"),
+    }
+}
+
+// A layer the error of a suspending native unwound through, pushed onto
+// `Interpretor::unwinding` innermost first. Once it reaches the top the
+// layers become the frames of its `Suspension`.
+pub(super) enum Unwound {
+    // the statement at `index` of a list running in `environment`
+    Stmt {
+        index: usize,
+        environment: Env,
+        target: Target,
+    },
+    // an if, while or block statement whose child `child` suspended
+    Branch {
+        child: usize,
+        environment: Env,
+        is_loop: bool,
+    },
+    // the body of a script function, with what it deferred so far
+    Body {
+        environment: Env,
+        defers: Vec<(Stmt, Env)>,
+    },
+    Call(Call),
+}
+
+// A call to a script function, to finish once the run is resumed.
+pub(super) struct Call {
+    function: Rc<Function>,
+    environment: Env,
+    defers: Vec<(Stmt, Env)>,
+    // the callee's name for the hooks, if there are any
+    name: Option<String>,
+    from_prelude: bool,
+    to_prelude: bool,
+    span: Span,
+}
+
+// What the statement a suspended call was made from does with its value.
+pub(super) enum Target {
+    Discard,
+    Let {
+        name: String,
+        annotation: Option<String>,
+        is_const: bool,
+        span: Span,
+    },
+    Assign {
+        name: String,
+        span: Span,
+    },
+    Return(Span),
+    // the statement is an if, while or block the call was made within
+    Nested,
+}
+
+// What is left of a suspended run, innermost last.
+pub(super) enum Frame {
+    // hands the value of the call over to its statement
+    Deliver {
+        target: Target,
+        environment: Env,
+    },
+    // runs the statements of `place` from `from` on
+    Rest {
+        place: Place,
+        from: usize,
+        environment: Env,
+    },
+    // runs the while loop at `place` again
+    Repeat {
+        place: Place,
+        environment: Env,
+    },
+    Return(Call),
+}
+
+// How the frames finished so far did.
+enum Outcome {
+    Value(Object),
+    Returning(Object, Span),
+    Failed(ErrorInfo),
+}
+
+impl Interpretor {
+    /// The error a native returns to suspend the script until the host
+    /// completes an operation, e.g. `return Err(interpretor.pending(7, span))`.
+    ///
+    /// The interpreter runs scripts on the Rust stack, so it can only pick up
+    /// where it left off when the native call, and each script function call
+    /// it was made within, is a whole statement or the value of a `let`,
+    /// assignment or `return`. The statements around it may be blocks, ifs
+    /// and loops. Anywhere else, including in functions natives call back,
+    /// the call fails with a runtime error.
+    pub fn pending(&self, token: u64, span: &Span) -> ErrorInfo {
+        if self.native_depth > 1 {
+            let error = Error::Runtime(CANNOT_SUSPEND.to_string());
+            return ErrorInfo::new_with_span(error, span.to_owned());
+        }
+        ErrorInfo::new_with_span(Error::Suspend(token), span.to_owned())
+    }
+
+    /// Whether a run is waiting to be resumed.
+    pub fn is_suspended(&self) -> bool {
+        self.suspension.is_some()
+    }
+
+    /// Carries on with the script after the host has completed the operation
+    /// through the handle returned with `RunState::Suspended`.
+    pub fn resume(&mut self) -> Result<RunState, ErrorInfo> {
+        let Some(suspension) = self.suspension.take() else {
+            let error = Error::Runtime("nothing to resume".to_string());
+            return Err(ErrorInfo::new_with_span(error, Span::default()));
+        };
+        let result = suspension.handle.0.borrow_mut().take();
+        let outcome = match result {
+            Some(Ok(value)) => Outcome::Value(value),
+            Some(Err(message)) => {
+                let error = Error::Runtime(message);
+                Outcome::Failed(ErrorInfo::new_with_span(error, suspension.span))
+            }
+            None => {
+                let error = Error::Runtime("resumed before the operation completed".to_string());
+                let span = suspension.span.clone();
+                self.suspension = Some(suspension);
+                return Err(ErrorInfo::new_with_span(error, span));
+            }
+        };
+        let result = self.timed(|interpretor| interpretor.run_frames(suspension.frames, outcome));
+        // the params of `run_with_params` last until its run is finished
+        if !matches!(result, Ok(RunState::Suspended(..))) {
+            self.params = None;
+        }
+        result
+    }
+
+    pub(crate) fn run_stmts(&mut self, stmts: &[Stmt]) -> Result<RunState, ErrorInfo> {
+        if let Some(suspension) = &self.suspension {
+            let error = Error::Runtime("already suspended, resume first".to_string());
+            let err = ErrorInfo::new_with_span(error, suspension.span.clone());
+            return Err(self.failed(err));
+        }
+        self.unwinding.clear();
+        let err = match self.exec_stmts(stmts, 0) {
+            Ok(()) => return Ok(RunState::Done),
+            Err(err) => err,
+        };
+        let Error::Suspend(token) = err.error else {
+            return Err(self.failed(err));
+        };
+        // copied once, then shared however often the run suspends again
+        let place = Place::new(Root::Program(stmts.into()));
+        let frames = self.continuation(0, place);
+        Ok(self.suspend(token, frames, err.span().clone()))
+    }
+
+    // Runs what is left of a suspended run, starting from how its innermost
+    // frame's call ended.
+    fn run_frames(
+        &mut self,
+        mut frames: Vec<Frame>,
+        mut outcome: Outcome,
+    ) -> Result<RunState, ErrorInfo> {
+        self.unwinding.clear();
+        for frame in &mut frames {
+            if let Frame::Return(call) = frame {
+                self.reenter(call);
+            }
+        }
+        self.resuming = frames;
+        while let Some(frame) = self.resuming.pop() {
+            let (place, result) = match (frame, outcome) {
+                (
+                    Frame::Deliver {
+                        target,
+                        environment,
+                    },
+                    Outcome::Value(value),
+                ) => {
+                    outcome = self.deliver(target, environment, value);
+                    continue;
+                }
+                (Frame::Return(call), finished) => {
+                    outcome = self.finish_call(call, finished);
+                    continue;
+                }
+                (
+                    Frame::Rest {
+                        place,
+                        from,
+                        environment,
+                    },
+                    Outcome::Value(_),
+                ) => {
+                    let result = self.exec_block_from(place.stmts(), from, environment);
+                    (place, result)
+                }
+                (Frame::Repeat { place, environment }, Outcome::Value(_)) => {
+                    let parent = std::mem::replace(&mut self.environment, environment);
+                    let result = self.exec(place.stmt());
+                    self.environment = parent;
+                    // only the loop's body can suspend
+                    let result = result.map_err(|err| match self.unwinding.last() {
+                        Some(Unwound::Branch { .. }) => err,
+                        _ => self.no_suspend(0, err),
+                    });
+                    (place, result)
+                }
+                // a frame the run returned or failed past
+                (_, finished) => {
+                    outcome = finished;
+                    continue;
+                }
+            };
+            outcome = match result {
+                Ok(()) => Outcome::Value(Object::Nil),
+                Err(err) => match &err.error {
+                    &Error::Suspend(token) => {
+                        let mut frames = std::mem::take(&mut self.resuming);
+                        for frame in frames.iter_mut().rev() {
+                            if let Frame::Return(call) = frame {
+                                self.leave(call);
+                            }
+                        }
+                        frames.extend(self.continuation(0, place));
+                        return Ok(self.suspend(token, frames, err.span().clone()));
+                    }
+                    Error::Return(value) => Outcome::Returning(value.clone(), err.span().clone()),
+                    _ => Outcome::Failed(err),
+                },
+            };
+        }
+        match outcome {
+            Outcome::Value(_) => Ok(RunState::Done),
+            Outcome::Returning(value, span) => {
+                let err = ErrorInfo::new_with_span(Error::Return(value), span);
+                Err(self.failed(err))
+            }
+            Outcome::Failed(err) => Err(self.failed(err)),
+        }
+    }
+
+    fn suspend(&mut self, token: u64, frames: Vec<Frame>, span: Span) -> RunState {
+        let handle = ResumeHandle(Rc::new(RefCell::new(None)));
+        self.suspension = Some(Suspension {
+            handle: handle.clone(),
+            frames,
+            span,
+        });
+        RunState::Suspended(token, handle)
+    }
+
+    // Turns the layers unwound since `mark` into frames, the outermost
+    // running the statements of `place`.
+    fn continuation(&mut self, mark: usize, mut place: Place) -> Vec<Frame> {
+        let mut frames = Vec::new();
+        for unwound in self.unwinding.split_off(mark).into_iter().rev() {
+            match unwound {
+                Unwound::Stmt {
+                    index,
+                    environment,
+                    target,
+                } => {
+                    frames.push(Frame::Rest {
+                        place: place.clone(),
+                        from: index + 1,
+                        environment: environment.clone(),
+                    });
+                    if !matches!(target, Target::Nested) {
+                        frames.push(Frame::Deliver {
+                            target,
+                            environment,
+                        });
+                    }
+                    place = place.child(index);
+                }
+                Unwound::Branch {
+                    child,
+                    environment,
+                    is_loop,
+                } => {
+                    if is_loop {
+                        frames.push(Frame::Repeat {
+                            place: place.clone(),
+                            environment,
+                        });
+                    }
+                    place = place.child(child);
+                }
+                Unwound::Call(call) => {
+                    place = Place::new(Root::Function(call.function.clone()));
+                    frames.push(Frame::Return(call));
+                }
+                Unwound::Body { .. } => unreachable!("a body is unwound through its call"),
+            }
+        }
+        frames
+    }
+
+    // Puts a suspended call back in progress, as `call_limited` had it.
+    fn reenter(&mut self, call: &mut Call) {
+        self.call_depth += 1;
+        self.frames.push(call.to_prelude);
+        self.defers.push(std::mem::take(&mut call.defers));
+    }
+
+    // Undoes `reenter` for a call suspended again.
+    fn leave(&mut self, call: &mut Call) {
+        call.defers = self.defers.pop().unwrap_or_default();
+        self.frames.pop();
+        self.call_depth -= 1;
+    }
+
+    fn deliver(&mut self, target: Target, environment: Env, value: Object) -> Outcome {
+        let parent = std::mem::replace(&mut self.environment, environment);
+        let result = match target {
+            Target::Discard | Target::Nested => Ok(()),
+            Target::Let {
+                name,
+                annotation,
+                is_const,
+                span,
+            } => self.define_let(&name, annotation.as_deref(), Some(value), is_const, &span),
+            Target::Assign { name, span } => self.assign(&name, value, &span).map(drop),
+            Target::Return(span) => {
+                self.environment = parent;
+                return Outcome::Returning(value, span);
+            }
+        };
+        self.environment = parent;
+        match result {
+            Ok(()) => Outcome::Value(Object::Nil),
+            Err(err) => Outcome::Failed(err),
+        }
+    }
+
+    // Does what `call_limited` and `exec_frame` left undone when `call`
+    // suspended, now that its body has finished with `outcome`.
+    fn finish_call(&mut self, call: Call, outcome: Outcome) -> Outcome {
+        let outcome = match outcome {
+            Outcome::Value(_) => Ok(()),
+            Outcome::Returning(value, site) => {
+                Err(ErrorInfo::new_with_span(Error::Return(value), site))
+            }
+            Outcome::Failed(err) => Err(err),
+        };
+        let defers = self.defers.pop().unwrap_or_default();
+        let outcome = self.run_defers(defers, outcome);
+        let mark = self.unwinding.len();
+        let result = call
+            .function
+            .finish(self, call.environment, outcome)
+            .map_err(|err| self.no_suspend(mark, err));
+        self.frames.pop();
+        let result = result
+            .map_err(|err| self.prelude_error(err, call.from_prelude, call.to_prelude, &call.span));
+        if let (Some(hooks), Some(name)) = (&mut self.hooks, &call.name) {
+            hooks.on_function_exit(name, self.call_depth);
+        }
+        self.call_depth -= 1;
+        match result {
+            Ok(value) => Outcome::Value(value),
+            Err(err) => Outcome::Failed(err),
+        }
+    }
+
+    // Notes that the statement at `index` of the list running `stmt` failed
+    // with `err`, which if a native suspended must be where it can resume.
+    pub(super) fn unwound_stmt(
+        &mut self,
+        index: usize,
+        stmt: &Stmt,
+        mark: usize,
+        err: ErrorInfo,
+    ) -> ErrorInfo {
+        if !matches!(err.error, Error::Suspend(_)) {
+            return err;
+        }
+        let target = match &self.unwinding[mark..] {
+            [] => target(stmt, err.span()),
+            [.., Unwound::Call(call)] => target(stmt, &call.span),
+            [.., Unwound::Branch { .. }] => {
+                let nested = matches!(
+                    stmt,
+                    Stmt::Block { .. } | Stmt::If { .. } | Stmt::While { .. }
+                );
+                nested.then_some(Target::Nested)
+            }
+            _ => None,
+        };
+        let Some(target) = target else {
+            return self.no_suspend(mark, err);
+        };
+        self.unwinding.push(Unwound::Stmt {
+            index,
+            environment: self.environment.clone(),
+            target,
+        });
+        err
+    }
+
+    // Notes that the child `child` of an if, while or block failed with
+    // `err`, after the list the child ran in did.
+    pub(super) fn unwound_branch(
+        &mut self,
+        child: usize,
+        is_loop: bool,
+        err: ErrorInfo,
+    ) -> ErrorInfo {
+        if matches!(err.error, Error::Suspend(_)) {
+            self.unwinding.push(Unwound::Branch {
+                child,
+                environment: self.environment.clone(),
+                is_loop,
+            });
+        }
+        err
+    }
+
+    // Notes that a function body running in `environment` suspended, which
+    // leaves its deferred statements for when it finishes.
+    pub(super) fn unwound_body(&mut self, environment: Env, defers: Vec<(Stmt, Env)>) {
+        self.unwinding.push(Unwound::Body {
+            environment,
+            defers,
+        });
+    }
+
+    // Notes that the call to `callee` at `span` failed with `err`, which if
+    // a native suspended must be a call to a script function.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn unwound_call(
+        &mut self,
+        mark: usize,
+        callee: &Object,
+        name: Option<String>,
+        from_prelude: bool,
+        to_prelude: bool,
+        span: &Span,
+        err: ErrorInfo,
+    ) -> ErrorInfo {
+        if !matches!(err.error, Error::Suspend(_)) {
+            return err;
+        }
+        let (Object::Function(function), [.., Unwound::Body { .. }]) =
+            (callee, &self.unwinding[mark..])
+        else {
+            return self.no_suspend(mark, err);
+        };
+        let function = function.clone();
+        let Some(Unwound::Body {
+            environment,
+            defers,
+        }) = self.unwinding.pop()
+        else {
+            unreachable!("matched above");
+        };
+        self.unwinding.push(Unwound::Call(Call {
+            function,
+            environment,
+            defers,
+            name,
+            from_prelude,
+            to_prelude,
+            span: span.clone(),
+        }));
+        err
+    }
+
+    // A native cannot suspend where `err` was raised, so if it tried the
+    // layers unwound since `mark` are dropped and it is a runtime error.
+    pub(super) fn no_suspend(&mut self, mark: usize, err: ErrorInfo) -> ErrorInfo {
+        if !matches!(err.error, Error::Suspend(_)) {
+            return err;
+        }
+        self.unwinding.truncate(mark);
+        let error = Error::Runtime(CANNOT_SUSPEND.to_string());
+        ErrorInfo::new_with_span(error, err.span().clone())
+    }
+
+    // The environments a suspended run holds on to, for `measure_heap`.
+    pub(super) fn suspended_environments(&self) -> Vec<Env> {
+        let mut environments = Vec::new();
+        let suspended = self.suspension.iter().flat_map(|s| &s.frames);
+        for frame in suspended.chain(&self.resuming) {
+            match frame {
+                Frame::Deliver { environment, .. }
+                | Frame::Rest { environment, .. }
+                | Frame::Repeat { environment, .. } => environments.push(environment.clone()),
+                Frame::Return(call) => {
+                    environments.push(call.environment.clone());
+                    environments.extend(call.defers.iter().map(|(_, env)| env.clone()));
+                }
+            }
+        }
+        for unwound in &self.unwinding {
+            match unwound {
+                Unwound::Stmt { environment, .. } | Unwound::Branch { environment, .. } => {
+                    environments.push(environment.clone())
+                }
+                Unwound::Body {
+                    environment,
+                    defers,
+                }
+                | Unwound::Call(Call {
+                    environment,
+                    defers,
+                    ..
+                }) => {
+                    environments.push(environment.clone());
+                    environments.extend(defers.iter().map(|(_, env)| env.clone()));
+                }
+            }
+        }
+        environments
+    }
+}
+
+// What `stmt` does with the value of the call at `span`, if it is a whole
+// statement or the value of a `let`, assignment or `return`.
+fn target(stmt: &Stmt, span: &Span) -> Option<Target> {
+    match stmt {
+        Stmt::Expr {
+            expr:
+                Expr::Assign {
+                    name,
+                    value,
+                    span: assigned,
+                    ..
+                },
+            ..
+        } if is_call_at(value, span) => Some(Target::Assign {
+            name: name.clone(),
+            span: assigned.clone(),
+        }),
+        Stmt::Expr { expr, .. } if is_call_at(expr, span) => Some(Target::Discard),
+        Stmt::Let {
+            name,
+            annotation,
+            value: Some(value),
+            is_const,
+            span: declared,
+            ..
+        } if is_call_at(value, span) => Some(Target::Let {
+            name: name.clone(),
+            annotation: annotation.clone(),
+            is_const: *is_const,
+            span: declared.clone(),
+        }),
+        Stmt::Return {
+            value: Some(value),
+            span: returned,
+            ..
+        } if is_call_at(value, span) => Some(Target::Return(returned.clone())),
+        _ => None,
+    }
+}
+
+// Whether `expr` is the call the suspending native was called from.
+fn is_call_at(expr: &Expr, span: &Span) -> bool {
+    matches!(expr, Expr::Call { span: call, .. } if call == span)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RunState, CANNOT_SUSPEND};
+    use crate::{Error, ErrorInfo, Interpretor, Lexer, Object, Parser, Program, Span};
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap()
+    }
+
+    // Stands in for a host function like `http_get`, with the argument as
+    // the token the host gets back.
+    fn fetch(
+        interpretor: &mut Interpretor,
+        args: Vec<Object>,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        match args[0] {
+            Object::Number(n) => Err(interpretor.pending(n as u64, span)),
+            _ => Ok(Object::Nil),
+        }
+    }
+
+    fn interpretor() -> Interpretor {
+        let mut interpretor = Interpretor::buffered();
+        interpretor.define_native("fetch", &["id"], 1, false, fetch);
+        interpretor
+    }
+
+    #[test]
+    fn test_resume_with_value() {
+        let mut interpretor = interpretor();
+        let program =
+            parse("print \"before\"; let body = fetch(7); print body; fetch(8); print \"done\";");
+        let RunState::Suspended(token, handle) = interpretor.run(&program).unwrap() else {
+            panic!("expected a suspension");
+        };
+        assert_eq!(token, 7);
+        assert_eq!(interpretor.take_output(), "before\n");
+
+        let err = interpretor.run(&parse("print 1;")).unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Runtime("already suspended, resume first".to_string())
+        );
+        let err = interpretor.resume().unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Runtime("resumed before the operation completed".to_string())
+        );
+
+        handle.resume(Object::String("hello".into()));
+        let RunState::Suspended(token, handle) = interpretor.resume().unwrap() else {
+            panic!("expected a second suspension");
+        };
+        assert_eq!(token, 8);
+        assert_eq!(interpretor.take_output(), "hello\n");
+        handle.resume(Object::Nil);
+        assert!(matches!(interpretor.resume().unwrap(), RunState::Done));
+        assert_eq!(interpretor.take_output(), "done\n");
+    }
+
+    #[test]
+    fn test_resume_with_error() {
+        let mut interpretor = interpretor();
+        let program = parse("let a = 1;\nlet body = fetch(1);\nprint \"unreached\";");
+        let RunState::Suspended(_, handle) = interpretor.run(&program).unwrap() else {
+            panic!("expected a suspension");
+        };
+        handle.resume_err("connection refused".to_string());
+        let err = interpretor.resume().unwrap_err();
+        assert_eq!(err.error, Error::Runtime("connection refused".to_string()));
+        assert_eq!(err.line(), 2);
+        assert_eq!(interpretor.take_output(), "");
+
+        // the failed run is over, so the interpreter takes new work
+        interpretor.run(&parse("print a;")).unwrap();
+        assert_eq!(interpretor.take_output(), "1\n");
+    }
+
+    #[test]
+    fn test_clear_drops_suspension() {
+        let mut interpretor = interpretor();
+        let program = parse("let body = fetch(1);\nprint \"unreached\";");
+        let RunState::Suspended(_, handle) = interpretor.run(&program).unwrap() else {
+            panic!("expected a suspension");
+        };
+        interpretor.clear();
+        assert!(!interpretor.is_suspended());
+        interpretor.run(&parse("print 1;")).unwrap();
+        assert_eq!(interpretor.take_output(), "1\n");
+        // the operation it waited on has nothing left to resume
+        handle.resume(Object::Nil);
+        let err = interpretor.resume().unwrap_err();
+        assert_eq!(err.error, Error::Runtime("nothing to resume".to_string()));
+    }
+
+    // Resumes each suspension with the token it was suspended on as a
+    // string, returning the tokens in order.
+    fn run_to_end(interpretor: &mut Interpretor, program: &Program) -> Vec<u64> {
+        let mut tokens = Vec::new();
+        let mut state = interpretor.run(program).unwrap();
+        while let RunState::Suspended(token, handle) = state {
+            tokens.push(token);
+            handle.resume(Object::String(token.to_string().into()));
+            state = interpretor.resume().unwrap();
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_resume_in_function() {
+        let mut interpretor = interpretor();
+        let program = parse(
+            "fn load(id) {
+                defer print \"closed\";
+                let body = fetch(id);
+                return body + \"!\";
+            }
+            fn both() { let a = load(1); let b = load(2); return a + b; }
+            let r = load(1);
+            print r;
+            let s = both();
+            print s;",
+        );
+        assert_eq!(run_to_end(&mut interpretor, &program), [1, 1, 2]);
+        assert_eq!(
+            interpretor.take_output(),
+            "closed\n1!\nclosed\nclosed\n1!2!\n"
+        );
+        assert!(!interpretor.is_suspended());
+        assert_eq!((interpretor.call_depth, interpretor.defers.len()), (0, 0));
+    }
+
+    #[test]
+    fn test_resume_in_loop() {
+        let mut interpretor = interpretor();
+        let program = parse(
+            "class Store { get(id) { return fetch(id); } }
+            let store = Store();
+            let i = 0;
+            while (i < 3) {
+                if (i != 1) {
+                    let body = store.get(i);
+                    print body;
+                }
+                i = i + 1;
+            }
+            print \"end\";",
+        );
+        assert_eq!(run_to_end(&mut interpretor, &program), [0, 2]);
+        assert_eq!(interpretor.take_output(), "0\n2\nend\n");
+    }
+
+    #[test]
+    fn test_resume_error_in_function() {
+        let mut interpretor = interpretor();
+        let program = parse("fn load() {\n defer print \"closed\";\n fetch(1);\n}\nload();");
+        let RunState::Suspended(_, handle) = interpretor.run(&program).unwrap() else {
+            panic!("expected a suspension");
+        };
+        handle.resume_err("timed out".to_string());
+        let err = interpretor.resume().unwrap_err();
+        assert_eq!(err.error, Error::Runtime("timed out".to_string()));
+        assert_eq!(err.line(), 3);
+        assert_eq!(interpretor.take_output(), "closed\n");
+    }
+
+    #[test]
+    fn test_suspend_rejected() {
+        let scripts = [
+            "time(\"t\", fetch);",
+            "fn f() { fetch(1); } time(\"t\", f);",
+            "print fetch(1);",
+            "let x = 1 + fetch(1);",
+            "fn f() { return 1 + fetch(1); } f();",
+            "fn f() { return fetch(1); } print f();",
+            "fn f() { return fetch(1); } if (f()) {}",
+            "fn f() { defer fetch(1); } f();",
+        ];
+        for script in scripts {
+            let mut interpretor = interpretor();
+            interpretor.define_native("fetch", &["id"], 0, false, |interpretor, _, span| {
+                Err(interpretor.pending(1, span))
+            });
+            let err = interpretor.run(&parse(script)).unwrap_err();
+            assert_eq!(
+                err.error,
+                Error::Runtime(CANNOT_SUSPEND.to_string()),
+                "{script}"
+            );
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use crate::{numfmt, Error, ErrorInfo, Interpretor, Object, Span};
+
+// The widest a placeholder may pad to, so a typo such as `{:99999999}`
+// fails rather than building a huge string.
+const MAX_WIDTH: usize = 1000;
+
+/// Where a value sits in the width it is padded to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+impl Align {
+    /// `text` padded with spaces to `width` characters. Centered text gets
+    /// the odd space on its right. Text already that wide is left alone.
+    pub fn pad(self, text: &str, width: usize) -> String {
+        let fill = width.saturating_sub(text.chars().count());
+        let (left, right) = match self {
+            Align::Left => (0, fill),
+            Align::Right => (fill, 0),
+            Align::Center => (fill / 2, fill - fill / 2),
+        };
+        format!("{}{text}{}", " ".repeat(left), " ".repeat(right))
+    }
+
+    // Numbers line up on the right, anything else on the left.
+    fn default_for(value: &Object) -> Align {
+        match value {
+            Object::Number(_) => Align::Right,
+            _ => Align::Left,
+        }
+    }
+}
+
+// What follows the `:` of a placeholder, `[<>^][0][width][,][.precision]`.
+#[derive(Debug, Default, PartialEq)]
+struct Spec {
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    grouping: bool,
+    precision: Option<usize>,
+}
+
+// A malformed template, or a value its placeholder cannot show, at the
+// 1-based character `position` of the template.
+fn template_error(position: usize, reason: &str) -> Error {
+    Error::Value(format!("format: {reason} at position {position}"))
+}
+
+/// `format(template, values...)` is `template` with each `{}` replaced by
+/// the next value, shown as `print` shows it. A placeholder may carry a spec
+/// after a colon, in this order:
+///
+/// - `<`, `>` or `^` to align the value left, right or centered in its
+///   width. Numbers align right by default, anything else left.
+/// - `0` to pad a number with zeros after its sign instead of with spaces.
+/// - a width, the fewest characters the value takes up.
+/// - `,` to group the digits of a number in threes, as in `1,234,567`.
+/// - `.` and a count of digits to show a number with after its point.
+///
+/// So `{:>8}` right-aligns in 8 characters and `{:08.2}` writes `-0001.50`
+/// for `-1.5`. `{{` and `}}` stand for braces. A malformed spec, or one
+/// asking for number formatting of anything else, is a ValueError giving
+/// the position of the problem in the template.
+pub fn format(_: &mut Interpretor, args: Vec<Object>, span: &Span) -> Result<Object, ErrorInfo> {
+    let Object::String(template) = &args[0] else {
+        let error = Error::Type(format!("template must be a string, not {}", args[0]));
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    };
+    fill(template, &args[1..])
+        .map(|text| Object::String(text.into()))
+        .map_err(|err| ErrorInfo::new_with_span(err, span.to_owned()))
+}
+
+fn fill(template: &str, values: &[Object]) -> Result<String, Error> {
+    let mut out = String::with_capacity(template.len());
+    let mut values = values.iter();
+    let mut chars = template.chars().enumerate().peekable();
+    while let Some((index, c)) = chars.next() {
+        let position = index + 1;
+        match c {
+            '{' if chars.next_if(|(_, c)| *c == '{').is_some() => out.push('{'),
+            '}' if chars.next_if(|(_, c)| *c == '}').is_some() => out.push('}'),
+            '}' => return Err(template_error(position, "unmatched '}'")),
+            '{' => {
+                let mut inside = String::new();
+                let mut closed = false;
+                for (_, c) in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    inside.push(c);
+                }
+                if !closed {
+                    return Err(template_error(position, "unclosed '{'"));
+                }
+                let spec = match inside.strip_prefix(':') {
+                    Some(spec) => parse_spec(spec, position + 2)?,
+                    None if inside.is_empty() => Spec::default(),
+                    None => return Err(template_error(position + 1, "expected ':' or '}'")),
+                };
+                let Some(value) = values.next() else {
+                    return Err(template_error(position, "no value for this placeholder"));
+                };
+                out.push_str(&show(&spec, value, position)?);
+            }
+            c => out.push(c),
+        }
+    }
+    match values.len() {
+        0 => Ok(out),
+        extra => Err(Error::Value(format!(
+            "format: {extra} more values than placeholders"
+        ))),
+    }
+}
+
+// `start` is the position of the spec's first character in the template.
+fn parse_spec(text: &str, start: usize) -> Result<Spec, Error> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spec = Spec::default();
+    let mut i = 0;
+    spec.align = match chars.first() {
+        Some('<') => Some(Align::Left),
+        Some('>') => Some(Align::Right),
+        Some('^') => Some(Align::Center),
+        _ => None,
+    };
+    if spec.align.is_some() {
+        i += 1;
+    }
+    if chars.get(i) == Some(&'0') {
+        if spec.align.is_some() {
+            return Err(template_error(
+                start + i,
+                "'0' padding cannot be combined with an alignment",
+            ));
+        }
+        spec.zero = true;
+        i += 1;
+    }
+    let width_at = start + i;
+    spec.width = digits(&chars, &mut i).unwrap_or(0);
+    if spec.width > MAX_WIDTH {
+        let reason = format!("width must be at most {MAX_WIDTH}");
+        return Err(template_error(width_at, &reason));
+    }
+    if chars.get(i) == Some(&',') {
+        spec.grouping = true;
+        i += 1;
+    }
+    if chars.get(i) == Some(&'.') {
+        i += 1;
+        let precision_at = start + i;
+        let Some(precision) = digits(&chars, &mut i) else {
+            return Err(template_error(precision_at, "expected digits after '.'"));
+        };
+        if precision > numfmt::MAX_DIGITS {
+            let reason = format!("precision must be at most {}", numfmt::MAX_DIGITS);
+            return Err(template_error(precision_at, &reason));
+        }
+        spec.precision = Some(precision);
+    }
+    match chars.get(i) {
+        Some(c) => Err(template_error(
+            start + i,
+            &format!("unexpected '{c}' in spec"),
+        )),
+        None => Ok(spec),
+    }
+}
+
+// The number the digits at `i` spell, moving past them, or None without
+// any. Too many digits for a usize saturate, and fail the range checks.
+fn digits(chars: &[char], i: &mut usize) -> Option<usize> {
+    let start = *i;
+    let mut n: usize = 0;
+    while let Some(digit) = chars.get(*i).and_then(|c| c.to_digit(10)) {
+        n = n.saturating_mul(10).saturating_add(digit as usize);
+        *i += 1;
+    }
+    (*i > start).then_some(n)
+}
+
+fn show(spec: &Spec, value: &Object, position: usize) -> Result<String, Error> {
+    let Object::Number(n) = value else {
+        let asked = if spec.grouping {
+            Some("','")
+        } else if spec.precision.is_some() {
+            Some("a precision")
+        } else if spec.zero {
+            Some("'0' padding")
+        } else {
+            None
+        };
+        if let Some(asked) = asked {
+            let reason = format!("{asked} needs a number, not {}", value.type_name());
+            return Err(template_error(position, &reason));
+        }
+        let align = spec.align.unwrap_or(Align::default_for(value));
+        return Ok(align.pad(&value.to_string(), spec.width));
+    };
+    let mut text = match spec.precision {
+        Some(digits) => numfmt::fixed(*n, digits)?,
+        None => numfmt::shortest(*n),
+    };
+    if spec.grouping {
+        text = numfmt::grouped(&text);
+    }
+    if spec.zero {
+        let (sign, unsigned) = match text.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", text.as_str()),
+        };
+        let zeros = spec.width.saturating_sub(text.chars().count());
+        return Ok(format!("{sign}{}{unsigned}", "0".repeat(zeros)));
+    }
+    let align = spec.align.unwrap_or(Align::Right);
+    Ok(align.pad(&text, spec.width))
+}
+
+/// `print_table(rows)` prints a tuple of rows, each a tuple of cells, with
+/// every column padded to its widest cell and two spaces between columns.
+/// Numbers align right and anything else left, as in `format`.
+pub fn print_table(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let not_rows = |value: &Object| {
+        let error = Error::Type(format!("rows must be a tuple of tuples, not {value}"));
+        ErrorInfo::new_with_span(error, span.to_owned())
+    };
+    let Object::Tuple(rows) = &args[0] else {
+        return Err(not_rows(&args[0]));
+    };
+    let mut cells = Vec::with_capacity(rows.len());
+    for row in rows.iter() {
+        match row {
+            Object::Tuple(row) => cells.push(row.clone()),
+            row => return Err(not_rows(row)),
+        }
+    }
+    let columns = cells.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            let cells = cells.iter().filter_map(|row| row.get(column));
+            let widths = cells.map(|cell| cell.to_string().chars().count());
+            widths.max().unwrap_or(0)
+        })
+        .collect();
+    for row in &cells {
+        let line: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| Align::default_for(cell).pad(&cell.to_string(), *width))
+            .collect();
+        interpretor.write_output(line.join("  ").trim_end());
+    }
+    Ok(Object::Nil)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fill, Align};
+    use crate::Object;
+
+    #[test]
+    fn test_specs() {
+        let text = |s: &str| Object::String(s.into());
+        let cases: &[(&str, &[Object], Result<&str, &str>)] = &[
+            (
+                "{} and {}",
+                &[Object::Number(1.5), text("x")],
+                Ok("1.5 and x"),
+            ),
+            ("{{{}}}", &[Object::Nil], Ok("{nil}")),
+            ("{:,}", &[Object::Number(1234567.89)], Ok("1,234,567.89")),
+            ("{:,}", &[Object::Number(-1234.0)], Ok("-1,234")),
+            ("{:,.2}", &[Object::Number(1234.5)], Ok("1,234.50")),
+            ("{:,}", &[Object::Number(1e21)], Ok("1e+21")),
+            ("[{:>8}]", &[Object::Number(-3.25)], Ok("[   -3.25]")),
+            ("[{:8}]", &[Object::Number(42.0)], Ok("[      42]")),
+            ("[{:8}]", &[text("ab")], Ok("[ab      ]")),
+            ("[{:<8}]", &[Object::Number(42.0)], Ok("[42      ]")),
+            ("[{:^8}]", &[text("mid")], Ok("[  mid   ]")),
+            ("[{:^6}]", &[Object::Nil], Ok("[ nil  ]")),
+            ("[{:>2}]", &[text("wider")], Ok("[wider]")),
+            ("{:08.2}", &[Object::Number(12.345)], Ok("00012.35")),
+            ("{:08.2}", &[Object::Number(-1.5)], Ok("-0001.50")),
+            ("{:010,}", &[Object::Number(1234.0)], Ok("000001,234")),
+            ("{:.0}", &[Object::Number(2.5)], Ok("2")),
+            (
+                "{}",
+                &[],
+                Err("format: no value for this placeholder at position 1"),
+            ),
+            (
+                "{}",
+                &[Object::Nil, Object::Nil],
+                Err("format: 1 more values than placeholders"),
+            ),
+            (
+                "a {",
+                &[Object::Nil],
+                Err("format: unclosed '{' at position 3"),
+            ),
+            ("a } b", &[], Err("format: unmatched '}' at position 3")),
+            (
+                "{x}",
+                &[Object::Nil],
+                Err("format: expected ':' or '}' at position 2"),
+            ),
+            (
+                "{:8x}",
+                &[Object::Nil],
+                Err("format: unexpected 'x' in spec at position 4"),
+            ),
+            (
+                "{:>08}",
+                &[Object::Nil],
+                Err("format: '0' padding cannot be combined with an alignment at position 4"),
+            ),
+            (
+                "{:8.}",
+                &[Object::Nil],
+                Err("format: expected digits after '.' at position 5"),
+            ),
+            (
+                "{:.101}",
+                &[Object::Nil],
+                Err("format: precision must be at most 100 at position 4"),
+            ),
+            (
+                "{:5000}",
+                &[Object::Nil],
+                Err("format: width must be at most 1000 at position 3"),
+            ),
+            (
+                "  {:,}",
+                &[text("1")],
+                Err("format: ',' needs a number, not string at position 3"),
+            ),
+            (
+                "{:.2}",
+                &[Object::Nil],
+                Err("format: a precision needs a number, not nil at position 1"),
+            ),
+            (
+                "{:05}",
+                &[text("1")],
+                Err("format: '0' padding needs a number, not string at position 1"),
+            ),
+        ];
+        for (template, values, expected) in cases {
+            let actual = fill(template, values).map_err(|err| err.to_string());
+            let expected = expected
+                .map(str::to_string)
+                .map_err(|message| format!("ValueError: {message}"));
+            assert_eq!(actual, expected, "{template}");
+        }
+    }
+
+    #[test]
+    fn test_pad() {
+        assert_eq!(Align::Center.pad("é", 4), " é  ");
+        assert_eq!(Align::Right.pad("", 2), "  ");
+    }
+}
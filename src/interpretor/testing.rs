@@ -0,0 +1,316 @@
+//! A small framework for tests written as scripts. `test` registers a test
+//! and `before_each` a function to run ahead of every test. The `expect_*`
+//! natives check values, and `run_tests` runs the tests in the order they
+//! were registered and prints how each went.
+
+use super::natives::callable;
+use crate::object::format;
+use crate::{Error, ErrorInfo, Interpretor, Object, Span};
+
+/// What `test` and `before_each` registered, and what has gone wrong in the
+/// test being run.
+#[derive(Default)]
+pub(crate) struct Tests {
+    // name, function, where `test` was called
+    tests: Vec<(String, Object, Span)>,
+    setups: Vec<Object>,
+    // None outside `run_tests`
+    failures: Option<Vec<String>>,
+    // set while `run_tests` runs, which may not run again inside a test
+    running: bool,
+}
+
+impl Interpretor {
+    /// Runs the tests the script registered with `test`, each after the
+    /// `before_each` functions, and prints `PASS name` or `FAIL name`
+    /// followed by what went wrong, then a count of each. A failed
+    /// expectation lets its test go on; an error ends just that test.
+    /// Called by the host, each test gets the whole of the timeout.
+    /// Returns how many tests failed. Calling it from a test is an error.
+    pub fn run_tests(&mut self) -> Result<usize, ErrorInfo> {
+        self.run_tests_at(&Span::default())
+    }
+
+    // `run_tests`, with `span` where a call from a test fails.
+    fn run_tests_at(&mut self, span: &Span) -> Result<usize, ErrorInfo> {
+        if self.tests.running {
+            let error = Error::Runtime("run_tests cannot run while tests are running".to_string());
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        }
+        self.tests.running = true;
+        let failed = self.run_each_test();
+        self.tests.running = false;
+        failed
+    }
+
+    fn run_each_test(&mut self) -> Result<usize, ErrorInfo> {
+        let tests = self.tests.tests.clone();
+        let setups = self.tests.setups.clone();
+        let mut failed = 0;
+        for (name, function, span) in &tests {
+            self.tests.failures = Some(Vec::new());
+            let result = self.timed(|interpretor| {
+                setups.iter().chain([function]).try_for_each(|f| {
+                    interpretor
+                        .call_value(f.clone(), vec![], vec![], span)
+                        .map(drop)
+                })
+            });
+            let mut failures = self.tests.failures.take().unwrap_or_default();
+            match result {
+                Ok(()) => {}
+                // the host has to see a suspension to resume it
+                Err(err) if matches!(err.error, Error::Suspend(_)) => return Err(err),
+                Err(err) => failures.push(format!("line {}: {}", err.span().line, err.error)),
+            }
+            if failures.is_empty() {
+                self.write_output(&format!("PASS {name}"));
+                continue;
+            }
+            failed += 1;
+            self.write_output(&format!("FAIL {name}"));
+            for failure in failures {
+                self.write_output(&format!("  {failure}"));
+            }
+        }
+        let passed = tests.len() - failed;
+        self.write_output(&format!("{passed} passed, {failed} failed"));
+        Ok(failed)
+    }
+
+    // Records `message` against the running test, or outside one stops the
+    // script with it, as `assert` would.
+    fn expectation(
+        &mut self,
+        held: bool,
+        message: impl FnOnce(&Self) -> String,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        if held {
+            return Ok(Object::Boolean(true));
+        }
+        let message = message(self);
+        match &mut self.tests.failures {
+            Some(failures) => {
+                failures.push(format!("line {}: {message}", span.line));
+                Ok(Object::Boolean(false))
+            }
+            None => {
+                let error = Error::Runtime(format!("expectation failed: {message}"));
+                Err(ErrorInfo::new_with_span(error, span.to_owned()))
+            }
+        }
+    }
+
+    // `value` as a failure shows it: strings quoted, so `"1"` and `1` differ.
+    fn shown(&self, value: &Object) -> String {
+        match value {
+            Object::String(text) => format!("{:?}", text.as_str()),
+            value => format::limited(value, self.print_limits),
+        }
+    }
+}
+
+/// `test(name, function)` registers `function` to be run by `run_tests`.
+pub fn test(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let Object::String(name) = &args[0] else {
+        let error = Error::Type(format!("name must be a string, not {}", args[0]));
+        return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+    };
+    callable("function", &args[1], span)?;
+    let test = (name.to_string(), args[1].clone(), span.to_owned());
+    interpretor.tests.tests.push(test);
+    Ok(Object::Nil)
+}
+
+/// `before_each(function)` has `run_tests` call `function` before each
+/// test, after those registered before it.
+pub fn before_each(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    callable("function", &args[0], span)?;
+    interpretor.tests.setups.push(args[0].clone());
+    Ok(Object::Nil)
+}
+
+/// `expect_eq(actual, expected)` is whether `actual == expected`. When not,
+/// the test fails with both values and the line of the call.
+pub fn expect_eq(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let held = Object::binary(args[0].clone(), &crate::TokenType::Eq, args[1].clone())
+        .is_ok_and(|equal| equal == Object::Boolean(true));
+    interpretor.expectation(
+        held,
+        |interpretor| {
+            let (actual, expected) = (interpretor.shown(&args[0]), interpretor.shown(&args[1]));
+            format!("expected {expected}, got {actual}")
+        },
+        span,
+    )
+}
+
+/// `expect_true(value)` is whether `value` is truthy, failing the test
+/// like `expect_eq` when not.
+pub fn expect_true(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    interpretor.expectation(
+        args[0].to_boolean(),
+        |interpretor| format!("expected a true value, got {}", interpretor.shown(&args[0])),
+        span,
+    )
+}
+
+/// `expect_throws(function)` calls `function` and is whether it raised an
+/// error, which goes no further. When it returns instead, the test fails.
+pub fn expect_throws(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    callable("function", &args[0], span)?;
+    let returned = match interpretor.call_value(args[0].clone(), vec![], vec![], span) {
+        Ok(value) => value,
+        Err(err) if matches!(err.error, Error::Suspend(_)) => return Err(err),
+        Err(_) => return Ok(Object::Boolean(true)),
+    };
+    interpretor.expectation(
+        false,
+        |interpretor| {
+            format!(
+                "expected an error, but it returned {}",
+                interpretor.shown(&returned)
+            )
+        },
+        span,
+    )
+}
+
+/// `run_tests()` is `Interpretor::run_tests`, returning the count of tests
+/// that failed.
+pub fn run_tests(
+    interpretor: &mut Interpretor,
+    _: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    interpretor
+        .run_tests_at(span)
+        .map(|failed| Object::Number(failed as f64))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Interpretor, Lexer, Parser};
+
+    // Runs `input` and then its tests, giving the number that failed and
+    // what was printed.
+    fn run_tests(input: &str) -> (usize, String) {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::buffered();
+        interpretor.run(&program).unwrap();
+        let failed = interpretor.run_tests().unwrap();
+        (failed, interpretor.take_output())
+    }
+
+    #[test]
+    fn test_passing() {
+        let input = "fn adds() { expect_eq(1 + 1, 2); expect_true(1 < 2); }
+test(\"adds\", adds);";
+        assert_eq!(
+            run_tests(input),
+            (0, "PASS adds\n1 passed, 0 failed\n".into())
+        );
+    }
+
+    #[test]
+    fn test_failure_message() {
+        let input = "fn compares() {
+    expect_eq(\"1\", 1);
+    expect_eq((1, 2), (1, 3));
+    expect_true(nil);
+    print \"went on\";
+}
+fn returns() { return 1; }
+fn expects_error() { expect_throws(returns); }
+test(\"compares\", compares);
+test(\"expects error\", expects_error);";
+        let output = "went on
+FAIL compares
+  line 2: expected 1, got \"1\"
+  line 3: expected (1, 3), got (1, 2)
+  line 4: expected a true value, got nil
+FAIL expects error
+  line 8: expected an error, but it returned 1
+0 passed, 2 failed
+";
+        assert_eq!(run_tests(input), (2, output.into()));
+    }
+
+    #[test]
+    fn test_errors_isolated() {
+        let input = "fn fails() { return 1 / nope; }
+fn catches() { expect_throws(fails); }
+fn raises() { fails(); print \"not reached\"; }
+fn after() {}
+test(\"catches\", catches);
+test(\"raises\", raises);
+test(\"after\", after);";
+        let (failed, output) = run_tests(input);
+        assert_eq!(failed, 1);
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines[..2], ["PASS catches", "FAIL raises"]);
+        assert!(lines[2].starts_with("  line 1: NameError"), "{output}");
+        assert_eq!(lines[3..], ["PASS after", "2 passed, 1 failed"]);
+    }
+
+    #[test]
+    fn test_before_each_order() {
+        let input = "fn first() { print \"first\"; }
+fn second() { print \"second\"; }
+fn one() { print \"one\"; }
+fn two() { print \"two\"; }
+before_each(first);
+test(\"one\", one);
+before_each(second);
+test(\"two\", two);";
+        let output =
+            "first\nsecond\none\nPASS one\nfirst\nsecond\ntwo\nPASS two\n2 passed, 0 failed\n";
+        assert_eq!(run_tests(input), (0, output.into()));
+    }
+
+    #[test]
+    fn test_run_from_a_test() {
+        let input = "fn again() { run_tests(); }\ntest(\"again\", again);";
+        let output = "FAIL again
+  line 1: RuntimeError: run_tests cannot run while tests are running
+0 passed, 1 failed
+";
+        assert_eq!(run_tests(input), (1, output.into()));
+    }
+
+    #[test]
+    fn test_outside_a_test() {
+        let program = Parser::new(Lexer::new("expect_eq(1, 2);".to_string()))
+            .parse_program()
+            .unwrap();
+        let mut interpretor = Interpretor::buffered();
+        let err = interpretor.run(&program).unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Runtime("expectation failed: expected 2, got 1".into())
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::{cmp::Ordering, rc::Rc};
+
+use crate::object::{Function, NativeFn};
+use crate::{Error, ErrorInfo, Interpretor, Object, Span, TokenType};
+
+// name, parameter names, how many of them are required, implementation
+type Method = (&'static str, &'static [&'static str], usize, NativeFn);
+
+const METHODS: [Method; 2] = [("len", &[], 0, len), ("sorted", &["cmp"], 0, sorted)];
+
+/// The names `method` finds.
+pub fn method_names() -> impl Iterator<Item = &'static str> {
+    METHODS.into_iter().map(|method| method.0)
+}
+
+/// Looks up a method on a tuple, e.g. `t.len`, bound to `tuple`.
+pub fn method(tuple: &Object, name: &str) -> Result<Object, Error> {
+    let Some((name, params, required, func)) = METHODS.into_iter().find(|method| method.0 == name)
+    else {
+        return Err(Error::Attribute(format!("undefined property \"{name}\"")));
+    };
+    Ok(Object::Function(Rc::new(Function::Method {
+        name,
+        receiver: tuple.clone(),
+        params,
+        required,
+        variadic: false,
+        func: Box::new(func),
+    })))
+}
+
+/// The elements of `value` for `let (a, b) = value;`, which must be a tuple
+/// with exactly `count` of them.
+pub fn unpack(value: Object, count: usize) -> Result<Rc<[Object]>, Error> {
+    let names = if count == 1 { "name" } else { "names" };
+    match value {
+        Object::Tuple(elements) if elements.len() == count => Ok(elements),
+        Object::Tuple(elements) => Err(Error::Value(format!(
+            "cannot unpack {} values into {count} {names}",
+            elements.len()
+        ))),
+        value => Err(Error::Type(format!(
+            "cannot unpack a {} into {count} {names}",
+            value.type_name()
+        ))),
+    }
+}
+
+fn len(_: &mut Interpretor, args: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+    match &args[0] {
+        Object::Tuple(elements) => Ok(Object::Number(elements.len() as f64)),
+        _ => unreachable!("tuple methods are only bound to tuples"),
+    }
+}
+
+/// `t.sorted()` is a tuple of the elements of `t` in ascending order, which
+/// must be all numbers or all strings. `t.sorted(cmp)` orders them by what
+/// `cmp(a, b)` returns instead: a number below zero if `a` goes first, above
+/// zero if `b` does, and zero if either may. Equal elements keep their order.
+///
+/// `cmp` is called through `Interpretor::call_value` like any other call, so
+/// an error it raises ends the sort with the comparator's own line, and its
+/// calls count towards `max_call_depth`. It cannot disturb the sort: the
+/// tuple is immutable and the sort works on a copy of its elements.
+fn sorted(
+    interpretor: &mut Interpretor,
+    args: Vec<Object>,
+    span: &Span,
+) -> Result<Object, ErrorInfo> {
+    let Object::Tuple(elements) = &args[0] else {
+        unreachable!("tuple methods are only bound to tuples");
+    };
+    let fail = |err| ErrorInfo::new_with_span(err, span.to_owned());
+    let cmp = &args[1];
+    let mut after = |a: &Object, b: &Object| match cmp {
+        Object::Nil => Ok(a.compare(&TokenType::Gt, b).map_err(fail)? == Some(Ordering::Greater)),
+        _ => match interpretor.call_value(cmp.clone(), vec![a.clone(), b.clone()], vec![], span)? {
+            Object::Number(n) => Ok(n > 0.0),
+            other => Err(fail(Error::Type(format!(
+                "sorted: cmp must return a number, not a {}",
+                other.type_name()
+            )))),
+        },
+    };
+    let items = merge_sort(elements.to_vec(), &mut after)?;
+    Ok(Object::Tuple(items.into()))
+}
+
+// Sorts `items` stably, with `after(a, b)` telling whether `a` goes after
+// `b`. Unlike the standard library's sorts, a comparison may fail, which
+// ends the sort, and one that contradicts itself cannot make it panic.
+fn merge_sort<F>(mut items: Vec<Object>, after: &mut F) -> Result<Vec<Object>, ErrorInfo>
+where
+    F: FnMut(&Object, &Object) -> Result<bool, ErrorInfo>,
+{
+    if items.len() < 2 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let mut left = merge_sort(items, after)?.into_iter().peekable();
+    let mut right = merge_sort(right, after)?.into_iter().peekable();
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        let next = if after(a, b)? { &mut right } else { &mut left };
+        merged.extend(next.next());
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Interpretor, Lexer, Parser};
+
+    fn run(interpretor: &mut Interpretor, input: &str) -> Result<(), Error> {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        interpretor
+            .run(&program)
+            .map(|_| ())
+            .map_err(|err| err.error)
+    }
+
+    #[test]
+    fn test_comparator_counted() {
+        let mut interpretor = Interpretor::buffered();
+        let input = "fn cmp(a, b) { return a - b; }
+let t = (4, 3, 2, 1);
+print t.sorted(cmp);";
+        run(&mut interpretor, input).unwrap();
+        assert_eq!(interpretor.take_output(), "(1, 2, 3, 4)\n");
+        // merging the halves of each half takes one comparison, and merging
+        // those two takes two more
+        let stats = interpretor.stats();
+        assert_eq!((stats.script_calls, stats.native_calls), (4, 1));
+        assert_eq!(stats.statements, 3 + 4);
+        assert_eq!(stats.max_call_depth, 2);
+    }
+
+    #[test]
+    fn test_comparator_depth() {
+        // each comparison sorts again, one level deeper
+        let input = "fn cmp(a, b) {
+    if (a > 0) { (a - 1, 0).sorted(cmp); }
+    return a - b;
+}
+(DEPTH, 0).sorted(cmp);";
+        // `sorted` and `cmp` make two calls per level
+        let mut interpretor = Interpretor::buffered();
+        interpretor.max_call_depth = Some(20);
+        assert_eq!(run(&mut interpretor, &input.replace("DEPTH", "9")), Ok(()));
+        assert_eq!(interpretor.stats().max_call_depth, 20);
+
+        let mut interpretor = Interpretor::buffered();
+        interpretor.max_call_depth = Some(20);
+        assert_eq!(
+            run(&mut interpretor, &input.replace("DEPTH", "10")),
+            Err(Error::CallDepth { limit: 20 })
+        );
+        // every call that was in progress has ended
+        assert_eq!(run(&mut interpretor, "(2, 1).sorted(cmp);"), Ok(()));
+    }
+}
@@ -0,0 +1,307 @@
+use std::ops::Range;
+
+use super::Lexer;
+use crate::token::{TokenInfo, TokenType};
+use crate::{ErrorInfo, Span};
+
+// How far past the end of a token the lexer may have looked to end it, as a
+// number does to see whether `e` starts an exponent.
+const LOOKAHEAD: usize = 3;
+
+/// Tokens kept up to date as their source is edited, for an editor that
+/// lexes on every keystroke. An edit re-lexes from the last token that
+/// cannot have seen it to the first token that comes out the same as
+/// before; the tokens after that are only moved. The tokens and errors are
+/// always those `Lexer` would give for the whole source.
+pub struct IncrementalLexer {
+    lexer: Lexer,
+    // ends with the `Eof` token
+    tokens: Vec<TokenInfo>,
+    errors: Vec<ErrorInfo>,
+}
+
+impl IncrementalLexer {
+    pub fn new(source: &str) -> Self {
+        let mut lexer = Lexer::new(source.to_string());
+        let mut tokens = Vec::new();
+        loop {
+            let token = lexer.next_token();
+            let eof = token.is(TokenType::Eof);
+            tokens.push(token);
+            if eof {
+                break;
+            }
+        }
+        let errors = std::mem::take(&mut lexer.errors);
+        Self {
+            lexer,
+            tokens,
+            errors,
+        }
+    }
+
+    /// The tokens of the source, the last of them `Eof`.
+    pub fn tokens(&self) -> &[TokenInfo] {
+        &self.tokens
+    }
+
+    /// The errors met lexing the source, in order.
+    pub fn errors(&self) -> &[ErrorInfo] {
+        &self.errors
+    }
+
+    pub fn source(&self) -> String {
+        self.lexer.data.iter().collect()
+    }
+
+    /// Replaces the chars in `range` of the source with `new_text` and
+    /// returns which of the new tokens were lexed again. Spans count chars,
+    /// as the lexer's do.
+    pub fn apply_edit(&mut self, range: Range<usize>, new_text: &str) -> Range<usize> {
+        let data = &mut self.lexer.data;
+        assert!(
+            range.start <= range.end && range.end <= data.len(),
+            "edit {range:?} is outside the source"
+        );
+        let removed = data[range.clone()].iter().filter(|ch| **ch == '\n').count();
+        let new_text: Vec<char> = new_text.chars().collect();
+        let added = new_text.iter().filter(|ch| **ch == '\n').count();
+        data.splice(range.clone(), new_text.iter().copied());
+        let edit = Edit {
+            start: range.start,
+            old_end: range.end,
+            new_end: range.start + new_text.len(),
+            lines: added as isize - removed as isize,
+        };
+
+        // the tokens that ended far enough before the edit not to have seen it
+        let keep = self
+            .tokens
+            .partition_point(|token| token.span.end + LOOKAHEAD <= edit.start);
+        let (restart, line, line_start) = match keep.checked_sub(1) {
+            Some(last) => {
+                // a token has the line it starts on, and a string may end
+                // lines later
+                let span = &self.tokens[last].span;
+                let text = &self.lexer.data[span.start..span.end];
+                let breaks = text.iter().filter(|ch| **ch == '\n').count();
+                let line_start = match text.iter().rposition(|ch| *ch == '\n') {
+                    Some(at) => span.start + at,
+                    None => span.line_start,
+                };
+                (span.end, span.line + breaks, line_start)
+            }
+            None => (0, 1, 0),
+        };
+        let lexer = &mut self.lexer;
+        lexer.start = restart;
+        lexer.curr = restart;
+        lexer.line = line;
+        lexer.line_start = line_start;
+        lexer.in_comment = false;
+        lexer.errors.clear();
+
+        // lex until a token comes out as one after the edit did before it:
+        // from there on the lexer does what it did then
+        let mut fresh = Vec::new();
+        let mut old = keep;
+        let synced = loop {
+            let token = self.lexer.next_token();
+            if token.span.start >= edit.new_end {
+                while old < self.tokens.len()
+                    && edit.to_new(self.tokens[old].span.start) < Some(token.span.start)
+                {
+                    old += 1;
+                }
+                if old < self.tokens.len() && edit.moved(&self.tokens[old], &token) {
+                    break Some(old);
+                }
+            }
+            let eof = token.is(TokenType::Eof);
+            fresh.push(token);
+            if eof {
+                break None;
+            }
+        };
+
+        let lexed = keep..keep + fresh.len();
+        let mut errors: Vec<_> = self
+            .errors
+            .iter()
+            .take_while(|err| err.span().start < restart)
+            .cloned()
+            .collect();
+        errors.append(&mut self.lexer.errors);
+        if let Some(synced) = synced {
+            let after = self.tokens[synced].span.start;
+            let moved = self.errors.iter().filter(|err| err.span().start >= after);
+            errors.extend(moved.map(|err| {
+                let span = edit
+                    .span(err.span())
+                    .expect("errors after an edit move with it");
+                ErrorInfo::new_with_span(err.error.clone(), span)
+            }));
+            self.tokens.splice(keep..synced, fresh);
+            for token in &mut self.tokens[lexed.end..] {
+                token.span = edit.span(&token.span).expect("tokens after an edit move");
+            }
+        } else {
+            self.tokens.truncate(keep);
+            self.tokens.append(&mut fresh);
+        }
+        self.errors = errors;
+        lexed
+    }
+}
+
+// Where a replacement moved the text after it.
+struct Edit {
+    start: usize,
+    old_end: usize,
+    new_end: usize,
+    // the lines it added, or less than none for lines it removed
+    lines: isize,
+}
+
+impl Edit {
+    // Where a position of the old source is now, if its text was kept.
+    fn to_new(&self, at: usize) -> Option<usize> {
+        if at < self.start {
+            Some(at)
+        } else if at >= self.old_end {
+            Some(at - self.old_end + self.new_end)
+        } else {
+            None
+        }
+    }
+
+    fn span(&self, span: &Span) -> Option<Span> {
+        Some(Span {
+            line: span.line.checked_add_signed(self.lines)?,
+            line_start: self.to_new(span.line_start)?,
+            start: self.to_new(span.start)?,
+            end: self.to_new(span.end)?,
+        })
+    }
+
+    // Whether `token` of the new source is `old` moved by the edit.
+    fn moved(&self, old: &TokenInfo, token: &TokenInfo) -> bool {
+        old.token == token.token && self.span(&old.span).as_ref() == Some(&token.span)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IncrementalLexer;
+    use crate::{ErrorInfo, Lexer, TokenInfo, TokenType};
+
+    // What lexing `source` from scratch gives.
+    fn lex(source: &str) -> (Vec<TokenInfo>, Vec<ErrorInfo>) {
+        let mut lexer = Lexer::new(source.to_string());
+        let mut tokens = Vec::new();
+        loop {
+            let token = lexer.next_token();
+            let eof = token.is(TokenType::Eof);
+            tokens.push(token);
+            if eof {
+                return (tokens, lexer.errors().to_vec());
+            }
+        }
+    }
+
+    fn check(lexer: &IncrementalLexer) {
+        let (tokens, errors) = lex(&lexer.source());
+        assert_eq!(lexer.tokens(), tokens, "tokens of {:?}", lexer.source());
+        assert_eq!(lexer.errors(), errors, "errors of {:?}", lexer.source());
+    }
+
+    #[test]
+    fn test_edits() {
+        let mut lexer = IncrementalLexer::new("let a = 1;\nlet b = \"two\";\nprint a + b;\n");
+        // renaming a variable re-lexes its token and the one before, which
+        // may have looked into it
+        let lexed = lexer.apply_edit(4..5, "alpha");
+        assert_eq!(lexed, 0..2);
+        assert_eq!(
+            lexer.tokens()[1].token,
+            TokenType::Identifier("alpha".to_string())
+        );
+        check(&lexer);
+
+        // commenting a line out drops its tokens and lexes nothing new,
+        // while taking the comment out again lexes them back
+        let count = lexer.tokens().len();
+        assert_eq!(lexer.apply_edit(0..0, "#"), 0..0);
+        assert_eq!(lexer.tokens().len(), count - 5);
+        check(&lexer);
+        assert_eq!(lexer.apply_edit(0..1, ""), 0..5);
+        check(&lexer);
+
+        // a string left open at the end is an error
+        lexer.apply_edit(43..43, "\"");
+        assert_eq!(lexer.errors().len(), 1);
+        check(&lexer);
+        lexer.apply_edit(43..44, "");
+        assert!(lexer.errors().is_empty());
+        check(&lexer);
+
+        // joining two lines moves the tokens on the later lines up
+        lexer.apply_edit(14..15, " ");
+        assert_eq!(lexer.tokens().last().unwrap().span.line, 3);
+        check(&lexer);
+    }
+
+    // A random number generator, so the test needs no crate and fails the
+    // same way every time.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    const PIECES: [&str; 24] = [
+        "let", " ", "x", "y1", "1", "1.5", "2e", "e+3", "0x1F", "1_0", "\"", "\"s\"", "#c", "\n",
+        "=", "==", "(", ")", "{", "}", ".", ">>", "@{a}", "\u{200B}",
+    ];
+
+    fn text(random: &mut Random, pieces: usize) -> String {
+        let pieces = random.below(pieces + 1);
+        (0..pieces)
+            .map(|_| PIECES[random.below(PIECES.len())])
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_lexing_from_scratch() {
+        let mut random = Random(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..200 {
+            let source = text(&mut random, 40);
+            let mut lexer = IncrementalLexer::new(&source);
+            check(&lexer);
+            for _ in 0..20 {
+                let len = lexer.source().chars().count();
+                let start = random.below(len + 1);
+                let end = start + random.below((len - start).min(6) + 1);
+                let new_text = text(&mut random, 3);
+                let before = lexer.source();
+                lexer.apply_edit(start..end, &new_text);
+                let (tokens, errors) = lex(&lexer.source());
+                assert_eq!(
+                    lexer.tokens(),
+                    tokens,
+                    "{before:?} with {start}..{end} replaced by {new_text:?}"
+                );
+                assert_eq!(
+                    lexer.errors(),
+                    errors,
+                    "{before:?} with {start}..{end} replaced by {new_text:?}"
+                );
+            }
+        }
+    }
+}
@@ -1,40 +1,93 @@
 use crate::token::{self, TokenInfo, TokenType};
-use crate::{Error, ErrorInfo};
+use crate::{numfmt, Error, ErrorInfo, Span, Trivia, TriviaKind};
+
+mod incremental;
+pub use incremental::IncrementalLexer;
+
+/// What the lexer does with comments and whitespace.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum LexerMode {
+    /// Skips them, which is all the parser needs.
+    #[default]
+    Default,
+    /// Hands comments and blank lines to tools as the `leading_trivia` of
+    /// the token after them.
+    WithTrivia,
+}
 
 pub struct Lexer {
     start: usize,
     curr: usize,    
     line: usize,
     line_start: usize,
+    // `line` and `line_start` where the token being scanned began, which a
+    // string running over several lines has left behind
+    token_line: (usize, usize),
     data: Vec<char>,
+    mode: LexerMode,
+    // an invisible character cut the comment being scanned short
+    in_comment: bool,
+    errors: Vec<ErrorInfo>,
 }
 
 //  methods
 impl Lexer {
     pub fn new(data: String) -> Self {
+        Self::with_mode(data, LexerMode::Default)
+    }
+
+    pub fn with_mode(data: String, mode: LexerMode) -> Self {
         Self {
             start: 0,
             curr: 0,
             line: 1,
             line_start: 0,
+            token_line: (1, 0),
             data: data.chars().collect(),
+            mode,
+            in_comment: false,
+            errors: Vec::new(),
         }
     }
 
-    pub fn next(&mut self) -> TokenInfo {
+    /// The errors `next_token` skipped past, in the order it met them.
+    pub fn errors(&self) -> &[ErrorInfo] {
+        &self.errors
+    }
+
+    pub fn source(&self, span: &Span) -> String {
+        self.data[span.start..span.end].iter().collect()
+    }
+
+    pub fn next_token(&mut self) -> TokenInfo {
+        let mut trivia = Vec::new();
         loop {
-            let result = self.scan();
-            if result.is_ok() {
-                return TokenInfo::new(result.unwrap(), self.start, self.curr, self.line, self.line_start);
-            } else {
-                let err = ErrorInfo::new(result.unwrap_err(), self.line, self.line_start, self.start, self.curr);
-                err.report();
+            if self.mode == LexerMode::WithTrivia {
+                self.trivia(&mut trivia);
+            }
+            match self.scan() {
+                Ok(token) => {
+                    let (line, line_start) = self.token_line;
+                    let mut info = TokenInfo::new(token, self.start, self.curr, line, line_start);
+                    info.leading_trivia = trivia;
+                    return info;
+                }
+                Err(error) => {
+                    let (line, line_start) = self.token_line;
+                    let err = ErrorInfo::new(error, line, line_start, self.start, self.curr);
+                    self.errors.push(err);
+                }
             }
         }
     }
 
     pub fn scan(&mut self) -> Result<TokenType, Error> {
         self.start = self.curr;
+        self.token_line = (self.line, self.line_start);
+        if self.in_comment {
+            self.in_comment = false;
+            return self.comment();
+        }
         match self.next_char() {
             '\0' => Ok(TokenType::Eof),
             ',' => Ok(TokenType::Comma),
@@ -45,6 +98,8 @@ impl Lexer {
             '(' => Ok(TokenType::LParen),
             ')' => Ok(TokenType::RParen),
             ';' => Ok(TokenType::Semicolon),
+            ':' => Ok(TokenType::Colon),
+            '.' => Ok(TokenType::Dot),
             '+' => {
                 if self.is_next_char('=') {
                     Ok(TokenType::PlusEq)
@@ -83,6 +138,8 @@ impl Lexer {
             '=' => {
                 if self.is_next_char('=') {
                     Ok(TokenType::Eq)
+                } else if self.is_next_char('>') {
+                    Ok(TokenType::FatArrow)
                 } else {
                     Ok(TokenType::Assign)
                 }
@@ -115,6 +172,8 @@ impl Lexer {
                     Ok(TokenType::OrEq)
                 } else if self.is_next_char('|') {
                     Ok(TokenType::LogicalOr)
+                } else if self.is_next_char('>') {
+                    Ok(TokenType::Pipe)
                 } else {
                     Ok(TokenType::Or)
                 }
@@ -123,7 +182,11 @@ impl Lexer {
                 if self.is_next_char('=') {
                     Ok(TokenType::Lte)
                 } else if self.is_next_char('<') {
-                    Ok(TokenType::LShift)
+                    if self.is_next_char('=') {
+                        Ok(TokenType::LShiftEq)
+                    } else {
+                        Ok(TokenType::LShift)
+                    }
                 } else {
                     Ok(TokenType::Lt)
                 }
@@ -132,7 +195,17 @@ impl Lexer {
                 if self.is_next_char('=') {
                     Ok(TokenType::Gte)
                 } else if self.is_next_char('>') {
-                    Ok(TokenType::RShift)
+                    if self.is_next_char('>') {
+                        if self.is_next_char('=') {
+                            Ok(TokenType::URShiftEq)
+                        } else {
+                            Ok(TokenType::URShift)
+                        }
+                    } else if self.is_next_char('=') {
+                        Ok(TokenType::RShiftEq)
+                    } else {
+                        Ok(TokenType::RShift)
+                    }
                 } else {
                     Ok(TokenType::Gt)
                 }
@@ -140,59 +213,217 @@ impl Lexer {
             '\"' => {
                 while self.peek_char() != '\"' {
                     if self.is_eof() {
-                        return Err(Error::Syntax(format!("unterminated string",)));
+                        return Err(Error::Lex("unterminated string".to_string()));
                     }
                     self.next_char();
                 }
                 let data = self.data[self.start+1..self.curr]
-                    .into_iter()
+                    .iter()
                     .collect::<String>();
                 self.next_char();
                 Ok(TokenType::String(data))
             }
-            'a'..='z' | 'A'..='Z' | '_' => {
-                while (self.is_digit(self.peek_char()) || self.is_identifier(self.peek_char()))
-                    && !self.is_eof()
-                {
+            // keywords are ASCII, but names may use letters of any script
+            ch if self.is_identifier(ch) => {
+                while self.peek_char().is_alphanumeric() || self.peek_char() == '_' {
                     self.next_char();
                 }
 
-                let data = self.data[self.start..self.curr]
-                    .into_iter()
-                    .collect::<String>();
-                Ok(token::lookup_identifier(data))
+                let word = &self.data[self.start..self.curr];
+                if let Some(keyword) = keyword(word) {
+                    return Ok(keyword);
+                }
+                Ok(TokenType::Identifier(word.iter().collect()))
             }
             ' ' | '\r' | '\t' | '\n' => {
                 while self.is_whitespace(self.peek_char()) {
                     self.next_char();
                 }
-                return self.scan();
+                self.scan()
             }
-            '#' => {
-                while self.peek_char() != '\n' && !self.is_eof() {
+            '#' => self.comment(),
+            '0'..='9' => self.number(),
+            '@' if self.is_next_char('{') => self.placeholder(),
+            '`' => self.raw_identifier(),
+            ch if hidden(ch).is_some() => Err(self.hidden_error(ch)),
+            ch => Err(Error::Lex(format!("unknown character:'{ch}'"))),
+        }
+    }
+}
+
+impl Lexer {
+    // Skips whitespace and comments up to the next token, collecting the
+    // comments and each run of blank lines. The line the previous token is
+    // on is not blank, even when nothing else follows it.
+    fn trivia(&mut self, out: &mut Vec<Trivia>) {
+        let mut has_content = self.curr > 0;
+        let mut blank: Option<(Span, usize)> = None;
+        loop {
+            match self.peek_char() {
+                '\n' => {
+                    let (line, line_start, start) = (self.line, self.line_start, self.curr);
                     self.next_char();
+                    if !has_content {
+                        let (span, count) = blank.get_or_insert_with(|| {
+                            let line_begin = if line == 1 { 0 } else { line_start + 1 };
+                            (Span::new(line, line_start, line_begin, start), 0)
+                        });
+                        span.end = self.curr;
+                        *count += 1;
+                    }
+                    has_content = false;
+                }
+                ' ' | '\r' | '\t' => {
+                    self.next_char();
+                }
+                '#' => {
+                    flush_blank(&mut blank, out);
+                    let (line, line_start, start) = (self.line, self.line_start, self.curr);
+                    while self.peek_char() != '\n' && !self.is_eof() {
+                        if hidden(self.peek_char()).is_some() {
+                            // `scan` reports it and skips the rest
+                            self.in_comment = true;
+                            break;
+                        }
+                        self.next_char();
+                    }
+                    let text = self.data[start..self.curr].iter().collect();
+                    out.push(Trivia {
+                        kind: TriviaKind::Comment(text),
+                        span: Span::new(line, line_start, start, self.curr),
+                    });
+                    has_content = true;
+                    if self.in_comment {
+                        break;
+                    }
                 }
-                return self.scan();
+                _ => break,
+            }
+        }
+        flush_blank(&mut blank, out);
+    }
+
+    // The rest of a `#` comment. Comments are not strings: an invisible
+    // character in one fails like anywhere else in the code.
+    fn comment(&mut self) -> Result<TokenType, Error> {
+        while self.peek_char() != '\n' && !self.is_eof() {
+            self.start = self.curr;
+            let ch = self.next_char();
+            if hidden(ch).is_some() {
+                self.in_comment = true;
+                return Err(self.hidden_error(ch));
+            }
+        }
+        self.scan()
+    }
+
+    fn hidden_error(&self, ch: char) -> Error {
+        let kind = hidden(ch).expect("only called for hidden characters");
+        Error::Lex(format!(
+            "{kind} U+{:04X} is only allowed inside a string",
+            ch as u32
+        ))
+    }
+
+    // `@{name}` after the `@{`, where the name is spelled like an
+    // identifier.
+    fn placeholder(&mut self) -> Result<TokenType, Error> {
+        let name_start = self.curr;
+        while self.is_identifier(self.peek_char()) || self.is_digit(self.peek_char()) {
+            self.next_char();
+        }
+        let name: String = self.data[name_start..self.curr].iter().collect();
+        let valid = name.starts_with(|ch: char| self.is_identifier(ch));
+        if !self.is_next_char('}') || !valid {
+            let text: String = self.data[self.start..self.curr].iter().collect();
+            return Err(Error::Lex(format!("invalid placeholder:'{text}'")));
+        }
+        Ok(TokenType::Placeholder(name))
+    }
+
+    // A name written between backticks, `` `class` ``, which is a name even
+    // when it is spelled like a keyword. Any other name means the same with
+    // or without them.
+    fn raw_identifier(&mut self) -> Result<TokenType, Error> {
+        let name_start = self.curr;
+        while self.peek_char().is_alphanumeric() || self.peek_char() == '_' {
+            self.next_char();
+        }
+        let name: String = self.data[name_start..self.curr].iter().collect();
+        let valid = name.starts_with(|ch: char| self.is_identifier(ch));
+        if !self.is_next_char('`') || !valid {
+            let text: String = self.data[self.start..self.curr].iter().collect();
+            return Err(Error::Lex(format!("invalid raw identifier:'{text}'")));
+        }
+        Ok(TokenType::Identifier(name))
+    }
+
+    // Decimal numbers with an optional fraction and exponent (`1.5e-3`), or
+    // hex integers (`0xFF`). Digits may be grouped with `_` (`1_000`).
+    fn number(&mut self) -> Result<TokenType, Error> {
+        let hex = self.data[self.start] == '0' && matches!(self.peek_char(), 'x' | 'X');
+        if hex {
+            self.next_char();
+            while self.peek_char().is_ascii_hexdigit() || self.peek_char() == '_' {
+                self.next_char();
+            }
+        } else {
+            // a dot without a digit after it is not part of the number, so
+            // `3.floor()` calls a method on 3
+            while self.is_digit(self.peek_char())
+                || self.peek_char() == '_'
+                || (self.peek_char() == '.' && self.is_digit(self.peek_nth(1)))
+            {
+                self.next_char();
             }
-            '0'..='9' => {
-                let start = self.curr;
-                while self.is_digit(self.peek_char()) || self.peek_char() == '.' {
+            let signed = matches!(self.peek_nth(1), '+' | '-');
+            if matches!(self.peek_char(), 'e' | 'E')
+                && self.is_digit(self.peek_nth(if signed { 2 } else { 1 }))
+            {
+                self.next_char();
+                if signed {
                     self.next_char();
                 }
-                let data = self.data[start - 1..self.curr]
-                    .into_iter()
-                    .collect::<String>();
-                match data.parse::<f64>() {
-                    Ok(x) => Ok(TokenType::Number(x)),
-                    Err(_) => Err(Error::Value(format!("invalid number:'{}'", data,))),
+                while self.is_digit(self.peek_char()) {
+                    self.next_char();
                 }
             }
-            ch => Err(Error::Syntax(format!("unknown character:'{ch}'"))),
         }
+
+        // the text is only collected for the error; a number that lexes
+        // does not allocate
+        let invalid =
+            |lexer: &Self| Error::Value(format!("invalid number:'{}'", lexer.source_text()));
+        let digits = &self.data[self.start + if hex { 2 } else { 0 }..self.curr];
+        // a separator has to sit between two digits, so not next to the `.`
+        // or the `e` of a decimal
+        let is_digit = |ch: &char| {
+            if hex {
+                ch.is_ascii_hexdigit()
+            } else {
+                ch.is_ascii_digit()
+            }
+        };
+        let grouped = digits.iter().enumerate().all(|(i, ch)| {
+            *ch != '_'
+                || (i > 0 && is_digit(&digits[i - 1]) && digits.get(i + 1).is_some_and(is_digit))
+        });
+        if !grouped {
+            return Err(invalid(self));
+        }
+        let value = if hex {
+            hex_value(digits).map(|x| x as f64)
+        } else {
+            decimal_value(digits)
+        };
+        value.map(TokenType::Number).ok_or_else(|| invalid(self))
+    }
+
+    // The text of the token being scanned.
+    fn source_text(&self) -> String {
+        self.data[self.start..self.curr].iter().collect()
     }
-}
 
-impl Lexer {
     fn is_eof(&self) -> bool {
         self.curr >= self.data.len()
     }
@@ -211,18 +442,21 @@ impl Lexer {
     }
 
     fn peek_char(&self) -> char {
-        if self.is_eof() {
-            return '\x00';
-        }
-        self.data[self.curr]
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> char {
+        self.data.get(self.curr + n).copied().unwrap_or('\x00')
     }
 
     fn is_digit(&self, ch: char) -> bool {
-        return '0' <= ch && ch <= '9';
+        ch.is_ascii_digit()
     }
 
+    // Letters of any script, an approximation of Unicode's XID_Start
+    // without its tables. Digits, ASCII or not, may follow the first char.
     fn is_identifier(&self, ch: char) -> bool {
-        return ('a' <= ch && ch <= 'z') || ('A' <= ch && ch <= 'Z') || '_' == ch;
+        ch.is_alphabetic() || '_' == ch
     }
 
     fn is_next_char(&mut self, ch: char) -> bool {
@@ -230,14 +464,88 @@ impl Lexer {
             self.next_char();
             return true;
         }
-        return false;
+        false
     }
 
     fn is_whitespace(&self, ch: char) -> bool {
-        return ch == ' ' || ch == '\r' || ch == '\t' || ch == '\n';
+        ch == ' ' || ch == '\r' || ch == '\t' || ch == '\n'
     }
 }
 
+// What kind of character `ch` is when it can change how code reads without
+// showing, as in "trojan source" attacks: bidi controls reorder the text
+// around them, and invisible characters hide between what is shown.
+fn hidden(ch: char) -> Option<&'static str> {
+    match ch {
+        '\u{202A}'..='\u{202E}'
+        | '\u{2066}'..='\u{2069}'
+        | '\u{200E}'
+        | '\u{200F}'
+        | '\u{061C}' => Some("bidirectional control character"),
+        '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}' | '\u{180E}' => {
+            Some("invisible character")
+        }
+        _ => None,
+    }
+}
+
+fn flush_blank(blank: &mut Option<(Span, usize)>, out: &mut Vec<Trivia>) {
+    if let Some((span, count)) = blank.take() {
+        out.push(Trivia {
+            kind: TriviaKind::BlankLines(count),
+            span,
+        });
+    }
+}
+
+// Keywords are short ASCII words, so they are recognised from a stack buffer
+// rather than by building a String for every identifier.
+fn keyword(word: &[char]) -> Option<TokenType> {
+    let mut buffer = [0; token::MAX_KEYWORD_LEN];
+    if word.len() > buffer.len() || !word.iter().all(char::is_ascii) {
+        return None;
+    }
+    for (byte, ch) in buffer.iter_mut().zip(word) {
+        *byte = *ch as u8;
+    }
+    let word = std::str::from_utf8(&buffer[..word.len()]).expect("ASCII is valid UTF-8");
+    token::lookup_keyword(word)
+}
+
+// The hex digits of a `0x` number, skipping `_`. None for no digits or more
+// than a u64 holds.
+fn hex_value(digits: &[char]) -> Option<u64> {
+    let mut value: u64 = 0;
+    let mut any = false;
+    for ch in digits.iter().filter(|ch| **ch != '_') {
+        let digit = ch.to_digit(16)?;
+        value = value.checked_mul(16)?.checked_add(digit as u64)?;
+        any = true;
+    }
+    any.then_some(value)
+}
+
+// The longest decimal number copied to the stack to parse; longer ones, which
+// are rare, are collected into a String.
+const MAX_NUMBER_LEN: usize = 64;
+
+// A decimal number, skipping `_`. Its chars are all ASCII, as `number`
+// scanned them.
+fn decimal_value(digits: &[char]) -> Option<f64> {
+    let mut buffer = [0; MAX_NUMBER_LEN];
+    let mut len = 0;
+    for ch in digits.iter().filter(|ch| **ch != '_') {
+        if len == buffer.len() {
+            let digits: String = digits.iter().filter(|ch| **ch != '_').collect();
+            return numfmt::parse(&digits);
+        }
+        buffer[len] = *ch as u8;
+        len += 1;
+    }
+    let digits = std::str::from_utf8(&buffer[..len]).expect("ASCII is valid UTF-8");
+    numfmt::parse(digits)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -258,8 +566,8 @@ mod tests {
 
     #[test]
     fn test_symbols() {
-        let input = "(){}[],;+-*/% =&|!^<>
-        == != <= >= && || += -= *= /= %= ^= << >>";
+        let input = "(){}[],;:+-*/% =&|!^<>
+        == != <= >= && || += -= *= /= %= ^= << >> >>> <<= >>= >>>= => |> | >";
         let expected = vec![
             TokenType::LParen,
             TokenType::RParen,
@@ -269,6 +577,7 @@ mod tests {
             TokenType::RBrace,
             TokenType::Comma,
             TokenType::Semicolon,
+            TokenType::Colon,
             TokenType::Plus,
             TokenType::Minus,
             TokenType::Times,
@@ -295,6 +604,14 @@ mod tests {
             TokenType::XorEq,
             TokenType::LShift,
             TokenType::RShift,
+            TokenType::URShift,
+            TokenType::LShiftEq,
+            TokenType::RShiftEq,
+            TokenType::URShiftEq,
+            TokenType::FatArrow,
+            TokenType::Pipe,
+            TokenType::Or,
+            TokenType::Gt,
         ];
         test_lexers(input, expected);
     }
@@ -362,13 +679,114 @@ mod tests {
         test_lexers(input, expected);
     }
 
+    #[test]
+    fn test_number_forms() {
+        let input = "0xFF 0x1_0 1_000 1e3 2.5E-1 1_000.5";
+        let expected = vec![
+            TokenType::Number(255.0),
+            TokenType::Number(16.0),
+            TokenType::Number(1000.0),
+            TokenType::Number(1000.0),
+            TokenType::Number(0.25),
+            TokenType::Number(1000.5),
+        ];
+        test_lexers(input, expected);
+
+        // a dot ends the number unless a digit follows it
+        let input = "3.floor 1.5.abs 2.";
+        let expected = vec![
+            TokenType::Number(3.0),
+            TokenType::Dot,
+            TokenType::Identifier("floor".to_string()),
+            TokenType::Number(1.5),
+            TokenType::Dot,
+            TokenType::Identifier("abs".to_string()),
+            TokenType::Number(2.0),
+            TokenType::Dot,
+        ];
+        test_lexers(input, expected);
+
+        // as the standard parsers read the same text without separators,
+        // including numbers too long to copy to the stack
+        let long = "1_2".repeat(40) + ".5";
+        for input in [
+            "007",
+            "1_2.2_5e+10",
+            "9_007_199_254_740_993",
+            "1e400",
+            &long,
+        ] {
+            let mut lexer = Lexer::new(input.to_string());
+            let expected = input.replace('_', "").parse::<f64>().unwrap();
+            assert_eq!(lexer.scan(), Ok(TokenType::Number(expected)), "{input}");
+        }
+        let mut lexer = Lexer::new("0xFFFF_FFFF_FFFF_FFFF".to_string());
+        assert_eq!(lexer.scan(), Ok(TokenType::Number(u64::MAX as f64)));
+
+        for input in [
+            "1__0",
+            "1_",
+            "1_.5",
+            "1_e3",
+            "1.5_e1",
+            "1_E3",
+            "0x",
+            "0x1_0000_0000_0000_0000",
+        ] {
+            let mut lexer = Lexer::new(input.to_string());
+            let expected = Error::Value(format!("invalid number:'{input}'"));
+            assert_eq!(lexer.scan(), Err(expected));
+        }
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let input = "@{name} + @{_x1}";
+        let expected = vec![
+            TokenType::Placeholder("name".to_string()),
+            TokenType::Plus,
+            TokenType::Placeholder("_x1".to_string()),
+        ];
+        test_lexers(input, expected);
+
+        for (input, text) in [("@{}", "@{}"), ("@{1x}", "@{1x}"), ("@{a b}", "@{a")] {
+            let mut lexer = Lexer::new(input.to_string());
+            let expected = Error::Lex(format!("invalid placeholder:'{text}'"));
+            assert_eq!(lexer.scan(), Err(expected));
+        }
+    }
+
+    #[test]
+    fn test_raw_identifiers() {
+        let input = "`class` `x` `é1`.`in`";
+        let expected = vec![
+            TokenType::Identifier("class".to_string()),
+            TokenType::Identifier("x".to_string()),
+            TokenType::Identifier("é1".to_string()),
+            TokenType::Dot,
+            TokenType::Identifier("in".to_string()),
+        ];
+        test_lexers(input, expected);
+
+        for (input, text) in [
+            ("``", "``"),
+            ("`1x`", "`1x`"),
+            ("`a b`", "`a"),
+            ("`a", "`a"),
+        ] {
+            let mut lexer = Lexer::new(input.to_string());
+            let expected = Error::Lex(format!("invalid raw identifier:'{text}'"));
+            assert_eq!(lexer.scan(), Err(expected));
+        }
+    }
+
     #[test]
     fn test_unknown_character() {
         let input = "@ 1.2.3 \"this is untermintated string";
         let expected = vec![
-            Error::Syntax(format!("unknown character:'@'")),
-            Error::Value(format!("invalid number:'1.2.3'")),
-            Error::Syntax(format!("unterminated string")),
+            Error::Lex("unknown character:'@'".to_string()),
+            Error::Value("invalid number:'1.2.3'".to_string()),
+            Error::Lex("unterminated string".to_string()),
         ];
         let mut lexer = Lexer::new(input.to_string());
         let mut result = Vec::new();
@@ -382,4 +800,157 @@ mod tests {
         }
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_unicode_identifier() {
+        let input = "let πλάτος = ύψος2 * ε_1; iffy ıf";
+        let expected = vec![
+            TokenType::Let,
+            TokenType::Identifier("πλάτος".to_string()),
+            TokenType::Assign,
+            TokenType::Identifier("ύψος2".to_string()),
+            TokenType::Times,
+            TokenType::Identifier("ε_1".to_string()),
+            TokenType::Semicolon,
+            TokenType::Identifier("iffy".to_string()),
+            // keywords are ASCII, a dotless i does not spell `if`
+            TokenType::Identifier("ıf".to_string()),
+        ];
+        test_lexers(input, expected);
+
+        // a name whose chars take several bytes ends where its chars do
+        let mut lexer = Lexer::new("let πλάτος = 2;".to_string());
+        lexer.next_token();
+        let name = lexer.next_token();
+        assert_eq!((name.span.start, name.span.end), (4, 10));
+        assert_eq!(lexer.source(&name.span), "πλάτος");
+    }
+
+    // The errors lexing `input` to the end runs into.
+    fn lexer_errors(input: &str) -> Vec<ErrorInfo> {
+        let mut lexer = Lexer::new(input.to_string());
+        while lexer.next_token().token != TokenType::Eof {}
+        lexer.errors().to_vec()
+    }
+
+    #[test]
+    fn test_zero_width_space() {
+        let errors = lexer_errors("let λ = 1;\nlet a\u{200B}b = 2;");
+        let [err] = &errors[..] else {
+            panic!("{errors:?}");
+        };
+        assert_eq!(
+            err.error,
+            Error::Lex("invisible character U+200B is only allowed inside a string".to_string())
+        );
+        // spans count chars: `λ` is one, though it takes two bytes
+        assert_eq!((err.line(), err.span().start, err.span().end), (2, 16, 17));
+    }
+
+    #[test]
+    fn test_bidi_override() {
+        let errors = lexer_errors("let a = 1; \u{202E} print a;");
+        assert_eq!(
+            errors.iter().map(|err| &err.error).collect::<Vec<_>>(),
+            [&Error::Lex(
+                "bidirectional control character U+202E is only allowed inside a string"
+                    .to_string()
+            )]
+        );
+        assert_eq!((errors[0].span().start, errors[0].span().end), (11, 12));
+
+        // comments are code too, and the rest of one is still skipped
+        let errors = lexer_errors("# \u{2067}x\u{2069}\nlet a = 1;");
+        assert_eq!(errors.len(), 2);
+
+        let input = "\"admin\u{202E} \u{2066}// check\u{2069}\"";
+        assert!(lexer_errors(input).is_empty());
+        test_lexers(
+            input,
+            vec![TokenType::String(
+                "admin\u{202E} \u{2066}// check\u{2069}".to_string(),
+            )],
+        );
+    }
+
+    // Every token with trivia, as its source text and the trivia before it,
+    // comments as written and blank-line runs as their count.
+    fn trivia(input: &str) -> Vec<(String, Vec<String>)> {
+        let mut lexer = Lexer::with_mode(input.to_string(), LexerMode::WithTrivia);
+        let mut tokens = Vec::new();
+        loop {
+            let info = lexer.next_token();
+            let trivia: Vec<_> = info
+                .leading_trivia
+                .iter()
+                .map(|trivia| match &trivia.kind {
+                    TriviaKind::Comment(text) => {
+                        assert_eq!(&lexer.source(&trivia.span), text);
+                        text.clone()
+                    }
+                    TriviaKind::BlankLines(count) => {
+                        let text = lexer.source(&trivia.span);
+                        assert_eq!(text.matches('\n').count(), *count);
+                        assert!(text.trim().is_empty());
+                        format!("{count} blank")
+                    }
+                })
+                .collect();
+            if !trivia.is_empty() {
+                tokens.push((lexer.source(&info.span), trivia));
+            }
+            if info.token == TokenType::Eof {
+                break tokens;
+            }
+        }
+    }
+
+    #[test]
+    fn test_trivia() {
+        let input = "# Adds two numbers.
+#   a, b: numbers
+fn add(a, b) {
+  return a + b; # no overflow checks
+}
+
+  \t
+# the end";
+        let owned = |texts: &[&str]| texts.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            trivia(input),
+            [
+                (
+                    "fn".to_string(),
+                    owned(&["# Adds two numbers.", "#   a, b: numbers"])
+                ),
+                ("}".to_string(), owned(&["# no overflow checks"])),
+                ("".to_string(), owned(&["2 blank", "# the end"])),
+            ]
+        );
+
+        // a file of nothing but comments hands them all to `Eof`
+        assert_eq!(
+            trivia("\n# one\n\n\n# two\n"),
+            [(
+                "".to_string(),
+                owned(&["1 blank", "# one", "2 blank", "# two"])
+            )]
+        );
+        assert_eq!(trivia(""), []);
+    }
+
+    #[test]
+    fn test_default_mode_skips_trivia() {
+        let input = "# doc\nlet a = 1; # note\n\nprint a;\n";
+        let mut plain = Lexer::new(input.to_string());
+        let mut rich = Lexer::with_mode(input.to_string(), LexerMode::WithTrivia);
+        loop {
+            let (plain, rich) = (plain.next_token(), rich.next_token());
+            assert!(plain.leading_trivia.is_empty());
+            assert_eq!((&plain.token, &plain.span), (&rich.token, &rich.span));
+            if plain.token == TokenType::Eof {
+                break;
+            }
+        }
+    }
 }
@@ -1,25 +1,117 @@
+//! An interpreter for a small scripting language. `run_source` takes a
+//! script through every stage, lexing, parsing, resolving and running it:
+//!
+//! ```
+//! use rlisp::{run_source, Object, RunOptions};
+//!
+//! let options = RunOptions {
+//!     capture_output: true,
+//!     ..RunOptions::default()
+//! };
+//! let outcome = run_source("print \"hi\"; 1 + 2;", options).unwrap();
+//! assert_eq!(outcome.output.as_deref(), Some("hi\n"));
+//! assert_eq!(outcome.value, Some(Object::Number(3.0)));
+//! ```
+//!
+//! `run_file` does the same for a script on disk, and `parse` and `check`
+//! stop short of running it. Hosts that need more control, e.g. to keep
+//! globals between runs, use the stages directly: `Parser`, `Resolver` and
+//! `Interpretor`. `prelude` gathers what most hosts need.
+//!
+//! What the crate root exports is its stable API. Tokens, visitors and the
+//! other pieces editors and other tools reach for are in `internals`, which
+//! may change in any release.
+
 mod token;
 pub use token::Span;
-pub use token::{TokenInfo, TokenType};
+pub(crate) use token::{TokenInfo, TokenType, Trivia, TriviaKind, KEYWORDS};
 
 mod error;
-pub use error::{Error, ErrorInfo};
+pub use error::{
+    ColorChoice, Diagnostic, DiagnosticSet, Error, ErrorInfo, Snippet, Style, Suppressions,
+    Warning, WARNING_CODES,
+};
 
 mod lexer;
 pub use lexer::Lexer;
+pub(crate) use lexer::LexerMode;
 
 mod ast;
-pub use ast::visitor;
-pub use ast::{Expr, LiteralType, Stmt};
+pub(crate) use ast::visitor;
+pub use ast::{
+    CacheError, Expr, FieldInit, ImportForm, ImportName, LiteralType, MatchArm, NamedArg, Pattern,
+    Program, Signature, Stmt, FORMAT_VERSION,
+};
 
 mod parser;
-pub use parser::Parser;
+pub use parser::{Dialect, Parser, ParserOptions};
+
+mod symbols;
+pub use symbols::{analyze, rename, Reference, Symbol, SymbolId, SymbolIndex, SymbolKind};
+
+mod fold;
+pub use fold::{eliminate_dead_code, fold_constants};
+
+mod lower;
+pub use lower::lower;
+
+mod resolver;
+pub use resolver::Resolver;
+
+mod numfmt;
 
 mod object;
 pub use object::Object;
 
 mod environment;
-pub use environment::Environment;
+pub(crate) use environment::Environment;
 
 mod interpretor;
-pub use interpretor::Interpretor;
+pub use interpretor::{
+    Clock, Completion, CompletionKind, Coverage, InterpreterHooks, Interpretor, LineCount,
+    LogLevel, ResumeHandle, RunState, ScriptLogger, Stats, StderrLogger, SystemClock, VirtualClock,
+};
+
+mod docs;
+pub use docs::document;
+
+mod diff;
+pub use diff::{diff_programs, AstChange, ChangeKind};
+
+mod project;
+pub use project::{FileCheck, Project, ProjectError};
+
+mod pipeline;
+pub use pipeline::{check, parse, run_file, run_program, run_source, RunOptions, RunOutcome};
+
+mod modules;
+pub use modules::{
+    resolve_specifier, FsModuleSource, LayeredModuleSource, MemoryModuleSource, ModuleError,
+    ModuleSource, SourceFile,
+};
+
+mod repl;
+pub use repl::{Disk, Host, Repl, ReplHistory, Terminal, CONTINUATION_PROMPT};
+
+/// What most hosts need, for `use rlisp::prelude::*;`: running and checking
+/// scripts, the values they give back, and what is reported when they fail.
+pub mod prelude {
+    pub use crate::{
+        check, parse, run_file, run_program, run_source, Diagnostic, Error, ErrorInfo, Interpretor,
+        Object, ParserOptions, Program, RunOptions, RunOutcome, Warning,
+    };
+}
+
+/// The lower-level pieces tools such as editors and formatters build on:
+/// tokens and their trivia, the incremental lexer, AST visitors and node
+/// ids, environments and native functions. Unlike the crate root, these
+/// may change in any release, so they are left out of the documentation.
+#[doc(hidden)]
+pub mod internals {
+    pub use crate::ast::visitor;
+    pub use crate::ast::{Node, NodeId};
+    pub use crate::environment::Environment;
+    pub use crate::lexer::{IncrementalLexer, LexerMode};
+    pub use crate::object::{NativeFn, SmallStr};
+    pub use crate::token::{TokenInfo, TokenType, Trivia, TriviaKind, KEYWORDS};
+}
@@ -0,0 +1,424 @@
+use crate::ast::{NodeId, Program};
+use crate::{Expr, LiteralType, Span, Stmt, TokenInfo, TokenType};
+
+/// Rewrites sugar into the core statements the interpreter runs.
+///
+/// `for (init; condition; increment) body` becomes
+///
+/// ```text
+/// { init; while (condition) { body; increment; } }
+/// ```
+///
+/// with a `true` literal for a missing condition. The block keeps the id of
+/// the `for` statement.
+///
+/// `for (let i = start to end step s) body` counts up to and including
+/// `end`, or down to it when the step is negative, and becomes
+///
+/// ```text
+/// { let i = start; const for.end = end; const for.by = for.step(s);
+///   while ((i - for.end) * for.by <= 0) { body; i = i + for.by; } }
+/// ```
+///
+/// with a step of 1 when none is given. The bound and the step are evaluated
+/// once, before the first iteration, and the native `for.step` fails on a
+/// step of zero. Names with a `.` cannot be written in a script, so these
+/// never clash with its own, and the constant is not called `for.step` so
+/// that a loop nested inside still finds the native.
+///
+/// Every node that did not exist in the source is recorded with the span of
+/// the `for` keyword, so `Program::is_synthetic` tells tools to step over it,
+/// while errors in the user's own parts still point at their original lines.
+pub fn lower(mut program: Program) -> Program {
+    let mut stmts = std::mem::take(&mut program.stmts);
+    for stmt in &mut stmts {
+        lower_stmt(stmt, &mut program);
+    }
+    program.stmts = stmts;
+    program
+}
+
+fn lower_stmt(stmt: &mut Stmt, program: &mut Program) {
+    match stmt {
+        Stmt::Expr { expr, .. } | Stmt::Destructure { value: expr, .. } => {
+            lower_expr(expr, program)
+        }
+        Stmt::Print { exprs, .. } => exprs.iter_mut().for_each(|expr| lower_expr(expr, program)),
+        Stmt::Let { value, .. } | Stmt::Return { value, .. } => {
+            if let Some(value) = value {
+                lower_expr(value, program);
+            }
+        }
+        Stmt::Block { stmts, .. } => stmts.iter_mut().for_each(|stmt| lower_stmt(stmt, program)),
+        Stmt::Function {
+            requires,
+            ensures,
+            body,
+            ..
+        } => {
+            requires
+                .iter_mut()
+                .chain(ensures)
+                .for_each(|expr| lower_expr(expr, program));
+            body.iter_mut().for_each(|stmt| lower_stmt(stmt, program));
+        }
+        Stmt::Class {
+            super_class,
+            mixins,
+            methods,
+            fields,
+            ..
+        } => {
+            super_class
+                .iter_mut()
+                .chain(mixins)
+                .chain(fields.iter_mut().map(|field| &mut field.value))
+                .for_each(|expr| lower_expr(expr, program));
+            methods
+                .iter_mut()
+                .for_each(|stmt| lower_stmt(stmt, program));
+        }
+        Stmt::If {
+            condition,
+            truthy,
+            falsy,
+            ..
+        } => {
+            lower_expr(condition, program);
+            lower_stmt(truthy, program);
+            if let Some(falsy) = falsy {
+                lower_stmt(falsy, program);
+            }
+        }
+        Stmt::While {
+            condition, body, ..
+        } => {
+            lower_expr(condition, program);
+            lower_stmt(body, program);
+        }
+        Stmt::For { .. } => lower_for(stmt, program),
+        Stmt::ForRange { .. } => lower_for_range(stmt, program),
+        Stmt::Defer { body, .. } => lower_stmt(body, program),
+        Stmt::Break { .. }
+        | Stmt::Continue { .. }
+        | Stmt::Import { .. }
+        | Stmt::Empty { .. }
+        | Stmt::Error { .. } => {}
+    }
+}
+
+fn lower_for(stmt: &mut Stmt, program: &mut Program) {
+    let id = stmt.id();
+    let Stmt::For {
+        initializer,
+        condition,
+        increment,
+        body,
+        span,
+        ..
+    } = stmt
+    else {
+        unreachable!("lower_for is only called on for loops")
+    };
+
+    let mut initializer = initializer.take().map(|initializer| *initializer);
+    let mut condition = condition.take();
+    let mut increment = increment.take();
+    let mut body = body.take();
+    let span = span.clone();
+    if let Some(initializer) = &mut initializer {
+        lower_stmt(initializer, program);
+    }
+    condition
+        .iter_mut()
+        .chain(&mut increment)
+        .for_each(|expr| lower_expr(expr, program));
+    lower_stmt(&mut body, program);
+
+    let condition = condition.unwrap_or_else(|| Expr::Literal {
+        id: program.synthetic_id(&span),
+        value: LiteralType::Boolean(true),
+        span: span.clone(),
+    });
+    if let Some(expr) = increment {
+        let increment = Stmt::Expr {
+            id: program.synthetic_id(&span),
+            expr,
+        };
+        body = Stmt::Block {
+            id: program.synthetic_id(&span),
+            stmts: vec![body, increment],
+        };
+    }
+    let while_stmt = Stmt::While {
+        id: program.synthetic_id(&span),
+        condition,
+        body: Box::new(body),
+        span: span.clone(),
+    };
+    program.mark_synthetic(id, &span);
+    *stmt = Stmt::Block {
+        id,
+        stmts: initializer.into_iter().chain([while_stmt]).collect(),
+    };
+}
+
+fn lower_for_range(stmt: &mut Stmt, program: &mut Program) {
+    let id = stmt.id();
+    let Stmt::ForRange {
+        initializer,
+        end,
+        step,
+        body,
+        span,
+        ..
+    } = stmt
+    else {
+        unreachable!("lower_for_range is only called on counting loops")
+    };
+    let (mut initializer, mut end, step) = (initializer.take(), end.take(), step.take());
+    let (mut body, span) = (body.take(), span.clone());
+    lower_stmt(&mut initializer, program);
+    lower_expr(&mut end, program);
+    lower_stmt(&mut body, program);
+    let Stmt::Let { name: counter, .. } = &initializer else {
+        unreachable!("the parser only builds counting loops on a let")
+    };
+    let counter = counter.clone();
+
+    let mut new = Synthetic { program, span };
+    let step = match step {
+        Some(mut step) => {
+            lower_expr(&mut step, new.program);
+            step
+        }
+        None => new.number(1.0),
+    };
+    let callee = new.variable("for.step");
+    let check = Expr::Call {
+        id: new.id(),
+        callee: Box::new(callee),
+        args: vec![step],
+        named: Vec::new(),
+        span: new.span.clone(),
+    };
+    let end = new.constant("for.end", end);
+    let step = new.constant("for.by", check);
+
+    let (i, bound) = (new.variable(&counter), new.variable("for.end"));
+    let distance = new.binary(i, TokenType::Minus, bound);
+    let by = new.variable("for.by");
+    let scaled = new.binary(distance, TokenType::Times, by);
+    let zero = new.number(0.0);
+    let condition = new.binary(scaled, TokenType::Lte, zero);
+    let (i, by) = (new.variable(&counter), new.variable("for.by"));
+    let next = new.binary(i, TokenType::Plus, by);
+    let increment = Stmt::Expr {
+        id: new.id(),
+        expr: Expr::Assign {
+            id: new.id(),
+            name: counter,
+            value: Box::new(next),
+            span: new.span.clone(),
+        },
+    };
+    let body = Stmt::Block {
+        id: new.id(),
+        stmts: vec![body, increment],
+    };
+    let while_stmt = Stmt::While {
+        id: new.id(),
+        condition,
+        body: Box::new(body),
+        span: new.span.clone(),
+    };
+    new.program.mark_synthetic(id, &new.span);
+    *stmt = Stmt::Block {
+        id,
+        stmts: vec![initializer, end, step, while_stmt],
+    };
+}
+
+// Builds the nodes a lowering adds, each recorded as generated from the
+// construct at `span`.
+struct Synthetic<'a> {
+    program: &'a mut Program,
+    span: Span,
+}
+
+impl Synthetic<'_> {
+    fn id(&mut self) -> NodeId {
+        self.program.synthetic_id(&self.span)
+    }
+
+    fn constant(&mut self, name: &str, value: Expr) -> Stmt {
+        Stmt::Let {
+            id: self.id(),
+            name: name.to_string(),
+            annotation: None,
+            value: Some(value),
+            is_const: true,
+            span: self.span.clone(),
+        }
+    }
+
+    fn variable(&mut self, name: &str) -> Expr {
+        Expr::Variable {
+            id: self.id(),
+            name: name.to_string(),
+            span: self.span.clone(),
+        }
+    }
+
+    fn number(&mut self, n: f64) -> Expr {
+        Expr::Literal {
+            id: self.id(),
+            value: LiteralType::Number(n, None),
+            span: self.span.clone(),
+        }
+    }
+
+    fn binary(&mut self, left: Expr, op: TokenType, right: Expr) -> Expr {
+        Expr::Binary {
+            id: self.id(),
+            left: Box::new(left),
+            op: TokenInfo {
+                token: op,
+                span: self.span.clone(),
+                leading_trivia: Vec::new(),
+            },
+            right: Box::new(right),
+        }
+    }
+}
+
+// Function bodies in class expressions can hold for loops too.
+fn lower_expr(expr: &mut Expr, program: &mut Program) {
+    if let Expr::Class {
+        methods, fields, ..
+    } = expr
+    {
+        methods
+            .iter_mut()
+            .for_each(|stmt| lower_stmt(stmt, program));
+        fields
+            .iter_mut()
+            .for_each(|field| lower_expr(&mut field.value, program));
+    }
+    expr.children_mut()
+        .into_iter()
+        .for_each(|expr| lower_expr(expr, program));
+}
+
+#[cfg(test)]
+mod test {
+    use super::lower;
+    use crate::ast::{Node, NodeId};
+    use crate::{Interpretor, Lexer, Parser, Program, Stmt};
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap()
+    }
+
+    // What a debugger stepping statement by statement would stop at.
+    fn steps<'a>(program: &Program, stmt: &'a Stmt, out: &mut Vec<&'a Stmt>) {
+        if !program.is_synthetic(stmt.id()) {
+            out.push(stmt);
+        }
+        match stmt {
+            Stmt::Block { stmts, .. } => stmts.iter().for_each(|s| steps(program, s, out)),
+            Stmt::While { body, .. } => steps(program, body, out),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_lower_for() {
+        let program = lower(parse("for (let i = 0; i < 2; i = i + 1) print i;"));
+        assert_eq!(
+            program.to_string(),
+            "((block (let i 0) (while (< i 2) (block (print i) (= i (+ i 1))))))"
+        );
+        let program = lower(parse("for (;;) print 1;"));
+        assert_eq!(program.to_string(), "((block (while true (print 1))))");
+        let synthetic: Vec<_> = (0..program.node_count() as u32)
+            .map(NodeId)
+            .filter(|id| program.is_synthetic(*id))
+            .collect();
+        assert_eq!(synthetic.len(), 3, "block, while and the true literal");
+        for id in synthetic {
+            assert_eq!(program.synthetic_span(id).unwrap().line, 1);
+        }
+    }
+
+    #[test]
+    fn test_lower_for_range() {
+        let program = lower(parse("for (let i = 0 to 4 step 2) print i;"));
+        assert_eq!(
+            program.to_string(),
+            "((block (let i 0) (const for.end 4) (const for.by (call for.step 2)) \
+             (while (<= (* (- i for.end) for.by) 0) (block (print i) (= i (+ i for.by))))))"
+        );
+        let program = lower(parse("for (let i = 0 to 4) print i;"));
+        assert!(program
+            .to_string()
+            .contains("(const for.by (call for.step 1))"));
+
+        // what the loop adds is the `for` keyword's; the user's parts keep
+        // their own lines
+        let program = lower(parse("for (let i = 0\n     to nope)\n  print i;"));
+        let synthetic: Vec<_> = (0..program.node_count() as u32)
+            .map(NodeId)
+            .filter(|id| program.is_synthetic(*id))
+            .collect();
+        assert_eq!(synthetic.len(), 20);
+        for id in synthetic {
+            assert_eq!(program.synthetic_span(id).unwrap().line, 1);
+        }
+        let mut interpretor = Interpretor::buffered();
+        let err = interpretor.run(&program).unwrap_err();
+        assert_eq!(err.line(), 2);
+        let mut out = Vec::new();
+        steps(&program, &program.stmts[0], &mut out);
+        let out: Vec<_> = out.iter().map(|stmt| stmt.to_string()).collect();
+        assert_eq!(out, ["(let i 0)", "(print i)"]);
+    }
+
+    #[test]
+    fn test_lowered_ids_are_unique() {
+        let program = lower(parse(
+            "{ for (let i = 0; i < 3; i = i + 1) { for (;;) print i; for (let j = i to 0) ; } }",
+        ));
+        let mut seen = std::collections::HashSet::new();
+        for id in 0..program.node_count() as u32 {
+            let node = program.node(NodeId(id)).expect("every id is reachable");
+            let found = match node {
+                Node::Expr(expr) => expr.id(),
+                Node::Stmt(stmt) => stmt.id(),
+            };
+            assert!(seen.insert(found));
+        }
+    }
+
+    #[test]
+    fn test_steps_skip_synthetic_nodes() {
+        let program = lower(parse("for (let i = 0; i < 2; i = i + 1) print i;"));
+        let mut out = Vec::new();
+        steps(&program, &program.stmts[0], &mut out);
+        let out: Vec<_> = out.iter().map(|stmt| stmt.to_string()).collect();
+        assert_eq!(out, ["(let i 0)", "(print i)"]);
+    }
+
+    #[test]
+    fn test_error_in_increment_keeps_its_line() {
+        let program = lower(parse(
+            "for (let i = 0;\n     i < 2;\n     i = i + nope)\n  print i;",
+        ));
+        let mut interpretor = Interpretor::buffered();
+        let err = interpretor.run(&program).unwrap_err();
+        assert_eq!(err.line(), 3);
+        assert_eq!(interpretor.take_output(), "0\n");
+    }
+}
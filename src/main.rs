@@ -1,20 +1,475 @@
+use rlisp::{
+    diff_programs, document, run_program, run_source, ColorChoice, Diagnostic, DiagnosticSet,
+    Dialect, Object, ParserOptions, Program, Project, Repl, ReplHistory, RunOptions, Style,
+    Terminal,
+};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::{env, fs, process, thread};
 use std::{fs::File, io::Read};
-use std::{env, process};
-use rlisp::{Interpretor, Lexer, Parser};
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("[usuage] rlisp <file_name>");
-        process::exit(0);
+fn repl(lenient: bool, history: Option<PathBuf>) {
+    let mut repl = Repl::new();
+    repl.set_lenient(lenient);
+    let mut history = match history.or_else(ReplHistory::default_path) {
+        Some(path) => ReplHistory::load(&path).unwrap_or_else(|err| {
+            eprintln!("cannot load history from {}: {err}", path.display());
+            ReplHistory::new()
+        }),
+        None => ReplHistory::new(),
+    };
+    let stdin = io::stdin();
+    let mut terminal = Terminal::new(stdin.lock(), io::stdout());
+    terminal.set_bracketed_paste(stdin.is_terminal());
+    if let Err(err) = terminal.run(&mut repl, &mut history) {
+        eprintln!("{err}");
     }
-    let file_name = &args[1];
+}
+
+const USAGE: &str = "[usuage] rlisp [--optimize] [--coverage] [--deterministic] [--no-prelude] [--no-contracts] [--strict-types] [--stats] [--lenient-semicolons] [--dialect=strict|teaching|permissive] [--color=auto|always|never] [--warnings-first] [--error-format=human|json] [--max-heap-bytes=N] [--max-call-depth=N] [file_name [args...]]
+        rlisp [--lenient-globals] [--history=path]
+        rlisp [--lenient-semicolons] [--dialect=strict|teaching|permissive] [--color=auto|always|never] compile file_name -o cache_file
+        rlisp [--optimize] [--coverage] [--deterministic] [--no-prelude] [--no-contracts] [--strict-types] [--stats] [--lenient-semicolons] [--dialect=strict|teaching|permissive] [--color=auto|always|never] [--warnings-first] [--error-format=human|json] [--max-heap-bytes=N] [--max-call-depth=N] run file_name|cache_file|directory [args...]
+        rlisp [--lenient-semicolons] [--dialect=strict|teaching|permissive] [--color=auto|always|never] [--warnings-first] [--error-format=human|json] check directory
+        rlisp [--optimize] [--deterministic] [--no-prelude] [--no-contracts] [--strict-types] [--lenient-semicolons] [--dialect=strict|teaching|permissive] [--color=auto|always|never] [--warnings-first] [--error-format=human|json] [--max-heap-bytes=N] [--max-call-depth=N] test file_name|directory
+        rlisp [--lenient-semicolons] [--dialect=strict|teaching|permissive] [--color=auto|always|never] doc file_name|directory
+        rlisp [--lenient-semicolons] [--dialect=strict|teaching|permissive] [--color=auto|always|never] diff old_file new_file";
+
+fn read(file_name: &str) -> String {
     let mut data = String::new();
     let mut f = File::open(file_name).expect("Unable to open file");
     f.read_to_string(&mut data).expect("Unable to read string");
-    let lexer = Lexer::new(data);
-    let mut parser = Parser::new(lexer);
-    let program = parser.parse_program().unwrap();
-    let mut interpretor = Interpretor::new();
-    interpretor.interpret(program);
+    data
+}
+
+fn parse(source: &str, options: ParserOptions, style: Style) -> Program {
+    match rlisp::parse(source, options) {
+        Ok((program, warnings)) => {
+            for warning in warnings {
+                warning.report(style);
+            }
+            program
+        }
+        Err(diagnostics) => {
+            for diagnostic in diagnostics {
+                diagnostic.report_in(Some(source), style);
+            }
+            process::exit(1);
+        }
+    }
+}
+
+// Writes the parsed program so `run` can skip lexing and parsing it.
+fn compile(file_name: &str, out: &str, options: ParserOptions, style: Style) {
+    let source = read(file_name);
+    let program = parse(&source, options, style);
+    fs::write(out, program.to_bytes(&source)).expect("Unable to write cache");
+}
+
+// A cache is checked against the script it was compiled from when that is
+// found next to it, e.g. `script.lang` for `script.astc`.
+fn load(file_name: &str) -> (Program, Option<String>) {
+    let bytes = fs::read(file_name).expect("Unable to open file");
+    let source = fs::read_to_string(Path::new(file_name).with_extension("lang")).ok();
+    match Program::from_bytes(&bytes, source.as_deref()) {
+        Ok(program) => (program, source),
+        Err(err) => {
+            eprintln!("{file_name}: {err}");
+            process::exit(1);
+        }
+    }
+}
+
+// A directory runs as a project: its `main.lang`, or the entry its
+// `project.toml` names. Imports are found under the project's directory,
+// or under the script's when it is a single file.
+fn script(path: &str, syntax: Syntax) -> (String, ParserOptions, PathBuf) {
+    if !Path::new(path).is_dir() {
+        let root = Path::new(path).parent().unwrap_or(Path::new(""));
+        return (
+            path.to_string(),
+            syntax.options(ParserOptions::default()),
+            root.to_path_buf(),
+        );
+    }
+    let project = Project::open(Path::new(path));
+    match project.and_then(|project| Ok((project.entry()?, project.parser_options()))) {
+        Ok((entry, pinned)) => (
+            entry.to_string_lossy().into_owned(),
+            syntax.options(pinned),
+            PathBuf::from(path),
+        ),
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            process::exit(1);
+        }
+    }
+}
+
+// The parser settings given on the command line.
+#[derive(Clone, Copy)]
+struct Syntax {
+    dialect: Option<Dialect>,
+    lenient_semicolons: bool,
+}
+
+impl Syntax {
+    // The options to parse with: those of the dialect if one was given,
+    // otherwise those a project pinned, then `--lenient-semicolons` on top.
+    fn options(self, pinned: ParserOptions) -> ParserOptions {
+        let mut options = self.dialect.map_or(pinned, Dialect::options);
+        options.lenient_semicolons |= self.lenient_semicolons;
+        options
+    }
+}
+
+// How the diagnostics of `run` and `check` are shown.
+#[derive(Clone, Copy)]
+struct Report {
+    warnings_first: bool,
+    json: bool,
+    style: Style,
+}
+
+impl Report {
+    // Prints `diagnostics` to stderr, sorted, as text with the lines of
+    // `sources` they point at, or as JSON.
+    fn show(self, mut diagnostics: DiagnosticSet, sources: &HashMap<&str, &str>) {
+        diagnostics.warnings_first = self.warnings_first;
+        diagnostics.sort_dedup();
+        if self.json {
+            eprintln!("{}", diagnostics.to_json());
+        } else {
+            eprint!("{}", diagnostics.render_all(sources, self.style));
+        }
+    }
+}
+
+// Parses and resolves every script of the project at `root`, printing each
+// problem with the path of its file relative to the root.
+fn check(root: &str, syntax: Syntax, report: Report) {
+    let checks = Project::open(Path::new(root))
+        .and_then(|project| project.check(syntax.options(project.parser_options())));
+    let checks = checks.unwrap_or_else(|err| {
+        eprintln!("{root}: {err}");
+        process::exit(1);
+    });
+    let mut failed = 0;
+    let mut diagnostics = DiagnosticSet::new();
+    let paths: Vec<_> = checks
+        .iter()
+        .map(|check| check.path.display().to_string())
+        .collect();
+    let mut sources = HashMap::new();
+    for (check, path) in checks.iter().zip(&paths) {
+        for warning in &check.warnings {
+            diagnostics.push(path, Diagnostic::Warning(warning.clone()));
+        }
+        for err in &check.errors {
+            diagnostics.push(path, Diagnostic::Error(err.clone()));
+        }
+        if !check.errors.is_empty() {
+            failed += 1;
+        }
+        sources.insert(path.as_str(), check.source.as_str());
+    }
+    report.show(diagnostics, &sources);
+    println!("checked {} files, {failed} with errors", checks.len());
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+// Prints the Markdown reference of a script, or of every script of the
+// project at `path`, one page after another, each headed by its path.
+fn doc(path: &str, syntax: Syntax, style: Style) {
+    if !Path::new(path).is_dir() {
+        let program = parse(&read(path), syntax.options(ParserOptions::default()), style);
+        print!("{}", document(path, &program));
+        return;
+    }
+    let project = Project::open(Path::new(path));
+    let files = project.and_then(|project| Ok((project.files()?, project.parser_options())));
+    let (files, pinned) = files.unwrap_or_else(|err| {
+        eprintln!("{path}: {err}");
+        process::exit(1);
+    });
+    for (index, file) in files.iter().enumerate() {
+        let full = Path::new(path).join(file);
+        let program = parse(
+            &read(&full.to_string_lossy()),
+            syntax.options(pinned),
+            style,
+        );
+        if index > 0 {
+            println!();
+        }
+        print!("{}", document(&file.display().to_string(), &program));
+    }
+}
+
+// Prints how the program in `new` differs from the one in `old`, one change
+// per line, ignoring formatting and comments.
+fn diff(old: &str, new: &str, syntax: Syntax, style: Style) {
+    let options = syntax.options(ParserOptions::default());
+    let old = parse(&read(old), options, style);
+    let new = parse(&read(new), options, style);
+    for change in diff_programs(&old, &new) {
+        println!("{change}");
+    }
+}
+
+// Enough for `--max-call-depth` calls, however much stack each takes in an
+// unoptimized build, so deep recursion fails with an error rather than
+// overflowing. Only the pages a run touches are ever committed.
+const STACK: usize = 256 * 1024 * 1024;
+
+fn main() {
+    let cli = thread::Builder::new().stack_size(STACK).spawn(cli);
+    if cli.expect("failed to start the CLI thread").join().is_err() {
+        // the panic has been reported by the thread
+        process::exit(101);
+    }
+}
+
+fn cli() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // `--optimize` removes dead code before running, warning about each removal
+    let optimize = args.iter().any(|arg| arg == "--optimize");
+    // `--coverage` prints the script with how often each line ran
+    let coverage = args.iter().any(|arg| arg == "--coverage");
+    // `--lenient-semicolons` warns about a missing `;` at the end of a line
+    // instead of failing
+    let lenient_semicolons = args.iter().any(|arg| arg == "--lenient-semicolons");
+    // `--stats` prints the interpreter's counters to stderr after the run
+    let stats = args.iter().any(|arg| arg == "--stats");
+    // `--deterministic` runs on a virtual clock, so every run prints the same
+    let deterministic = args.iter().any(|arg| arg == "--deterministic");
+    // `--no-prelude` runs the script without the functions of the prelude
+    let prelude = !args.iter().any(|arg| arg == "--no-prelude");
+    // `--no-contracts` skips the `requires` and `ensures` clauses of functions
+    let contracts = !args.iter().any(|arg| arg == "--no-contracts");
+    // `--strict-types` checks values against the types annotating them
+    let strict_types = args.iter().any(|arg| arg == "--strict-types");
+    // `--lenient-globals` lets REPL input assign to names it never declared
+    let lenient_globals = args.iter().any(|arg| arg == "--lenient-globals");
+    // `--color=auto|always|never` picks whether errors and warnings on
+    // stderr are colored; `auto` colors a terminal unless `NO_COLOR` is set
+    let mut color = ColorChoice::Auto;
+    for arg in &args {
+        let Some(value) = arg.strip_prefix("--color=") else {
+            continue;
+        };
+        color = ColorChoice::parse(value).unwrap_or_else(|| {
+            eprintln!("{USAGE}");
+            process::exit(0);
+        });
+    }
+    // `--dialect=strict|teaching|permissive` parses with that dialect's
+    // options instead of those a project pins
+    let mut dialect = None;
+    for arg in &args {
+        let Some(value) = arg.strip_prefix("--dialect=") else {
+            continue;
+        };
+        dialect = Some(Dialect::parse(value).unwrap_or_else(|| {
+            eprintln!("{USAGE}");
+            process::exit(0);
+        }));
+    }
+    let syntax = Syntax {
+        dialect,
+        lenient_semicolons,
+    };
+    // `--error-format=human|json` prints diagnostics as text, or as a JSON
+    // array for tools
+    let mut json = false;
+    for arg in &args {
+        let Some(value) = arg.strip_prefix("--error-format=") else {
+            continue;
+        };
+        json = match value {
+            "human" => false,
+            "json" => true,
+            _ => {
+                eprintln!("{USAGE}");
+                process::exit(0);
+            }
+        };
+    }
+    // `--max-heap-bytes=N` stops the script once its values take more than
+    // about N bytes
+    let mut max_heap_bytes = None;
+    for arg in &args {
+        let Some(value) = arg.strip_prefix("--max-heap-bytes=") else {
+            continue;
+        };
+        max_heap_bytes = Some(value.parse().unwrap_or_else(|_| {
+            eprintln!("{USAGE}");
+            process::exit(0);
+        }));
+    }
+    // `--max-call-depth=N` fails a call made while N calls are in progress,
+    // instead of the default 1000
+    let mut max_call_depth = RunOptions::default().max_call_depth;
+    for arg in &args {
+        let Some(value) = arg.strip_prefix("--max-call-depth=") else {
+            continue;
+        };
+        max_call_depth = Some(value.parse().unwrap_or_else(|_| {
+            eprintln!("{USAGE}");
+            process::exit(0);
+        }));
+    }
+    // `--history=path` keeps the REPL's history there instead of under the
+    // user's data directory
+    let mut history = None;
+    for arg in &args {
+        if let Some(value) = arg.strip_prefix("--history=") {
+            history = Some(PathBuf::from(value));
+        }
+    }
+    // `--warnings-first` shows each file's warnings before its errors
+    let warnings_first = args.iter().any(|arg| arg == "--warnings-first");
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let style = color.style(io::stderr().is_terminal(), no_color);
+    let report = Report {
+        warnings_first,
+        json,
+        style,
+    };
+    args.retain(|arg| {
+        !arg.starts_with("--color=")
+            && !arg.starts_with("--dialect=")
+            && !arg.starts_with("--error-format=")
+            && !arg.starts_with("--max-heap-bytes=")
+            && !arg.starts_with("--max-call-depth=")
+            && !arg.starts_with("--history=")
+            && !matches!(
+                arg.as_str(),
+                "--optimize"
+                    | "--coverage"
+                    | "--deterministic"
+                    | "--no-prelude"
+                    | "--no-contracts"
+                    | "--strict-types"
+                    | "--stats"
+                    | "--lenient-semicolons"
+                    | "--lenient-globals"
+                    | "--warnings-first"
+            )
+    });
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (file_name, script_args, entry) = match args[..] {
+        [] => {
+            repl(lenient_globals, history);
+            return;
+        }
+        ["compile", file_name, "-o", out] => {
+            compile(
+                file_name,
+                out,
+                syntax.options(ParserOptions::default()),
+                style,
+            );
+            return;
+        }
+        ["check", root] => {
+            check(root, syntax, report);
+            return;
+        }
+        ["doc", path] => {
+            doc(path, syntax, style);
+            return;
+        }
+        ["diff", old, new] => {
+            diff(old, new, syntax, style);
+            return;
+        }
+        // the script registers its tests, which `run_tests` then runs, exiting
+        // with 1 when any failed
+        ["test", path] => (script(path, syntax), &[][..], "run_tests"),
+        ["run", path, ref rest @ ..] => (script(path, syntax), rest, "main"),
+        [path, ref rest @ ..] if !matches!(path, "compile" | "check" | "doc" | "diff" | "test") => {
+            (script(path, syntax), rest, "main")
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(0);
+        }
+    };
+    let (file_name, options, root) = file_name;
+    let options = RunOptions {
+        parser: options,
+        module_root: Some(root),
+        optimize,
+        coverage,
+        deterministic,
+        prelude,
+        contracts,
+        strict_types,
+        max_heap_bytes,
+        max_call_depth,
+        entry: Some((
+            entry.to_string(),
+            script_args.iter().map(|arg| arg.to_string()).collect(),
+        )),
+        ..RunOptions::default()
+    };
+    let mut code = run(&file_name, options, stats, report);
+    if entry == "run_tests" {
+        // a count of 256 failures would exit as a success
+        code = code.min(1);
+    }
+    if code != 0 {
+        io::stdout().flush().expect("failed to flush stdout");
+        process::exit(code);
+    }
+}
+
+// Runs the script, then its `main` if it has one, stopping at the first
+// error. A number `main` returns is the exit code, cut to an integer; any
+// other value exits with 0, and an error with 1. With `stats`, the
+// interpreter's counters follow the output, on stderr.
+fn run(file_name: &str, options: RunOptions, stats: bool, report: Report) -> i32 {
+    let (outcome, source) = if file_name.ends_with(".astc") {
+        let (program, source) = load(file_name);
+        (run_program(program, options), source)
+    } else {
+        let source = read(file_name);
+        (run_source(&source, options), Some(source))
+    };
+    let sources = source
+        .as_deref()
+        .map(|source| HashMap::from([("", source)]))
+        .unwrap_or_default();
+    let mut diagnostics = DiagnosticSet::new();
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(failure) => {
+            for diagnostic in failure {
+                diagnostics.push("", diagnostic);
+            }
+            report.show(diagnostics, &sources);
+            return 1;
+        }
+    };
+    for warning in &outcome.warnings {
+        diagnostics.push("", Diagnostic::Warning(warning.clone()));
+    }
+    if report.json || !diagnostics.is_empty() {
+        report.show(diagnostics, &sources);
+    }
+    if let Some(report) = &outcome.coverage {
+        print!("{}", report.annotate(source.as_deref().unwrap_or_default()));
+    }
+    if stats {
+        io::stdout().flush().expect("failed to flush stdout");
+        eprint!("{}", outcome.stats);
+    }
+    match outcome.returned {
+        Some(Object::Number(code)) => code as i32,
+        _ => 0,
+    }
 }
@@ -0,0 +1,357 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::object::Module;
+use crate::{Environment, Object};
+
+/// The text of a module and the name it was loaded by.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+}
+
+#[derive(Debug)]
+pub enum ModuleError {
+    /// No module has this name.
+    NotFound(String),
+    /// The specifier leads out of the folder modules are loaded from, as
+    /// `../x.lang` does from the root.
+    OutsideRoot(String),
+    /// A module imports itself, directly or through others. The chain runs
+    /// from the first import of the module to the repeated one.
+    Cycle(Vec<String>),
+    Io(String, io::Error),
+}
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModuleError::NotFound(name) => write!(f, "module \"{name}\" not found"),
+            ModuleError::OutsideRoot(specifier) => {
+                write!(f, "\"{specifier}\" is outside the module root")
+            }
+            ModuleError::Cycle(chain) => write!(f, "import cycle: {}", chain.join(" -> ")),
+            ModuleError::Io(name, err) => write!(f, "cannot read module \"{name}\": {err}"),
+        }
+    }
+}
+
+/// Where `import` gets the text of modules from.
+///
+/// Specifiers are resolved by the interpreter before they get here, so
+/// `specifier` is always a path from the root such as `lib/math.lang`,
+/// never `./` or `../`. `importer` is the resolved name of the module doing
+/// the import, or None for the script being run.
+pub trait ModuleSource {
+    fn load(&self, specifier: &str, importer: Option<&str>) -> Result<SourceFile, ModuleError>;
+}
+
+/// Reads modules from files under `root`. Nothing outside it can be
+/// loaded, even through a symlink.
+#[derive(Debug, Clone)]
+pub struct FsModuleSource {
+    root: PathBuf,
+}
+
+impl FsModuleSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl ModuleSource for FsModuleSource {
+    fn load(&self, specifier: &str, _importer: Option<&str>) -> Result<SourceFile, ModuleError> {
+        let path = Path::new(specifier);
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(ModuleError::OutsideRoot(specifier.to_string()));
+        }
+        let io_error = |err| ModuleError::Io(specifier.to_string(), err);
+        let root = self.root.canonicalize().map_err(io_error)?;
+        let full = match root.join(path).canonicalize() {
+            Ok(full) => full,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(ModuleError::NotFound(specifier.to_string()))
+            }
+            Err(err) => return Err(io_error(err)),
+        };
+        if !full.starts_with(&root) {
+            return Err(ModuleError::OutsideRoot(specifier.to_string()));
+        }
+        let source = fs::read_to_string(&full).map_err(io_error)?;
+        Ok(SourceFile {
+            name: specifier.to_string(),
+            source,
+        })
+    }
+}
+
+/// Modules kept in memory, by their names from the root, for tests and
+/// hosts whose scripts do not live on disk.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryModuleSource {
+    modules: HashMap<String, String>,
+}
+
+impl MemoryModuleSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces the module `name`, e.g. `lib/math.lang`.
+    pub fn insert(&mut self, name: impl Into<String>, source: impl Into<String>) {
+        self.modules.insert(name.into(), source.into());
+    }
+
+    /// `insert` for building a source in one expression.
+    pub fn with(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.insert(name, source);
+        self
+    }
+}
+
+impl ModuleSource for MemoryModuleSource {
+    fn load(&self, specifier: &str, _importer: Option<&str>) -> Result<SourceFile, ModuleError> {
+        match self.modules.get(specifier) {
+            Some(source) => Ok(SourceFile {
+                name: specifier.to_string(),
+                source: source.clone(),
+            }),
+            None => Err(ModuleError::NotFound(specifier.to_string())),
+        }
+    }
+}
+
+/// Tries several sources in order, e.g. modules in memory over those on
+/// disk. Only a module one source does not have falls through to the next;
+/// any other error stops the search.
+pub struct LayeredModuleSource {
+    layers: Vec<Box<dyn ModuleSource>>,
+}
+
+impl LayeredModuleSource {
+    pub fn new(layers: Vec<Box<dyn ModuleSource>>) -> Self {
+        Self { layers }
+    }
+}
+
+impl ModuleSource for LayeredModuleSource {
+    fn load(&self, specifier: &str, importer: Option<&str>) -> Result<SourceFile, ModuleError> {
+        for layer in &self.layers {
+            match layer.load(specifier, importer) {
+                Err(ModuleError::NotFound(_)) => continue,
+                result => return result,
+            }
+        }
+        Err(ModuleError::NotFound(specifier.to_string()))
+    }
+}
+
+/// The name `specifier` stands for when the module `importer` imports it.
+/// A specifier starting with `./` or `../` is relative to the folder of the
+/// importer; any other is relative to the root, as are all the specifiers
+/// of the script being run. `.` segments are dropped and `..` segments
+/// climb, but never above the root.
+pub fn resolve_specifier(specifier: &str, importer: Option<&str>) -> Result<String, ModuleError> {
+    let relative = specifier.starts_with("./") || specifier.starts_with("../");
+    let mut parts: Vec<&str> = match importer {
+        Some(importer) if relative => {
+            let mut folder: Vec<&str> = importer.split('/').collect();
+            folder.pop();
+            folder
+        }
+        _ => Vec::new(),
+    };
+    for part in specifier.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if parts.pop().is_none() {
+                    return Err(ModuleError::OutsideRoot(specifier.to_string()));
+                }
+            }
+            part => parts.push(part),
+        }
+    }
+    if parts.is_empty() {
+        return Err(ModuleError::NotFound(specifier.to_string()));
+    }
+    Ok(parts.join("/"))
+}
+
+/// The interpreter's side of `import`: resolves specifiers, loads each
+/// module once from its source, notices import cycles and keeps what each
+/// module exports, which every form of import reads.
+pub(crate) struct ModuleLoader {
+    source: Box<dyn ModuleSource>,
+    // the modules being loaded, the outermost first, with what each
+    // re-exported so far
+    loading: Vec<(String, BTreeMap<String, Object>)>,
+    // the exports of the modules that finished loading
+    loaded: HashMap<String, Rc<Module>>,
+}
+
+pub(crate) enum Load {
+    /// The module ran before; these are its exports.
+    Done(Rc<Module>),
+    /// The module has to run, then be handed to `finish`.
+    Start(SourceFile),
+}
+
+impl ModuleLoader {
+    pub fn new(source: Box<dyn ModuleSource>) -> Self {
+        Self {
+            source,
+            loading: Vec::new(),
+            loaded: HashMap::new(),
+        }
+    }
+
+    // The module whose code is running, or None for the script.
+    fn importer(&self) -> Option<&str> {
+        self.loading.last().map(|(name, _)| name.as_str())
+    }
+
+    /// Looks up the module `specifier` names for the code running now.
+    pub fn begin(&mut self, specifier: &str) -> Result<Load, ModuleError> {
+        let name = resolve_specifier(specifier, self.importer())?;
+        if let Some(module) = self.loaded.get(&name) {
+            return Ok(Load::Done(module.clone()));
+        }
+        if let Some(first) = self
+            .loading
+            .iter()
+            .position(|(loading, _)| *loading == name)
+        {
+            let mut chain: Vec<_> = self.loading[first..]
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect();
+            chain.push(name);
+            return Err(ModuleError::Cycle(chain));
+        }
+        let file = self.source.load(&name, self.importer())?;
+        self.loading.push((name, BTreeMap::new()));
+        Ok(Load::Start(file))
+    }
+
+    /// Adds `name` to what the module being loaded exports, returning the
+    /// value an earlier re-export gave it. The script being run has no
+    /// exports, so there this does nothing.
+    pub fn re_export(&mut self, name: &str, value: Object) -> Option<Object> {
+        let (_, exports) = self.loading.last_mut()?;
+        exports.insert(name.to_string(), value)
+    }
+
+    /// Ends the load `begin` started last. When the module ran to the end,
+    /// its top-level names in `environment` and its re-exports become its
+    /// exports, which are kept and returned; a re-export replaces a name of
+    /// its own. A module that failed is loaded afresh by the next import
+    /// of it.
+    pub fn finish(&mut self, environment: Option<Rc<RefCell<Environment>>>) -> Option<Rc<Module>> {
+        let (name, re_exports) = self.loading.pop().expect("finish follows begin");
+        let mut exports: BTreeMap<_, _> = environment?.borrow().bindings().into_iter().collect();
+        exports.extend(re_exports);
+        let module = Rc::new(Module::new(name.clone(), exports));
+        self.loaded.insert(name, module.clone());
+        Some(module)
+    }
+
+    /// Forgets every module loaded so far.
+    pub fn clear(&mut self) {
+        self.loaded.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        resolve_specifier, FsModuleSource, LayeredModuleSource, Load, MemoryModuleSource,
+        ModuleError, ModuleLoader, ModuleSource,
+    };
+
+    #[test]
+    fn test_resolve_specifier() {
+        let resolve = |specifier, importer| match resolve_specifier(specifier, importer) {
+            Ok(name) => name,
+            Err(err) => err.to_string(),
+        };
+        assert_eq!(resolve("math.lang", None), "math.lang");
+        assert_eq!(resolve("./math.lang", None), "math.lang");
+        assert_eq!(resolve("lib/./math.lang", None), "lib/math.lang");
+        // relative to the importer's folder, anything else to the root
+        assert_eq!(resolve("./vec.lang", Some("lib/math.lang")), "lib/vec.lang");
+        assert_eq!(resolve("../app.lang", Some("lib/math.lang")), "app.lang");
+        assert_eq!(resolve("../x/y.lang", Some("a/b/c.lang")), "a/x/y.lang");
+        assert_eq!(resolve("util.lang", Some("lib/math.lang")), "util.lang");
+        assert_eq!(
+            resolve("../../x.lang", Some("lib/math.lang")),
+            "\"../../x.lang\" is outside the module root"
+        );
+        assert_eq!(
+            resolve("../x.lang", None),
+            "\"../x.lang\" is outside the module root"
+        );
+        assert_eq!(resolve("./", None), "module \"./\" not found");
+    }
+
+    #[test]
+    fn test_loader() {
+        let source = MemoryModuleSource::new()
+            .with("a.lang", "a")
+            .with("lib/b.lang", "b")
+            .with("lib/c.lang", "c");
+        let mut loader = ModuleLoader::new(Box::new(source));
+        let start = |loader: &mut ModuleLoader, specifier| match loader.begin(specifier) {
+            Ok(Load::Start(file)) => format!("{}: {}", file.name, file.source),
+            Ok(Load::Done(_)) => "done".to_string(),
+            Err(err) => err.to_string(),
+        };
+        assert_eq!(start(&mut loader, "./a.lang"), "a.lang: a");
+        assert_eq!(start(&mut loader, "lib/b.lang"), "lib/b.lang: b");
+        // `b` imports relative to its own folder
+        assert_eq!(start(&mut loader, "./c.lang"), "lib/c.lang: c");
+        assert_eq!(
+            start(&mut loader, "../a.lang"),
+            "import cycle: a.lang -> lib/b.lang -> lib/c.lang -> a.lang"
+        );
+        assert_eq!(
+            start(&mut loader, "./d.lang"),
+            "module \"lib/d.lang\" not found"
+        );
+        loader.finish(Some(Default::default()));
+        loader.finish(None);
+        // a loaded module is not loaded again; a failed one is
+        assert_eq!(start(&mut loader, "lib/c.lang"), "done");
+        assert_eq!(start(&mut loader, "lib/b.lang"), "lib/b.lang: b");
+    }
+
+    #[test]
+    fn test_layers() {
+        let memory = MemoryModuleSource::new().with("a.lang", "memory");
+        let other = MemoryModuleSource::new()
+            .with("a.lang", "other")
+            .with("b.lang", "other");
+        let layered = LayeredModuleSource::new(vec![Box::new(memory), Box::new(other)]);
+        assert_eq!(layered.load("a.lang", None).unwrap().source, "memory");
+        assert_eq!(layered.load("b.lang", None).unwrap().source, "other");
+        assert!(matches!(
+            layered.load("c.lang", None),
+            Err(ModuleError::NotFound(name)) if name == "c.lang"
+        ));
+
+        // an error other than a missing module stops the search
+        let fs = FsModuleSource::new("tests");
+        let fallback = MemoryModuleSource::new().with("../Cargo.toml", "memory");
+        let layered = LayeredModuleSource::new(vec![Box::new(fs), Box::new(fallback)]);
+        assert!(matches!(
+            layered.load("../Cargo.toml", None),
+            Err(ModuleError::OutsideRoot(_))
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+//! Conversions between numbers and text: how `print`, string concatenation
+//! and `to_string` show a number, the fixed and significant-digit forms of
+//! `to_fixed` and `to_precision`, and the parsing of number literals.
+//!
+//! Everything here goes through Rust's float formatting and parsing, which
+//! never consult the host locale: the decimal separator is always `.`. Both
+//! are exact, so `parse(&shortest(x)) == Some(x)` for every finite `x`.
+//!
+//! Rounding is done on the exact binary value of the number, with ties going
+//! to the even digit. `2.675` is stored as `2.67499999...`, so
+//! `to_fixed(2.675, 2)` is `"2.67"`, while the exact tie `0.125` rounds to
+//! `"0.12"`.
+//!
+//! Digit groups are likewise always separated by `,`, as in `1,234,567.5`.
+//! A script that needs another separator can replace it in the text.
+
+use crate::Error;
+
+pub const MAX_DIGITS: usize = 100;
+
+/// The fewest digits that read back as `n`, the way a number is printed.
+/// Numbers from 1e-6 up to 1e21 are written out, as in `0.000001` and
+/// `123.5`, and any others with an exponent, as in `1e+21` and `5e-324`.
+pub fn shortest(n: f64) -> String {
+    let magnitude = n.abs();
+    if !n.is_finite() || n == 0.0 || (1e-6..1e21).contains(&magnitude) {
+        return format!("{n}");
+    }
+    let text = format!("{n:e}");
+    match text.split_once('e') {
+        Some((mantissa, exponent)) if !exponent.starts_with('-') => {
+            format!("{mantissa}e+{exponent}")
+        }
+        _ => text,
+    }
+}
+
+/// The number `text` is the decimal form of, such as the digits of a
+/// literal or what `shortest` or `fixed` gave. None for anything else.
+pub fn parse(text: &str) -> Option<f64> {
+    text.parse().ok()
+}
+
+/// `n` with exactly `digits` digits after the decimal point.
+pub fn fixed(n: f64, digits: usize) -> Result<String, Error> {
+    if digits > MAX_DIGITS {
+        return Err(digits_error("digits", 0));
+    }
+    if !n.is_finite() {
+        return Ok(shortest(n));
+    }
+    Ok(without_negative_zero(format!("{n:.digits$}")))
+}
+
+/// `n` rounded to `sig` significant digits. Exponents below -6 or not smaller
+/// than `sig` switch to exponential notation, e.g. `1.2e+5`.
+pub fn precision(n: f64, sig: usize) -> Result<String, Error> {
+    if !(1..=MAX_DIGITS).contains(&sig) {
+        return Err(digits_error("significant digits", 1));
+    }
+    if !n.is_finite() {
+        return Ok(shortest(n));
+    }
+    if n == 0.0 {
+        return fixed(0.0, sig - 1);
+    }
+
+    let scientific = format!("{n:.*e}", sig - 1);
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("exponential formatting always has an exponent");
+    let exponent: i64 = exponent.parse().expect("exponent is an integer");
+    if exponent < -6 || exponent >= sig as i64 {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        Ok(format!("{mantissa}e{sign}{}", exponent.abs()))
+    } else {
+        fixed(n, (sig as i64 - 1 - exponent) as usize)
+    }
+}
+
+/// `text`, a number as `shortest` or `fixed` writes it, with a `,` between
+/// each group of three digits before the decimal point. A number written
+/// with an exponent, or one that is not finite, comes back as it is.
+pub fn grouped(text: &str) -> String {
+    let (sign, unsigned) = match text.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", text),
+    };
+    let end = unsigned
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unsigned.len());
+    let (whole, rest) = unsigned.split_at(end);
+    if whole.is_empty() || rest.contains('e') {
+        return text.to_string();
+    }
+    let mut out = String::from(sign);
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Checks that a script-supplied digit count is a whole number in range.
+pub fn digits(value: f64, name: &str, min: usize) -> Result<usize, Error> {
+    if value.fract() != 0.0 || value < min as f64 || value > MAX_DIGITS as f64 {
+        return Err(digits_error(name, min));
+    }
+    Ok(value as usize)
+}
+
+fn digits_error(name: &str, min: usize) -> Error {
+    Error::Value(format!(
+        "{name} must be an integer between {min} and {MAX_DIGITS}"
+    ))
+}
+
+// Rounding a small negative number can leave only zeros behind; `-0.00`
+// is never what a script wants to show.
+fn without_negative_zero(text: String) -> String {
+    match text.strip_prefix('-') {
+        Some(rest) if rest.chars().all(|c| c == '0' || c == '.') => rest.to_string(),
+        _ => text,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Lexer, TokenType};
+
+    #[test]
+    fn test_shortest() {
+        assert_eq!(shortest(0.1), "0.1");
+        assert_eq!(shortest(1e300), "1e+300");
+        assert_eq!(shortest(5e-324), "5e-324");
+        assert_eq!(shortest(-0.0), "-0");
+        assert_eq!(shortest(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(shortest(123456789.0), "123456789");
+        assert_eq!(shortest(0.000001), "0.000001");
+        assert_eq!(shortest(1.5e-7), "1.5e-7");
+        assert_eq!(shortest(1e21), "1e+21");
+        assert_eq!(shortest(-2.5e21), "-2.5e+21");
+        assert_eq!(shortest(f64::INFINITY), "inf");
+    }
+
+    // A random number generator, so the test needs no crate and fails the
+    // same way every time.
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut random = Random(0x2545_F491_4F6C_DD1D);
+        let edges = [
+            0.0,
+            -0.0,
+            f64::MIN_POSITIVE,
+            f64::MAX,
+            f64::MIN,
+            f64::EPSILON,
+        ];
+        let randoms = (0..20_000).map(|_| f64::from_bits(random.next()));
+        for n in edges.into_iter().chain(randoms).filter(|n| n.is_finite()) {
+            let text = shortest(n);
+            let parsed = parse(&text).map(f64::to_bits);
+            assert_eq!(parsed, Some(n.to_bits()), "{text} is not {n:e}");
+
+            // the lexer reads a printed number back as the same number
+            let text = shortest(n.abs());
+            let token = Lexer::new(text.clone()).next_token().token;
+            assert_eq!(token, TokenType::Number(n.abs()), "lexing {text}");
+        }
+    }
+
+    #[test]
+    fn test_fixed() {
+        assert_eq!(fixed(1.0, 2).unwrap(), "1.00");
+        assert_eq!(fixed(2.675, 2).unwrap(), "2.67");
+        assert_eq!(fixed(0.125, 2).unwrap(), "0.12");
+        assert_eq!(fixed(0.375, 2).unwrap(), "0.38");
+        assert_eq!(fixed(2.5, 0).unwrap(), "2");
+        assert_eq!(fixed(-0.001, 2).unwrap(), "0.00");
+        assert_eq!(fixed(-1.5, 1).unwrap(), "-1.5");
+        assert!(fixed(1.0, 101).is_err());
+    }
+
+    #[test]
+    fn test_precision() {
+        assert_eq!(precision(123.456, 4).unwrap(), "123.5");
+        assert_eq!(precision(9.99, 2).unwrap(), "10");
+        assert_eq!(precision(0.000123, 2).unwrap(), "0.00012");
+        assert_eq!(precision(123456.0, 2).unwrap(), "1.2e+5");
+        assert_eq!(precision(0.00000012, 2).unwrap(), "1.2e-7");
+        assert_eq!(precision(0.0, 3).unwrap(), "0.00");
+        assert!(precision(1.0, 0).is_err());
+    }
+
+    #[test]
+    fn test_grouped() {
+        assert_eq!(grouped("1234567.89"), "1,234,567.89");
+        assert_eq!(grouped("-1234"), "-1,234");
+        assert_eq!(grouped("123"), "123");
+        assert_eq!(grouped("100000"), "100,000");
+        assert_eq!(grouped("0.000001"), "0.000001");
+        assert_eq!(grouped(&shortest(1e21)), "1e+21");
+        assert_eq!(grouped("-inf"), "-inf");
+    }
+
+    #[test]
+    fn test_digits() {
+        assert_eq!(digits(2.0, "digits", 0).unwrap(), 2);
+        assert!(digits(2.5, "digits", 0).is_err());
+        assert!(digits(-1.0, "digits", 0).is_err());
+        assert!(digits(0.0, "digits", 1).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt,
+    rc::Rc,
+};
+
+use crate::{Environment, Error, FieldInit, Object};
+
+use super::field_map::{FieldMap, HashSeed};
+use super::{Function, Teardown};
+
+/// Classes never change once created, which lets the interpreter cache
+/// method lookups per class. Anything that makes them mutable has to clear
+/// that cache.
+pub struct Class {
+    pub name: Option<String>,
+    pub super_class: Option<Rc<Class>>,
+    pub methods: HashMap<String, Rc<Function>>,
+    /// Members declared `priv`, methods and fields alike.
+    pub private: HashSet<String>,
+    /// The `name = value;` members, run on each new instance after those of
+    /// the superclass and before `init`.
+    pub fields: Vec<FieldInit>,
+    /// The string the class body starts with.
+    pub doc: Option<String>,
+    /// The environment the methods close over. Code running inside it is
+    /// code of this class, which may reach its private members.
+    pub scope: Rc<RefCell<Environment>>,
+}
+
+impl Class {
+    /// Looks `name` up on this class first and then along its superclasses.
+    pub fn find_method(&self, name: &str) -> Option<Rc<Function>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.super_class.as_ref()?.find_method(name),
+        }
+    }
+
+    /// The class along the chain from this one that keeps `name` private.
+    /// The nearest class with a member called `name` decides, so a public
+    /// method in a subclass hides a private one above it.
+    pub fn private_owner(self: &Rc<Self>, name: &str) -> Option<&Rc<Class>> {
+        if self.private.contains(name) {
+            return Some(self);
+        }
+        if self.methods.contains_key(name) {
+            return None;
+        }
+        self.super_class.as_ref()?.private_owner(name)
+    }
+
+    /// Whether this class or one of its superclasses is called `name`.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.as_deref() == Some(name)
+            || self
+                .super_class
+                .as_ref()
+                .is_some_and(|class| class.is_named(name))
+    }
+
+    /// The names of the methods of this class and all its superclasses.
+    pub fn method_names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self.methods.keys().map(String::as_str).collect();
+        if let Some(super_class) = &self.super_class {
+            names.extend(super_class.method_names());
+        }
+        names
+    }
+}
+
+// Classes are compared by identity: two evaluations of the same class
+// expression produce two distinct classes.
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Methods close over the environment the class was defined in, which may hold
+// the class itself, so only the name is printed.
+impl fmt::Debug for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Class").field("name", &self.name).finish()
+    }
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "<class {name}>"),
+            None => write!(f, "<class>"),
+        }
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    fields: FieldMap,
+    frozen: bool,
+    // while the field initializers run, the fields they have yet to set
+    uninitialized: HashSet<String>,
+}
+
+impl Instance {
+    /// An instance of `class` without fields, which hashes their names with
+    /// `seed`.
+    pub fn new(class: Rc<Class>, seed: HashSeed) -> Self {
+        Self {
+            class,
+            fields: FieldMap::new(seed),
+            frozen: false,
+            uninitialized: HashSet::new(),
+        }
+    }
+
+    /// Marks the fields with initializers anywhere in the class chain as
+    /// not yet set, until `set` sets each or `end_init` is called.
+    pub fn begin_init(&mut self) {
+        let mut class = Some(&self.class);
+        while let Some(next) = class {
+            let names = next.fields.iter().map(|field| field.name.clone());
+            self.uninitialized.extend(names);
+            class = next.super_class.as_ref();
+        }
+    }
+
+    pub fn end_init(&mut self) {
+        self.uninitialized.clear();
+    }
+
+    /// Fails for a field whose initializer has not run yet. Reading one is
+    /// an error rather than a lookup that falls through to the methods.
+    pub fn check_initialized(&self, name: &str) -> Result<(), Error> {
+        if self.uninitialized.contains(name) {
+            return Err(Error::Runtime(format!(
+                "field \"{name}\" read before initialization"
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn field(&self, name: &str) -> Option<Object> {
+        self.fields.get(name).cloned()
+    }
+
+    /// The names of the fields set on this instance, without its methods,
+    /// in the order they were first set.
+    pub fn field_names(&self) -> impl Iterator<Item = &str> {
+        self.fields.names()
+    }
+
+    /// The fields set on this instance with their names, in the order they
+    /// were first set.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &Object)> {
+        self.fields.iter()
+    }
+
+    /// The names `get` can find: fields and the methods of the class chain.
+    pub fn property_names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self.fields.names().collect();
+        names.extend(self.class.method_names());
+        names
+    }
+
+    /// Every write to a field goes through here so frozen instances are
+    /// checked in one place.
+    pub fn set(&mut self, name: &str, value: Object) -> Result<(), Error> {
+        if self.frozen {
+            return Err(Error::Type(format!(
+                "cannot set property \"{name}\" on a frozen instance"
+            )));
+        }
+        self.uninitialized.remove(name);
+        if let Some(old) = self.fields.insert(name, value) {
+            Teardown::discard(old);
+        }
+        Ok(())
+    }
+
+    /// Makes later `set` calls fail. Freezing is shallow: objects stored in
+    /// fields stay mutable.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub(crate) fn tear_down(&mut self, teardown: &mut Teardown) {
+        for value in self.fields.drain() {
+            teardown.object(value);
+        }
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        let mut teardown = Teardown::default();
+        self.tear_down(&mut teardown);
+        teardown.run();
+    }
+}
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Instance")
+            .field("class", &self.class)
+            .field("fields", &self.fields.names().collect::<Vec<_>>())
+            .field("frozen", &self.frozen)
+            .finish()
+    }
+}
+
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.class.name {
+            Some(name) => write!(f, "<{name} instance>"),
+            None => write!(f, "<instance>"),
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, DefaultHasher, Hasher, RandomState},
+    rc::Rc,
+};
+
+use super::Object;
+
+/// What the field names of an interpreter's instances are hashed with.
+///
+/// Normally a `RandomState` drawn once per interpreter and shared by its
+/// instances, keyed as randomly as any std `HashMap`. Deterministic mode
+/// uses SipHash with the zero key, fed the seed first, which anyone can
+/// compute and so is only safe with trusted scripts.
+#[derive(Debug, Clone)]
+pub enum HashSeed {
+    Random(RandomState),
+    Fixed(u64),
+}
+
+impl HashSeed {
+    pub fn random() -> Self {
+        Self::Random(RandomState::new())
+    }
+
+    pub fn fixed(seed: u64) -> Self {
+        Self::Fixed(seed)
+    }
+}
+
+impl BuildHasher for HashSeed {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        match self {
+            Self::Random(state) => state.build_hasher(),
+            Self::Fixed(seed) => {
+                let mut hasher = DefaultHasher::new();
+                hasher.write_u64(*seed);
+                hasher
+            }
+        }
+    }
+}
+
+/// The fields of an instance, listed in the order they were first set,
+/// whatever the seed their names are hashed with. Fields are never
+/// removed, only replaced.
+pub struct FieldMap {
+    entries: Vec<(Rc<str>, Object)>,
+    // the position of each name in `entries`
+    index: HashMap<Rc<str>, usize, HashSeed>,
+}
+
+impl FieldMap {
+    pub fn new(seed: HashSeed) -> Self {
+        Self {
+            entries: Vec::new(),
+            index: HashMap::with_hasher(seed),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Object> {
+        self.index.get(name).map(|&i| &self.entries[i].1)
+    }
+
+    /// Sets `name` to `value`, returning the value it replaces. A name set
+    /// again keeps its place.
+    pub fn insert(&mut self, name: &str, value: Object) -> Option<Object> {
+        if let Some(&i) = self.index.get(name) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+        let name: Rc<str> = name.into();
+        self.index.insert(name.clone(), self.entries.len());
+        self.entries.push((name, value));
+        None
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(name, _)| &**name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Object)> {
+        self.entries.iter().map(|(name, value)| (&**name, value))
+    }
+
+    /// Empties the map, handing over the values.
+    pub fn drain(&mut self) -> impl Iterator<Item = Object> + '_ {
+        self.index.clear();
+        self.entries.drain(..).map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, hash::BuildHasher};
+
+    use super::{FieldMap, HashSeed};
+    use crate::Object;
+
+    // Names that differ only in their last characters.
+    fn similar_names(count: usize) -> Vec<String> {
+        let prefix = "k".repeat(64);
+        (0..count).map(|i| format!("{prefix}{i:08}")).collect()
+    }
+
+    #[test]
+    fn test_many_similar_names() {
+        let names = similar_names(100_000);
+        for seed in [HashSeed::fixed(0), HashSeed::fixed(1), HashSeed::random()] {
+            // spread over as many buckets as a table of them has, none gets
+            // more than a handful, where names hashing alike would pile up
+            let mut buckets: HashMap<u64, usize> = HashMap::new();
+            for name in &names {
+                *buckets
+                    .entry(seed.hash_one(name.as_str()) % (1 << 17))
+                    .or_default() += 1;
+            }
+            let fullest = buckets.values().max().copied();
+            assert!(fullest < Some(32), "{fullest:?} in one bucket");
+
+            let mut fields = FieldMap::new(seed);
+            for (i, name) in names.iter().enumerate() {
+                fields.insert(name, Object::Number(i as f64));
+            }
+            for (i, name) in names.iter().enumerate() {
+                assert_eq!(fields.get(name), Some(&Object::Number(i as f64)));
+            }
+            assert!(fields.names().eq(names.iter().map(String::as_str)));
+        }
+    }
+
+    #[test]
+    fn test_order_independent_of_seed() {
+        let names = similar_names(100);
+        let fill = |seed| {
+            let mut fields = FieldMap::new(seed);
+            for name in &names {
+                fields.insert(name, Object::Nil);
+            }
+            fields
+        };
+        let first = fill(HashSeed::fixed(1));
+        let second = fill(HashSeed::fixed(2));
+        // the tables differ
+        let hashed = |fields: &FieldMap| fields.index.keys().cloned().collect::<Vec<_>>();
+        assert_ne!(hashed(&first), hashed(&second));
+        // but not what is seen of them
+        assert!(first.names().eq(second.names()));
+        assert!(first.names().eq(names.iter().map(String::as_str)));
+
+        let mut fields = fill(HashSeed::random());
+        assert_eq!(
+            fields.insert(&names[0], Object::Boolean(true)),
+            Some(Object::Nil)
+        );
+        assert_eq!(
+            fields.iter().next(),
+            Some((&*names[0], &Object::Boolean(true)))
+        );
+        assert_eq!(fields.drain().count(), 100);
+        assert!(fields.get(&names[0]).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+//! The limited rendering of values `print` and the REPL show. Numbers are
+//! written by `numfmt`.
+
+use crate::Object;
+
+/// How much of a value `print` and the REPL show, so that a huge or deeply
+/// nested tuple cannot flood the output. `Display` still renders everything.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Limits {
+    /// Tuples nested deeper than this show as `(...)`.
+    pub depth: usize,
+    /// Elements shown per tuple, after which `... N more` stands for the rest.
+    pub elements: usize,
+    /// Chars shown in all, after which the text ends in `...`.
+    pub length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            depth: 8,
+            elements: 100,
+            length: 10_000,
+        }
+    }
+}
+
+/// `value` as `print` shows it, within `limits`.
+pub fn limited(value: &Object, limits: Limits) -> String {
+    let mut out = String::new();
+    write_limited(&mut out, value, limits, 0);
+    match out.char_indices().nth(limits.length) {
+        Some((end, _)) => {
+            out.truncate(end);
+            out.push_str("...");
+            out
+        }
+        None => out,
+    }
+}
+
+// Stops adding elements once `out` holds more than `limits.length` bytes,
+// which is at least as many chars, so a wide tuple is never rendered whole.
+fn write_limited(out: &mut String, value: &Object, limits: Limits, depth: usize) {
+    let Object::Tuple(elements) = value else {
+        out.push_str(&value.to_string());
+        return;
+    };
+    if depth >= limits.depth {
+        out.push_str("(...)");
+        return;
+    }
+    out.push('(');
+    for (i, element) in elements.iter().enumerate() {
+        if out.len() > limits.length {
+            return;
+        }
+        if i > 0 {
+            out.push_str(", ");
+        }
+        if i == limits.elements {
+            out.push_str(&format!("... {} more", elements.len() - i));
+            break;
+        }
+        write_limited(out, element, limits, depth + 1);
+    }
+    // `(7,)` tells a one-element tuple from a parenthesized 7
+    if elements.len() == 1 {
+        out.push(',');
+    }
+    out.push(')');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // nested tuples `depth` deep around a 1
+    fn nested(depth: usize) -> Object {
+        (0..depth).fold(Object::Number(1.0), |inner, _| {
+            Object::Tuple([inner, Object::Nil].into())
+        })
+    }
+
+    #[test]
+    fn test_limited_depth() {
+        let limits = Limits::default();
+        assert_eq!(
+            limited(&nested(10), limits),
+            "(((((((((...), nil), nil), nil), nil), nil), nil), nil), nil)"
+        );
+        assert_eq!(limited(&nested(2), limits), nested(2).to_string());
+        let limits = Limits { depth: 0, ..limits };
+        assert_eq!(limited(&nested(1), limits), "(...)");
+        assert_eq!(limited(&Object::Nil, limits), "nil");
+    }
+
+    #[test]
+    fn test_limited_width() {
+        let wide = |len: usize| Object::Tuple((0..len).map(|n| Object::Number(n as f64)).collect());
+        let limits = Limits {
+            elements: 3,
+            ..Limits::default()
+        };
+        assert_eq!(limited(&wide(1000), limits), "(0, 1, 2, ... 997 more)");
+        assert_eq!(limited(&wide(3), limits), "(0, 1, 2)");
+        assert_eq!(limited(&wide(1), limits), "(0,)");
+
+        let limits = Limits {
+            length: 10,
+            ..Limits::default()
+        };
+        assert_eq!(limited(&wide(100_000), limits), "(0, 1, 2, ...");
+        assert_eq!(
+            limited(&Object::String("ééééééééééé".into()), limits),
+            "éééééééééé..."
+        );
+        assert_eq!(limited(&wide(3), limits), "(0, 1, 2)");
+    }
+}
@@ -1,18 +1,46 @@
 use std::{cell::RefCell, rc::Rc};
 
-use crate::{Environment, Error, ErrorInfo, Interpretor, Object, Span, Stmt, environment};
+use crate::{Environment, Error, ErrorInfo, Expr, Interpretor, Object, Signature, Span, Stmt};
+
+use super::Instance;
+
+pub type NativeFn = fn(&mut Interpretor, Vec<Object>, &Span) -> Result<Object, ErrorInfo>;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Function {
     Inbuilt {
+        name: String,
         arity: usize,
-        func: Box<fn(Vec<Object>) -> Result<Object, ErrorInfo>>,
+        // optional names so natives can take keyword arguments
+        params: &'static [&'static str],
+        // leading arguments that must be given; the rest default to nil
+        required: usize,
+        // whether extra positional arguments are passed on after the params
+        variadic: bool,
+        func: Box<NativeFn>,
+    },
+
+    /// A native method looked up on a value, such as `f.arity`. `func` gets
+    /// the receiver as its first argument, followed by the call's arguments.
+    Method {
+        name: &'static str,
+        receiver: Object,
+        params: &'static [&'static str],
+        required: usize,
+        // whether extra positional arguments are passed on after the params
+        variadic: bool,
+        func: Box<NativeFn>,
     },
 
     User {
         name: String,
         params: Vec<String>,
+        signature: Signature,
+        requires: Vec<Expr>,
+        ensures: Vec<Expr>,
         body: Vec<Stmt>,
+        // the function's doc string, if its body starts with one
+        doc: Option<String>,
         closure: Rc<RefCell<Environment>>,
         is_initializer: bool,
         span: Span,
@@ -20,44 +48,262 @@ pub enum Function {
 }
 
 impl Function {
+    pub fn is_native(&self) -> bool {
+        matches!(self, Function::Inbuilt { .. } | Function::Method { .. })
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Function::Inbuilt { name, .. } | Function::User { name, .. } => name,
+            Function::Method { name, .. } => name,
+        }
+    }
+
+    /// The string a user function's body starts with. Natives have none.
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Function::User { doc, .. } => doc.as_deref(),
+            Function::Inbuilt { .. } | Function::Method { .. } => None,
+        }
+    }
+
+    /// Number of parameters, not counting a native's variadic rest.
     pub fn arity(&self) -> usize {
         match self {
             Function::Inbuilt { arity, .. } => *arity,
+            Function::Method { params, .. } => params.len(),
+            Function::User { params, .. } => params.len(),
+        }
+    }
+
+    /// Fewest arguments a call may pass.
+    pub fn min_arity(&self) -> usize {
+        match self {
+            Function::Inbuilt { required, .. } | Function::Method { required, .. } => *required,
             Function::User { params, .. } => params.len(),
         }
     }
 
+    /// Most arguments a call may pass, or None when there is no limit.
+    pub fn max_arity(&self) -> Option<usize> {
+        match self {
+            Function::Inbuilt { variadic: true, .. } | Function::Method { variadic: true, .. } => {
+                None
+            }
+            _ => Some(self.arity()),
+        }
+    }
+
+    pub fn params(&self) -> Vec<&str> {
+        match self {
+            Function::Inbuilt { params, .. } | Function::Method { params, .. } => params.to_vec(),
+            Function::User { params, .. } => params.iter().map(|p| p.as_str()).collect(),
+        }
+    }
+
+    /// Returns a copy of a method whose closure has `this` bound to `instance`.
+    pub fn bind(&self, instance: &Rc<RefCell<Instance>>) -> Function {
+        match self {
+            Function::Inbuilt { .. } | Function::Method { .. } => self.clone(),
+            Function::User {
+                name,
+                params,
+                signature,
+                requires,
+                ensures,
+                body,
+                doc,
+                closure,
+                is_initializer,
+                span,
+            } => {
+                let mut environment = Environment::new_from_closure(closure);
+                environment
+                    .define("this".to_string(), Object::Instance(instance.clone()), true)
+                    .expect("this is a fresh binding");
+                Function::User {
+                    name: name.clone(),
+                    params: params.clone(),
+                    signature: signature.clone(),
+                    requires: requires.clone(),
+                    ensures: ensures.clone(),
+                    body: body.clone(),
+                    doc: doc.clone(),
+                    closure: Rc::new(RefCell::new(environment)),
+                    is_initializer: *is_initializer,
+                    span: span.clone(),
+                }
+            }
+        }
+    }
+
+    /// Orders positional arguments followed by `name: value` arguments into
+    /// parameter order, checking that every parameter gets exactly one value.
+    pub fn bind_arguments(
+        &self,
+        mut args: Vec<Object>,
+        named: Vec<(String, Object, Span)>,
+        span: &Span,
+    ) -> Result<Vec<Object>, ErrorInfo> {
+        let arity = self.arity();
+        let rest = match self {
+            Function::Inbuilt { variadic: true, .. } | Function::Method { variadic: true, .. }
+                if args.len() > arity =>
+            {
+                args.split_off(arity)
+            }
+            _ => Vec::new(),
+        };
+        if args.len() > arity {
+            let error = Error::Arity {
+                expected: arity,
+                got: args.len() + named.len(),
+            };
+            return Err(ErrorInfo::new_with_span(error, span.to_owned()));
+        }
+
+        let params = self.params();
+        let required = self.min_arity();
+        let mut slots: Vec<Option<Object>> = args.into_iter().map(Some).collect();
+        slots.resize(arity, None);
+        for (name, value, name_span) in named {
+            let error = match params.iter().position(|param| *param == name) {
+                Some(index) if slots[index].is_none() => {
+                    slots[index] = Some(value);
+                    continue;
+                }
+                Some(_) => Error::Type(format!("got multiple values for argument \"{name}\"")),
+                None => Error::Type(format!("unexpected keyword argument \"{name}\"")),
+            };
+            return Err(ErrorInfo::new_with_span(error, name_span));
+        }
+
+        slots
+            .into_iter()
+            .enumerate()
+            .map(|(index, slot)| match slot {
+                Some(value) => Ok(value),
+                None if index >= required => Ok(Object::Nil),
+                None => {
+                    let error = match params.get(index) {
+                        Some(param) => Error::Type(format!("missing argument \"{param}\"")),
+                        None => Error::Type(format!("expected {arity} arguments")),
+                    };
+                    Err(ErrorInfo::new_with_span(error, span.to_owned()))
+                }
+            })
+            .chain(rest.into_iter().map(Ok))
+            .collect()
+    }
+
     pub fn call(
         &self,
         interpreter: &mut Interpretor,
-        args: &Vec<Object>,
+        args: &[Object],
+        call_span: &Span,
     ) -> Result<Object, ErrorInfo> {
         match self {
-            Function::Inbuilt { func, .. } => Ok(func(args.to_vec())?),
+            Function::Inbuilt { func, .. } => func(interpreter, args.to_vec(), call_span),
+            Function::Method { receiver, func, .. } => {
+                let args = std::iter::once(receiver.clone())
+                    .chain(args.iter().cloned())
+                    .collect();
+                func(interpreter, args, call_span)
+            }
             Function::User {
+                name,
                 params,
+                signature,
+                requires,
                 body,
                 closure,
+                span,
                 ..
             } => {
                 let mut environment = Environment::new_from_closure(closure);
-                for (param, argument) in params.iter().zip(args) {
+                for (index, (param, argument)) in params.iter().zip(args).enumerate() {
+                    if let Some(annotation) = signature.param(index) {
+                        interpreter
+                            .check_type(argument, annotation, || {
+                                format!("argument \"{param}\" of \"{name}\"")
+                            })
+                            .map_err(|e| ErrorInfo::new_with_span(e, call_span.to_owned()))?;
+                    }
                     environment
-                        .define(param
-                            .clone(), argument.to_owned(), false);
+                        .define(param.clone(), argument.to_owned(), false)
+                        .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
                 }
                 let environment = Rc::new(RefCell::new(environment));
-                match  interpreter.exec_block(body, environment) {
-                Ok(()) => Ok(Object::Nil),
-                Err(x) => {
-                    if let Error::Return(value) = x.error {
-                        Ok(value)
-                    } else {
-                        Err(x)
-                    }                }
+                interpreter.check_contract("requires", name, requires, environment.clone())?;
+                let outcome = interpreter.exec_frame(body, environment.clone());
+                self.finish(interpreter, environment, outcome)
+            }
+        }
+    }
+
+    /// The statements a script function runs, empty for natives.
+    pub(crate) fn body(&self) -> &[Stmt] {
+        match self {
+            Function::User { body, .. } => body,
+            _ => &[],
+        }
+    }
+
+    // Turns how the body of a call running in `environment` finished into
+    // what the call returns, checking its type and `ensures` clauses. A
+    // suspended call is finished here once it is resumed.
+    pub(crate) fn finish(
+        &self,
+        interpreter: &mut Interpretor,
+        environment: Rc<RefCell<Environment>>,
+        outcome: Result<(), ErrorInfo>,
+    ) -> Result<Object, ErrorInfo> {
+        let Function::User {
+            name,
+            signature,
+            ensures,
+            closure,
+            is_initializer,
+            span,
+            ..
+        } = self
+        else {
+            return outcome.map(|()| Object::Nil);
+        };
+        // the span of the `return` the value came from, if any
+        let (value, site) = match outcome {
+            Ok(()) => (Object::Nil, span.to_owned()),
+            Err(x) => {
+                let site = x.span().clone();
+                if let Error::Return(value) = x.error {
+                    (value, site)
+                } else {
+                    return Err(x);
                 }
-               
             }
+        };
+        // `init` always hands back the instance it was bound to.
+        let value = if *is_initializer {
+            closure
+                .borrow_mut()
+                .get("this")
+                .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?
+        } else {
+            if let Some(annotation) = &signature.returns {
+                interpreter
+                    .check_type(&value, annotation, || format!("the result of \"{name}\""))
+                    .map_err(|e| ErrorInfo::new_with_span(e, site))?;
+            }
+            value
+        };
+        if interpreter.check_contracts && !ensures.is_empty() {
+            let mut environment = Environment::new_from_closure(&environment);
+            environment
+                .define("result".to_string(), value.clone(), false)
+                .map_err(|e| ErrorInfo::new_with_span(e, span.to_owned()))?;
+            let environment = Rc::new(RefCell::new(environment));
+            interpreter.check_contract("ensures", name, ensures, environment)?;
         }
+        Ok(value)
     }
 }
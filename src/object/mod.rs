@@ -1,14 +1,35 @@
-use std::fmt;
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::numfmt;
+mod class;
+mod field_map;
+pub mod format;
 mod function;
+mod module;
+mod small_str;
+mod teardown;
 pub mod utils;
-pub use function::Function;
+pub use class::{Class, Instance};
+pub(crate) use field_map::HashSeed;
+pub use function::{Function, NativeFn};
+pub use module::Module;
+pub use small_str::SmallStr;
+pub(crate) use teardown::Teardown;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Object {
     Boolean(bool),
     Number(f64),
-    String(String),
-    Function(Function),
+    String(SmallStr),
+    Function(Rc<Function>),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+    /// An immutable sequence, compared element by element.
+    Tuple(Rc<[Object]>),
+    /// The buffer of a `StringBuilder()`, shared by every copy of it.
+    Builder(Rc<RefCell<String>>),
+    /// A module bound by `import "m.lang" as m;`.
+    Module(Rc<Module>),
     Nil,
 }
 
@@ -16,10 +37,28 @@ impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Object::Boolean(b) => write!(f, "{}", b),
-            Object::Number(n) => write!(f, "{}", n),
+            Object::Number(n) => write!(f, "{}", numfmt::shortest(*n)),
             Object::String(s) => write!(f, "{}", s),
             Object::Nil => write!(f, "nil"),
             Object::Function(_) => write!(f, "<function>"),
+            Object::Class(class) => write!(f, "{class}"),
+            Object::Instance(instance) => write!(f, "{}", instance.borrow()),
+            Object::Builder(_) => write!(f, "<StringBuilder>"),
+            Object::Module(module) => write!(f, "{module}"),
+            Object::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                // `(7,)` tells a one-element tuple from a parenthesized 7
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
         }
     }
 }
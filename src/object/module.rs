@@ -0,0 +1,41 @@
+use std::{collections::BTreeMap, fmt};
+
+use super::Object;
+
+/// What a module exports, as `import "m.lang" as m;` binds it: each of its
+/// top-level names and each name it re-exports, with the value it had when
+/// the module finished running. Its exports are read as `m.name` and can
+/// never be set.
+#[derive(Debug, PartialEq, Default)]
+pub struct Module {
+    /// The name the module was loaded by, from the root.
+    pub name: String,
+    exports: BTreeMap<String, Object>,
+}
+
+impl Module {
+    pub fn new(name: String, exports: BTreeMap<String, Object>) -> Self {
+        Self { name, exports }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Object> {
+        self.exports.get(name)
+    }
+
+    /// The exports, sorted by name.
+    pub fn exports(&self) -> impl Iterator<Item = (&str, &Object)> {
+        self.exports
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.exports.keys().map(String::as_str)
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<module \"{}\">", self.name)
+    }
+}
@@ -0,0 +1,264 @@
+use std::{borrow::Borrow, cmp::Ordering, collections::TryReserveError, fmt, hash, ops::Deref};
+
+use crate::Error;
+
+/// Longest string, in bytes, kept inline without a heap allocation.
+pub const INLINE_CAPACITY: usize = 22;
+
+/// The string held by `Object::String`. Most strings a script makes are
+/// short, so those up to `INLINE_CAPACITY` bytes live in the value itself
+/// and only longer ones go to the heap. It behaves like a `str` otherwise:
+/// equality, ordering, hashing and printing only look at the text.
+#[derive(Clone)]
+pub struct SmallStr(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    // the first `len` bytes are valid UTF-8: only `Inline::push_str` writes
+    // them, a whole `str` at a time
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Heap(Box<str>),
+}
+
+impl SmallStr {
+    pub fn new(text: &str) -> Self {
+        Self::build(text.len(), |out| out.push_str(text))
+    }
+
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            // SAFETY: the bytes up to `len` are whole `str`s copied in by
+            // `try_build`, see `Repr::Inline`, so they are UTF-8
+            Repr::Inline { len, bytes } => unsafe {
+                std::str::from_utf8_unchecked(&bytes[..*len as usize])
+            },
+            Repr::Heap(text) => text,
+        }
+    }
+
+    /// Whether the text lives on the heap.
+    pub fn is_heap(&self) -> bool {
+        matches!(self.0, Repr::Heap(_))
+    }
+
+    /// `self` followed by `other`, allocating only when the result is too
+    /// long to keep inline.
+    pub fn concat(&self, other: &str) -> Self {
+        Self::build(self.len() + other.len(), |out| {
+            out.push_str(self);
+            out.push_str(other);
+        })
+    }
+
+    /// `self` written `n` times over, or `Error::MemoryLimit` when that
+    /// would take more than `limit` bytes, or more memory than there is.
+    pub fn repeat(&self, n: usize, limit: usize) -> Result<Self, Error> {
+        let too_long = || Error::MemoryLimit { limit };
+        let len = self.len().checked_mul(n).filter(|&len| len <= limit);
+        let len = len.ok_or_else(too_long)?;
+        Self::try_build(len, |out| {
+            (0..n).for_each(|_| out.push_str(self));
+        })
+        .map_err(|_| too_long())
+    }
+
+    // Runs `fill` against an inline buffer when `len` bytes fit in one, and
+    // against a string of exactly `len` bytes' capacity otherwise.
+    fn build(len: usize, fill: impl FnOnce(&mut dyn Push)) -> Self {
+        Self::try_build(len, fill).expect("capacity overflow")
+    }
+
+    // `build`, failing rather than aborting when the string cannot be
+    // allocated.
+    fn try_build(len: usize, fill: impl FnOnce(&mut dyn Push)) -> Result<Self, TryReserveError> {
+        if len <= INLINE_CAPACITY {
+            let mut inline = Inline {
+                len: 0,
+                bytes: [0; INLINE_CAPACITY],
+            };
+            fill(&mut inline);
+            debug_assert!(std::str::from_utf8(&inline.bytes[..inline.len]).is_ok());
+            return Ok(SmallStr(Repr::Inline {
+                len: inline.len as u8,
+                bytes: inline.bytes,
+            }));
+        }
+        let mut text = String::new();
+        text.try_reserve_exact(len)?;
+        fill(&mut text);
+        Ok(SmallStr(Repr::Heap(text.into_boxed_str())))
+    }
+}
+
+trait Push {
+    fn push_str(&mut self, text: &str);
+}
+
+struct Inline {
+    len: usize,
+    bytes: [u8; INLINE_CAPACITY],
+}
+
+impl Push for Inline {
+    fn push_str(&mut self, text: &str) {
+        self.bytes[self.len..self.len + text.len()].copy_from_slice(text.as_bytes());
+        self.len += text.len();
+    }
+}
+
+impl Push for String {
+    fn push_str(&mut self, text: &str) {
+        String::push_str(self, text)
+    }
+}
+
+impl Deref for SmallStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for SmallStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for SmallStr {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for SmallStr {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+/// Reuses the string's buffer when the text is too long to inline.
+impl From<String> for SmallStr {
+    fn from(text: String) -> Self {
+        if text.len() <= INLINE_CAPACITY {
+            return Self::new(&text);
+        }
+        SmallStr(Repr::Heap(text.into_boxed_str()))
+    }
+}
+
+impl PartialEq for SmallStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SmallStr {}
+
+impl PartialEq<str> for SmallStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SmallStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialOrd for SmallStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SmallStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl hash::Hash for SmallStr {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for SmallStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for SmallStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use super::{SmallStr, INLINE_CAPACITY};
+    use crate::{Error, Object};
+
+    fn hash(value: impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_inline_and_heap() {
+        assert_eq!(std::mem::size_of::<SmallStr>(), 24);
+        // no bigger than when it held a `String`
+        assert!(std::mem::size_of::<Object>() <= 32);
+
+        let fits = "é".repeat(INLINE_CAPACITY / 2);
+        let long = format!("{fits}!");
+        assert!(!SmallStr::new(&fits).is_heap());
+        assert!(SmallStr::new(&long).is_heap());
+        assert!(SmallStr::from(long.clone()).is_heap());
+        assert_eq!(SmallStr::from(long.clone()), SmallStr::new(&long));
+        assert_eq!(SmallStr::new("").as_str(), "");
+
+        let short = SmallStr::new("ab");
+        assert_eq!(short.concat("cd"), "abcd");
+        assert!(!short.concat("cd").is_heap());
+        assert_eq!(short.concat(&long).as_str(), format!("ab{long}"));
+        let repeat = |n| short.repeat(n, usize::MAX).unwrap();
+        assert_eq!(repeat(11).as_str(), "ab".repeat(11));
+        assert!(!repeat(11).is_heap());
+        assert!(repeat(12).is_heap());
+        assert_eq!(repeat(0), "");
+        // too long to count, or past the limit, is an error and not a panic
+        let too_long = Err(Error::MemoryLimit { limit: 100 });
+        assert_eq!(short.repeat(usize::MAX, 100), too_long);
+        assert_eq!(short.repeat(51, 100), too_long);
+        assert_eq!(short.repeat(50, 100).map(|s| s.len()), Ok(100));
+    }
+
+    #[test]
+    fn test_like_str() {
+        let long = "x".repeat(40);
+        for text in ["", "name", "tschüß", long.as_str()] {
+            let small = SmallStr::new(text);
+            // the same text is equal, hashes and prints the same either way
+            let other = SmallStr::from(text.to_string());
+            assert_eq!(small, other);
+            assert_eq!(hash(&small), hash(text));
+            assert_eq!(small.to_string(), text);
+            assert_eq!(format!("{small:?}"), format!("{text:?}"));
+            assert_eq!(small.clone().as_str(), text);
+        }
+        assert!(SmallStr::new("a") < SmallStr::new("b"));
+        assert!(SmallStr::new(&long) > SmallStr::new("x"));
+    }
+}
@@ -0,0 +1,144 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{Environment, Object};
+
+use super::Function;
+
+/// Values and scopes going away, taken apart one at a time rather than by
+/// recursion: a list of instances, each field holding the next, would
+/// otherwise recurse once per element as it is dropped. Only what nothing
+/// else shares is taken apart, since that is all dropping it frees.
+#[derive(Default)]
+pub(crate) struct Teardown {
+    objects: Vec<Object>,
+    scopes: Vec<Rc<RefCell<Environment>>>,
+}
+
+impl Teardown {
+    pub(crate) fn object(&mut self, object: Object) {
+        let owned = match &object {
+            Object::Function(function) => Rc::strong_count(function) == 1,
+            Object::Class(class) => Rc::strong_count(class) == 1,
+            Object::Instance(instance) => Rc::strong_count(instance) == 1,
+            Object::Tuple(elements) => Rc::strong_count(elements) == 1,
+            _ => false,
+        };
+        // anything else is dropped here, which frees nothing it refers to
+        if owned {
+            self.objects.push(object);
+        }
+    }
+
+    /// Drops a value a variable or field no longer holds.
+    pub(crate) fn discard(object: Object) {
+        let mut teardown = Teardown::default();
+        teardown.object(object);
+        teardown.run();
+    }
+
+    pub(crate) fn scope(&mut self, scope: Rc<RefCell<Environment>>) {
+        if Rc::strong_count(&scope) == 1 {
+            self.scopes.push(scope);
+        }
+    }
+
+    /// Empties what was handed over, leaving each value to drop with
+    /// nothing left inside it.
+    pub(crate) fn run(mut self) {
+        loop {
+            if let Some(object) = self.objects.pop() {
+                self.take_apart(object);
+            } else if let Some(mut scope) = self.scopes.pop() {
+                if let Some(scope) = Rc::get_mut(&mut scope) {
+                    scope.get_mut().tear_down(&mut self);
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn take_apart(&mut self, mut object: Object) {
+        match &mut object {
+            Object::Instance(instance) => {
+                if let Some(instance) = Rc::get_mut(instance) {
+                    instance.get_mut().tear_down(self);
+                }
+            }
+            Object::Tuple(elements) => {
+                for element in Rc::get_mut(elements).into_iter().flatten() {
+                    self.object(std::mem::replace(element, Object::Nil));
+                }
+            }
+            Object::Function(function) => match Rc::get_mut(function) {
+                Some(Function::User { closure, .. }) => {
+                    if let Some(closure) = Rc::get_mut(closure) {
+                        closure.get_mut().tear_down(self);
+                    }
+                }
+                Some(Function::Method { receiver, .. }) => {
+                    self.object(std::mem::replace(receiver, Object::Nil));
+                }
+                _ => {}
+            },
+            Object::Class(class) => {
+                if let Some(class) = Rc::get_mut(class) {
+                    for (_, method) in class.methods.drain() {
+                        self.object(Object::Function(method));
+                    }
+                    if let Some(super_class) = class.super_class.take() {
+                        self.object(Object::Class(super_class));
+                    }
+                    if let Some(scope) = Rc::get_mut(&mut class.scope) {
+                        scope.get_mut().tear_down(self);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Interpretor, Lexer, Parser};
+
+    // Far less than the main thread gets, and far less than dropping the
+    // values below by recursion would take.
+    const STACK: usize = 256 * 1024;
+
+    // Runs `input`, which builds a long chain and lets go of it. Globals
+    // are never freed, as functions defined there close over them, so the
+    // script drops the chain itself.
+    fn run(input: &'static str) {
+        let thread = std::thread::Builder::new().stack_size(STACK);
+        let run = move || {
+            let program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            let mut interpretor = Interpretor::buffered();
+            interpretor.run(&program).unwrap();
+            assert_eq!(interpretor.take_output(), "199999\n");
+        };
+        thread.spawn(run).unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn test_long_list_of_instances() {
+        run(
+            "class Cons { init(head, tail) { this.head = head; this.tail = tail; } }
+let (list, i) = (nil, 0);
+while (i < 200000) { list = Cons(i, list); i = i + 1; }
+print list.head;
+list = nil;",
+        );
+    }
+
+    #[test]
+    fn test_nested_tuples() {
+        run("let (list, i) = (nil, 0);
+while (i < 200000) { list = (i, list); i = i + 1; }
+print list[0];
+list = nil;");
+    }
+}
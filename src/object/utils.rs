@@ -1,13 +1,67 @@
+use std::{cmp::Ordering, rc::Rc};
+
+use crate::interpretor::MAX_LEN;
 use crate::{Error, Object, TokenType};
 
 impl Object {
     pub fn is_nil(&self) -> bool {
+        matches!(self, Object::Nil)
+    }
+
+    pub fn type_name(&self) -> &'static str {
         match self {
-            Object::Nil => true,
+            Object::Boolean(_) => "boolean",
+            Object::Number(_) => "number",
+            Object::String(_) => "string",
+            Object::Function(_) => "function",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "instance",
+            Object::Tuple(_) => "tuple",
+            Object::Builder(_) => "StringBuilder",
+            Object::Module(_) => "module",
+            Object::Nil => "nil",
+        }
+    }
+
+    /// Orders two values for `<`, `<=`, `>` and `>=`, which `op` names in
+    /// the error. Numbers compare numerically and strings by unicode scalar
+    /// values; any other pair, mixed types included, cannot be ordered.
+    /// `None` means the pair is unordered, as with NaN, so every comparison
+    /// is false.
+    pub fn compare(&self, op: &TokenType, other: &Object) -> Result<Option<Ordering>, Error> {
+        match (self, other) {
+            (Object::Number(l), Object::Number(r)) => Ok(l.partial_cmp(r)),
+            (Object::String(l), Object::String(r)) => Ok(Some(l.cmp(r))),
+            (l, r) => Err(Error::Operands {
+                op: op.clone(),
+                left: l.type_name(),
+                right: r.type_name(),
+            }),
+        }
+    }
+
+    /// Whether the value has the type a strict-types annotation names:
+    /// `any`, a name `type_name` gives, or a class its instances have.
+    pub fn has_type(&self, annotation: &str) -> bool {
+        match self {
+            _ if annotation == "any" || annotation == self.type_name() => true,
+            Object::Instance(instance) => instance.borrow().class.is_named(annotation),
             _ => false,
         }
     }
 
+    /// The type to report when a value lacks the annotated one: the class
+    /// of an instance, otherwise its `type_name`.
+    pub fn annotated_type(&self) -> String {
+        match self {
+            Object::Instance(instance) => match &instance.borrow().class.name {
+                Some(name) => name.clone(),
+                None => self.type_name().to_string(),
+            },
+            _ => self.type_name().to_string(),
+        }
+    }
+
     pub fn to_boolean(&self) -> bool {
         match self {
             Object::Nil => false,
@@ -29,39 +83,46 @@ impl Object {
                         -val
                     }))
                 } else {
-                    Err(Error::Runtime("Operand must be a number.".to_string()))
+                    Err(Error::Operand {
+                        op: op.clone(),
+                        operand: self.type_name(),
+                    })
                 }
             }
             TokenType::Not => Ok(Object::Boolean(!self.to_boolean())),
-            _ => Err(Error::Runtime("Invalid unary operator.".to_string())),
+            _ => Err(Error::Internal(format!("\"{op}\" is not a unary operator"))),
         }
     }
 
     pub fn binary(left: Object, op: &TokenType, right: Object) -> Result<Object, Error> {
+        let unsupported = |left: &Object, right: &Object| Error::Operands {
+            op: op.clone(),
+            left: left.type_name(),
+            right: right.type_name(),
+        };
         match op {
             TokenType::Plus => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Number(l + r)),
-                (Object::String(l), Object::String(r)) => Ok(Object::String(l + &r)),
-                _ => Err(Error::Runtime(
-                    "Operands must be two numbers or two strings.".to_string(),
-                )),
+                (Object::String(l), Object::String(r)) => Ok(Object::String(l.concat(&r))),
+                (Object::Tuple(l), Object::Tuple(r)) => {
+                    Ok(Object::Tuple(l.iter().chain(r.iter()).cloned().collect()))
+                }
+                (l, r) => Err(unsupported(&l, &r)),
             },
             TokenType::Minus => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Number(l - r)),
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
+                (l, r) => Err(unsupported(&l, &r)),
             },
             TokenType::Times => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Number(l * r)),
                 (Object::String(l), Object::Number(r)) | (Object::Number(r), Object::String(l)) => {
-                    Ok(Object::String(l.repeat(r as usize)))
+                    Ok(Object::String(l.repeat(r as usize, MAX_LEN)?))
                 }
                 (Object::Number(l), Object::Boolean(r))
                 | (Object::Boolean(r), Object::Number(l)) => {
                     Ok(Object::Number(if r { l } else { 0.0 }))
                 }
-                _ => Err(Error::Runtime(
-                    "Operands must be two numbers or a string and a number.".to_string(),
-                )),
+                (l, r) => Err(unsupported(&l, &r)),
             },
             TokenType::Divide => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => {
@@ -75,33 +136,58 @@ impl Object {
                 | (Object::Boolean(r), Object::Number(l)) => {
                     Ok(Object::Number(if r { l } else { 0.0 }))
                 }
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
+                (l, r) => Err(unsupported(&l, &r)),
             },
-            TokenType::And => match (left, right) {
-                (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l && r)),
-                _ => Err(Error::Runtime("Operands must be two booleans.".to_string())),
-            },
-            TokenType::Gt => match (left, right) {
-                (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l > r)),
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
-            },
-            TokenType::Gte => match (left, right) {
-                (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l >= r)),
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
+            // the remainder takes the sign of the dividend, as in Rust
+            TokenType::Mod => match (left, right) {
+                (Object::Number(l), Object::Number(r)) => {
+                    if r == 0.0 {
+                        Err(Error::ZeroDivision)
+                    } else {
+                        Ok(Object::Number(l % r))
+                    }
+                }
+                (l, r) => Err(unsupported(&l, &r)),
             },
-            TokenType::Lt => match (left, right) {
-                (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l < r)),
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
+            TokenType::LShift | TokenType::RShift | TokenType::URShift => shift(left, op, right),
+            TokenType::And | TokenType::Or | TokenType::Xor => match (left, right) {
+                (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(match op {
+                    TokenType::And => l && r,
+                    TokenType::Or => l || r,
+                    _ => l != r,
+                })),
+                (l, r) => Err(unsupported(&l, &r)),
             },
-            TokenType::Lte => match (left, right) {
-                (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l <= r)),
-                _ => Err(Error::Runtime("Operands must be two numbers.".to_string())),
+            TokenType::Gt | TokenType::Gte | TokenType::Lt | TokenType::Lte => {
+                let ordering = left.compare(op, &right)?;
+                Ok(Object::Boolean(matches!(
+                    (op, ordering),
+                    (TokenType::Gt, Some(Ordering::Greater))
+                        | (TokenType::Gte, Some(Ordering::Greater | Ordering::Equal))
+                        | (TokenType::Lt, Some(Ordering::Less))
+                        | (TokenType::Lte, Some(Ordering::Less | Ordering::Equal))
+                )))
+            }
+            TokenType::In => match (left, right) {
+                (Object::String(l), Object::String(r)) => Ok(Object::Boolean(r.contains(&*l))),
+                (l, Object::Tuple(r)) => Ok(Object::Boolean(r.iter().any(|r| equal(&l, r)))),
+                (l, r) => Err(unsupported(&l, &r)),
             },
             TokenType::Eq => match (left, right) {
                 (Object::Number(l), Object::Number(r)) => Ok(Object::Boolean(l == r)),
                 (Object::String(l), Object::String(r)) => Ok(Object::Boolean(l == r)),
                 (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l == r)),
                 (Object::Nil, Object::Nil) => Ok(Object::Boolean(true)),
+                (Object::Function(l), Object::Function(r)) => {
+                    Ok(Object::Boolean(Rc::ptr_eq(&l, &r)))
+                }
+                (Object::Class(l), Object::Class(r)) => Ok(Object::Boolean(Rc::ptr_eq(&l, &r))),
+                (Object::Instance(l), Object::Instance(r)) => {
+                    Ok(Object::Boolean(Rc::ptr_eq(&l, &r)))
+                }
+                (Object::Tuple(l), Object::Tuple(r)) => Ok(Object::Boolean(tuples_equal(&l, &r))),
+                (Object::Builder(l), Object::Builder(r)) => Ok(Object::Boolean(Rc::ptr_eq(&l, &r))),
+                (Object::Module(l), Object::Module(r)) => Ok(Object::Boolean(Rc::ptr_eq(&l, &r))),
                 _ => Ok(Object::Boolean(false)),
             },
             TokenType::Ne => match (left, right) {
@@ -109,9 +195,72 @@ impl Object {
                 (Object::String(l), Object::String(r)) => Ok(Object::Boolean(l != r)),
                 (Object::Boolean(l), Object::Boolean(r)) => Ok(Object::Boolean(l != r)),
                 (Object::Nil, Object::Nil) => Ok(Object::Boolean(false)),
+                (Object::Function(l), Object::Function(r)) => {
+                    Ok(Object::Boolean(!Rc::ptr_eq(&l, &r)))
+                }
+                (Object::Class(l), Object::Class(r)) => Ok(Object::Boolean(!Rc::ptr_eq(&l, &r))),
+                (Object::Instance(l), Object::Instance(r)) => {
+                    Ok(Object::Boolean(!Rc::ptr_eq(&l, &r)))
+                }
+                (Object::Tuple(l), Object::Tuple(r)) => Ok(Object::Boolean(!tuples_equal(&l, &r))),
+                (Object::Builder(l), Object::Builder(r)) => {
+                    Ok(Object::Boolean(!Rc::ptr_eq(&l, &r)))
+                }
+                (Object::Module(l), Object::Module(r)) => Ok(Object::Boolean(!Rc::ptr_eq(&l, &r))),
                 _ => Ok(Object::Boolean(true)),
             },
-            _ => Err(Error::Runtime("Invalid Logical operator.".to_string())),
+            _ => Err(unsupported(&left, &right)),
+        }
+    }
+}
+
+// `==`, which never fails.
+fn equal(left: &Object, right: &Object) -> bool {
+    matches!(
+        Object::binary(left.clone(), &TokenType::Eq, right.clone()),
+        Ok(Object::Boolean(true))
+    )
+}
+
+// Tuples are equal when they have the same length and their elements are
+// pairwise `==`.
+fn tuples_equal(left: &[Object], right: &[Object]) -> bool {
+    left.len() == right.len() && left.iter().zip(right).all(|(l, r)| equal(l, r))
+}
+
+/// Shifts work on integers, meaning numbers with no fractional part that fit
+/// in 64 bits, and treat them as two's complement. `<<` drops the bits
+/// shifted out, so it wraps on overflow; `>>` copies the sign bit in and
+/// `>>>` copies zeroes in, reading the left operand as unsigned. Shifting by
+/// a negative amount or by 64 or more is an error.
+fn shift(left: Object, op: &TokenType, right: Object) -> Result<Object, Error> {
+    let (l, r) = match (integer(&left), integer(&right)) {
+        (Some(l), Some(r)) => (l, r),
+        _ => {
+            return Err(Error::Type(format!(
+                "\"{op}\" needs two integers, not {left} and {right}"
+            )))
+        }
+    };
+    if !(0..64).contains(&r) {
+        return Err(Error::Value(format!(
+            "shift amount must be between 0 and 63, not {r}"
+        )));
+    }
+    let shifted = match op {
+        TokenType::LShift => l.wrapping_shl(r as u32) as f64,
+        TokenType::RShift => (l >> r) as f64,
+        _ => ((l as u64) >> r) as f64,
+    };
+    Ok(Object::Number(shifted))
+}
+
+fn integer(value: &Object) -> Option<i64> {
+    match value {
+        // 2^63 is the first value past i64::MAX
+        Object::Number(n) if n.fract() == 0.0 && *n >= -(2f64.powi(63)) && *n < 2f64.powi(63) => {
+            Some(*n as i64)
         }
+        _ => None,
     }
 }
@@ -1,36 +1,230 @@
-use crate::ast::Program;
+use std::collections::HashMap;
+
+use crate::ast::{
+    FieldInit, ImportForm, ImportName, MatchArm, NamedArg, NodeId, Pattern, Program, Signature,
+};
 use crate::Error;
 use crate::ErrorInfo;
 use crate::Lexer;
 use crate::Span;
 use crate::{Expr, LiteralType, Stmt};
-use crate::{TokenInfo, TokenType};
+use crate::{TokenInfo, TokenType, Warning, KEYWORDS};
+
+mod options;
+pub use options::{Dialect, ParserOptions};
+
+// What `class_body` reads, for both kinds of class.
+struct ClassBody {
+    super_class: Option<Expr>,
+    mixins: Vec<Expr>,
+    methods: Vec<Stmt>,
+    fields: Vec<FieldInit>,
+    private: Vec<String>,
+    // where each of `private` is named
+    private_spans: Vec<Span>,
+    doc: Option<String>,
+}
 
 pub struct Parser {
     lexer: Lexer,
-    prev: TokenInfo,
+    // span of the token most recently consumed by `advance`
+    prev_span: Span,
     curr: TokenInfo,
+    next_id: u32,
+    // number of function bodies enclosing the current token
+    function_depth: usize,
+    // an already parsed operand that `primary` hands out next
+    pending: Option<Expr>,
+    // where each statement starts
+    stmt_spans: HashMap<NodeId, Span>,
+    // the first and last line of each statement
+    stmt_lines: Vec<(usize, usize)>,
+    // where each name a node binds without a span of its own is written
+    name_spans: HashMap<NodeId, Vec<Span>>,
+    options: ParserOptions,
+    // set while parsing the initializer of a `for`, whose `;` is required
+    in_for_clause: bool,
+    warnings: Vec<Warning>,
+    // set by `parse_partial`: a statement that fails becomes a
+    // `Stmt::Error`, with the error kept in `errors`
+    recover: bool,
+    errors: Vec<ErrorInfo>,
 }
 
 impl Parser {
-    pub fn new(mut lexer: Lexer) -> Self {
+    pub fn new(lexer: Lexer) -> Self {
+        Self::with_options(lexer, ParserOptions::default())
+    }
+
+    pub fn with_options(mut lexer: Lexer, options: ParserOptions) -> Self {
         Self {
-            prev: TokenInfo::new(TokenType::Eof, 0, 0, 0, 0),
-            curr: lexer.next(),
+            prev_span: Span::new(0, 0, 0, 0),
+            curr: lexer.next_token(),
             lexer,
+            next_id: 0,
+            function_depth: 0,
+            pending: None,
+            stmt_spans: HashMap::new(),
+            stmt_lines: Vec::new(),
+            name_spans: HashMap::new(),
+            options,
+            in_for_clause: false,
+            warnings: Vec::new(),
+            recover: false,
+            errors: Vec::new(),
         }
     }
 
+    /// Makes node ids count up from `id`, for programs that must not share
+    /// ids with the scripts they run alongside.
+    pub(crate) fn with_first_id(mut self, id: u32) -> Self {
+        self.next_id = id;
+        self
+    }
+
+    /// Warnings collected by `parse_program` so far.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// The first and last line of every statement parsed so far, nested
+    /// ones included, each before the statement it is in.
+    pub fn stmt_lines(&self) -> &[(usize, usize)] {
+        &self.stmt_lines
+    }
+
+    /// Parses the whole input. A character the lexer rejected fails the
+    /// parse even where the tokens around it still make a program, and
+    /// it is reported ahead of the parse error it may have caused.
     pub fn parse_program(&mut self) -> Result<Program, ErrorInfo> {
         let mut stmt = Vec::new();
         while !self.curr.is(TokenType::Eof) {
-            stmt.push(self.declaration()?);
+            let parsed = self.declaration();
+            if let Some(err) = self.lexer.errors().first() {
+                return Err(err.clone());
+            }
+            stmt.push(parsed?);
+        }
+        if let Some(err) = self.lexer.errors().first() {
+            return Err(err.clone());
+        }
+        Ok(self.program(stmt))
+    }
+
+    /// Parses the whole input for an editor, which needs the declarations
+    /// around a mistake as well: a statement that fails to parse becomes a
+    /// `Stmt::Error` and parsing goes on after it, at the top level or in
+    /// the block around it. Every error found comes back with the program,
+    /// those of the lexer first.
+    pub fn parse_partial(&mut self) -> (Program, Vec<ErrorInfo>) {
+        self.recover = true;
+        let mut stmts = Vec::new();
+        while !self.curr.is(TokenType::Eof) {
+            let start = self.curr.span.clone();
+            match self.declaration() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) => {
+                    stmts.push(self.recover_from(err, start.clone()));
+                    // a `}` with no block to close, which is all that failed
+                    if self.curr.span.start == start.start && self.curr.is(TokenType::RCurly) {
+                        self.advance();
+                    }
+                }
+            }
+        }
+        let mut errors = self.lexer.errors().to_vec();
+        errors.append(&mut self.errors);
+        (self.program(stmts), errors)
+    }
+
+    fn program(&mut self, stmts: Vec<Stmt>) -> Program {
+        let stmt_spans = std::mem::take(&mut self.stmt_spans);
+        let name_spans = std::mem::take(&mut self.name_spans);
+        Program::new(stmts, self.next_id)
+            .with_stmt_spans(stmt_spans)
+            .with_name_spans(name_spans)
+    }
+
+    // Keeps `err` and skips what is left of the statement that started at
+    // `start`, returning the `Stmt::Error` standing in for it.
+    fn recover_from(&mut self, err: ErrorInfo, start: Span) -> Stmt {
+        self.errors.push(err);
+        self.pending = None;
+        if self.curr.span.start == start.start && !self.curr.is(TokenType::RCurly) {
+            self.advance();
+        }
+        self.synchronize();
+        let id = self.node_id();
+        self.stmt_spans.insert(id, start.clone());
+        // nothing was consumed when the statement failed at a `}`
+        let span = if self.prev_span.end > start.start {
+            start.merge(&self.prev_span)
+        } else {
+            start
+        };
+        Stmt::Error { id, span }
+    }
+
+    // Skips tokens up to where the next statement should start: past a `;`
+    // or past the `}` closing a block opened along the way, or up to the `}`
+    // closing the block around, or to a keyword starting a statement.
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.curr.token {
+                TokenType::Eof => return,
+                TokenType::RCurly if depth == 0 => return,
+                TokenType::RCurly => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.advance();
+                        return;
+                    }
+                }
+                TokenType::LCurly => depth += 1,
+                TokenType::Semicolon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                TokenType::Let
+                | TokenType::Const
+                | TokenType::Function
+                | TokenType::Class
+                | TokenType::Return
+                | TokenType::If
+                | TokenType::While
+                | TokenType::For
+                | TokenType::Print
+                | TokenType::Defer
+                | TokenType::Import
+                | TokenType::Export
+                    if depth == 0 =>
+                {
+                    return
+                }
+                _ => {}
+            }
+            self.advance();
         }
+    }
 
-        Ok(Program::new(stmt))
+    // Parses a statement with `parse` and records where it starts.
+    fn spanned(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Stmt, ErrorInfo>,
+    ) -> Result<Stmt, ErrorInfo> {
+        let span = self.curr.span.clone();
+        let stmt = parse(self)?;
+        self.stmt_lines.push((span.line, self.prev_span.line));
+        self.stmt_spans.insert(stmt.id(), span);
+        Ok(stmt)
     }
 
     fn declaration(&mut self) -> Result<Stmt, ErrorInfo> {
+        self.spanned(Self::unspanned_declaration)
+    }
+
+    fn unspanned_declaration(&mut self) -> Result<Stmt, ErrorInfo> {
         match self.curr.token {
             TokenType::Let | TokenType::Const => self.let_declaration(),
             TokenType::Class => self.class_declaration(),
@@ -38,22 +232,71 @@ impl Parser {
                 self.advance();
                 self.function_declaration()
             }
-            _ => self.statement(),
+            _ => self.unspanned_statement(),
         }
     }
 
     fn let_declaration(&mut self) -> Result<Stmt, ErrorInfo> {
+        let stmt = self.let_binding()?;
+        self.end_statement()?;
+        Ok(stmt)
+    }
+
+    // A `let` or `const` up to where its `;` would be.
+    fn let_binding(&mut self) -> Result<Stmt, ErrorInfo> {
         let is_const = self.curr.is(TokenType::Const);
         self.advance();
-        let (name, span) = self.get_identifier()?;
+        if self.curr.is(TokenType::LParen) {
+            return self.destructure(is_const);
+        }
+        let (name, span) = self.get_identifier("variable")?;
+        let annotation = self.annotation()?;
         let mut value = None;
         if self.curr.is(TokenType::Assign) {
             self.advance();
             value = Some(self.expression()?);
         }
-        self.should_be(TokenType::Semicolon)?;
         Ok(Stmt::Let {
+            id: self.node_id(),
             name,
+            annotation,
+            value,
+            is_const,
+            span,
+        })
+    }
+
+    // `let (a, b) = value;`, which always needs a value. A trailing comma is
+    // allowed, so `let (a,) = value;` unpacks a one-element tuple.
+    fn destructure(&mut self, is_const: bool) -> Result<Stmt, ErrorInfo> {
+        let start = self.should_be(TokenType::LParen)?;
+        let mut names: Vec<String> = Vec::new();
+        let mut name_spans = Vec::new();
+        while !self.curr.is(TokenType::RParen) {
+            let (name, span) = self.get_identifier("variable")?;
+            if names.contains(&name) {
+                let error = Error::Parse(format!("\"{name}\" is bound twice in one pattern"));
+                return Err(ErrorInfo::new_with_span(error, span));
+            }
+            names.push(name);
+            name_spans.push(span);
+            if !self.curr.is(TokenType::RParen) {
+                self.should_be(TokenType::Comma)?;
+            }
+        }
+        self.should_be(TokenType::RParen)?;
+        let span = start.merge(&self.prev_span);
+        if names.is_empty() {
+            let error = Error::Parse("expected at least one name to unpack into".to_string());
+            return Err(ErrorInfo::new_with_span(error, span));
+        }
+        self.should_be(TokenType::Assign)?;
+        let value = self.expression()?;
+        let id = self.node_id();
+        self.name_spans.insert(id, name_spans);
+        Ok(Stmt::Destructure {
+            id,
+            names,
             value,
             is_const,
             span,
@@ -61,51 +304,306 @@ impl Parser {
     }
 
     fn class_declaration(&mut self) -> Result<Stmt, ErrorInfo> {
-        let (name, span) = self.get_identifier()?;
+        self.advance();
+        let (name, span) = self.get_identifier("class")?;
+        let ClassBody {
+            super_class,
+            mixins,
+            methods,
+            fields,
+            private,
+            private_spans,
+            doc,
+        } = self.class_body(Some(&name))?;
+        let id = self.node_id();
+        self.name_spans.insert(id, private_spans);
+        Ok(Stmt::Class {
+            id,
+            name,
+            super_class,
+            mixins,
+            methods,
+            fields,
+            private,
+            doc,
+            span,
+        })
+    }
+
+    // `(< superclass)? (with mixin, ...)? { members }`, shared by class
+    // declarations and class expressions. The superclass and the mixins may
+    // be any expressions yielding classes.
+    // A member is a method, a field initializer `name = value;`, either of
+    // them after `priv`, or `priv name;` declaring a private field; the
+    // names declared `priv` come back in order. A string before the first
+    // member is the class's doc string.
+    fn class_body(&mut self, name: Option<&str>) -> Result<ClassBody, ErrorInfo> {
         let super_class = if self.curr.is(TokenType::Lt) {
             self.advance();
-            let (super_class_name, span) = self.get_identifier()?;
-            if name == super_class_name {
-                let error = Error::Parse("Cannot inherit from itself".to_string());
-                return Err(ErrorInfo::new_with_span(error, span));
+            let super_class = self.expression()?;
+            if let Expr::Variable {
+                name: super_class_name,
+                span,
+                ..
+            } = &super_class
+            {
+                if Some(super_class_name.as_str()) == name {
+                    let error = Error::Parse("Cannot inherit from itself".to_string());
+                    return Err(ErrorInfo::new_with_span(error, span.clone()));
+                }
             }
-            Some(super_class_name)
+            Some(super_class)
         } else {
             None
         };
-        self.should_be(TokenType::LBrace)?;
+        let mut mixins = Vec::new();
+        if self.at_word("with") {
+            self.advance();
+            mixins.push(self.expression()?);
+            while self.curr.is(TokenType::Comma) {
+                self.advance();
+                mixins.push(self.expression()?);
+            }
+        }
+        let open = self.should_be(TokenType::LCurly)?;
+        let doc = match &self.curr.token {
+            TokenType::String(doc) => {
+                let doc = doc.clone();
+                self.advance();
+                if self.curr.is(TokenType::Semicolon) {
+                    self.advance();
+                }
+                Some(doc)
+            }
+            _ => None,
+        };
         let mut methods = Vec::new();
-        while !self.curr.is(TokenType::RBrace) && !self.curr.is(TokenType::Eof) {
-            methods.push(self.function_declaration()?);
+        let mut fields: Vec<FieldInit> = Vec::new();
+        let mut private = Vec::new();
+        let mut private_spans = Vec::new();
+        while !self.curr.is(TokenType::RCurly) && !self.curr.is(TokenType::Eof) {
+            let is_private = self.curr.is(TokenType::Priv);
+            if is_private {
+                self.advance();
+            }
+            let is_function = self.curr.is(TokenType::Function);
+            if is_function {
+                self.advance();
+            }
+            let (member, span) = self.get_identifier("member")?;
+            if is_private {
+                if member == "init" {
+                    let error = Error::Parse("init cannot be private".to_string());
+                    return Err(ErrorInfo::new_with_span(error, span));
+                }
+                if !private.contains(&member) {
+                    private.push(member.clone());
+                    private_spans.push(span.clone());
+                }
+                if self.curr.is(TokenType::Semicolon) {
+                    self.advance();
+                    continue;
+                }
+            }
+            if !is_function && self.curr.is(TokenType::Assign) {
+                self.advance();
+                if fields.iter().any(|field| field.name == member) {
+                    let error = Error::Parse(format!("field \"{member}\" is initialized twice"));
+                    return Err(ErrorInfo::new_with_span(error, span));
+                }
+                let value = self.expression()?;
+                self.end_statement()?;
+                fields.push(FieldInit {
+                    id: self.node_id(),
+                    name: member,
+                    value,
+                    span,
+                });
+                continue;
+            }
+            methods.push(self.function_rest(member, span)?);
         }
-        self.should_be(TokenType::RBrace)?;
-        Ok(Stmt::Class {
-            name,
+        self.expect_closing(open, TokenType::RCurly)?;
+        Ok(ClassBody {
             super_class,
+            mixins,
             methods,
+            fields,
+            private,
+            private_spans,
+            doc,
+        })
+    }
+
+    // `match (value) { pattern (if guard)? => expr, ... }` after the `match`,
+    // which spans the expression up to the paren closing the value.
+    fn match_expr(&mut self, keyword: Span) -> Result<Expr, ErrorInfo> {
+        let open = self.should_be(TokenType::LParen)?;
+        let value = self.expression()?;
+        self.expect_closing(open, TokenType::RParen)?;
+        let span = keyword.merge(&self.prev_span);
+        let open = self.should_be(TokenType::LCurly)?;
+        let mut arms = Vec::new();
+        while !self.curr.is(TokenType::RCurly) && !self.curr.is(TokenType::Eof) {
+            let start = self.curr.span.clone();
+            let pattern = self.pattern()?;
+            let mut names = pattern.names();
+            names.sort_unstable();
+            if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+                let error = Error::Parse(format!("\"{}\" is bound twice in one pattern", pair[0]));
+                return Err(ErrorInfo::new_with_span(error, start));
+            }
+            let guard = if self.curr.is(TokenType::If) {
+                self.advance();
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            self.should_be(TokenType::FatArrow)?;
+            let body = self.expression()?;
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+            if !self.curr.is(TokenType::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect_closing(open, TokenType::RCurly)?;
+        Ok(Expr::Match {
+            id: self.node_id(),
+            value: Box::new(value),
+            arms,
             span,
         })
     }
 
+    fn pattern(&mut self) -> Result<Pattern, ErrorInfo> {
+        let TokenInfo { token, span, .. } = self.advance();
+        let literal = match token {
+            TokenType::Identifier(name) if name == "_" => return Ok(Pattern::Wildcard),
+            TokenType::Identifier(name) => return Ok(Pattern::Bind(name)),
+            TokenType::LCurly => return self.fields_pattern(span),
+            TokenType::True => LiteralType::Boolean(true),
+            TokenType::False => LiteralType::Boolean(false),
+            TokenType::Nil => LiteralType::Nil,
+            TokenType::String(x) => LiteralType::String(x),
+            TokenType::Number(x) => LiteralType::Number(x, Some(self.lexer.source(&span))),
+            TokenType::Minus if matches!(self.curr.token, TokenType::Number(_)) => {
+                let number = self.advance();
+                let TokenType::Number(x) = number.token else {
+                    unreachable!("checked above");
+                };
+                let lexeme = self.lexer.source(&span.merge(&number.span));
+                LiteralType::Number(-x, Some(lexeme))
+            }
+            token => {
+                let error = Error::Parse(format!("Expect pattern found \"{token}\""));
+                return Err(ErrorInfo::new_with_span(error, span));
+            }
+        };
+        Ok(Pattern::Literal(literal))
+    }
+
+    // `{name: pattern, other}` after the `{` at `open`.
+    fn fields_pattern(&mut self, open: Span) -> Result<Pattern, ErrorInfo> {
+        let mut fields = Vec::new();
+        while !self.curr.is(TokenType::RCurly) && !self.curr.is(TokenType::Eof) {
+            let (name, _) = self.get_identifier("field")?;
+            let pattern = if self.curr.is(TokenType::Colon) {
+                self.advance();
+                self.pattern()?
+            } else {
+                Pattern::Bind(name.clone())
+            };
+            fields.push((name, pattern));
+            if !self.curr.is(TokenType::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect_closing(open, TokenType::RCurly)?;
+        Ok(Pattern::Fields(fields))
+    }
+
     fn function_declaration(&mut self) -> Result<Stmt, ErrorInfo> {
-        let (name, span) = self.get_identifier()?;
-        self.should_be(TokenType::LParen)?;
+        let (name, span) = self.get_identifier("function")?;
+        self.function_rest(name, span)
+    }
+
+    // The parameters and body of a function whose name has been read.
+    fn function_rest(&mut self, name: String, span: Span) -> Result<Stmt, ErrorInfo> {
+        let open = self.should_be(TokenType::LParen)?;
         let mut params = Vec::new();
+        let mut param_spans = Vec::new();
+        let mut signature = Signature::default();
         if !self.curr.is(TokenType::RParen) {
-            let (param, _) = self.get_identifier()?;
+            let (param, span) = self.get_identifier("parameter")?;
             params.push(param);
+            param_spans.push(span);
+            signature.params.push(self.annotation()?);
             while self.curr.is(TokenType::Comma) {
                 self.advance();
-                let (param, _) = self.get_identifier()?;
+                let (param, span) = self.get_identifier("parameter")?;
                 params.push(param);
+                param_spans.push(span);
+                signature.params.push(self.annotation()?);
             }
         }
-        self.should_be(TokenType::RParen)?;
-        if let Stmt::Block { stmts: body } = self.block_statement()? {
+        self.expect_closing(open, TokenType::RParen)?;
+        signature.returns = self.annotation()?;
+        if signature.params.iter().all(Option::is_none) {
+            signature.params.clear();
+        }
+        // `requires (cond)` and `ensures (cond)` clauses, in any order and
+        // any number, between the parameters and the body
+        let mut requires = Vec::new();
+        let mut ensures = Vec::new();
+        while self.at_word("requires") || self.at_word("ensures") {
+            let clauses = if self.at_word("requires") {
+                &mut requires
+            } else {
+                &mut ensures
+            };
+            self.advance();
+            let span = self.should_be(TokenType::LParen)?;
+            let expr = self.expression()?;
+            self.expect_closing(span.clone(), TokenType::RParen)?;
+            // kept as a grouping for the span a failure points at
+            clauses.push(Expr::Grouping {
+                id: self.node_id(),
+                expr: Box::new(expr),
+                span: span.merge(&self.prev_span),
+            });
+        }
+        self.function_depth += 1;
+        let body = self.block_statement();
+        self.function_depth -= 1;
+        if let Stmt::Block { stmts: body, .. } = &mut body? {
+            let doc = match body.first() {
+                Some(Stmt::Expr {
+                    expr:
+                        Expr::Literal {
+                            value: LiteralType::String(doc),
+                            ..
+                        },
+                    ..
+                }) => Some(doc.clone()),
+                _ => None,
+            };
+            let id = self.node_id();
+            self.name_spans.insert(id, param_spans);
             Ok(Stmt::Function {
+                id,
                 name,
                 params,
-                body,
+                signature,
+                requires,
+                ensures,
+                body: std::mem::take(body),
+                doc,
                 span,
             })
         } else {
@@ -114,87 +612,361 @@ impl Parser {
     }
 
     fn statement(&mut self) -> Result<Stmt, ErrorInfo> {
+        self.spanned(Self::unspanned_statement)
+    }
+
+    fn unspanned_statement(&mut self) -> Result<Stmt, ErrorInfo> {
         match self.curr.token {
             TokenType::Print => self.print_statement(),
             TokenType::If => self.if_statement(),
             TokenType::While => self.while_statement(),
             TokenType::For => self.for_statement(),
             TokenType::Return => self.return_statement(),
+            TokenType::Defer => self.defer_statement(),
+            TokenType::Import => self.import_statement(),
+            TokenType::Export => self.export_statement(),
             TokenType::LCurly => self.block_statement(),
+            TokenType::Semicolon => self.empty_statement(),
             _ => self.expression_statement(),
         }
     }
 
+    fn empty_statement(&mut self) -> Result<Stmt, ErrorInfo> {
+        let span = self.advance().span;
+        Ok(Stmt::Empty {
+            id: self.node_id(),
+            span,
+        })
+    }
+
     fn expression_statement(&mut self) -> Result<Stmt, ErrorInfo> {
         let expr = self.expression()?;
-        self.should_be(TokenType::Semicolon)?;
-        Ok(Stmt::Expr { expr })
+        self.end_statement()?;
+        Ok(Stmt::Expr {
+            id: self.node_id(),
+            expr,
+        })
     }
 
+    // `print a, b;` prints its values on one line like the `print` native,
+    // and `print;` an empty line. A parenthesized first operand is
+    // ambiguous: `print(a, b);` with several arguments is a call, but only
+    // when its `)` ends the statement. Otherwise the parens are a grouping
+    // or a tuple that the rest of the expression goes on from, so
+    // `print (1 + 2) * 3;` prints 9 and `print (3, 1).sorted();` the sorted
+    // tuple. `print (a,);` prints a one-element tuple.
     fn print_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
-        let expr = self.expression()?;
-        self.should_be(TokenType::Semicolon)?;
-        Ok(Stmt::Print { expr })
+        let print = self.advance();
+        if self.curr.is(TokenType::LParen) {
+            let open = self.curr.span.clone();
+            let (mut args, named, trailing) = self.get_argument_list(true)?;
+            if self.at_statement_end() && (args.len() != 1 || !named.is_empty()) {
+                let span = print.span.merge(&self.prev_span);
+                if self.options.print_call_warnings {
+                    self.warnings.push(Warning::new(
+                        "print-call",
+                        "this calls the print function; the statement is print a, b;".to_string(),
+                        span.clone(),
+                    ));
+                }
+                let callee = Expr::Variable {
+                    id: self.node_id(),
+                    name: "print".to_string(),
+                    span: print.span,
+                };
+                let expr = Expr::Call {
+                    id: self.node_id(),
+                    callee: Box::new(callee),
+                    args,
+                    named,
+                    span,
+                };
+                self.end_statement()?;
+                return Ok(Stmt::Expr {
+                    id: self.node_id(),
+                    expr,
+                });
+            }
+            if let Some(arg) = named.first() {
+                let error =
+                    Error::Parse(format!("keyword argument \"{}\" outside a call", arg.name));
+                return Err(ErrorInfo::new_with_span(error, arg.span.clone()));
+            }
+            if args.is_empty() {
+                let error =
+                    Error::Parse(format!("Expect expression found \"{}\"", TokenType::RParen));
+                return Err(ErrorInfo::new_with_span(error, self.prev_span.clone()));
+            }
+            self.pending = Some(if args.len() == 1 && !trailing {
+                Expr::Grouping {
+                    id: self.node_id(),
+                    expr: Box::new(args.remove(0)),
+                    span: open,
+                }
+            } else {
+                Expr::Tuple {
+                    id: self.node_id(),
+                    elements: args,
+                    span: open.merge(&self.prev_span),
+                }
+            });
+        }
+        let mut exprs = Vec::new();
+        if !self.curr.is(TokenType::Semicolon) || self.pending.is_some() {
+            exprs.push(self.expression()?);
+            while self.curr.is(TokenType::Comma) {
+                self.advance();
+                exprs.push(self.expression()?);
+            }
+        }
+        self.end_statement()?;
+        Ok(Stmt::Print {
+            id: self.node_id(),
+            exprs,
+        })
     }
 
     fn return_statement(&mut self) -> Result<Stmt, ErrorInfo> {
         let val = self.advance();
         let mut value = None;
-        if !self.curr.is(TokenType::Semicolon) {
-            value = Some(self.expression()?);
+        let bare = self.curr.is(TokenType::Semicolon)
+            || self.options.lenient_semicolons && self.curr.is(TokenType::RCurly);
+        if !bare {
+            // `return a, b;` returns the tuple `(a, b)`
+            let start = self.curr.span.clone();
+            let first = self.expression()?;
+            value = Some(if self.curr.is(TokenType::Comma) {
+                let mut elements = vec![first];
+                while self.curr.is(TokenType::Comma) {
+                    self.advance();
+                    elements.push(self.expression()?);
+                }
+                Expr::Tuple {
+                    id: self.node_id(),
+                    elements,
+                    span: start.merge(&self.prev_span),
+                }
+            } else {
+                first
+            });
         }
-        self.should_be(TokenType::Semicolon)?;
+        self.end_statement()?;
         Ok(Stmt::Return {
+            id: self.node_id(),
             value,
             span: val.span,
         })
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
-        let mut stmts = Vec::new();
-        self.should_be(TokenType::LParen)?;
+    fn defer_statement(&mut self) -> Result<Stmt, ErrorInfo> {
+        let span = self.advance().span;
+        if self.function_depth == 0 {
+            let error = Error::Parse("defer outside of a function".to_string());
+            return Err(ErrorInfo::new_with_span(error, span));
+        }
+        let body = Box::new(self.statement()?);
+        Ok(Stmt::Defer {
+            id: self.node_id(),
+            body,
+            span,
+        })
+    }
 
-        match self.curr.token {
-            TokenType::Semicolon => {}
-            TokenType::Let | TokenType::Const => stmts.push(self.let_declaration()?),
-            _ => stmts.push(self.expression_statement()?),
+    // `import "path.lang";`, naming the module with a string literal, or
+    // with `as name` after it, or `import { names } from "path.lang";`.
+    // `as` and `from` are names anywhere else.
+    fn import_statement(&mut self) -> Result<Stmt, ErrorInfo> {
+        let start = self.advance().span;
+        let names = match self.curr.token {
+            TokenType::LCurly => Some(self.import_names()?),
+            _ => None,
+        };
+        let (specifier, end) = self.module_specifier("import", names.is_some())?;
+        let form = match names {
+            Some(names) => ImportForm::Names(names),
+            None if self.at_word("as") => {
+                self.advance();
+                let (alias, span) = self.get_identifier("module")?;
+                ImportForm::Module { alias, span }
+            }
+            None => ImportForm::All,
+        };
+        let span = start.merge(&end);
+        self.end_statement()?;
+        Ok(Stmt::Import {
+            id: self.node_id(),
+            specifier,
+            form,
+            span,
+        })
+    }
+
+    // `export { names } from "path.lang";`
+    fn export_statement(&mut self) -> Result<Stmt, ErrorInfo> {
+        let start = self.advance().span;
+        if !self.curr.is(TokenType::LCurly) {
+            let error = Error::Parse(format!(
+                "expected the names to export in braces, found \"{}\"",
+                self.curr.token
+            ));
+            return Err(ErrorInfo::new_with_span(error, self.curr.span.clone()));
+        }
+        let names = self.import_names()?;
+        let (specifier, end) = self.module_specifier("export from", true)?;
+        let span = start.merge(&end);
+        self.end_statement()?;
+        Ok(Stmt::Import {
+            id: self.node_id(),
+            specifier,
+            form: ImportForm::ReExport(names),
+            span,
+        })
+    }
+
+    // `{ a, b as c }`, with a comma allowed after the last name.
+    fn import_names(&mut self) -> Result<Vec<ImportName>, ErrorInfo> {
+        let open = self.should_be(TokenType::LCurly)?;
+        let mut names = Vec::new();
+        while !self.curr.is(TokenType::RCurly) && !self.curr.is(TokenType::Eof) {
+            let (name, mut span) = self.get_identifier("variable")?;
+            let mut alias = None;
+            if self.at_word("as") {
+                self.advance();
+                let (name, end) = self.get_identifier("variable")?;
+                span = span.merge(&end);
+                alias = Some(name);
+            }
+            names.push(ImportName { name, alias, span });
+            if !self.curr.is(TokenType::RCurly) {
+                self.should_be(TokenType::Comma)?;
+            }
+        }
+        let close = self.expect_closing(open.clone(), TokenType::RCurly)?;
+        if names.is_empty() {
+            let error = Error::Parse("expected at least one name in the braces".to_string());
+            return Err(ErrorInfo::new_with_span(error, open.merge(&close)));
+        }
+        Ok(names)
+    }
+
+    // The string literal naming a module, after `from` when the names came
+    // first.
+    fn module_specifier(&mut self, what: &str, from: bool) -> Result<(String, Span), ErrorInfo> {
+        if from {
+            if !self.at_word("from") {
+                let error = Error::Expected {
+                    expected: "from".to_string(),
+                    found: self.curr.token.to_string(),
+                };
+                return Err(ErrorInfo::new_with_span(error, self.curr.span.clone()));
+            }
+            self.advance();
+        }
+        let token = self.advance();
+        let TokenType::String(specifier) = token.token else {
+            let error = Error::Parse(format!(
+                "expected the module to {what} as a string, found \"{}\"",
+                token.token
+            ));
+            return Err(ErrorInfo::new_with_span(error, token.span));
         };
+        Ok((specifier, token.span))
+    }
+
+    fn for_statement(&mut self) -> Result<Stmt, ErrorInfo> {
+        let span = self.advance().span;
+        let open = self.should_be(TokenType::LParen)?;
 
-        let condition = if self.curr.is(TokenType::Semicolon) {
-            Expr::Literal {
-                value: LiteralType::Boolean(true),
+        self.in_for_clause = true;
+        let initializer = match self.curr.token {
+            TokenType::Semicolon => {
+                self.advance();
+                Ok(None)
             }
-        } else {
-            self.expression()?
+            TokenType::Let | TokenType::Const => self.spanned(Self::let_binding).map(Some),
+            _ => self.spanned(Self::expression_statement).map(Some),
+        };
+        // `to` after the declaration instead of `;` makes it a counting loop
+        let is_range = matches!(
+            initializer,
+            Ok(Some(Stmt::Let { .. } | Stmt::Destructure { .. }))
+        ) && self.options.range_for
+            && self.at_word("to");
+        let initializer = match initializer {
+            Ok(Some(stmt @ (Stmt::Let { .. } | Stmt::Destructure { .. }))) if !is_range => {
+                self.end_statement().map(|_| Some(stmt))
+            }
+            initializer => initializer,
+        };
+        self.in_for_clause = false;
+        let initializer = initializer?;
+        if is_range {
+            let initializer = initializer.expect("a counting loop starts with a declaration");
+            return self.for_range(span, open, initializer);
+        }
+        let condition = match self.curr.token {
+            TokenType::Semicolon => None,
+            _ => Some(self.expression()?),
         };
         self.should_be(TokenType::Semicolon)?;
         let increment = match self.curr.token {
             TokenType::RParen => None,
             _ => Some(self.expression()?),
         };
-        self.should_be(TokenType::RParen)?;
+        self.expect_closing(open, TokenType::RParen)?;
+
+        let body = self.statement()?;
+        Ok(Stmt::For {
+            id: self.node_id(),
+            initializer: initializer.map(Box::new),
+            condition,
+            increment,
+            body: Box::new(body),
+            span,
+        })
+    }
 
-        let mut body = self.statement()?;
-        if let Some(expr) = increment {
-            body = Stmt::Block {
-                stmts: vec![body, Stmt::Expr { expr }],
+    // The rest of `for (let i = start to end step s) body`, from `to`. The
+    // counter must be a plain `let` with a start value. `open` is the `(`.
+    fn for_range(&mut self, span: Span, open: Span, initializer: Stmt) -> Result<Stmt, ErrorInfo> {
+        match &initializer {
+            Stmt::Let {
+                is_const: false,
+                value: Some(_),
+                ..
+            } => {}
+            Stmt::Let { span, .. } | Stmt::Destructure { span, .. } => {
+                let error = Error::Parse(
+                    "the counter of a for-to loop must be \"let name = start\"".to_string(),
+                );
+                return Err(ErrorInfo::new_with_span(error, span.clone()));
             }
+            _ => unreachable!("only declarations start a for-to loop"),
         }
-        let while_stmt = Stmt::While {
-            condition,
+        self.advance();
+        let end = self.expression()?;
+        let mut step = None;
+        if self.at_word("step") {
+            self.advance();
+            step = Some(self.expression()?);
+        }
+        self.expect_closing(open, TokenType::RParen)?;
+        let body = self.statement()?;
+        Ok(Stmt::ForRange {
+            id: self.node_id(),
+            initializer: Box::new(initializer),
+            end,
+            step,
             body: Box::new(body),
-        };
-        stmts.push(while_stmt);
-        Ok(Stmt::Block { stmts })
+            span,
+        })
     }
 
     fn if_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
-        self.should_be(TokenType::LParen)?;
+        let span = self.advance().span;
+        let open = self.should_be(TokenType::LParen)?;
         let condition = self.expression()?;
-        self.should_be(TokenType::RParen)?;
+        self.expect_closing(open, TokenType::RParen)?;
         let truthy = Box::new(self.statement()?);
         let mut falsy = None;
         if self.curr.is(TokenType::Else) {
@@ -202,29 +974,44 @@ impl Parser {
             falsy = Some(Box::new(self.statement()?));
         }
         Ok(Stmt::If {
+            id: self.node_id(),
             condition,
             truthy,
             falsy,
+            span,
         })
     }
 
     fn while_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.advance();
-        self.should_be(TokenType::LParen)?;
+        let span = self.advance().span;
+        let open = self.should_be(TokenType::LParen)?;
         let condition = self.expression()?;
-        self.should_be(TokenType::RParen)?;
+        self.expect_closing(open, TokenType::RParen)?;
         let body = Box::new(self.statement()?);
-        Ok(Stmt::While { condition, body })
+        Ok(Stmt::While {
+            id: self.node_id(),
+            condition,
+            body,
+            span,
+        })
     }
 
     fn block_statement(&mut self) -> Result<Stmt, ErrorInfo> {
-        self.should_be(TokenType::LCurly)?;
+        let open = self.should_be(TokenType::LCurly)?;
         let mut stmts = Vec::new();
         while !self.curr.is(TokenType::RCurly) && !self.curr.is(TokenType::Eof) {
-            stmts.push(self.declaration()?);
+            let start = self.curr.span.clone();
+            match self.declaration() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) if self.recover => stmts.push(self.recover_from(err, start)),
+                Err(err) => return Err(err),
+            }
         }
-        self.should_be(TokenType::RCurly)?;
-        Ok(Stmt::Block { stmts })
+        self.expect_closing(open, TokenType::RCurly)?;
+        Ok(Stmt::Block {
+            id: self.node_id(),
+            stmts,
+        })
     }
 }
 
@@ -234,42 +1021,67 @@ impl Parser {
     }
 
     fn assignment(&mut self) -> Result<Expr, ErrorInfo> {
-        let left = self.or()?;
+        let mut left = self.pipeline()?;
         if let TokenType::Assign
         | TokenType::PlusEq
+        | TokenType::MinusEq
         | TokenType::TimesEq
         | TokenType::ModEq
         | TokenType::DivideEq
         | TokenType::AndEq
         | TokenType::OrEq
-        | TokenType::XorEq = self.curr.token
+        | TokenType::XorEq
+        | TokenType::LShiftEq
+        | TokenType::RShiftEq
+        | TokenType::URShiftEq = self.curr.token
         {
             let mut op = self.advance();
-            let mut right = self.or()?;
-            if let Some(token) = desugar_assign(op.token) {
+            let mut right = self.pipeline()?;
+            let compound = desugar_assign(op.token.clone());
+            if let Some(token) = compound.clone() {
                 op.token = token;
-                right = Expr::Binary {
-                    left: Box::new(left.clone()),
-                    op: op.clone(),
-                    right: Box::new(right),
-                };
+                // a compound assignment to a field stays one node, so the
+                // object is evaluated once
+                if !matches!(left, Expr::Get { .. }) {
+                    right = Expr::Binary {
+                        id: self.node_id(),
+                        left: Box::new(self.clone_expr(&left)),
+                        op: op.clone(),
+                        right: Box::new(right),
+                    };
+                }
             }
 
-            return match left {
-                Expr::Variable { name, span } => Ok(Expr::Assign {
-                    name,
+            // the target node becomes the assignment, so it keeps its id
+            return match &mut left {
+                Expr::Variable { id, name, span } => Ok(Expr::Assign {
+                    id: *id,
+                    name: std::mem::take(name),
                     value: Box::new(right),
-                    span,
+                    span: span.clone(),
                 }),
-                Expr::Get { object, name, span } => Ok(Expr::Set {
+                Expr::Get {
+                    id,
                     object,
                     name,
-                    value: Box::new(right),
                     span,
+                } => Ok(Expr::Set {
+                    id: *id,
+                    object: Box::new(object.take()),
+                    name: std::mem::take(name),
+                    op: compound.map(|_| op),
+                    value: Box::new(right),
+                    span: span.clone(),
                 }),
+                Expr::Index { .. } => {
+                    let error = Error::Parse(
+                        "cannot assign to an index: tuples and strings are immutable".to_string(),
+                    );
+                    Err(ErrorInfo::new_with_span(error, op.span))
+                }
                 _ => {
                     let error = Error::Parse("Invalid assignment target".to_string());
-                    return Err(ErrorInfo::new_with_span(error, op.span));
+                    Err(ErrorInfo::new_with_span(error, op.span))
                 }
             };
         }
@@ -277,12 +1089,40 @@ impl Parser {
         Ok(left)
     }
 
+    // `value |> stage`, run left to right. It is only a way of writing a
+    // call: a stage that is a call gets the value as its first argument,
+    // `x |> f(a)` being `f(x, a)`, and any other stage is called with it,
+    // `x |> f` being `f(x)`. Each call keeps the stage's span, so an error
+    // points at the stage that failed.
+    fn pipeline(&mut self) -> Result<Expr, ErrorInfo> {
+        let mut left = self.or()?;
+        while self.curr.is(TokenType::Pipe) {
+            self.advance();
+            let mut stage = self.or()?;
+            left = match &mut stage {
+                Expr::Call { args, .. } => {
+                    args.insert(0, left);
+                    stage
+                }
+                _ => Expr::Call {
+                    id: self.node_id(),
+                    span: stage.extent(),
+                    callee: Box::new(stage),
+                    args: vec![left],
+                    named: Vec::new(),
+                },
+            };
+        }
+        Ok(left)
+    }
+
     fn or(&mut self) -> Result<Expr, ErrorInfo> {
         let mut left = self.and()?;
-        while self.curr.is(TokenType::Or) {
+        while self.curr.is(TokenType::LogicalOr) {
             let op = self.advance();
             let right = self.and()?;
-            left = Expr::Binary {
+            left = Expr::Logical {
+                id: self.node_id(),
                 left: Box::new(left),
                 op,
                 right: Box::new(right),
@@ -296,7 +1136,8 @@ impl Parser {
         while self.curr.is(TokenType::LogicalAnd) {
             let op = self.advance();
             let right = self.equality()?;
-            left = Expr::Binary {
+            left = Expr::Logical {
+                id: self.node_id(),
                 left: Box::new(left),
                 op,
                 right: Box::new(right),
@@ -311,6 +1152,7 @@ impl Parser {
             let op = self.advance();
             let right = self.comparison()?;
             left = Expr::Binary {
+                id: self.node_id(),
                 left: Box::new(left),
                 op,
                 right: Box::new(right),
@@ -320,12 +1162,51 @@ impl Parser {
     }
 
     fn comparison(&mut self) -> Result<Expr, ErrorInfo> {
-        let mut left = self.term()?;
-        while let TokenType::Gt | TokenType::Gte | TokenType::Lt | TokenType::Lte = self.curr.token
+        let mut operands = vec![self.operand_start()];
+        let mut left = self.shift()?;
+        operands[0] = operands[0].merge(&self.prev_span);
+        while let TokenType::Gt | TokenType::Gte | TokenType::Lt | TokenType::Lte | TokenType::In =
+            self.curr.token
         {
+            let op = self.advance();
+            let start = self.curr.span.clone();
+            let right = self.shift()?;
+            operands.push(start.merge(&self.prev_span));
+            left = Expr::Binary {
+                id: self.node_id(),
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+            };
+        }
+        if operands.len() > 2 {
+            return Err(self.chained_comparison(&operands));
+        }
+        Ok(left)
+    }
+
+    // `1 < x < 10` would compare a boolean with a number, so it is rejected
+    // with a suggestion spelling out the intended conjunction.
+    fn chained_comparison(&self, operands: &[Span]) -> ErrorInfo {
+        let suggestion = operands
+            .windows(2)
+            .map(|pair| self.lexer.source(&pair[0].merge(&pair[1])))
+            .collect::<Vec<_>>()
+            .join(" && ");
+        let error = Error::Parse(format!(
+            "chained comparisons are not supported; did you mean `{suggestion}`?"
+        ));
+        let span = operands[0].merge(&operands[operands.len() - 1]);
+        ErrorInfo::new_with_span(error, span)
+    }
+
+    fn shift(&mut self) -> Result<Expr, ErrorInfo> {
+        let mut left = self.term()?;
+        while let TokenType::LShift | TokenType::RShift | TokenType::URShift = self.curr.token {
             let op = self.advance();
             let right = self.term()?;
             left = Expr::Binary {
+                id: self.node_id(),
                 left: Box::new(left),
                 op,
                 right: Box::new(right),
@@ -346,6 +1227,7 @@ impl Parser {
             let op = self.advance();
             let right = self.factor()?;
             left = Expr::Binary {
+                id: self.node_id(),
                 left: Box::new(left),
                 op,
                 right: Box::new(right),
@@ -360,6 +1242,7 @@ impl Parser {
             let op = self.advance();
             let right = self.unary()?;
             left = Expr::Binary {
+                id: self.node_id(),
                 left: Box::new(left),
                 op,
                 right: Box::new(right),
@@ -369,10 +1252,15 @@ impl Parser {
     }
 
     fn unary(&mut self) -> Result<Expr, ErrorInfo> {
+        // with an operand pending, a sign after it is a binary operator
+        if self.pending.is_some() {
+            return self.call();
+        }
         if let TokenType::Minus | TokenType::Not | TokenType::Plus = self.curr.token {
             let op = self.advance();
             let right = self.unary()?;
             Ok(Expr::Unary {
+                id: self.node_id(),
                 op,
                 right: Box::new(right),
             })
@@ -382,20 +1270,34 @@ impl Parser {
     }
 
     fn call(&mut self) -> Result<Expr, ErrorInfo> {
+        // calls span from the start of the callee to the closing paren
+        let start = self.operand_start();
         let mut expr = self.primary()?;
         loop {
             if self.curr.is(TokenType::LParen) {
-                let span = self.curr.span.clone();
-                let args = self.get_argument_list()?;
-                break Ok(Expr::Call {
+                let (args, named, _) = self.get_argument_list(false)?;
+                expr = Expr::Call {
+                    id: self.node_id(),
                     callee: Box::new(expr),
                     args,
-                    span,
-                });
+                    named,
+                    span: start.merge(&self.prev_span),
+                };
+            } else if self.curr.is(TokenType::LBrace) {
+                self.advance();
+                let index = self.expression()?;
+                self.should_be(TokenType::RBrace)?;
+                expr = Expr::Index {
+                    id: self.node_id(),
+                    object: Box::new(expr),
+                    index: Box::new(index),
+                    span: start.merge(&self.prev_span),
+                };
             } else if self.curr.is(TokenType::Dot) {
                 self.advance();
-                let (name, span) = self.get_identifier()?;
+                let (name, span) = self.get_identifier("property")?;
                 expr = Expr::Get {
+                    id: self.node_id(),
                     object: Box::new(expr),
                     name,
                     span,
@@ -406,110 +1308,571 @@ impl Parser {
         }
     }
 
-    fn get_argument_list(&mut self) -> Result<Vec<Expr>, ErrorInfo> {
+    // With `trailing_comma`, a `,` before the closing paren is accepted and
+    // reported in the result, which `print (a,);` needs to see a tuple.
+    fn get_argument_list(
+        &mut self,
+        trailing_comma: bool,
+    ) -> Result<(Vec<Expr>, Vec<NamedArg>, bool), ErrorInfo> {
         let mut args = Vec::new();
-        self.should_be(TokenType::LParen)?;
+        let mut named: Vec<NamedArg> = Vec::new();
+        let mut trailing = false;
+        let open = self.should_be(TokenType::LParen)?;
         if !self.curr.is(TokenType::RParen) {
             loop {
-                if args.len() >= 127 {
+                if args.len() + named.len() >= 127 {
                     let error = Error::TooManyParamerters;
                     return Err(ErrorInfo::new_with_span(error, self.curr.span.clone()));
                 }
-                args.push(self.expression()?);
+                // `name: value` is told apart from a positional argument once the
+                // expression is parsed: a bare variable followed by a colon. Any
+                // colon belonging to a nested expression has been consumed by then.
+                let start = self.curr.span.clone();
+                let mut arg = self.expression()?;
+                match &mut arg {
+                    Expr::Variable { name, span, .. } if self.curr.is(TokenType::Colon) => {
+                        let (name, span) = (std::mem::take(name), span.clone());
+                        self.advance();
+                        let value = self.expression()?;
+                        named.push(NamedArg { name, value, span });
+                    }
+                    _ => {
+                        if let Some(prev) = named.last() {
+                            let error = Error::Parse(format!(
+                                "positional argument follows keyword argument \"{}\"",
+                                prev.name
+                            ));
+                            return Err(ErrorInfo::new_with_span(error, start));
+                        }
+                        args.push(arg);
+                    }
+                }
                 if !self.curr.is(TokenType::Comma) {
                     break;
                 }
                 self.should_be(TokenType::Comma)?;
+                if trailing_comma && self.curr.is(TokenType::RParen) {
+                    trailing = true;
+                    break;
+                }
             }
         }
-        self.should_be(TokenType::RParen)?;
-        Ok(args)
+        self.expect_closing(open, TokenType::RParen)?;
+        Ok((args, named, trailing))
+    }
+
+    // where the next operand starts: at the pending one if there is one
+    fn operand_start(&self) -> Span {
+        match &self.pending {
+            Some(Expr::Grouping { span, .. } | Expr::Tuple { span, .. }) => span.clone(),
+            _ => self.curr.span.clone(),
+        }
     }
 
     fn primary(&mut self) -> Result<Expr, ErrorInfo> {
-        let tok = self.curr.clone();
-        let span = tok.span;
-        match tok.token {
+        if let Some(expr) = self.pending.take() {
+            return Ok(expr);
+        }
+        if !starts_expression(&self.curr.token) {
+            let error = Error::Parse(format!("Expect expression found \"{}\"", self.curr.token));
+            return Err(ErrorInfo::new_with_span(error, self.curr.span.clone()));
+        }
+        let TokenInfo { token, span, .. } = self.advance();
+        match token {
             TokenType::True => {
-                self.advance();
                 let value = LiteralType::Boolean(true);
-                Ok(Expr::Literal { value })
+                Ok(Expr::Literal {
+                    id: self.node_id(),
+                    value,
+                    span,
+                })
             }
             TokenType::False => {
-                self.advance();
                 let value = LiteralType::Boolean(false);
-                Ok(Expr::Literal { value })
+                Ok(Expr::Literal {
+                    id: self.node_id(),
+                    value,
+                    span,
+                })
             }
             TokenType::Nil => {
-                self.advance();
                 let value = LiteralType::Nil;
-                Ok(Expr::Literal { value })
+                Ok(Expr::Literal {
+                    id: self.node_id(),
+                    value,
+                    span,
+                })
             }
             TokenType::Number(x) => {
-                self.advance();
-                let value = LiteralType::Number(x);
-                Ok(Expr::Literal { value })
+                let value = LiteralType::Number(x, Some(self.lexer.source(&span)));
+                Ok(Expr::Literal {
+                    id: self.node_id(),
+                    value,
+                    span,
+                })
             }
             TokenType::String(x) => {
-                self.advance();
                 let value = LiteralType::String(x);
-                Ok(Expr::Literal { value })
-            }
-            TokenType::Identifier(name) => {
-                self.advance();
-                Ok(Expr::Variable { name, span })
+                Ok(Expr::Literal {
+                    id: self.node_id(),
+                    value,
+                    span,
+                })
             }
+            TokenType::Identifier(name) => Ok(Expr::Variable {
+                id: self.node_id(),
+                name,
+                span,
+            }),
+            TokenType::Placeholder(name) => Ok(Expr::Placeholder {
+                id: self.node_id(),
+                name,
+                span,
+            }),
+            // `(a)` groups while `(a, b)` and `(a,)` are tuples
             TokenType::LParen => {
-                self.advance();
-                let expr = Box::new(self.expression()?);
-                self.should_be(TokenType::RParen)?;
-                Ok(Expr::Grouping { expr, span })
+                let expr = self.expression()?;
+                if !self.curr.is(TokenType::Comma) {
+                    self.expect_closing(span.clone(), TokenType::RParen)?;
+                    return Ok(Expr::Grouping {
+                        id: self.node_id(),
+                        expr: Box::new(expr),
+                        span,
+                    });
+                }
+                let mut elements = vec![expr];
+                while self.curr.is(TokenType::Comma) {
+                    self.advance();
+                    if self.curr.is(TokenType::RParen) {
+                        break;
+                    }
+                    elements.push(self.expression()?);
+                }
+                self.expect_closing(span.clone(), TokenType::RParen)?;
+                Ok(Expr::Tuple {
+                    id: self.node_id(),
+                    elements,
+                    span: span.merge(&self.prev_span),
+                })
+            }
+            TokenType::Class => {
+                let name = match self.curr.token {
+                    TokenType::Identifier(_) => Some(self.get_identifier("class")?.0),
+                    _ => None,
+                };
+                let ClassBody {
+                    super_class,
+                    mixins,
+                    methods,
+                    fields,
+                    private,
+                    private_spans,
+                    doc,
+                } = self.class_body(name.as_deref())?;
+                let id = self.node_id();
+                self.name_spans.insert(id, private_spans);
+                Ok(Expr::Class {
+                    id,
+                    name,
+                    super_class: super_class.map(Box::new),
+                    mixins,
+                    methods,
+                    fields,
+                    private,
+                    doc,
+                    span,
+                })
             }
+            TokenType::Match => self.match_expr(span),
             TokenType::Super => {
                 self.should_be(TokenType::Dot)?;
-                let (name, span) = self.get_identifier()?;
-                Ok(Expr::Super { name, span })
+                let (name, span) = self.get_identifier("method")?;
+                Ok(Expr::Super {
+                    id: self.node_id(),
+                    name,
+                    span,
+                })
             }
+            // `print` is a keyword only at the start of a statement; elsewhere
+            // it names the native function
+            TokenType::Print => Ok(Expr::Variable {
+                id: self.node_id(),
+                name: "print".to_string(),
+                span,
+            }),
             TokenType::This => {
                 let name = "this".to_string();
-                Ok(Expr::Variable { name, span })
-            }
-            _ => {
-                let error = Error::Parse(format!("Expect expression found \"{}\"", tok.token));
-                Err(ErrorInfo::new_with_span(error, span))
+                Ok(Expr::Variable {
+                    id: self.node_id(),
+                    name,
+                    span,
+                })
             }
+            _ => unreachable!("checked by starts_expression"),
         }
     }
 }
 
 impl Parser {
+    // whether a statement can end at the current token: a `;`, or where
+    // lenient parsing assumes one
+    fn at_statement_end(&self) -> bool {
+        self.curr.is(TokenType::Semicolon)
+            || self.options.lenient_semicolons
+                && !self.in_for_clause
+                && (self.curr.span.line > self.prev_span.line
+                    || self.curr.is(TokenType::RCurly)
+                    || self.curr.is(TokenType::Eof))
+    }
+
+    // The `;` ending a statement. In lenient mode one missing at the end of
+    // a line, before a `}` or at the end of the file is assumed, with a
+    // warning pointing just past the statement.
+    fn end_statement(&mut self) -> Result<(), ErrorInfo> {
+        if !self.curr.is(TokenType::Semicolon) && self.at_statement_end() {
+            let prev = &self.prev_span;
+            let span = Span::new(prev.line, prev.line_start, prev.end, prev.end);
+            self.warnings.push(Warning::new(
+                "missing-semicolon",
+                "missing \";\" assumed".to_string(),
+                span,
+            ));
+            return Ok(());
+        }
+        self.should_be(TokenType::Semicolon).map(|_| ())
+    }
+
     fn should_be(&mut self, token_type: TokenType) -> Result<Span, ErrorInfo> {
         let val = self.advance();
         if val.token == token_type {
             Ok(val.span)
         } else {
-            let error = Error::Syntax(format!(
-                "Expected: \"{}\" Found: \"{}\"",
-                token_type, val.token
-            ));
+            let error = Error::Expected {
+                expected: token_type.to_string(),
+                found: val.token.to_string(),
+            };
             Err(ErrorInfo::new_with_span(error, val.span))
         }
     }
 
-    fn get_identifier(&mut self) -> Result<(String, Span), ErrorInfo> {
+    // The bracket closing the one opened at `open`. Running out of input
+    // before it is reported at the opener: the end of the file says nothing
+    // about which bracket was left open.
+    fn expect_closing(&mut self, open: Span, close: TokenType) -> Result<Span, ErrorInfo> {
+        if !self.curr.is(TokenType::Eof) {
+            return self.should_be(close);
+        }
+        let bracket = self.lexer.source(&open);
+        let error = Error::Syntax(format!("this '{bracket}' is never closed"));
+        Err(ErrorInfo::new_with_span(error, open)
+            .with_label("the file ends here".to_string(), self.curr.span.clone()))
+    }
+
+    // Whether the current token is the identifier `word`, for words such as
+    // `to` that are keywords only in one place and names everywhere else.
+    // The `: type` after a name or a parameter list, if there is one. A type
+    // is any name, or `nil`.
+    fn annotation(&mut self) -> Result<Option<String>, ErrorInfo> {
+        if !self.curr.is(TokenType::Colon) {
+            return Ok(None);
+        }
+        let colon = self.advance();
+        if !self.options.type_annotations {
+            let error = Error::Syntax("type annotations are off in this dialect".to_string());
+            return Err(ErrorInfo::new_with_span(error, colon.span));
+        }
+        if self.curr.is(TokenType::Nil) {
+            self.advance();
+            return Ok(Some("nil".to_string()));
+        }
+        let (name, _) = self.get_identifier("type")?;
+        Ok(Some(name))
+    }
+
+    fn at_word(&self, word: &str) -> bool {
+        matches!(&self.curr.token, TokenType::Identifier(name) if name == word)
+    }
+
+    // A name, of the kind `what` says, e.g. "parameter". A keyword gets its
+    // own message, pointing at the escape that makes it a name.
+    fn get_identifier(&mut self, what: &str) -> Result<(String, Span), ErrorInfo> {
         let val = self.advance();
         if let TokenType::Identifier(name) = val.token {
-            Ok((name, val.span))
-        } else {
-            let error = Error::Syntax(format!("Expected: \"Identifier\" Found: \"{}\"", val.token));
-            Err(ErrorInfo::new_with_span(error, val.span))
+            return Ok((name, val.span));
         }
+        let word = self.lexer.source(&val.span);
+        let error = if KEYWORDS.contains(&word.as_str()) {
+            Error::Syntax(format!(
+                "'{word}' is a reserved keyword and cannot be used as a {what} name; write `{word}` to use it as one"
+            ))
+        } else {
+            Error::Expected {
+                expected: "Identifier".to_string(),
+                found: val.token.to_string(),
+            }
+        };
+        Err(ErrorInfo::new_with_span(error, val.span))
     }
 
+    // Hands out the current token by value; payloads such as identifier
+    // names move into the AST without being copied.
     fn advance(&mut self) -> TokenInfo {
-        self.prev = self.curr.clone();
-        self.curr = self.lexer.next();
-        self.prev.clone()
+        let token = std::mem::replace(&mut self.curr, self.lexer.next_token());
+        self.prev_span = token.span.clone();
+        token
+    }
+
+    fn node_id(&mut self) -> NodeId {
+        let id = NodeId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    // Duplicates a subtree for desugaring, handing the copy fresh ids so that
+    // no two nodes in a program share one.
+    fn clone_expr(&mut self, expr: &Expr) -> Expr {
+        let mut expr = expr.clone();
+        self.renumber(&mut expr);
+        expr
+    }
+
+    fn renumber(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Assign { id, value, .. } => {
+                *id = self.node_id();
+                self.renumber(value);
+            }
+            Expr::Binary {
+                id, left, right, ..
+            }
+            | Expr::Logical {
+                id, left, right, ..
+            } => {
+                *id = self.node_id();
+                self.renumber(left);
+                self.renumber(right);
+            }
+            Expr::Call {
+                id,
+                callee,
+                args,
+                named,
+                ..
+            } => {
+                *id = self.node_id();
+                self.renumber(callee);
+                args.iter_mut().for_each(|arg| self.renumber(arg));
+                named
+                    .iter_mut()
+                    .for_each(|arg| self.renumber(&mut arg.value));
+            }
+            Expr::Get { id, object, .. } => {
+                *id = self.node_id();
+                self.renumber(object);
+            }
+            Expr::Grouping { id, expr, .. } => {
+                *id = self.node_id();
+                self.renumber(expr);
+            }
+            Expr::Index {
+                id, object, index, ..
+            } => {
+                *id = self.node_id();
+                self.renumber(object);
+                self.renumber(index);
+            }
+            Expr::Tuple { id, elements, .. } => {
+                *id = self.node_id();
+                elements.iter_mut().for_each(|expr| self.renumber(expr));
+            }
+            Expr::Set {
+                id, object, value, ..
+            } => {
+                *id = self.node_id();
+                self.renumber(object);
+                self.renumber(value);
+            }
+            Expr::Unary { id, right, .. } => {
+                *id = self.node_id();
+                self.renumber(right);
+            }
+            Expr::Class {
+                id,
+                super_class,
+                mixins,
+                methods,
+                fields,
+                ..
+            } => {
+                *id = self.node_id();
+                if let Some(super_class) = super_class {
+                    self.renumber(super_class);
+                }
+                mixins.iter_mut().for_each(|expr| self.renumber(expr));
+                methods.iter_mut().for_each(|stmt| self.renumber_stmt(stmt));
+                for field in fields {
+                    field.id = self.node_id();
+                    self.renumber(&mut field.value);
+                }
+            }
+            Expr::Match {
+                id, value, arms, ..
+            } => {
+                *id = self.node_id();
+                self.renumber(value);
+                for arm in arms {
+                    if let Some(guard) = &mut arm.guard {
+                        self.renumber(guard);
+                    }
+                    self.renumber(&mut arm.body);
+                }
+            }
+            Expr::Literal { id, .. }
+            | Expr::Placeholder { id, .. }
+            | Expr::Super { id, .. }
+            | Expr::Variable { id, .. } => {
+                *id = self.node_id();
+            }
+        }
+    }
+
+    fn renumber_stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Print { id, exprs } => {
+                *id = self.node_id();
+                exprs.iter_mut().for_each(|expr| self.renumber(expr));
+            }
+            Stmt::Expr { id, expr }
+            | Stmt::Destructure {
+                id, value: expr, ..
+            } => {
+                *id = self.node_id();
+                self.renumber(expr);
+            }
+            Stmt::Let { id, value, .. } | Stmt::Return { id, value, .. } => {
+                *id = self.node_id();
+                if let Some(value) = value {
+                    self.renumber(value);
+                }
+            }
+            Stmt::Block { id, stmts } => {
+                *id = self.node_id();
+                stmts.iter_mut().for_each(|stmt| self.renumber_stmt(stmt));
+            }
+            Stmt::Function {
+                id,
+                requires,
+                ensures,
+                body,
+                ..
+            } => {
+                *id = self.node_id();
+                requires
+                    .iter_mut()
+                    .chain(ensures)
+                    .for_each(|expr| self.renumber(expr));
+                body.iter_mut().for_each(|stmt| self.renumber_stmt(stmt));
+            }
+            Stmt::Class {
+                id,
+                super_class,
+                mixins,
+                methods,
+                fields,
+                ..
+            } => {
+                *id = self.node_id();
+                if let Some(super_class) = super_class {
+                    self.renumber(super_class);
+                }
+                mixins.iter_mut().for_each(|expr| self.renumber(expr));
+                methods.iter_mut().for_each(|stmt| self.renumber_stmt(stmt));
+                for field in fields {
+                    field.id = self.node_id();
+                    self.renumber(&mut field.value);
+                }
+            }
+            Stmt::If {
+                id,
+                condition,
+                truthy,
+                falsy,
+                ..
+            } => {
+                *id = self.node_id();
+                self.renumber(condition);
+                self.renumber_stmt(truthy);
+                if let Some(falsy) = falsy {
+                    self.renumber_stmt(falsy);
+                }
+            }
+            Stmt::While {
+                id,
+                condition,
+                body,
+                ..
+            } => {
+                *id = self.node_id();
+                self.renumber(condition);
+                self.renumber_stmt(body);
+            }
+            Stmt::For {
+                id,
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                *id = self.node_id();
+                if let Some(initializer) = initializer {
+                    self.renumber_stmt(initializer);
+                }
+                if let Some(condition) = condition {
+                    self.renumber(condition);
+                }
+                if let Some(increment) = increment {
+                    self.renumber(increment);
+                }
+                self.renumber_stmt(body);
+            }
+            Stmt::ForRange {
+                id,
+                initializer,
+                end,
+                step,
+                body,
+                ..
+            } => {
+                *id = self.node_id();
+                self.renumber_stmt(initializer);
+                self.renumber(end);
+                if let Some(step) = step {
+                    self.renumber(step);
+                }
+                self.renumber_stmt(body);
+            }
+            Stmt::Defer { id, body, .. } => {
+                *id = self.node_id();
+                self.renumber_stmt(body);
+            }
+            Stmt::Break { id, .. }
+            | Stmt::Continue { id, .. }
+            | Stmt::Import { id, .. }
+            | Stmt::Empty { id, .. }
+            | Stmt::Error { id, .. } => *id = self.node_id(),
+        }
+    }
+}
+
+/// `source` with a `;` after it unless it ends in one or in a `}`, so that
+/// input such as `1 + 2` parses as a statement.
+pub(crate) fn terminated(source: &str) -> String {
+    let trimmed = source.trim_end();
+    if trimmed.ends_with(';') || trimmed.ends_with('}') {
+        trimmed.to_string()
+    } else {
+        format!("{trimmed};")
     }
 }
 
@@ -523,13 +1886,38 @@ pub fn desugar_assign(tok: TokenType) -> Option<TokenType> {
         TokenType::OrEq => Some(TokenType::Or),
         TokenType::TimesEq => Some(TokenType::Times),
         TokenType::XorEq => Some(TokenType::Xor),
+        TokenType::LShiftEq => Some(TokenType::LShift),
+        TokenType::RShiftEq => Some(TokenType::RShift),
+        TokenType::URShiftEq => Some(TokenType::URShift),
         _ => None,
     }
 }
 
+// The tokens `primary` can build an expression from.
+fn starts_expression(token: &TokenType) -> bool {
+    matches!(
+        token,
+        TokenType::True
+            | TokenType::False
+            | TokenType::Nil
+            | TokenType::Number(_)
+            | TokenType::String(_)
+            | TokenType::Identifier(_)
+            | TokenType::Placeholder(_)
+            | TokenType::LParen
+            | TokenType::Class
+            | TokenType::Match
+            | TokenType::Super
+            | TokenType::Print
+            | TokenType::This
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::Node;
+    use crate::{analyze, Interpretor, Resolver, SymbolKind};
 
     #[test]
     fn test_hello_world() {
@@ -553,7 +1941,7 @@ mod tests {
         print a; ";
         let mut parser = Parser::new(Lexer::new(input.to_string()));
         let expr = parser.parse_program().unwrap();
-        assert_eq!(expr.to_string(), "((let a 1)(print a))");
+        assert_eq!(expr.to_string(), "((let a 1) (print a))");
     }
 
     #[test]
@@ -568,7 +1956,703 @@ mod tests {
         let expr = parser.parse_program().unwrap();
         assert_eq!(
             expr.to_string(),
-            "((if (== a 1) then ((print a)) else ((print b))))"
+            "((if (== a 1) then (block (print a)) else (block (print b))))"
+        );
+    }
+
+    fn parse(input: &str) -> String {
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        parser.parse_program().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_keywords_as_names() {
+        let reserved = |input: &str| {
+            let mut parser = Parser::new(Lexer::new(input.to_string()));
+            match parser.parse_program().unwrap_err().error {
+                Error::Syntax(message) => message,
+                error => panic!("{input:?} failed with {error}"),
+            }
+        };
+        let message = |word: &str, what: &str| {
+            format!(
+                "'{word}' is a reserved keyword and cannot be used as a {what} name; write `{word}` to use it as one"
+            )
+        };
+        let cases = [
+            ("let class = 3;", "class", "variable"),
+            ("let (a, if) = t;", "if", "variable"),
+            ("fn while() {}", "while", "function"),
+            ("fn f(a, return) {}", "return", "parameter"),
+            ("class import {}", "import", "class"),
+            ("class A { let = 1; }", "let", "member"),
+            ("class A { priv nil; }", "nil", "member"),
+            ("x.for;", "for", "property"),
+        ];
+        for (input, word, what) in cases {
+            assert_eq!(reserved(input), message(word, what), "{input}");
+        }
+        // anything else that is not a name is reported as before
+        let mut parser = Parser::new(Lexer::new("let 1 = 2;".to_string()));
+        let err = parser.parse_program().unwrap_err();
+        assert!(matches!(err.error, Error::Expected { .. }));
+
+        // escaped, a keyword is a name like any other, and other names are
+        // the same escaped or not
+        assert_eq!(
+            parse("let `class` = 3; fn f(`if`) { return `if`; } x.`for` = `x`;"),
+            "((let class 3) (fn f (if) (return if)) (set x for x))"
         );
     }
+
+    #[test]
+    fn test_dangling_else() {
+        // the `else` goes with the nearest `if`
+        assert_eq!(
+            parse("if (a) if (b) print 1; else print 2;"),
+            "((if a then (if b then (print 1) else (print 2))))"
+        );
+        assert_eq!(
+            parse("if (a) { if (b) print 1; } else print 2;"),
+            "((if a then (block (if b then (print 1))) else (print 2)))"
+        );
+        assert_eq!(
+            parse("if (a) print 1; else if (b) print 2; else print 3;"),
+            "((if a then (print 1) else (if b then (print 2) else (print 3))))"
+        );
+        assert_eq!(
+            parse("if (a) print 1; else if (b) print 2;"),
+            "((if a then (print 1) else (if b then (print 2))))"
+        );
+
+        let chain = "
+        if (n == 1) print 1;
+        else if (n == 2) print 2;
+        else if (n == 3) print 3;
+        else if (n == 4) print 4;
+        else if (n == 5) print 5;
+        else print 0;";
+        assert_eq!(
+            parse(chain),
+            "((if (== n 1) then (print 1) else (if (== n 2) then (print 2) else \
+             (if (== n 3) then (print 3) else (if (== n 4) then (print 4) else \
+             (if (== n 5) then (print 5) else (print 0)))))))"
+        );
+    }
+
+    #[test]
+    fn test_print_values() {
+        assert_eq!(parse("print a, 1 + 2, \"s\";"), "((print a (+ 1 2) \"s\"))");
+        assert_eq!(parse("print;"), "((print))");
+        assert_eq!(parse("print (a), b;"), "((print a b))");
+    }
+
+    #[test]
+    fn test_assignment_targets() {
+        assert_eq!(parse("a.b[0].c = 1;"), "((set (index (get a b) 0) c 1))");
+        // a compound field assignment keeps the object once
+        assert_eq!(parse("f().x += 1;"), "((set (call f) x += 1))");
+        assert_eq!(parse("a -= 1;"), "((= a (- a 1)))");
+    }
+
+    #[test]
+    fn test_single_statement_bodies() {
+        assert_eq!(
+            parse("while (a) if (b) a = 1; else a = 2;"),
+            "((while a (if b then (= a 1) else (= a 2))))"
+        );
+        assert_eq!(
+            parse("for (;;) while (a) print a;"),
+            "((for (; ; ) (while a (print a))))"
+        );
+        assert_eq!(parse("if (a); else;"), "((if a then ; else ;))");
+        assert_eq!(parse(";;"), "(; ;)");
+    }
+
+    #[test]
+    fn test_for_to() {
+        assert_eq!(
+            parse("for (let i = 0 to n) print i;"),
+            "((for (let i 0) to n (print i)))"
+        );
+        assert_eq!(
+            parse("for (let i = n to 0 step -1) {}"),
+            "((for (let i n) to 0 step (- 1) (block)))"
+        );
+        // without `let`, `to` is a name like any other
+        assert_eq!(
+            parse("for (i = 0; to; ) {}"),
+            "((for ((= i 0); to; ) (block)))"
+        );
+        let err = Parser::new(Lexer::new("for (let (a, b) = t to 3) {}".to_string()))
+            .parse_program()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ParseError: the counter of a for-to loop must be \"let name = start\", line 1, pos 9"
+        );
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        assert_eq!(
+            parse("fn add(a: number, b): number { let s: string = a; }"),
+            "((fn add:number (a:number b) (let s:string a)))"
+        );
+        assert_eq!(
+            parse("class P { m(x: P): nil requires (x) {} }"),
+            "((class P (fn m:nil (x:P) (requires x))))"
+        );
+        assert_eq!(
+            parse("for (let i: number = 0 to 3) {}"),
+            "((for (let i:number 0) to 3 (block)))"
+        );
+        let err = Parser::new(Lexer::new("let x: = 1;".to_string()))
+            .parse_program()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "SyntaxError: Expected: \"Identifier\" Found: \"=\", line 1, pos 7"
+        );
+    }
+
+    #[test]
+    fn test_import() {
+        assert_eq!(
+            parse("import \"lib/a.lang\"; import \"./b.lang\";"),
+            "((import \"lib/a.lang\") (import \"./b.lang\"))"
+        );
+        let err = Parser::new(Lexer::new("import lib;".to_string()))
+            .parse_program()
+            .unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Parse("expected the module to import as a string, found \"lib\"".to_string())
+        );
+
+        let input = "import \"m.lang\" as m;
+import { a, b as c, } from \"m.lang\";
+export { d as e } from \"n.lang\";
+let as = 1; let from = as;";
+        assert_eq!(
+            parse(input),
+            "((import \"m.lang\" as m) (import \"m.lang\" a (b as c)) (export \"n.lang\" (d as e)) \
+             (let as 1) (let from as))"
+        );
+        let error = |input: &str| {
+            Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap_err()
+                .error
+                .to_string()
+        };
+        assert_eq!(
+            error("import { a } \"m.lang\";"),
+            "SyntaxError: Expected: \"from\" Found: \"\"m.lang\"\""
+        );
+        assert_eq!(
+            error("import {} from \"m.lang\";"),
+            "ParseError: expected at least one name in the braces"
+        );
+        assert_eq!(
+            error("export a from \"m.lang\";"),
+            "ParseError: expected the names to export in braces, found \"a\""
+        );
+        assert_eq!(
+            error("import \"m.lang\" as 1;"),
+            "SyntaxError: Expected: \"Identifier\" Found: \"1\""
+        );
+    }
+
+    #[test]
+    fn test_node_ids() {
+        let input = "
+        let a = 1;
+        a += 2;
+        for (let i = 0; i < a; i += 1) print i;";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        let mut ids = std::collections::HashSet::new();
+        for id in 0..program.node_count() as u32 {
+            let node = program.node(NodeId(id)).expect("every id is reachable");
+            let found = match node {
+                Node::Expr(expr) => expr.id(),
+                Node::Stmt(stmt) => stmt.id(),
+            };
+            assert_eq!(found, NodeId(id));
+            assert!(ids.insert(found));
+        }
+        assert_eq!(program.node(NodeId(program.node_count() as u32)), None);
+    }
+
+    #[test]
+    fn test_keyword_arguments() {
+        let input = "draw(1, color: \"red\", y: a + 1);";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        let Stmt::Expr {
+            expr: Expr::Call { args, named, .. },
+            ..
+        } = &program.stmts[0]
+        else {
+            panic!("expected a call statement");
+        };
+        assert_eq!(args.len(), 1);
+        let named: Vec<String> = named
+            .iter()
+            .map(|arg| format!("{}={}", arg.name, arg.value))
+            .collect();
+        assert_eq!(named, vec!["color=\"red\"", "y=(+ a 1)"]);
+    }
+
+    #[test]
+    fn test_chained_comparison() {
+        let cases = [
+            ("1 < x < 10;", "1 < x && x < 10"),
+            ("a <= b < c >= d;", "a <= b && b < c && c >= d"),
+        ];
+        for (input, suggestion) in cases {
+            let mut parser = Parser::new(Lexer::new(input.to_string()));
+            let err = parser.parse_program().unwrap_err();
+            let expected =
+                format!("chained comparisons are not supported; did you mean `{suggestion}`?");
+            assert_eq!(err.error, Error::Parse(expected));
+            assert_eq!(err.span().start, 0);
+            assert_eq!(err.span().end, input.len() - 1);
+        }
+    }
+
+    #[test]
+    fn test_tuples() {
+        let parse = |input: &str| {
+            Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .map(|program| program.to_string())
+        };
+        assert_eq!(parse("(a);").unwrap(), "(a)");
+        assert_eq!(parse("(a,);").unwrap(), "((tuple a))");
+        assert_eq!(parse("(a, b + 1,);").unwrap(), "((tuple a (+ b 1)))");
+        assert_eq!(parse("t[0][i];").unwrap(), "((index (index t 0) i))");
+        assert_eq!(
+            parse("return a, (b, c);").unwrap(),
+            "((return (tuple a (tuple b c))))"
+        );
+        assert_eq!(parse("let (x, y,) = t;").unwrap(), "((let (x y) t))");
+
+        // there is no comma operator
+        let err = parse("a, b;").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "SyntaxError: Expected: \";\" Found: \",\", line 1, pos 1"
+        );
+        let err = parse("let (x, x) = t;").unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Parse("\"x\" is bound twice in one pattern".to_string())
+        );
+        let err = parse("let () = t;").unwrap_err();
+        assert_eq!(err.span().start, 4);
+        assert!(parse("let (x, y);").is_err());
+    }
+
+    #[test]
+    fn test_class_expression() {
+        let input = "let A = class < make(1) { get() { return 1; } }; a.b().c;";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        let Stmt::Let {
+            value:
+                Some(Expr::Class {
+                    name,
+                    super_class,
+                    methods,
+                    ..
+                }),
+            ..
+        } = &program.stmts[0]
+        else {
+            panic!("expected a class expression, got {}", program.stmts[0]);
+        };
+        assert_eq!(*name, None);
+        assert!(matches!(super_class.as_deref(), Some(Expr::Call { .. })));
+        assert_eq!(methods.len(), 1);
+        assert_eq!(program.stmts[1].to_string(), "(get (call (get a b)) c)");
+
+        assert_eq!(
+            parse("class A { m(x) requires (x) ensures (result) {} }"),
+            "((class A (fn m (x) (requires x) (ensures result))))"
+        );
+        assert_eq!(
+            parse("class A < B with C, make(D) {}"),
+            "((class A < B (with C (call make D))))"
+        );
+        // `with` is only a keyword after the class name or superclass
+        assert_eq!(parse("with = 1;"), "((= with 1))");
+
+        let mut parser = Parser::new(Lexer::new("class A < A {}".to_string()));
+        let err = parser.parse_program().unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Parse("Cannot inherit from itself".to_string())
+        );
+    }
+
+    #[test]
+    fn test_private_members() {
+        let input = "let A = class { priv n; priv fn get() { return 1; } priv get; set() {} };";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        let Stmt::Let {
+            value: Some(Expr::Class {
+                methods, private, ..
+            }),
+            ..
+        } = &program.stmts[0]
+        else {
+            panic!("expected a class expression, got {}", program.stmts[0]);
+        };
+        assert_eq!(methods.len(), 2);
+        assert_eq!(*private, ["n", "get"]);
+
+        let mut parser = Parser::new(Lexer::new("class A { priv init() {} }".to_string()));
+        let err = parser.parse_program().unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Parse("init cannot be private".to_string())
+        );
+    }
+
+    #[test]
+    fn test_field_initializers() {
+        assert_eq!(
+            parse("class A { x = 1; priv y = this.x; m() {} }"),
+            "((class A (priv y) (field x 1) (field y (get this x)) (fn m ())))"
+        );
+
+        let input = "class A { x = 1; x = 2; }";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let err = parser.parse_program().unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Parse("field \"x\" is initialized twice".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unclosed_brackets() {
+        // the message and where it points, and where the label says the
+        // file ends
+        let unclosed = |input: &str| {
+            let mut parser = Parser::new(Lexer::new(input.to_string()));
+            let err = parser.parse_program().unwrap_err();
+            let Error::Syntax(message) = &err.error else {
+                panic!("{input:?} failed with {}", err.error);
+            };
+            let labels: Vec<_> = err.labels().iter().map(|(_, span)| span.start).collect();
+            (message.clone(), err.span().start, labels)
+        };
+        let never = |bracket: &str| format!("this '{bracket}' is never closed");
+
+        let input = "fn f() {\n  print 1;\n";
+        assert_eq!(unclosed(input), (never("{"), 7, vec![input.len()]));
+        let input = "class A {\n  x = 1;\n  m() {}\n";
+        assert_eq!(unclosed(input), (never("{"), 8, vec![input.len()]));
+        assert_eq!(unclosed("print max(1, 2"), (never("("), 9, vec![14]));
+        assert_eq!(unclosed("fn f(a, b"), (never("("), 4, vec![9]));
+        assert_eq!(unclosed("if (x"), (never("("), 3, vec![5]));
+        assert_eq!(unclosed("print (1, 2"), (never("("), 6, vec![11]));
+        // the innermost bracket is the one reported
+        let input = "fn f() {\n  while (true) {\n    g(h(1)\n";
+        assert_eq!(unclosed(input), (never("("), 31, vec![input.len()]));
+        let input = "fn f() {\n  while (true) {\n    g(h(1));\n";
+        assert_eq!(unclosed(input), (never("{"), 24, vec![input.len()]));
+
+        // a wrong token before the end is still reported where it is
+        let mut parser = Parser::new(Lexer::new("print max(1, 2;".to_string()));
+        let err = parser.parse_program().unwrap_err();
+        assert_eq!(err.span().start, 14);
+        assert!(err.labels().is_empty());
+    }
+
+    #[test]
+    fn test_number_lexemes() {
+        let input = "print 0xFF + 1_000 * 1e3 + 2.50;";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let program = parser.parse_program().unwrap();
+        assert_eq!(
+            program.to_string(),
+            "((print (+ (+ 0xFF (* 1_000 1e3)) 2.50)))"
+        );
+
+        // the spelling is kept for printing but the values are equal
+        let values = |input: &str| -> Vec<LiteralType> {
+            let program = Parser::new(Lexer::new(input.to_string())).parse_program();
+            let stmts = program.unwrap().stmts.into_iter();
+            stmts
+                .map(|stmt| match &stmt {
+                    Stmt::Expr {
+                        expr: Expr::Literal { value, .. },
+                        ..
+                    } => value.clone(),
+                    stmt => panic!("not a literal: {stmt}"),
+                })
+                .collect()
+        };
+        assert_eq!(values("0xFF; 1_000; 1e3;"), values("255; 1000; 1000;"));
+    }
+
+    #[test]
+    fn test_parenthesized_comparison() {
+        let input = "(a < b) == c; (1 < x) < 10;";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let expr = parser.parse_program().unwrap();
+        assert_eq!(expr.to_string(), "((== (< a b) c) (< (< 1 x) 10))");
+    }
+
+    #[test]
+    fn test_shift_precedence() {
+        let input = "1 + 2 << 3 - 1; a < 1 << b; x >> 1 >>> 2 << 3; x <<= 2; x >>>= y + 1;";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let expr = parser.parse_program().unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "((<< (+ 1 2) (- 3 1)) (< a (<< 1 b)) (<< (>>> (>> x 1) 2) 3) (= x (<< x 2)) (= x (>>> x (+ y 1))))"
+        );
+    }
+
+    #[test]
+    fn test_logical_precedence() {
+        let input = "a && b || c; a || b && c; a || b || c;";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let expr = parser.parse_program().unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "((|| (&& a b) c) (|| a (&& b c)) (|| (|| a b) c))"
+        );
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let input = "x |> f(a) |> g |> h.m(); y = x | 1 |> (k(2)); x |> f(b: 1);";
+        let mut parser = Parser::new(Lexer::new(input.to_string()));
+        let expr = parser.parse_program().unwrap();
+        assert_eq!(
+            expr.to_string(),
+            "((call (get h m) (call g (call f x a))) (= y (call (call k 2) (| x 1))) (call f x (b: 1)))"
+        );
+
+        // each call spans its stage
+        let input = "data\n  |> first()\n  |> second(1);";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let Stmt::Expr { expr, .. } = &program.stmts[0] else {
+            panic!("an expression statement");
+        };
+        let Expr::Call { args, span, .. } = expr else {
+            panic!("a call");
+        };
+        assert_eq!((span.line, span.start, span.end), (3, 23, 32));
+        let Expr::Call { span, .. } = &args[0] else {
+            panic!("a call");
+        };
+        assert_eq!((span.line, span.start, span.end), (2, 10, 17));
+    }
+
+    #[test]
+    fn test_lenient_semicolons() {
+        let lenient = ParserOptions {
+            lenient_semicolons: true,
+            ..ParserOptions::default()
+        };
+        let parse = |input: &str, options| {
+            let mut parser = Parser::with_options(Lexer::new(input.to_string()), options);
+            let program = parser.parse_program();
+            let warnings: Vec<_> = parser.warnings().iter().map(|w| w.to_string()).collect();
+            (program, warnings)
+        };
+
+        let strict = "let a = 1;\nfn f(x) {\n  print x;\n  return x + a; }\nfn g() { return; }\nprint(f(a), 2);\nf(2);";
+        // blanked out rather than removed, so the spans line up
+        let loose = strict.replace(';', " ");
+        let (expected, warnings) = parse(strict, lenient);
+        assert!(warnings.is_empty());
+        let (program, warnings) = parse(&loose, lenient);
+        assert_eq!(program.unwrap(), expected.unwrap());
+        assert_eq!(
+            warnings,
+            [
+                "warning: missing \";\" assumed, line 1, pos 9",
+                "warning: missing \";\" assumed, line 3, pos 10",
+                "warning: missing \";\" assumed, line 4, pos 15",
+                "warning: missing \";\" assumed, line 5, pos 16",
+                "warning: missing \";\" assumed, line 6, pos 15",
+                "warning: missing \";\" assumed, line 7, pos 5",
+            ]
+        );
+
+        // strict by default
+        let (program, _) = parse(&loose, ParserOptions::default());
+        assert_eq!(
+            program.unwrap_err().to_string(),
+            "SyntaxError: Expected: \";\" Found: \"fn\", line 2, pos 1"
+        );
+
+        // still errors: statements sharing a line, and the clauses of a for
+        for (input, error) in [
+            (
+                "let a = 1 print a;",
+                "SyntaxError: Expected: \";\" Found: \"print\", line 1, pos 10",
+            ),
+            (
+                "for (let i = 0\n i < 3; i = i + 1) {}",
+                "SyntaxError: Expected: \";\" Found: \"i\", line 2, pos 2",
+            ),
+            (
+                "for (let i = 0; i < 3\n i = i + 1) {}",
+                "SyntaxError: Expected: \";\" Found: \"i\", line 2, pos 2",
+            ),
+        ] {
+            let (program, _) = parse(input, lenient);
+            assert_eq!(program.unwrap_err().to_string(), error);
+        }
+    }
+
+    #[test]
+    fn test_dialect_options() {
+        let parse = |input: &str, options| {
+            let mut parser = Parser::with_options(Lexer::new(input.to_string()), options);
+            let program = parser.parse_program().map_err(|err| err.to_string());
+            let warnings: Vec<_> = parser.warnings().iter().map(|w| w.code()).collect();
+            (program.map(|program| program.to_string()), warnings)
+        };
+        let on = ParserOptions::default();
+
+        let input = "let s: string = \"a\";";
+        let off = ParserOptions {
+            type_annotations: false,
+            ..on
+        };
+        assert_eq!(parse(input, on).0.unwrap(), "((let s:string \"a\"))");
+        assert_eq!(
+            parse(input, off).0.unwrap_err(),
+            "SyntaxError: type annotations are off in this dialect, line 1, pos 5"
+        );
+        let input = "fn f(x: number): nil {}";
+        assert!(parse(input, on).0.is_ok());
+        assert!(parse(input, off).0.is_err());
+
+        let input = "for (let i = 1 to 3) {}";
+        let off = ParserOptions {
+            range_for: false,
+            ..on
+        };
+        assert!(parse(input, on).0.is_ok());
+        assert_eq!(
+            parse(input, off).0.unwrap_err(),
+            "SyntaxError: Expected: \";\" Found: \"to\", line 1, pos 15"
+        );
+        // `to` is a name as ever
+        assert!(parse("let to = 1; print to;", off).0.is_ok());
+
+        let input = "print(1, 2);\nprint (1);\nprint 1, 2;";
+        let warn = ParserOptions {
+            print_call_warnings: true,
+            ..on
+        };
+        let (program, warnings) = parse(input, on);
+        assert!(warnings.is_empty());
+        assert_eq!(parse(input, warn), (program, vec!["print-call"]));
+    }
+
+    // four functions, the third of them with two broken statements
+    const BROKEN: &str = "fn one() { return 1; }
+fn two() { return 2; }
+fn three() {
+  let x = ;
+  return x +;
+  print \"still here\";
+}
+fn four() { return 4; }
+";
+
+    #[test]
+    fn test_broken_function_body() {
+        let (program, errors) = Parser::new(Lexer::new(BROKEN.to_string())).parse_partial();
+        let lines: Vec<_> = errors.iter().map(|err| err.line()).collect();
+        assert_eq!(lines, [4, 5]);
+        let names: Vec<_> = program
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Function { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, ["one", "two", "three", "four"]);
+        assert_eq!(
+            program.stmts[2].to_string(),
+            "(fn three () (error) (error) (print \"still here\"))"
+        );
+
+        let index = analyze(&program);
+        let functions: Vec<_> = index
+            .symbols()
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Function)
+            .map(|symbol| symbol.name.as_str())
+            .collect();
+        assert_eq!(functions, ["one", "two", "three", "four"]);
+
+        // the broken statements only fail when they run
+        Resolver::new().resolve(&program).unwrap();
+        let mut interpretor = Interpretor::buffered();
+        interpretor.run(&program).unwrap();
+        let (program, _) =
+            Parser::new(Lexer::new("print one() + two() + four();".to_string())).parse_partial();
+        interpretor.run(&program).unwrap();
+        assert_eq!(interpretor.take_output(), "7\n");
+        let (program, _) = Parser::new(Lexer::new("three();".to_string())).parse_partial();
+        let err = interpretor.run(&program).unwrap_err();
+        assert_eq!(
+            (err.to_string().as_str(), err.line()),
+            (
+                "SyntaxError: this statement failed to parse, line 4, pos 3",
+                4
+            )
+        );
+    }
+
+    #[test]
+    fn test_broken_declarations() {
+        // a broken header, a stray `}` and an unfinished class at the top level
+        let source = "fn one() { return 1; }
+fn (a) { return a; }
+}
+let two = 2;
+class Three { init( }
+print two;
+";
+        let (program, errors) = Parser::new(Lexer::new(source.to_string())).parse_partial();
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        let kinds: Vec<_> = program
+            .stmts
+            .iter()
+            .map(|stmt| stmt.to_string().split(' ').next().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            kinds,
+            ["(fn", "(error)", "(error)", "(let", "(error)", "(print"]
+        );
+        let mut interpretor = Interpretor::buffered();
+        interpretor.run(&program).unwrap_err();
+        assert_eq!(interpretor.take_output(), "");
+
+        // without errors, the program is the one `parse_program` gives
+        let fixed = BROKEN.replace(" ;", " 3;").replace("+;", "+ 1;");
+        let (partial, errors) = Parser::new(Lexer::new(fixed.clone())).parse_partial();
+        assert!(errors.is_empty());
+        let program = Parser::new(Lexer::new(fixed)).parse_program().unwrap();
+        assert_eq!(partial.to_string(), program.to_string());
+    }
 }
@@ -0,0 +1,139 @@
+use std::fmt;
+
+/// Settings for a `Parser`, one for each piece of syntax a dialect may
+/// leave out or warn about. The default is the language as it always was.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ParserOptions {
+    /// Lets a statement leave out its `;` at the end of a line or before a
+    /// `}`, collecting a warning instead of failing. Two statements on one
+    /// line still need one, as do the clauses of a `for`.
+    pub lenient_semicolons: bool,
+    /// Allows the `: type` after a variable, a parameter or a parameter
+    /// list. Without it an annotation is a syntax error.
+    pub type_annotations: bool,
+    /// Allows `for (let i = a to b step s)`. Without it `to` is a name like
+    /// any other, and the loop a syntax error.
+    pub range_for: bool,
+    /// Warns about `print(a, b);`, which calls the `print` function rather
+    /// than being the `print a, b;` statement.
+    pub print_call_warnings: bool,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            lenient_semicolons: false,
+            type_annotations: true,
+            range_for: true,
+            print_call_warnings: false,
+        }
+    }
+}
+
+/// A named set of `ParserOptions`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Dialect {
+    /// Every `;` required, all of the syntax, and a warning for a `print`
+    /// written as a call.
+    Strict,
+    /// Forgiving about `;` and without type annotations, for people
+    /// learning the language; `print` calls warn, so they learn the
+    /// statement.
+    Teaching,
+    /// Forgiving about `;` and quiet about `print` calls.
+    Permissive,
+}
+
+impl Dialect {
+    /// The dialect `strict`, `teaching` or `permissive` names.
+    pub fn parse(name: &str) -> Option<Dialect> {
+        match name {
+            "strict" => Some(Dialect::Strict),
+            "teaching" => Some(Dialect::Teaching),
+            "permissive" => Some(Dialect::Permissive),
+            _ => None,
+        }
+    }
+
+    pub fn options(self) -> ParserOptions {
+        match self {
+            Dialect::Strict => ParserOptions {
+                print_call_warnings: true,
+                ..ParserOptions::default()
+            },
+            Dialect::Teaching => ParserOptions {
+                lenient_semicolons: true,
+                type_annotations: false,
+                print_call_warnings: true,
+                ..ParserOptions::default()
+            },
+            Dialect::Permissive => ParserOptions {
+                lenient_semicolons: true,
+                ..ParserOptions::default()
+            },
+        }
+    }
+}
+
+impl ParserOptions {
+    /// Each setting by the name a manifest gives it, and its value.
+    pub fn settings(&self) -> [(&'static str, bool); 4] {
+        [
+            ("lenient_semicolons", self.lenient_semicolons),
+            ("type_annotations", self.type_annotations),
+            ("range_for", self.range_for),
+            ("print_call_warnings", self.print_call_warnings),
+        ]
+    }
+
+    /// Changes the setting `name` names, as `settings` does. False for a
+    /// name that is not a setting.
+    pub fn set(&mut self, name: &str, value: bool) -> bool {
+        let setting = match name {
+            "lenient_semicolons" => &mut self.lenient_semicolons,
+            "type_annotations" => &mut self.type_annotations,
+            "range_for" => &mut self.range_for,
+            "print_call_warnings" => &mut self.print_call_warnings,
+            _ => return false,
+        };
+        *setting = value;
+        true
+    }
+}
+
+/// One `name = value` line per setting, as a `project.toml` pins them.
+impl fmt::Display for ParserOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in self.settings() {
+            writeln!(f, "{name} = {value}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Dialect, ParserOptions};
+
+    #[test]
+    fn test_settings() {
+        let teaching = Dialect::Teaching.options();
+        assert_eq!(
+            teaching.to_string(),
+            "lenient_semicolons = true\ntype_annotations = false\n\
+             range_for = true\nprint_call_warnings = true\n"
+        );
+        // what `Display` writes reads back the same
+        for dialect in ["strict", "teaching", "permissive"] {
+            let options = Dialect::parse(dialect).unwrap().options();
+            let mut read = ParserOptions::default();
+            for line in options.to_string().lines() {
+                let (name, value) = line.split_once(" = ").unwrap();
+                assert!(read.set(name, value == "true"));
+            }
+            assert_eq!(read, options);
+        }
+        assert!(!ParserOptions::default().set("semicolons", true));
+        assert_eq!(Dialect::parse("loose"), None);
+    }
+}
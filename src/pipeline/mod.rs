@@ -0,0 +1,495 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::interpretor::{native_names, DEFAULT_MAX_CALL_DEPTH};
+use crate::{
+    eliminate_dead_code, fold_constants, lower, Coverage, Diagnostic, DiagnosticSet, Error,
+    ErrorInfo, FsModuleSource, Interpretor, Lexer, LogLevel, Object, Parser, ParserOptions,
+    Program, Resolver, RunState, Span, Stats, Suppressions, Warning,
+};
+
+/// Settings for `run_source`, `run_file` and `run_program`. The default
+/// parses strictly, runs with every capability and prints to stdout.
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    pub parser: ParserOptions,
+    /// Stops once the program has parsed and resolved, running nothing.
+    pub parse_only: bool,
+    /// Removes dead code before running, warning about each removal.
+    pub optimize: bool,
+    /// Keeps what the script prints for `RunOutcome::output` instead of
+    /// writing it to stdout.
+    pub capture_output: bool,
+    /// See `Interpretor::allow_clock`.
+    pub allow_clock: bool,
+    /// See `Interpretor::allow_eval`.
+    pub allow_eval: bool,
+    /// See `Interpretor::allow_log`.
+    pub allow_log: bool,
+    /// See `Interpretor::log_level`.
+    pub log_level: LogLevel,
+    /// See `Interpretor::max_heap_bytes`.
+    pub max_heap_bytes: Option<usize>,
+    /// See `Interpretor::max_call_depth`.
+    pub max_call_depth: Option<usize>,
+    /// See `Interpretor::timeout`. The entry function runs within the same
+    /// time as the top-level statements.
+    pub timeout: Option<Duration>,
+    /// See `Interpretor::check_contracts`.
+    pub contracts: bool,
+    /// Checks values against their type annotations, see
+    /// `Interpretor::strict_types` and `Resolver::with_strict_types`.
+    pub strict_types: bool,
+    /// See `Interpretor::set_deterministic`.
+    pub deterministic: bool,
+    /// Defines the functions of the prelude before the script runs, see
+    /// `Interpretor::no_prelude`.
+    pub prelude: bool,
+    /// The directory `import` finds modules under, see `FsModuleSource`.
+    /// The working directory when not given.
+    pub module_root: Option<PathBuf>,
+    /// Globals defined before the script runs.
+    pub globals: Vec<(String, Object)>,
+    /// Counts how often each line runs, for `RunOutcome::coverage`.
+    pub coverage: bool,
+    /// A function to call with these arguments once the top-level
+    /// statements have run, see `Interpretor::call_entry`.
+    pub entry: Option<(String, Vec<String>)>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            parser: ParserOptions::default(),
+            parse_only: false,
+            optimize: false,
+            capture_output: false,
+            allow_clock: true,
+            allow_eval: true,
+            allow_log: true,
+            log_level: LogLevel::default(),
+            max_heap_bytes: None,
+            max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH),
+            timeout: None,
+            contracts: true,
+            strict_types: false,
+            deterministic: false,
+            prelude: true,
+            module_root: None,
+            globals: Vec::new(),
+            coverage: false,
+            entry: None,
+        }
+    }
+}
+
+/// What a successful run left behind.
+#[derive(Debug)]
+pub struct RunOutcome {
+    /// What the script printed, with `capture_output`.
+    pub output: Option<String>,
+    /// The value of the last statement, when that is an expression
+    /// statement and the program ran.
+    pub value: Option<Object>,
+    /// What the entry function returned, when one was named and defined.
+    pub returned: Option<Object>,
+    pub stats: Stats,
+    pub warnings: Vec<Warning>,
+    /// How often each line ran, with `coverage`.
+    pub coverage: Option<Coverage>,
+}
+
+/// Parses, resolves and runs `source` in a fresh interpreter. On failure
+/// the diagnostics start with the error that stopped it, followed by any
+/// warnings collected on the way, in the order of `DiagnosticSet`. Warnings the source turns off with pragmas are
+/// left out, see `Suppressions`.
+pub fn run_source(source: &str, options: RunOptions) -> Result<RunOutcome, Vec<Diagnostic>> {
+    let mut parser = Parser::with_options(Lexer::new(source.to_string()), options.parser);
+    let program = parser.parse_program();
+    let warnings = parser.warnings().to_vec();
+    let suppressions = Suppressions::scan(source, parser.stmt_lines());
+    let result = match program {
+        Ok(program) => run_parsed(program, warnings, options),
+        Err(err) => Err(failure(warnings, err)),
+    };
+    match result {
+        Ok(mut outcome) => {
+            outcome.warnings = suppressions.apply(outcome.warnings, true);
+            Ok(outcome)
+        }
+        Err(diagnostics) => {
+            let mut err = None;
+            let mut warnings = Vec::new();
+            for diagnostic in diagnostics {
+                match diagnostic {
+                    Diagnostic::Error(error) => err = Some(error),
+                    Diagnostic::Warning(warning) => warnings.push(warning),
+                }
+            }
+            let err = err.expect("a failure has its error");
+            Err(failure(suppressions.apply(warnings, false), err))
+        }
+    }
+}
+
+/// Parses `source` without resolving or running it. The program comes back
+/// with the parser's warnings; on failure they follow the error, as for
+/// `run_source`. Warnings the source turns off with pragmas are left out.
+pub fn parse(
+    source: &str,
+    options: ParserOptions,
+) -> Result<(Program, Vec<Warning>), Vec<Diagnostic>> {
+    let mut parser = Parser::with_options(Lexer::new(source.to_string()), options);
+    let program = parser.parse_program();
+    // without resolving, a pragma may be there for a warning not given yet
+    let suppressions = Suppressions::scan(source, parser.stmt_lines());
+    let warnings = suppressions.apply(parser.warnings().to_vec(), false);
+    match program {
+        Ok(program) => Ok((program, warnings)),
+        Err(err) => Err(failure(warnings, err)),
+    }
+}
+
+/// Parses and resolves `source` like `run_source` without running it, and
+/// returns the warnings found.
+pub fn check(source: &str, options: RunOptions) -> Result<Vec<Warning>, Vec<Diagnostic>> {
+    let options = RunOptions {
+        parse_only: true,
+        ..options
+    };
+    run_source(source, options).map(|outcome| outcome.warnings)
+}
+
+/// `run_source` on the contents of the file at `path`. A file that cannot
+/// be read is a runtime error without a position.
+pub fn run_file(path: &Path, options: RunOptions) -> Result<RunOutcome, Vec<Diagnostic>> {
+    match fs::read_to_string(path) {
+        Ok(source) => run_source(&source, options),
+        Err(err) => {
+            let error = Error::Runtime(format!("cannot read {}: {err}", path.display()));
+            Err(vec![Diagnostic::Error(ErrorInfo::new_with_span(
+                error,
+                Span::default(),
+            ))])
+        }
+    }
+}
+
+/// `run_source` for a program already parsed, e.g. loaded from a cache.
+/// `options.parser` goes unused.
+pub fn run_program(program: Program, options: RunOptions) -> Result<RunOutcome, Vec<Diagnostic>> {
+    run_parsed(program, Vec::new(), options)
+}
+
+fn run_parsed(
+    mut program: Program,
+    mut warnings: Vec<Warning>,
+    options: RunOptions,
+) -> Result<RunOutcome, Vec<Diagnostic>> {
+    let mut resolver = if options.prelude {
+        Resolver::new()
+    } else {
+        Resolver::with_natives(native_names())
+    };
+    if options.strict_types {
+        resolver = resolver.with_strict_types();
+    }
+    let resolved = resolver.resolve(&program);
+    warnings.extend_from_slice(resolver.warnings());
+    if let Err(err) = resolved {
+        return Err(failure(warnings, err));
+    }
+    if options.parse_only {
+        return Ok(RunOutcome {
+            output: options.capture_output.then(String::new),
+            value: None,
+            returned: None,
+            stats: Stats::default(),
+            warnings,
+            coverage: None,
+        });
+    }
+
+    program = lower(program);
+    fold_constants(&mut program);
+    if options.optimize {
+        let (optimized, removed) = eliminate_dead_code(program);
+        warnings.extend(removed);
+        program = optimized;
+    }
+    let mut interpretor = if options.capture_output {
+        Interpretor::buffered()
+    } else {
+        Interpretor::new()
+    };
+    if !options.prelude {
+        interpretor = interpretor.no_prelude();
+    }
+    if let Some(root) = options.module_root {
+        interpretor = interpretor.with_modules(Box::new(FsModuleSource::new(root)));
+    }
+    interpretor.allow_clock = options.allow_clock;
+    interpretor.allow_eval = options.allow_eval;
+    interpretor.allow_log = options.allow_log;
+    interpretor.log_level = options.log_level;
+    interpretor.max_heap_bytes = options.max_heap_bytes;
+    interpretor.max_call_depth = options.max_call_depth;
+    interpretor.timeout = options.timeout;
+    interpretor.check_contracts = options.contracts;
+    interpretor.strict_types = options.strict_types;
+    interpretor.set_deterministic(options.deterministic);
+    for (name, value) in options.globals {
+        interpretor
+            .globals
+            .borrow_mut()
+            .define(name, value, false)
+            .expect("natives are not constants");
+    }
+    if options.coverage {
+        interpretor.enable_coverage();
+    }
+
+    let run = |interpretor: &mut Interpretor| {
+        // the last statement runs on its own so its value can be kept
+        let Some((last, rest)) = program.stmts.split_last() else {
+            return Ok(None);
+        };
+        if let RunState::Suspended(..) = interpretor.run_stmts(rest)? {
+            let error = Error::Runtime("the script suspended, which nothing resumes".to_string());
+            return Err(ErrorInfo::new_with_span(error, Span::default()));
+        }
+        interpretor.exec_value(last)
+    };
+    // the entry runs within the same time as the top-level statements
+    let ran = interpretor.timed(|interpretor| {
+        let value = run(interpretor)?;
+        let returned = match &options.entry {
+            Some((entry, args)) => interpretor.call_entry(entry, args)?,
+            None => None,
+        };
+        Ok((value, returned))
+    });
+    let (value, returned) = match ran {
+        Ok(ran) => ran,
+        Err(err) => return Err(failure(warnings, err)),
+    };
+    interpretor.measure_heap();
+    Ok(RunOutcome {
+        output: options.capture_output.then(|| interpretor.take_output()),
+        value,
+        returned,
+        stats: interpretor.stats(),
+        warnings,
+        coverage: options
+            .coverage
+            .then(|| interpretor.coverage_report(&program)),
+    })
+}
+
+fn failure(warnings: Vec<Warning>, err: ErrorInfo) -> Vec<Diagnostic> {
+    let mut diagnostics = DiagnosticSet::new();
+    diagnostics.push("", Diagnostic::Error(err));
+    for warning in warnings {
+        diagnostics.push("", Diagnostic::Warning(warning));
+    }
+    diagnostics.sort_dedup();
+    diagnostics.into_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::Path, time::Duration};
+
+    use super::{run_file, run_source, RunOptions};
+    use crate::{Diagnostic, Error, Object, ParserOptions};
+
+    fn captured() -> RunOptions {
+        RunOptions {
+            capture_output: true,
+            ..RunOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_run_source() {
+        let source = "fn double(x) { return x * 2; }\nprint double(2);\ndouble(3);";
+        let outcome = run_source(source, captured()).unwrap();
+        assert_eq!(outcome.output.as_deref(), Some("4\n"));
+        assert_eq!(outcome.value, Some(Object::Number(6.0)));
+        assert_eq!(outcome.returned, None);
+
+        // a last statement that is not an expression leaves no value
+        let outcome = run_source("let x = 1;", captured()).unwrap();
+        assert_eq!(outcome.value, None);
+        assert_eq!(run_source("", captured()).unwrap().value, None);
+
+        let source =
+            "class A { m() {} }\nlet a = A();\nlet i = 0;\nwhile (i < 3) { a.m(); i = i + 1; }";
+        let outcome = run_source(source, captured()).unwrap();
+        assert_eq!(
+            (outcome.stats.chain_walks, outcome.stats.cache_hits),
+            (1, 2)
+        );
+
+        let options = RunOptions {
+            globals: vec![("name".to_string(), Object::String("host".into()))],
+            entry: Some(("main".to_string(), vec!["a".to_string()])),
+            ..captured()
+        };
+        let source = "fn main(args) { print name; return args.len(); }";
+        let outcome = run_source(source, options).unwrap();
+        assert_eq!(outcome.output.as_deref(), Some("host\n"));
+        assert_eq!(outcome.returned, Some(Object::Number(1.0)));
+    }
+
+    #[test]
+    fn test_contracts_off() {
+        // a clause that would fail, or fail to evaluate, is not run at all
+        let source = "fn half(x) requires (x > 0) requires (missing()) ensures (result < 0) {\n\
+                      return x / 2;\n}\nprint half(-4);";
+        let diagnostics = run_source(source, captured()).unwrap_err();
+        assert!(diagnostics[0]
+            .to_string()
+            .contains("ContractError: requires clause of \"half\" failed: (> x 0)"));
+
+        let options = RunOptions {
+            contracts: false,
+            ..captured()
+        };
+        let outcome = run_source(source, options).unwrap();
+        assert_eq!(outcome.output.as_deref(), Some("-2\n"));
+        assert_eq!(outcome.stats.script_calls, 1);
+    }
+
+    #[test]
+    fn test_heap_limit() {
+        let source = "let sb = StringBuilder();\nwhile (true) { sb.append(\"xxxxxxxx\"); }";
+        let options = RunOptions {
+            max_heap_bytes: Some(100_000),
+            ..captured()
+        };
+        let diagnostics = run_source(source, options.clone()).unwrap_err();
+        assert!(diagnostics[0]
+            .to_string()
+            .contains("MemoryError: the script needs more than 100000 bytes, line 2"));
+
+        let outcome = run_source("let s = \"ab\" + \"cd\";", options).unwrap();
+        assert!(outcome.stats.heap_bytes > 0);
+        assert!(outcome.stats.peak_heap_bytes >= outcome.stats.heap_bytes);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let options = RunOptions {
+            timeout: Some(Duration::from_millis(50)),
+            deterministic: true,
+            entry: Some(("main".to_string(), vec![])),
+            ..captured()
+        };
+        let diagnostics = run_source("fn main() { sleep(30); }\nsleep(30);", options).unwrap_err();
+        // the entry shares the time of the top-level statements
+        assert!(diagnostics[0]
+            .to_string()
+            .contains("TimeoutError: ran for 50ms, past the limit of 50ms, line 1"));
+    }
+
+    #[test]
+    fn test_strict_types() {
+        let source = "fn twice(x: number): number { return x + x; }\nprint twice(\"ab\");";
+        let outcome = run_source(source, captured()).unwrap();
+        assert_eq!(outcome.output.as_deref(), Some("abab\n"));
+
+        let options = RunOptions {
+            strict_types: true,
+            ..captured()
+        };
+        let diagnostics = run_source(source, options.clone()).unwrap_err();
+        assert!(diagnostics[0]
+            .to_string()
+            .contains("TypeError: argument \"x\" of \"twice\" should be number, got string"));
+        // literals are caught before anything runs
+        let diagnostics = run_source("print 1;\nlet s: string = 2;", options).unwrap_err();
+        assert!(diagnostics[0].to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_parse_only() {
+        let options = RunOptions {
+            parse_only: true,
+            ..captured()
+        };
+        let outcome = run_source("print 1;\n1 + 1;", options).unwrap();
+        assert_eq!(outcome.output.as_deref(), Some(""));
+        assert_eq!(outcome.value, None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let diagnostics = run_source("print 1 +;", captured()).unwrap_err();
+        assert!(matches!(
+            &diagnostics[..],
+            [Diagnostic::Error(err)] if matches!(err.error, Error::Parse(_))
+        ));
+
+        // the error comes first, then the warnings met before it
+        let options = RunOptions {
+            parser: ParserOptions {
+                lenient_semicolons: true,
+                ..ParserOptions::default()
+            },
+            ..captured()
+        };
+        let diagnostics = run_source("print 1\nlet x = 2;\nprint missing;", options).unwrap_err();
+        let [Diagnostic::Error(err), Diagnostic::Warning(_)] = &diagnostics[..] else {
+            panic!("{diagnostics:?}");
+        };
+        assert!(matches!(err.error, Error::Name(_)));
+        assert_eq!(err.line(), 3);
+
+        let options = RunOptions {
+            prelude: false,
+            ..captured()
+        };
+        let diagnostics = run_source("print sum((1, 2));", options).unwrap_err();
+        assert!(diagnostics[0]
+            .to_string()
+            .contains("undefined variable \"sum\""));
+
+        let diagnostics = run_file(Path::new("no/such/file.lang"), captured()).unwrap_err();
+        assert!(diagnostics[0].is_error());
+        assert!(diagnostics[0].to_string().contains("cannot read"));
+    }
+
+    #[test]
+    fn test_suppressions() {
+        let options = RunOptions {
+            optimize: true,
+            ..captured()
+        };
+        let source = "fn f() {
+  return 1;
+  # lang-ignore: shadowing
+  print 2;
+}
+                      if (true) { print 1; } else { print 2; } # lang-ignore: constant-condition
+                      # lang-ignore: unreachable-code
+fn g() { return; print 3; }";
+        let outcome = run_source(source, options.clone()).unwrap();
+        let codes: Vec<_> = outcome
+            .warnings
+            .iter()
+            .map(|warning| (warning.code(), warning.line()))
+            .collect();
+        assert_eq!(codes, [("unreachable-code", 2), ("unused-suppression", 3)]);
+
+        // a failed run may not have reached what a pragma covers
+        let source = "# lang-ignore: unreachable-code
+fn g() { return; print 3; }
+print x;";
+        let diagnostics = run_source(source, options).unwrap_err();
+        assert!(matches!(&diagnostics[..], [Diagnostic::Error(_)]));
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Dialect, ErrorInfo, Lexer, Parser, ParserOptions, Program, Resolver, Suppressions, Warning,
+};
+
+/// The file a project runs when its manifest does not name another.
+pub const DEFAULT_MAIN: &str = "main.lang";
+
+/// A folder of scripts. It runs `main.lang`, or the file named by a
+/// `main = "path"` line in a `project.toml` at its root.
+///
+/// The manifest can also pin how the scripts parse: `dialect = "teaching"`
+/// for the options of a `Dialect`, and lines such as `range_for = false`
+/// for single settings of `ParserOptions`, over the dialect's.
+#[derive(Debug)]
+pub struct Project {
+    root: PathBuf,
+    main: PathBuf,
+    parser: ParserOptions,
+}
+
+/// The diagnostics for one file of a project, from `Project::check`.
+#[derive(Debug)]
+pub struct FileCheck {
+    /// Relative to the project root.
+    pub path: PathBuf,
+    pub source: String,
+    /// What parsed, with a `Stmt::Error` standing in for each statement that
+    /// did not, so the declarations around a mistake are still there.
+    pub program: Program,
+    /// The parse errors, then the resolve error that stopped the check, if
+    /// there was one.
+    pub errors: Vec<ErrorInfo>,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug)]
+pub enum ProjectError {
+    Io(PathBuf, io::Error),
+    /// A line of `project.toml` that is not `key = "value"`, or an unknown
+    /// key, with its line number.
+    Manifest(usize, String),
+    /// The entry file the project names does not exist.
+    MissingMain(PathBuf),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            ProjectError::Manifest(line, message) => {
+                write!(f, "project.toml, line {line}: {message}")
+            }
+            ProjectError::MissingMain(path) => {
+                write!(f, "no entry file: {} does not exist", path.display())
+            }
+        }
+    }
+}
+
+impl Project {
+    pub fn open(root: &Path) -> Result<Project, ProjectError> {
+        let manifest = root.join("project.toml");
+        let manifest = match fs::read_to_string(&manifest) {
+            Ok(text) => read_manifest(&text)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Manifest::default(),
+            Err(err) => return Err(ProjectError::Io(manifest, err)),
+        };
+        Ok(Project {
+            root: root.to_path_buf(),
+            main: PathBuf::from(manifest.main),
+            parser: manifest.parser,
+        })
+    }
+
+    /// How the manifest says the scripts parse, the default when it does
+    /// not say.
+    pub fn parser_options(&self) -> ParserOptions {
+        self.parser
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The entry file, relative to the root.
+    pub fn main(&self) -> &Path {
+        &self.main
+    }
+
+    /// The path of the entry file, if it exists.
+    pub fn entry(&self) -> Result<PathBuf, ProjectError> {
+        let entry = self.root.join(&self.main);
+        if !entry.is_file() {
+            return Err(ProjectError::MissingMain(self.main.clone()));
+        }
+        Ok(entry)
+    }
+
+    /// Every `.lang` file under the root, relative to it and sorted.
+    pub fn files(&self) -> Result<Vec<PathBuf>, ProjectError> {
+        let mut files = Vec::new();
+        collect(&self.root, Path::new(""), &mut files)?;
+        files.sort();
+        Ok(files)
+    }
+
+    /// Parses and resolves every file of the project, reachable from the
+    /// entry file or not, each on its own. A file is parsed past its syntax
+    /// errors, as `Parser::parse_partial` does, and what did parse is
+    /// resolved too.
+    pub fn check(&self, options: ParserOptions) -> Result<Vec<FileCheck>, ProjectError> {
+        let mut checks = Vec::new();
+        for path in self.files()? {
+            let full = self.root.join(&path);
+            let source = fs::read_to_string(&full).map_err(|err| ProjectError::Io(full, err))?;
+            let mut parser = Parser::with_options(Lexer::new(source.clone()), options);
+            let (program, mut errors) = parser.parse_partial();
+            let mut warnings = parser.warnings().to_vec();
+            let mut resolver = Resolver::new();
+            if let Err(err) = resolver.resolve(&program) {
+                errors.push(err);
+            }
+            warnings.extend_from_slice(resolver.warnings());
+            let suppressions = Suppressions::scan(&source, parser.stmt_lines());
+            let warnings = suppressions.apply(warnings, errors.is_empty());
+            checks.push(FileCheck {
+                path,
+                source,
+                program,
+                errors,
+                warnings,
+            });
+        }
+        Ok(checks)
+    }
+}
+
+#[derive(Debug)]
+struct Manifest {
+    main: String,
+    parser: ParserOptions,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            main: DEFAULT_MAIN.to_string(),
+            parser: ParserOptions::default(),
+        }
+    }
+}
+
+// One `key = value` per line, with blank lines and `#` comments around
+// them. `main` and `dialect` take quoted strings, the parser settings
+// `true` or `false`.
+fn read_manifest(text: &str) -> Result<Manifest, ProjectError> {
+    let mut main = DEFAULT_MAIN.to_string();
+    let mut dialect = None;
+    let mut settings = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: &str| ProjectError::Manifest(i + 1, message.to_string());
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected key = \"value\""))?;
+        let (key, value) = (key.trim(), value.trim());
+        if ParserOptions::default().set(key, false) {
+            let value = match value {
+                "true" => true,
+                "false" => false,
+                _ => return Err(error("expected true or false")),
+            };
+            settings.push((key, value));
+            continue;
+        }
+        let value = value
+            .trim()
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .ok_or_else(|| error("expected a quoted string"))?;
+        match key {
+            "main" => main = value.to_string(),
+            "dialect" => {
+                let parsed = Dialect::parse(value);
+                dialect =
+                    Some(parsed.ok_or_else(|| error("expected strict, teaching or permissive"))?);
+            }
+            key => return Err(error(&format!("unknown key \"{key}\""))),
+        }
+    }
+    let mut parser = dialect.map_or_else(ParserOptions::default, Dialect::options);
+    for (key, value) in settings {
+        parser.set(key, value);
+    }
+    Ok(Manifest { main, parser })
+}
+
+fn collect(root: &Path, dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), ProjectError> {
+    let full = root.join(dir);
+    let entries = fs::read_dir(&full).map_err(|err| ProjectError::Io(full.clone(), err))?;
+    for entry in entries {
+        let entry = entry.map_err(|err| ProjectError::Io(full.clone(), err))?;
+        let path = dir.join(entry.file_name());
+        if entry.path().is_dir() {
+            collect(root, &path, out)?;
+        } else if path.extension().is_some_and(|ext| ext == "lang") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_manifest, ProjectError};
+    use crate::{Dialect, ParserOptions};
+
+    #[test]
+    fn test_manifest() {
+        assert_eq!(read_manifest("").unwrap().main, "main.lang");
+        assert_eq!(
+            read_manifest("# entry\n\nmain = \"src/app.lang\"\n")
+                .unwrap()
+                .main,
+            "src/app.lang"
+        );
+        for (text, expected) in [
+            ("main", "project.toml, line 1: expected key = \"value\""),
+            (
+                "\nmain = app.lang",
+                "project.toml, line 2: expected a quoted string",
+            ),
+            ("name = \"x\"", "project.toml, line 1: unknown key \"name\""),
+            (
+                "range_for = \"yes\"",
+                "project.toml, line 1: expected true or false",
+            ),
+            (
+                "dialect = \"lax\"",
+                "project.toml, line 1: expected strict, teaching or permissive",
+            ),
+        ] {
+            let err = read_manifest(text).unwrap_err();
+            assert!(matches!(err, ProjectError::Manifest(..)));
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_pinned_options() {
+        assert_eq!(read_manifest("").unwrap().parser, ParserOptions::default());
+        // single settings win over the dialect, wherever they are
+        let manifest = read_manifest("range_for = false\ndialect = \"teaching\"").unwrap();
+        let expected = ParserOptions {
+            range_for: false,
+            ..Dialect::Teaching.options()
+        };
+        assert_eq!(manifest.parser, expected);
+        // what `Display` writes pins the same options
+        let manifest = read_manifest(&expected.to_string()).unwrap();
+        assert_eq!(manifest.parser, expected);
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+// The entries kept, dropping the oldest beyond it, so the file stays small.
+const MAX_ENTRIES: usize = 1000;
+
+/// The inputs of past sessions, oldest first, kept in a file between them.
+/// An input of several lines, such as a pasted function, is one entry.
+///
+/// The file has an entry per line, with a newline in an entry written as
+/// `\n` and a backslash as `\\`.
+#[derive(Debug, Default)]
+pub struct ReplHistory {
+    entries: Vec<String>,
+    // where `save` writes, or None to keep the history in memory
+    path: Option<PathBuf>,
+}
+
+impl ReplHistory {
+    /// A history kept only in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The history saved at `path`, which is empty if there is no file yet.
+    /// `save` writes it back there.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let entries = match fs::read_to_string(&path) {
+            Ok(text) => text.lines().map(unescape).collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            entries,
+            path: Some(path),
+        })
+    }
+
+    /// `$XDG_DATA_HOME/rlisp/history`, or `~/.local/share/rlisp/history`
+    /// without it. None if neither is set.
+    pub fn default_path() -> Option<PathBuf> {
+        let data = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => Path::new(&env::var_os("HOME")?).join(".local/share"),
+        };
+        Some(data.join("rlisp").join("history"))
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Adds `entry` unless it is blank or the same as the last entry, and
+    /// returns whether it was added.
+    pub fn append(&mut self, entry: &str) -> bool {
+        if entry.trim().is_empty() || self.entries.last().is_some_and(|last| last == entry) {
+            return false;
+        }
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+        true
+    }
+
+    /// Writes the history to the file it was loaded from, creating its
+    /// directory if needed. Does nothing for a history kept in memory.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .entries
+            .iter()
+            .map(|entry| escape(entry) + "\n")
+            .collect();
+        fs::write(path, text)
+    }
+}
+
+fn escape(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(line: &str) -> String {
+    let mut entry = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match (ch, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                chars.next();
+                entry.push('\n');
+            }
+            ('\\', Some('\\')) => {
+                chars.next();
+                entry.push('\\');
+            }
+            _ => entry.push(ch),
+        }
+    }
+    entry
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, path::PathBuf};
+
+    use super::{ReplHistory, MAX_ENTRIES};
+
+    // A file in a directory of the temp dir no other test or run uses.
+    fn temp_file(test: &str) -> PathBuf {
+        let dir = format!("rlisp-{test}-{}", std::process::id());
+        env::temp_dir().join(dir).join("history")
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = temp_file("save_and_load");
+        let mut history = ReplHistory::load(&path).unwrap();
+        assert!(history.entries().is_empty());
+        assert!(history.append("let a = 1;"));
+        assert!(!history.append("let a = 1;"), "repeats are kept once");
+        assert!(!history.append("  "));
+        assert!(history.append("fn f() {\n  return \"a\\\\b\";\n}"));
+        assert!(
+            history.append("let a = 1;"),
+            "only repeats in a row are dropped"
+        );
+        history.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "let a = 1;\nfn f() {\\n  return \"a\\\\\\\\b\";\\n}\nlet a = 1;\n"
+        );
+
+        let loaded = ReplHistory::load(&path).unwrap();
+        assert_eq!(loaded.entries(), history.entries());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_limit() {
+        let mut history = ReplHistory::new();
+        for i in 0..MAX_ENTRIES + 5 {
+            history.append(&i.to_string());
+        }
+        assert_eq!(history.entries().len(), MAX_ENTRIES);
+        assert_eq!(history.entries()[0], "5");
+        // nothing to write to
+        history.save().unwrap();
+    }
+}
@@ -0,0 +1,681 @@
+use std::{collections::HashSet, fs, io};
+
+use crate::parser::terminated;
+use crate::{
+    fold_constants, lower, object::format, Completion, Error, ErrorInfo, Interpretor, Lexer,
+    Object, Parser, Program, Resolver, Stmt, TokenType,
+};
+
+mod history;
+pub use history::ReplHistory;
+
+mod terminal;
+pub use terminal::{Terminal, CONTINUATION_PROMPT};
+
+const HELP: &str = "\
+:load <path>       run a file in this session
+:save <path>       write everything entered so far to a file
+:ast <input>       print the syntax tree of <input> without running it
+:time <input>      run <input> and report how long it took
+:env               list global variables
+:complete <input>  list the names that could end <input>
+:clear             forget every global defined in this session
+:help              show this message";
+
+/// File access used by `:load` and `:save`, so tests can swap the disk out.
+pub trait Host {
+    fn read_file(&mut self, path: &str) -> io::Result<String>;
+    fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()>;
+}
+
+pub struct Disk;
+
+impl Host for Disk {
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+}
+
+/// An interactive session. Each line is either script code, run against the
+/// globals left by earlier lines, or a command: a line whose first column is
+/// `:`. Colons anywhere else belong to the code.
+///
+/// Assigning to a name no line has declared is an error, as in scripts,
+/// unless `set_lenient(true)` was called: then the assignment declares a
+/// global, which saves typing `let` at the prompt.
+///
+/// Each line of code that runs without an error takes the number `prompt`
+/// shows. When it ends in an expression whose value is shown, the value is
+/// bound to `_` and to `_N` for its number N, so `_2 + _3` reuses earlier
+/// results. They are plain globals, which `:save` writes as `let`s on the
+/// lines that gave them when a later line reads them, so the saved file
+/// runs on its own.
+pub struct Repl {
+    interpretor: Interpretor,
+    host: Box<dyn Host>,
+    // every input that ran, for `:save`
+    session: Vec<Entry>,
+    // the number of the next line of code
+    number: usize,
+}
+
+// What running one input did.
+struct Ran {
+    output: String,
+    // whether it ran to the end without an error
+    completed: bool,
+    // the value of a trailing expression, if it was shown
+    shown: Option<Object>,
+    // the char where the trailing expression starts, if there is one
+    tail: Option<usize>,
+}
+
+// An input that ran, as `:save` writes it.
+struct Entry {
+    source: String,
+    // the number its shown value took, and the char where the expression
+    // that gave it starts
+    shown: Option<(usize, usize)>,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self::with_host(Box::new(Disk))
+    }
+
+    pub fn with_host(host: Box<dyn Host>) -> Self {
+        Self {
+            interpretor: Interpretor::buffered(),
+            host,
+            session: Vec::new(),
+            number: 1,
+        }
+    }
+
+    pub fn interpretor(&mut self) -> &mut Interpretor {
+        &mut self.interpretor
+    }
+
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.interpretor.implicit_globals = lenient;
+    }
+
+    /// The prompt for the next line, e.g. `[3]> `.
+    pub fn prompt(&self) -> String {
+        format!("[{}]> ", self.number)
+    }
+
+    /// The names that could go at byte `cursor` of `line`, for a line
+    /// editor to offer on tab. See `Interpretor::complete`.
+    pub fn complete(&self, line: &str, cursor: usize) -> Vec<Completion> {
+        self.interpretor.complete(line, cursor)
+    }
+
+    /// Whether `input` is whole rather than the first lines of something
+    /// longer: it leaves no bracket or string open. Anything else wrong
+    /// with it is left for running it to report.
+    pub fn is_complete(input: &str) -> bool {
+        let mut lexer = Lexer::new(input.to_string());
+        let mut depth = 0;
+        loop {
+            match lexer.next_token().token {
+                TokenType::LParen | TokenType::LBrace | TokenType::LCurly => depth += 1,
+                TokenType::RParen | TokenType::RBrace | TokenType::RCurly => depth -= 1,
+                TokenType::Eof => break,
+                _ => {}
+            }
+        }
+        let unterminated = Error::Lex("unterminated string".to_string());
+        depth <= 0 && !lexer.errors().iter().any(|err| err.error == unterminated)
+    }
+
+    /// Handles one line of input and returns the text to show for it.
+    pub fn eval_line(&mut self, line: &str) -> String {
+        let Some(command) = line.strip_prefix(':') else {
+            if line.trim().is_empty() {
+                return String::new();
+            }
+            // input that does not parse or resolve never ran, so `:save`
+            // leaves it out, and what it keeps is what ran, `;` and all
+            let source = terminated(line);
+            return match self.run(&source) {
+                Ok(ran) => {
+                    let shown = ran.shown.as_ref().and(ran.tail);
+                    self.record(&source, shown.map(|start| (self.number, start)));
+                    if ran.completed {
+                        self.remember(ran.shown);
+                    }
+                    ran.output
+                }
+                Err(message) => message,
+            };
+        };
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+        match name {
+            // what a file or `:time` runs is saved like a line typed in, so
+            // the saved session does what this one did
+            "load" => match self.host.read_file(argument) {
+                Ok(source) => {
+                    let source = terminated(&source);
+                    match self.run(&source) {
+                        Ok(ran) => {
+                            self.record(&source, None);
+                            ran.output
+                        }
+                        Err(message) => message,
+                    }
+                }
+                Err(err) => format!("cannot read {argument}: {err}\n"),
+            },
+            "save" => match self.host.write_file(argument, &self.saved()) {
+                Ok(()) => format!("saved to {argument}\n"),
+                Err(err) => format!("cannot write {argument}: {err}\n"),
+            },
+            "ast" => match parse(argument) {
+                Ok(program) => format!("{program}\n"),
+                Err(message) => message,
+            },
+            "time" => {
+                let source = terminated(argument);
+                let start = self.interpretor.now();
+                let mut output = match self.run(&source) {
+                    Ok(ran) => {
+                        self.record(&source, None);
+                        ran.output
+                    }
+                    Err(message) => message,
+                };
+                let elapsed = self.interpretor.now().saturating_sub(start);
+                output.push_str(&format!("elapsed: {}ms\n", elapsed.as_secs_f64() * 1000.0));
+                output
+            }
+            "env" => {
+                let mut output = String::new();
+                let builtins = self.interpretor.native_names();
+                for (name, value) in self.interpretor.globals.borrow().bindings() {
+                    if !builtins.contains(&name) {
+                        output.push_str(&format!("{name} = {value}\n"));
+                    }
+                }
+                output
+            }
+            "complete" => self
+                .complete(argument, argument.len())
+                .iter()
+                .map(|completion| format!("{} ({})\n", completion.text, completion.kind))
+                .collect(),
+            "clear" => {
+                self.interpretor.clear();
+                self.session.clear();
+                self.number = 1;
+                String::new()
+            }
+            "help" => format!("{HELP}\n"),
+            _ => format!("unknown command :{name}, try :help\n"),
+        }
+    }
+
+    // Adds code that ran to what `:save` writes.
+    fn record(&mut self, source: &str, shown: Option<(usize, usize)>) {
+        self.session.push(Entry {
+            source: source.to_string(),
+            shown,
+        });
+    }
+
+    // What `:save` writes: every input that ran, one after another. An
+    // input whose value is read through `_` or `_N` binds those itself,
+    // as `remember` did.
+    fn saved(&self) -> String {
+        let used: HashSet<String> = self
+            .session
+            .iter()
+            .flat_map(|entry| identifiers(&entry.source))
+            .collect();
+        let mut saved = String::new();
+        for entry in &self.session {
+            match entry.shown {
+                Some((number, start)) => saved.push_str(&bound(entry, number, start, &used)),
+                None => saved.push_str(&entry.source),
+            }
+            saved.push('\n');
+        }
+        saved
+    }
+
+    // Gives the line that just ran its number, binding `_` and `_N` to the
+    // value it showed, if any.
+    fn remember(&mut self, shown: Option<Object>) {
+        let number = self.number;
+        self.number += 1;
+        let Some(value) = shown else {
+            return;
+        };
+        let mut globals = self.interpretor.globals.borrow_mut();
+        for name in ["_".to_string(), format!("_{number}")] {
+            // a constant of the same name keeps its value
+            let _ = globals.define(name, value.clone(), false);
+        }
+    }
+
+    // Runs `source`, echoing the value of a trailing expression statement.
+    // Fails without running anything if `source` does not parse or resolve
+    // against the session's globals.
+    fn run(&mut self, source: &str) -> Result<Ran, String> {
+        let mut program = parse(source)?;
+        let tail = match program.stmts.last() {
+            Some(stmt @ Stmt::Expr { .. }) => program.stmt_span(stmt.id()).map(|span| span.start),
+            _ => None,
+        };
+        let mut resolver = Resolver::with_natives(self.interpretor.native_names())
+            .with_existing_globals(self.interpretor.global_names());
+        if self.interpretor.implicit_globals {
+            resolver = resolver.with_implicit_globals();
+        }
+        resolver.resolve(&program).map_err(|err| describe(&err))?;
+        let mut output: String = resolver
+            .warnings()
+            .iter()
+            .map(|warning| format!("{warning}\n"))
+            .collect();
+        program = lower(program);
+        fold_constants(&mut program);
+
+        let last = match program.stmts.last() {
+            Some(Stmt::Expr { .. }) => program.stmts.pop(),
+            _ => None,
+        };
+        // the REPL cannot wait on the host, so a suspended line just stops
+        let mut result = self.interpretor.run(&program).map(|_| None);
+        if let (Ok(None), false, Some(Stmt::Expr { expr, .. })) =
+            (&result, self.interpretor.is_suspended(), &last)
+        {
+            result = self.interpretor.eval(expr).map(|value| {
+                if value.is_nil() {
+                    return None;
+                }
+                let shown = format::limited(&value, self.interpretor.print_limits);
+                self.interpretor.write_output(&shown);
+                Some(value)
+            });
+        }
+        output.push_str(&self.interpretor.take_output());
+        let (completed, shown) = match result {
+            Ok(shown) => (true, shown),
+            Err(err) => {
+                output.push_str(&describe(&err));
+                (false, None)
+            }
+        };
+        Ok(Ran {
+            output,
+            completed,
+            shown,
+            tail,
+        })
+    }
+}
+
+// An error with its hint, if it has one, on the next line.
+fn describe(err: &ErrorInfo) -> String {
+    match err.hint() {
+        Some(hint) => format!("{err}\nhint: {hint}\n"),
+        None => format!("{err}\n"),
+    }
+}
+
+// The names `source` mentions.
+fn identifiers(source: &str) -> Vec<String> {
+    let mut lexer = Lexer::new(source.to_string());
+    let mut names = Vec::new();
+    loop {
+        match lexer.next_token().token {
+            TokenType::Identifier(name) => names.push(name),
+            TokenType::Eof => return names,
+            _ => {}
+        }
+    }
+}
+
+// The input of `entry` with the value of its trailing expression, which
+// starts at char `start`, bound to those of `_N` and `_` that `used`
+// holds.
+fn bound(entry: &Entry, number: usize, start: usize, used: &HashSet<String>) -> String {
+    let names: Vec<String> = [format!("_{number}"), "_".to_string()]
+        .into_iter()
+        .filter(|name| used.contains(name))
+        .collect();
+    let Some(first) = names.first() else {
+        return entry.source.clone();
+    };
+    let head: String = entry.source.chars().take(start).collect();
+    let tail: String = entry.source.chars().skip(start).collect();
+    let expr = tail.strip_suffix(';').unwrap_or(&tail).trim_end();
+    let mut bound = format!("{head}let {first} = {expr};");
+    for name in &names[1..] {
+        bound.push_str(&format!(" let {name} = {first};"));
+    }
+    bound
+}
+
+// A line without a closing `;` or `}` gets one, so `1 + 2` works as input.
+fn parse(source: &str) -> Result<Program, String> {
+    Parser::new(Lexer::new(terminated(source)))
+        .parse_program()
+        .map_err(|err| format!("{err}\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, cell::RefCell, collections::HashMap, io, rc::Rc, time::Duration};
+
+    use super::{Host, Repl};
+    use crate::Clock;
+
+    #[derive(Clone, Default)]
+    struct MemoryHost(Rc<RefCell<HashMap<String, String>>>);
+
+    impl Host for MemoryHost {
+        fn read_file(&mut self, path: &str) -> io::Result<String> {
+            self.0
+                .borrow()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+        }
+
+        fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+            self.0
+                .borrow_mut()
+                .insert(path.to_string(), contents.to_string());
+            Ok(())
+        }
+    }
+
+    struct FakeClock(Cell<Duration>);
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            let now = self.0.get() + Duration::from_millis(2);
+            self.0.set(now);
+            now
+        }
+    }
+
+    fn repl() -> (Repl, MemoryHost) {
+        let host = MemoryHost::default();
+        (Repl::with_host(Box::new(host.clone())), host)
+    }
+
+    #[test]
+    fn test_code() {
+        let (mut repl, _) = repl();
+        assert_eq!(repl.eval_line("let a = 1;"), "");
+        assert_eq!(repl.eval_line("a + 2"), "3\n");
+        assert_eq!(repl.eval_line("print a;"), "1\n");
+        assert_eq!(
+            repl.eval_line("nope"),
+            "ResolveError: undefined variable \"nope\", line 1, pos 0\n"
+        );
+        assert_eq!(
+            repl.eval_line("fn g() { return nope; } g()"),
+            "NameError: undefined variable \"nope\", line 1, pos 16\n"
+        );
+        assert_eq!(repl.eval_line("fn f(x) { return x; } f(x: 5)"), "5\n");
+        assert_eq!(
+            repl.eval_line("print b + 1;"),
+            "ResolveError: undefined variable \"b\", line 1, pos 6\n"
+        );
+        assert_eq!(
+            repl.eval_line("let count = 0; fn h() { return cout; } h()"),
+            "NameError: undefined variable \"cout\", line 1, pos 31\nhint: did you mean 'count'?\n"
+        );
+    }
+
+    #[test]
+    fn test_history() {
+        let (mut repl, host) = repl();
+        assert_eq!(repl.prompt(), "[1]> ");
+        assert_eq!(repl.eval_line("1 + 1"), "2\n");
+        assert_eq!(repl.eval_line("let a = 10;"), "");
+        assert_eq!(repl.prompt(), "[3]> ");
+        // errors take no number and leave `_` alone
+        assert_eq!(
+            repl.eval_line("nope"),
+            "ResolveError: undefined variable \"nope\", line 1, pos 0\n"
+        );
+        assert_eq!(
+            repl.eval_line("1 / 0"),
+            "ZeroDivisionError: division by zero, line 1, pos 2\n"
+        );
+        assert_eq!(repl.eval_line(":env"), "_ = 2\n_1 = 2\na = 10\n");
+        assert_eq!(repl.prompt(), "[3]> ");
+        assert_eq!(repl.eval_line("a * 3"), "30\n");
+        assert_eq!(repl.eval_line("print _;"), "30\n");
+        assert_eq!(repl.eval_line("_1 + _3"), "32\n");
+        // statements and nil results take a number but bind nothing
+        assert_eq!(repl.eval_line("nil"), "");
+        assert_eq!(repl.eval_line("_"), "32\n");
+        assert_eq!(repl.eval_line("_7"), "32\n");
+        assert_eq!(
+            repl.eval_line("_4"),
+            "ResolveError: undefined variable \"_4\", line 1, pos 0\n"
+        );
+        assert_eq!(repl.prompt(), "[9]> ");
+
+        // taking a binding over is no shadowing, and the saved lines that
+        // gave the values bind them for those that read them
+        assert_eq!(repl.eval_line("let _ = \"mine\";"), "");
+        repl.eval_line(":save out.lang");
+        let saved = "let _1 = 1 + 1; let _ = _1;\nlet a = 10;\n1 / 0;\n\
+            let _3 = a * 3; let _ = _3;\nprint _;\nlet _ = _1 + _3;\nnil;\n\
+            let _7 = _; let _ = _7;\nlet _ = _7;\nlet _ = \"mine\";\n";
+        assert_eq!(host.0.borrow()["out.lang"], saved);
+
+        repl.eval_line(":clear");
+        assert_eq!(repl.prompt(), "[1]> ");
+    }
+
+    #[test]
+    fn test_save_history() {
+        let (mut repl, host) = repl();
+        repl.eval_line("2 * 3");
+        repl.eval_line("let b = 1; b + 1");
+        assert_eq!(repl.eval_line("_1 + _2"), "8\n");
+        repl.eval_line("print _;");
+        repl.eval_line(":save out.lang");
+        let saved = "let _1 = 2 * 3; let _ = _1;\nlet b = 1; let _2 = b + 1; let _ = _2;\n\
+            let _ = _1 + _2;\nprint _;\n";
+        assert_eq!(host.0.borrow()["out.lang"], saved);
+
+        // the file runs without the history that made it
+        let mut replay = Repl::with_host(Box::new(host.clone()));
+        assert_eq!(replay.eval_line(":load out.lang"), "8\n");
+        assert_eq!(replay.eval_line("_2"), "2\n");
+    }
+
+    #[test]
+    fn test_load() {
+        let (mut repl, host) = repl();
+        let script = "fn double(x) { return x * 2; }\nprint \"loaded\";";
+        host.0
+            .borrow_mut()
+            .insert("lib.lang".to_string(), script.to_string());
+        assert_eq!(repl.eval_line(":load lib.lang"), "loaded\n");
+        assert_eq!(repl.eval_line("double(4)"), "8\n");
+        assert!(repl
+            .eval_line(":load missing.lang")
+            .starts_with("cannot read missing.lang"));
+
+        // the saved session defines what the file did
+        repl.eval_line(":time let t = double(1);");
+        repl.eval_line(":save out.lang");
+        let saved = format!("{script}\ndouble(4);\nlet t = double(1);\n");
+        assert_eq!(host.0.borrow()["out.lang"], saved);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        // lines that left out their `;` are saved with it, so the file
+        // parses and does what the session did
+        let (mut repl, host) = repl();
+        repl.eval_line("1 + 2");
+        repl.eval_line("let a = 5");
+        repl.eval_line("fn twice(x) { return x * 2; }");
+        repl.eval_line("print twice(a)");
+        assert_eq!(repl.eval_line(":save out.lang"), "saved to out.lang\n");
+
+        let mut replay = Repl::with_host(Box::new(host.clone()));
+        assert_eq!(replay.eval_line(":load out.lang"), "10\n");
+        replay.eval_line(":save again.lang");
+        assert_eq!(host.0.borrow()["again.lang"], host.0.borrow()["out.lang"]);
+        assert_eq!(replay.eval_line("a"), "5\n");
+    }
+
+    #[test]
+    fn test_save() {
+        let (mut repl, host) = repl();
+        repl.eval_line("let a = 1;");
+        repl.eval_line(":env");
+        repl.eval_line("print a;");
+        assert_eq!(repl.eval_line(":save out.lang"), "saved to out.lang\n");
+        assert_eq!(host.0.borrow()["out.lang"], "let a = 1;\nprint a;\n");
+    }
+
+    #[test]
+    fn test_session() {
+        let (mut repl, host) = repl();
+        assert_eq!(
+            repl.eval_line("fn greet(name) { return \"hi \" + name; }"),
+            ""
+        );
+        assert_eq!(repl.eval_line("greet(\"ann\")"), "hi ann\n");
+        assert_eq!(
+            repl.eval_line("fn greet(name) { return \"bye \" + name; }"),
+            ""
+        );
+        assert_eq!(repl.eval_line("greet(\"ann\")"), "bye ann\n");
+        assert_eq!(
+            repl.eval_line("let a = greet(missing);"),
+            "ResolveError: undefined variable \"missing\", line 1, pos 14\n"
+        );
+        assert_eq!(
+            repl.eval_line("a"),
+            "ResolveError: undefined variable \"a\", line 1, pos 0\n"
+        );
+        repl.eval_line(":save session.lang");
+        assert_eq!(
+            host.0.borrow()["session.lang"].lines().count(),
+            4,
+            "rejected input is not saved"
+        );
+    }
+
+    #[test]
+    fn test_ast() {
+        let (mut repl, _) = repl();
+        assert_eq!(repl.eval_line(":ast 1 + 2 * x"), "((+ 1 (* 2 x)))\n");
+        assert_eq!(repl.eval_line(":ast print x;"), "((print x))\n");
+        assert_eq!(
+            repl.eval_line("x"),
+            "ResolveError: undefined variable \"x\", line 1, pos 0\n"
+        );
+    }
+
+    #[test]
+    fn test_time() {
+        let (mut repl, _) = repl();
+        repl.interpretor()
+            .set_clock(FakeClock(Cell::new(Duration::ZERO)));
+        assert_eq!(repl.eval_line(":time 1 + 1"), "2\nelapsed: 2ms\n");
+    }
+
+    #[test]
+    fn test_env_and_clear() {
+        let (mut repl, _) = repl();
+        repl.eval_line("let b = \"two\"; let a = 1;");
+        assert_eq!(repl.eval_line(":env"), "a = 1\nb = two\n");
+        assert_eq!(
+            repl.eval_line("let trace = 3;"),
+            "warning: this shadows the built-in 'trace', line 1, pos 4\n"
+        );
+        assert_eq!(repl.eval_line("let trace = 4;"), "");
+        assert_eq!(repl.eval_line(":clear"), "");
+        assert_eq!(repl.eval_line(":env"), "");
+        assert_eq!(repl.eval_line("to_fixed(1, 1)"), "1.0\n");
+    }
+
+    #[test]
+    fn test_complete() {
+        let (mut repl, _) = repl();
+        repl.eval_line("class Point { init(x) { this.x = x; } norm() {} }");
+        repl.eval_line("let point = Point(1);");
+        assert_eq!(repl.eval_line(":complete poi"), "point (variable)\n");
+        assert_eq!(
+            repl.eval_line(":complete print point."),
+            "init (method)\nnorm (method)\nx (field)\n"
+        );
+        assert_eq!(repl.eval_line(":complete \"poi"), "");
+        let completions = repl.complete("Poi(1)", 2);
+        assert_eq!(completions[0].text, "Point");
+        assert_eq!(completions[0].replace, 0..3);
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(Repl::is_complete("print 1;"));
+        assert!(!Repl::is_complete("fn f() {"));
+        assert!(!Repl::is_complete("f(1, [2"));
+        assert!(!Repl::is_complete("print \"open"));
+        assert!(!Repl::is_complete("print \"a (\" + (1"));
+        assert!(Repl::is_complete("print \"a (\";"));
+        // too many closers is an error to report, not more to wait for
+        assert!(Repl::is_complete("f())"));
+    }
+
+    #[test]
+    fn test_help_and_unknown() {
+        let (mut repl, _) = repl();
+        assert!(repl.eval_line(":help").contains(":load <path>"));
+        assert_eq!(
+            repl.eval_line(":nope"),
+            "unknown command :nope, try :help\n"
+        );
+        assert_eq!(
+            repl.eval_line(" :help"),
+            "ParseError: Expect expression found \":\", line 1, pos 1\n"
+        );
+    }
+
+    #[test]
+    fn test_lenient() {
+        let (mut repl, _) = repl();
+        assert_eq!(
+            repl.eval_line("total = 1"),
+            "ResolveError: undefined variable \"total\", line 1, pos 0\n"
+        );
+        repl.set_lenient(true);
+        assert_eq!(repl.eval_line("total = 1; total = total + 1"), "2\n");
+        assert_eq!(repl.eval_line(":env"), "_ = 2\n_1 = 2\ntotal = 2\n");
+        // a function body is only checked when it runs
+        repl.eval_line("fn bump() { count = 10; }");
+        assert_eq!(repl.eval_line("bump();"), "");
+        assert_eq!(repl.eval_line("count"), "10\n");
+        assert_eq!(
+            repl.eval_line("print missing;"),
+            "ResolveError: undefined variable \"missing\", line 1, pos 6\n"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::io::{self, BufRead, Write};
+
+use super::{Repl, ReplHistory};
+
+// What a terminal in bracketed-paste mode puts around pasted text.
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+// Turn bracketed-paste mode on and off.
+const ENABLE_PASTE: &str = "\x1b[?2004h";
+const DISABLE_PASTE: &str = "\x1b[?2004l";
+
+/// The prompt for the lines after the first of an input left open.
+pub const CONTINUATION_PROMPT: &str = "...> ";
+
+/// Runs a `Repl` on lines read from a terminal, or anything standing in for
+/// one, writing the prompts and results back.
+///
+/// An input that leaves a bracket or string open goes on over the next
+/// lines, and runs once it is whole. Text pasted into a terminal in
+/// bracketed-paste mode is taken in whole before that check, so a pasted
+/// program runs once rather than line by line.
+pub struct Terminal<R, W> {
+    input: R,
+    output: W,
+    bracketed_paste: bool,
+}
+
+impl<R: BufRead, W: Write> Terminal<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            bracketed_paste: false,
+        }
+    }
+
+    /// Asks the terminal to mark pasted text while `run` runs. Only worth
+    /// it for a real terminal; marked text is taken in whole either way.
+    pub fn set_bracketed_paste(&mut self, on: bool) {
+        self.bracketed_paste = on;
+    }
+
+    /// Runs each input until the input ends, adding it to `history` and
+    /// saving that as it goes.
+    pub fn run(&mut self, repl: &mut Repl, history: &mut ReplHistory) -> io::Result<()> {
+        if self.bracketed_paste {
+            write!(self.output, "{ENABLE_PASTE}")?;
+        }
+        let mut saved = true;
+        while let Some(input) = self.read_input(repl)? {
+            write!(self.output, "{}", repl.eval_line(&input))?;
+            if history.append(&input) {
+                // a history that cannot be saved is only worth one mention
+                if let (Err(err), true) = (history.save(), saved) {
+                    writeln!(self.output, "cannot save history: {err}")?;
+                    saved = false;
+                }
+            }
+        }
+        if self.bracketed_paste {
+            write!(self.output, "{DISABLE_PASTE}")?;
+        }
+        self.output.flush()
+    }
+
+    // Reads lines until they make a whole input, or None at the end of the
+    // input with nothing read.
+    fn read_input(&mut self, repl: &Repl) -> io::Result<Option<String>> {
+        write!(self.output, "{}", repl.prompt())?;
+        let mut input = String::new();
+        let mut pasting = false;
+        loop {
+            self.output.flush()?;
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok((!input.is_empty()).then_some(input));
+            }
+            let mut line = line.trim_end_matches(['\n', '\r']);
+            if let Some((before, pasted)) = line.split_once(PASTE_START) {
+                input.push_str(before);
+                line = pasted;
+                pasting = true;
+            }
+            if pasting {
+                let Some((pasted, after)) = line.split_once(PASTE_END) else {
+                    input.push_str(line);
+                    input.push('\n');
+                    continue;
+                };
+                input.push_str(pasted);
+                input.push_str(after);
+                pasting = false;
+            } else {
+                input.push_str(line);
+            }
+            if input.starts_with(':') || Repl::is_complete(&input) {
+                return Ok(Some(input));
+            }
+            input.push('\n');
+            write!(self.output, "{CONTINUATION_PROMPT}")?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Terminal, CONTINUATION_PROMPT};
+    use crate::{Repl, ReplHistory};
+
+    // Feeds `typed` to a REPL as a terminal would and returns all it shows,
+    // prompts included, along with the history.
+    fn session(typed: &str) -> (String, ReplHistory) {
+        let mut repl = Repl::new();
+        let mut history = ReplHistory::new();
+        let mut output = Vec::new();
+        let mut terminal = Terminal::new(typed.as_bytes(), &mut output);
+        terminal.run(&mut repl, &mut history).unwrap();
+        let shown = String::from_utf8(output).unwrap();
+        (shown, history)
+    }
+
+    #[test]
+    fn test_open_brackets_continue() {
+        let (shown, history) = session("fn f(x) {\n  return x * 2;\n}\nf(\n21)\n");
+        assert_eq!(
+            shown,
+            format!("[1]> {CONTINUATION_PROMPT}{CONTINUATION_PROMPT}[2]> {CONTINUATION_PROMPT}42\n[3]> ")
+        );
+        assert_eq!(
+            history.entries(),
+            ["fn f(x) {\n  return x * 2;\n}", "f(\n21)"]
+        );
+    }
+
+    #[test]
+    fn test_bracketed_paste() {
+        // the first pasted line is whole on its own, but nothing runs until
+        // the paste ends
+        let pasted = "print 1;\nfn g() {\n  return 2;\n}\nprint g();";
+        let typed = format!("\x1b[200~{pasted}\x1b[201~\n:help\n");
+        let (shown, history) = session(&typed);
+        assert!(shown.starts_with("[1]> 1\n2\n[2]> "), "{shown:?}");
+        assert_eq!(history.entries(), [pasted, ":help"]);
+
+        // a paste that leaves a bracket open is continued by typing
+        let typed = "\x1b[200~print (1 +\x1b[201~\n2);\n";
+        let (shown, history) = session(typed);
+        assert_eq!(shown, format!("[1]> {CONTINUATION_PROMPT}3\n[2]> "));
+        assert_eq!(history.entries(), ["print (1 +\n2);"]);
+    }
+
+    #[test]
+    fn test_paste_mode_switched() {
+        let mut output = Vec::new();
+        let mut terminal = Terminal::new("".as_bytes(), &mut output);
+        terminal.set_bracketed_paste(true);
+        terminal
+            .run(&mut Repl::new(), &mut ReplHistory::new())
+            .unwrap();
+        assert_eq!(output, b"\x1b[?2004h[1]> \x1b[?2004l");
+    }
+}
@@ -0,0 +1,1581 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{FieldInit, ImportForm, MatchArm, NamedArg, NodeId, Program, Signature};
+use crate::interpretor::{native_names, prelude_names};
+use crate::{
+    visitor, Error, ErrorInfo, Expr, LiteralType, Object, Span, Stmt, TokenInfo, TokenType, Warning,
+};
+
+/// Static checks run between parsing and interpreting.
+///
+/// Inside a block or function body a name may not be used before the `let`,
+/// `fn` or `class` that declares it in that same scope, even when an outer
+/// scope has a binding of the same name. Function and method bodies are
+/// exempt: they run later, so two functions or classes declared in one block
+/// may refer to each other, e.g. `class A { make() { return B(); } }` ahead
+/// of `class B {}`. Statements outside any body still run in order, so
+/// `print B();` ahead of `class B {}` in a block is rejected.
+/// Globals keep the laxer dynamic rules: any global name is looked up when the
+/// code using it runs, and one that is still undefined then, such as a typo
+/// inside a method, is a NameError at that point.
+///
+/// A global `let`, `fn` or `class` named after a native function is allowed
+/// but collected as a warning, since every later use of the name then gets the
+/// script's definition. Local declarations never warn.
+///
+/// Inside a loop, growing a string in place with `s = s + ... "text" ...` or
+/// `s += ...` is collected as a warning pointing at `StringBuilder()`, since
+/// each step copies the whole string. Only additions that involve a string
+/// literal are flagged, so a number being summed is never mistaken for one.
+///
+/// A resolver built `with_existing_globals` also rejects top-level code that
+/// uses a name which is neither one of those globals nor declared by the
+/// program. This is for hosts such as the REPL that run many programs against
+/// one set of globals. Adding `with_implicit_globals` lets top-level code
+/// assign to such a name, which declares it, as a lenient REPL does.
+///
+/// Inside a class, `this.name` and `super.name` are rejected when `name` is
+/// private to a superclass the resolver can see declared by name. Anything
+/// it cannot tell statically is left to the interpreter.
+///
+/// A resolver built `with_strict_types` also rejects a literal given to a
+/// `let` annotated with a type the literal cannot have, as in
+/// `let n: number = "one";`, whether as its value or assigned later. Every
+/// other value is left for the interpreter to check.
+///
+/// A call whose target the resolver can see declared, a `fn` or `class`
+/// called by name or a `super.method(...)`, is checked for how many
+/// arguments it passes, with a note at the declaration. A name assigned
+/// anywhere, declared twice in one scope or possibly replaced by an `import`
+/// is not trusted, and calls through anything else are left to the runtime
+/// check.
+pub struct Resolver {
+    scopes: Vec<Scope>,
+    function_depth: usize,
+    // loops around the code being resolved, within the current function
+    loop_depth: usize,
+    natives: HashSet<String>,
+    warnings: Vec<Warning>,
+    // the globals names must resolve against, if they are checked at all
+    globals: Option<HashSet<String>>,
+    // whether assigning to an unknown global declares it
+    implicit_globals: bool,
+    global_classes: HashMap<String, ClassInfo>,
+    // for each class being resolved, the private members of its superclasses
+    // that `this.name` cannot reach, with the class keeping each
+    inherited_private: Vec<HashMap<String, String>>,
+    strict_types: bool,
+    // the annotated types of globals declared with one
+    global_types: HashMap<String, String>,
+    // every `fn` and `class` declaration calls may be checked against
+    callables: Vec<Callable>,
+    global_callables: HashMap<String, usize>,
+    // the superclass of each class being resolved, if known by name
+    super_classes: Vec<Option<String>>,
+    // checked once the whole program is resolved, when every assignment
+    // that makes a callee unknown has been seen
+    calls: Vec<CallCheck>,
+}
+
+struct Scope {
+    bindings: HashMap<String, Binding>,
+    // the annotated types of the names declared with one
+    types: HashMap<String, String>,
+    classes: HashMap<String, ClassInfo>,
+    // the names declared by a `fn` or `class`, as indexes into `callables`
+    callables: HashMap<String, usize>,
+    // `function_depth` of the code that runs this scope's statements
+    function_depth: usize,
+}
+
+enum Binding {
+    // declared further down the scope; the span is the declaration's name
+    Pending(Span),
+    Declared,
+}
+
+// What a class declaration says about who may reach its members.
+struct ClassInfo {
+    super_class: Option<String>,
+    methods: HashSet<String>,
+    private: HashSet<String>,
+    // the parameters of the methods, including those of mixins known by name
+    arities: HashMap<String, Arity>,
+    // whether the superclass and every mixin are known by name, so a method
+    // missing from `arities` and the superclass chain does not exist
+    complete: bool,
+}
+
+#[derive(Clone)]
+struct Arity {
+    params: usize,
+    // the declaration, e.g. the `init` a class gets its arity from
+    name: String,
+    span: Span,
+}
+
+// A `fn` or `class` declaration. A class's arity is that of its `init`, and
+// is only known once the class has been resolved.
+struct Callable {
+    kind: &'static str,
+    arity: Option<Arity>,
+    // assigned to, or otherwise possibly not the declaration when called
+    reassigned: bool,
+}
+
+struct CallCheck {
+    // the declaration the callee names, None for `super.method`
+    callable: Option<usize>,
+    // e.g. `function 'greet'`
+    callee: String,
+    arity: Arity,
+    provided: usize,
+    span: Span,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    /// A resolver that knows the built-ins every interpreter starts with:
+    /// the natives and the functions of the prelude.
+    pub fn new() -> Self {
+        Self::with_natives(native_names().chain(prelude_names().iter().map(String::as_str)))
+    }
+
+    /// A resolver for an interpreter with a different set of natives, e.g.
+    /// `Resolver::with_natives(interpretor.native_names())` after the
+    /// embedder has called `define_native`.
+    pub fn with_natives<I>(natives: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self {
+            scopes: Vec::new(),
+            function_depth: 0,
+            loop_depth: 0,
+            natives: natives.into_iter().map(Into::into).collect(),
+            warnings: Vec::new(),
+            globals: None,
+            implicit_globals: false,
+            global_classes: HashMap::new(),
+            inherited_private: Vec::new(),
+            strict_types: false,
+            global_types: HashMap::new(),
+            callables: Vec::new(),
+            global_callables: HashMap::new(),
+            super_classes: Vec::new(),
+            calls: Vec::new(),
+        }
+    }
+
+    /// Checks top-level names against `names`, the globals already defined
+    /// by earlier programs, plus whatever each resolved program declares at
+    /// its top level. A program may declare one of `names` again, replacing
+    /// it. Function bodies are not checked, since they may run after a later
+    /// program has defined what they use.
+    pub fn with_existing_globals<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.globals = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Lets top-level code assign to a name the existing globals lack, for
+    /// interpreters with `implicit_globals` set. The name is a global from
+    /// then on.
+    pub fn with_implicit_globals(mut self) -> Self {
+        self.implicit_globals = true;
+        self
+    }
+
+    /// Checks literals against the types annotating the `let`s they are
+    /// given to, for interpreters with `strict_types` set.
+    pub fn with_strict_types(mut self) -> Self {
+        self.strict_types = true;
+        self
+    }
+
+    /// Warnings collected by `resolve` so far.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    pub fn resolve(&mut self, program: &Program) -> Result<(), ErrorInfo> {
+        check_imported(&program.stmts)?;
+        if let Some(globals) = &mut self.globals {
+            for stmt in &program.stmts {
+                for (name, _) in declarations(stmt) {
+                    globals.insert(name.clone());
+                }
+            }
+        }
+        for (name, _) in program.stmts.iter().flat_map(declarations) {
+            self.global_callables.remove(name);
+        }
+        let callables = self.declare_callables(&program.stmts);
+        self.global_callables.extend(callables);
+        self.calls.clear();
+        for stmt in &program.stmts {
+            stmt.accept(self)?;
+        }
+        self.check_calls()
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) -> Result<(), ErrorInfo> {
+        expr.accept(self).map(|_| ())
+    }
+
+    // Opens a scope in which every name declared by `stmts` is pending.
+    fn begin_scope(&mut self, stmts: &[Stmt]) {
+        let mut bindings = HashMap::new();
+        for (name, span) in stmts.iter().flat_map(declarations) {
+            bindings
+                .entry(name.clone())
+                .or_insert_with(|| Binding::Pending(span.clone()));
+        }
+        let callables = self.declare_callables(stmts);
+        self.scopes.push(Scope {
+            bindings,
+            types: HashMap::new(),
+            classes: HashMap::new(),
+            callables,
+            function_depth: self.function_depth,
+        });
+    }
+
+    // Records the `fn` and `class` declarations among `stmts` whose names
+    // nothing else in `stmts` declares.
+    fn declare_callables(&mut self, stmts: &[Stmt]) -> HashMap<String, usize> {
+        let mut counts: HashMap<&String, usize> = HashMap::new();
+        for (name, _) in stmts.iter().flat_map(declarations) {
+            *counts.entry(name).or_default() += 1;
+        }
+        let mut callables = HashMap::new();
+        for stmt in stmts {
+            let (name, callable) = match stmt {
+                Stmt::Function {
+                    name, params, span, ..
+                } => {
+                    let arity = Arity {
+                        params: params.len(),
+                        name: name.clone(),
+                        span: span.clone(),
+                    };
+                    (name, ("function", Some(arity)))
+                }
+                Stmt::Class { name, .. } => (name, ("class", None)),
+                _ => continue,
+            };
+            if counts[name] == 1 {
+                let (kind, arity) = callable;
+                self.callables.push(Callable {
+                    kind,
+                    arity,
+                    reassigned: false,
+                });
+                callables.insert(name.clone(), self.callables.len() - 1);
+            }
+        }
+        callables
+    }
+
+    // The declaration `name` refers to here, if it is a `fn` or `class`.
+    fn callable(&self, name: &str) -> Option<usize> {
+        match self
+            .scopes
+            .iter()
+            .rev()
+            .find(|scope| scope.bindings.contains_key(name))
+        {
+            Some(scope) => scope.callables.get(name).copied(),
+            None => self.global_callables.get(name).copied(),
+        }
+    }
+
+    // Checks the argument count of `callee(args)` once the program is
+    // resolved, when the callee is a declaration the resolver can see.
+    fn check_call(&mut self, callee: &Expr, provided: usize, span: &Span) {
+        let (callable, callee, arity) = match callee {
+            Expr::Variable { name, .. } => {
+                let Some(index) = self.callable(name) else {
+                    return;
+                };
+                let Callable { kind, arity, .. } = &self.callables[index];
+                let Some(arity) = arity else {
+                    return;
+                };
+                (Some(index), format!("{kind} '{name}'"), arity.clone())
+            }
+            Expr::Super { name, .. } => {
+                let Some(Some(super_class)) = self.super_classes.last() else {
+                    return;
+                };
+                let Some(Some(arity)) = self.find_method(super_class, name) else {
+                    return;
+                };
+                (None, format!("method '{name}'"), arity.clone())
+            }
+            _ => return,
+        };
+        self.calls.push(CallCheck {
+            callable,
+            callee,
+            arity,
+            provided,
+            span: span.clone(),
+        });
+    }
+
+    fn check_calls(&mut self) -> Result<(), ErrorInfo> {
+        for call in std::mem::take(&mut self.calls) {
+            let reassigned = call
+                .callable
+                .is_some_and(|index| self.callables[index].reassigned);
+            if reassigned || call.provided == call.arity.params {
+                continue;
+            }
+            let expects = match call.arity.params {
+                1 => "1 argument".to_string(),
+                params => format!("{params} arguments"),
+            };
+            let error = Error::Type(format!(
+                "{} expects {expects}, {} provided",
+                call.callee, call.provided
+            ));
+            let note = format!("'{}' is declared here", call.arity.name);
+            return Err(ErrorInfo::new_with_span(error, call.span).with_note(note, call.arity.span));
+        }
+        Ok(())
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn define(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.bindings.insert(name.to_string(), Binding::Declared);
+        }
+    }
+
+    // Only globals can shadow a native; `self.scopes` is empty at the top
+    // level.
+    fn declare_global(&mut self, name: &str, span: &Span) {
+        if self.scopes.is_empty() && self.natives.contains(name) {
+            self.warnings.push(Warning::new(
+                "shadowing",
+                format!("this shadows the built-in '{name}'"),
+                span.clone(),
+            ));
+        }
+    }
+
+    // Records the type the `let` of `name` just resolved is annotated with.
+    fn annotate(&mut self, name: &str, annotation: Option<&str>) {
+        let types = match self.scopes.last_mut() {
+            Some(scope) => &mut scope.types,
+            None => &mut self.global_types,
+        };
+        match annotation {
+            Some(annotation) => types.insert(name.to_string(), annotation.to_string()),
+            None => types.remove(name),
+        };
+    }
+
+    // With strict types, rejects `value` given to `name` when it is a
+    // literal that cannot have the type `annotation`.
+    fn check_literal(
+        &self,
+        name: &str,
+        annotation: &str,
+        value: &Expr,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        let Expr::Literal { value: literal, .. } = value else {
+            return Ok(());
+        };
+        let actual = match literal {
+            LiteralType::String(_) => "string",
+            LiteralType::Number(..) => "number",
+            LiteralType::Boolean(_) => "boolean",
+            LiteralType::Nil => "nil",
+        };
+        if !self.strict_types || annotation == "any" || annotation == actual {
+            return Ok(());
+        }
+        let error = Error::Type(format!("\"{name}\" should be {annotation}, got {actual}"));
+        Err(ErrorInfo::new_with_span(error, span.clone()))
+    }
+
+    fn check(&self, name: &str, span: &Span) -> Result<(), ErrorInfo> {
+        for scope in self.scopes.iter().rev() {
+            match scope.bindings.get(name) {
+                Some(Binding::Pending(declaration))
+                    if scope.function_depth == self.function_depth =>
+                {
+                    let error = Error::Resolve(format!(
+                        "\"{name}\" used before its declaration in this scope"
+                    ));
+                    return Err(ErrorInfo::new_with_span(error, span.clone())
+                        .with_note(format!("\"{name}\" is declared here"), declaration.clone()));
+                }
+                Some(_) => return Ok(()),
+                None => {}
+            }
+        }
+        match &self.globals {
+            Some(globals) if self.function_depth == 0 && !globals.contains(name) => {
+                let visible = self.scopes.iter().flat_map(|scope| scope.bindings.keys());
+                let candidates = visible.chain(globals).chain(&self.natives);
+                Err(ErrorInfo::new_with_span(
+                    Error::Resolve(format!("undefined variable \"{name}\"")),
+                    span.clone(),
+                )
+                .with_suggestion(name, candidates.map(String::as_str)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn function(
+        &mut self,
+        params: &[String],
+        requires: &[Expr],
+        ensures: &[Expr],
+        body: &[Stmt],
+    ) -> Result<(), ErrorInfo> {
+        self.function_depth += 1;
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        self.begin_scope(body);
+        // parameters live in the same scope as the body, so a later `let` of
+        // the same name redeclares an existing binding
+        for param in params {
+            self.define(param);
+        }
+        // `requires` runs before the body, so its locals are still pending;
+        // `ensures` runs after it, in a scope of its own binding `result`
+        let result = requires
+            .iter()
+            .try_for_each(|expr| self.resolve_expr(expr))
+            .and_then(|()| body.iter().try_for_each(|stmt| stmt.accept(self)))
+            .and_then(|()| {
+                self.begin_scope(&[]);
+                self.define("result");
+                let result = ensures.iter().try_for_each(|expr| self.resolve_expr(expr));
+                self.end_scope();
+                result
+            });
+        self.end_scope();
+        self.loop_depth = loop_depth;
+        self.function_depth -= 1;
+        result
+    }
+
+    fn loop_body(&mut self, body: &Stmt) -> Result<(), ErrorInfo> {
+        self.loop_depth += 1;
+        let result = body.accept(self);
+        self.loop_depth -= 1;
+        result
+    }
+
+    fn class(
+        &mut self,
+        name: Option<&str>,
+        super_class: Option<&Expr>,
+        mixins: &[Expr],
+        methods: &[Stmt],
+        fields: &[FieldInit],
+        private: &[String],
+    ) -> Result<(), ErrorInfo> {
+        for expr in super_class.into_iter().chain(mixins) {
+            self.resolve_expr(expr)?;
+        }
+        let mut info = ClassInfo {
+            super_class: match super_class {
+                Some(Expr::Variable { name, .. }) => Some(name.clone()),
+                _ => None,
+            },
+            methods: methods
+                .iter()
+                .filter_map(|method| match method {
+                    Stmt::Function { name, .. } => Some(name.clone()),
+                    _ => None,
+                })
+                .collect(),
+            private: private.iter().cloned().collect(),
+            arities: methods
+                .iter()
+                .filter_map(|method| match method {
+                    Stmt::Function {
+                        name, params, span, ..
+                    } => Some((
+                        name.clone(),
+                        Arity {
+                            params: params.len(),
+                            name: name.clone(),
+                            span: span.clone(),
+                        },
+                    )),
+                    _ => None,
+                })
+                .collect(),
+            complete: super_class.is_none_or(|expr| matches!(expr, Expr::Variable { .. })),
+        };
+        // the methods of mixins are copied in, so they hide private members
+        // of the superclasses as the class's own do
+        for mixin in mixins {
+            let copied = match mixin {
+                Expr::Variable { name, .. } => self
+                    .class_info(name)
+                    .map(|mixin| (mixin.methods.clone(), mixin.arities.clone())),
+                _ => None,
+            };
+            match copied {
+                Some((methods, arities)) => {
+                    info.methods.extend(methods);
+                    for (name, arity) in arities {
+                        info.arities.entry(name).or_insert(arity);
+                    }
+                }
+                None => info.complete = false,
+            }
+        }
+        self.inherited_private.push(self.inherited(&info));
+        self.super_classes.push(info.super_class.clone());
+        let result = methods.iter().try_for_each(|method| match method {
+            Stmt::Function {
+                params,
+                requires,
+                ensures,
+                body,
+                ..
+            } => self.function(params, requires, ensures, body),
+            _ => Ok(()),
+        });
+        // an initializer runs like the body of a method without parameters
+        let result = result.and_then(|()| {
+            fields.iter().try_for_each(|field| {
+                self.function_depth += 1;
+                self.begin_scope(&[]);
+                let result = self.resolve_expr(&field.value);
+                self.end_scope();
+                self.function_depth -= 1;
+                result
+            })
+        });
+        self.super_classes.pop();
+        self.inherited_private.pop();
+        if let Some(name) = name {
+            match self.scopes.last_mut() {
+                Some(scope) => scope.classes.insert(name.to_string(), info),
+                None => self.global_classes.insert(name.to_string(), info),
+            };
+        }
+        result
+    }
+
+    fn class_info(&self, name: &str) -> Option<&ClassInfo> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.classes.get(name))
+            .or_else(|| self.global_classes.get(name))
+    }
+
+    // The parameters of the method `name` a class finds, looking through
+    // its superclasses: Some(None) when no class in the chain has one, None
+    // when some class in it is not known well enough to tell.
+    fn find_method(&self, class: &str, name: &str) -> Option<Option<&Arity>> {
+        let mut next = Some(class);
+        let mut seen = HashSet::new();
+        while let Some(class) = next.filter(|class| seen.insert(*class)) {
+            let info = self.class_info(class)?;
+            if let Some(arity) = info.arities.get(name) {
+                return Some(Some(arity));
+            }
+            if !info.complete {
+                return None;
+            }
+            next = info.super_class.as_deref();
+        }
+        // a cycle can only come from names the resolver got wrong
+        next.map_or(Some(None), |_| None)
+    }
+
+    // The private members of the superclasses of `class` that it does not
+    // hide with a member of its own, mapped to the class keeping each. The
+    // nearest class naming a member decides whether it is private.
+    fn inherited(&self, class: &ClassInfo) -> HashMap<String, String> {
+        let mut hidden: HashSet<&String> = class.methods.iter().chain(&class.private).collect();
+        let mut inherited = HashMap::new();
+        let mut next = class.super_class.as_ref();
+        let mut seen = HashSet::new();
+        while let Some(name) = next.filter(|name| seen.insert(*name)) {
+            let Some(info) = self.class_info(name) else {
+                break;
+            };
+            for member in &info.private {
+                if !hidden.contains(member) {
+                    inherited.insert(member.clone(), name.clone());
+                }
+            }
+            hidden.extend(info.methods.iter().chain(&info.private));
+            next = info.super_class.as_ref();
+        }
+        inherited
+    }
+
+    // `this.name` or `super.name` in a subclass reaching a private member of
+    // a superclass.
+    fn check_private(&self, name: &str, span: &Span) -> Result<(), ErrorInfo> {
+        let Some(class) = self
+            .inherited_private
+            .last()
+            .and_then(|names| names.get(name))
+        else {
+            return Ok(());
+        };
+        let error = Error::Resolve(format!("\"{name}\" is private to class {class}"));
+        Err(ErrorInfo::new_with_span(error, span.clone()))
+    }
+}
+
+// The names `stmt` declares in the scope it runs in, with the span each
+// declaration reports.
+fn declarations(stmt: &Stmt) -> Vec<(&String, &Span)> {
+    match stmt {
+        Stmt::Let { name, span, .. }
+        | Stmt::Function { name, span, .. }
+        | Stmt::Class { name, span, .. } => vec![(name, span)],
+        Stmt::Destructure { names, span, .. } => names.iter().map(|name| (name, span)).collect(),
+        Stmt::Import {
+            form: ImportForm::Module { alias, span },
+            ..
+        } => vec![(alias, span)],
+        Stmt::Import {
+            form: ImportForm::Names(names),
+            ..
+        } => names
+            .iter()
+            .map(|name| (name.alias.as_ref().unwrap_or(&name.name), &name.span))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// Rejects a name an import binds, or a re-export exports, when another
+// statement among `stmts` declares it too or another import binds it, as
+// which one the name means would depend on the order they run in. Two
+// re-exports of one name are left to the interpreter, which accepts them
+// when both are the same value, as when both come from one module.
+fn check_imported(stmts: &[Stmt]) -> Result<(), ErrorInfo> {
+    let mut seen: HashMap<&str, (&str, &Span)> = HashMap::new();
+    for stmt in stmts {
+        let (how, names): (_, Vec<(&str, &Span)>) = match stmt {
+            Stmt::Import {
+                form: ImportForm::ReExport(names),
+                ..
+            } => {
+                let names = names.iter().map(|name| (name.bound(), &name.span));
+                ("exported", names.collect())
+            }
+            stmt => {
+                let how = match stmt {
+                    Stmt::Import { .. } => "imported",
+                    _ => "declared",
+                };
+                let names = declarations(stmt).into_iter();
+                (
+                    how,
+                    names.map(|(name, span)| (name.as_str(), span)).collect(),
+                )
+            }
+        };
+        for (name, span) in names {
+            let Some(&(first_how, first)) = seen.get(name) else {
+                seen.insert(name, (how, span));
+                continue;
+            };
+            if how == first_how && how != "imported" {
+                continue;
+            }
+            let error =
+                Error::Resolve(format!("\"{name}\" is {how} here, but already {first_how}"));
+            return Err(ErrorInfo::new_with_span(error, span.clone())
+                .with_note(format!("\"{name}\" is {first_how} here"), first.clone()));
+        }
+    }
+    Ok(())
+}
+
+// Whether `value` adds to `name` itself, with a string literal among what it
+// adds, as in `s = s + x + ","` or `s += x + ","`.
+fn appends_string(name: &str, value: &Expr) -> bool {
+    fn is_sum(expr: &Expr) -> Option<(&Expr, &Expr)> {
+        match expr {
+            Expr::Binary {
+                left, op, right, ..
+            } if op.token == TokenType::Plus => Some((left, right)),
+            _ => None,
+        }
+    }
+    fn has_string(expr: &Expr) -> bool {
+        match expr {
+            Expr::Literal {
+                value: LiteralType::String(_),
+                ..
+            } => true,
+            Expr::Grouping { expr, .. } => has_string(expr),
+            _ => is_sum(expr).is_some_and(|(left, right)| has_string(left) || has_string(right)),
+        }
+    }
+
+    let mut leftmost = value;
+    while let Some((left, _)) = is_sum(leftmost) {
+        leftmost = left;
+    }
+    matches!(leftmost, Expr::Variable { name: target, .. } if target == name) && has_string(value)
+}
+
+impl visitor::Expr for Resolver {
+    fn visit_assign_expr(
+        &mut self,
+        _id: NodeId,
+        name: &str,
+        value: &Expr,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.resolve_expr(value)?;
+        let scope = self
+            .scopes
+            .iter()
+            .rev()
+            .find(|scope| scope.bindings.contains_key(name));
+        let types = scope.map_or(&self.global_types, |scope| &scope.types);
+        if let Some(annotation) = types.get(name) {
+            self.check_literal(name, annotation, value, span)?;
+        }
+        if self.loop_depth > 0 && appends_string(name, value) {
+            self.warnings.push(Warning::new(
+                "string-append-in-loop",
+                format!(
+                    "\"{name}\" is copied each time it grows in this loop; \
+                     StringBuilder() appends in place"
+                ),
+                span.clone(),
+            ));
+        }
+        if let Some(index) = self.callable(name) {
+            self.callables[index].reassigned = true;
+        }
+        let declared = self.scopes.iter().any(|s| s.bindings.contains_key(name));
+        if let Some(globals) = &mut self.globals {
+            if self.implicit_globals && self.function_depth == 0 && !declared {
+                globals.insert(name.to_string());
+            }
+        }
+        self.check(name, span)?;
+        Ok(Object::Nil)
+    }
+
+    fn visit_binary_expr(
+        &mut self,
+        left: &Expr,
+        _op: &TokenInfo,
+        right: &Expr,
+    ) -> Result<Object, ErrorInfo> {
+        self.resolve_expr(left)?;
+        self.resolve_expr(right)?;
+        Ok(Object::Nil)
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+        named: &[NamedArg],
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.resolve_expr(callee)?;
+        for arg in args {
+            self.resolve_expr(arg)?;
+        }
+        for arg in named {
+            self.resolve_expr(&arg.value)?;
+        }
+        self.check_call(callee, args.len() + named.len(), span);
+        Ok(Object::Nil)
+    }
+
+    fn visit_class_expr(
+        &mut self,
+        name: Option<&str>,
+        super_class: Option<&Expr>,
+        mixins: &[Expr],
+        methods: &[Stmt],
+        fields: &[FieldInit],
+        private: &[String],
+        _doc: Option<&str>,
+        _span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.class(name, super_class, mixins, methods, fields, private)?;
+        Ok(Object::Nil)
+    }
+
+    fn visit_get_expr(
+        &mut self,
+        _id: NodeId,
+        object: &Expr,
+        name: &str,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.resolve_expr(object)?;
+        if object.is_this() {
+            self.check_private(name, span)?;
+        }
+        Ok(Object::Nil)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Expr, _span: &Span) -> Result<Object, ErrorInfo> {
+        self.resolve_expr(expr)?;
+        Ok(Object::Nil)
+    }
+
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
+        _span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.resolve_expr(object)?;
+        self.resolve_expr(index)?;
+        Ok(Object::Nil)
+    }
+
+    fn visit_match_expr(
+        &mut self,
+        value: &Expr,
+        arms: &[MatchArm],
+        _span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.resolve_expr(value)?;
+        for arm in arms {
+            // names bound by the pattern are only visible in their arm
+            self.begin_scope(&[]);
+            for name in arm.pattern.names() {
+                self.define(name);
+            }
+            let result = arm
+                .guard
+                .iter()
+                .chain([&arm.body])
+                .try_for_each(|expr| self.resolve_expr(expr));
+            self.end_scope();
+            result?;
+        }
+        Ok(Object::Nil)
+    }
+
+    fn visit_literal_expr(&mut self, _value: &LiteralType) -> Result<Object, ErrorInfo> {
+        Ok(Object::Nil)
+    }
+
+    fn visit_logical_expr(
+        &mut self,
+        left: &Expr,
+        _op: &TokenInfo,
+        right: &Expr,
+    ) -> Result<Object, ErrorInfo> {
+        self.resolve_expr(left)?;
+        self.resolve_expr(right)?;
+        Ok(Object::Nil)
+    }
+
+    // always defined: the host supplies the value when the program runs
+    fn visit_placeholder_expr(&mut self, _name: &str, _span: &Span) -> Result<Object, ErrorInfo> {
+        Ok(Object::Nil)
+    }
+
+    fn visit_set_expr(
+        &mut self,
+        object: &Expr,
+        name: &str,
+        _op: Option<&TokenInfo>,
+        value: &Expr,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.resolve_expr(value)?;
+        self.resolve_expr(object)?;
+        if object.is_this() {
+            self.check_private(name, span)?;
+        }
+        Ok(Object::Nil)
+    }
+
+    fn visit_super_expr(
+        &mut self,
+        _id: NodeId,
+        name: &str,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.check_private(name, span)?;
+        Ok(Object::Nil)
+    }
+
+    fn visit_tuple_expr(&mut self, elements: &[Expr], _span: &Span) -> Result<Object, ErrorInfo> {
+        elements
+            .iter()
+            .try_for_each(|expr| self.resolve_expr(expr))?;
+        Ok(Object::Nil)
+    }
+
+    fn visit_unary_expr(&mut self, _op: &TokenInfo, right: &Expr) -> Result<Object, ErrorInfo> {
+        self.resolve_expr(right)?;
+        Ok(Object::Nil)
+    }
+
+    fn visit_variable_expr(
+        &mut self,
+        _id: NodeId,
+        name: &str,
+        span: &Span,
+    ) -> Result<Object, ErrorInfo> {
+        self.check(name, span)?;
+        Ok(Object::Nil)
+    }
+}
+
+impl visitor::Stmt for Resolver {
+    fn visit_expr_stmt(&mut self, expr: &Expr) -> Result<(), ErrorInfo> {
+        self.resolve_expr(expr)
+    }
+
+    fn visit_print_stmt(&mut self, exprs: &[Expr]) -> Result<(), ErrorInfo> {
+        exprs.iter().try_for_each(|expr| self.resolve_expr(expr))
+    }
+
+    fn visit_block_stmt(&mut self, stmts: &[Stmt]) -> Result<(), ErrorInfo> {
+        self.begin_scope(stmts);
+        let result = stmts.iter().try_for_each(|stmt| stmt.accept(self));
+        self.end_scope();
+        result
+    }
+
+    fn visit_function_stmt(
+        &mut self,
+        name: &str,
+        params: &[String],
+        _signature: &Signature,
+        requires: &[Expr],
+        ensures: &[Expr],
+        body: &[Stmt],
+        _doc: Option<&str>,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        self.declare_global(name, span);
+        self.define(name);
+        self.function(params, requires, ensures, body)
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        condition: &Expr,
+        truthy: &Stmt,
+        falsy: Option<&Stmt>,
+    ) -> Result<(), ErrorInfo> {
+        self.resolve_expr(condition)?;
+        // `if (x); { ... }` runs the block whatever `x` is
+        if let Stmt::Empty { span, .. } = truthy {
+            self.warnings.push(Warning::new(
+                "empty-if-body",
+                "empty if body: the \";\" after the condition ends the if".to_string(),
+                span.clone(),
+            ));
+        }
+        truthy.accept(self)?;
+        if let Some(falsy) = falsy {
+            falsy.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_let_stmt(
+        &mut self,
+        name: &str,
+        annotation: Option<&str>,
+        value: Option<&Expr>,
+        _is_const: bool,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        if let Some(value) = value {
+            self.resolve_expr(value)?;
+            if let Some(annotation) = annotation {
+                self.check_literal(name, annotation, value, span)?;
+            }
+        }
+        self.declare_global(name, span);
+        self.define(name);
+        self.annotate(name, annotation);
+        Ok(())
+    }
+
+    fn visit_destructure_stmt(
+        &mut self,
+        names: &[String],
+        value: &Expr,
+        _is_const: bool,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        self.resolve_expr(value)?;
+        for name in names {
+            self.declare_global(name, span);
+            self.define(name);
+        }
+        Ok(())
+    }
+
+    fn visit_return_stmt(&mut self, value: Option<&Expr>, _span: &Span) -> Result<(), ErrorInfo> {
+        if let Some(value) = value {
+            self.resolve_expr(value)?;
+        }
+        Ok(())
+    }
+
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<(), ErrorInfo> {
+        self.resolve_expr(condition)?;
+        self.loop_body(body)
+    }
+
+    // The initializer is scoped to the loop, as in the block it is lowered to.
+    fn visit_for_stmt(
+        &mut self,
+        initializer: Option<&Stmt>,
+        condition: Option<&Expr>,
+        increment: Option<&Expr>,
+        body: &Stmt,
+        _span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        self.begin_scope(initializer.map(std::slice::from_ref).unwrap_or_default());
+        let result = (|| {
+            if let Some(initializer) = initializer {
+                initializer.accept(self)?;
+            }
+            if let Some(condition) = condition {
+                self.resolve_expr(condition)?;
+            }
+            if let Some(increment) = increment {
+                self.resolve_expr(increment)?;
+            }
+            self.loop_body(body)
+        })();
+        self.end_scope();
+        result
+    }
+
+    // The bound and the step are evaluated once, after the counter is
+    // declared, as in the block the loop is lowered to.
+    fn visit_for_range_stmt(
+        &mut self,
+        initializer: &Stmt,
+        end: &Expr,
+        step: Option<&Expr>,
+        body: &Stmt,
+        _span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        self.begin_scope(std::slice::from_ref(initializer));
+        let result = (|| {
+            initializer.accept(self)?;
+            self.resolve_expr(end)?;
+            if let Some(step) = step {
+                self.resolve_expr(step)?;
+            }
+            self.loop_body(body)
+        })();
+        self.end_scope();
+        result
+    }
+
+    fn visit_class_stmt(
+        &mut self,
+        name: &str,
+        super_class: Option<&Expr>,
+        mixins: &[Expr],
+        methods: &[Stmt],
+        fields: &[FieldInit],
+        private: &[String],
+        _doc: Option<&str>,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        self.declare_global(name, span);
+        self.class(Some(name), super_class, mixins, methods, fields, private)?;
+        self.define(name);
+        // a class without an `init` anywhere in its chain takes nothing
+        let arity = match self.find_method(name, "init") {
+            Some(Some(arity)) => Some(arity.clone()),
+            Some(None) => Some(Arity {
+                params: 0,
+                name: name.to_string(),
+                span: span.clone(),
+            }),
+            None => None,
+        };
+        if let Some(index) = self.callable(name) {
+            self.callables[index].arity = arity;
+        }
+        Ok(())
+    }
+
+    fn visit_break_stmt(&mut self, _span: &Span) -> Result<(), ErrorInfo> {
+        Ok(())
+    }
+
+    fn visit_continue_stmt(&mut self, _span: &Span) -> Result<(), ErrorInfo> {
+        Ok(())
+    }
+
+    fn visit_defer_stmt(&mut self, body: &Stmt, _span: &Span) -> Result<(), ErrorInfo> {
+        body.accept(self)
+    }
+
+    // What a module defines is only known once it runs, so the names an
+    // `import` of all of it binds are globals like any other the resolver
+    // cannot see declared, and the existing globals no longer tell which
+    // names are undefined. The other forms bind only the names they list.
+    fn visit_import_stmt(
+        &mut self,
+        _specifier: &str,
+        form: &ImportForm,
+        span: &Span,
+    ) -> Result<(), ErrorInfo> {
+        if !self.scopes.is_empty() {
+            let keyword = match form {
+                ImportForm::ReExport(_) => "export",
+                _ => "import",
+            };
+            let error = Error::Resolve(format!("{keyword} is only allowed at the top level"));
+            return Err(ErrorInfo::new_with_span(error, span.clone()));
+        }
+        match form {
+            ImportForm::All => {
+                self.globals = None;
+                for index in self.global_callables.values() {
+                    self.callables[*index].reassigned = true;
+                }
+            }
+            ImportForm::Module { alias, span } => self.declare_global(alias, span),
+            ImportForm::Names(names) => {
+                for name in names {
+                    self.declare_global(name.bound(), &name.span);
+                }
+            }
+            ImportForm::ReExport(_) => {}
+        }
+        Ok(())
+    }
+
+    fn visit_empty_stmt(&mut self) -> Result<(), ErrorInfo> {
+        Ok(())
+    }
+
+    // The parse error was already reported; there is nothing here to check.
+    fn visit_error_stmt(&mut self, _span: &Span) -> Result<(), ErrorInfo> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, ErrorInfo, Interpretor, Lexer, Object, Parser, Program, Resolver, Span};
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap()
+    }
+
+    fn resolve(input: &str) -> Result<(), ErrorInfo> {
+        Resolver::new().resolve(&parse(input))
+    }
+
+    fn warnings(mut resolver: Resolver, input: &str) -> Vec<String> {
+        resolver.resolve(&parse(input)).unwrap();
+        resolver
+            .warnings()
+            .iter()
+            .map(|warning| format!("{} (line {})", warning.message, warning.line()))
+            .collect()
+    }
+
+    #[test]
+    fn test_use_before_declaration() {
+        let input = "let a = 0;\n{\n  print a;\n  let a = 1;\n}";
+        let err = resolve(input).unwrap_err();
+        let message = "\"a\" used before its declaration in this scope".to_string();
+        assert_eq!(err.error, Error::Resolve(message));
+        assert_eq!(err.line(), 3);
+        let (note, span) = &err.notes()[0];
+        assert_eq!(note, "\"a\" is declared here");
+        assert_eq!(span.line, 4);
+
+        assert!(resolve("fn f() { let b = b; }").is_err());
+        assert!(resolve("fn f() { g(); fn g() {} }").is_err());
+        assert_eq!(resolve("fn f(x) { print x; let x = 2; }"), Ok(()));
+    }
+
+    #[test]
+    fn test_allowed_forward_references() {
+        let input = "
+        fn outer() {
+            fn is_even(n) { if (n == 0) { return true; } return is_odd(n - 1); }
+            fn is_odd(n) { if (n == 0) { return false; } return is_even(n - 1); }
+            return is_even(4);
+        }
+        print later;
+        let later = 1;
+        ";
+        assert_eq!(resolve(input), Ok(()));
+
+        let input = "
+        {
+            class A { make() { return B(); } }
+            class B { make() { return A(); } }
+        }
+        ";
+        assert_eq!(resolve(input), Ok(()));
+        assert!(resolve("{ print B(); class B {} }").is_err());
+    }
+
+    #[test]
+    fn test_private_members() {
+        let base = "class A { priv n; priv get() { return this.n; } }\n";
+        let err = resolve(&format!(
+            "{base}class B < A {{ m() {{ return super.get(); }} }}"
+        ))
+        .unwrap_err();
+        let message = "\"get\" is private to class A".to_string();
+        assert_eq!(err.error, Error::Resolve(message));
+        assert_eq!(err.line(), 2);
+        // through a class in between, and from a function in a method
+        let input = "class B < A {}\nclass C < B { m() { fn f() { this.n = 1; } } }";
+        assert!(resolve(&format!("{base}{input}")).is_err());
+
+        // a member of the subclass hides the private one
+        let input = "class B < A { get() { return this.get; } }";
+        assert_eq!(resolve(&format!("{base}{input}")), Ok(()));
+        // other objects and unknown superclasses are left to the interpreter
+        let input = "fn f(a) { return a.n; }\nclass B < (A) { m() { return this.n; } }";
+        assert_eq!(resolve(&format!("{base}{input}")), Ok(()));
+        // a local class of the same name does not leak out of its block
+        let input = "{ class A { priv x; } }\nclass X {}\nclass B < X { m() { this.x; } }";
+        assert_eq!(resolve(input), Ok(()));
+    }
+
+    #[test]
+    fn test_shadowed_natives() {
+        let input = "let to_fixed = 1;\nfn trace() {}\nclass freeze {}\nlet other = 2;";
+        assert_eq!(
+            warnings(Resolver::new(), input),
+            [
+                "this shadows the built-in 'to_fixed' (line 1)",
+                "this shadows the built-in 'trace' (line 2)",
+                "this shadows the built-in 'freeze' (line 3)",
+            ]
+        );
+
+        let input = "fn f(trace) { let to_fixed = 1; fn freeze() {} }\n{ let trace = 1; }";
+        assert!(warnings(Resolver::new(), input).is_empty());
+
+        // the functions of the prelude are built-ins too
+        assert_eq!(
+            warnings(Resolver::new(), "fn sum(items) {}"),
+            ["this shadows the built-in 'sum' (line 1)"]
+        );
+    }
+
+    #[test]
+    fn test_empty_if_body() {
+        let input = "let x = 1;\nif (x); { print x; }\nif (x) {} else;\nwhile (x);";
+        assert_eq!(
+            warnings(Resolver::new(), input),
+            ["empty if body: the \";\" after the condition ends the if (line 2)"]
+        );
+    }
+
+    #[test]
+    fn test_string_growth_in_loops() {
+        let input = "let s = \"\";\nlet i = 0;\nwhile (i < 3) {\n  s = s + i + \",\";\n  \
+                     s += (\"[\" + i) + \"]\";\n  i = i + 1;\n}\nfor (let j = 0 to 3) s = s + \"!\";";
+        assert_eq!(
+            warnings(Resolver::new(), input),
+            [
+                "\"s\" is copied each time it grows in this loop; StringBuilder() appends in place (line 4)",
+                "\"s\" is copied each time it grows in this loop; StringBuilder() appends in place (line 5)",
+                "\"s\" is copied each time it grows in this loop; StringBuilder() appends in place (line 8)",
+            ]
+        );
+
+        // numbers, other names, code outside loops and functions merely
+        // declared in a loop are left alone
+        let input = "let s = \"a\";\nlet t = \"\";\nlet n = 0;\ns = s + \"b\";\n\
+                     while (n < 3) {\n  n = n + 1;\n  t = s + \"c\";\n  fn f() { s = s + \"d\"; }\n}";
+        assert!(warnings(Resolver::new(), input).is_empty());
+    }
+
+    #[test]
+    fn test_strict_types() {
+        let strict = |input: &str| {
+            let result = Resolver::new().with_strict_types().resolve(&parse(input));
+            result.map_err(|err| format!("{} (line {})", err.error, err.line()))
+        };
+        assert_eq!(
+            strict("let n: number = \"one\";"),
+            Err("TypeError: \"n\" should be number, got string (line 1)".to_string())
+        );
+        assert_eq!(
+            strict("let n: number = 1;\nfn f() {\n  n = nil;\n}"),
+            Err("TypeError: \"n\" should be number, got nil (line 3)".to_string())
+        );
+        // a local without a type hides the annotated global
+        assert_eq!(
+            strict("let n: number = 1;\nfn f(n) { n = \"s\"; { let n = 2; n = true; } }"),
+            Ok(())
+        );
+        // anything but a literal is left for the interpreter
+        assert_eq!(strict("let s: string = 1 + 1;\nlet a: any = nil;"), Ok(()));
+        assert_eq!(
+            strict("let p: Point = 1;").unwrap_err(),
+            "TypeError: \"p\" should be Point, got number (line 1)"
+        );
+
+        // annotations are ignored by default
+        assert_eq!(resolve("let n: number = \"one\"; n = false;"), Ok(()));
+    }
+
+    #[test]
+    fn test_static_arity() {
+        let check = |input: &str| {
+            resolve(input).map_err(|err| {
+                let (note, span) = &err.notes()[0];
+                format!(
+                    "{} (line {}), {note} (line {})",
+                    err.error,
+                    err.line(),
+                    span.line
+                )
+            })
+        };
+        assert_eq!(
+            check("fn greet(a, b) {}\ngreet(1, 2, 3);"),
+            Err(
+                "TypeError: function 'greet' expects 2 arguments, 3 provided (line 2), \
+                 'greet' is declared here (line 1)"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check("fn f() { g(1); }\nfn g() {}").unwrap_err(),
+            "TypeError: function 'g' expects 0 arguments, 1 provided (line 1), \
+             'g' is declared here (line 2)"
+        );
+        assert_eq!(
+            check("{\n  fn one(x) {}\n  one(x: 1, y: 2);\n}").unwrap_err(),
+            "TypeError: function 'one' expects 1 argument, 2 provided (line 3), \
+             'one' is declared here (line 2)"
+        );
+        // classes take the arguments of their `init`, or none without one
+        assert_eq!(
+            check("class P {\n  init(x, y) {}\n}\nP(1);").unwrap_err(),
+            "TypeError: class 'P' expects 2 arguments, 1 provided (line 4), \
+             'init' is declared here (line 2)"
+        );
+        assert_eq!(
+            check("class A { init(x) {} }\nclass B < A {}\nB();").unwrap_err(),
+            "TypeError: class 'B' expects 1 argument, 0 provided (line 3), \
+             'init' is declared here (line 1)"
+        );
+        assert_eq!(
+            check("class E {}\nE(1);").unwrap_err(),
+            "TypeError: class 'E' expects 0 arguments, 1 provided (line 2), \
+             'E' is declared here (line 1)"
+        );
+        assert_eq!(
+            check("class M { m(a) {} }\nclass C with M {}\nclass D < C {\n  m() { super.m(); }\n}")
+                .unwrap_err(),
+            "TypeError: method 'm' expects 1 argument, 0 provided (line 4), \
+             'm' is declared here (line 1)"
+        );
+        assert_eq!(
+            check("fn f(a, b) {}\nf(1, b: 2);\nclass E {}\nE();"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_static_arity_bail_out() {
+        // reassigned, before or after the call, or declared twice
+        assert_eq!(resolve("fn f(a) {}\nf = fields;\nf(1, 2);"), Ok(()));
+        assert_eq!(
+            resolve("fn f(a) {}\nfn g() { f(1, 2); }\nf = fields;"),
+            Ok(())
+        );
+        assert_eq!(resolve("fn f(a) {}\nlet f = fields;\nf(1, 2);"), Ok(()));
+        assert_eq!(resolve("class A {}\nfn g() { A = 1; }\nA(1);"), Ok(()));
+        // called through a parameter, variable or property
+        assert_eq!(resolve("fn f(a) {}\nfn g(f) { f(1, 2); }"), Ok(()));
+        assert_eq!(resolve("fn f(a) {}\nlet h = f;\nh(1, 2);"), Ok(()));
+        assert_eq!(resolve("class A { m(a) {} }\nA().m(1, 2);"), Ok(()));
+        // a class whose chain the resolver cannot see through
+        assert_eq!(resolve("class A < make() {}\nA(1);"), Ok(()));
+        assert_eq!(resolve("class A with make() {}\nA(1);"), Ok(()));
+        assert_eq!(resolve("fn f() { class B < C {} B(1); }"), Ok(()));
+        // a module may define a function of the same name
+        assert_eq!(resolve("fn f(a) {}\nimport \"m.lang\";\nf(1, 2);"), Ok(()));
+
+        // the runtime check still catches what is left
+        let mut interpretor = Interpretor::buffered();
+        let program = parse("fn f(a) {}\nlet h = f;\nh(1, 2);");
+        let mut resolver = Resolver::new();
+        assert_eq!(resolver.resolve(&program), Ok(()));
+        let err = program
+            .stmts
+            .iter()
+            .try_for_each(|stmt| interpretor.exec(stmt))
+            .unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Arity {
+                expected: 1,
+                got: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_import() {
+        assert_eq!(resolve("import \"a.lang\";\nprint f();"), Ok(()));
+        let err = resolve("fn f() {\n  import \"a.lang\";\n}").unwrap_err();
+        let message = "import is only allowed at the top level".to_string();
+        assert_eq!((err.line(), err.error), (2, Error::Resolve(message)));
+        assert!(resolve("{ import \"a.lang\"; }").is_err());
+
+        // the module may define any name
+        let mut existing = Resolver::new().with_existing_globals(["a"]);
+        assert!(existing
+            .resolve(&parse("import \"a.lang\"; print b;"))
+            .is_ok());
+        // but only those listed are bound by the other forms
+        let mut existing = Resolver::new().with_existing_globals(["a"]);
+        assert!(existing
+            .resolve(&parse(
+                "import \"a.lang\" as m;\nimport { b as c } from \"a.lang\";\nprint m, c;"
+            ))
+            .is_ok());
+        let mut existing = Resolver::new().with_existing_globals(["a"]);
+        assert!(existing
+            .resolve(&parse("export { b } from \"a.lang\";\nprint b;"))
+            .is_err());
+        let err = resolve("fn f() {\n  export { b } from \"a.lang\";\n}").unwrap_err();
+        let message = "export is only allowed at the top level".to_string();
+        assert_eq!((err.line(), err.error), (2, Error::Resolve(message)));
+    }
+
+    #[test]
+    fn test_import_collisions() {
+        let collision = |input: &str| {
+            let err = resolve(input).unwrap_err();
+            let Error::Resolve(message) = &err.error else {
+                panic!("expected a resolve error, got {:?}", err.error);
+            };
+            (err.line(), message.clone(), err.notes()[0].1.line)
+        };
+        // before or after the declaration
+        assert_eq!(
+            collision("import { a as limit } from \"m.lang\";\nfn limit() {}"),
+            (
+                2,
+                "\"limit\" is declared here, but already imported".into(),
+                1
+            )
+        );
+        assert_eq!(
+            collision("let m = 1;\nimport \"m.lang\" as m;"),
+            (2, "\"m\" is imported here, but already declared".into(), 1)
+        );
+        assert_eq!(
+            collision("import { a } from \"m.lang\";\nimport { b as a } from \"n.lang\";"),
+            (2, "\"a\" is imported here, but already imported".into(), 1)
+        );
+        assert_eq!(
+            collision("class A {}\nexport { A } from \"m.lang\";"),
+            (2, "\"A\" is exported here, but already declared".into(), 1)
+        );
+        // the interpreter checks that both are the same value
+        assert_eq!(
+            resolve("export { a } from \"m.lang\";\nexport { a } from \"n.lang\";"),
+            Ok(())
+        );
+        // as for any other declaration, a name may be declared again
+        assert_eq!(
+            resolve("let a = 1;\nlet a = 2;\nimport { b } from \"m.lang\";"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_custom_natives() {
+        let input = "let clock = 1; let trace = 2;";
+        let resolver = Resolver::with_natives(["clock"]);
+        assert_eq!(
+            warnings(resolver, input),
+            ["this shadows the built-in 'clock' (line 1)"]
+        );
+
+        fn clock(_: &mut Interpretor, _: Vec<Object>, _: &Span) -> Result<Object, ErrorInfo> {
+            Ok(Object::Number(0.0))
+        }
+        let mut interpretor = Interpretor::buffered();
+        interpretor.define_native("clock", &[], 0, false, clock);
+        let resolver = Resolver::with_natives(interpretor.native_names());
+        assert_eq!(warnings(resolver, input).len(), 2);
+    }
+
+    #[test]
+    fn test_existing_globals() {
+        let resolver = || Resolver::new().with_existing_globals(["f", "print"]);
+        assert_eq!(resolver().resolve(&parse("f(); print(1);")), Ok(()));
+        assert_eq!(resolver().resolve(&parse("g(); fn g() {}")), Ok(()));
+        assert_eq!(resolver().resolve(&parse("fn f() { return h(); }")), Ok(()));
+        assert_eq!(resolver().resolve(&parse("let f = 1; f = 2;")), Ok(()));
+
+        let err = resolver().resolve(&parse("{ print nope; }")).unwrap_err();
+        let message = "undefined variable \"nope\"".to_string();
+        assert_eq!(err.error, Error::Resolve(message));
+        assert!(resolver().resolve(&parse("nope = 1;")).is_err());
+        assert_eq!(resolve("print nope;"), Ok(()));
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let hint = |input: &str| {
+            let mut resolver = Resolver::new().with_existing_globals(["counter"]);
+            let err = resolver.resolve(&parse(input)).unwrap_err();
+            err.hint().map(str::to_owned)
+        };
+        let did_you_mean = |name: &str| Some(format!("did you mean '{name}'?"));
+        assert_eq!(hint("print countr;"), did_you_mean("counter"));
+        assert_eq!(
+            hint("let total = 1; { let item = 2; print itme; }"),
+            did_you_mean("item")
+        );
+        assert_eq!(hint("to_fixd(1, 2);"), did_you_mean("to_fixed"));
+        assert_eq!(hint("print tally;"), None);
+        assert_eq!(hint("print y;"), None);
+    }
+}
@@ -0,0 +1,1165 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{FieldInit, NodeId, Program};
+use crate::interpretor::{native_names, prelude_names};
+use crate::{Error, ErrorInfo, Expr, Lexer, Resolver, Span, Stmt, TokenType};
+
+/// Identifies a symbol of the `SymbolIndex` it came from. Indexing a program
+/// again after `rename` hands every symbol the same id.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct SymbolId(pub usize);
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SymbolKind {
+    Let,
+    Const,
+    Function,
+    Class,
+    Param,
+    Method,
+    Field,
+}
+
+/// A name read or assigned, or a member reached as `this.name` or
+/// `super.name`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reference {
+    pub node: NodeId,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Symbol {
+    pub id: SymbolId,
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The node declaring the symbol: the `let`, `fn`, `class` or method, the
+    /// function a param belongs to, the class a `priv` field is declared in,
+    /// or the first `this.name = value` of a field that is not.
+    pub node: NodeId,
+    pub span: Span,
+    pub references: Vec<Reference>,
+    // which of the names `node` binds is this one, for nodes binding several
+    slot: Option<usize>,
+    owner: Owner,
+}
+
+// Where a symbol is declared: a scope for variables, a class for members.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Owner {
+    Scope(usize),
+    Class(usize),
+}
+
+/// Every declaration of a program, with the references the scoping rules
+/// tie to each, built by `analyze`.
+///
+/// Variables resolve as the resolver scopes them: a block, function body or
+/// loop initializer is a scope, and a name means the nearest declaration of
+/// it. Two `x`s in nested scopes are two symbols. Of several declarations of
+/// a name in one scope, a use means the last one before it, or the first
+/// when none is. Members are only referenced as `this.name` and
+/// `super.name` inside the methods of a class and its subclasses, since what
+/// other objects are is only known when the program runs.
+#[derive(Debug)]
+pub struct SymbolIndex {
+    symbols: Vec<Symbol>,
+    // what each name in the program resolved to, if anything indexed
+    uses: HashMap<NodeId, (Span, Option<SymbolId>)>,
+    // for each scope, the scope around it
+    parents: Vec<Option<usize>>,
+    // for each class, its members and the class it extends, if known
+    classes: Vec<ClassInfo>,
+    // each `object.name` whose object is not `this`, by name
+    outside: Vec<(String, Span)>,
+}
+
+#[derive(Debug, Default)]
+struct ClassInfo {
+    members: HashMap<String, SymbolId>,
+    super_class: Option<usize>,
+}
+
+impl SymbolIndex {
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> Option<&Symbol> {
+        self.symbols.get(id.0)
+    }
+
+    /// The references resolved to the symbol `id`, in source order.
+    pub fn references_of(&self, id: SymbolId) -> &[Reference] {
+        self.symbol(id).map_or(&[], |symbol| &symbol.references)
+    }
+
+    /// The symbol declared or referenced at the `offset`th character of the
+    /// source.
+    pub fn declaration_at(&self, offset: usize) -> Option<&Symbol> {
+        let covers = |span: &Span| span.start <= offset && offset < span.end;
+        self.symbols.iter().find(|symbol| {
+            covers(&symbol.span) || symbol.references.iter().any(|r| covers(&r.span))
+        })
+    }
+
+    // The member `name` of the class `class` or of the classes it extends.
+    fn member(&self, class: usize, name: &str) -> Option<SymbolId> {
+        let mut next = Some(class);
+        let mut seen = HashSet::new();
+        while let Some(class) = next.filter(|class| seen.insert(*class)) {
+            if let Some(id) = self.classes[class].members.get(name) {
+                return Some(*id);
+            }
+            next = self.classes[class].super_class;
+        }
+        None
+    }
+}
+
+/// Indexes the declarations of `program` and the references to each.
+pub fn analyze(program: &Program) -> SymbolIndex {
+    let mut analyzer = Analyzer {
+        program,
+        index: SymbolIndex {
+            symbols: Vec::new(),
+            uses: HashMap::new(),
+            parents: Vec::new(),
+            classes: Vec::new(),
+            outside: Vec::new(),
+        },
+        scopes: Vec::new(),
+        declared: HashMap::new(),
+        class_of: HashMap::new(),
+        this: Vec::new(),
+        field_nodes: HashSet::new(),
+    };
+    let global = analyzer.scope(None);
+    analyzer.stmts(&program.stmts, global);
+    for symbol in &mut analyzer.index.symbols {
+        symbol.references.sort_by_key(|r| r.span.start);
+    }
+    analyzer.index
+}
+
+/// Renames the symbol `id` of `program`, rewriting its declaration and
+/// every reference to it.
+///
+/// The rename is refused when `new_name` is not a name, is declared
+/// alongside the symbol already, would shadow a built-in, or would make any
+/// name in the program mean something else than it did, as when a reference
+/// would be captured by an inner declaration of `new_name`. A member is not
+/// renamed while its name is used on any object other than `this` or
+/// `super`, as in `a.m()`, since which member that means is only known when
+/// the program runs. What comes back always resolves.
+pub fn rename(program: &Program, id: SymbolId, new_name: &str) -> Result<Program, ErrorInfo> {
+    let index = analyze(program);
+    let Some(symbol) = index.symbol(id) else {
+        let error = Error::Value(format!("there is no symbol {}", id.0));
+        return Err(ErrorInfo::new_with_span(error, Span::default()));
+    };
+    let fail = |message: String, span: &Span| {
+        Err(ErrorInfo::new_with_span(
+            Error::Resolve(message),
+            span.clone(),
+        ))
+    };
+    let mut lexer = Lexer::new(new_name.to_string());
+    let is_name = matches!(lexer.next_token().token, TokenType::Identifier(name) if name == new_name)
+        && lexer.next_token().token == TokenType::Eof;
+    if !is_name || new_name == "this" {
+        let error = Error::Value(format!("\"{new_name}\" is not a valid name"));
+        return Err(ErrorInfo::new_with_span(error, symbol.span.clone()));
+    }
+    if symbol.name == new_name {
+        return Ok(program.clone());
+    }
+
+    let old = &symbol.name;
+    let taken = index.symbols.iter().find(|other| {
+        other.name == new_name
+            && match (symbol.owner, other.owner) {
+                (Owner::Scope(scope), Owner::Scope(other)) => scope == other,
+                (Owner::Class(class), Owner::Class(_)) => {
+                    index.member(class, new_name) == Some(other.id)
+                }
+                _ => false,
+            }
+    });
+    if let Some(taken) = taken {
+        return fail(
+            format!("cannot rename \"{old}\" to \"{new_name}\", which is already declared here"),
+            &taken.span,
+        );
+    }
+    if let Owner::Class(_) = symbol.owner {
+        if let Some((_, span)) = index.outside.iter().find(|(name, _)| name == old) {
+            return fail(
+                format!("cannot rename \"{old}\", which is used on another object here"),
+                span,
+            );
+        }
+    }
+    let builtin = native_names().any(|name| name == new_name)
+        || prelude_names().iter().any(|name| name == new_name);
+    if symbol.owner == Owner::Scope(0) && builtin {
+        return fail(
+            format!("renaming \"{old}\" to \"{new_name}\" would shadow the built-in"),
+            &symbol.span,
+        );
+    }
+
+    let mut nodes: HashSet<NodeId> = symbol.references.iter().map(|r| r.node).collect();
+    let mut slots = HashMap::new();
+    match symbol.slot {
+        Some(slot) => {
+            slots.insert(symbol.node, slot);
+        }
+        None => {
+            nodes.insert(symbol.node);
+        }
+    }
+    let renames = Renames {
+        nodes,
+        slots,
+        name: new_name,
+    };
+    let mut renamed = program.clone();
+    renamed.stmts.iter_mut().for_each(|stmt| renames.stmt(stmt));
+
+    Resolver::new().resolve(&renamed)?;
+    let after = analyze(&renamed);
+    let mut changed: Vec<_> = index
+        .uses
+        .iter()
+        .filter(|(node, (_, target))| after.uses.get(*node).map(|(_, after)| after) != Some(target))
+        .map(|(_, (span, _))| span)
+        .collect();
+    changed.sort_by_key(|span| span.start);
+    if let Some(span) = changed.first() {
+        return fail(
+            format!(
+                "renaming \"{old}\" to \"{new_name}\" would change what the name here refers to"
+            ),
+            span,
+        );
+    }
+    Ok(renamed)
+}
+
+struct Analyzer<'a> {
+    program: &'a Program,
+    index: SymbolIndex,
+    // for each scope, the bindings of each name in it
+    scopes: Vec<HashMap<String, Vec<Binding>>>,
+    // the symbol made for each declaring node and slot
+    declared: HashMap<(NodeId, Option<usize>), SymbolId>,
+    // the class made for each class symbol
+    class_of: HashMap<SymbolId, usize>,
+    // the classes whose methods are being analyzed, innermost last
+    this: Vec<usize>,
+    // the `this.name = value` nodes that declare a field
+    field_nodes: HashSet<NodeId>,
+}
+
+// A name bound in a scope. Opaque bindings, such as the names a `match`
+// pattern binds or `result` in an `ensures` clause, hide outer symbols
+// without being indexed.
+#[derive(Clone, Copy)]
+enum Binding {
+    Symbol(SymbolId, usize),
+    Opaque,
+}
+
+impl Analyzer<'_> {
+    fn scope(&mut self, parent: Option<usize>) -> usize {
+        self.scopes.push(HashMap::new());
+        self.index.parents.push(parent);
+        self.scopes.len() - 1
+    }
+
+    fn symbol(
+        &mut self,
+        name: &str,
+        kind: SymbolKind,
+        node: NodeId,
+        slot: Option<usize>,
+        span: &Span,
+        owner: Owner,
+    ) -> SymbolId {
+        if let Some(id) = self.declared.get(&(node, slot)) {
+            return *id;
+        }
+        let id = SymbolId(self.index.symbols.len());
+        self.index.symbols.push(Symbol {
+            id,
+            name: name.to_string(),
+            kind,
+            node,
+            span: span.clone(),
+            references: Vec::new(),
+            slot,
+            owner,
+        });
+        self.declared.insert((node, slot), id);
+        if let Owner::Scope(scope) = owner {
+            let bindings = self.scopes[scope].entry(name.to_string()).or_default();
+            bindings.push(Binding::Symbol(id, span.start));
+        }
+        id
+    }
+
+    // The span of the `slot`th name `node` binds, or `fallback` when the
+    // parser left none.
+    fn name_span(&self, node: NodeId, slot: usize, fallback: &Span) -> Span {
+        let spans = self.program.name_spans(node);
+        spans.get(slot).unwrap_or(fallback).clone()
+    }
+
+    // Declares what `stmt` binds in `scope`, the scope it runs in.
+    fn declare(&mut self, stmt: &Stmt, scope: usize) {
+        let owner = Owner::Scope(scope);
+        match stmt {
+            Stmt::Let {
+                id,
+                name,
+                is_const,
+                span,
+                ..
+            } => {
+                let kind = if *is_const {
+                    SymbolKind::Const
+                } else {
+                    SymbolKind::Let
+                };
+                self.symbol(name, kind, *id, None, span, owner);
+            }
+            Stmt::Destructure {
+                id,
+                names,
+                is_const,
+                span,
+                ..
+            } => {
+                let kind = if *is_const {
+                    SymbolKind::Const
+                } else {
+                    SymbolKind::Let
+                };
+                for (slot, name) in names.iter().enumerate() {
+                    let span = self.name_span(*id, slot, span);
+                    self.symbol(name, kind, *id, Some(slot), &span, owner);
+                }
+            }
+            Stmt::Function { id, name, span, .. } => {
+                self.symbol(name, SymbolKind::Function, *id, None, span, owner);
+            }
+            Stmt::Class { id, name, span, .. } => {
+                self.symbol(name, SymbolKind::Class, *id, None, span, owner);
+            }
+            _ => {}
+        }
+    }
+
+    // Runs `stmts` in `scope`, with every declaration among them made first
+    // so that function bodies see the ones further down.
+    fn stmts(&mut self, stmts: &[Stmt], scope: usize) {
+        for stmt in stmts {
+            self.declare(stmt, scope);
+        }
+        for stmt in stmts {
+            self.stmt(stmt, scope);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, scope: usize) {
+        // a declaration that is the branch of an `if` or the body of a loop
+        // was not declared with a list of statements
+        self.declare(stmt, scope);
+        match stmt {
+            Stmt::Expr { expr, .. } | Stmt::Destructure { value: expr, .. } => {
+                self.expr(expr, scope)
+            }
+            Stmt::Print { exprs, .. } => exprs.iter().for_each(|expr| self.expr(expr, scope)),
+            Stmt::Let { value, .. } | Stmt::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.expr(value, scope);
+                }
+            }
+            Stmt::Block { stmts, .. } => {
+                let inner = self.scope(Some(scope));
+                self.stmts(stmts, inner);
+            }
+            Stmt::If {
+                condition,
+                truthy,
+                falsy,
+                ..
+            } => {
+                self.expr(condition, scope);
+                self.stmt(truthy, scope);
+                if let Some(falsy) = falsy {
+                    self.stmt(falsy, scope);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.expr(condition, scope);
+                self.stmt(body, scope);
+            }
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                let inner = self.scope(Some(scope));
+                if let Some(initializer) = initializer {
+                    self.stmt(initializer, inner);
+                }
+                for expr in condition.iter().chain(increment) {
+                    self.expr(expr, inner);
+                }
+                self.stmt(body, inner);
+            }
+            Stmt::ForRange {
+                initializer,
+                end,
+                step,
+                body,
+                ..
+            } => {
+                let inner = self.scope(Some(scope));
+                self.stmt(initializer, inner);
+                for expr in std::iter::once(end).chain(step) {
+                    self.expr(expr, inner);
+                }
+                self.stmt(body, inner);
+            }
+            Stmt::Function {
+                id,
+                params,
+                requires,
+                ensures,
+                body,
+                span,
+                ..
+            } => self.function(*id, params, requires, ensures, body, span, scope),
+            Stmt::Class {
+                id,
+                super_class,
+                mixins,
+                methods,
+                fields,
+                private,
+                ..
+            } => {
+                let symbol = self.declared.get(&(*id, None)).copied();
+                let super_class = super_class.as_ref();
+                let class = self.class(*id, super_class, mixins, methods, fields, private, scope);
+                if let Some(symbol) = symbol {
+                    self.class_of.insert(symbol, class);
+                }
+            }
+            Stmt::Defer { body, .. } => self.stmt(body, scope),
+            Stmt::Break { .. }
+            | Stmt::Continue { .. }
+            | Stmt::Import { .. }
+            | Stmt::Empty { .. }
+            | Stmt::Error { .. } => {}
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn function(
+        &mut self,
+        id: NodeId,
+        params: &[String],
+        requires: &[Expr],
+        ensures: &[Expr],
+        body: &[Stmt],
+        span: &Span,
+        scope: usize,
+    ) {
+        let inner = self.scope(Some(scope));
+        for (slot, param) in params.iter().enumerate() {
+            let span = self.name_span(id, slot, span);
+            let owner = Owner::Scope(inner);
+            self.symbol(param, SymbolKind::Param, id, Some(slot), &span, owner);
+        }
+        for stmt in body {
+            self.declare(stmt, inner);
+        }
+        requires.iter().for_each(|expr| self.expr(expr, inner));
+        body.iter().for_each(|stmt| self.stmt(stmt, inner));
+        let clauses = self.scope(Some(inner));
+        self.scopes[clauses].insert("result".to_string(), vec![Binding::Opaque]);
+        ensures.iter().for_each(|expr| self.expr(expr, clauses));
+    }
+
+    // Indexes the members of a class declared or written as an expression
+    // at `id`, then its field initializers and methods, returning the class.
+    #[allow(clippy::too_many_arguments)]
+    fn class(
+        &mut self,
+        id: NodeId,
+        super_class: Option<&Expr>,
+        mixins: &[Expr],
+        methods: &[Stmt],
+        fields: &[FieldInit],
+        private: &[String],
+        scope: usize,
+    ) -> usize {
+        for expr in super_class.into_iter().chain(mixins) {
+            self.expr(expr, scope);
+        }
+        let super_class = match super_class {
+            Some(Expr::Variable { id, .. }) => match self.index.uses.get(id) {
+                Some((_, Some(symbol))) => self.class_of.get(symbol).copied(),
+                _ => None,
+            },
+            _ => None,
+        };
+        let class = self.index.classes.len();
+        self.index.classes.push(ClassInfo {
+            members: HashMap::new(),
+            super_class,
+        });
+        let owner = Owner::Class(class);
+
+        let mut members = HashMap::new();
+        for method in methods {
+            if let Stmt::Function { id, name, span, .. } = method {
+                let symbol = self.symbol(name, SymbolKind::Method, *id, None, span, owner);
+                members.insert(name.clone(), symbol);
+            }
+        }
+        for (slot, name) in private.iter().enumerate() {
+            if !members.contains_key(name) {
+                let span = self.name_span(id, slot, &Span::default());
+                let symbol = self.symbol(name, SymbolKind::Field, id, Some(slot), &span, owner);
+                members.insert(name.clone(), symbol);
+            }
+        }
+        for field in fields {
+            match members.get(&field.name) {
+                // a private field, named again where it is initialized
+                Some(symbol) => {
+                    let symbol = *symbol;
+                    self.reference(field.id, &field.span, Some(symbol));
+                }
+                None => {
+                    let kind = SymbolKind::Field;
+                    let symbol = self.symbol(&field.name, kind, field.id, None, &field.span, owner);
+                    members.insert(field.name.clone(), symbol);
+                }
+            }
+        }
+        let mut sets = Vec::new();
+        for method in methods {
+            if let Stmt::Function { body, .. } = method {
+                body.iter().for_each(|stmt| field_sets(stmt, &mut sets));
+            }
+        }
+        for (node, name, span) in sets {
+            if !members.contains_key(name) {
+                let symbol = self.symbol(name, SymbolKind::Field, node, None, span, owner);
+                members.insert(name.clone(), symbol);
+                self.field_nodes.insert(node);
+            }
+        }
+        self.index.classes[class].members = members;
+
+        self.this.push(class);
+        for field in fields {
+            // as a method body would, in a scope of its own
+            let inner = self.scope(Some(scope));
+            self.expr(&field.value, inner);
+        }
+        for method in methods {
+            if let Stmt::Function {
+                id,
+                params,
+                requires,
+                ensures,
+                body,
+                span,
+                ..
+            } = method
+            {
+                self.function(*id, params, requires, ensures, body, span, scope);
+            }
+        }
+        self.this.pop();
+        class
+    }
+
+    fn expr(&mut self, expr: &Expr, scope: usize) {
+        match expr {
+            Expr::Variable { id, name, span } => self.variable(*id, name, span, scope),
+            Expr::Assign {
+                id,
+                name,
+                value,
+                span,
+            } => {
+                self.expr(value, scope);
+                self.variable(*id, name, span, scope);
+            }
+            Expr::Get {
+                id,
+                object,
+                name,
+                span,
+            } => {
+                self.expr(object, scope);
+                if object.is_this() {
+                    self.member(*id, name, span, self.this.last().copied());
+                } else {
+                    self.index.outside.push((name.clone(), span.clone()));
+                }
+            }
+            Expr::Set {
+                id,
+                object,
+                name,
+                value,
+                span,
+                ..
+            } => {
+                self.expr(value, scope);
+                self.expr(object, scope);
+                if !object.is_this() {
+                    self.index.outside.push((name.clone(), span.clone()));
+                } else if !self.field_nodes.contains(id) {
+                    self.member(*id, name, span, self.this.last().copied());
+                }
+            }
+            Expr::Super { id, name, span } => {
+                let class = self.this.last().copied();
+                let super_class = class.and_then(|class| self.index.classes[class].super_class);
+                self.member(*id, name, span, super_class);
+            }
+            Expr::Class {
+                id,
+                super_class,
+                mixins,
+                methods,
+                fields,
+                private,
+                ..
+            } => {
+                let super_class = super_class.as_deref();
+                self.class(*id, super_class, mixins, methods, fields, private, scope);
+            }
+            Expr::Match { value, arms, .. } => {
+                self.expr(value, scope);
+                for arm in arms {
+                    let inner = self.scope(Some(scope));
+                    for name in arm.pattern.names() {
+                        self.scopes[inner].insert(name.to_string(), vec![Binding::Opaque]);
+                    }
+                    for expr in arm.guard.iter().chain([&arm.body]) {
+                        self.expr(expr, inner);
+                    }
+                }
+            }
+            _ => {
+                for child in expr.children() {
+                    self.expr(child, scope);
+                }
+            }
+        }
+    }
+
+    fn variable(&mut self, id: NodeId, name: &str, span: &Span, scope: usize) {
+        let mut next = Some(scope);
+        let mut target = None;
+        while let Some(scope) = next {
+            if let Some(bindings) = self.scopes[scope].get(name) {
+                let before = bindings.iter().rev().find(|binding| match binding {
+                    Binding::Symbol(_, start) => *start <= span.start,
+                    Binding::Opaque => true,
+                });
+                target = match before.or(bindings.first()) {
+                    Some(Binding::Symbol(symbol, _)) => Some(*symbol),
+                    _ => None,
+                };
+                break;
+            }
+            next = self.index.parents[scope];
+        }
+        self.reference(id, span, target);
+    }
+
+    fn member(&mut self, id: NodeId, name: &str, span: &Span, class: Option<usize>) {
+        let target = class.and_then(|class| self.index.member(class, name));
+        self.reference(id, span, target);
+    }
+
+    fn reference(&mut self, id: NodeId, span: &Span, target: Option<SymbolId>) {
+        self.index.uses.insert(id, (span.clone(), target));
+        if let Some(symbol) = target {
+            let reference = Reference {
+                node: id,
+                span: span.clone(),
+            };
+            self.index.symbols[symbol.0].references.push(reference);
+        }
+    }
+}
+
+// The `this.name = value` nodes in a method, in order, leaving out those in
+// classes written inside it.
+fn field_sets<'a>(stmt: &'a Stmt, out: &mut Vec<(NodeId, &'a String, &'a Span)>) {
+    fn expr<'a>(e: &'a Expr, out: &mut Vec<(NodeId, &'a String, &'a Span)>) {
+        if let Expr::Set {
+            id,
+            object,
+            name,
+            span,
+            ..
+        } = e
+        {
+            if object.is_this() {
+                out.push((*id, name, span));
+            }
+        }
+        if !matches!(e, Expr::Class { .. }) {
+            e.children().into_iter().for_each(|child| expr(child, out));
+        }
+    }
+    match stmt {
+        Stmt::Expr { expr: e, .. } | Stmt::Destructure { value: e, .. } => expr(e, out),
+        Stmt::Print { exprs, .. } => exprs.iter().for_each(|e| expr(e, out)),
+        Stmt::Let { value, .. } | Stmt::Return { value, .. } => {
+            value.iter().for_each(|e| expr(e, out))
+        }
+        Stmt::Block { stmts, .. } | Stmt::Function { body: stmts, .. } => {
+            stmts.iter().for_each(|stmt| field_sets(stmt, out))
+        }
+        Stmt::If {
+            condition,
+            truthy,
+            falsy,
+            ..
+        } => {
+            expr(condition, out);
+            field_sets(truthy, out);
+            falsy.iter().for_each(|stmt| field_sets(stmt, out));
+        }
+        Stmt::While {
+            condition, body, ..
+        } => {
+            expr(condition, out);
+            field_sets(body, out);
+        }
+        Stmt::For {
+            initializer,
+            condition,
+            increment,
+            body,
+            ..
+        } => {
+            initializer.iter().for_each(|stmt| field_sets(stmt, out));
+            condition.iter().chain(increment).for_each(|e| expr(e, out));
+            field_sets(body, out);
+        }
+        Stmt::ForRange {
+            initializer,
+            end,
+            step,
+            body,
+            ..
+        } => {
+            field_sets(initializer, out);
+            std::iter::once(end).chain(step).for_each(|e| expr(e, out));
+            field_sets(body, out);
+        }
+        Stmt::Defer { body, .. } => field_sets(body, out),
+        Stmt::Class { .. }
+        | Stmt::Break { .. }
+        | Stmt::Continue { .. }
+        | Stmt::Import { .. }
+        | Stmt::Empty { .. }
+        | Stmt::Error { .. } => {}
+    }
+}
+
+// The nodes `rename` rewrites, with the name they get.
+struct Renames<'a> {
+    // nodes whose one name is the symbol's
+    nodes: HashSet<NodeId>,
+    // nodes binding several names, with which of them is the symbol's
+    slots: HashMap<NodeId, usize>,
+    name: &'a str,
+}
+
+impl Renames<'_> {
+    fn rename(&self, id: NodeId, name: &mut String) {
+        if self.nodes.contains(&id) {
+            *name = self.name.to_string();
+        }
+    }
+
+    fn rename_slot(&self, id: NodeId, names: &mut [String]) {
+        if let Some(slot) = self.slots.get(&id) {
+            names[*slot] = self.name.to_string();
+        }
+    }
+
+    fn fields(&self, fields: &mut [FieldInit]) {
+        for field in fields {
+            self.rename(field.id, &mut field.name);
+            self.expr(&mut field.value);
+        }
+    }
+
+    fn stmt(&self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Expr { expr, .. } => self.expr(expr),
+            Stmt::Print { exprs, .. } => exprs.iter_mut().for_each(|expr| self.expr(expr)),
+            Stmt::Let {
+                id, name, value, ..
+            } => {
+                self.rename(*id, name);
+                value.iter_mut().for_each(|expr| self.expr(expr));
+            }
+            Stmt::Destructure {
+                id, names, value, ..
+            } => {
+                self.rename_slot(*id, names);
+                self.expr(value);
+            }
+            Stmt::Block { stmts, .. } => stmts.iter_mut().for_each(|stmt| self.stmt(stmt)),
+            Stmt::If {
+                condition,
+                truthy,
+                falsy,
+                ..
+            } => {
+                self.expr(condition);
+                self.stmt(truthy);
+                falsy.iter_mut().for_each(|stmt| self.stmt(stmt));
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.expr(condition);
+                self.stmt(body);
+            }
+            Stmt::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                initializer.iter_mut().for_each(|stmt| self.stmt(stmt));
+                condition
+                    .iter_mut()
+                    .chain(increment)
+                    .for_each(|expr| self.expr(expr));
+                self.stmt(body);
+            }
+            Stmt::ForRange {
+                initializer,
+                end,
+                step,
+                body,
+                ..
+            } => {
+                self.stmt(initializer);
+                std::iter::once(end)
+                    .chain(step)
+                    .for_each(|expr| self.expr(expr));
+                self.stmt(body);
+            }
+            Stmt::Function {
+                id,
+                name,
+                params,
+                requires,
+                ensures,
+                body,
+                ..
+            } => {
+                self.rename(*id, name);
+                self.rename_slot(*id, params);
+                requires
+                    .iter_mut()
+                    .chain(ensures)
+                    .for_each(|expr| self.expr(expr));
+                body.iter_mut().for_each(|stmt| self.stmt(stmt));
+            }
+            Stmt::Return { value, .. } => value.iter_mut().for_each(|expr| self.expr(expr)),
+            Stmt::Class {
+                id,
+                name,
+                super_class,
+                mixins,
+                methods,
+                fields,
+                private,
+                ..
+            } => {
+                self.rename(*id, name);
+                self.rename_slot(*id, private);
+                super_class
+                    .iter_mut()
+                    .chain(mixins)
+                    .for_each(|expr| self.expr(expr));
+                methods.iter_mut().for_each(|stmt| self.stmt(stmt));
+                self.fields(fields);
+            }
+            Stmt::Defer { body, .. } => self.stmt(body),
+            Stmt::Break { .. }
+            | Stmt::Continue { .. }
+            | Stmt::Import { .. }
+            | Stmt::Empty { .. }
+            | Stmt::Error { .. } => {}
+        }
+    }
+
+    fn expr(&self, expr: &mut Expr) {
+        match expr {
+            Expr::Variable { id, name, .. }
+            | Expr::Assign { id, name, .. }
+            | Expr::Get { id, name, .. }
+            | Expr::Set { id, name, .. }
+            | Expr::Super { id, name, .. } => self.rename(*id, name),
+            Expr::Class {
+                id,
+                methods,
+                fields,
+                private,
+                ..
+            } => {
+                self.rename_slot(*id, private);
+                methods.iter_mut().for_each(|stmt| self.stmt(stmt));
+                self.fields(fields);
+            }
+            _ => {}
+        }
+        for child in expr.children_mut() {
+            self.expr(child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{analyze, rename, SymbolId, SymbolIndex, SymbolKind};
+    use crate::{Error, Lexer, Parser, Program, Resolver};
+
+    fn parse(input: &str) -> Program {
+        Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap()
+    }
+
+    // The symbol declared or referenced at the `nth` occurrence of the word
+    // `name` in `source`.
+    fn find(index: &SymbolIndex, source: &str, name: &str, nth: usize) -> SymbolId {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let offset = source
+            .match_indices(name)
+            .filter(|(offset, _)| {
+                let before = source[..*offset].chars().next_back();
+                let after = source[offset + name.len()..].chars().next();
+                !before.is_some_and(is_word) && !after.is_some_and(is_word)
+            })
+            .nth(nth)
+            .map(|(offset, _)| offset)
+            .unwrap();
+        let symbol = index.declaration_at(offset).unwrap();
+        assert_eq!(symbol.name, name);
+        symbol.id
+    }
+
+    fn offsets(index: &SymbolIndex, id: SymbolId) -> Vec<usize> {
+        let references = index.references_of(id);
+        references.iter().map(|r| r.span.start).collect()
+    }
+
+    #[test]
+    fn test_kinds() {
+        let source = "let a = 1;\nconst b = 2;\nlet (c, d) = (1, 2);\nfn f(p, q) { return p; }\n\
+                      class K { priv secret; init() { this.n = 1; } get() { return this.n; } }";
+        let index = analyze(&parse(source));
+        let kinds: Vec<_> = index
+            .symbols()
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("a", SymbolKind::Let),
+                ("b", SymbolKind::Const),
+                ("c", SymbolKind::Let),
+                ("d", SymbolKind::Let),
+                ("f", SymbolKind::Function),
+                ("K", SymbolKind::Class),
+                ("p", SymbolKind::Param),
+                ("q", SymbolKind::Param),
+                ("init", SymbolKind::Method),
+                ("get", SymbolKind::Method),
+                ("secret", SymbolKind::Field),
+                ("n", SymbolKind::Field),
+            ]
+        );
+        // every declaration points at its name
+        for symbol in index.symbols() {
+            let span = &symbol.span;
+            assert_eq!(&source[span.start..span.end], symbol.name, "{symbol:?}");
+        }
+        let n = find(&index, source, "n", 1);
+        assert_eq!(offsets(&index, n), [source.rfind(".n").unwrap() + 1]);
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let source = "let x = 1;\nfn f(y) {\n  print x;\n  { let x = 2; print x + y; x = 3; }\n  return x;\n}\nprint x;";
+        let index = analyze(&parse(source));
+        let outer = find(&index, source, "x", 0);
+        let inner = find(&index, source, "x", 2);
+        assert_ne!(outer, inner);
+        let at = |line: usize, column: usize| -> usize {
+            source
+                .lines()
+                .take(line - 1)
+                .map(|l| l.len() + 1)
+                .sum::<usize>()
+                + column
+                - 1
+        };
+        assert_eq!(offsets(&index, outer), [at(3, 9), at(5, 10), at(7, 7)]);
+        assert_eq!(offsets(&index, inner), [at(4, 22), at(4, 29)]);
+        // a reference leads back to its declaration
+        assert_eq!(index.declaration_at(at(4, 29)).unwrap().id, inner);
+        assert_eq!(index.declaration_at(at(5, 10)).unwrap().id, outer);
+        assert!(index
+            .declaration_at(source.find("print").unwrap())
+            .is_none());
+
+        // names bound by a pattern hide outer ones without being symbols
+        let source = "let v = 1;\nprint match (2) { 1 => v, v => v };";
+        let index = analyze(&parse(source));
+        let v = find(&index, source, "v", 0);
+        assert_eq!(offsets(&index, v), [source.find("=> v").unwrap() + 3]);
+    }
+
+    #[test]
+    fn test_members() {
+        let source = "class A { init() { this.n = 0; } bump() { this.n = this.n + 1; return this.bump; } }\n\
+                      class B < A { bump() { return super.bump(); } twice() { this.bump(); } }";
+        let index = analyze(&parse(source));
+        let bumps: Vec<_> = index
+            .symbols()
+            .iter()
+            .filter(|symbol| symbol.name == "bump")
+            .map(|symbol| symbol.id)
+            .collect();
+        let [a_bump, b_bump] = bumps[..] else {
+            panic!("{bumps:?}");
+        };
+        // `super.bump` is A's, and `this.bump` in B is B's own
+        assert_eq!(index.references_of(a_bump).len(), 2);
+        assert_eq!(index.references_of(b_bump).len(), 1);
+        let n = find(&index, source, "n", 0);
+        assert_eq!(index.references_of(n).len(), 2);
+    }
+
+    #[test]
+    fn test_rename() {
+        let source =
+            "let x = 1;\nfn f(y) {\n  { let x = 2; print x + y; }\n  return x + y;\n}\nprint f(x);";
+        let program = parse(source);
+        let index = analyze(&program);
+        let outer = find(&index, source, "x", 0);
+        let renamed = rename(&program, outer, "total").unwrap();
+        assert_eq!(
+            renamed.to_string(),
+            "((let total 1) (fn f (y) (block (let x 2) (print (+ x y))) (return (+ total y))) \
+             (print (call f total)))"
+        );
+        assert_eq!(Resolver::new().resolve(&renamed), Ok(()));
+
+        let y = find(&index, source, "y", 0);
+        let renamed = rename(&program, y, "n").unwrap();
+        assert!(renamed
+            .to_string()
+            .contains("(fn f (n) (block (let x 2) (print (+ x n)))"));
+        assert_eq!(Resolver::new().resolve(&renamed), Ok(()));
+
+        // members are renamed with every `this.name`
+        let source =
+            "class A { init() { this.n = 0; } get() { return this.n; } }\nprint A().get();";
+        let program = parse(source);
+        let n = find(&analyze(&program), source, "n", 0);
+        let renamed = rename(&program, n, "count").unwrap();
+        assert_eq!(
+            renamed.to_string(),
+            "((class A (fn init () (set this count 0)) (fn get () (return (get this count)))) \
+             (print (call (get (call A) get))))"
+        );
+    }
+
+    #[test]
+    fn test_rename_refused() {
+        let source = "let a = 1;\nlet b = 2;\nfn f(y) {\n  let a = 3;\n  return a + b + y;\n}";
+        let program = parse(source);
+        let index = analyze(&program);
+        let message = |result: Result<Program, crate::ErrorInfo>| match result.unwrap_err().error {
+            Error::Resolve(message) | Error::Value(message) => message,
+            error => panic!("{error:?}"),
+        };
+
+        // another `a` is declared in the same scope
+        let b = find(&index, source, "b", 0);
+        assert_eq!(
+            message(rename(&program, b, "a")),
+            "cannot rename \"b\" to \"a\", which is already declared here"
+        );
+        let y = find(&index, source, "y", 0);
+        let err = rename(&program, y, "a").unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Resolve(
+                "cannot rename \"y\" to \"a\", which is already declared here".to_string()
+            )
+        );
+        // the local `a` would capture the reference to the global `b`
+        let local = find(&index, source, "a", 1);
+        let err = rename(&program, local, "b").unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Resolve(
+                "renaming \"a\" to \"b\" would change what the name here refers to".to_string()
+            )
+        );
+        assert_eq!(err.line(), 5);
+        // the global `a` would hide the built-in
+        let a = find(&index, source, "a", 0);
+        assert!(message(rename(&program, a, "fields")).contains("shadow the built-in"));
+        assert!(message(rename(&program, a, "1x")).contains("not a valid name"));
+        assert!(message(rename(&program, a, "while")).contains("not a valid name"));
+        // a name that changes nothing is fine
+        assert_eq!(rename(&program, a, "a").unwrap(), program);
+
+        // `a.m()` might call the method, so it cannot be left behind
+        let source =
+            "class A { m() { return 1; } n() { return this.m(); } }\nlet a = A();\nprint a.m();";
+        let program = parse(source);
+        let m = find(&analyze(&program), source, "m", 0);
+        let err = rename(&program, m, "k").unwrap_err();
+        assert_eq!(
+            err.error,
+            Error::Resolve("cannot rename \"m\", which is used on another object here".to_string())
+        );
+        assert_eq!(err.line(), 3);
+    }
+}
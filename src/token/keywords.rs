@@ -2,10 +2,20 @@
 use crate::TokenType;
 
 
-pub fn lookup_identifier(ident: String) -> TokenType {
+/// The longest keyword, in bytes.
+pub const MAX_KEYWORD_LEN: usize = 8;
+
+/// Every keyword, as `lookup_keyword` knows them.
+pub const KEYWORDS: [&str; 23] = [
+    "true", "false", "fn", "let", "else", "if", "for", "while", "return", "import", "nil", "class",
+    "this", "break", "continue", "super", "print", "const", "defer", "match", "priv", "in",
+    "export",
+];
+
+pub fn lookup_keyword(ident: &str) -> Option<TokenType> {
     // 1. the language is case sensitive
     // 2. all the keywords have full lowercase
-    match ident.as_str() {
+    let keyword = match ident {
         "true" => TokenType::True,
         "false" => TokenType::False,
         "fn" => TokenType::Function,
@@ -24,14 +34,20 @@ pub fn lookup_identifier(ident: String) -> TokenType {
         "super" => TokenType::Super,
         "print" => TokenType::Print,
         "const" => TokenType::Const,
-        _ => TokenType::Identifier(ident),
-    }
+        "defer" => TokenType::Defer,
+        "match" => TokenType::Match,
+        "priv" => TokenType::Priv,
+        "in" => TokenType::In,
+        "export" => TokenType::Export,
+        _ => return None,
+    };
+    Some(keyword)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{TokenType};
-    use super::lookup_identifier;
+    use super::{lookup_keyword, KEYWORDS};
 
 
     #[test]
@@ -57,12 +73,18 @@ mod tests {
             ("print", TokenType::Print),
             ("super", TokenType::Super),
             ("const", TokenType::Const),
+            ("defer", TokenType::Defer),
+            ("match", TokenType::Match),
+            ("priv", TokenType::Priv),
+            ("in", TokenType::In),
+            ("export", TokenType::Export),
 
         ];
-        is_keyword.iter().for_each(|x| {
-            let ident = x.0.to_string();
-            assert_eq!(lookup_identifier(ident), x.1)
-        });
+        assert_eq!(is_keyword.len(), KEYWORDS.len());
+        is_keyword
+            .into_iter()
+            .for_each(|x| assert_eq!(lookup_keyword(x.0), Some(x.1)));
+        assert!(KEYWORDS.iter().all(|word| lookup_keyword(word).is_some()));
     }
 
     #[test]
@@ -83,8 +105,7 @@ mod tests {
         ];
         is_keyword
             .iter()
-            .map(|x| (x.to_string(), TokenType::Identifier(x.to_string())))
-            .for_each(|x| assert_eq!(lookup_identifier(x.0), x.1));
+            .for_each(|x| assert_eq!(lookup_keyword(x), None));
     }
 }
 
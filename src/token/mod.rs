@@ -7,5 +7,8 @@ pub use token_type::TokenType;
 mod token_info;
 pub use token_info::TokenInfo;
 
+mod trivia;
+pub use trivia::{Trivia, TriviaKind};
+
 mod keywords;
-pub use keywords::lookup_identifier;
+pub use keywords::{lookup_keyword, KEYWORDS, MAX_KEYWORD_LEN};
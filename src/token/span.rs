@@ -1,6 +1,6 @@
 use std::cmp;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct Span {
     pub line: usize,
     pub line_start: usize,
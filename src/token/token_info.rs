@@ -1,9 +1,14 @@
-use crate::{TokenType, Span};
+use crate::{TokenType, Span, Trivia};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct TokenInfo {
     pub token: TokenType,
     pub span: Span,
+    /// The comments and blank lines before the token, in order. Only a lexer
+    /// in `LexerMode::WithTrivia` fills this in. A comment after code on
+    /// the same line belongs to the next token too, so trivia at the end of
+    /// the file goes to the `Eof` token.
+    pub leading_trivia: Vec<Trivia>,
 }
 
 impl TokenInfo {
@@ -11,7 +16,8 @@ impl TokenInfo {
        let span = Span {start, end, line, line_start };
         Self {
             token,
-            span
+            span,
+            leading_trivia: Vec::new(),
         }
     }
     pub fn is(&self, token: TokenType) -> bool {
@@ -2,8 +2,9 @@ use std::fmt;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
-    Identifier(String), // variable
-    String(String),     /* data types */
+    Identifier(String),  // variable
+    Placeholder(String), // `@{name}`, filled in by the host
+    String(String),      /* data types */
     Number(f64),
     True,
     False,
@@ -32,6 +33,7 @@ pub enum TokenType {
     Mod,
     LShift,
     RShift,
+    URShift,
     And, /* logical operator */
     Or,
     Not,
@@ -51,6 +53,9 @@ pub enum TokenType {
     OrEq,
     XorEq,
     ModEq,
+    LShiftEq,
+    RShiftEq,
+    URShiftEq,
     LogicalAnd,
     LogicalOr,
     Import,
@@ -62,6 +67,13 @@ pub enum TokenType {
     Print,
     Dot,
     Super,
+    Defer,
+    Match,
+    FatArrow,
+    Pipe,
+    Priv,
+    In,
+    Export,
 }
 
 impl fmt::Display for TokenType {
@@ -71,6 +83,7 @@ impl fmt::Display for TokenType {
 
         match self {
             Identifier(x) => write!(f, "{}", x),
+            Placeholder(x) => write!(f, "@{{{}}}", x),
             String(x)     => write!(f, "\"{}\"", x),
             Number(x)        => write!(f, "{}", x),
             True          => write!(f, "True"),
@@ -117,10 +130,14 @@ impl fmt::Display for TokenType {
             Lt            => write!(f, "<"),
             Eq            => write!(f, "=="),
             Ne            => write!(f, "!="),
-            Lte           => write!(f, "=<"),
+            Lte           => write!(f, "<="),
             Gte           => write!(f, ">="),
             LShift        => write!(f, "<<"),
             RShift        => write!(f, ">>"),
+            URShift       => write!(f, ">>>"),
+            LShiftEq      => write!(f, "<<="),
+            RShiftEq      => write!(f, ">>="),
+            URShiftEq     => write!(f, ">>>="),
             Eof           => write!(f, "EOF"),
             Import        => write!(f, "import"),
             Class         => write!(f, "class"),
@@ -130,6 +147,13 @@ impl fmt::Display for TokenType {
             Continue      => write!(f, "continue"),
             Print         => write!(f, "print"),
             Super         => write!(f, "super"),
+            Defer         => write!(f, "defer"),
+            Match         => write!(f, "match"),
+            FatArrow      => write!(f, "=>"),
+            Pipe          => write!(f, "|>"),
+            Priv          => write!(f, "priv"),
+            In            => write!(f, "in"),
+            Export        => write!(f, "export"),
 
         }
     }
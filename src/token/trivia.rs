@@ -0,0 +1,17 @@
+use crate::Span;
+
+/// Source text between tokens that the parser never sees, kept by a lexer
+/// in `LexerMode::WithTrivia` for tools that rewrite source.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TriviaKind {
+    /// A `#` comment as written, `#` included, without its line break.
+    Comment(String),
+    /// A run of lines holding nothing but whitespace, and how many.
+    BlankLines(usize),
+}
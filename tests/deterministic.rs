@@ -0,0 +1,64 @@
+//! Runs one script several times to check that deterministic mode makes its
+//! output the same on every run, and that without it the clock shows through.
+
+use rlisp::{run_source, RunOptions};
+
+const SCRIPT: &str = r#"
+class Job {
+  init(name, cost) { this.name = name; this.cost = cost; this.done = false; }
+  run() { sleep(this.cost); this.done = true; return this.cost; }
+}
+class Report < Job {
+  run() { print "report " + this.name; return super.run(); }
+}
+
+let jobs = (Job("build", 12), Report("lint", 3), Job("test", 40));
+let start = time();
+let i = 0;
+while (i < jobs.len()) {
+  let job = jobs[i];
+  print (job.name, time("  ran", job.run), fields(job), methods(job));
+  i = i + 1;
+}
+print time() - start;
+"#;
+
+fn run(deterministic: bool) -> String {
+    let options = RunOptions {
+        capture_output: true,
+        deterministic,
+        ..RunOptions::default()
+    };
+    let outcome = run_source(SCRIPT, options).unwrap_or_else(|err| panic!("{err:?}"));
+    outcome.output.unwrap()
+}
+
+#[test]
+fn deterministic_runs_match() {
+    let first = run(true);
+    assert_eq!(first, run(true));
+    assert_eq!(
+        first,
+        "  ran: 12ms
+build 12 (cost, done, name) ((init, false), (run, false))
+report lint
+  ran: 3ms
+lint 3 (cost, done, name) ((init, true), (run, false))
+  ran: 40ms
+test 40 (cost, done, name) ((init, false), (run, false))
+55000000
+"
+    );
+}
+
+#[test]
+fn system_clock_shows_through() {
+    // the first reading of the system clock is far past 0
+    let options = RunOptions {
+        capture_output: true,
+        ..RunOptions::default()
+    };
+    let normal = run_source("print time() > 1000000000;", options).unwrap();
+    assert_eq!(normal.output.as_deref(), Some("true\n"));
+    assert_ne!(run(false), run(true));
+}
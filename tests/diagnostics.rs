@@ -0,0 +1,42 @@
+//! Snapshots of rendered errors: each script in `tests/diagnostics` fails,
+//! and its error, shown with the source it points at, must read as the
+//! `.txt` file of the same name.
+
+use std::fs;
+use std::path::Path;
+
+use rlisp::{run_source, Diagnostic, RunOptions, Snippet};
+
+#[test]
+fn rendered_errors() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/diagnostics");
+    let mut scripts: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lang"))
+        .collect();
+    scripts.sort();
+    assert!(!scripts.is_empty());
+
+    let mut stale = Vec::new();
+    for script in scripts {
+        let source = fs::read_to_string(&script).unwrap();
+        let options = RunOptions {
+            capture_output: true,
+            ..RunOptions::default()
+        };
+        let diagnostics = run_source(&source, options).err().unwrap_or_default();
+        let rendered = match diagnostics.iter().find(|diagnostic| diagnostic.is_error()) {
+            Some(Diagnostic::Error(err)) => err.render(Some(&Snippet::new(&source))),
+            _ => "no error\n".to_string(),
+        };
+        let snapshot = script.with_extension("txt");
+        if fs::read_to_string(&snapshot).unwrap_or_default() != rendered {
+            let name = snapshot.file_name().unwrap().to_string_lossy().into_owned();
+            stale.push(format!(
+                "{name} is out of date, it should read:\n{rendered}"
+            ));
+        }
+    }
+    assert!(stale.is_empty(), "{}", stale.join("\n"));
+}
@@ -0,0 +1,78 @@
+//! Runs the CLI on the scripts under `tests/entry/`, which exercise the
+//! `main` a script may define.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlisp"))
+        .args(args)
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/entry"))
+        .output()
+        .expect("the binary runs")
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[test]
+fn main_return_is_exit_code() {
+    let output = cli(&["exit_code.lang", "one", "two"]);
+    assert_eq!(output.status.code(), Some(42), "{}", text(&output.stderr));
+    assert_eq!(
+        text(&output.stdout),
+        "top level runs first\n(one, two)\n"
+    );
+
+    let output = cli(&["run", "exit_code.lang"]);
+    assert_eq!(output.status.code(), Some(40));
+    assert_eq!(text(&output.stdout), "top level runs first\n()\n");
+}
+
+#[test]
+fn main_without_exit_code() {
+    // takes no arguments, so the script's are dropped, and returns a string
+    let output = cli(&["no_params.lang", "ignored"]);
+    assert_eq!(output.status.code(), Some(0), "{}", text(&output.stderr));
+    assert_eq!(text(&output.stdout), "main without arguments\n");
+
+    // a `main` that is not a function is an ordinary global
+    let output = cli(&["no_main.lang"]);
+    assert_eq!(output.status.code(), Some(0), "{}", text(&output.stderr));
+    assert_eq!(text(&output.stdout), "3\n");
+}
+
+#[test]
+fn main_failing() {
+    let output = cli(&["failing.lang"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(text(&output.stdout), "before\n");
+    assert!(
+        text(&output.stderr).starts_with("NameError: undefined variable \"missing\", line 3"),
+        "{}",
+        text(&output.stderr)
+    );
+}
+
+#[test]
+fn failing_tests_exit_with_one() {
+    // whatever the count, which 256 failures would wrap to 0
+    let output = cli(&["test", "failing_tests.lang"]);
+    assert_eq!(output.status.code(), Some(1), "{}", text(&output.stderr));
+    assert!(text(&output.stdout).ends_with("1 passed, 2 failed\n"));
+}
+
+#[test]
+fn deep_recursion_limited_by_default() {
+    // a comparator recursing through `sorted` hits the default call depth
+    // limit rather than the end of the stack
+    let output = cli(&["deep_recursion.lang"]);
+    assert_eq!(output.status.code(), Some(1), "{}", text(&output.stderr));
+    assert_eq!(text(&output.stdout), "done\n");
+    assert!(
+        text(&output.stderr).starts_with("RecursionError: calls nested more than 1000 deep, line 5"),
+        "{}",
+        text(&output.stderr)
+    );
+}
@@ -0,0 +1,228 @@
+//! Golden-file tests for language behaviour.
+//!
+//! Every `*.lang` script under `tests/lang/` is run through the lexer, parser,
+//! resolver, `lower` and interpreter, and what it produces is compared with
+//! the expectations written in its comments:
+//!
+//! ```text
+//! print 1 + 2;   # expect: 3
+//! print nope;    # expect-runtime-error: NameError: undefined variable "nope"
+//! let = 1;       # expect-parse-error: SyntaxError: Expected: "Identifier" Found: "="
+//! { a; let a; }  # expect-resolve-error: ResolveError: "a" used before its declaration in this scope
+//! ```
+//!
+//! Output lines must appear in the order of their `expect:` comments. Error
+//! expectations must also be reported on the line holding the comment. Files
+//! run in parallel, but failures are always reported in path order.
+//!
+//! A script whose first line is `# strict-types` is resolved and run with
+//! type annotations checked, as `--strict-types` does.
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::{fs, thread};
+
+use rlisp::{lower, Interpretor, Lexer, Parser, Resolver};
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Output(String),
+    RuntimeError(String, usize),
+    ParseError(String, usize),
+    ResolveError(String, usize),
+}
+
+impl Event {
+    fn describe(&self) -> String {
+        match self {
+            Event::Output(text) => format!("output {text:?}"),
+            Event::RuntimeError(message, line) => {
+                format!("runtime error {message:?} on line {line}")
+            }
+            Event::ParseError(message, line) => format!("parse error {message:?} on line {line}"),
+            Event::ResolveError(message, line) => {
+                format!("resolve error {message:?} on line {line}")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Capture {
+    fn drain_lines(&self, events: &mut Vec<Event>) {
+        let bytes = std::mem::take(&mut *self.0.borrow_mut());
+        let text = String::from_utf8(bytes).expect("output is utf-8");
+        events.extend(text.lines().map(|line| Event::Output(line.to_string())));
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn expectations(source: &str) -> Vec<Event> {
+    let mut expected = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let Some(start) = line.find("# expect") else {
+            continue;
+        };
+        let comment = line[start + 1..].trim();
+        if let Some(text) = comment.strip_prefix("expect:") {
+            expected.push(Event::Output(text.trim().to_string()));
+        } else if let Some(text) = comment.strip_prefix("expect-runtime-error:") {
+            expected.push(Event::RuntimeError(text.trim().to_string(), index + 1));
+        } else if let Some(text) = comment.strip_prefix("expect-parse-error:") {
+            expected.push(Event::ParseError(text.trim().to_string(), index + 1));
+        } else if let Some(text) = comment.strip_prefix("expect-resolve-error:") {
+            expected.push(Event::ResolveError(text.trim().to_string(), index + 1));
+        }
+    }
+    expected
+}
+
+fn run(source: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut parser = Parser::new(Lexer::new(source.to_string()));
+    let program = match parser.parse_program() {
+        Ok(program) => program,
+        Err(err) => {
+            events.push(Event::ParseError(err.error.to_string(), err.line()));
+            return events;
+        }
+    };
+    let strict_types = source.lines().next() == Some("# strict-types");
+    let mut resolver = Resolver::new();
+    if strict_types {
+        resolver = resolver.with_strict_types();
+    }
+    if let Err(err) = resolver.resolve(&program) {
+        events.push(Event::ResolveError(err.error.to_string(), err.line()));
+        return events;
+    }
+    let program = lower(program);
+
+    let capture = Capture::default();
+    let mut interpretor = Interpretor::with_output(Box::new(capture.clone()));
+    interpretor.strict_types = strict_types;
+    for stmt in &program.stmts {
+        let result = interpretor.exec(stmt);
+        capture.drain_lines(&mut events);
+        if let Err(err) = result {
+            events.push(Event::RuntimeError(err.error.to_string(), err.line()));
+        }
+    }
+    events
+}
+
+fn check(path: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("cannot read: {e}"))?;
+    let expected = expectations(&source);
+    if expected.is_empty() {
+        return Err("script has no expectations".to_string());
+    }
+    let actual = run(&source);
+
+    for (index, want) in expected.iter().enumerate() {
+        match actual.get(index) {
+            Some(got) if got == want => {}
+            Some(got) => {
+                return Err(format!(
+                    "event {}: expected {}, got {}",
+                    index + 1,
+                    want.describe(),
+                    got.describe()
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "event {}: expected {}, but the script produced nothing more",
+                    index + 1,
+                    want.describe()
+                ))
+            }
+        }
+    }
+    if let Some(extra) = actual.get(expected.len()) {
+        return Err(format!(
+            "event {}: unexpected {}",
+            expected.len() + 1,
+            extra.describe()
+        ));
+    }
+    Ok(())
+}
+
+fn scripts(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).expect("tests/lang exists") {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(scripts(&path));
+        } else if path.extension().is_some_and(|ext| ext == "lang") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn golden_files() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("lang");
+    let files = scripts(&root);
+    assert!(!files.is_empty(), "no scripts found in {}", root.display());
+
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut results: Vec<Option<Result<(), String>>> = vec![None; files.len()];
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| {
+                let files = &files;
+                scope.spawn(move || {
+                    (worker..files.len())
+                        .step_by(workers)
+                        .map(|index| (index, check(&files[index])))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (index, result) in handle.join().expect("worker panicked") {
+                results[index] = Some(result);
+            }
+        }
+    });
+
+    let failures: Vec<String> = files
+        .iter()
+        .zip(results)
+        .filter_map(|(path, result)| match result {
+            Some(Ok(())) => None,
+            Some(Err(message)) => Some(format!(
+                "{}: {message}",
+                path.strip_prefix(&root).unwrap().display()
+            )),
+            None => Some(format!("{}: was not run", path.display())),
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} scripts failed:\n{}",
+        failures.len(),
+        files.len(),
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,87 @@
+//! Counts heap allocations made while parsing, so regressions that copy
+//! tokens around show up as a failing test rather than a slow profile.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rlisp::internals::TokenType;
+use rlisp::{Lexer, Parser};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn source() -> String {
+    let mut source = String::new();
+    for i in 0..200 {
+        source.push_str(&format!(
+            "fn f{i}(a, b) {{ let name = \"value {i}\"; if (a < b) {{ return a + b * 2; }} return name; }}\n"
+        ));
+    }
+    source
+}
+
+fn tokens(source: &str) -> usize {
+    let mut lexer = Lexer::new(source.to_string());
+    let mut count = 0;
+    while lexer.next_token().token != TokenType::Eof {
+        count += 1;
+    }
+    count
+}
+
+#[test]
+fn parser_allocations_per_token() {
+    let source = source();
+    let tokens = tokens(&source);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let program = Parser::new(Lexer::new(source))
+        .parse_program()
+        .expect("generated source parses");
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(program);
+
+    let per_token = allocations as f64 / tokens as f64;
+    println!("{allocations} allocations for {tokens} tokens ({per_token:.2} per token)");
+    // identifiers, strings, number lexemes and AST nodes still allocate, but
+    // tokens themselves are never copied
+    assert!(per_token < 1.0, "{per_token:.2} allocations per token");
+}
+
+#[test]
+fn numbers_lex_without_allocating() {
+    let source = "1 1_000 2.5e-3 0xFF 0x1_0 1_000.5 ".repeat(200);
+    let mut lexer = Lexer::new(source);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let mut count = 0;
+    while let TokenType::Number(_) = lexer.next_token().token {
+        count += 1;
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    assert_eq!(count, 1200);
+    assert_eq!(
+        allocations, 0,
+        "{allocations} allocations for {count} numbers"
+    );
+}
@@ -0,0 +1,28 @@
+//! Snapshot of the S-expression printer on a program that uses every kind
+//! of node, so a change to the format shows up as a diff of
+//! `tests/printer/kitchen_sink.sexp`.
+
+use std::fs;
+use std::path::Path;
+
+use rlisp::{Lexer, Parser};
+
+#[test]
+fn kitchen_sink() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/printer");
+    let source = fs::read_to_string(dir.join("kitchen_sink.lang")).unwrap();
+    let program = Parser::new(Lexer::new(source))
+        .parse_program()
+        .expect("the fixture parses");
+    // one top-level statement per line keeps the diff readable
+    let printed: String = program
+        .stmts
+        .iter()
+        .map(|stmt| format!("{stmt}\n"))
+        .collect();
+    let expected = fs::read_to_string(dir.join("kitchen_sink.sexp")).unwrap_or_default();
+    assert!(
+        printed == expected,
+        "kitchen_sink.sexp is out of date, it should read:\n{printed}"
+    );
+}
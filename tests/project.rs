@@ -0,0 +1,93 @@
+//! Runs the CLI on the projects under `tests/projects/`.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use rlisp::{ParserOptions, Project};
+
+fn cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlisp"))
+        .args(args)
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/projects"))
+        .output()
+        .expect("the binary runs")
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[test]
+fn run_directory() {
+    // the manifest points at src/app.lang
+    let output = cli(&["run", "greeter"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    assert_eq!(text(&output.stdout), "hello project\n");
+
+    // main.lang by convention
+    let output = cli(&["broken"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    assert_eq!(text(&output.stdout), "3\n");
+
+    let output = cli(&["run", "broken/lib"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        text(&output.stderr),
+        "broken/lib: no entry file: main.lang does not exist\n"
+    );
+}
+
+#[test]
+fn run_with_imports() {
+    // from outside the project, as a directory and as a script
+    for path in ["calc", "calc/main.lang"] {
+        let output = cli(&["run", path]);
+        assert!(output.status.success(), "{}", text(&output.stderr));
+        assert_eq!(text(&output.stdout), "9\n");
+    }
+}
+
+#[test]
+fn check_directory() {
+    let output = cli(&["check", "greeter"]);
+    assert!(output.status.success());
+    assert_eq!(text(&output.stdout), "checked 2 files, 0 with errors\n");
+    assert_eq!(
+        text(&output.stderr),
+        "lib/unused.lang: warning: this shadows the built-in 'trace', line 1, pos 4\n"
+    );
+
+    // every file is checked, including those main.lang never reaches
+    let output = cli(&["check", "broken"]);
+    assert!(!output.status.success());
+    assert_eq!(text(&output.stdout), "checked 4 files, 2 with errors\n");
+    let stderr = text(&output.stderr);
+    let first_lines: Vec<_> = stderr.lines().filter(|line| line.contains(".lang: ")).collect();
+    assert_eq!(
+        first_lines,
+        [
+            "lib/deep/bad.lang: SyntaxError: Expected: \"Identifier\" Found: \"=\", line 2, pos 5",
+            "lib/scope.lang: ResolveError: \"b\" used before its declaration in this scope, line 2, pos 9",
+        ]
+    );
+    assert!(stderr.contains("2 | let = 2;\n"), "{stderr}");
+}
+
+#[test]
+fn project_files() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/projects/broken");
+    let project = Project::open(&root).unwrap();
+    assert_eq!(project.main(), Path::new("main.lang"));
+    let files: Vec<_> = project
+        .files()
+        .unwrap()
+        .into_iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(
+        files,
+        ["lib/deep/bad.lang", "lib/ok.lang", "lib/scope.lang", "main.lang"]
+    );
+    let checks = project.check(ParserOptions::default()).unwrap();
+    assert_eq!(checks.iter().filter(|c| !c.errors.is_empty()).count(), 2);
+}
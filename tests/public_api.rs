@@ -0,0 +1,64 @@
+//! Names every item the crate root, `prelude` and `internals` export, so
+//! one removed or renamed by accident fails to compile here. Adding to the
+//! API means adding to these lists; taking from it means a new version.
+
+#[allow(unused_imports)]
+mod root {
+    use rlisp::{
+        analyze, check, diff_programs, document, eliminate_dead_code, fold_constants, lower, parse,
+        rename, resolve_specifier, run_file, run_program, run_source, AstChange, CacheError,
+        ChangeKind, Clock, ColorChoice, Completion, CompletionKind, Coverage, Diagnostic,
+        DiagnosticSet, Dialect, Disk, Error, ErrorInfo, Expr, FieldInit, FileCheck, FsModuleSource,
+        Host, ImportForm, ImportName, InterpreterHooks, Interpretor, LayeredModuleSource, Lexer,
+        LineCount, LiteralType, LogLevel, MatchArm, MemoryModuleSource, ModuleError, ModuleSource,
+        NamedArg, Object, Parser, ParserOptions, Pattern, Program, Project, ProjectError,
+        Reference, Repl, ReplHistory, Resolver, ResumeHandle, RunOptions, RunOutcome, RunState,
+        ScriptLogger, Signature, Snippet, SourceFile, Span, Stats, StderrLogger, Stmt, Style,
+        Suppressions, Symbol, SymbolId, SymbolIndex, SymbolKind, SystemClock, Terminal,
+        VirtualClock, Warning, CONTINUATION_PROMPT, FORMAT_VERSION, WARNING_CODES,
+    };
+}
+
+#[allow(unused_imports)]
+mod internals {
+    use rlisp::internals::{
+        visitor, Environment, IncrementalLexer, LexerMode, NativeFn, Node, NodeId, SmallStr,
+        TokenInfo, TokenType, Trivia, TriviaKind, KEYWORDS,
+    };
+}
+
+// The prelude on its own is enough to parse, check and run a script.
+mod prelude {
+    #[allow(unused_imports)]
+    use rlisp::prelude::{
+        check, parse, run_file, run_program, run_source, Diagnostic, Error, ErrorInfo, Interpretor,
+        Object, ParserOptions, Program, RunOptions, RunOutcome, Warning,
+    };
+
+    const SCRIPT: &str = "fn double(x) { return x * 2; }\nprint double(21);";
+
+    #[test]
+    fn test_prelude_is_enough() {
+        let (program, warnings) = parse(SCRIPT, ParserOptions::default()).unwrap();
+        assert_eq!(program.stmts.len(), 2);
+        assert!(warnings.is_empty());
+        assert!(check(SCRIPT, RunOptions::default()).unwrap().is_empty());
+
+        let options = RunOptions {
+            capture_output: true,
+            ..RunOptions::default()
+        };
+        let outcome = run_program(program, options.clone()).unwrap();
+        assert_eq!(outcome.output.as_deref(), Some("42\n"));
+        let outcome = run_source("double(1.5);", options).unwrap_err();
+        let [Diagnostic::Error(ErrorInfo { error, .. })] = &outcome[..] else {
+            panic!("expected one error, got {outcome:?}");
+        };
+        assert!(matches!(error, Error::Name(_)), "{error:?}");
+
+        let mut interpretor = Interpretor::buffered();
+        let (program, _) = parse("print 1 + 1;", ParserOptions::default()).unwrap();
+        interpretor.run(&program).unwrap();
+        assert_eq!(interpretor.take_output(), "2\n");
+    }
+}
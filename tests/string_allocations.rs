@@ -0,0 +1,64 @@
+//! Counts heap allocations made by a loop building short strings, which
+//! `Object::String` keeps inline, against the same loop with long strings.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rlisp::{Interpretor, Lexer, Parser};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const ITERATIONS: usize = 1000;
+
+// Allocations made running a loop that evaluates two string literals and
+// joins them with `+` three times, all strings `prefix` long or a bit more.
+fn allocations(prefix: &str) -> usize {
+    let source = format!(
+        "let i = 0;
+let key = \"\";
+while (i < {ITERATIONS}) {{
+  key = \"{prefix}\" + \"-\" + \"k\";
+  key = key + \"!\";
+  i = i + 1;
+}}
+print key;"
+    );
+    let program = Parser::new(Lexer::new(source))
+        .parse_program()
+        .expect("generated source parses");
+    let mut interpretor = Interpretor::with_output(Box::new(io::sink()));
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    interpretor.run(&program).expect("generated source runs");
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[test]
+fn short_strings_stay_off_the_heap() {
+    let short = allocations("id");
+    let long = allocations(&"x".repeat(40));
+    println!("{short} allocations with short strings, {long} with long ones");
+    // long strings allocate for both literals and all three joins
+    assert!(long - short >= 5 * ITERATIONS, "{short} vs {long}");
+}